@@ -0,0 +1,50 @@
+; demo.ch8 is assembled from this: a stand-in for a real game, written for PotatOcho's benchmarks and in the public
+; domain. Every frame it erases and redraws a row of score digits, drops a handful of random blocks, checks the keys, and
+; waits for the delay timer, so a frame has the same kind of mix as most games.
+
+        .org 0x200
+start:  CLS
+        LD VA, 0
+frame:  LD V0, 1
+        LD DT, V0
+        ; The score, as three digits, drawn and erased again (so the screen keeps changing).
+        LD I, score
+        LD B, VA
+        LD V2, [I]
+        LD V3, 2
+        LD V4, 0
+        LD F, V0
+        DRW V3, V4, 5
+        DRW V3, V4, 5
+        ADD V3, 5
+        LD F, V1
+        DRW V3, V4, 5
+        DRW V3, V4, 5
+        ADD V3, 5
+        LD F, V2
+        DRW V3, V4, 5
+        DRW V3, V4, 5
+        ; Eight random blocks.
+        LD V5, 8
+        LD I, block
+rain:   RND V6, 0x3F
+        RND V7, 0x1F
+        DRW V6, V7, 4
+        ADD V5, 0xFF
+        SE V5, 0
+        JP rain
+        ; Any of the first two keys clears the screen.
+        LD V8, 0
+        SKNP V8
+        CLS
+        LD V8, 1
+        SKNP V8
+        CLS
+        ADD VA, 1
+wait:   LD V9, DT
+        SE V9, 0
+        JP wait
+        JP frame
+
+block:  DB 0xF0 0x90 0x90 0xF0
+score:  DB 0x00 0x00 0x00
@@ -0,0 +1,32 @@
+; An instruction mix for measuring dispatch: the arithmetic, logic, skips, jumps, and calls a typical game runs between
+; draws, in a loop that never touches the screen, the keypad, or the timers. It runs forever, so any number of steps can
+; be taken from it.
+
+        .org 0x200
+start:  LD V0, 0
+        LD V1, 1
+        LD I, data
+loop:   ADD V0, 3
+        LD V2, V0
+        OR V2, V1
+        AND V2, V0
+        XOR V3, V2
+        ADD V3, V1
+        SUB V4, V3
+        SHR V4
+        SHL V5
+        SE V0, 0x30
+        SNE V1, 1
+        LD V6, 0x55
+        SE V2, V3
+        ADD I, V1
+        CALL leaf
+        SNE V0, 0xFF
+        LD V0, 0
+        JP loop
+
+leaf:   LD V7, V0
+        SUBN V7, V1
+        RET
+
+data:   DB 0x00
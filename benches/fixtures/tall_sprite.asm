@@ -0,0 +1,11 @@
+; A 15-row sprite drawn over and over, moving across (and wrapping around) the screen, for measuring DXYN on its own.
+
+        .org 0x200
+start:  LD V0, 0
+        LD V1, 4
+        LD I, sprite
+loop:   DRW V0, V1, 15
+        ADD V0, 1
+        JP loop
+
+sprite: DB 0xFF 0x81 0xBD 0xA5 0xA5 0xBD 0x81 0xFF 0x81 0xBD 0xA5 0xA5 0xBD 0x81 0xFF
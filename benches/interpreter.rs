@@ -0,0 +1,52 @@
+// Benchmarks for the interpreter's hot paths, run headlessly through the library with no SDL or display:
+//
+//   dispatch      fetching, decoding, and executing a mix of everyday instructions, one step() at a time
+//   draw          DXYN drawing a 15-row sprite, over and over
+//   frame         a whole 60Hz frame of demo.ch8 through frame(), timers and all
+//
+// cargo bench --manifest-path benches/Cargo.toml
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use potatocho::{ChipEight, Keypad};
+use potatocho_benches::{assembled, machine, DEMO_ROM, DISPATCH_MIX, TALL_SPRITE};
+
+// How many instructions each iteration of the step benchmarks runs.
+const STEPS: u64 = 1_000;
+
+fn dispatch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch");
+    group.throughput(Throughput::Elements(STEPS));
+    let mut chip_eight: ChipEight = assembled(DISPATCH_MIX);
+    group.bench_function("instruction mix", |b| {
+        b.iter(|| {
+            for _ in 0..STEPS {
+                black_box(chip_eight.step().expect("the mix runs forever"));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw");
+    group.throughput(Throughput::Elements(STEPS));
+    let mut chip_eight: ChipEight = assembled(TALL_SPRITE);
+    group.bench_function("tall sprite", |b| {
+        b.iter(|| {
+            for _ in 0..STEPS {
+                black_box(chip_eight.step().expect("the sprite loop runs forever"));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn frame(c: &mut Criterion) {
+    let mut chip_eight: ChipEight = machine(DEMO_ROM);
+    let keys: Keypad = Keypad::new();
+    c.bench_function("frame/demo.ch8", |b| {
+        b.iter(|| black_box(chip_eight.frame(&keys).expect("the demo runs forever")))
+    });
+}
+
+criterion_group!(benches, dispatch, draw, frame);
+criterion_main!(benches);
@@ -0,0 +1,23 @@
+// What the benchmarks share: the fixtures, and machines loaded with them. The instruction mixes are kept as assembly so
+// they're easy to read and change, and assembled when they're needed; demo.ch8 is a ROM blob like any other (its source
+// is next to it).
+use potatocho::{assemble, ChipEight};
+
+pub const DISPATCH_MIX: &str = include_str!("fixtures/dispatch_mix.asm");
+pub const TALL_SPRITE: &str = include_str!("fixtures/tall_sprite.asm");
+pub const DEMO_ROM: &[u8] = include_bytes!("fixtures/demo.ch8");
+
+// A machine with rom loaded, seeded so every run draws the same random numbers.
+pub fn machine(rom: &[u8]) -> ChipEight {
+    let mut chip_eight: ChipEight = ChipEight::new();
+    chip_eight.seed_rng(0);
+    chip_eight
+        .load_program_from_slice(rom)
+        .expect("the fixtures load");
+    chip_eight
+}
+
+// A machine with source assembled and loaded.
+pub fn assembled(source: &str) -> ChipEight {
+    machine(&assemble(source).expect("the fixtures assemble"))
+}
@@ -0,0 +1,65 @@
+// A fantasy serial port at 0xFF0, as an example of a memory-mapped device. Bytes the program writes there are printed to
+// stdout, and reads return the next byte of the input (0 once it runs out).
+//
+// cargo run --example serial_port
+use potatocho::backend::{NullAudio, NullDisplay, NullInput};
+use potatocho::{assemble, ChipEight, MemoryMappedDevice};
+use std::collections::VecDeque;
+use std::io::Write;
+
+const SERIAL_PORT: u16 = 0xFF0;
+
+struct SerialPort {
+    input: VecDeque<u8>,
+}
+
+impl MemoryMappedDevice for SerialPort {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        if addr != SERIAL_PORT {
+            return None;
+        }
+        Some(self.input.pop_front().unwrap_or(0))
+    }
+    fn write(&mut self, addr: u16, value: u8) -> bool {
+        if addr != SERIAL_PORT {
+            return false;
+        }
+        print!("{}", value as char);
+        let _ = std::io::stdout().flush();
+        true
+    }
+}
+
+fn main() {
+    // Echoes the serial port back to itself until it reads a 0, then spins.
+    let program: Vec<u8> = match assemble(
+        "
+        read:   LD I, 0xFF0
+                LD V0, [I]
+                SE V0, 0x00
+                JP echo
+        done:   JP done
+        echo:   LD I, 0xFF0     ; Fx65 may have moved I along
+                LD [I], V0
+                JP read
+        ",
+    ) {
+        Ok(program) => program,
+        Err(e) => panic!("Error assembling program: {}", e),
+    };
+
+    let mut chip_eight = ChipEight::new();
+    chip_eight.attach_device(SerialPort {
+        input: b"Hello from the serial port!\n".iter().copied().collect(),
+    });
+    if let Err(e) = chip_eight.load_program(program) {
+        panic!("Error loading program: {}", e);
+    }
+    if let Err(e) = chip_eight.run(
+        &mut NullDisplay,
+        &mut NullInput::for_frames(60),
+        &mut NullAudio,
+    ) {
+        panic!("Error running program: {}", e);
+    }
+}
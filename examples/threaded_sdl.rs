@@ -0,0 +1,89 @@
+// Runs the emulator on a background thread through an EmulatorHandle, with the SDL window, keyboard, and audio handled
+// on the main thread. This is the shape a frontend with its own UI loop (egui, an editor) would take.
+//
+// cargo run --example threaded_sdl -- path/to/rom.ch8
+use potatocho::backend::Audio;
+use potatocho::frontend_sdl::{
+    find_sdl_gl_driver, Keymap, SdlAudio, SdlDisplay, DEFAULT_AUDIO_BUFFER,
+};
+use potatocho::{ChipEight, EmulatorHandle, Frame};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use std::path::PathBuf;
+
+fn main() -> Result<(), String> {
+    let rom: PathBuf = match std::env::args_os().nth(1) {
+        Some(rom) => PathBuf::from(rom),
+        None => return Err("Usage: threaded_sdl ROM".to_string()),
+    };
+    let mut chip_eight = ChipEight::new();
+    chip_eight
+        .load_program_from_path(&rom)
+        .map_err(|e| format!("Error loading {}: {}", rom.display(), e))?;
+    // The main thread only needs a keymap to turn key presses into the keypad's, and the machine goes to the handle.
+    let keymap: Keymap = Keymap::default();
+
+    let sdl_context = sdl2::init()?;
+    let window = sdl_context
+        .video()?
+        .window("PotatOcho (threaded)", 1280, 640)
+        .opengl()
+        .position_centered()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut canvas = window
+        .into_canvas()
+        .index(find_sdl_gl_driver().ok_or("Unable to find compatible OpenGL driver!")?)
+        .present_vsync()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut audio = SdlAudio::new(&sdl_context.audio()?, DEFAULT_AUDIO_BUFFER)?;
+    let mut event_pump = sdl_context.event_pump()?;
+    let texture_creator = canvas.texture_creator();
+    let mut display = SdlDisplay::new(&mut canvas, &texture_creator);
+
+    let handle = EmulatorHandle::spawn(chip_eight);
+    'running: loop {
+        // The handle has no debugger commands, so only the keypad's keys (and Escape) do anything.
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'running,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => {
+                    for key in keymap.keys_bound_to(keycode) {
+                        handle.press(key);
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    for key in keymap.keys_bound_to(keycode) {
+                        handle.release(key);
+                    }
+                }
+                _ => {}
+            }
+        }
+        // The emulator thread keeps time, so waiting for its next frame paces this loop too.
+        let frame: Frame = match handle.frames().recv() {
+            Ok(frame) => frame,
+            Err(_) => break,
+        };
+        audio.set_beeping(frame.beep);
+        display.present_frame(&frame)?;
+    }
+
+    audio.set_beeping(false);
+    handle
+        .join()
+        .map_err(|e| format!("Error running program: {}", e))?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+// One instruction on a machine in an arbitrary state: the configuration byte, the registers, I, the PC, how many calls
+// deep the stack is, the opcode, and then whatever memory follows the instruction. Decoding and executing must return
+// errors rather than panic, whatever the state.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use potatocho::{decode, ChipEight, Instruction};
+use potatocho_fuzz::machine;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 24 {
+        return;
+    }
+    let mut chip_eight: ChipEight = machine(data[0]);
+    for (x, &value) in data[1..17].iter().enumerate() {
+        chip_eight.set_v(x, value);
+    }
+    let word = |at: usize| -> u16 { u16::from_be_bytes([data[at], data[at + 1]]) };
+    let pc: u16 = word(19);
+
+    // Pushing return addresses is the only way onto the stack, and one call too many has to be an error too.
+    for depth in 0..data[21] % 18 {
+        let _ = chip_eight.execute_instruction(Instruction::CallSubroutine {
+            nnn: pc.wrapping_add(depth as u16) & 0xFFF,
+        });
+    }
+    chip_eight.set_pc(pc);
+    chip_eight.set_i_register(word(17));
+    let _ = chip_eight.write_memory(pc as usize, &data[24..]);
+
+    if let Ok(instruction) = decode(word(22)) {
+        let _ = chip_eight.execute_instruction(instruction);
+    }
+    let _ = chip_eight.step();
+});
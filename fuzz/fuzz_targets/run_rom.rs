@@ -0,0 +1,28 @@
+// Arbitrary bytes as a ROM: a configuration byte, then the program. Loading it and running it for a while must only ever
+// return errors, never panic.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use potatocho::{ChipEight, Keypad};
+use potatocho_fuzz::machine;
+
+// How many instructions to run each ROM for. Most random programs fail or loop long before this.
+const STEPS: usize = 10_000;
+
+fuzz_target!(|data: &[u8]| {
+    let (config, rom) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let mut chip_eight: ChipEight = machine(*config);
+    if chip_eight.load_program_from_slice(rom).is_err() {
+        return;
+    }
+    for _ in 0..STEPS {
+        if chip_eight.step().is_err() {
+            return;
+        }
+    }
+    // Frames also run the timers and the display wait.
+    let _ = chip_eight.frame(&Keypad::new());
+});
@@ -0,0 +1,29 @@
+// What the fuzz targets share: turning fuzzer input into a machine to run things on.
+use potatocho::{ChipEight, ChipEightBuilder, ChipVariant, MemorySize, Quirks};
+
+// A machine configured by one byte of fuzzer input: the low two bits pick the variant, the next six switch the quirks
+// on and off, and XO-CHIP machines get 64KiB of memory when the top quirk bit is set too.
+pub fn machine(config: u8) -> ChipEight {
+    let variant: ChipVariant = match config & 0b11 {
+        0 => ChipVariant::Chip8,
+        1 => ChipVariant::SuperChipLegacy,
+        2 => ChipVariant::SuperChipModern,
+        _ => ChipVariant::XoChip,
+    };
+    let mut quirks: Quirks = variant.quirks();
+    for (i, name) in Quirks::NAMES.iter().enumerate() {
+        quirks.set(name, config & (0b100 << i) != 0);
+    }
+    let memory_size: MemorySize = if variant == ChipVariant::XoChip && config & 0x80 != 0 {
+        MemorySize::Extended
+    } else {
+        MemorySize::Classic
+    };
+
+    ChipEightBuilder::new()
+        .variant(variant)
+        .quirks(quirks)
+        .memory_size(memory_size)
+        .build()
+        .expect("every variant can be built with its own memory size")
+}
@@ -0,0 +1,7 @@
+#!/bin/sh
+# Builds the no_std core for a Cortex-M4F, to catch anything that quietly pulls in std or an allocator.
+set -e
+cd "$(dirname "$0")/.."
+rustup target add thumbv7em-none-eabihf
+cargo build --lib --no-default-features --target thumbv7em-none-eabihf
+cargo clippy --lib --no-default-features --target thumbv7em-none-eabihf -- -D warnings
@@ -0,0 +1,441 @@
+// A small assembler for the mnemonics the disassembler prints, so tests and examples can be written as assembly rather than
+// hand-assembled hex. One instruction or directive per line, with optional labels ("loop:") and ; comments:
+//
+//     .org 0x200
+//     start:  LD V0, 0x05
+//             CALL draw
+//             JP start
+//     draw:   LD I, digit
+//             DRW V0, V1, 5
+//             RET
+//     digit:  DB 0xF0 0x90 0xF0 0x90 0xF0
+//
+// Numbers can be decimal, 0x hex, or 0b binary, and anywhere an address goes a label can go instead. Besides the
+// instructions there are DB (bytes), DW (big-endian words), and .org (skip ahead to an address, filling the gap with
+// zeros). .byte and .word work like DB and DW, so anything disassemble() prints assembles back to the same opcode.
+use crate::Instruction;
+use std::collections::HashMap;
+use std::fmt;
+
+// Where programs are loaded, and so where the output starts.
+const ORIGIN: u32 = 0x200;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl AsmError {
+    pub(crate) fn new(line: usize, message: impl Into<String>) -> Self {
+        AsmError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// One line's instruction or directive, with the operands still as text since they may name labels defined further down.
+struct Statement<'a> {
+    line: usize,
+    address: u32,
+    mnemonic: String,
+    operands: Vec<&'a str>,
+}
+
+// Assembles src into a ROM to be loaded at 0x200.
+pub fn assemble(src: &str) -> Result<Vec<u8>, AsmError> {
+    let mut labels: HashMap<&str, u32> = HashMap::new();
+    let mut statements: Vec<Statement> = vec![];
+    let mut address: u32 = ORIGIN;
+
+    // The first pass works out where everything goes, so the second knows where every label points.
+    for (i, raw_line) in src.lines().enumerate() {
+        let line_number: usize = i + 1;
+        let mut line: &str = raw_line.split(';').next().unwrap_or("").trim();
+
+        while let Some((label, rest)) = line.split_once(':') {
+            let label: &str = label.trim();
+            if !is_label(label) {
+                break;
+            }
+            if labels.insert(label, address).is_some() {
+                return Err(AsmError::new(
+                    line_number,
+                    format!("label {} is defined twice", label),
+                ));
+            }
+            line = rest.trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let statement = Statement {
+            line: line_number,
+            address,
+            mnemonic: mnemonic.to_ascii_uppercase(),
+            operands: split_operands(mnemonic, operands),
+        };
+        if statement.mnemonic == ".ORG" {
+            let target: u32 = statement.operand(0, &labels)?;
+            if target < address {
+                return Err(statement.error(format!(
+                    ".org {:#05X} is before the current address {:#05X}",
+                    target, address
+                )));
+            }
+            address = target;
+        } else {
+            address += statement.size();
+        }
+        if address > 0x10000 {
+            return Err(statement.error("the program doesn't fit in 64KiB of memory"));
+        }
+        statements.push(statement);
+    }
+
+    let mut rom: Vec<u8> = vec![];
+    for statement in &statements {
+        rom.resize((statement.address - ORIGIN) as usize, 0);
+        statement.assemble(&labels, &mut rom)?;
+    }
+    Ok(rom)
+}
+
+// Operands are separated by commas, except that DB and DW take spaces too, and SAVE and LOAD write their range as
+// "V1 - V3".
+fn split_operands<'a>(mnemonic: &str, operands: &'a str) -> Vec<&'a str> {
+    let separators: &[char] = match mnemonic.to_ascii_uppercase().as_str() {
+        "DB" | "DW" | ".BYTE" | ".WORD" => &[',', ' ', '\t'],
+        "SAVE" | "LOAD" => &[',', '-'],
+        _ => &[','],
+    };
+    operands
+        .split(separators)
+        .map(str::trim)
+        .filter(|operand| !operand.is_empty())
+        .collect()
+}
+
+// Labels can't be named after registers or the other special operands, or LD I, label would be ambiguous.
+fn is_label(s: &str) -> bool {
+    let reserved: bool = register(s).is_some()
+        || ["I", "K", "DT", "ST", "F", "B", "LONG"]
+            .iter()
+            .any(|name| s.eq_ignore_ascii_case(name));
+    !reserved
+        && s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Vx, as a register number.
+fn register(operand: &str) -> Option<u8> {
+    let digit: &str = operand.strip_prefix(['V', 'v'])?;
+    match digit.len() {
+        1 => u8::from_str_radix(digit, 16).ok(),
+        _ => None,
+    }
+}
+
+fn number(operand: &str) -> Option<u32> {
+    let lower: String = operand.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        u32::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+impl Statement<'_> {
+    // How many bytes the statement assembles to.
+    fn size(&self) -> u32 {
+        match self.mnemonic.as_str() {
+            "DB" | ".BYTE" => self.operands.len() as u32,
+            "DW" | ".WORD" => self.operands.len() as u32 * 2,
+            ".ORG" => 0,
+            // LD I, LONG addr is F000 followed by the address.
+            "LD" if self.operands.len() == 2
+                && self.operands[1].to_ascii_uppercase().starts_with("LONG ") =>
+            {
+                4
+            }
+            _ => 2,
+        }
+    }
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError::new(self.line, message)
+    }
+    // The nth operand as a number or a label's address.
+    fn operand(&self, n: usize, labels: &HashMap<&str, u32>) -> Result<u32, AsmError> {
+        let operand: &str = self
+            .operands
+            .get(n)
+            .ok_or_else(|| self.error(format!("{} needs more operands", self.mnemonic)))?;
+        if let Some(value) = number(operand) {
+            return Ok(value);
+        }
+        match labels.get(operand) {
+            Some(&address) => Ok(address),
+            None if is_label(operand) => Err(self.error(format!("no label called {}", operand))),
+            None => Err(self.error(format!("bad operand {}", operand))),
+        }
+    }
+    // The nth operand as a number or label no bigger than max.
+    fn value(&self, n: usize, max: u32, labels: &HashMap<&str, u32>) -> Result<u32, AsmError> {
+        let value: u32 = self.operand(n, labels)?;
+        if value > max {
+            return Err(self.error(format!(
+                "{} is too big here, the most is {:#X}",
+                self.operands[n], max
+            )));
+        }
+        Ok(value)
+    }
+    fn byte(&self, n: usize, labels: &HashMap<&str, u32>) -> Result<u8, AsmError> {
+        Ok(self.value(n, 0xFF, labels)? as u8)
+    }
+    fn address(&self, n: usize, labels: &HashMap<&str, u32>) -> Result<u16, AsmError> {
+        Ok(self.value(n, 0xFFF, labels)? as u16)
+    }
+    fn register(&self, n: usize) -> Result<u8, AsmError> {
+        let operand: &str = self
+            .operands
+            .get(n)
+            .ok_or_else(|| self.error(format!("{} needs more operands", self.mnemonic)))?;
+        register(operand).ok_or_else(|| self.error(format!("{} isn't a register", operand)))
+    }
+    fn assemble(&self, labels: &HashMap<&str, u32>, rom: &mut Vec<u8>) -> Result<(), AsmError> {
+        match self.mnemonic.as_str() {
+            ".ORG" => {}
+            "DB" | ".BYTE" => {
+                for n in 0..self.operands.len() {
+                    rom.push(self.byte(n, labels)?);
+                }
+            }
+            "DW" | ".WORD" => {
+                for n in 0..self.operands.len() {
+                    rom.extend_from_slice(&(self.value(n, 0xFFFF, labels)? as u16).to_be_bytes());
+                }
+            }
+            _ => {
+                let (instruction, long): (Instruction, Option<u16>) = self.instruction(labels)?;
+                rom.extend_from_slice(&instruction.opcode().to_be_bytes());
+                if let Some(long) = long {
+                    rom.extend_from_slice(&long.to_be_bytes());
+                }
+            }
+        }
+        Ok(())
+    }
+    // The instruction this line stands for, and the address word that goes after it for LD I, LONG addr.
+    fn instruction(
+        &self,
+        labels: &HashMap<&str, u32>,
+    ) -> Result<(Instruction, Option<u16>), AsmError> {
+        use Instruction::*;
+
+        let operands: Vec<String> = self
+            .operands
+            .iter()
+            .map(|operand| operand.to_ascii_uppercase())
+            .collect();
+        let operands: Vec<&str> = operands.iter().map(String::as_str).collect();
+        // Which operands are registers, for telling apart instructions like SE Vx, kk and SE Vx, Vy.
+        let registers: Vec<Option<u8>> = operands.iter().map(|operand| register(operand)).collect();
+        let x = || self.register(0);
+        let y = || self.register(1);
+
+        let instruction: Instruction = match (self.mnemonic.as_str(), &operands[..]) {
+            ("CLS", []) => ClearScreen,
+            ("RET", []) => ReturnFromSubroutine,
+            ("EXIT", []) => Exit,
+            ("SCR", []) => ScrollRight,
+            ("SCL", []) => ScrollLeft,
+            ("LOW", []) => Lores,
+            ("HIGH", []) => Hires,
+            ("HCLS", []) => HiresClearScreen,
+            ("SCD", [_]) => ScrollDown {
+                n: self.value(0, 0xF, labels)? as u8,
+            },
+            ("SCU", [_]) => ScrollUp {
+                n: self.value(0, 0xF, labels)? as u8,
+            },
+            ("SYS", [_]) => JumpToMachineCode {
+                nnn: self.address(0, labels)?,
+            },
+            ("JP", ["V0", _]) => JumpPlusV0 {
+                nnn: self.address(1, labels)?,
+            },
+            ("JP", [_]) => JumpToAddress {
+                nnn: self.address(0, labels)?,
+            },
+            ("CALL", [_]) => CallSubroutine {
+                nnn: self.address(0, labels)?,
+            },
+            ("SE", [_, _]) if registers[1].is_some() => SkipIfEquals { x: x()?, y: y()? },
+            ("SE", [_, _]) => SkipIfEqualsData {
+                x: x()?,
+                kk: self.byte(1, labels)?,
+            },
+            ("SNE", [_, _]) if registers[1].is_some() => SkipIfNotEquals { x: x()?, y: y()? },
+            ("SNE", [_, _]) => SkipIfNotEqualsData {
+                x: x()?,
+                kk: self.byte(1, labels)?,
+            },
+            ("SAVE", [_, _]) => StoreRange { x: x()?, y: y()? },
+            ("LOAD", [_, _]) => RestoreRange { x: x()?, y: y()? },
+            ("LD", ["I", "LONG"]) => SetILong,
+            ("LD", ["I", long]) if long.starts_with("LONG ") => {
+                let target: &str = self.operands[1][5..].trim();
+                let long: u32 = match number(target).or_else(|| labels.get(target).copied()) {
+                    Some(long) if long <= 0xFFFF => long,
+                    _ => return Err(self.error(format!("bad address {}", target))),
+                };
+                return Ok((SetILong, Some(long as u16)));
+            }
+            ("LD", ["I", _]) => SetI {
+                nnn: self.address(1, labels)?,
+            },
+            ("LD", [_, "DT"]) => GetDelay { x: x()? },
+            ("LD", [_, "K"]) => WaitForKey { x: x()? },
+            ("LD", ["DT", _]) => SetDelay { x: y()? },
+            ("LD", ["ST", _]) => SetSound { x: y()? },
+            ("LD", ["F", _]) => SetIToFont { x: y()? },
+            ("LD", ["HF", _]) => SetIToBigFont { x: y()? },
+            ("LD", ["R", _]) => StoreFlags { x: y()? },
+            ("LD", [_, "R"]) => RestoreFlags { x: x()? },
+            ("LD", ["B", _]) => Bcd { x: y()? },
+            ("LD", ["[I]", _]) => StoreRegisters { x: y()? },
+            ("LD", [_, "[I]"]) => RestoreRegisters { x: x()? },
+            ("LD", [_, _]) if registers[1].is_some() => Set { x: x()?, y: y()? },
+            ("LD", [_, _]) => SetData {
+                x: x()?,
+                kk: self.byte(1, labels)?,
+            },
+            ("ADD", ["I", _]) => AddAssignI { x: y()? },
+            ("ADD", [_, _]) if registers[1].is_some() => AddAssign { x: x()?, y: y()? },
+            ("ADD", [_, _]) => AddAssignData {
+                x: x()?,
+                kk: self.byte(1, labels)?,
+            },
+            ("OR", [_, _]) => BitOrAssign { x: x()?, y: y()? },
+            ("AND", [_, _]) => BitAndAssign { x: x()?, y: y()? },
+            ("XOR", [_, _]) => BitXorAssign { x: x()?, y: y()? },
+            ("SUB", [_, _]) => SubAssign { x: x()?, y: y()? },
+            ("SUBN", [_, _]) => SubFrom { x: x()?, y: y()? },
+            // With only one register, the shifts shift it in place whichever way the shift quirk is set.
+            ("SHR", [_]) => ShiftRight { x: x()?, y: x()? },
+            ("SHR", [_, _]) => ShiftRight { x: x()?, y: y()? },
+            ("SHL", [_]) => ShiftLeft { x: x()?, y: x()? },
+            ("SHL", [_, _]) => ShiftLeft { x: x()?, y: y()? },
+            ("RND", [_, _]) => Random {
+                x: x()?,
+                kk: self.byte(1, labels)?,
+            },
+            ("DRW", [_, _, _]) => Draw {
+                x: x()?,
+                y: y()?,
+                n: self.value(2, 0xF, labels)? as u8,
+            },
+            ("SKP", [_]) => SkipIfPressed { x: x()? },
+            ("SKNP", [_]) => SkipIfNotPressed { x: x()? },
+            (mnemonic, operands) => {
+                return Err(self.error(format!(
+                    "{} {} isn't an instruction",
+                    mnemonic,
+                    operands.join(", ")
+                )))
+            }
+        };
+        Ok((instruction, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(src: &str) -> (usize, String) {
+        let error: AsmError = assemble(src).unwrap_err();
+        (error.line, error.message)
+    }
+
+    #[test]
+    fn the_example_at_the_top_assembles() {
+        let src: &str = "
+            .org 0x200
+            start:  LD V0, 0x05
+                    CALL draw
+                    JP start
+            draw:   LD I, digit
+                    DRW V0, V1, 5
+                    RET
+            digit:  DB 0xF0 0x90 0xF0 0x90 0xF0
+        ";
+        assert_eq!(
+            assemble(src),
+            Ok(vec![
+                0x60, 0x05, 0x22, 0x06, 0x12, 0x00, 0xA2, 0x0C, 0xD0, 0x15, 0x00, 0xEE, 0xF0, 0x90,
+                0xF0, 0x90, 0xF0,
+            ])
+        );
+    }
+
+    #[test]
+    fn numbers_directives_and_odd_operands() {
+        let src: &str = "
+            ld v1, 32            ; decimal, and lowercase
+            ADD V1, 0b101
+            SAVE V1 - V3
+            DW 0x1234, end
+            LD I, LONG end
+            .org 0x210
+            end: .byte 7
+        ";
+        assert_eq!(
+            assemble(src),
+            Ok(vec![
+                0x61, 0x20, 0x71, 0x05, 0x51, 0x32, 0x12, 0x34, 0x02, 0x10, 0xF0, 0x00, 0x02, 0x10,
+                0x00, 0x00, 0x07,
+            ])
+        );
+    }
+
+    #[test]
+    fn errors_say_which_line() {
+        for (src, line, message) in [
+            ("CLS\nFOO V1", 2, "FOO V1 isn't an instruction"),
+            ("JP nowhere", 1, "no label called nowhere"),
+            ("LD V1, 0x100", 1, "0x100 is too big here, the most is 0xFF"),
+            ("a: CLS\na: RET", 2, "label a is defined twice"),
+            ("CLS\n\nLD VG, 1", 3, "VG isn't a register"),
+            ("DRW V0, V1", 1, "DRW V0, V1 isn't an instruction"),
+            (
+                ".org 0x300\n.org 0x200",
+                2,
+                ".org 0x200 is before the current address 0x300",
+            ),
+        ] {
+            assert_eq!(error(src), (line, message.to_string()), "{}", src);
+        }
+    }
+
+    #[test]
+    fn labels_cant_be_registers() {
+        // v1: isn't a label, so it's left in the line as an instruction.
+        assert_eq!(error("v1: CLS").0, 1);
+        assert_eq!(AsmError::new(4, "oops").to_string(), "line 4: oops");
+    }
+}
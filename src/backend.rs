@@ -0,0 +1,267 @@
+// The traits the emulator uses to talk to the outside world. run() only knows about these, so the same core can drive an SDL
+// window (see frontend_sdl), a terminal, a browser canvas, or nothing at all (the Null* backends, for tests and headless runs).
+use crate::{dirty, ChipEight, EditorKey};
+use std::ops::ControlFlow;
+
+pub trait Display {
+    // Called once per frame, after the frame's instructions have run. Read the screen with ChipEight::framebuffer.
+    fn present(&mut self, chip_eight: &ChipEight) -> Result<(), String>;
+}
+
+pub trait Input {
+    // Called once per frame, before the frame's instructions run. Report key changes through ChipEight::keypad_mut (or
+    // apply_input_events), and return ControlFlow::Break to stop the emulator.
+    fn poll(&mut self, chip_eight: &mut ChipEight) -> ControlFlow<()>;
+}
+
+// Something that happened on the frontend's side since the last poll.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputEvent {
+    Quit,
+    // The frontend's window was uncovered (or otherwise lost what was drawn in it) and needs the screen drawn again, even
+    // if nothing on it changed, like while the program waits for a key.
+    Redraw,
+    KeyPressed(u8),
+    KeyReleased(u8),
+    // Debugger controls, see ChipEight::pause, resume, single_step, step_over, and step_back.
+    Pause,
+    Resume,
+    Step,
+    StepOver,
+    StepBack,
+    // Shows or hides the frontend's debug overlay or speed statistics, if it has them.
+    ToggleDebugHud,
+    ToggleStats,
+    // Switches to the frontend's next layout of debugging panels, if it has them.
+    NextLayout,
+    // Opens (while paused) or closes the frontend's memory editor, and passes it a key, if it has one.
+    ToggleMemoryEditor,
+    EditMemory(EditorKey),
+    // Switches the frontend to the next of the built-in THEMES, if it draws in color.
+    NextTheme,
+    // Switches the frontend's buzzer to the next of WAVEFORMS, turns it up or down by VOLUME_STEP, or mutes or unmutes
+    // it, if it can.
+    NextWaveform,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    // Switches to the frontend's next filter, or shows or hides lines between the screen's pixels, if it has them.
+    NextFilter,
+    TogglePixelGrid,
+    // Shows or hides the keypad overlay, if the frontend has one.
+    ToggleKeypad,
+    // Switches between sharp and smooth scaling, if the frontend scales the screen.
+    ToggleSmoothScaling,
+    // Takes the frontend's window into or out of fullscreen, if it has one.
+    ToggleFullscreen,
+    // Saves a screenshot, starts or stops recording a GIF, or writes a dump of the machine, if the frontend can.
+    Screenshot,
+    ToggleRecording,
+    Dump,
+}
+
+// How many instructions InputEvent::StepOver gives a subroutine to return before giving up. That's several seconds'
+// worth at any sensible speed.
+pub const STEP_OVER_LIMIT: u32 = 100_000;
+
+// How much InputEvent::VolumeUp and VolumeDown turn the buzzer up or down, in percent.
+pub const VOLUME_STEP: u8 = 5;
+
+// Applies a batch of events to the keypad in order. Every event is applied, even ones that come after a Quit, so a key that's
+// pressed and released within the same frame still registers with Fx0A. Returns ControlFlow::Break if any of them was a Quit.
+pub fn apply_input_events(
+    chip_eight: &mut ChipEight,
+    events: impl IntoIterator<Item = InputEvent>,
+) -> ControlFlow<()> {
+    let mut flow: ControlFlow<()> = ControlFlow::Continue(());
+
+    for event in events {
+        match event {
+            InputEvent::Quit => flow = ControlFlow::Break(()),
+            InputEvent::Redraw => chip_eight.mark_dirty(dirty::WHOLE_SCREEN),
+            InputEvent::KeyPressed(key) => chip_eight.keypad_mut().press(key),
+            InputEvent::KeyReleased(key) => chip_eight.keypad_mut().release(key),
+            InputEvent::Pause => chip_eight.pause(),
+            InputEvent::Resume => chip_eight.resume(),
+            InputEvent::Step => chip_eight.single_step(),
+            // If an instruction fails, it's still the next one to run, so the error comes back when the program is
+            // resumed or stepped.
+            InputEvent::StepOver => {
+                let _ = chip_eight.step_over(STEP_OVER_LIMIT);
+            }
+            // Likewise, a replay that fails leaves the machine where it was.
+            InputEvent::StepBack => {
+                let _ = chip_eight.step_back();
+            }
+            // The frontend's own business, which it handles before they get here. SdlInput keeps the memory editor, and
+            // passes the rest on to SdlDisplay.
+            InputEvent::ToggleDebugHud
+            | InputEvent::ToggleStats
+            | InputEvent::NextLayout
+            | InputEvent::ToggleMemoryEditor
+            | InputEvent::EditMemory(_)
+            | InputEvent::NextTheme
+            | InputEvent::NextWaveform
+            | InputEvent::VolumeUp
+            | InputEvent::VolumeDown
+            | InputEvent::ToggleMute
+            | InputEvent::NextFilter
+            | InputEvent::TogglePixelGrid
+            | InputEvent::ToggleKeypad
+            | InputEvent::ToggleSmoothScaling
+            | InputEvent::ToggleFullscreen
+            | InputEvent::Screenshot
+            | InputEvent::Dump
+            | InputEvent::ToggleRecording => {}
+        }
+    }
+    flow
+}
+
+pub trait Audio {
+    // Called once per frame with FrameOutput::beep, whether the sound timer is running. It's the same most frames, so
+    // backends should only do anything when it changes.
+    fn set_beeping(&mut self, beeping: bool);
+}
+
+// Draws nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullDisplay;
+
+impl Display for NullDisplay {
+    fn present(&mut self, _chip_eight: &ChipEight) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// Never presses any keys. By default it never asks to stop either, so use NullInput::for_frames to run for a fixed amount of time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullInput {
+    frames_left: Option<u64>,
+}
+
+impl NullInput {
+    pub fn new() -> Self {
+        NullInput { frames_left: None }
+    }
+    // Stops the emulator after the given number of frames.
+    pub fn for_frames(frames: u64) -> Self {
+        NullInput {
+            frames_left: Some(frames),
+        }
+    }
+}
+
+impl Input for NullInput {
+    fn poll(&mut self, _chip_eight: &mut ChipEight) -> ControlFlow<()> {
+        match self.frames_left {
+            Some(0) => ControlFlow::Break(()),
+            Some(ref mut frames) => {
+                *frames -= 1;
+                ControlFlow::Continue(())
+            }
+            None => ControlFlow::Continue(()),
+        }
+    }
+}
+
+// Stays silent.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullAudio;
+
+impl Audio for NullAudio {
+    fn set_beeping(&mut self, _beeping: bool) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keypad;
+
+    #[test]
+    fn every_event_is_applied_even_after_a_quit() {
+        let mut chip_eight: ChipEight = ChipEight::new();
+        chip_eight.load_program(vec![0x12, 0x00]).unwrap();
+        let flow: ControlFlow<()> = apply_input_events(
+            &mut chip_eight,
+            [
+                InputEvent::KeyPressed(0x5),
+                InputEvent::KeyPressed(0xA),
+                InputEvent::KeyReleased(0x5),
+                InputEvent::Quit,
+                InputEvent::KeyPressed(0x1),
+                InputEvent::Pause,
+            ],
+        );
+        assert_eq!(flow, ControlFlow::Break(()));
+        let held: Vec<u8> = (0..16)
+            .filter(|&key| chip_eight.keypad().is_pressed(key))
+            .collect();
+        assert_eq!(held, [0x1, 0xA]);
+        // The pause comes before the next instruction.
+        let keys: Keypad = *chip_eight.keypad();
+        chip_eight.frame(&keys).unwrap();
+        assert_eq!(chip_eight.paused_at(), Some(0x200));
+
+        let flow: ControlFlow<()> = apply_input_events(
+            &mut chip_eight,
+            [InputEvent::KeyReleased(0xA), InputEvent::Resume],
+        );
+        assert_eq!(flow, ControlFlow::Continue(()));
+        assert!(!chip_eight.keypad().is_pressed(0xA));
+        assert_eq!(chip_eight.paused_at(), None);
+    }
+
+    #[test]
+    fn a_key_tapped_within_a_frame_still_counts_for_fx0a() {
+        let mut chip_eight: ChipEight = ChipEight::new();
+        // LD V0, K, and then spin.
+        chip_eight
+            .load_program(vec![0xF0, 0x0A, 0x12, 0x02])
+            .unwrap();
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.pc(), 0x200, "nothing's been pressed yet");
+
+        let flow: ControlFlow<()> = apply_input_events(
+            &mut chip_eight,
+            [InputEvent::KeyPressed(0x7), InputEvent::KeyReleased(0x7)],
+        );
+        assert_eq!(flow, ControlFlow::Continue(()));
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.v_registers()[0], 0x7);
+        assert_eq!(chip_eight.pc(), 0x202);
+    }
+
+    #[test]
+    fn frames_keep_running_while_fx0a_waits() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        // LD V1, 30; LD DT, V1; LD V0, K; LD V2, 1; and then spin.
+        chip_eight
+            .load_program_from_slice(&[0x61, 0x1E, 0xF1, 0x15, 0xF0, 0x0A, 0x62, 0x01, 0x12, 0x08])
+            .unwrap();
+        for frame in 1..=10u8 {
+            let keys: Keypad = *chip_eight.keypad();
+            chip_eight.frame(&keys).unwrap();
+            assert_eq!(chip_eight.pc(), 0x204, "frame {}", frame);
+        }
+        // The timers kept ticking all the while.
+        assert_eq!(chip_eight.delay_timer(), 21);
+        assert_eq!(chip_eight.v_registers()[0], 0);
+
+        // Nothing's changed on the screen, but a redraw asks for all of it.
+        chip_eight.take_dirty_region();
+        let flow: ControlFlow<()> = apply_input_events(&mut chip_eight, [InputEvent::Redraw]);
+        assert_eq!(flow, ControlFlow::Continue(()));
+        assert!(chip_eight.dirty_region().is_some());
+
+        for event in [InputEvent::KeyPressed(0x7), InputEvent::KeyReleased(0x7)] {
+            let flow: ControlFlow<()> = apply_input_events(&mut chip_eight, [event]);
+            assert_eq!(flow, ControlFlow::Continue(()));
+            let keys: Keypad = *chip_eight.keypad();
+            chip_eight.frame(&keys).unwrap();
+        }
+        assert_eq!(chip_eight.v_registers()[0], 0x7);
+        assert_eq!(chip_eight.v_registers()[2], 1);
+        assert_eq!(chip_eight.pc(), 0x208);
+    }
+}
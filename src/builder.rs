@@ -0,0 +1,245 @@
+// For setting up a machine with something other than the defaults. ChipEight::new() is the same as ChipEightBuilder::new().build().unwrap().
+use crate::font::FONTS_END;
+use crate::{ChipEight, ChipVariant, FontSet, MemorySize, Quirks};
+use core::fmt;
+
+#[derive(Clone, Debug, Default)]
+pub struct ChipEightBuilder {
+    variant: ChipVariant,
+    quirks: Option<Quirks>,
+    start_address: Option<u16>,
+    rng_seed: Option<u64>,
+    instructions_per_frame: Option<u32>,
+    memory_size: Option<MemorySize>,
+    font: FontSet,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    // Only XO-CHIP can address more than 4KiB.
+    ExtendedMemoryRequiresXoChip(ChipVariant),
+    // Without the std feature there's no heap to put 64KiB of memory on.
+    ExtendedMemoryRequiresStd,
+    // Programs have to start after the font and leave at least one instruction's worth of room before the end of memory.
+    StartAddressOutOfRange {
+        start_address: u16,
+        memory_size: usize,
+    },
+    ZeroInstructionsPerFrame,
+    // Without the std feature there's no entropy to seed the RNG from, so rng_seed has to be given.
+    SeedRequired,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::ExtendedMemoryRequiresXoChip(variant) => {
+                write!(f, "{} can't use 64KiB of memory, only XO-CHIP can", variant)
+            }
+            BuildError::ExtendedMemoryRequiresStd => {
+                write!(f, "64KiB of memory needs the std feature")
+            }
+            BuildError::StartAddressOutOfRange {
+                start_address,
+                memory_size,
+            } => write!(
+                f,
+                "start address {:#05X} is outside of program memory ({:#05X} - {:#05X})",
+                start_address,
+                FONTS_END,
+                memory_size - 2
+            ),
+            BuildError::ZeroInstructionsPerFrame => {
+                write!(f, "instructions per frame must be at least 1")
+            }
+            BuildError::SeedRequired => {
+                write!(f, "an RNG seed is needed without the std feature")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BuildError {}
+
+impl ChipEightBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn variant(mut self, variant: ChipVariant) -> Self {
+        self.variant = variant;
+        self
+    }
+    // Overrides the variant's quirk preset.
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = Some(quirks);
+        self
+    }
+    // Where programs are loaded and execution starts. This is 0x200 for nearly everything, but ETI-660 programs start at 0x600.
+    pub fn start_address(mut self, start_address: u16) -> Self {
+        self.start_address = Some(start_address);
+        self
+    }
+    // Seeds Cxkk's random number generator, so runs can be reproduced exactly. Without std, build() needs one.
+    pub fn rng_seed(mut self, seed: u64) -> Self {
+        self.rng_seed = Some(seed);
+        self
+    }
+    pub fn instructions_per_frame(mut self, instructions_per_frame: u32) -> Self {
+        self.instructions_per_frame = Some(instructions_per_frame);
+        self
+    }
+    // Defaults to 64KiB for XO-CHIP and 4KiB for everything else.
+    pub fn memory_size(mut self, memory_size: MemorySize) -> Self {
+        self.memory_size = Some(memory_size);
+        self
+    }
+    pub fn font(mut self, font: FontSet) -> Self {
+        self.font = font;
+        self
+    }
+    pub fn build(self) -> Result<ChipEight, BuildError> {
+        let memory_size: MemorySize = match self.memory_size {
+            Some(MemorySize::Extended) if self.variant != ChipVariant::XoChip => {
+                return Err(BuildError::ExtendedMemoryRequiresXoChip(self.variant))
+            }
+            Some(MemorySize::Extended) if cfg!(not(feature = "std")) => {
+                return Err(BuildError::ExtendedMemoryRequiresStd)
+            }
+            Some(memory_size) => memory_size,
+            None if self.variant == ChipVariant::XoChip && cfg!(feature = "std") => {
+                MemorySize::Extended
+            }
+            None => MemorySize::Classic,
+        };
+        let start_address: u16 = self.start_address.unwrap_or(0x200);
+        // Programs can't start on top of the fonts.
+        if start_address < FONTS_END || start_address as usize > memory_size.bytes() - 2 {
+            return Err(BuildError::StartAddressOutOfRange {
+                start_address,
+                memory_size: memory_size.bytes(),
+            });
+        }
+        if self.instructions_per_frame == Some(0) {
+            return Err(BuildError::ZeroInstructionsPerFrame);
+        }
+
+        #[cfg(feature = "std")]
+        let mut chip_eight = ChipEight::with_memory_size(memory_size);
+        #[cfg(not(feature = "std"))]
+        let mut chip_eight = match self.rng_seed {
+            Some(seed) => ChipEight::with_rng(memory_size, crate::random::Xorshift::new(seed)),
+            None => return Err(BuildError::SeedRequired),
+        };
+        chip_eight.set_variant(self.variant);
+        chip_eight.set_quirks(self.quirks);
+        chip_eight.set_font(self.font);
+        chip_eight.start_address = start_address;
+        chip_eight.pc = start_address;
+        if let Some(seed) = self.rng_seed {
+            chip_eight.seed_rng(seed);
+        }
+        if let Some(instructions_per_frame) = self.instructions_per_frame {
+            chip_eight.set_instructions_per_frame(instructions_per_frame);
+        }
+        Ok(chip_eight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_applies_every_setting() {
+        let quirks: Quirks = ChipVariant::XoChip.quirks();
+        let chip_eight: ChipEight = ChipEightBuilder::new()
+            .variant(ChipVariant::SuperChipModern)
+            .quirks(quirks)
+            .start_address(0x600)
+            .rng_seed(7)
+            .instructions_per_frame(30)
+            .font(FontSet::CosmacVip)
+            .build()
+            .unwrap();
+        assert_eq!(chip_eight.variant(), ChipVariant::SuperChipModern);
+        assert_eq!(chip_eight.quirks(), quirks);
+        assert_eq!(chip_eight.pc(), 0x600);
+        assert_eq!(chip_eight.instructions_per_frame(), 30);
+        assert_eq!(chip_eight.font(), FontSet::CosmacVip.glyphs());
+        assert_eq!(chip_eight.memory().len(), MemorySize::Classic.bytes());
+        // The same seed gives the same machine.
+        let again: ChipEight = ChipEightBuilder::new()
+            .variant(ChipVariant::SuperChipModern)
+            .quirks(quirks)
+            .start_address(0x600)
+            .rng_seed(7)
+            .instructions_per_frame(30)
+            .font(FontSet::CosmacVip)
+            .build()
+            .unwrap();
+        assert!(chip_eight == again);
+    }
+
+    #[test]
+    fn extended_memory_is_only_for_xo_chip() {
+        let built = ChipEightBuilder::new()
+            .rng_seed(0)
+            .memory_size(MemorySize::Extended)
+            .build();
+        assert_eq!(
+            built.err(),
+            Some(BuildError::ExtendedMemoryRequiresXoChip(ChipVariant::Chip8))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn xo_chip_gets_extended_memory_by_default() {
+        let chip_eight: ChipEight = ChipEightBuilder::new()
+            .variant(ChipVariant::XoChip)
+            .build()
+            .unwrap();
+        assert_eq!(chip_eight.memory().len(), MemorySize::Extended.bytes());
+    }
+
+    #[test]
+    fn programs_cant_start_on_the_fonts_or_past_the_end() {
+        for start_address in [0x000, FONTS_END - 1, 0xFFF] {
+            let built = ChipEightBuilder::new()
+                .rng_seed(0)
+                .start_address(start_address)
+                .build();
+            assert_eq!(
+                built.err(),
+                Some(BuildError::StartAddressOutOfRange {
+                    start_address,
+                    memory_size: 4096,
+                })
+            );
+        }
+        assert!(ChipEightBuilder::new()
+            .rng_seed(0)
+            .start_address(FONTS_END)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn zero_instructions_per_frame_is_rejected() {
+        let built = ChipEightBuilder::new()
+            .rng_seed(0)
+            .instructions_per_frame(0)
+            .build();
+        assert_eq!(built.err(), Some(BuildError::ZeroInstructionsPerFrame));
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn a_seed_is_needed_without_std() {
+        assert_eq!(
+            ChipEightBuilder::new().build().err(),
+            Some(BuildError::SeedRequired)
+        );
+    }
+}
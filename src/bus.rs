@@ -0,0 +1,193 @@
+// Memory-mapped peripherals. The program's own data accesses (Fx55, Fx65, Fx33, Dxyn, and XO-CHIP's 5xy2 and 5xy3) go
+// through the bus, so attached devices can answer reads and swallow writes to the addresses they care about. Instruction
+// fetches always come straight from RAM. Devices are boxed, so they're only available with std.
+use crate::ChipEight;
+#[cfg(feature = "std")]
+use crate::WatchKind;
+
+pub trait MemoryMappedDevice: Send {
+    // Called for every read that reaches this device. Return Some to answer it, or None to pass it on to the next device
+    // (and eventually RAM).
+    fn read(&mut self, addr: u16) -> Option<u8>;
+    // Called for every write that reaches this device. Return true if the write was handled, or false to pass it on.
+    fn write(&mut self, addr: u16, value: u8) -> bool;
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub(crate) struct Devices {
+    // In the order they were attached. Earlier devices get the first look at every access.
+    devices: Vec<Box<dyn MemoryMappedDevice>>,
+}
+
+// Like hooks, devices belong to whoever attached them rather than to the machine's state, so a cloned machine starts out
+// with none.
+#[cfg(feature = "std")]
+impl Clone for Devices {
+    fn clone(&self) -> Self {
+        Devices::default()
+    }
+}
+
+#[cfg(feature = "std")]
+impl ChipEight {
+    pub fn attach_device(&mut self, device: impl MemoryMappedDevice + 'static) {
+        self.devices.devices.push(Box::new(device));
+    }
+    pub fn detach_devices(&mut self) {
+        self.devices = Devices::default();
+    }
+}
+
+impl ChipEight {
+    // Reads a byte the way the program sees it: attached devices first, then RAM. Addresses wrap to the size of RAM.
+    pub fn read_bus(&mut self, addr: usize) -> u8 {
+        let addr: usize = self.address(addr);
+
+        #[cfg(feature = "std")]
+        {
+            let value: u8 = self
+                .devices
+                .devices
+                .iter_mut()
+                .find_map(|device| device.read(addr as u16))
+                .unwrap_or(self.memory[addr]);
+            self.check_watchpoint(WatchKind::Read, addr as u16, value, value);
+            value
+        }
+        #[cfg(not(feature = "std"))]
+        self.memory[addr]
+    }
+    // Writes a byte the way the program does: attached devices first, then RAM. Addresses wrap to the size of RAM.
+    pub fn write_bus(&mut self, addr: usize, value: u8) {
+        let addr: usize = self.address(addr);
+
+        #[cfg(feature = "std")]
+        self.check_watchpoint(WatchKind::Write, addr as u16, self.memory[addr], value);
+        #[cfg(feature = "std")]
+        for device in self.devices.devices.iter_mut() {
+            if device.write(addr as u16, value) {
+                return;
+            }
+        }
+        self.memory[addr] = value;
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // A serial port at 0xFF0 that keeps what's written to it, and a counter at 0xFFE that counts up on every read.
+    #[derive(Default)]
+    struct Peripherals {
+        sent: Arc<Mutex<Vec<u8>>>,
+        reads: u8,
+    }
+
+    impl MemoryMappedDevice for Peripherals {
+        fn read(&mut self, addr: u16) -> Option<u8> {
+            match addr {
+                0xFFE => {
+                    self.reads += 1;
+                    Some(self.reads)
+                }
+                _ => None,
+            }
+        }
+        fn write(&mut self, addr: u16, value: u8) -> bool {
+            match addr {
+                0xFF0 => {
+                    self.sent.lock().unwrap().push(value);
+                    true
+                }
+                _ => false,
+            }
+        }
+    }
+
+    // A machine with program at 0x200, V0 - V2 set to 1, 2, 3, and the peripherals attached.
+    fn machine(program: &[u8]) -> (ChipEight, Arc<Mutex<Vec<u8>>>) {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.write_memory(0x200, program).unwrap();
+        for (x, value) in [1, 2, 3].into_iter().enumerate() {
+            chip_eight.set_v(x, value);
+        }
+        let peripherals: Peripherals = Peripherals::default();
+        let sent: Arc<Mutex<Vec<u8>>> = Arc::clone(&peripherals.sent);
+        chip_eight.attach_device(peripherals);
+        (chip_eight, sent)
+    }
+
+    #[test]
+    fn stores_go_to_the_device_and_ram_around_it() {
+        // Stores V0 - V2 from 0xFEF, so V1 lands on the serial port.
+        let (mut chip_eight, sent) = machine(&[0xAF, 0xEF, 0xF2, 0x55]);
+        chip_eight.step().unwrap();
+        chip_eight.step().unwrap();
+        assert_eq!(*sent.lock().unwrap(), [2]);
+        assert_eq!(chip_eight.memory()[0xFEF..0xFF2], [1, 0, 3]);
+    }
+
+    #[test]
+    fn bcd_goes_through_the_device() {
+        // 123 as BCD at 0xFEF.
+        let (mut chip_eight, sent) = machine(&[0x60, 123, 0xAF, 0xEF, 0xF0, 0x33]);
+        for _ in 0..3 {
+            chip_eight.step().unwrap();
+        }
+        assert_eq!(*sent.lock().unwrap(), [2]);
+        assert_eq!(chip_eight.memory()[0xFEF..0xFF2], [1, 0, 3]);
+    }
+
+    #[test]
+    fn loads_and_sprites_read_from_the_device() {
+        // Loads V0 - V1 from 0xFFE (the counter) and 0xFFF, then draws a one-row sprite from the counter.
+        let program: [u8; 10] = [0xAF, 0xFE, 0xF1, 0x65, 0xAF, 0xFE, 0xD3, 0x31, 0x12, 0x08];
+        let (mut chip_eight, _) = machine(&program);
+        chip_eight.write_memory(0xFFF, &[0x42]).unwrap();
+        for _ in 0..4 {
+            chip_eight.step().unwrap();
+        }
+        assert_eq!(chip_eight.v_registers()[..2], [1, 0x42]);
+        // The second read of the counter is 2, so only the seventh pixel is lit.
+        let lit: Vec<(u8, u8)> = chip_eight.pixels_set().collect();
+        assert_eq!(lit, [(6, 0)]);
+        assert_eq!(chip_eight.memory()[0xFFE], 0);
+
+        // Without the device, the same program reads RAM.
+        let mut plain: ChipEight = ChipEight::with_seed(1);
+        plain.write_memory(0x200, &program).unwrap();
+        plain.write_memory(0xFFE, &[0x80, 0x42]).unwrap();
+        for _ in 0..4 {
+            plain.step().unwrap();
+        }
+        assert_eq!(plain.v_registers()[..2], [0x80, 0x42]);
+        assert_eq!(plain.pixels_set().collect::<Vec<(u8, u8)>>(), [(0, 0)]);
+    }
+
+    #[test]
+    fn earlier_devices_get_the_first_look() {
+        struct Answers(u8);
+        impl MemoryMappedDevice for Answers {
+            fn read(&mut self, _addr: u16) -> Option<u8> {
+                Some(self.0)
+            }
+            fn write(&mut self, _addr: u16, _value: u8) -> bool {
+                true
+            }
+        }
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.attach_device(Answers(1));
+        chip_eight.attach_device(Answers(2));
+        assert_eq!(chip_eight.read_bus(0x300), 1);
+        chip_eight.write_bus(0x300, 9);
+        assert_eq!(chip_eight.memory()[0x300], 0);
+        // Clones and detached machines are back to plain RAM.
+        assert_eq!(chip_eight.clone().read_bus(0x300), 0);
+        chip_eight.detach_devices();
+        chip_eight.write_bus(0x300, 9);
+        assert_eq!(chip_eight.read_bus(0x300), 9);
+    }
+}
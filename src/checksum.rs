@@ -0,0 +1,124 @@
+// CRC-32 (the IEEE polynomial used by zip, PNG, etc.). This is the bit-at-a-time version, which is plenty fast for ROM-sized inputs.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFFFFFF;
+
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// SHA-1, for matching ROMs against hash lists that use it (most ROM archives do). Nothing here is security sensitive.
+pub fn sha1(bytes: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    // The message is followed by a 1 bit, zeroes up to 8 bytes short of a block boundary, and the length in bits.
+    let bit_length: u64 = (bytes.len() as u64).wrapping_mul(8);
+    let padded_length: usize = (bytes.len() + 9).div_ceil(64) * 64;
+    let byte_at = |i: usize| -> u8 {
+        if i < bytes.len() {
+            bytes[i]
+        } else if i == bytes.len() {
+            0x80
+        } else if i >= padded_length - 8 {
+            (bit_length >> ((padded_length - 1 - i) * 8)) as u8
+        } else {
+            0
+        }
+    };
+
+    for block in (0..padded_length).step_by(64) {
+        let mut w: [u32; 80] = [0; 80];
+        for (t, word) in w.iter_mut().take(16).enumerate() {
+            let i: usize = block + t * 4;
+            *word =
+                u32::from_be_bytes([byte_at(i), byte_at(i + 1), byte_at(i + 2), byte_at(i + 3)]);
+        }
+        for t in 16..80 {
+            w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (t, word) in w.iter().enumerate() {
+            let (f, k): (u32, u32) = match t {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp: u32 = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest: [u8; 20] = [0; 20];
+    for (chunk, state) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&state.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChipEight, RomInfo};
+
+    fn digest(hex: &str) -> [u8; 20] {
+        ::core::array::from_fn(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap())
+    }
+
+    #[test]
+    fn crc32_check_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn sha1_test_vectors() {
+        for (input, expected) in [
+            (&b""[..], "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            (b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            // 56 bytes, so the padding spills into a second block.
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+        ] {
+            assert_eq!(sha1(input), digest(expected), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn rom_info_hashes_the_program() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        let info: RomInfo = chip_eight
+            .load_program_from_slice(include_bytes!("../tests/fixtures/ibm_logo.ch8"))
+            .unwrap();
+        // As sha1sum and zlib's crc32 have them.
+        assert_eq!(info.size, 132);
+        assert_eq!(info.crc32, 0xC46CA868);
+        assert_eq!(
+            info.sha1,
+            digest("1ba58656810b67fd131eb9af3e3987863bf26c90")
+        );
+    }
+}
@@ -0,0 +1,71 @@
+// Running the same program on two machines in lockstep, to see where two quirk profiles part ways. Both machines get
+// exactly the same keys every frame, so as long as they also share an RNG seed, any difference on screen comes down to
+// how they're configured.
+use crate::{ChipEight, ChipEightError, FrameOutput, Keypad};
+
+pub struct Comparison {
+    pub left: ChipEight,
+    pub right: ChipEight,
+    // How many frames both machines have run.
+    frames: u64,
+    // The first frame after which the screens didn't match.
+    divergence: Option<u64>,
+}
+
+impl Comparison {
+    // The machines should already have the program loaded. If they start out with different screens, they count as
+    // diverging at frame 0.
+    pub fn new(left: ChipEight, right: ChipEight) -> Self {
+        let mut comparison = Comparison {
+            left,
+            right,
+            frames: 0,
+            divergence: None,
+        };
+        comparison.check_divergence();
+        comparison
+    }
+    // Runs one 60Hz frame on both machines with the same keys.
+    pub fn frame(&mut self, keys: &Keypad) -> Result<(FrameOutput, FrameOutput), ChipEightError> {
+        let left: FrameOutput = self.left.frame(keys)?;
+        let right: FrameOutput = self.right.frame(keys)?;
+
+        self.frames += 1;
+        self.check_divergence();
+        Ok((left, right))
+    }
+    // Runs up to frames frames without a display, asking keys for the keypad state at the start of each one (frames are
+    // numbered from 1). Stops early once the screens diverge or both programs have exited, and returns the divergence frame.
+    pub fn run_headless(
+        &mut self,
+        frames: u64,
+        mut keys: impl FnMut(u64) -> Keypad,
+    ) -> Result<Option<u64>, ChipEightError> {
+        for _ in 0..frames {
+            if self.divergence.is_some() {
+                break;
+            }
+            let (left, right) = self.frame(&keys(self.frames + 1))?;
+            if left.halted && right.halted {
+                break;
+            }
+        }
+        Ok(self.divergence)
+    }
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+    // The first frame after which the two screens differed, if they have yet. Switching between lores and hires counts.
+    pub fn divergence(&self) -> Option<u64> {
+        self.divergence
+    }
+    fn check_divergence(&mut self) {
+        if self.divergence.is_none() && !self.screens_match() {
+            self.divergence = Some(self.frames);
+        }
+    }
+    fn screens_match(&self) -> bool {
+        self.left.resolution() == self.right.resolution()
+            && self.left.framebuffer() == self.right.framebuffer()
+    }
+}
@@ -0,0 +1,15 @@
+// Everything needed to run Chip-8 programs, and nothing that talks to the outside world. Embedders (tests, web builds,
+// microcontrollers) can depend on this alone; the SDL window, keyboard, and audio live in frontend_sdl.
+pub use crate::{
+    decode, BuildError, ChipEight, ChipEightBuilder, ChipEightError, ChipVariant, Comparison,
+    DirtyRect, DrawRect, ExitReason, Font, FontSet, FrameOutput, HookAction, Instruction, Keypad,
+    LoadError, MemoryMappedDevice, MemorySize, Palette, Phosphor, Quirks, RandomSource,
+    RegisterSnapshot, RenderError, RomInfo, Screen, ScreenMode, ScreenTextError, StepOutcome,
+    TimingMode, TraceEntry, UnknownOpcode, Xorshift, FONT_ADDRESS, NAMED_COLORS, PHOSPHOR_FRAMES,
+    THEMES, VIP_CYCLES_PER_FRAME,
+};
+#[cfg(feature = "std")]
+pub use crate::{
+    run_headless, screen_from_string, Command, CoverageMap, EmulatorHandle, Frame, HeadlessResult,
+    MockRng, StateError, StopToken,
+};
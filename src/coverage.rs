@@ -0,0 +1,210 @@
+// Which addresses a program has executed, for seeing how much of a ROM a run (or a set of test inputs) exercises. The
+// machine marks the bytes of every instruction it fetches, including the address word after F000, and keeps them
+// across resets and program loads until clear_coverage().
+use crate::{decode, ChipEight, Instruction};
+use std::fmt::Write;
+use std::ops::Range;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CoverageMap {
+    // One bit per byte of memory.
+    bits: Vec<u64>,
+}
+
+impl CoverageMap {
+    pub fn new(memory_size: usize) -> Self {
+        CoverageMap {
+            bits: vec![0; memory_size.div_ceil(64)],
+        }
+    }
+    // Reads back the executed ranges from a report(), e.g. to annotate a listing after the run. Lines that aren't ranges
+    // under "Executed:" are ignored.
+    pub fn from_report(report: &str) -> Self {
+        let mut coverage: CoverageMap = CoverageMap::default();
+        let mut executed: bool = false;
+
+        for line in report.lines() {
+            if !line.starts_with(' ') {
+                executed = line.trim() == "Executed:";
+                continue;
+            }
+            let (start, end) = line
+                .trim()
+                .split_once('-')
+                .unwrap_or((line.trim(), line.trim()));
+            if let (true, Ok(start), Ok(end)) = (
+                executed,
+                usize::from_str_radix(start, 16),
+                usize::from_str_radix(end, 16),
+            ) {
+                coverage.mark(start, (end + 1).saturating_sub(start));
+            }
+        }
+        coverage
+    }
+    // Marks len bytes from addr as executed.
+    pub fn mark(&mut self, addr: usize, len: usize) {
+        for addr in addr..addr + len {
+            if addr / 64 >= self.bits.len() {
+                self.bits.resize(addr / 64 + 1, 0);
+            }
+            self.bits[addr / 64] |= 1 << (addr % 64);
+        }
+    }
+    pub fn executed(&self, addr: usize) -> bool {
+        self.bits
+            .get(addr / 64)
+            .is_some_and(|word| word & (1 << (addr % 64)) != 0)
+    }
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+    // How many bytes have been executed.
+    pub fn executed_bytes(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+    // Every run of executed bytes, in address order.
+    pub fn executed_ranges(&self) -> Vec<Range<usize>> {
+        runs(0..self.bits.len() * 64, |addr| self.executed(addr))
+    }
+    // The runs of the program that look like code but never ran: words at even addresses that decode to an instruction
+    // other than 0nnn (which is what zeroes decode to). Data that happens to decode is counted too, since nothing says
+    // which is which.
+    pub fn unexecuted_code(&self, memory: &[u8], program: Range<usize>) -> Vec<Range<usize>> {
+        let looks_like_code = |addr: usize| -> bool {
+            let word: usize = addr & !1;
+            if word + 1 >= memory.len() || self.executed(word) || self.executed(word + 1) {
+                return false;
+            }
+            let opcode: u16 = u16::from_be_bytes([memory[word], memory[word + 1]]);
+            !matches!(
+                decode(opcode),
+                Err(_) | Ok(Instruction::JumpToMachineCode { .. })
+            )
+        };
+        runs(program, looks_like_code)
+    }
+    // A text report of what ran in memory, and of what looks like code in program but didn't:
+    // Executed 312 of 480 bytes that look like code (65.0%).
+    // Executed:
+    //   200-2A7
+    // Not executed:
+    //   2A8-2AF
+    pub fn report(&self, memory: &[u8], program: Range<usize>) -> String {
+        let executed: usize = program.clone().filter(|&addr| self.executed(addr)).count();
+        let unexecuted: Vec<Range<usize>> = self.unexecuted_code(memory, program);
+        let code: usize = executed + unexecuted.iter().map(|range| range.len()).sum::<usize>();
+        let percent: f64 = if code == 0 {
+            0.0
+        } else {
+            executed as f64 * 100.0 / code as f64
+        };
+
+        let mut report: String = format!(
+            "Executed {} of {} bytes that look like code ({:.1}%).\n",
+            executed, code, percent
+        );
+        for (heading, ranges) in [
+            ("Executed:", self.executed_ranges()),
+            ("Not executed:", unexecuted),
+        ] {
+            let _ = writeln!(report, "{}", heading);
+            for range in ranges {
+                let _ = writeln!(report, "  {:03X}-{:03X}", range.start, range.end - 1);
+            }
+        }
+        report
+    }
+}
+
+// The runs of consecutive addresses in range that pred holds for.
+fn runs(range: Range<usize>, pred: impl Fn(usize) -> bool) -> Vec<Range<usize>> {
+    let mut runs: Vec<Range<usize>> = vec![];
+    let mut start: Option<usize> = None;
+
+    for addr in range.clone() {
+        match (pred(addr), start) {
+            (true, None) => start = Some(addr),
+            (false, Some(run)) => {
+                runs.push(run..addr);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(run) = start {
+        runs.push(run..range.end);
+    }
+    runs
+}
+
+impl ChipEight {
+    pub fn coverage(&self) -> &CoverageMap {
+        &self.coverage
+    }
+    pub fn clear_coverage(&mut self) {
+        self.coverage.clear();
+    }
+    // The coverage report for the loaded program, or for everything from the start address on if there isn't one.
+    pub fn coverage_report(&self) -> String {
+        let start: usize = self.start_address as usize;
+        let end: usize = match &self.rom_info {
+            Some(info) => start + info.size,
+            None => self.memory.len(),
+        };
+        self.coverage.report(&self.memory, start..end)
+    }
+    // Called by step() for each instruction it's about to execute.
+    pub(crate) fn mark_executed(&mut self, opcode: u16) {
+        let len: usize = if self.variant.has_xo_chip_opcodes() && opcode == 0xF000 {
+            4
+        } else {
+            2
+        };
+        self.coverage.mark(self.pc as usize, len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_branch_that_is_never_taken_shows_as_not_executed() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        // LD V0, 5; SE V0, 5; LD V1, 1 (always skipped); then spin.
+        chip_eight
+            .load_program_from_slice(&[0x60, 0x05, 0x30, 0x05, 0x61, 0x01, 0x12, 0x06])
+            .unwrap();
+        for _ in 0..10 {
+            chip_eight.step().unwrap();
+        }
+
+        let coverage: &CoverageMap = chip_eight.coverage();
+        assert_eq!(coverage.executed_ranges(), vec![0x200..0x204, 0x206..0x208]);
+        assert_eq!(coverage.executed_bytes(), 6);
+        assert!(!coverage.executed(0x204));
+
+        let report: String = chip_eight.coverage_report();
+        assert_eq!(
+            report,
+            "Executed 6 of 8 bytes that look like code (75.0%).\n\
+             Executed:\n  \
+             200-203\n  \
+             206-207\n\
+             Not executed:\n  \
+             204-205\n"
+        );
+        // A report reads back to the same executed ranges.
+        assert_eq!(
+            CoverageMap::from_report(&report).executed_ranges(),
+            coverage.executed_ranges()
+        );
+
+        chip_eight.clear_coverage();
+        assert_eq!(chip_eight.coverage().executed_bytes(), 0);
+    }
+}
@@ -0,0 +1,255 @@
+// Everything needed to look into a failure after the fact, for attaching to bug reports: a save state, what the machine
+// was running and how, the last instructions it executed, and the screen. The machine always remembers its last
+// RECENT_INSTRUCTIONS instructions for this, which is cheap enough that it doesn't need turning on.
+use crate::{disassemble, ChipEight, ChipEightError, Quirks};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// How many of the most recently executed instructions are kept.
+pub const RECENT_INSTRUCTIONS: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecentInstruction {
+    // The frame it ran in, where it was fetched from, and what it was.
+    pub frame: u64,
+    pub pc: u16,
+    pub opcode: u16,
+}
+
+// A ring buffer of the last RECENT_INSTRUCTIONS instructions.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RecentInstructions {
+    entries: Vec<RecentInstruction>,
+    // Where the next one goes, which once the buffer is full is also the oldest.
+    next: usize,
+}
+
+impl RecentInstructions {
+    fn push(&mut self, entry: RecentInstruction) {
+        if self.entries.len() < RECENT_INSTRUCTIONS {
+            self.entries.push(entry);
+        } else {
+            self.entries[self.next] = entry;
+        }
+        self.next = (self.next + 1) % RECENT_INSTRUCTIONS;
+    }
+    // Oldest first. Until the buffer fills up, next is the end of it, so this works either way.
+    fn to_vec(&self) -> Vec<RecentInstruction> {
+        let (newer, older) = self.entries.split_at(self.next);
+        older.iter().chain(newer).copied().collect()
+    }
+}
+
+// The contents of a dump, as the files write_to() writes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrashDump {
+    // state.bin: save_state().
+    pub state: Vec<u8>,
+    // info.txt: the error, the ROM, the settings, and the registers.
+    pub info: String,
+    // trace.txt: the recent instructions, oldest first, e.g. "f=120 pc=0228 8124 ADD V1, V2".
+    pub trace: String,
+    // screen.txt: screen_to_string().
+    pub screen: String,
+}
+
+impl CrashDump {
+    // Writes the dump's files to a new directory in dir named after the time, e.g. potatocho-dump-1700000000, and returns
+    // its path.
+    pub fn write_to(&self, dir: &Path) -> io::Result<PathBuf> {
+        let time: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let mut path: PathBuf = dir.join(format!("potatocho-dump-{}", time));
+        // More than one dump a second gets a suffix rather than overwriting the last one.
+        let mut n: u32 = 1;
+        while path.exists() {
+            n += 1;
+            path = dir.join(format!("potatocho-dump-{}-{}", time, n));
+        }
+
+        fs::create_dir_all(&path)?;
+        fs::write(path.join("state.bin"), &self.state)?;
+        fs::write(path.join("info.txt"), &self.info)?;
+        fs::write(path.join("trace.txt"), &self.trace)?;
+        fs::write(path.join("screen.txt"), &self.screen)?;
+        Ok(path)
+    }
+}
+
+impl ChipEight {
+    // The last RECENT_INSTRUCTIONS instructions executed (or started, for one that failed), oldest first.
+    pub fn recent_instructions(&self) -> Vec<RecentInstruction> {
+        self.recent.to_vec()
+    }
+    // A dump of the machine as it is now, after error if it failed, or as asked for otherwise.
+    pub fn crash_dump(&self, error: Option<&ChipEightError>) -> CrashDump {
+        let quirks: Quirks = self.quirks();
+        let quirks: Vec<String> = Quirks::NAMES
+            .iter()
+            .map(|name| format!("{}={}", name, (quirks.get(name) == Some(true)) as u8))
+            .collect();
+        let rom: String = match &self.rom_info {
+            Some(info) => format!(
+                "{} bytes, SHA-1 {}",
+                info.size,
+                info.sha1
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>()
+            ),
+            None => "none".to_string(),
+        };
+        let registers: String = self
+            .v_registers
+            .iter()
+            .map(|v| format!("{:02X}", v))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let stack: String = self
+            .stack()
+            .iter()
+            .map(|addr| format!(" {:03X}", addr))
+            .collect();
+
+        let info: String = [
+            format!(
+                "error: {}",
+                error.map_or("none (dumped on request)".to_string(), |e| e.to_string())
+            ),
+            format!("rom: {}", rom),
+            format!("variant: {}", self.variant.name()),
+            format!("quirks: {}", quirks.join(" ")),
+            format!("instructions_per_frame: {}", self.instructions_per_frame),
+            format!("timing: {}", self.timing_mode),
+            format!("frame: {}", self.frames),
+            format!(
+                "pc: {:03X}  i: {:03X}  dt: {:02X}  st: {:02X}",
+                self.pc, self.i_register, self.delay_timer, self.sound_timer
+            ),
+            format!("v: {}", registers),
+            format!("stack:{}", stack),
+        ]
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect();
+        let trace: String = self
+            .recent
+            .to_vec()
+            .iter()
+            .map(|entry| {
+                format!(
+                    "f={} pc={:04X} {:04X} {}\n",
+                    entry.frame,
+                    entry.pc,
+                    entry.opcode,
+                    disassemble(entry.opcode)
+                )
+            })
+            .collect();
+
+        CrashDump {
+            state: self.save_state(),
+            info,
+            trace,
+            screen: self.screen_to_string(),
+        }
+    }
+    // Called by step() for each instruction it's about to execute.
+    pub(crate) fn record_recent(&mut self, opcode: u16) {
+        self.recent.push(RecentInstruction {
+            frame: self.frames,
+            pc: self.pc,
+            opcode,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChipVariant, Keypad};
+
+    // Counts V0 from 5 up past 0xFF to 0, draws a 0, then returns with an empty stack.
+    const PROGRAM: [u8; 12] = [
+        0x60, 0x05, 0x70, 0x01, 0x30, 0x00, 0x12, 0x02, 0xD0, 0x05, 0x00, 0xEE,
+    ];
+
+    fn crashed() -> (ChipEight, ChipEightError) {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        chip_eight.set_quirks(Some(ChipVariant::Chip8.quirks()));
+        chip_eight.load_program_from_slice(&PROGRAM).unwrap();
+        loop {
+            if let Err(e) = chip_eight.frame(&Keypad::default()) {
+                return (chip_eight, e);
+            }
+        }
+    }
+
+    #[test]
+    fn the_dump_has_the_error_rom_registers_trace_and_screen() {
+        let (chip_eight, error) = crashed();
+        let dump: CrashDump = chip_eight.crash_dump(Some(&error));
+
+        assert_eq!(
+            dump.info,
+            "error: returned from a subroutine at 0x20A with an empty stack\n\
+             rom: 12 bytes, SHA-1 9dea768737c1cb6543ba583cf3f2b6a2cf35cb6b\n\
+             variant: chip8\n\
+             quirks: vf_reset=1 shift_uses_vy=1 load_store_increments_i=1 jump_uses_vx=0 clip_sprites=1 \
+             display_wait=1\n\
+             instructions_per_frame: 10\n\
+             timing: ipf\n\
+             frame: 77\n\
+             pc: 20A  i: 000  dt: 00  st: 00\n\
+             v: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00\n\
+             stack:\n"
+        );
+
+        // Only the last RECENT_INSTRUCTIONS are kept, ending with the one that failed.
+        let trace: Vec<&str> = dump.trace.lines().collect();
+        assert_eq!(trace.len(), RECENT_INSTRUCTIONS);
+        assert_eq!(
+            trace[trace.len() - 3..],
+            [
+                "f=76 pc=0204 3000 SE V0, 0x00",
+                "f=76 pc=0208 D005 DRW V0, V0, 5",
+                "f=77 pc=020A 00EE RET",
+            ]
+        );
+        assert_eq!(chip_eight.recent_instructions().len(), RECENT_INSTRUCTIONS);
+
+        assert_eq!(dump.screen, chip_eight.screen_to_string());
+        assert!(dump.screen.starts_with("####....."));
+
+        // The save state puts a fresh machine back where this one failed.
+        let mut restored: ChipEight = ChipEight::new();
+        restored.load_state(&dump.state).unwrap();
+        assert_eq!(restored.pc(), 0x20A);
+        assert_eq!(restored.screen_to_string(), dump.screen);
+    }
+
+    #[test]
+    fn dumps_on_request_say_so_and_write_every_file() {
+        let (chip_eight, _) = crashed();
+        let dump: CrashDump = chip_eight.crash_dump(None);
+        assert!(dump.info.starts_with("error: none (dumped on request)\n"));
+
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("potatocho-test-{}", std::process::id()));
+        let first: PathBuf = dump.write_to(&dir).unwrap();
+        let second: PathBuf = dump.write_to(&dir).unwrap();
+        assert_ne!(first, second, "a second dump shouldn't overwrite the first");
+        for (file, contents) in [
+            ("info.txt", dump.info.as_bytes()),
+            ("trace.txt", dump.trace.as_bytes()),
+            ("screen.txt", dump.screen.as_bytes()),
+            ("state.bin", &dump.state[..]),
+        ] {
+            assert_eq!(fs::read(second.join(file)).unwrap(), contents, "{}", file);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+// The debug overlay's text, and the tiny bitmap font the SDL frontend draws it with. There's no font rendering otherwise,
+// and pulling in SDL_ttf for a few lines of hex isn't worth the extra dependency.
+use crate::{disassemble, ChipEight};
+
+// Glyphs are 3x5 pixels, with a pixel of space after each character and each line.
+pub(crate) const GLYPH_WIDTH: usize = 3;
+pub(crate) const GLYPH_HEIGHT: usize = 5;
+pub(crate) const CHAR_ADVANCE: usize = GLYPH_WIDTH + 1;
+pub(crate) const LINE_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+// The lines of the overlay: registers, timers, the current and next instructions, and the calls on the stack (innermost
+// first, by the address of each call).
+pub(crate) fn lines(chip_eight: &ChipEight) -> [String; 6] {
+    let v: &[u8; 16] = chip_eight.v_registers();
+    let registers = |range: core::ops::Range<usize>| -> String {
+        range
+            .map(|x| format!("V{:X} {:02X}", x, v[x]))
+            .collect::<Vec<String>>()
+            .join(" ")
+    };
+    let memory: &[u8] = chip_eight.memory();
+    let word_at = |addr: usize| -> u16 {
+        u16::from_be_bytes([
+            memory[addr % memory.len()],
+            memory[(addr + 1) % memory.len()],
+        ])
+    };
+
+    let pc: u16 = chip_eight.pc();
+    let opcode: u16 = word_at(pc as usize);
+    // XO-CHIP's F000 nnnn is twice as long as everything else.
+    let next_pc: u16 = if opcode == 0xF000 && chip_eight.variant().has_xo_chip_opcodes() {
+        pc.wrapping_add(4)
+    } else {
+        pc.wrapping_add(2)
+    };
+    let next_opcode: u16 = word_at(next_pc as usize);
+
+    [
+        format!(
+            "PC {:04X}  I {:04X}  DT {:02X}  ST {:02X}  SP {}",
+            pc,
+            chip_eight.i_register(),
+            chip_eight.delay_timer(),
+            chip_eight.sound_timer(),
+            chip_eight.stack().len()
+        ),
+        registers(0..8),
+        registers(8..16),
+        format!("> {:04X} {:04X} {}", pc, opcode, disassemble(opcode)),
+        format!(
+            "  {:04X} {:04X} {}",
+            next_pc,
+            next_opcode,
+            disassemble(next_opcode)
+        ),
+        chip_eight
+            .call_stack()
+            .iter()
+            .fold(String::from("CALLS"), |line, frame| {
+                format!("{} {:03X}", line, frame.call_site)
+            }),
+    ]
+}
+
+// The rows of a character's glyph, top to bottom, with the leftmost pixel in bit 2. Letters are all uppercase, except
+// for the x in 0x. Characters without a glyph come out as a question mark.
+pub(crate) fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        'x' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        c if c.is_ascii_lowercase() => glyph(c.to_ascii_uppercase()),
+        _ => [0b111, 0b001, 0b011, 0b000, 0b010],
+    }
+}
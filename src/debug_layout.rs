@@ -0,0 +1,373 @@
+// Debugging panels beside the screen, for when the overlay under it gets crowded. A layout is a list of panels, each
+// showing one thing about the machine in the overlay's bitmap font, and F6 cycles through LAYOUTS. Where everything goes
+// is worked out by layout(), in logical pixels (the font's), from nothing but sizes, so it doesn't need SDL to follow.
+use crate::debug_hud::{CHAR_ADVANCE, LINE_HEIGHT};
+use crate::{disassemble_with_symbols, ChipEight};
+
+// What a panel shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Panel {
+    // PC, I, the timers, and V0 to VF.
+    Registers,
+    // The instructions around PC.
+    Code,
+    // The bytes around I.
+    Memory,
+    // The subroutine calls the machine is inside of.
+    Stack,
+}
+
+// The layouts F6 cycles through: the panels in each, in order away from the screen. The first has none, and leaves the
+// screen as it always was.
+pub const LAYOUTS: [&[Panel]; 5] = [
+    &[],
+    &[Panel::Registers],
+    &[Panel::Registers, Panel::Code],
+    &[Panel::Code, Panel::Memory],
+    &[Panel::Memory, Panel::Stack],
+];
+
+// The space left around a panel's text, inside its border.
+const MARGIN: usize = 2;
+
+// Instructions shown before PC in the code panel, and bytes a row in the memory panel.
+const CODE_BEFORE: usize = 4;
+const MEMORY_COLUMNS: usize = 8;
+
+impl Panel {
+    // How many characters wide and lines tall the panel's text is. It's the same whatever's in it, so the layout doesn't
+    // shift about as the machine runs.
+    fn text_size(&self) -> (usize, usize) {
+        match self {
+            Panel::Registers => (15, 12),
+            Panel::Code => (26, 16),
+            Panel::Memory => (4 + MEMORY_COLUMNS * 3, 17),
+            Panel::Stack => (14, 17),
+        }
+    }
+    // The size of the panel in logical pixels, text and margins.
+    pub fn size(&self) -> (usize, usize) {
+        let (columns, rows) = self.text_size();
+        (
+            columns * CHAR_ADVANCE - 1 + MARGIN * 2,
+            rows * LINE_HEIGHT - 1 + MARGIN * 2,
+        )
+    }
+    // The panel's lines of text, each cut to fit.
+    pub fn lines(&self, chip_eight: &ChipEight) -> Vec<String> {
+        let (columns, _) = self.text_size();
+        let lines: Vec<String> = match self {
+            Panel::Registers => registers_lines(chip_eight),
+            Panel::Code => code_lines(chip_eight),
+            Panel::Memory => memory_lines(chip_eight),
+            Panel::Stack => stack_lines(chip_eight),
+        };
+        lines
+            .into_iter()
+            .map(|line| line.chars().take(columns).collect())
+            .collect()
+    }
+}
+
+fn registers_lines(chip_eight: &ChipEight) -> Vec<String> {
+    let v: &[u8; 16] = chip_eight.v_registers();
+    let mut lines: Vec<String> = vec![
+        "REGISTERS".to_string(),
+        format!(
+            "PC {:04X}  I {:04X}",
+            chip_eight.pc(),
+            chip_eight.i_register()
+        ),
+        format!(
+            "DT {:02X}    ST {:02X}",
+            chip_eight.delay_timer(),
+            chip_eight.sound_timer()
+        ),
+        String::new(),
+    ];
+    lines.extend((0..8).map(|x| format!("V{:X} {:02X}  V{:X} {:02X}", x, v[x], x + 8, v[x + 8])));
+    lines
+}
+
+// A few instructions before PC, and the rest after it. Instructions are two bytes, so the ones before are read back two
+// at a time, which is right unless there's data or an odd address in the way. The breakpoints are starred.
+fn code_lines(chip_eight: &ChipEight) -> Vec<String> {
+    let memory: &[u8] = chip_eight.memory();
+    let pc: usize = chip_eight.pc() as usize;
+    let (_, rows) = Panel::Code.text_size();
+    let first: usize = pc.saturating_sub(CODE_BEFORE * 2);
+
+    let mut lines: Vec<String> = vec!["CODE".to_string()];
+    lines.extend((0..rows - 1).map(|row| {
+        let addr: usize = (first + row * 2) % memory.len();
+        let opcode: u16 = u16::from_be_bytes([memory[addr], memory[(addr + 1) % memory.len()]]);
+        let mark: char = if addr == pc {
+            '>'
+        } else if chip_eight.breakpoints().contains(&(addr as u16)) {
+            '*'
+        } else {
+            ' '
+        };
+        format!(
+            "{} {:04X} {:04X} {}",
+            mark,
+            addr,
+            opcode,
+            disassemble_with_symbols(opcode, chip_eight.symbols())
+        )
+    }));
+    lines
+}
+
+// The rows of memory around I, which is usually what the program is drawing or loading.
+fn memory_lines(chip_eight: &ChipEight) -> Vec<String> {
+    let memory: &[u8] = chip_eight.memory();
+    let (_, rows) = Panel::Memory.text_size();
+    let i: usize = chip_eight.i_register() as usize % memory.len();
+    let first: usize = (i - i % MEMORY_COLUMNS).saturating_sub(MEMORY_COLUMNS * 2);
+
+    let mut lines: Vec<String> = vec!["MEMORY AT I".to_string()];
+    lines.extend(
+        (first..memory.len())
+            .step_by(MEMORY_COLUMNS)
+            .take(rows - 1)
+            .map(|row| {
+                let bytes: String = memory[row..(row + MEMORY_COLUMNS).min(memory.len())]
+                    .iter()
+                    .map(|byte| format!(" {:02X}", byte))
+                    .collect();
+                format!("{:04X}{}", row, bytes)
+            }),
+    );
+    lines
+}
+
+// Innermost first: where each call was made from, and where it went.
+fn stack_lines(chip_eight: &ChipEight) -> Vec<String> {
+    let calls = chip_eight.call_stack();
+    let mut lines: Vec<String> = vec![format!("STACK {}/16", calls.len())];
+    if calls.is_empty() {
+        lines.push("EMPTY".to_string());
+    }
+    lines.extend(
+        calls
+            .iter()
+            .map(|call| format!("{:04X} > {:04X}", call.call_site, call.opcode & 0x0FFF)),
+    );
+    lines
+}
+
+// A rectangle of logical pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Area {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Area {
+    // Where the text of a panel given this area starts.
+    pub fn text_origin(&self) -> (usize, usize) {
+        (self.x + MARGIN, self.y + MARGIN)
+    }
+}
+
+// Where everything goes: the game (the screen, with the overlay under it if there is one), each panel, and the lines
+// between them, all inside size, which the canvas's logical size is set to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Layout {
+    pub size: (usize, usize),
+    pub game: Area,
+    pub panels: Vec<Area>,
+    pub borders: Vec<Area>,
+}
+
+// Lays out the game, game logical pixels big, and panels of the given sizes, for a window of window real pixels (the
+// right way up). The panels go in columns beside the game, or side by side under it, whichever lets everything be drawn
+// bigger in the window; beside, if it's a tie. Panels are as tall (or in a row, the row as tall) as the tallest, so the
+// lines between them run the whole way.
+pub fn layout(window: (u32, u32), game: (usize, usize), panels: &[(usize, usize)]) -> Layout {
+    let (game_width, game_height) = game;
+    if panels.is_empty() {
+        return Layout {
+            size: game,
+            game: Area {
+                x: 0,
+                y: 0,
+                width: game_width,
+                height: game_height,
+            },
+            ..Layout::default()
+        };
+    }
+    let panels_width: usize = panels.iter().map(|(width, _)| width).sum();
+    let panels_height: usize = panels.iter().map(|(_, height)| *height).max().unwrap_or(0);
+    let beside: (usize, usize) = (
+        game_width + panels.len() + panels_width,
+        game_height.max(panels_height),
+    );
+    let below: (usize, usize) = (
+        game_width.max(panels_width + panels.len() - 1),
+        game_height + 1 + panels_height,
+    );
+    let scale = |(width, height): (usize, usize)| -> f64 {
+        (window.0 as f64 / width as f64).min(window.1 as f64 / height as f64)
+    };
+
+    let mut layout: Layout = Layout::default();
+    if scale(beside) >= scale(below) {
+        let height: usize = beside.1;
+        layout.size = beside;
+        layout.game = Area {
+            x: 0,
+            y: 0,
+            width: game_width,
+            height: game_height,
+        };
+        let mut x: usize = game_width;
+        for &(width, _) in panels {
+            layout.borders.push(Area {
+                x,
+                y: 0,
+                width: 1,
+                height,
+            });
+            layout.panels.push(Area {
+                x: x + 1,
+                y: 0,
+                width,
+                height,
+            });
+            x += 1 + width;
+        }
+    } else {
+        let (width, height) = (below.0, panels_height);
+        let y: usize = game_height + 1;
+        layout.size = below;
+        layout.game = Area {
+            x: (width - game_width) / 2,
+            y: 0,
+            width: game_width,
+            height: game_height,
+        };
+        layout.borders.push(Area {
+            x: 0,
+            y: game_height,
+            width,
+            height: 1,
+        });
+        let mut x: usize = 0;
+        for (i, &(panel_width, _)) in panels.iter().enumerate() {
+            if i > 0 {
+                layout.borders.push(Area {
+                    x,
+                    y,
+                    width: 1,
+                    height,
+                });
+                x += 1;
+            }
+            layout.panels.push(Area {
+                x,
+                y,
+                width: panel_width,
+                height,
+            });
+            x += panel_width;
+        }
+    }
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(x: usize, y: usize, width: usize, height: usize) -> Area {
+        Area {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn no_panels_leave_the_game_as_it_is() {
+        let layout: Layout = layout((1280, 640), (64, 40), &[]);
+        assert_eq!(layout.size, (64, 40));
+        assert_eq!(layout.game, area(0, 0, 64, 40));
+        assert!(layout.panels.is_empty() && layout.borders.is_empty());
+    }
+
+    #[test]
+    fn a_wide_window_puts_the_panels_beside_the_game() {
+        let layout: Layout = layout((950, 500), (64, 40), &[(30, 50)]);
+        assert_eq!(layout.size, (95, 50));
+        assert_eq!(layout.game, area(0, 0, 64, 40));
+        assert_eq!(layout.borders, vec![area(64, 0, 1, 50)]);
+        assert_eq!(layout.panels, vec![area(65, 0, 30, 50)]);
+    }
+
+    #[test]
+    fn a_tall_window_puts_the_panels_under_the_game() {
+        let layout: Layout = layout((710, 910), (64, 40), &[(30, 50), (40, 20)]);
+        // The row of panels is wider than the game, which is centered over it.
+        assert_eq!(layout.size, (71, 91));
+        assert_eq!(layout.game, area(3, 0, 64, 40));
+        assert_eq!(
+            layout.borders,
+            vec![area(0, 40, 71, 1), area(30, 41, 1, 50)]
+        );
+        assert_eq!(
+            layout.panels,
+            vec![area(0, 41, 30, 50), area(31, 41, 40, 50)]
+        );
+    }
+
+    #[test]
+    fn a_tie_goes_beside() {
+        // Both ways draw everything 10 times bigger.
+        let layout: Layout = layout((950, 910), (64, 40), &[(30, 50)]);
+        assert_eq!(layout.size, (95, 50));
+    }
+
+    #[test]
+    fn panel_text_fits_its_panel() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        chip_eight
+            .load_program_from_slice(&[0x22, 0x04, 0x12, 0x02, 0xA2, 0x00, 0x12, 0x06])
+            .unwrap();
+        for _ in 0..3 {
+            chip_eight.step().unwrap();
+        }
+
+        for panel in [Panel::Registers, Panel::Code, Panel::Memory, Panel::Stack] {
+            let (columns, rows) = panel.text_size();
+            assert_eq!(
+                panel.size(),
+                (
+                    columns * CHAR_ADVANCE - 1 + MARGIN * 2,
+                    rows * LINE_HEIGHT - 1 + MARGIN * 2
+                )
+            );
+            let lines: Vec<String> = panel.lines(&chip_eight);
+            assert!(lines.len() <= rows, "{:?} has {} lines", panel, lines.len());
+            assert!(
+                lines.iter().all(|line| line.chars().count() <= columns),
+                "{:?}",
+                lines
+            );
+        }
+        assert_eq!(
+            Panel::Stack.lines(&chip_eight),
+            ["STACK 1/16", "0200 > 0204"]
+        );
+        // PC is on the fifth instruction, after the four before it.
+        assert_eq!(
+            Panel::Code.lines(&chip_eight)[4..6],
+            ["  0204 A200 LD I, 0x200", "> 0206 1206 JP 0x206"]
+        );
+    }
+}
@@ -0,0 +1,618 @@
+// A line-based debugger protocol, for driving the debugger from a terminal, a socket, or a script. Every command gets
+// back its output followed by a line saying "ok" or "error: ...". Whenever the machine stops, the client is told with
+// an event line starting with *, e.g. "*stopped pc=0x22A reason=breakpoint".
+use crate::backend::STEP_OVER_LIMIT;
+use crate::{
+    disassemble_rom_with_symbols, disassemble_with_symbols, render_sprite, render_sprite_16,
+    ChipEight,
+};
+use std::fmt::Write as _;
+use std::io::{ErrorKind, Read, Write};
+
+const HELP: &str = "\
+regs                    show the registers
+bt                      show the subroutine calls the PC is inside of
+break, b ADDR           pause when the PC reaches ADDR
+delete ADDR             remove the breakpoint at ADDR
+breakpoints             list the breakpoints
+watch read|write RANGE  pause after an access to ADDR or ADDR-ADDR
+unwatch                 remove every watchpoint
+pause                   pause before the next instruction
+continue, c             carry on running
+step, s                 run one instruction
+next, n                 run one instruction, stepping over calls
+x/N ADDR                dump N bytes of memory (16 by default)
+dis ADDR [N]            disassemble N instructions (8 by default)
+sprites ADDR [H] [N]    draw N sprites H rows tall (1 and 5 by default, 16 for 16x16)
+ADDR is hex, or the name of a symbol if the ROM came with a symbol file.";
+
+// Runs one command against the machine and returns what it printed. Commands that start the machine running return
+// straight away; the machine stopping again is reported by DebugSession.
+pub fn run_debug_command(chip_eight: &mut ChipEight, line: &str) -> Result<String, String> {
+    let mut words = line.split_whitespace();
+    let command: &str = words.next().unwrap_or("");
+    let args: Vec<&str> = words.collect();
+    let address = |chip_eight: &ChipEight, i: usize| -> Result<u16, String> {
+        let arg: &str = args.get(i).ok_or("missing address")?;
+        resolve_address(chip_eight, arg).ok_or_else(|| format!("bad address {}", arg))
+    };
+
+    match command {
+        "help" => Ok(HELP.to_string()),
+        "regs" => Ok(registers(chip_eight)),
+        "bt" => Ok(backtrace(chip_eight)),
+        "break" | "b" => {
+            chip_eight.add_breakpoint(address(chip_eight, 0)?);
+            Ok(String::new())
+        }
+        "delete" => {
+            chip_eight.remove_breakpoint(address(chip_eight, 0)?);
+            Ok(String::new())
+        }
+        "breakpoints" => Ok(chip_eight
+            .breakpoints()
+            .iter()
+            .map(|addr| format!("{:#05X}", addr))
+            .collect::<Vec<String>>()
+            .join("\n")),
+        "watch" => {
+            let range: &str = args.get(1).ok_or("missing address")?;
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            let (start, end) = match (
+                resolve_address(chip_eight, start),
+                resolve_address(chip_eight, end),
+            ) {
+                (Some(start), Some(end)) if start <= end => (start, end),
+                _ => return Err(format!("bad address range {}", range)),
+            };
+            match args.first().copied() {
+                Some("read") => chip_eight.watch_read(start..=end),
+                Some("write") => chip_eight.watch_write(start..=end),
+                _ => return Err("watch needs read or write".to_string()),
+            }
+            Ok(String::new())
+        }
+        "unwatch" => {
+            chip_eight.clear_watchpoints();
+            Ok(String::new())
+        }
+        "pause" => {
+            chip_eight.pause();
+            Ok(String::new())
+        }
+        "continue" | "c" => {
+            chip_eight.resume();
+            Ok(String::new())
+        }
+        // Run here rather than on the next frame, so the stop (and anything asked for after it) sees the instruction done.
+        "step" | "s" => {
+            chip_eight.single_step();
+            chip_eight.step().map_err(|e| e.to_string())?;
+            Ok(String::new())
+        }
+        "next" | "n" => {
+            chip_eight
+                .step_over(STEP_OVER_LIMIT)
+                .map_err(|e| e.to_string())?;
+            Ok(String::new())
+        }
+        "dis" => {
+            let addr: u16 = address(chip_eight, 0)?;
+            let count: usize = match args.get(1) {
+                Some(count) => count.parse().map_err(|_| format!("bad count {}", count))?,
+                None => 8,
+            };
+            let memory: &[u8] = chip_eight.memory();
+            let start: usize = (addr as usize).min(memory.len());
+            let end: usize = start
+                .saturating_add(count.saturating_mul(4))
+                .min(memory.len());
+            let symbols = chip_eight.symbols();
+            Ok(
+                disassemble_rom_with_symbols(&memory[start..end], addr, symbols)
+                    .into_iter()
+                    .take(count)
+                    .map(|(addr, opcode, mnemonic)| match symbols.name(addr) {
+                        Some(name) => {
+                            format!("{}:\n{:03X}: {:04X}  {}", name, addr, opcode, mnemonic)
+                        }
+                        None => format!("{:03X}: {:04X}  {}", addr, opcode, mnemonic),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            )
+        }
+        "sprites" => {
+            let addr: u16 = address(chip_eight, 0)?;
+            let number = |i: usize, default: usize, max: usize| -> Result<usize, String> {
+                match args.get(i) {
+                    Some(arg) => match arg.parse() {
+                        Ok(n) if (1..=max).contains(&n) => Ok(n),
+                        _ => Err(format!("bad number {}, it has to be 1 to {}", arg, max)),
+                    },
+                    None => Ok(default),
+                }
+            };
+            let height: usize = number(1, 5, 16)?;
+            let count: usize = number(2, 1, 64)?;
+            Ok(sprites(chip_eight.memory(), addr as usize, height, count))
+        }
+        // x/N ADDR, gdb style.
+        x if x == "x" || x.starts_with("x/") => {
+            let len: usize = match x.strip_prefix("x/") {
+                Some(len) => len.parse().map_err(|_| format!("bad length {}", len))?,
+                None => 16,
+            };
+            let addr: usize = address(chip_eight, 0)? as usize;
+            Ok(chip_eight
+                .dump_memory(addr..addr.saturating_add(len))
+                .trim_end()
+                .to_string())
+        }
+        "" => Err("empty command".to_string()),
+        _ => Err(format!("unknown command {}, try help", command)),
+    }
+}
+
+// A symbol's name, or else a hex address, with or without the 0x. Symbols come first, since a name like "add" is both.
+fn resolve_address(chip_eight: &ChipEight, s: &str) -> Option<u16> {
+    let digits: &str = s.trim().trim_start_matches("0x").trim_start_matches("0X");
+    chip_eight
+        .symbols()
+        .address(s)
+        .or_else(|| u16::from_str_radix(digits, 16).ok())
+}
+
+// The current instruction, then every call on the stack from the innermost out, e.g.
+// #0  20C: 7101  ADD V1, 0x01
+// #1  208: 220C  CALL 0x20C  (returns to 20A)
+fn backtrace(chip_eight: &ChipEight) -> String {
+    let pc: u16 = chip_eight.pc();
+    let opcode: u16 = chip_eight.read_word(pc);
+    let disassemble =
+        |opcode: u16| -> String { disassemble_with_symbols(opcode, chip_eight.symbols()) };
+    let mut backtrace: String = format!("#0  {:03X}: {:04X}  {}", pc, opcode, disassemble(opcode));
+    for (depth, frame) in chip_eight.call_stack().iter().enumerate() {
+        let _ = write!(
+            backtrace,
+            "\n#{}  {:03X}: {:04X}  {}  (returns to {:03X})",
+            depth + 1,
+            frame.call_site,
+            frame.opcode,
+            disassemble(frame.opcode),
+            frame.return_address
+        );
+    }
+    backtrace
+}
+
+// Sprites drawn with # and ., each under its address. A height of 16 means SUPER-CHIP's 16x16 sprites.
+fn sprites(memory: &[u8], addr: usize, height: usize, count: usize) -> String {
+    let size: usize = if height == 16 { 32 } else { height };
+    let draw =
+        |row: &[bool]| -> String { row.iter().map(|&lit| if lit { '#' } else { '.' }).collect() };
+
+    (0..count)
+        .map(|n| {
+            let addr: usize = (addr + n * size) % memory.len();
+            let rows: Vec<String> = if height == 16 {
+                render_sprite_16(memory, addr)
+                    .iter()
+                    .map(|row| draw(row))
+                    .collect()
+            } else {
+                render_sprite(memory, addr, height)
+                    .iter()
+                    .map(|row| draw(row))
+                    .collect()
+            };
+            format!("{:03X}:\n{}", addr, rows.join("\n"))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn registers(chip_eight: &ChipEight) -> String {
+    let mut registers: String = format!(
+        "pc={:#05X} i={:#05X} sp={} dt={} st={}",
+        chip_eight.pc(),
+        chip_eight.i_register(),
+        chip_eight.stack().len(),
+        chip_eight.delay_timer(),
+        chip_eight.sound_timer()
+    );
+    for (x, v) in chip_eight.v_registers().iter().enumerate() {
+        let _ = write!(registers, " v{:x}={:#04X}", x, v);
+    }
+    registers
+}
+
+// One client's connection. The stream should be non-blocking (reads returning ErrorKind::WouldBlock when there's
+// nothing to read), so polling it never holds up emulation.
+pub struct DebugSession<S> {
+    stream: S,
+    // Bytes read that don't make up a whole line yet.
+    incoming: Vec<u8>,
+    // Bytes the stream wasn't ready to take yet.
+    outgoing: Vec<u8>,
+    // Where the client was last told the machine stopped, so each stop is only reported once.
+    reported: Option<u16>,
+    // Why the machine is going to stop next, if a command asked it to.
+    expected_stop: Option<&'static str>,
+    closed: bool,
+}
+
+impl<S: Read + Write> DebugSession<S> {
+    pub fn new(stream: S) -> Self {
+        DebugSession {
+            stream,
+            incoming: vec![],
+            outgoing: vec![],
+            reported: None,
+            expected_stop: None,
+            closed: false,
+        }
+    }
+    // Runs any commands the client has sent, tells it if the machine has stopped since the last poll, and sends off as
+    // much output as the stream will take. Returns false once the client has gone away.
+    pub fn poll(&mut self, chip_eight: &mut ChipEight) -> bool {
+        self.report_stop(chip_eight);
+        self.read_commands(chip_eight);
+        self.flush();
+        !self.closed
+    }
+    // Queues a line to send to the client.
+    pub fn send(&mut self, line: &str) {
+        self.outgoing.extend_from_slice(line.as_bytes());
+        self.outgoing.push(b'\n');
+    }
+    fn read_commands(&mut self, chip_eight: &mut ChipEight) {
+        let mut buffer: [u8; 512] = [0; 512];
+
+        while !self.closed {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => self.closed = true,
+                Ok(n) => self.incoming.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => self.closed = true,
+            }
+        }
+        while let Some(end) = self.incoming.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.incoming.drain(..=end).collect();
+            let line: String = String::from_utf8_lossy(&line).trim().to_string();
+            if line.is_empty() {
+                continue;
+            }
+            self.run(chip_eight, &line);
+        }
+    }
+    fn run(&mut self, chip_eight: &mut ChipEight, line: &str) {
+        let command: &str = line.split_whitespace().next().unwrap_or("");
+        let result: Result<String, String> = run_debug_command(chip_eight, line);
+
+        if result.is_ok() {
+            match command {
+                "continue" | "c" => self.reported = None,
+                "step" | "s" | "next" | "n" => {
+                    self.reported = None;
+                    self.expected_stop = Some("step");
+                }
+                "pause" if chip_eight.paused_at().is_none() => self.expected_stop = Some("pause"),
+                _ => {}
+            }
+        }
+        match result {
+            Ok(output) => {
+                if !output.is_empty() {
+                    self.send(&output);
+                }
+                self.send("ok");
+            }
+            Err(e) => self.send(&format!("error: {}", e)),
+        }
+        // Stepping over a call stops again before the command returns, and the next command might start the machine
+        // running again, so stops are checked for after every command.
+        self.report_stop(chip_eight);
+    }
+    fn report_stop(&mut self, chip_eight: &ChipEight) {
+        let pc: u16 = match chip_eight.paused_at() {
+            Some(pc) => pc,
+            None => {
+                self.reported = None;
+                return;
+            }
+        };
+        if self.reported == Some(pc) {
+            return;
+        }
+        let reason: &str = if chip_eight.watch_hit().is_some() {
+            "watchpoint"
+        } else if let Some(reason) = self.expected_stop {
+            reason
+        } else if chip_eight.breakpoints().contains(&pc) {
+            "breakpoint"
+        } else {
+            "pause"
+        };
+        let mut event: String = format!("*stopped pc={:#05X} reason={}", pc, reason);
+        if let Some(hit) = chip_eight.watch_hit() {
+            let _ = write!(event, " {}", hit);
+        }
+        self.send(&event);
+        self.reported = Some(pc);
+        self.expected_stop = None;
+    }
+    fn flush(&mut self) {
+        while !self.outgoing.is_empty() && !self.closed {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => self.closed = true,
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => self.closed = true,
+            }
+        }
+        let _ = self.stream.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::io;
+
+    // An in-memory stand-in for a non-blocking socket: reads take what the client has sent so far, and writes collect what
+    // it's been sent back.
+    #[derive(Default)]
+    struct Duplex {
+        from_client: VecDeque<u8>,
+        to_client: Vec<u8>,
+        hung_up: bool,
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            if self.from_client.is_empty() {
+                return match self.hung_up {
+                    true => Ok(0),
+                    false => Err(ErrorKind::WouldBlock.into()),
+                };
+            }
+            self.from_client.read(buffer)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.to_client.write(bytes)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Adds 1 to V0 at 0x200 and jumps back to it at 0x202, forever.
+    fn machine() -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight
+            .write_memory(0x200, &[0x70, 0x01, 0x12, 0x00])
+            .unwrap();
+        chip_eight
+    }
+
+    // Sends text as the client, polls, and returns the lines the client got back.
+    fn exchange(
+        session: &mut DebugSession<Duplex>,
+        chip_eight: &mut ChipEight,
+        text: &str,
+    ) -> Vec<String> {
+        session.stream.from_client.extend(text.as_bytes());
+        assert!(session.poll(chip_eight));
+        let reply: String =
+            String::from_utf8(std::mem::take(&mut session.stream.to_client)).unwrap();
+        reply.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn commands_get_their_output_then_ok_or_an_error() {
+        let mut chip_eight: ChipEight = machine();
+        let mut session: DebugSession<Duplex> = DebugSession::new(Duplex::default());
+        assert_eq!(
+            exchange(
+                &mut session,
+                &mut chip_eight,
+                "b 202\nbreak 0x300\nbreakpoints\nfrobnicate\nb\n"
+            ),
+            [
+                "ok",
+                "ok",
+                "0x202",
+                "0x300",
+                "ok",
+                "error: unknown command frobnicate, try help",
+                "error: missing address"
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_lines_wait_for_the_rest() {
+        let mut chip_eight: ChipEight = machine();
+        let mut session: DebugSession<Duplex> = DebugSession::new(Duplex::default());
+        assert!(exchange(&mut session, &mut chip_eight, "x/2 2").is_empty());
+        let reply: Vec<String> = exchange(&mut session, &mut chip_eight, "00\n\n");
+        assert_eq!(reply.len(), 2);
+        assert!(reply[0].starts_with("0200  70 01 "), "{}", reply[0]);
+        assert_eq!(reply[1], "ok");
+    }
+
+    #[test]
+    fn stops_are_reported_once_each() {
+        let mut chip_eight: ChipEight = machine();
+        let mut session: DebugSession<Duplex> = DebugSession::new(Duplex::default());
+        exchange(&mut session, &mut chip_eight, "b 202\n");
+        chip_eight.step().unwrap();
+        chip_eight.step().unwrap();
+        assert_eq!(
+            exchange(&mut session, &mut chip_eight, ""),
+            ["*stopped pc=0x202 reason=breakpoint"]
+        );
+        assert!(exchange(&mut session, &mut chip_eight, "").is_empty());
+        // Carrying on and coming back round is a new stop.
+        assert_eq!(exchange(&mut session, &mut chip_eight, "c\n"), ["ok"]);
+        for _ in 0..3 {
+            chip_eight.step().unwrap();
+        }
+        assert_eq!(
+            exchange(&mut session, &mut chip_eight, "regs\n")[0],
+            "*stopped pc=0x202 reason=breakpoint"
+        );
+    }
+
+    #[test]
+    fn watchpoint_stops_say_what_was_touched() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight
+            .write_memory(0x200, &[0xA3, 0x00, 0xF0, 0x55])
+            .unwrap();
+        let mut session: DebugSession<Duplex> = DebugSession::new(Duplex::default());
+        assert_eq!(
+            exchange(
+                &mut session,
+                &mut chip_eight,
+                "watch write 300-30f\nwatch write 30f-300\n"
+            ),
+            ["ok", "error: bad address range 30f-300"]
+        );
+        chip_eight.step().unwrap();
+        chip_eight.step().unwrap();
+        assert_eq!(
+            exchange(&mut session, &mut chip_eight, ""),
+            ["*stopped pc=0x204 reason=watchpoint F055 at 0x202 wrote 0x00 to 0x300 (was 0x00)"]
+        );
+    }
+
+    // Calls a subroutine at 0x206 that sets I, loads V1 and returns.
+    fn calling() -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight
+            .write_memory(
+                0x200,
+                &[
+                    0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0xA2, 0x0C, 0x61, 0x2A, 0x00, 0xEE, 0xF0,
+                    0x90,
+                ],
+            )
+            .unwrap();
+        chip_eight
+    }
+
+    #[test]
+    fn step_runs_the_instruction_before_replying() {
+        let mut chip_eight: ChipEight = calling();
+        let mut session: DebugSession<Duplex> = DebugSession::new(Duplex::default());
+        let reply: Vec<String> = exchange(
+            &mut session,
+            &mut chip_eight,
+            "s
+s
+regs
+",
+        );
+        assert_eq!(
+            reply[..4],
+            [
+                "ok",
+                "*stopped pc=0x206 reason=step",
+                "ok",
+                "*stopped pc=0x208 reason=step"
+            ]
+        );
+        assert!(
+            reply[4].starts_with("pc=0x208 i=0x20C sp=1 "),
+            "{}",
+            reply[4]
+        );
+        assert_eq!(reply[5], "ok");
+        assert_eq!(chip_eight.paused_at(), Some(0x208));
+    }
+
+    #[test]
+    fn next_steps_over_calls() {
+        let mut chip_eight: ChipEight = calling();
+        let mut session: DebugSession<Duplex> = DebugSession::new(Duplex::default());
+        assert_eq!(
+            exchange(
+                &mut session,
+                &mut chip_eight,
+                "n
+"
+            ),
+            ["ok", "*stopped pc=0x202 reason=step"]
+        );
+        assert_eq!(chip_eight.v_registers()[1], 0x2A);
+    }
+
+    #[test]
+    fn inspecting_commands_show_the_machine() {
+        let mut chip_eight: ChipEight = calling();
+        chip_eight.set_v(0xA, 0x5F);
+        for _ in 0..2 {
+            chip_eight.step().unwrap();
+        }
+        for (command, expected) in [
+            (
+                "regs",
+                "pc=0x208 i=0x20C sp=1 dt=0 st=0 v0=0x00 v1=0x00 v2=0x00 v3=0x00 v4=0x00 v5=0x00 v6=0x00 v7=0x00 \
+                 v8=0x00 v9=0x00 va=0x5F vb=0x00 vc=0x00 vd=0x00 ve=0x00 vf=0x00",
+            ),
+            (
+                "bt",
+                "#0  208: 612A  LD V1, 0x2A\n#1  200: 2206  CALL 0x206  (returns to 202)",
+            ),
+            ("dis 206 2", "206: A20C  LD I, 0x20C\n208: 612A  LD V1, 0x2A"),
+            ("sprites 20c 2", "20C:\n####....\n#..#...."),
+        ] {
+            assert_eq!(run_debug_command(&mut chip_eight, command).as_deref(), Ok(expected), "{}", command);
+        }
+        let dump: String = run_debug_command(&mut chip_eight, "x/4 0x206").unwrap();
+        assert!(dump.starts_with("0206  A2 0C 61 2A"), "{}", dump);
+    }
+
+    #[test]
+    fn bad_arguments_are_errors() {
+        let mut chip_eight: ChipEight = calling();
+        for (command, error) in [
+            ("", "empty command"),
+            ("dis zz", "bad address zz"),
+            ("dis 200 lots", "bad count lots"),
+            ("x/big 200", "bad length big"),
+            ("sprites 200 17", "bad number 17, it has to be 1 to 16"),
+            ("sprites 200 5 0", "bad number 0, it has to be 1 to 64"),
+            ("watch peek 200", "watch needs read or write"),
+            ("watch read", "missing address"),
+        ] {
+            assert_eq!(
+                run_debug_command(&mut chip_eight, command),
+                Err(error.to_string()),
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn a_hang_up_ends_the_session() {
+        let mut chip_eight: ChipEight = machine();
+        let mut session: DebugSession<Duplex> = DebugSession::new(Duplex::default());
+        session.stream.from_client.extend(b"b 202\n");
+        session.stream.hung_up = true;
+        assert!(!session.poll(&mut chip_eight));
+        // Whatever was sent before hanging up still ran.
+        assert_eq!(chip_eight.breakpoints(), [0x202]);
+    }
+}
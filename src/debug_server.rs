@@ -0,0 +1,58 @@
+// Serving the debugger protocol over TCP, so an external tool (or netcat) can drive the emulator. The server wraps the
+// frontend's input backend and gets polled along with it once a frame, so it needs no threads of its own.
+use crate::backend::Input;
+use crate::debug_protocol::DebugSession;
+use crate::ChipEight;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::ControlFlow;
+
+pub struct DebugServer<I> {
+    listener: TcpListener,
+    // Only one client at a time. Anyone else who connects is told so and disconnected.
+    session: Option<DebugSession<TcpStream>>,
+    input: I,
+}
+
+impl<I: Input> DebugServer<I> {
+    // Starts listening on addr. Input is polled after the client's commands have run.
+    pub fn bind(addr: impl ToSocketAddrs, input: I) -> io::Result<Self> {
+        let listener: TcpListener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(DebugServer {
+            listener,
+            session: None,
+            input,
+        })
+    }
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+    // Problems with the connection only ever end the connection. Emulation carries on regardless.
+    fn accept(&mut self) {
+        while let Ok((mut stream, _)) = self.listener.accept() {
+            if self.session.is_some() {
+                let _ = stream.write_all(b"error: another client is already connected\n");
+                continue;
+            }
+            if stream.set_nonblocking(true).is_err() {
+                continue;
+            }
+            let mut session = DebugSession::new(stream);
+            session.send("PotatOcho debugger ready, try help");
+            self.session = Some(session);
+        }
+    }
+}
+
+impl<I: Input> Input for DebugServer<I> {
+    fn poll(&mut self, chip_eight: &mut ChipEight) -> ControlFlow<()> {
+        self.accept();
+        if let Some(session) = &mut self.session {
+            if !session.poll(chip_eight) {
+                self.session = None;
+            }
+        }
+        self.input.poll(chip_eight)
+    }
+}
@@ -0,0 +1,439 @@
+// PC breakpoints, memory watchpoints, and single-stepping. A machine that hits a breakpoint pauses before executing the
+// instruction there, and stays paused (steps and frames don't execute anything) until it's resumed or stepped.
+use crate::{ChipEight, ChipEightError, StepOutcome, Symbols};
+use std::fmt;
+use std::ops::{Bound, RangeBounds, RangeInclusive};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+}
+
+// An instruction touched a watched address. For reads, old and new are both the value that was read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    pub kind: WatchKind,
+    // Where the instruction that made the access is.
+    pub pc: u16,
+    pub opcode: u16,
+    pub addr: u16,
+    pub old: u8,
+    pub new: u8,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            WatchKind::Read => write!(
+                f,
+                "{:04X} at {:#05X} read {:#04X} from {:#05X}",
+                self.opcode, self.pc, self.new, self.addr
+            ),
+            WatchKind::Write => write!(
+                f,
+                "{:04X} at {:#05X} wrote {:#04X} to {:#05X} (was {:#04X})",
+                self.opcode, self.pc, self.new, self.addr, self.old
+            ),
+        }
+    }
+}
+
+// One subroutine call on the stack. The stack holds the address of each 2nnn (the PC is pushed before it moves past
+// the call), and returning carries on at the instruction after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CallFrame {
+    // Where the 2nnn that made the call is.
+    pub call_site: u16,
+    pub opcode: u16,
+    // Where execution carries on once the subroutine returns.
+    pub return_address: u16,
+}
+
+impl CallFrame {
+    // The call that will return to return_address.
+    pub fn from_return_address(chip_eight: &ChipEight, return_address: u16) -> Self {
+        Self::from_call_site(chip_eight, return_address.wrapping_sub(2))
+    }
+    fn from_call_site(chip_eight: &ChipEight, call_site: u16) -> Self {
+        CallFrame {
+            call_site,
+            opcode: chip_eight.read_word(call_site),
+            return_address: call_site.wrapping_add(2),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Debugger {
+    // Sorted, with no duplicates.
+    breakpoints: Vec<u16>,
+    // Where the machine is paused, if it is.
+    paused_at: Option<u16>,
+    // The instruction at this address runs without checking for a breakpoint, so resuming from a breakpoint doesn't hit
+    // it again straight away.
+    skip_at: Option<u16>,
+    // Pause before the next instruction, wherever it is (see pause()).
+    break_next: bool,
+    // Pause as soon as the current instruction finishes. This is how single_step() gets back into the paused state.
+    pause_after: bool,
+    watchpoints: Vec<(WatchKind, RangeInclusive<u16>)>,
+    // The (pc, opcode) of the instruction step() is executing. Bus accesses from outside of an instruction (a frontend
+    // peeking at memory, say) don't trigger watchpoints.
+    executing: Option<(u16, u16)>,
+    // The first watchpoint the current instruction hit, and then the one the machine is paused for.
+    watch_hit: Option<WatchHit>,
+    // Names for addresses, for the debugger to show and accept in place of hex.
+    symbols: Symbols,
+}
+
+impl ChipEight {
+    // Pauses the machine whenever pc reaches addr, before the instruction there executes.
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        if let Err(index) = self.debugger.breakpoints.binary_search(&addr) {
+            self.debugger.breakpoints.insert(index, addr);
+        }
+    }
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        if let Ok(index) = self.debugger.breakpoints.binary_search(&addr) {
+            self.debugger.breakpoints.remove(index);
+        }
+    }
+    pub fn clear_breakpoints(&mut self) {
+        self.debugger.breakpoints.clear();
+    }
+    // In ascending order.
+    pub fn breakpoints(&self) -> &[u16] {
+        &self.debugger.breakpoints
+    }
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.debugger.symbols = symbols;
+    }
+    pub fn symbols(&self) -> &Symbols {
+        &self.debugger.symbols
+    }
+    // Pauses the machine after any instruction that reads from addresses in range, via Fx65, Dxyn, 5xy3, or the bus.
+    pub fn watch_read(&mut self, range: impl RangeBounds<u16>) {
+        self.debugger
+            .watchpoints
+            .push((WatchKind::Read, inclusive(range)));
+    }
+    // Pauses the machine after any instruction that writes to addresses in range, via Fx33, Fx55, 5xy2, or the bus.
+    pub fn watch_write(&mut self, range: impl RangeBounds<u16>) {
+        self.debugger
+            .watchpoints
+            .push((WatchKind::Write, inclusive(range)));
+    }
+    pub fn clear_watchpoints(&mut self) {
+        self.debugger.watchpoints.clear();
+    }
+    // The subroutine calls the machine is inside of, innermost first.
+    pub fn call_stack(&self) -> Vec<CallFrame> {
+        self.stack()
+            .iter()
+            .rev()
+            .map(|&call_site| CallFrame::from_call_site(self, call_site))
+            .collect()
+    }
+    // The watchpoint hit the machine is paused for, if that's why it's paused.
+    pub fn watch_hit(&self) -> Option<WatchHit> {
+        self.debugger.paused_at.and(self.debugger.watch_hit)
+    }
+    // The address the machine is paused at, after hitting a breakpoint or single-stepping. None while it's running.
+    pub fn paused_at(&self) -> Option<u16> {
+        self.debugger.paused_at
+    }
+    // Carries on from a breakpoint. The instruction the machine is paused at runs even if there's a breakpoint on it.
+    pub fn resume(&mut self) {
+        if let Some(addr) = self.debugger.paused_at.take() {
+            self.debugger.skip_at = Some(addr);
+        }
+        self.debugger.watch_hit = None;
+    }
+    // Pauses the machine before the next instruction, as if there were a breakpoint there.
+    pub fn pause(&mut self) {
+        self.debugger.break_next = true;
+    }
+    // Lets exactly one more instruction execute, then pauses again at whatever comes next. This works on a running machine
+    // too, to stop it without a breakpoint.
+    pub fn single_step(&mut self) {
+        self.unpause();
+        self.debugger.pause_after = true;
+    }
+    // Like single_step, except that a 2nnn call runs to completion: execution carries on until the subroutine returns to
+    // the instruction after the call. That can take forever (the subroutine might never return), so it gives up after
+    // max_instructions. Breakpoints and watchpoints inside the subroutine still pause it. Either way the machine ends up
+    // paused, and the outcome of the last instruction executed is returned.
+    pub fn step_over(&mut self, max_instructions: u32) -> Result<StepOutcome, ChipEightError> {
+        let is_call: bool = self.read_word(self.pc) >> 12 == 0x2;
+        let return_address: u16 = self.pc.wrapping_add(2);
+        let depth: u8 = self.sp;
+        let mut outcome: StepOutcome = StepOutcome::default();
+
+        self.unpause();
+        for _ in 0..max_instructions {
+            outcome = self.step()?;
+            let stopped: bool = outcome.paused
+                || outcome.watchpoint.is_some()
+                || outcome.waiting_for_key
+                || outcome.halted;
+            let returned: bool = self.pc == return_address && self.sp == depth;
+            if stopped || !is_call || returned {
+                break;
+            }
+        }
+        if self.debugger.paused_at.is_none() {
+            self.debugger.paused_at = Some(self.pc);
+        }
+        Ok(outcome)
+    }
+    // Whether the machine is going to pause again after the instruction it's about to run.
+    pub(crate) fn single_stepping(&self) -> bool {
+        self.debugger.pause_after
+    }
+    // Pauses the machine where it is, e.g. after stepping back.
+    pub(crate) fn pause_at_pc(&mut self) {
+        self.debugger.paused_at = Some(self.pc);
+    }
+    // Lets the instruction at pc run, even if it has a breakpoint on it.
+    fn unpause(&mut self) {
+        self.debugger.skip_at = Some(self.debugger.paused_at.take().unwrap_or(self.pc));
+        self.debugger.break_next = false;
+        self.debugger.pause_after = false;
+        self.debugger.watch_hit = None;
+    }
+    // Forgets that the machine was paused, e.g. because it was reset. Breakpoints are kept.
+    pub(crate) fn clear_pause(&mut self) {
+        self.debugger.paused_at = None;
+        self.debugger.skip_at = None;
+        self.debugger.break_next = false;
+        self.debugger.pause_after = false;
+        self.debugger.watch_hit = None;
+    }
+    // Called by step() before each fetch. Returns the outcome to report instead of executing, if the machine is paused.
+    pub(crate) fn check_breakpoint(&mut self) -> Option<StepOutcome> {
+        let debugger: &mut Debugger = &mut self.debugger;
+
+        if debugger.paused_at.is_none() && debugger.skip_at.take() != Some(self.pc) {
+            let hit: bool = debugger.breakpoints.binary_search(&self.pc).is_ok();
+            if hit || debugger.break_next {
+                debugger.paused_at = Some(self.pc);
+                debugger.break_next = false;
+            }
+        }
+        debugger.paused_at.map(|addr| StepOutcome {
+            paused: true,
+            breakpoint: Some(addr),
+            ..StepOutcome::default()
+        })
+    }
+    // step() brackets each instruction with these, so watchpoints know which instruction made an access. end_instruction
+    // returns the first watchpoint the instruction hit. After a hit or a single_step(), the machine is now paused before
+    // the next instruction.
+    pub(crate) fn begin_instruction(&mut self, opcode: u16) {
+        self.debugger.executing = Some((self.pc, opcode));
+    }
+    pub(crate) fn end_instruction(&mut self) -> Option<WatchHit> {
+        self.debugger.executing = None;
+        let hit: Option<WatchHit> = self.debugger.watch_hit;
+        if hit.is_some() || std::mem::take(&mut self.debugger.pause_after) {
+            self.debugger.paused_at = Some(self.pc);
+        }
+        hit
+    }
+    // Called by the bus for every access.
+    pub(crate) fn check_watchpoint(&mut self, kind: WatchKind, addr: u16, old: u8, new: u8) {
+        let debugger: &mut Debugger = &mut self.debugger;
+        let (pc, opcode) = match debugger.executing {
+            Some(executing) if debugger.watch_hit.is_none() => executing,
+            _ => return,
+        };
+        let watched: bool = debugger
+            .watchpoints
+            .iter()
+            .any(|(watch_kind, range)| *watch_kind == kind && range.contains(&addr));
+        if watched {
+            debugger.watch_hit = Some(WatchHit {
+                kind,
+                pc,
+                opcode,
+                addr,
+                old,
+                new,
+            });
+        }
+    }
+}
+
+fn inclusive(range: impl RangeBounds<u16>) -> RangeInclusive<u16> {
+    let start: u16 = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    // An empty range like 0x300..0x300 ends up as 0x300..=0x2FF, which contains nothing, as it should.
+    let end: u16 = match range.end_bound() {
+        Bound::Included(&end) => end,
+        Bound::Excluded(&0) => return RangeInclusive::new(1, 0),
+        Bound::Excluded(&end) => end - 1,
+        Bound::Unbounded => u16::MAX,
+    };
+    start..=end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keypad;
+
+    // Adds 1 to V0 over and over: ADD V0, 1 at 0x200, then a jump back to it at 0x202.
+    fn counting() -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight
+            .write_memory(0x200, &[0x70, 0x01, 0x12, 0x00])
+            .unwrap();
+        chip_eight
+    }
+
+    #[test]
+    fn a_breakpoint_pauses_before_its_instruction() {
+        let mut chip_eight: ChipEight = counting();
+        chip_eight.add_breakpoint(0x202);
+        assert!(!chip_eight.step().unwrap().paused);
+        let outcome: StepOutcome = chip_eight.step().unwrap();
+        assert_eq!((outcome.paused, outcome.breakpoint), (true, Some(0x202)));
+        assert_eq!(chip_eight.paused_at(), Some(0x202));
+        // Nothing runs while it's paused, not even the timers.
+        chip_eight.delay_timer = 5;
+        chip_eight.step().unwrap();
+        chip_eight.frame(&Keypad::default()).unwrap();
+        assert_eq!(chip_eight.pc(), 0x202);
+        assert_eq!(chip_eight.v_registers()[0], 1);
+        assert_eq!(chip_eight.delay_timer(), 5);
+    }
+
+    #[test]
+    fn resuming_runs_the_instruction_under_the_breakpoint() {
+        let mut chip_eight: ChipEight = counting();
+        chip_eight.add_breakpoint(0x200);
+        assert!(chip_eight.step().unwrap().paused);
+        chip_eight.resume();
+        assert_eq!(chip_eight.paused_at(), None);
+        assert!(!chip_eight.step().unwrap().paused);
+        assert!(!chip_eight.step().unwrap().paused);
+        // Back round the loop, it's hit again.
+        assert_eq!(chip_eight.step().unwrap().breakpoint, Some(0x200));
+        assert_eq!(chip_eight.v_registers()[0], 1);
+    }
+
+    #[test]
+    fn single_step_runs_one_instruction_then_pauses() {
+        let mut chip_eight: ChipEight = counting();
+        chip_eight.add_breakpoint(0x200);
+        chip_eight.step().unwrap();
+        for (pc, v0) in [(0x202, 1), (0x200, 1), (0x202, 2)] {
+            chip_eight.single_step();
+            assert!(!chip_eight.step().unwrap().paused);
+            assert_eq!(chip_eight.paused_at(), Some(pc));
+            assert!(chip_eight.step().unwrap().paused);
+            assert_eq!(chip_eight.v_registers()[0], v0);
+        }
+    }
+
+    #[test]
+    fn pause_stops_a_running_machine() {
+        let mut chip_eight: ChipEight = counting();
+        chip_eight.step().unwrap();
+        chip_eight.pause();
+        assert_eq!(chip_eight.step().unwrap().breakpoint, Some(0x202));
+    }
+
+    #[test]
+    fn breakpoints_are_kept_sorted_without_duplicates() {
+        let mut chip_eight: ChipEight = counting();
+        for addr in [0x300, 0x200, 0x300, 0x250] {
+            chip_eight.add_breakpoint(addr);
+        }
+        assert_eq!(chip_eight.breakpoints(), [0x200, 0x250, 0x300]);
+        chip_eight.remove_breakpoint(0x250);
+        chip_eight.remove_breakpoint(0x251);
+        assert_eq!(chip_eight.breakpoints(), [0x200, 0x300]);
+        chip_eight.clear_breakpoints();
+        assert!(chip_eight.breakpoints().is_empty());
+        assert!(!chip_eight.step().unwrap().paused);
+    }
+
+    // A machine with program at 0x200 and V0 - V3 set to 0xA0 - 0xA3.
+    fn registers_set(program: &[u8]) -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.write_memory(0x200, program).unwrap();
+        for x in 0..4 {
+            chip_eight.set_v(x, 0xA0 + x as u8);
+        }
+        chip_eight
+    }
+
+    #[test]
+    fn a_write_watch_reports_the_store_that_hit_it() {
+        // I := 0x2FE, then store V0 - V3 there, over the watched 0x300.
+        let mut chip_eight: ChipEight = registers_set(&[0xA2, 0xFE, 0xF3, 0x55, 0x00, 0xE0]);
+        chip_eight.write_memory(0x300, &[0x11]).unwrap();
+        chip_eight.watch_write(0x300..=0x300);
+        assert_eq!(chip_eight.step().unwrap().watchpoint, None);
+        let hit: WatchHit = chip_eight.step().unwrap().watchpoint.unwrap();
+        assert_eq!(
+            hit,
+            WatchHit {
+                kind: WatchKind::Write,
+                pc: 0x202,
+                opcode: 0xF355,
+                addr: 0x300,
+                old: 0x11,
+                new: 0xA2,
+            }
+        );
+        assert_eq!(
+            hit.to_string(),
+            "F355 at 0x202 wrote 0xA2 to 0x300 (was 0x11)"
+        );
+        // The store finished, and the machine is paused before the next instruction.
+        assert_eq!(chip_eight.memory()[0x2FE..0x302], [0xA0, 0xA1, 0xA2, 0xA3]);
+        assert_eq!(chip_eight.paused_at(), Some(0x204));
+        assert_eq!(chip_eight.watch_hit(), Some(hit));
+        chip_eight.resume();
+        assert_eq!(chip_eight.watch_hit(), None);
+        assert!(!chip_eight.step().unwrap().paused);
+    }
+
+    #[test]
+    fn a_read_watch_catches_loads_and_sprites() {
+        // I := 0x300, load V0 - V1 from it, then draw a sprite from it.
+        for (program, opcode) in [
+            ([0xA3, 0x00, 0xF1, 0x65], 0xF165),
+            ([0xA3, 0x00, 0xD0, 0x12], 0xD012),
+        ] {
+            let mut chip_eight: ChipEight = registers_set(&program);
+            chip_eight.write_memory(0x300, &[0x11, 0x22]).unwrap();
+            chip_eight.watch_read(0x301..0x302);
+            chip_eight.step().unwrap();
+            let hit: WatchHit = chip_eight.step().unwrap().watchpoint.unwrap();
+            assert_eq!(
+                (hit.kind, hit.opcode, hit.addr, hit.old, hit.new),
+                (WatchKind::Read, opcode, 0x301, 0x22, 0x22)
+            );
+        }
+    }
+
+    #[test]
+    fn watches_only_see_their_own_kind_of_access_inside_instructions() {
+        let mut chip_eight: ChipEight = registers_set(&[0xA3, 0x00, 0xF1, 0x65]);
+        chip_eight.watch_write(0x300..=0x3FF);
+        // Reads don't hit a write watch, and nor does anything from outside an instruction.
+        chip_eight.write_bus(0x300, 1);
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.step().unwrap().watchpoint, None);
+        chip_eight.clear_watchpoints();
+        chip_eight.watch_write(0x300..0x300);
+        assert!(chip_eight.debugger.watchpoints[0].1.is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+// Tracking which part of the screen changed since the renderer last looked, so it can redraw just that part (or nothing).
+use crate::ChipEight;
+
+// A rectangle of pixels, in screen coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirtyRect {
+    pub x: u8,
+    pub y: u8,
+    pub width: u8,
+    pub height: u8,
+}
+
+impl DirtyRect {
+    // The smallest rectangle covering both self and other.
+    pub fn union(&self, other: &DirtyRect) -> DirtyRect {
+        let left: u8 = self.x.min(other.x);
+        let top: u8 = self.y.min(other.y);
+        let right: u8 = (self.x + self.width).max(other.x + other.width);
+        let bottom: u8 = (self.y + self.height).max(other.y + other.height);
+
+        DirtyRect {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x as usize..self.x as usize + self.width as usize).contains(&x)
+            && (self.y as usize..self.y as usize + self.height as usize).contains(&y)
+    }
+}
+
+// The whole screen buffer, hires rows and columns included.
+pub(crate) const WHOLE_SCREEN: DirtyRect = DirtyRect {
+    x: 0,
+    y: 0,
+    width: 128,
+    height: 64,
+};
+
+impl ChipEight {
+    // The part of the screen that changed since the last take_dirty_region, if anything did.
+    pub fn dirty_region(&self) -> Option<DirtyRect> {
+        self.dirty
+    }
+    // Hands the changed part of the screen to the renderer and starts tracking afresh. None means the screen looks exactly
+    // like it did last time, so there's nothing to redraw.
+    pub fn take_dirty_region(&mut self) -> Option<DirtyRect> {
+        self.dirty.take()
+    }
+    pub(crate) fn mark_dirty(&mut self, rect: DirtyRect) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(&rect),
+            None => rect,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChipVariant;
+
+    // A machine that's drawn the one-row sprite byte at (x, y), having already handed over the rest of the screen.
+    fn drawn(variant: ChipVariant, byte: u8, x: u8, y: u8) -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.set_variant(variant);
+        chip_eight
+            .write_memory(0x200, &[0xA3, 0x00, 0xD0, 0x11])
+            .unwrap();
+        chip_eight.write_memory(0x300, &[byte]).unwrap();
+        chip_eight.set_v(0, x);
+        chip_eight.set_v(1, y);
+        chip_eight.take_dirty_region();
+        chip_eight.step().unwrap();
+        chip_eight.step().unwrap();
+        chip_eight
+    }
+
+    fn rect(x: u8, y: u8, width: u8, height: u8) -> DirtyRect {
+        DirtyRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn a_new_machine_needs_drawing_in_full() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        assert_eq!(chip_eight.take_dirty_region(), Some(WHOLE_SCREEN));
+        assert_eq!(chip_eight.take_dirty_region(), None);
+    }
+
+    #[test]
+    fn a_sprite_marks_the_pixels_it_flipped() {
+        let mut chip_eight: ChipEight = drawn(ChipVariant::Chip8, 0xFF, 10, 5);
+        assert_eq!(chip_eight.take_dirty_region(), Some(rect(10, 5, 8, 1)));
+        assert_eq!(chip_eight.take_dirty_region(), None);
+        // Blank columns don't count, and a blank sprite changes nothing.
+        let mut chip_eight: ChipEight = drawn(ChipVariant::Chip8, 0x3C, 10, 5);
+        assert_eq!(chip_eight.take_dirty_region(), Some(rect(12, 5, 4, 1)));
+        let mut chip_eight: ChipEight = drawn(ChipVariant::Chip8, 0x00, 10, 5);
+        assert_eq!(chip_eight.take_dirty_region(), None);
+    }
+
+    #[test]
+    fn a_wrapped_sprite_marks_both_sides() {
+        let mut chip_eight: ChipEight = drawn(ChipVariant::XoChip, 0xFF, 60, 5);
+        assert_eq!(chip_eight.take_dirty_region(), Some(rect(0, 5, 64, 1)));
+    }
+
+    #[test]
+    fn changes_add_up_until_taken() {
+        let mut chip_eight: ChipEight = drawn(ChipVariant::Chip8, 0xFF, 10, 5);
+        chip_eight.set_v(0, 2);
+        chip_eight.set_v(1, 20);
+        chip_eight.set_pc(0x202);
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.dirty_region(), Some(rect(2, 5, 16, 16)));
+        // 00E0 marks everything.
+        chip_eight.write_memory(0x204, &[0x00, 0xE0]).unwrap();
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.take_dirty_region(), Some(WHOLE_SCREEN));
+    }
+
+    #[test]
+    fn union_and_contains() {
+        let union: DirtyRect = rect(10, 5, 8, 1).union(&rect(2, 20, 4, 2));
+        assert_eq!(union, rect(2, 5, 16, 17));
+        for (x, y, inside) in [
+            (2, 5, true),
+            (17, 21, true),
+            (18, 21, false),
+            (2, 22, false),
+        ] {
+            assert_eq!(union.contains(x, y), inside, "({}, {})", x, y);
+        }
+    }
+}
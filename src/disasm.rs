@@ -0,0 +1,138 @@
+// Turning opcodes back into assembly, for ROM listings and debugging output. The mnemonics are Instruction's Display,
+// with addresses replaced by their names when there are symbols for them.
+use crate::{decode, Instruction, Symbols};
+
+// The mnemonic for one opcode. Opcodes that don't decode are written as data: .word 0xNNNN.
+pub fn disassemble(opcode: u16) -> String {
+    disassemble_with_symbols(opcode, &Symbols::new())
+}
+
+// Like disassemble(), but jumps, calls, and LD I name the address they use if it has a symbol.
+pub fn disassemble_with_symbols(opcode: u16, symbols: &Symbols) -> String {
+    let instruction: Instruction = match decode(opcode) {
+        Ok(instruction) => instruction,
+        Err(_) => return format!(".word {:#06X}", opcode),
+    };
+    let name = |nnn: u16| -> Option<&str> { symbols.name(nnn) };
+
+    match instruction {
+        Instruction::JumpToMachineCode { nnn } => name(nnn).map(|name| format!("SYS {}", name)),
+        Instruction::JumpToAddress { nnn } => name(nnn).map(|name| format!("JP {}", name)),
+        Instruction::CallSubroutine { nnn } => name(nnn).map(|name| format!("CALL {}", name)),
+        Instruction::SetI { nnn } => name(nnn).map(|name| format!("LD I, {}", name)),
+        Instruction::JumpPlusV0 { nnn } => name(nnn).map(|name| format!("JP V0, {}", name)),
+        _ => None,
+    }
+    .unwrap_or_else(|| instruction.to_string())
+}
+
+// An (address, opcode, mnemonic) listing of a whole ROM loaded at base. Nothing distinguishes code from data in a ROM, so
+// every word is listed as an instruction if it decodes as one. The address word after F000 is shown as part of that
+// instruction (as LD I, LONG addr, the way assemble() takes it) and listed again as data, and an odd byte at the end is listed as .byte 0xNN.
+pub fn disassemble_rom(bytes: &[u8], base: u16) -> Vec<(u16, u16, String)> {
+    disassemble_rom_with_symbols(bytes, base, &Symbols::new())
+}
+
+// Like disassemble_rom(), but with names for addresses from symbols, and bytes under data symbols listed one at a time as
+// .byte 0xNN rather than disassembled.
+pub fn disassemble_rom_with_symbols(
+    bytes: &[u8],
+    base: u16,
+    symbols: &Symbols,
+) -> Vec<(u16, u16, String)> {
+    let mut listing: Vec<(u16, u16, String)> = Vec::with_capacity(bytes.len() / 2 + 1);
+    let word_at = |offset: usize| -> Option<u16> {
+        Some(u16::from_be_bytes([
+            *bytes.get(offset)?,
+            *bytes.get(offset + 1)?,
+        ]))
+    };
+    let mut offset: usize = 0;
+
+    while offset < bytes.len() {
+        let address: u16 = base.wrapping_add(offset as u16);
+        // A word that starts or ends in data isn't an instruction.
+        let data: bool = symbols.is_data(address) || symbols.is_data(address.wrapping_add(1));
+        let opcode: u16 = match word_at(offset) {
+            Some(opcode) if !data => opcode,
+            _ => {
+                let byte: u8 = bytes[offset];
+                listing.push((address, byte as u16, format!(".byte {:#04X}", byte)));
+                offset += 1;
+                continue;
+            }
+        };
+
+        match (decode(opcode), word_at(offset + 2)) {
+            (Ok(Instruction::SetILong), Some(long)) => {
+                let target: String = match symbols.name(long) {
+                    Some(name) => name.to_string(),
+                    None => format!("{:#06X}", long),
+                };
+                listing.push((address, opcode, format!("LD I, LONG {}", target)));
+                listing.push((
+                    address.wrapping_add(2),
+                    long,
+                    format!(".word {:#06X}", long),
+                ));
+                offset += 4;
+            }
+            _ => {
+                listing.push((address, opcode, disassemble_with_symbols(opcode, symbols)));
+                offset += 2;
+            }
+        }
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    #[test]
+    fn every_opcode_assembles_back_from_its_disassembly() {
+        for opcode in 0..=u16::MAX {
+            let text: String = disassemble(opcode);
+            assert_eq!(
+                assemble(&text),
+                Ok(opcode.to_be_bytes().to_vec()),
+                "{:04X} disassembled to {}",
+                opcode,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn a_rom_listing_assembles_back_to_the_rom() {
+        // Ends with LD I, LONG and its address, then an odd byte.
+        let rom: [u8; 11] = [
+            0x60, 0x05, 0xA2, 0x0A, 0x22, 0x00, 0xF0, 0x00, 0x12, 0x34, 0xAB,
+        ];
+        let listing: Vec<(u16, u16, String)> = disassemble_rom(&rom, 0x200);
+        assert_eq!(listing[3], (0x206, 0xF000, "LD I, LONG 0x1234".to_string()));
+        assert_eq!(listing[5], (0x20A, 0xAB, ".byte 0xAB".to_string()));
+        // The address after LD I, LONG is listed twice, so skip the copy that's only there as data.
+        let src: String = listing
+            .iter()
+            .filter(|(address, _, _)| *address != 0x208)
+            .map(|(_, _, text)| format!("{}\n", text))
+            .collect();
+        assert_eq!(assemble(&src), Ok(rom.to_vec()));
+    }
+
+    #[test]
+    fn symbols_name_addresses_and_mark_data() {
+        let mut symbols: Symbols = Symbols::new();
+        symbols.insert(0x200, "start", false);
+        symbols.insert(0x204, "sprite", true);
+        let rom: [u8; 5] = [0x12, 0x00, 0xA2, 0x04, 0xF0];
+        let listing: Vec<String> = disassemble_rom_with_symbols(&rom, 0x200, &symbols)
+            .into_iter()
+            .map(|(_, _, text)| text)
+            .collect();
+        assert_eq!(listing, ["JP start", "LD I, sprite", ".byte 0xF0"]);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::LoadError;
+use core::fmt;
+
+// Errors the interpreter can run into while loading or executing a program. Bad ROMs produce one of these rather than a panic.
+#[derive(Debug)]
+pub enum ChipEightError {
+    // The instruction at pc doesn't decode to anything the current variant supports.
+    UnknownOpcode {
+        opcode: u16,
+        pc: u16,
+    },
+    // 2nnn was called with all 16 stack levels already in use.
+    StackOverflow {
+        pc: u16,
+    },
+    // 00EE was executed with nothing on the stack to return to.
+    StackUnderflow {
+        pc: u16,
+    },
+    // Something outside the program tried to access memory past the end of RAM. The program's own accesses wrap around instead.
+    MemoryOutOfBounds {
+        addr: usize,
+        pc: u16,
+    },
+    // The program couldn't be loaded.
+    Load(LoadError),
+    // A display, input, or audio backend failed.
+    #[cfg(feature = "std")]
+    Frontend(String),
+}
+
+impl fmt::Display for ChipEightError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChipEightError::UnknownOpcode { opcode, pc } => {
+                write!(f, "unknown opcode {:04X} at {:#05X}", opcode, pc)
+            }
+            ChipEightError::StackOverflow { pc } => {
+                write!(f, "stack overflow calling a subroutine at {:#05X}", pc)
+            }
+            ChipEightError::StackUnderflow { pc } => write!(
+                f,
+                "returned from a subroutine at {:#05X} with an empty stack",
+                pc
+            ),
+            ChipEightError::MemoryOutOfBounds { addr, pc } => write!(
+                f,
+                "memory access at {:#06X} is out of bounds (pc {:#05X})",
+                addr, pc
+            ),
+            ChipEightError::Load(e) => write!(f, "{}", e),
+            #[cfg(feature = "std")]
+            ChipEightError::Frontend(e) => write!(f, "frontend error: {}", e),
+        }
+    }
+}
+
+impl ChipEightError {
+    // Where the program was when it failed, for the errors the program itself caused. Loading and frontend errors have
+    // nothing to do with the program counter, so they return None.
+    pub fn pc(&self) -> Option<u16> {
+        match self {
+            ChipEightError::UnknownOpcode { pc, .. }
+            | ChipEightError::StackOverflow { pc }
+            | ChipEightError::StackUnderflow { pc }
+            | ChipEightError::MemoryOutOfBounds { pc, .. } => Some(*pc),
+            ChipEightError::Load(_) => None,
+            #[cfg(feature = "std")]
+            ChipEightError::Frontend(_) => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ChipEightError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ChipEightError::Load(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<LoadError> for ChipEightError {
+    fn from(e: LoadError) -> Self {
+        ChipEightError::Load(e)
+    }
+}
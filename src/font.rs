@@ -0,0 +1,218 @@
+// The hexadecimal font Fx29 points I at. It lives at the very start of memory, FONT_ADDRESS through FONT_ADDRESS + 0x4F,
+// with the glyph for digit n at FONT_ADDRESS + 5n. Every interpreter shipped its own take on the same 16 glyphs; a few of
+// them are built in, and load_font takes any other. SUPER-CHIP added a second font of big digits for Fx30, which goes right
+// after it, at BIG_FONT_ADDRESS, with the glyph for n at BIG_FONT_ADDRESS + 10n.
+use crate::ChipEight;
+
+// Where the font starts in memory.
+pub const FONT_ADDRESS: u16 = 0x000;
+// Where the big font starts, and where both fonts end.
+pub const BIG_FONT_ADDRESS: u16 = FONT_ADDRESS + 16 * 5;
+pub(crate) const FONTS_END: u16 = BIG_FONT_ADDRESS + 16 * 10;
+
+// A font is 16 glyphs, 0 through F, each 5 rows of up to 8 pixels.
+pub type Font = [[u8; 5]; 16];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FontSet {
+    // The font from Cowgod's reference, which most modern interpreters use.
+    #[default]
+    Classic,
+    // The COSMAC VIP interpreter's original font. Its 1, 4, and 7 differ from the classic font.
+    CosmacVip,
+    // The ETI-660's narrow 3-pixel-wide font, with lowercase-style b and d.
+    Eti660,
+    // The DREAM 6800's narrow font.
+    Dream6800,
+}
+
+impl FontSet {
+    pub fn glyphs(&self) -> &'static Font {
+        match self {
+            FontSet::Classic => &crate::SPRITES,
+            FontSet::CosmacVip => &COSMAC_VIP,
+            FontSet::Eti660 => &ETI_660,
+            FontSet::Dream6800 => &DREAM_6800,
+        }
+    }
+}
+
+static COSMAC_VIP: Font = [
+    [0xF0, 0x90, 0x90, 0x90, 0xF0],
+    [0x60, 0x20, 0x20, 0x20, 0x70],
+    [0xF0, 0x10, 0xF0, 0x80, 0xF0],
+    [0xF0, 0x10, 0xF0, 0x10, 0xF0],
+    [0xA0, 0xA0, 0xF0, 0x20, 0x20],
+    [0xF0, 0x80, 0xF0, 0x10, 0xF0],
+    [0xF0, 0x80, 0xF0, 0x90, 0xF0],
+    [0xF0, 0x10, 0x10, 0x10, 0x10],
+    [0xF0, 0x90, 0xF0, 0x90, 0xF0],
+    [0xF0, 0x90, 0xF0, 0x10, 0xF0],
+    [0xF0, 0x90, 0xF0, 0x90, 0x90],
+    [0xF0, 0x50, 0x70, 0x50, 0xF0],
+    [0xF0, 0x80, 0x80, 0x80, 0xF0],
+    [0xF0, 0x50, 0x50, 0x50, 0xF0],
+    [0xF0, 0x80, 0xF0, 0x80, 0xF0],
+    [0xF0, 0x80, 0xF0, 0x80, 0x80],
+];
+
+static ETI_660: Font = [
+    [0xE0, 0xA0, 0xA0, 0xA0, 0xE0],
+    [0x20, 0x20, 0x20, 0x20, 0x20],
+    [0xE0, 0x20, 0xE0, 0x80, 0xE0],
+    [0xE0, 0x20, 0xE0, 0x20, 0xE0],
+    [0xA0, 0xA0, 0xE0, 0x20, 0x20],
+    [0xE0, 0x80, 0xE0, 0x20, 0xE0],
+    [0xE0, 0x80, 0xE0, 0xA0, 0xE0],
+    [0xE0, 0x20, 0x20, 0x20, 0x20],
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xE0],
+    [0xE0, 0xA0, 0xE0, 0x20, 0xE0],
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xA0],
+    [0x80, 0x80, 0xE0, 0xA0, 0xE0],
+    [0xE0, 0x80, 0x80, 0x80, 0xE0],
+    [0x20, 0x20, 0xE0, 0xA0, 0xE0],
+    [0xE0, 0x80, 0xE0, 0x80, 0xE0],
+    [0xE0, 0x80, 0xC0, 0x80, 0x80],
+];
+
+static DREAM_6800: Font = [
+    [0xE0, 0xA0, 0xA0, 0xA0, 0xE0],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0xE0, 0x20, 0xE0, 0x80, 0xE0],
+    [0xE0, 0x20, 0xE0, 0x20, 0xE0],
+    [0x80, 0xA0, 0xA0, 0xE0, 0x20],
+    [0xE0, 0x80, 0xE0, 0x20, 0xE0],
+    [0xE0, 0x80, 0xE0, 0xA0, 0xE0],
+    [0xE0, 0x20, 0x20, 0x20, 0x20],
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xE0],
+    [0xE0, 0xA0, 0xE0, 0x20, 0xE0],
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xA0],
+    [0xC0, 0xA0, 0xE0, 0xA0, 0xC0],
+    [0xE0, 0x80, 0x80, 0x80, 0xE0],
+    [0xC0, 0xA0, 0xA0, 0xA0, 0xC0],
+    [0xE0, 0x80, 0xE0, 0x80, 0xE0],
+    [0xE0, 0x80, 0xC0, 0x80, 0x80],
+];
+
+// The big font, 8x10 glyphs. SUPER-CHIP only had the digits; the letters are Octo's, as XO-CHIP programs expect them.
+pub(crate) static BIG_FONT: [[u8; 10]; 16] = [
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF],
+    [0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF],
+    [0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03],
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18],
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF],
+    [0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3],
+    [0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC],
+    [0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C],
+    [0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC],
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0],
+];
+
+impl ChipEight {
+    // The font reset() puts back in memory.
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+    // Replaces the font, both in memory and for future resets. A program that has already copied glyphs elsewhere keeps
+    // its copies.
+    pub fn load_font(&mut self, font: &Font) {
+        self.font = *font;
+        Self::copy_font(&mut self.memory, font);
+    }
+    pub fn set_font(&mut self, font: FontSet) {
+        self.load_font(font.glyphs());
+    }
+    pub(crate) fn copy_big_font(memory: &mut [u8]) {
+        let start: usize = BIG_FONT_ADDRESS as usize;
+
+        for (i, glyph) in BIG_FONT.iter().enumerate() {
+            memory[start + i * glyph.len()..][..glyph.len()].copy_from_slice(glyph);
+        }
+    }
+    pub(crate) fn copy_font(memory: &mut [u8], font: &Font) {
+        let start: usize = FONT_ADDRESS as usize;
+
+        for (i, glyph) in font.iter().enumerate() {
+            memory[start + i * glyph.len()..][..glyph.len()].copy_from_slice(glyph);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A font where every glyph is n's bits in the top row and an X below, so each one is easy to tell apart.
+    fn custom_font() -> Font {
+        ::core::array::from_fn(|n| [n as u8, 0x81, 0x42, 0x24, 0x18])
+    }
+
+    // The left 8 pixels of the top 5 rows, as bytes.
+    fn drawn(chip_eight: &ChipEight) -> [u8; 5] {
+        ::core::array::from_fn(|y| {
+            chip_eight.framebuffer()[y][..8]
+                .iter()
+                .fold(0, |bits, &pixel| bits << 1 | pixel as u8)
+        })
+    }
+
+    #[test]
+    fn fx29_and_dxy5_draw_the_loaded_glyph() {
+        for digit in [0x0, 0x7, 0xF] {
+            let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+            chip_eight.load_font(&custom_font());
+            // I := the glyph for V0, then draw it at (V1, V1).
+            chip_eight
+                .write_memory(0x200, &[0xF0, 0x29, 0xD1, 0x15])
+                .unwrap();
+            chip_eight.set_v(0, digit);
+            chip_eight.step().unwrap();
+            chip_eight.step().unwrap();
+            assert_eq!(chip_eight.i_register(), FONT_ADDRESS + 5 * digit as u16);
+            assert_eq!(
+                drawn(&chip_eight),
+                custom_font()[digit as usize],
+                "digit {:X}",
+                digit
+            );
+        }
+    }
+
+    #[test]
+    fn the_font_goes_at_font_address_and_outlives_resets() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.load_font(&custom_font());
+        chip_eight.write_memory(0x200, &[0x00, 0xE0]).unwrap();
+        chip_eight.reset();
+        let start: usize = FONT_ADDRESS as usize;
+        assert_eq!(chip_eight.memory()[start + 5 * 3..][..5], custom_font()[3]);
+        assert_eq!(chip_eight.font(), &custom_font());
+        // The big font after it is left alone.
+        let big: usize = BIG_FONT_ADDRESS as usize;
+        assert_eq!(chip_eight.memory()[big..big + 10], BIG_FONT[0]);
+    }
+
+    #[test]
+    fn the_built_in_fonts_differ() {
+        let sets: [FontSet; 4] = [
+            FontSet::Classic,
+            FontSet::CosmacVip,
+            FontSet::Eti660,
+            FontSet::Dream6800,
+        ];
+        for (i, a) in sets.iter().enumerate() {
+            for b in &sets[i + 1..] {
+                assert_ne!(a.glyphs(), b.glyphs(), "{:?} and {:?}", a, b);
+            }
+        }
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.set_font(FontSet::Eti660);
+        assert_eq!(chip_eight.memory()[..5], FontSet::Eti660.glyphs()[0]);
+    }
+}
@@ -0,0 +1,2654 @@
+// The SDL2 frontend: a window for the display, the keyboard for input, and a square wave for the beeper.
+use crate::backend::{apply_input_events, Audio, Display, Input, InputEvent, VOLUME_STEP};
+use crate::debug_hud::{self, CHAR_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, LINE_HEIGHT};
+use crate::debug_layout;
+use crate::{
+    ChipEight, ChipEightError, ChipVariant, EditorKey, EditorMark, Frame, GifRecorder,
+    MemoryEditor, Palette, Phosphor, RomInfo, SampleTap, Sound, Stats, StatsReport, Tone,
+    WavWriter, Waveform, GIF_FPS, GIF_SCALE, MAX_VOLUME, SCREENSHOT_SCALE, STATS_WINDOW,
+};
+use sdl2::audio::{
+    AudioCallback, AudioDevice, AudioQueue, AudioSpec, AudioSpecDesired, AudioStatus,
+};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator};
+use sdl2::video::{FullscreenType, Window, WindowContext, WindowPos};
+use std::fmt;
+use std::io;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+pub use crate::debug_layout::{layout, Area, Layout, Panel, LAYOUTS};
+pub use crate::keymap::{Keymap, DEFAULT_KEYMAP};
+pub use crate::keypad_overlay::{
+    key_label, keypad_layout, KeypadCell, KeypadLayout, KeypadView, FLASH_FRAMES, KEYPAD_ROWS,
+    LABEL_CHARS,
+};
+pub use crate::osd::{Osd, OSD_LINES, OSD_TIME};
+pub use crate::splash::{splash_rom, splash_screen, SPLASH_HINT};
+pub use crate::sprite_viewer::SpriteViewer;
+
+// What an SdlInput sends its display (see SdlDisplay::events).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisplayEvent {
+    // An event that's the window's business rather than the machine's, like F3 showing the debug overlay.
+    Input(InputEvent),
+    // The memory editor as it is after the input changed it, to draw, or None once it's closed.
+    MemoryEditor(Option<MemoryEditor>),
+}
+
+// A part of the overlay text to make stand out, as (line, first character, characters, mark).
+type HudMark = (usize, usize, usize, EditorMark);
+
+// What's drawn around the screen: lines of text under it, with marks on parts of them, short messages over its bottom
+// left corner (oldest first), the keypad over its bottom right corner, and the debugging panels beside it, with their
+// lines.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Hud {
+    lines: Vec<String>,
+    marks: Vec<HudMark>,
+    messages: Vec<String>,
+    keypad: Option<KeypadView>,
+    panels: Vec<(Panel, Vec<String>)>,
+}
+
+// How long a frame lasts, at the machine's 60Hz.
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// How much of the end of a frame FrameLimiter spins through rather than sleeps, since sleeping can overshoot by about this
+// much.
+const FRAME_SPIN: Duration = Duration::from_millis(1);
+
+// Keeps frames to 60 a second without vsync. Each frame is due FRAME_TIME after the last one was due, rather than after it
+// actually ended, so time lost oversleeping one frame is made up in the next and the rate doesn't drift below 60. A frame
+// that's more than a frame late (after a breakpoint, or dragging the window) starts the count again instead of rushing
+// through frames to catch up.
+#[derive(Debug, Default)]
+struct FrameLimiter {
+    due: Option<Instant>,
+}
+
+impl FrameLimiter {
+    // Waits until the frame is due: asleep for most of the way, and spinning for the last FRAME_SPIN.
+    fn wait(&mut self) {
+        let now: Instant = Instant::now();
+        let due: Instant = match self.due {
+            Some(due) if now < due + FRAME_TIME => due,
+            _ => {
+                self.due = Some(now + FRAME_TIME);
+                return;
+            }
+        };
+        if let Some(sleep) = due.checked_duration_since(now + FRAME_SPIN) {
+            std::thread::sleep(sleep);
+        }
+        while Instant::now() < due {
+            std::hint::spin_loop();
+        }
+        self.due = Some(due + FRAME_TIME);
+    }
+}
+
+// An effect drawn over the screen for an old CRT look. It's drawn at the window's real resolution rather than the
+// screen's, so it stays fine-grained however big the window gets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Filter {
+    #[default]
+    None,
+    // Every other row of the window's pixels darkened.
+    Scanlines,
+    // Faint lines between the program's pixels.
+    Grid,
+}
+
+impl Filter {
+    // The filter F9 switches to from this one.
+    pub fn next(&self) -> Filter {
+        match self {
+            Filter::None => Filter::Scanlines,
+            Filter::Scanlines => Filter::Grid,
+            Filter::Grid => Filter::None,
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Filter::None),
+            "scanlines" => Ok(Filter::Scanlines),
+            "grid" => Ok(Filter::Grid),
+            _ => Err(format!(
+                "unknown filter '{}' (expected scanlines, grid, or none)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Filter::None => "none",
+            Filter::Scanlines => "scanlines",
+            Filter::Grid => "grid",
+        })
+    }
+}
+
+// Which way the picture in the window is turned, for displays mounted on their side. Only what's presented turns: the
+// machine's screen (and screenshots of it) stay the way the program drew them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    // 90 degrees clockwise.
+    Clockwise,
+    // 180 degrees.
+    UpsideDown,
+    // 270 degrees clockwise, or 90 counterclockwise.
+    Counterclockwise,
+}
+
+impl Rotation {
+    // How far clockwise the picture is turned, as SDL's copy_ex takes it.
+    pub fn degrees(&self) -> f64 {
+        match self {
+            Rotation::None => 0.0,
+            Rotation::Clockwise => 90.0,
+            Rotation::UpsideDown => 180.0,
+            Rotation::Counterclockwise => 270.0,
+        }
+    }
+    // The size a picture of size takes up once it's turned.
+    pub fn size(&self, (width, height): (usize, usize)) -> (usize, usize) {
+        match self {
+            Rotation::None | Rotation::UpsideDown => (width, height),
+            Rotation::Clockwise | Rotation::Counterclockwise => (height, width),
+        }
+    }
+    // Where rect, in a picture of size, ends up once the picture is turned. A rect stays a rect at quarter turns, so
+    // anything drawn as rects can be turned by moving them.
+    pub fn rect(&self, rect: Rect, (width, height): (usize, usize)) -> Rect {
+        let (width, height) = (width as i32, height as i32);
+        match self {
+            Rotation::None => rect,
+            Rotation::Clockwise => Rect::new(
+                height - rect.bottom(),
+                rect.x(),
+                rect.height(),
+                rect.width(),
+            ),
+            Rotation::UpsideDown => Rect::new(
+                width - rect.right(),
+                height - rect.bottom(),
+                rect.width(),
+                rect.height(),
+            ),
+            Rotation::Counterclockwise => {
+                Rect::new(rect.y(), width - rect.right(), rect.height(), rect.width())
+            }
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(Rotation::None),
+            "90" => Ok(Rotation::Clockwise),
+            "180" => Ok(Rotation::UpsideDown),
+            "270" => Ok(Rotation::Counterclockwise),
+            _ => Err(format!(
+                "unknown rotation '{}' (expected 0, 90, 180, or 270)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.degrees())
+    }
+}
+
+// The shape the screen's pixels are presented in, as width:height. Plenty of the displays CHIP-8 ran on didn't show its
+// pixels square, and the picture can be stretched to look the way it did. Like Rotation, this only changes what's
+// presented: everything is drawn square, and stretched by a whole number each way as it goes on the canvas, so integer
+// scaling stays exact in both directions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelAspect {
+    width: u32,
+    height: u32,
+}
+
+impl PixelAspect {
+    pub const SQUARE: PixelAspect = PixelAspect {
+        width: 1,
+        height: 1,
+    };
+    // The most either side can be, once the ratio is in its lowest terms. More than that and the picture stops fitting on
+    // a screen at any whole number scale.
+    pub const MAX: u32 = 4;
+    // width:height in its lowest terms, if neither side comes out more than MAX.
+    pub fn new(width: u32, height: u32) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("{}:{} isn't a pixel aspect", width, height));
+        }
+        let gcd: u32 = (1..=width.min(height))
+            .rev()
+            .find(|n| width.is_multiple_of(*n) && height.is_multiple_of(*n))
+            .unwrap_or(1);
+        let (width, height) = (width / gcd, height / gcd);
+        if width > Self::MAX || height > Self::MAX {
+            return Err(format!(
+                "pixel aspect {}:{} is too lopsided (neither side can be more than {})",
+                width,
+                height,
+                Self::MAX
+            ));
+        }
+        Ok(PixelAspect { width, height })
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    // The size a picture of size takes up once it's stretched.
+    pub fn size(&self, (width, height): (usize, usize)) -> (usize, usize) {
+        (width * self.width as usize, height * self.height as usize)
+    }
+    // The size that takes up size once it's stretched, rounded down.
+    pub fn unstretch(&self, (width, height): (usize, usize)) -> (usize, usize) {
+        (width / self.width as usize, height / self.height as usize)
+    }
+    // Where rect ends up once the picture is stretched.
+    pub fn rect(&self, rect: Rect) -> Rect {
+        Rect::new(
+            rect.x() * self.width as i32,
+            rect.y() * self.height as i32,
+            rect.width() * self.width,
+            rect.height() * self.height,
+        )
+    }
+}
+
+impl Default for PixelAspect {
+    fn default() -> Self {
+        PixelAspect::SQUARE
+    }
+}
+
+impl FromStr for PixelAspect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once(':')
+            .and_then(|(width, height)| {
+                Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+            })
+            .ok_or_else(|| format!("bad pixel aspect '{}' (expected W:H, like 2:1)", s))?;
+        PixelAspect::new(width, height)
+    }
+}
+
+impl fmt::Display for PixelAspect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.width, self.height)
+    }
+}
+
+// A filter's overlay, and what it was made for: whether it has the pixel grid, its size in the window's pixels, and the
+// size of the screen under it.
+struct FilterOverlay<'a> {
+    texture: Texture<'a>,
+    filter: Filter,
+    grid: bool,
+    size: (u32, u32),
+    screen: (usize, usize),
+}
+
+// Borrows the canvas rather than owning it, so the application can keep using it once the emulator stops. The screen is
+// drawn by filling a texture with its pixels and stretching that over the window, so the texture creator (from
+// Canvas::texture_creator) has to outlive the display too.
+pub struct SdlDisplay<'a> {
+    canvas: &'a mut Canvas<Window>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    // The texture the screen is drawn into, its size, which follows the machine into and out of hires mode, and whether it
+    // scales smoothly. pixels is the buffer it's filled from, kept to save allocating one every frame.
+    texture: Option<(Texture<'a>, (usize, usize), bool)>,
+    pixels: Vec<u8>,
+    // Whether the screen is scaled up smoothly, which the texture is made again to follow.
+    smooth: bool,
+    // The colors the screen is drawn in, and screenshots and GIFs made.
+    palette: Palette,
+    // Whether a setting changed that the window has to be drawn again for, even if the screen didn't.
+    redraw: bool,
+    // How the buzzer sounds, and where it's sent whenever it changes, if the display was tied to an SdlAudio (see
+    // audio()).
+    sound: Sound,
+    sounds: Option<Sender<Sound>>,
+    // The logical size the canvas is currently set to (before it's turned), and the size of the window in real pixels
+    // when it was last drawn.
+    logical_size: Option<(usize, usize)>,
+    output_size: Option<(u32, u32)>,
+    // Whether the logical size is stretched to fill the window, rather than scaled by a whole number with bars around it.
+    fill: bool,
+    // Which way everything drawn is turned. It's all drawn the right way up and moved or turned as it goes on the canvas.
+    rotation: Rotation,
+    // How everything drawn is stretched, before it's turned.
+    aspect: PixelAspect,
+    // The filter drawn over the screen, and its overlay, once it's been made.
+    filter: Filter,
+    filter_overlay: Option<FilterOverlay<'a>>,
+    // The overlay last presented, so a frame where neither it nor the screen changed isn't drawn again.
+    hud: Hud,
+    // How bright the pixels are as they fade out, if they fade (see Phosphor).
+    phosphor: Option<Phosphor>,
+    // The keys the keypad overlay labels its keys with, which should be the input's.
+    keymap: Keymap,
+    // Lines always shown under the screen, before any of the overlays'.
+    footer: Vec<String>,
+    // Whether the machine was paused when the last frame was presented, to notice when it pauses or resumes, and the
+    // messages over the screen.
+    paused: Option<bool>,
+    osd: Osd,
+    // Whether the window should be fullscreen, and its size and position from before it went fullscreen, to put it
+    // back to.
+    fullscreen: bool,
+    windowed: Option<((u32, u32), (i32, i32))>,
+    // Where screenshots (and GIFs) are saved, and how many times bigger than the screen they are.
+    screenshots: (PathBuf, u32),
+    // Whether screenshots are stretched by the pixel aspect too.
+    screenshot_aspect: bool,
+    // Whether F12 asked for a screenshot since the last frame.
+    screenshot_requested: bool,
+    // Whether Shift+F12 asked for a dump since the last frame.
+    dump_requested: bool,
+    // How many times bigger than the screen GIFs are, and how many frames a second they take.
+    gifs: (u32, u32),
+    // Whether F10 asked to start or stop recording since the last frame.
+    recording_toggled: bool,
+    // The GIF being recorded, if there is one.
+    recording: Option<GifRecorder>,
+    // When the last frame was presented. Frames with nothing to redraw aren't presented, so they can't wait for vsync and
+    // have to keep time themselves.
+    last_present: Option<Instant>,
+    // What keeps time instead of vsync, if the canvas was built without it.
+    limiter: Option<FrameLimiter>,
+    // The window's title as it was last set from outside (the program's name), and the title shown with the speed or
+    // where the machine is paused added to it. A title that isn't the one shown was set from outside again.
+    title: Option<(String, String)>,
+    // The speed shown in the title, and when it was worked out. It's only worked out once every STATS_WINDOW, so the
+    // title isn't set every frame.
+    speed: Option<(String, Instant)>,
+    // Where SdlInput sends the events that are the window's business rather than the machine's (see events()), and
+    // where they're picked up each frame.
+    events: (Sender<DisplayEvent>, Receiver<DisplayEvent>),
+    // Whether the debug overlay is shown under the screen.
+    debug_hud: bool,
+    // Whether the keypad overlay is shown.
+    keypad_overlay: bool,
+    // Which of the LAYOUTS of debugging panels is used.
+    debug_layout: usize,
+    // Whether the speed statistics are shown under the screen.
+    stats_overlay: bool,
+    // The input's memory editor, while it's open.
+    memory_editor: Option<MemoryEditor>,
+    // Whether lines are drawn between the screen's pixels.
+    pixel_grid: bool,
+    // How fast the machine runs and the screen is drawn, for the F2 overlay and --log-timing. presents counts the frames
+    // actually presented, and last_log is when the numbers were last printed, if they're being printed.
+    stats: Stats,
+    presents: u64,
+    log_timing: bool,
+    last_log: Option<Instant>,
+}
+
+impl<'a> SdlDisplay<'a> {
+    pub fn new(
+        canvas: &'a mut Canvas<Window>,
+        texture_creator: &'a TextureCreator<WindowContext>,
+    ) -> Self {
+        SdlDisplay {
+            canvas,
+            texture_creator,
+            texture: None,
+            pixels: vec![],
+            smooth: false,
+            palette: Palette::default(),
+            redraw: false,
+            sound: Sound::default(),
+            sounds: None,
+            logical_size: None,
+            output_size: None,
+            fill: false,
+            rotation: Rotation::None,
+            aspect: PixelAspect::SQUARE,
+            filter: Filter::None,
+            filter_overlay: None,
+            hud: Hud::default(),
+            phosphor: None,
+            keymap: Keymap::default(),
+            footer: vec![],
+            paused: None,
+            osd: Osd::new(),
+            fullscreen: false,
+            windowed: None,
+            screenshots: (PathBuf::from("."), SCREENSHOT_SCALE),
+            screenshot_aspect: false,
+            screenshot_requested: false,
+            dump_requested: false,
+            gifs: (GIF_SCALE, GIF_FPS),
+            recording_toggled: false,
+            recording: None,
+            last_present: None,
+            limiter: None,
+            title: None,
+            speed: None,
+            events: mpsc::channel(),
+            debug_hud: false,
+            keypad_overlay: false,
+            debug_layout: 0,
+            stats_overlay: false,
+            memory_editor: None,
+            pixel_grid: false,
+            stats: Stats::new(),
+            presents: 0,
+            log_timing: false,
+            last_log: None,
+        }
+    }
+    // Stretches the screen as far as it fits in the window, keeping its shape, even if its pixels come out different sizes.
+    pub fn fill_window(mut self) -> Self {
+        self.fill = true;
+        self
+    }
+    // Turns the picture in the window, the HUD and error screen along with it.
+    pub fn rotate(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+    // Stretches the picture in the window so its pixels are aspect's shape, the HUD and error screen along with it.
+    pub fn pixel_aspect(mut self, aspect: PixelAspect) -> Self {
+        self.aspect = aspect;
+        self
+    }
+    // Stretches screenshots by the pixel aspect too, rather than saving the screen's pixels square.
+    pub fn screenshot_respect_aspect(mut self) -> Self {
+        self.screenshot_aspect = true;
+        self
+    }
+    // Saves screenshots in dir (the current directory unless this says otherwise), scale times bigger than the screen.
+    pub fn screenshots(mut self, dir: PathBuf, scale: u32) -> Self {
+        self.screenshots = (dir, scale);
+        self
+    }
+    // Records GIFs scale times bigger than the screen, taking fps frames a second.
+    pub fn gifs(mut self, scale: u32, fps: u32) -> Self {
+        self.gifs = (scale, fps);
+        self
+    }
+    // Keeps frames to 60 a second itself, for a canvas built without present_vsync (or where vsync isn't 60Hz).
+    pub fn frame_limiter(mut self) -> Self {
+        self.limiter = Some(FrameLimiter::default());
+        self
+    }
+    // Shows lines of text under the screen all the time, like the splash screen's hint.
+    pub fn footer(mut self, lines: &[&str]) -> Self {
+        self.footer = lines.iter().map(|line| line.to_string()).collect();
+        self
+    }
+    // Prints the speed statistics to stderr once a second while the machine runs.
+    pub fn log_timing(mut self) -> Self {
+        self.log_timing = true;
+        self
+    }
+    // Makes pixels fade out over frames frames rather than going out at once, to hide flicker. 0 or 1 turns it off.
+    pub fn phosphor(mut self, frames: u8) -> Self {
+        self.phosphor = (frames > 1).then(|| Phosphor::new(frames));
+        self
+    }
+    // Labels the keypad overlay's keys with keymap's physical keys, for a keymap with keys rebound.
+    pub fn keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+    // Plays the buzzer through audio the way this display's sound says, as it's changed with keys (see set_sound).
+    pub fn audio(mut self, audio: &SdlAudio) -> Self {
+        self.sounds = Some(audio.sounds.0.clone());
+        self
+    }
+}
+
+impl SdlDisplay<'_> {
+    // Where an SdlInput sends this display the events it handles itself, like F3 showing the debug overlay, and its
+    // memory editor whenever that changes. They're handled as the next frame is presented.
+    pub fn events(&self) -> Sender<DisplayEvent> {
+        self.events.0.clone()
+    }
+    // Shows or hides the debug overlay (F3 toggles it).
+    pub fn set_debug_hud(&mut self, visible: bool) {
+        self.debug_hud = visible;
+    }
+    pub fn debug_hud(&self) -> bool {
+        self.debug_hud
+    }
+    // Shows or hides the keypad overlay (F1 toggles it).
+    pub fn set_keypad_overlay(&mut self, visible: bool) {
+        self.keypad_overlay = visible;
+    }
+    pub fn keypad_overlay(&self) -> bool {
+        self.keypad_overlay
+    }
+    // Switches to the layout'th of the LAYOUTS of debugging panels, wrapping around past the last one (F6 cycles through
+    // them). The first has no panels.
+    pub fn set_debug_layout(&mut self, layout: usize) {
+        self.debug_layout = layout % LAYOUTS.len();
+    }
+    pub fn debug_layout(&self) -> usize {
+        self.debug_layout
+    }
+    // Shows or hides the speed statistics (F2 toggles them).
+    pub fn set_stats_overlay(&mut self, visible: bool) {
+        self.stats_overlay = visible;
+    }
+    pub fn stats_overlay(&self) -> bool {
+        self.stats_overlay
+    }
+    // Draws lines between the screen's pixels, to make out sprites pixel by pixel (F7 toggles it).
+    pub fn set_pixel_grid(&mut self, pixel_grid: bool) {
+        self.pixel_grid = pixel_grid;
+        self.redraw = true;
+    }
+    pub fn pixel_grid(&self) -> bool {
+        self.pixel_grid
+    }
+    // Scales the screen up with linear filtering instead of keeping its pixels square and sharp (Shift+F9 toggles it).
+    // Screenshots and GIFs are made from the screen itself, so they stay sharp either way.
+    pub fn set_smooth_scaling(&mut self, smooth: bool) {
+        self.smooth = smooth;
+        self.redraw = true;
+    }
+    pub fn smooth_scaling(&self) -> bool {
+        self.smooth
+    }
+    // Draws the screen in palette's colors (F8 cycles through the THEMES). The window is drawn again in them straight away.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.redraw = true;
+    }
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+    // Sets how the buzzer sounds (Shift+F8 cycles through the WAVEFORMS, [ and ] turn it down and up, and M mutes it),
+    // and passes it on to the audio it plays through.
+    pub fn set_sound(&mut self, sound: Sound) {
+        self.sound = sound;
+        if let Some(sounds) = &self.sounds {
+            let _ = sounds.send(sound);
+        }
+    }
+    pub fn sound(&self) -> Sound {
+        self.sound
+    }
+    // Sets the filter drawn over the screen (F9 cycles through them). The window is drawn again with it straight away.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.redraw = true;
+    }
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+    // Makes the window fill the screen, or go back to the size and place it was (F11 toggles it), as the next frame is
+    // presented.
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.fullscreen = fullscreen;
+    }
+    pub fn fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+    // Handles the events SdlInput sent since the last frame.
+    fn handle_events(&mut self) {
+        while let Ok(event) = self.events.1.try_recv() {
+            match event {
+                DisplayEvent::Input(InputEvent::ToggleDebugHud) => {
+                    self.set_debug_hud(!self.debug_hud)
+                }
+                DisplayEvent::Input(InputEvent::ToggleKeypad) => {
+                    self.set_keypad_overlay(!self.keypad_overlay)
+                }
+                DisplayEvent::Input(InputEvent::NextLayout) => {
+                    self.set_debug_layout(self.debug_layout + 1)
+                }
+                DisplayEvent::Input(InputEvent::ToggleStats) => {
+                    self.set_stats_overlay(!self.stats_overlay)
+                }
+                DisplayEvent::Input(InputEvent::NextTheme) => {
+                    self.set_palette(self.palette.next_theme());
+                    let name: &str = self.palette.theme_name().unwrap_or("custom colors");
+                    self.osd
+                        .show(format!("THEME: {}", name.to_uppercase()), Instant::now());
+                }
+                DisplayEvent::Input(InputEvent::NextWaveform) => {
+                    let waveform: Waveform = self.sound.waveform.next();
+                    self.set_sound(Sound {
+                        waveform,
+                        ..self.sound
+                    });
+                    self.osd.show(
+                        format!("WAVE: {}", waveform.name().to_uppercase()),
+                        Instant::now(),
+                    );
+                }
+                DisplayEvent::Input(event @ (InputEvent::VolumeUp | InputEvent::VolumeDown)) => {
+                    let volume: u8 = if event == InputEvent::VolumeUp {
+                        self.sound
+                            .volume
+                            .saturating_add(VOLUME_STEP)
+                            .min(MAX_VOLUME)
+                    } else {
+                        self.sound.volume.saturating_sub(VOLUME_STEP)
+                    };
+                    if volume != self.sound.volume {
+                        self.set_sound(Sound {
+                            volume,
+                            ..self.sound
+                        });
+                        self.osd
+                            .show(format!("VOLUME: {}%", volume), Instant::now());
+                    }
+                }
+                DisplayEvent::Input(InputEvent::ToggleMute) => {
+                    let muted: bool = !self.sound.muted;
+                    self.set_sound(Sound {
+                        muted,
+                        ..self.sound
+                    });
+                    self.osd
+                        .show(if muted { "MUTED" } else { "UNMUTED" }, Instant::now());
+                }
+                DisplayEvent::Input(InputEvent::NextFilter) => {
+                    self.set_filter(self.filter.next());
+                    let filter: String = self.filter.to_string().to_uppercase();
+                    self.osd.show(format!("FILTER: {}", filter), Instant::now());
+                }
+                DisplayEvent::Input(InputEvent::ToggleSmoothScaling) => {
+                    self.set_smooth_scaling(!self.smooth);
+                    let scaling: &str = if self.smooth { "SMOOTH" } else { "SHARP" };
+                    self.osd
+                        .show(format!("SCALING: {}", scaling), Instant::now());
+                }
+                DisplayEvent::Input(InputEvent::ToggleFullscreen) => {
+                    self.set_fullscreen(!self.fullscreen)
+                }
+                DisplayEvent::Input(InputEvent::Screenshot) => self.screenshot_requested = true,
+                DisplayEvent::Input(InputEvent::Dump) => self.dump_requested = true,
+                DisplayEvent::Input(InputEvent::ToggleRecording) => self.recording_toggled = true,
+                DisplayEvent::Input(InputEvent::TogglePixelGrid) => {
+                    self.set_pixel_grid(!self.pixel_grid)
+                }
+                DisplayEvent::Input(_) => {}
+                DisplayEvent::MemoryEditor(editor) => self.memory_editor = editor,
+            }
+        }
+    }
+    // Draws a frame published by an EmulatorHandle, for frontends running the emulator on another thread.
+    pub fn present_frame(&mut self, frame: &Frame) -> Result<(), String> {
+        let palette: Palette = self.palette;
+        self.draw(
+            (frame.width, frame.height),
+            |out| {
+                fill_rgba(out, frame.width, |x, y| frame.pixel(x, y), palette);
+                Ok(())
+            },
+            frame.dirty.is_some(),
+            self.filter,
+            false,
+        )
+    }
+    // Draws two machines next to each other with a gap between them, for comparing quirk profiles.
+    pub fn present_side_by_side(
+        &mut self,
+        left: &ChipEight,
+        right: &ChipEight,
+    ) -> Result<(), String> {
+        const GAP: usize = 2;
+        self.handle_events();
+        self.show_fullscreen()?;
+        let (left_width, left_height) = left.resolution();
+        let (right_width, right_height) = right.resolution();
+        let right_x: usize = left_width + GAP;
+        let (left_screen, right_screen) = (left.framebuffer(), right.framebuffer());
+        let pixel = |x: usize, y: usize| -> bool {
+            if x < left_width {
+                y < left_height && left_screen[y][x]
+            } else if x >= right_x {
+                y < right_height && right_screen[y][x - right_x]
+            } else {
+                false
+            }
+        };
+        let palette: Palette = self.palette;
+
+        self.draw(
+            (right_x + right_width, left_height.max(right_height)),
+            |out| {
+                fill_rgba(out, right_x + right_width, pixel, palette);
+                Ok(())
+            },
+            left.dirty_region().is_some() || right.dirty_region().is_some(),
+            self.filter,
+            self.pixel_grid,
+        )
+    }
+    // Shows a message when the program pauses or resumes, however that happened. The first frame just notes which it is.
+    fn notice(&mut self, chip_eight: &ChipEight, now: Instant) {
+        let paused: bool = chip_eight.paused_at().is_some();
+        if self.paused.is_some_and(|shown| shown != paused) {
+            self.osd
+                .show(if paused { "PAUSED" } else { "RESUMED" }, now);
+        }
+        self.paused = Some(paused);
+    }
+    // Adds how fast the machine runs to the window title, e.g. "60fps 100%", or where it's paused (and which watchpoint
+    // it hit, if that's why) while it is, since the screen itself belongs to the program. The speed is the frames drawn a
+    // second, and how fast the timers tick next to the 60Hz they should. "(muted)" goes in front while the buzzer is.
+    fn show_status(&mut self, chip_eight: &ChipEight, now: Instant) -> Result<(), String> {
+        if self
+            .speed
+            .as_ref()
+            .is_none_or(|(_, at)| now.duration_since(*at) >= STATS_WINDOW)
+        {
+            let report: StatsReport = self.stats.report();
+            let speed: String = format!(
+                "{:.0}fps {:.0}%",
+                report.frames_per_second,
+                report.timer_hz / 60.0 * 100.0
+            );
+            self.speed = (report != StatsReport::default()).then_some((speed, now));
+        }
+        let status: Option<String> = match chip_eight.paused_at() {
+            Some(addr) => Some(
+                match chip_eight.watch_hit() {
+                    Some(hit) => format!("(paused at {:#05X}: {})", addr, hit),
+                    None => format!("(paused at {:#05X})", addr),
+                } + " — F5 to resume, N to step, O to step over",
+            ),
+            None => self.speed.as_ref().map(|(speed, _)| speed.clone()),
+        };
+        let status: Option<String> = if self.sound.muted {
+            Some(match status {
+                Some(status) => format!("(muted) {}", status),
+                None => "(muted)".to_string(),
+            })
+        } else {
+            status
+        };
+        let window: &mut Window = self.canvas.window_mut();
+
+        let base: String = match self.title.take() {
+            Some((base, shown)) if shown == window.title() => base,
+            _ => window.title().to_string(),
+        };
+        let title: String = match status {
+            Some(status) => format!("{} {}", base, status),
+            None => base.clone(),
+        };
+        if title != window.title() {
+            window.set_title(&title).map_err(|e| e.to_string())?;
+        }
+        self.title = Some((base, title));
+        Ok(())
+    }
+    // Draws the screen with lines of overlay text underneath it and the message over it. The text needs more pixels than
+    // a lores screen has, so the screen is scaled up to HUD_WIDTH wide in logical pixels and the lines get a margin below
+    // it (if there are any). Of the marks, the cursor is drawn inverted, and edited bytes underlined. The screen's texture is only refilled when the screen changed, and if the text didn't change either
+    // the frame isn't drawn at all, which is most frames while paused. The panels go wherever debug_layout::layout()
+    // puts them for the window's shape, with the screen and its overlay (the game) moved out of their way.
+    fn draw_with_hud(
+        &mut self,
+        chip_eight: &ChipEight,
+        hud: &Hud,
+        fill: impl FnOnce(&mut [u8]) -> Result<(), String>,
+        changed: bool,
+    ) -> Result<(), String> {
+        const HUD_WIDTH: usize = 256;
+        const MARGIN: usize = 2;
+        let (width, height) = chip_eight.resolution();
+        let scale: usize = HUD_WIDTH / width;
+        let text_y: usize = height * scale + 1 + MARGIN;
+        let game: (usize, usize) = match hud.lines.len() {
+            0 => (HUD_WIDTH, height * scale),
+            lines => (HUD_WIDTH, text_y + lines * LINE_HEIGHT + MARGIN),
+        };
+        let (output_width, output_height) = self.canvas.output_size()?;
+        let (window_width, window_height) = self.aspect.unstretch(
+            self.rotation
+                .size((output_width as usize, output_height as usize)),
+        );
+        let panels: Vec<(usize, usize)> =
+            hud.panels.iter().map(|(panel, _)| panel.size()).collect();
+        let layout: Layout =
+            debug_layout::layout((window_width as u32, window_height as u32), game, &panels);
+        let size: (usize, usize) = layout.size;
+        // The game is drawn as if it were in the top left corner, and moved to where the layout has it.
+        let (game_x, game_y) = (layout.game.x as i32, layout.game.y as i32);
+        let place = |rect: Rect| {
+            Rect::new(
+                rect.x() + game_x,
+                rect.y() + game_y,
+                rect.width(),
+                rect.height(),
+            )
+        };
+
+        let resized: bool = self.set_logical_size(size)?;
+        let changed: bool = changed || std::mem::take(&mut self.redraw);
+        self.update_texture((width, height), fill, changed)?;
+        if !resized && !changed && self.hud == *hud {
+            self.end_frame(false);
+            return Ok(());
+        }
+        self.hud = hud.clone();
+
+        let [fg_r, fg_g, fg_b] = self.palette.foreground;
+        let [bg_r, bg_g, bg_b] = self.palette.background;
+        self.clear(self.palette.background)?;
+        let screen: Rect = place(Rect::new(
+            0,
+            0,
+            (width * scale) as u32,
+            (height * scale) as u32,
+        ));
+        if let Some((texture, _, _)) = &self.texture {
+            copy_turned(
+                self.canvas,
+                texture,
+                screen,
+                self.rotation,
+                self.aspect,
+                size,
+            )?;
+        }
+        self.draw_filter(self.filter, self.pixel_grid, screen, (width, height))?;
+
+        // A line between the screen and the overlay, so it's clear where the program's pixels end.
+        let mut rects: Vec<Rect> = vec![];
+        if !hud.lines.is_empty() {
+            rects.push(Rect::new(0, (height * scale) as i32, HUD_WIDTH as u32, 1));
+        }
+        for (row, line) in hud.lines.iter().enumerate() {
+            push_text(
+                &mut rects,
+                line,
+                MARGIN,
+                text_y + row * LINE_HEIGHT,
+                HUD_WIDTH,
+            );
+        }
+        // Inverted text is a solid block with the characters cut out of it in the background color.
+        let mut inverted: Vec<Rect> = vec![];
+        for &(row, column, chars, mark) in &hud.marks {
+            let x: usize = MARGIN + column * CHAR_ADVANCE;
+            let y: usize = text_y + row * LINE_HEIGHT;
+            let width: u32 = (chars * CHAR_ADVANCE - 1) as u32;
+            match mark {
+                EditorMark::Cursor => {
+                    rects.push(Rect::new(
+                        x as i32 - 1,
+                        y as i32 - 1,
+                        width + 2,
+                        LINE_HEIGHT as u32 + 1,
+                    ));
+                    let text: String = hud.lines[row].chars().skip(column).take(chars).collect();
+                    push_text(&mut inverted, &text, x, y, HUD_WIDTH);
+                }
+                EditorMark::Edited => {
+                    rects.push(Rect::new(x as i32, (y + GLYPH_HEIGHT) as i32, width, 1))
+                }
+            }
+        }
+        // Each message goes on a patch of plain background in the corner, so the screen doesn't get in the way of reading
+        // it. They're stacked up from the bottom, newest last.
+        for (row, message) in hud.messages.iter().enumerate() {
+            let from_bottom: usize = hud.messages.len() - row;
+            let top: usize =
+                (height * scale).saturating_sub(from_bottom * (LINE_HEIGHT + MARGIN) + MARGIN);
+            let message_width: usize =
+                (message.chars().count() * CHAR_ADVANCE + MARGIN * 2).min(HUD_WIDTH);
+            self.canvas.set_draw_color(Color::RGB(bg_r, bg_g, bg_b));
+            self.fill_rects(&[place(Rect::new(
+                0,
+                top as i32,
+                message_width as u32,
+                (LINE_HEIGHT + MARGIN * 2) as u32,
+            ))])?;
+            push_text(&mut rects, message, MARGIN, top + MARGIN, HUD_WIDTH);
+        }
+        // The keypad goes on a patch of background too, with held keys inverted.
+        let keypad: Option<KeypadLayout> = hud
+            .keypad
+            .as_ref()
+            .and_then(|_| keypad_layout((width * scale, height * scale)));
+        if let (Some(view), Some(keypad)) = (&hud.keypad, &keypad) {
+            self.canvas.set_draw_color(Color::RGB(bg_r, bg_g, bg_b));
+            self.fill_rects(&[place(area_rect(&keypad.area))])?;
+            rects.extend(keypad.lines.iter().map(area_rect));
+            for cell in &keypad.cells {
+                let key: usize = cell.key as usize;
+                let text: &mut Vec<Rect> = if view.inverted[key] {
+                    rects.push(area_rect(&cell.area));
+                    &mut inverted
+                } else {
+                    &mut rects
+                };
+                let right: usize = cell.area.x + cell.area.width;
+                let (x, y) = cell.text_origin(0, 1);
+                push_text(text, &format!("{:X}", key), x, y, right);
+                let label: &str = &view.labels[key];
+                let (x, y) = cell.text_origin(1, label.chars().count());
+                push_text(text, label, x, y, right);
+            }
+        }
+        let mut rects: Vec<Rect> = rects.into_iter().map(place).collect();
+        let inverted: Vec<Rect> = inverted.into_iter().map(place).collect();
+
+        // The panels are drawn where they are, with lines between them and the game.
+        rects.extend(layout.borders.iter().map(area_rect));
+        for ((_, lines), area) in hud.panels.iter().zip(&layout.panels) {
+            let (x, y) = area.text_origin();
+            for (row, line) in lines.iter().enumerate() {
+                push_text(
+                    &mut rects,
+                    line,
+                    x,
+                    y + row * LINE_HEIGHT,
+                    area.x + area.width,
+                );
+            }
+        }
+        self.canvas.set_draw_color(Color::RGB(fg_r, fg_g, fg_b));
+        self.fill_rects(&rects)?;
+        if !inverted.is_empty() {
+            self.canvas.set_draw_color(Color::RGB(bg_r, bg_g, bg_b));
+            self.fill_rects(&inverted)?;
+        }
+        self.canvas.present();
+        self.end_frame(true);
+        Ok(())
+    }
+    // Draws the screen the program failed on, dimmed, with the error over it: what went wrong, the opcode and registers
+    // at the PC it failed at, and the keys for error_screen().
+    pub fn present_error(
+        &mut self,
+        chip_eight: &ChipEight,
+        error: &ChipEightError,
+    ) -> Result<(), String> {
+        const WIDTH: usize = 256;
+        const MARGIN: usize = 2;
+        let (width, height) = chip_eight.resolution();
+        let scale: usize = WIDTH / width;
+        let memory: &[u8] = chip_eight.memory();
+        let pc: usize = error.pc().unwrap_or(chip_eight.pc()) as usize;
+        let opcode: u16 =
+            u16::from_be_bytes([memory[pc % memory.len()], memory[(pc + 1) % memory.len()]]);
+        let registers: String = chip_eight
+            .v_registers()
+            .iter()
+            .map(|v| format!(" {:02X}", v))
+            .collect();
+        let lines: [String; 5] = [
+            error.to_string(),
+            format!(
+                "PC {:04X}  OPCODE {:04X}  I {:04X}  SP {}",
+                pc,
+                opcode,
+                chip_eight.i_register(),
+                chip_eight.stack().len()
+            ),
+            format!("V0-VF{}", registers),
+            String::new(),
+            "F5: RESET  ESC: QUIT".to_string(),
+        ];
+
+        self.set_logical_size((WIDTH, height * scale))?;
+        let [fg_r, fg_g, fg_b] = self.palette.foreground;
+        let [bg_r, bg_g, bg_b] = self.palette.background;
+        let dim = |fg: u8, bg: u8| ((fg as u16 + bg as u16 * 3) / 4) as u8;
+        self.clear(self.palette.background)?;
+
+        let framebuffer: &[[bool; 128]] = chip_eight.framebuffer();
+        let mut pixels: Vec<Rect> = vec![];
+        for (y, row) in framebuffer.iter().take(height).enumerate() {
+            for x in (0..width).filter(|&x| row[x]) {
+                pixels.push(Rect::new(
+                    (x * scale) as i32,
+                    (y * scale) as i32,
+                    scale as u32,
+                    scale as u32,
+                ));
+            }
+        }
+        self.canvas.set_draw_color(Color::RGB(
+            dim(fg_r, bg_r),
+            dim(fg_g, bg_g),
+            dim(fg_b, bg_b),
+        ));
+        self.fill_rects(&pixels)?;
+
+        // The text goes on a patch of plain background, so the dimmed pixels don't get in the way of reading it.
+        let text_height: usize = lines.len() * LINE_HEIGHT + MARGIN * 2;
+        self.canvas.set_draw_color(Color::RGB(bg_r, bg_g, bg_b));
+        self.fill_rects(&[Rect::new(0, 0, WIDTH as u32, text_height as u32)])?;
+        let mut text: Vec<Rect> = vec![Rect::new(0, text_height as i32, WIDTH as u32, 1)];
+        for (row, line) in lines.iter().enumerate() {
+            push_text(&mut text, line, MARGIN, MARGIN + row * LINE_HEIGHT, WIDTH);
+        }
+        self.canvas.set_draw_color(Color::RGB(fg_r, fg_g, fg_b));
+        self.fill_rects(&text)?;
+        self.canvas.present();
+        // The overlay was drawn over, so it has to be drawn again next time.
+        self.hud = Hud::default();
+        self.end_frame(true);
+        Ok(())
+    }
+    // Presents the screen's texture stretched over the window, after refilling it with fill (which gets a buffer of RGBA
+    // pixels the size of the screen) if the screen changed. If nothing changed, this just waits out the frame instead.
+    fn draw(
+        &mut self,
+        size: (usize, usize),
+        fill: impl FnOnce(&mut [u8]) -> Result<(), String>,
+        changed: bool,
+        filter: Filter,
+        grid: bool,
+    ) -> Result<(), String> {
+        let changed: bool = changed || std::mem::take(&mut self.redraw);
+        let resized: bool = self.set_logical_size(size)?;
+        self.update_texture(size, fill, changed)?;
+        if !resized && !changed {
+            self.end_frame(false);
+            return Ok(());
+        }
+
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+        let screen: Rect = Rect::new(0, 0, size.0 as u32, size.1 as u32);
+        if let Some((texture, _, _)) = &self.texture {
+            copy_turned(
+                self.canvas,
+                texture,
+                screen,
+                self.rotation,
+                self.aspect,
+                size,
+            )?;
+        }
+        self.draw_filter(filter, grid, screen, size)?;
+        self.canvas.present();
+        self.end_frame(true);
+        Ok(())
+    }
+    // Refills the texture with fill if the screen changed, or if the texture has to be (re)created at a new size or to
+    // scale differently, which is also when it's first drawn.
+    fn update_texture(
+        &mut self,
+        (width, height): (usize, usize),
+        fill: impl FnOnce(&mut [u8]) -> Result<(), String>,
+        changed: bool,
+    ) -> Result<(), String> {
+        let smooth: bool = self.smooth;
+        let created: bool = !matches!(&self.texture,
+            Some((_, size, made_smooth)) if *size == (width, height) && *made_smooth == smooth);
+        if created {
+            // Nearest neighbor scaling keeps the pixels crisp, unless they're asked to be smoothed. SDL only looks at the
+            // hint when a texture is created, and it's put back after, so the filter overlays stay crisp either way.
+            sdl2::hint::set(
+                "SDL_RENDER_SCALE_QUALITY",
+                if smooth { "linear" } else { "nearest" },
+            );
+            let texture: Result<Texture<'_>, String> = self
+                .texture_creator
+                .create_texture_streaming(PixelFormatEnum::RGBA32, width as u32, height as u32)
+                .map_err(|e| e.to_string());
+            sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "nearest");
+            self.texture = Some((texture?, (width, height), smooth));
+        } else if !changed {
+            return Ok(());
+        }
+        self.pixels.resize(width * height * 4, 0);
+        fill(&mut self.pixels)?;
+
+        let (texture, _, _) = self.texture.as_mut().expect("the texture was just created");
+        texture
+            .update(None, &self.pixels, width * 4)
+            .map_err(|e| e.to_string())
+    }
+    // Fills rects, which are where they'd be the right way up and unstretched, in the draw color, wherever the pixel
+    // aspect and rotation put them.
+    fn fill_rects(&mut self, rects: &[Rect]) -> Result<(), String> {
+        let size: (usize, usize) = self.aspect.size(self.logical_size.unwrap_or_default());
+        let turned: Vec<Rect> = rects
+            .iter()
+            .map(|rect| self.rotation.rect(self.aspect.rect(*rect), size))
+            .collect();
+        self.canvas.fill_rects(&turned)
+    }
+    // Clears the window to black, which leaves bars around the logical area where the window's shape doesn't fit it, and
+    // the logical area itself to background.
+    fn clear(&mut self, [r, g, b]: [u8; 3]) -> Result<(), String> {
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+        self.canvas.set_draw_color(Color::RGB(r, g, b));
+        self.canvas.fill_rect(None)
+    }
+    // Saves a screenshot or writes a dump if one was asked for, starts or stops recording a GIF if that was asked for,
+    // and adds the frame to the GIF being recorded, if it's time for another. What was saved is shown over the screen.
+    fn capture(&mut self, chip_eight: &ChipEight, now: Instant) {
+        if std::mem::take(&mut self.screenshot_requested) {
+            let (dir, scale) = &self.screenshots;
+            let scales: (u32, u32) = if self.screenshot_aspect {
+                (scale * self.aspect.width(), scale * self.aspect.height())
+            } else {
+                (*scale, *scale)
+            };
+            let message: String = match chip_eight.save_screenshot_scaled(self.palette, dir, scales)
+            {
+                Ok(path) => {
+                    println!("Saved screenshot {}!", path.display());
+                    format!("SAVED {}", path.display())
+                }
+                Err(e) => {
+                    eprintln!("Error saving screenshot: {}", e);
+                    format!("SCREENSHOT FAILED: {}", e)
+                }
+            };
+            self.osd.show(message, now);
+        }
+        // Dumps go to the current directory, for bug reports.
+        if std::mem::take(&mut self.dump_requested) {
+            let message: String = match chip_eight.crash_dump(None).write_to(Path::new(".")) {
+                Ok(path) => {
+                    println!("Wrote a dump to {}!", path.display());
+                    format!("DUMPED {}", path.display())
+                }
+                Err(e) => {
+                    eprintln!("Error writing a dump: {}", e);
+                    format!("DUMP FAILED: {}", e)
+                }
+            };
+            self.osd.show(message, now);
+        }
+        if std::mem::take(&mut self.recording_toggled) {
+            if self.recording.is_some() {
+                self.stop_recording(now);
+            } else {
+                let ((dir, _), (scale, fps)) = (&self.screenshots, self.gifs);
+                match GifRecorder::start(chip_eight, self.palette, dir, scale, fps) {
+                    Ok(recording) => {
+                        println!("Recording {}...", recording.path().display());
+                        self.osd.show("RECORDING", now);
+                        self.recording = Some(recording);
+                    }
+                    Err(e) => {
+                        eprintln!("Error recording a GIF: {}", e);
+                        self.osd.show(format!("RECORDING FAILED: {}", e), now);
+                    }
+                }
+            }
+        }
+        let palette: Palette = self.palette;
+        let captured: Option<io::Result<bool>> = self
+            .recording
+            .as_mut()
+            .map(|recording| recording.capture(chip_eight, palette));
+        match captured {
+            Some(Ok(false)) => self.stop_recording(now),
+            Some(Err(e)) => {
+                eprintln!("Error recording a GIF: {}", e);
+                self.osd.show(format!("RECORDING FAILED: {}", e), now);
+                self.recording = None;
+            }
+            Some(Ok(true)) | None => {}
+        }
+    }
+    // Finishes the GIF being recorded, if there is one.
+    fn stop_recording(&mut self, now: Instant) {
+        let message: String = match self.recording.take().map(GifRecorder::finish) {
+            Some(Ok(path)) => {
+                println!("Saved recording {}!", path.display());
+                format!("SAVED {}", path.display())
+            }
+            Some(Err(e)) => {
+                eprintln!("Error recording a GIF: {}", e);
+                format!("RECORDING FAILED: {}", e)
+            }
+            None => return,
+        };
+        self.osd.show(message, now);
+    }
+    // Takes the window into desktop fullscreen, or back to the size and position it had before, if it isn't already.
+    fn show_fullscreen(&mut self) -> Result<(), String> {
+        let fullscreen: bool = self.fullscreen;
+        let window: &mut Window = self.canvas.window_mut();
+        match (fullscreen, self.windowed) {
+            (true, None) => {
+                self.windowed = Some((window.size(), window.position()));
+                window.set_fullscreen(FullscreenType::Desktop)?;
+            }
+            (false, Some(((width, height), (x, y)))) => {
+                window.set_fullscreen(FullscreenType::Off)?;
+                window.set_size(width, height).map_err(|e| e.to_string())?;
+                window.set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+                self.windowed = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    // Sets the canvas's logical size, if it isn't that already. SDL centres it in the window, scaled up by a whole number
+    // (unless fill is set), so the pixels (and the overlay's text) all come out the same size, with black bars around it.
+    // The size given is unstretched; the canvas's is stretched by the pixel aspect, so a logical pixel is a whole number
+    // of the canvas's each way, and integer scaling keeps them all the same size.
+    // Returns whether it changed, or the window was resized (or went fullscreen) since the last frame, in which case
+    // everything has to be drawn again. Window resize events aren't watched for, since this catches them all.
+    fn set_logical_size(&mut self, (width, height): (usize, usize)) -> Result<bool, String> {
+        let output_size: (u32, u32) = self.canvas.output_size()?;
+        let window_resized: bool = self.output_size != Some(output_size);
+        self.output_size = Some(output_size);
+        if self.logical_size == Some((width, height)) {
+            return Ok(window_resized);
+        }
+        let (turned_width, turned_height) = self.rotation.size(self.aspect.size((width, height)));
+        self.canvas
+            .set_logical_size(turned_width as u32, turned_height as u32)
+            .map_err(|e| e.to_string())?;
+        self.canvas.set_integer_scale(!self.fill)?;
+        self.logical_size = Some((width, height));
+        Ok(true)
+    }
+    // Draws filter (and the pixel grid, if grid is set) over dst, the part of the canvas a screen of screen pixels was
+    // drawn to. The overlay is made at the size dst really covers in the window, so each of its pixels is one of the
+    // window's and the grid's lines are hairlines, and made again when that changes. Since dst is in logical pixels, SDL
+    // puts the overlay wherever it puts the screen, bars and all.
+    fn draw_filter(
+        &mut self,
+        filter: Filter,
+        grid: bool,
+        dst: Rect,
+        screen: (usize, usize),
+    ) -> Result<(), String> {
+        let (logical_width, logical_height) = match (filter, grid, self.logical_size) {
+            (Filter::None, false, _) | (_, _, None) => return Ok(()),
+            (_, _, Some(size)) => size,
+        };
+        let scale: f64 = logical_scale(
+            self.canvas.output_size()?,
+            self.rotation
+                .size(self.aspect.size((logical_width, logical_height))),
+            self.fill,
+        );
+        let stretched: Rect = self.aspect.rect(dst);
+        let size: (u32, u32) = (
+            (stretched.width() as f64 * scale) as u32,
+            (stretched.height() as f64 * scale) as u32,
+        );
+        if size.0 == 0 || size.1 == 0 {
+            return Ok(());
+        }
+
+        let made: bool = matches!(&self.filter_overlay, Some(overlay)
+            if overlay.filter == filter && overlay.grid == grid && overlay.size == size && overlay.screen == screen);
+        if !made {
+            let mut texture: Texture<'_> = self
+                .texture_creator
+                .create_texture_static(PixelFormatEnum::RGBA32, size.0, size.1)
+                .map_err(|e| e.to_string())?;
+            texture.set_blend_mode(BlendMode::Blend);
+            texture
+                .update(
+                    None,
+                    &filter_pixels(filter, grid, size, screen),
+                    size.0 as usize * 4,
+                )
+                .map_err(|e| e.to_string())?;
+            self.filter_overlay = Some(FilterOverlay {
+                texture,
+                filter,
+                grid,
+                size,
+                screen,
+            });
+        }
+        let overlay: &FilterOverlay = self
+            .filter_overlay
+            .as_ref()
+            .expect("the overlay was just made");
+        copy_turned(
+            self.canvas,
+            &overlay.texture,
+            dst,
+            self.rotation,
+            self.aspect,
+            (logical_width, logical_height),
+        )
+    }
+    // Finishes a frame, presented or not, and waits until the next one is due. With vsync, presenting already waited; a
+    // frame with nothing new to show isn't presented (SDL keeps showing the last one), so there's no vsync to keep time by
+    // and it waits out the rest of the frame itself.
+    fn end_frame(&mut self, presented: bool) {
+        if presented {
+            self.presents += 1;
+        }
+        match (&mut self.limiter, self.last_present) {
+            (Some(limiter), _) => limiter.wait(),
+            (None, Some(last_present)) if !presented => {
+                std::thread::sleep(FRAME_TIME.saturating_sub(last_present.elapsed()));
+            }
+            _ => {}
+        }
+        self.last_present = Some(Instant::now());
+    }
+}
+
+// Writes the machine's screen to out as RGBA pixels, in palette's colors, and with fading pixels if phosphor is given.
+fn render_chip_eight(
+    chip_eight: &ChipEight,
+    palette: Palette,
+    phosphor: Option<&Phosphor>,
+    out: &mut [u8],
+) -> Result<(), String> {
+    match phosphor {
+        Some(phosphor) => phosphor.render_to_rgba(palette, out),
+        None => {
+            chip_eight.render_to_rgba(palette.foreground_rgba(), palette.background_rgba(), out)
+        }
+    }
+    .map_err(|e| e.to_string())
+}
+
+// Copies texture to dst, where it would be in a picture of size the right way up and unstretched, stretched by aspect
+// and turned with the picture. copy_ex turns about the middle of where it's given, so that's put where the middle of the
+// turned rect is.
+fn copy_turned(
+    canvas: &mut Canvas<Window>,
+    texture: &Texture,
+    dst: Rect,
+    rotation: Rotation,
+    aspect: PixelAspect,
+    size: (usize, usize),
+) -> Result<(), String> {
+    let dst: Rect = aspect.rect(dst);
+    let turned: Rect = rotation.rect(dst, aspect.size(size));
+    let dst: Rect = Rect::from_center(turned.center(), dst.width(), dst.height());
+    canvas.copy_ex(texture, None, dst, rotation.degrees(), None, false, false)
+}
+
+// How many of the window's real pixels SDL draws each logical pixel with: as many as fit, by a whole number unless the
+// logical size fills the window. output is the canvas's output_size(), which on a high-DPI display is bigger than the
+// window's size() (in screen coordinates), and which has to be used here for the result to be in real pixels.
+fn logical_scale(
+    (output_width, output_height): (u32, u32),
+    (logical_width, logical_height): (usize, usize),
+    fill: bool,
+) -> f64 {
+    let scale: f64 = (output_width as f64 / logical_width as f64)
+        .min(output_height as f64 / logical_height as f64);
+    if fill {
+        scale
+    } else {
+        scale.floor().max(1.0)
+    }
+}
+
+// A filter's overlay as RGBA pixels: black, with how much it darkens the pixel under it in the alpha. Scanlines need at
+// least two of the window's rows for each of the screen's, and the grid three, or they'd just muddy the picture, so a
+// window too small for them gets a blank overlay. The pixel grid is darker, to pick pixels out rather than for looks,
+// and needs four, or it would swallow the pixels between its lines.
+fn filter_pixels(
+    filter: Filter,
+    grid: bool,
+    (width, height): (u32, u32),
+    (screen_width, screen_height): (usize, usize),
+) -> Vec<u8> {
+    const SCANLINE_ALPHA: u8 = 0x60;
+    const GRID_ALPHA: u8 = 0x30;
+    const PIXEL_GRID_ALPHA: u8 = 0x90;
+    let (width, height) = (width as usize, height as usize);
+    // Whether the i'th of len window pixels is the first one a screen pixel covers.
+    let starts_pixel =
+        |i: usize, len: usize, pixels: usize| i == 0 || i * pixels / len != (i - 1) * pixels / len;
+
+    let pixel_grid: bool = grid && width >= screen_width * 4 && height >= screen_height * 4;
+
+    let mut out: Vec<u8> = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let alpha: u8 = match filter {
+                // Only between pixels, not around the edge of the screen.
+                _ if pixel_grid
+                    && ((x > 0 && starts_pixel(x, width, screen_width))
+                        || (y > 0 && starts_pixel(y, height, screen_height))) =>
+                {
+                    PIXEL_GRID_ALPHA
+                }
+                Filter::Scanlines if height >= screen_height * 2 && y % 2 == 1 => SCANLINE_ALPHA,
+                Filter::Grid
+                    if width >= screen_width * 3
+                        && height >= screen_height * 3
+                        && (starts_pixel(x, width, screen_width)
+                            || starts_pixel(y, height, screen_height)) =>
+                {
+                    GRID_ALPHA
+                }
+                _ => 0,
+            };
+            out.extend_from_slice(&[0, 0, 0, alpha]);
+        }
+    }
+    out
+}
+
+// Writes the colors of a screen that isn't a ChipEight's (which has render_to_rgba) to out as RGBA, row by row.
+fn fill_rgba(out: &mut [u8], width: usize, pixel: impl Fn(usize, usize) -> bool, palette: Palette) {
+    let (fg, bg) = (palette.foreground_rgba(), palette.background_rgba());
+    for (i, color) in out.chunks_exact_mut(4).enumerate() {
+        color.copy_from_slice(if pixel(i % width, i / width) {
+            &fg
+        } else {
+            &bg
+        });
+    }
+}
+
+// The rect covering area.
+fn area_rect(area: &Area) -> Rect {
+    Rect::new(
+        area.x as i32,
+        area.y as i32,
+        area.width as u32,
+        area.height as u32,
+    )
+}
+
+// Adds the pixels of a line of text with its top left corner at (left, top), cutting it off at max_width.
+fn push_text(rects: &mut Vec<Rect>, text: &str, left: usize, top: usize, max_width: usize) {
+    for (column, c) in text.chars().enumerate() {
+        let x: usize = left + column * CHAR_ADVANCE;
+        if x + GLYPH_WIDTH > max_width {
+            break;
+        }
+        for (dy, bits) in debug_hud::glyph(c).into_iter().enumerate() {
+            for dx in (0..GLYPH_WIDTH).filter(|dx| bits & (0b100 >> dx) != 0) {
+                rects.push(Rect::new((x + dx) as i32, (top + dy) as i32, 1, 1));
+            }
+        }
+    }
+}
+
+// What to do after the program fails, chosen on the error screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorAction {
+    Reset,
+    Quit,
+}
+
+// Keeps the failed program's screen up with the error over it (see SdlDisplay::present_error) until F5 is pressed to
+// reset it, or Escape or closing the window quits.
+pub fn error_screen(
+    display: &mut SdlDisplay,
+    event_pump: &mut sdl2::EventPump,
+    chip_eight: &ChipEight,
+    error: &ChipEightError,
+) -> Result<ErrorAction, String> {
+    loop {
+        display.present_error(chip_eight, error)?;
+        match event_pump.wait_event() {
+            Event::Quit { .. }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => return Ok(ErrorAction::Quit),
+            Event::KeyDown {
+                keycode: Some(Keycode::F5),
+                ..
+            } => return Ok(ErrorAction::Reset),
+            _ => {}
+        }
+    }
+}
+
+// A GIF still being recorded when the window closes is finished, rather than left without its last frame.
+impl Drop for SdlDisplay<'_> {
+    fn drop(&mut self) {
+        self.stop_recording(Instant::now());
+    }
+}
+
+impl Display for SdlDisplay<'_> {
+    fn present(&mut self, chip_eight: &ChipEight) -> Result<(), String> {
+        self.handle_events();
+        let now: Instant = Instant::now();
+        self.show_status(chip_eight, now)?;
+        self.show_fullscreen()?;
+        self.notice(chip_eight, now);
+        self.capture(chip_eight, now);
+        self.osd.expire(now);
+
+        let mut hud: Hud = Hud {
+            messages: self
+                .osd
+                .visible(now)
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            ..Hud::default()
+        };
+        if let Some(recording) = &self.recording {
+            hud.messages
+                .insert(0, format!("REC {}S  F10: STOP", recording.seconds()));
+        }
+        hud.lines.extend(self.footer.iter().cloned());
+        if self.debug_hud {
+            hud.lines.extend(debug_hud::lines(chip_eight));
+        }
+        if self.keypad_overlay {
+            hud.keypad = Some(KeypadView::new(chip_eight, &self.keymap));
+        }
+        hud.panels.extend(
+            LAYOUTS[self.debug_layout]
+                .iter()
+                .map(|panel| (*panel, panel.lines(chip_eight))),
+        );
+        if self.stats_overlay {
+            hud.lines.extend(self.stats.report().lines());
+            hud.lines.push(
+                match self.limiter {
+                    Some(_) => "PACING: 60HZ LIMITER",
+                    None => "PACING: VSYNC",
+                }
+                .to_string(),
+            );
+        }
+        if let Some(editor) = &self.memory_editor {
+            let first: usize = hud.lines.len();
+            hud.marks.extend(
+                editor
+                    .marks(chip_eight)
+                    .into_iter()
+                    .map(|(row, column, chars, mark)| (first + row, column, chars, mark)),
+            );
+            hud.lines.extend(editor.lines(chip_eight));
+        }
+        // Pixels fade while the program runs, but while it's paused the screen is shown exactly as it is, to step through.
+        let mut phosphor: Option<Phosphor> = self.phosphor.take();
+        let mut changed: bool = chip_eight.dirty_region().is_some();
+        if let Some(phosphor) = &mut phosphor {
+            changed |= match chip_eight.paused_at() {
+                Some(_) => phosphor.settle(chip_eight),
+                None => phosphor.update(chip_eight),
+            };
+        }
+        let palette: Palette = self.palette;
+        let fill = |out: &mut [u8]| render_chip_eight(chip_eight, palette, phosphor.as_ref(), out);
+
+        let presents: u64 = self.presents;
+        let drawn: Result<(), String> = if hud == Hud::default() {
+            self.draw(
+                chip_eight.resolution(),
+                fill,
+                changed,
+                self.filter,
+                self.pixel_grid,
+            )
+        } else {
+            self.draw_with_hud(chip_eight, &hud, fill, changed)
+        };
+        self.phosphor = phosphor;
+        drawn?;
+
+        let now: Instant = Instant::now();
+        self.stats.record(
+            now,
+            chip_eight.instructions_executed(),
+            chip_eight.frame_count(),
+            self.presents > presents,
+        );
+        // The first second is spent filling the window, so nothing is printed until the end of it.
+        if self.log_timing {
+            match self.last_log {
+                Some(at) if now.duration_since(at) < STATS_WINDOW => {}
+                Some(_) => {
+                    eprintln!("{}", self.stats.report());
+                    self.last_log = Some(now);
+                }
+                None => self.last_log = Some(now),
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct SdlInput<'a> {
+    event_pump: &'a mut sdl2::EventPump,
+    keymap: Keymap,
+    // Where the events for the display go (see SdlDisplay::events).
+    display: Sender<DisplayEvent>,
+    // The memory editor, while it's open.
+    editor: Option<MemoryEditor>,
+    // While N (or Shift+N) is held down: the step it repeats, and how many frames it's been held for.
+    step_held: Option<(InputEvent, u32)>,
+}
+
+// Holding N steps 15 times a second, after the same delay. So does Shift+N, backwards.
+const STEP_REPEAT_FRAMES: u32 = 4;
+const STEP_REPEAT_DELAY: u32 = 15;
+
+impl<'a> SdlInput<'a> {
+    // Reads the keyboard from event_pump, passing display the events that are its business.
+    pub fn new(event_pump: &'a mut sdl2::EventPump, display: &SdlDisplay) -> Self {
+        SdlInput {
+            event_pump,
+            keymap: Keymap::default(),
+            display: display.events(),
+            editor: None,
+            step_held: None,
+        }
+    }
+    // Presses keypad keys with keymap's physical keys rather than the DEFAULT_KEYMAP.
+    pub fn keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+}
+
+impl SdlInput<'_> {
+    // Turns an SDL event into the keypad events it stands for, according to the keymap. F1 shows and hides the keypad
+    // overlay, F2 the speed statistics, F3 the debug overlay, and F4 the memory editor (which only opens while paused,
+    // and takes the arrow keys, Page Up, Page Down, and hex digits for itself while it's open). F5 pauses and resumes,
+    // and while paused, N steps, Shift+N steps backwards (if rewinding is enabled), and O steps over subroutine calls.
+    // F6 switches to the next layout of debugging panels, F8 to the next theme (and Shift+F8 to the buzzer's next
+    // waveform), F9 to the next filter (and Shift+F9 between sharp and smooth scaling), and F11 in and out of fullscreen.
+    // F7 shows and hides the pixel grid, F10 starts and stops recording a GIF, and F12 saves a screenshot (Shift+F12
+    // writes a dump of the machine instead, for bug reports). [ and ] turn the buzzer down and up, and M mutes and
+    // unmutes it (unless M is bound to a key on the keypad).
+    pub fn translate_event(&self, chip_eight: &ChipEight, event: &Event) -> Vec<InputEvent> {
+        match event {
+            Event::Quit { .. }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => vec![InputEvent::Quit],
+            // Frames where nothing changed aren't drawn, so a window that was covered up would stay blank until something
+            // did.
+            Event::Window {
+                win_event: WindowEvent::Exposed,
+                ..
+            } => vec![InputEvent::Redraw],
+            Event::KeyDown {
+                keycode: Some(Keycode::F4),
+                repeat: false,
+                ..
+            } => vec![InputEvent::ToggleMemoryEditor],
+            // Held keys repeat in the editor, like they would in a text box.
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } if self.editor.is_some() && editor_key(*keycode).is_some() => editor_key(*keycode)
+                .map(InputEvent::EditMemory)
+                .into_iter()
+                .collect(),
+            // Holding a key down makes SDL send repeated KeyDowns, which shouldn't count as new presses.
+            Event::KeyDown { repeat: true, .. } => vec![],
+            Event::KeyDown {
+                keycode: Some(Keycode::F2),
+                ..
+            } => vec![InputEvent::ToggleStats],
+            Event::KeyDown {
+                keycode: Some(Keycode::F1),
+                ..
+            } => vec![InputEvent::ToggleKeypad],
+            Event::KeyDown {
+                keycode: Some(Keycode::F3),
+                ..
+            } => vec![InputEvent::ToggleDebugHud],
+            Event::KeyDown {
+                keycode: Some(Keycode::F8),
+                keymod,
+                ..
+            } if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                vec![InputEvent::NextWaveform]
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F8),
+                ..
+            } => vec![InputEvent::NextTheme],
+            Event::KeyDown {
+                keycode: Some(Keycode::LeftBracket),
+                ..
+            } => vec![InputEvent::VolumeDown],
+            Event::KeyDown {
+                keycode: Some(Keycode::RightBracket),
+                ..
+            } => vec![InputEvent::VolumeUp],
+            Event::KeyDown {
+                keycode: Some(Keycode::F9),
+                keymod,
+                ..
+            } if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                vec![InputEvent::ToggleSmoothScaling]
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F9),
+                ..
+            } => vec![InputEvent::NextFilter],
+            Event::KeyDown {
+                keycode: Some(Keycode::F7),
+                ..
+            } => vec![InputEvent::TogglePixelGrid],
+            Event::KeyDown {
+                keycode: Some(Keycode::F6),
+                repeat: false,
+                ..
+            } => vec![InputEvent::NextLayout],
+            Event::KeyDown {
+                keycode: Some(Keycode::F11),
+                ..
+            } => vec![InputEvent::ToggleFullscreen],
+            Event::KeyDown {
+                keycode: Some(Keycode::F12),
+                keymod,
+                repeat: false,
+                ..
+            } if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => vec![InputEvent::Dump],
+            Event::KeyDown {
+                keycode: Some(Keycode::F12),
+                repeat: false,
+                ..
+            } => vec![InputEvent::Screenshot],
+            Event::KeyDown {
+                keycode: Some(Keycode::F10),
+                repeat: false,
+                ..
+            } => vec![InputEvent::ToggleRecording],
+            Event::KeyDown {
+                keycode: Some(Keycode::F5),
+                ..
+            } => match chip_eight.paused_at() {
+                Some(_) => vec![InputEvent::Resume],
+                None => vec![InputEvent::Pause],
+            },
+            Event::KeyDown {
+                keycode: Some(Keycode::N),
+                keymod,
+                ..
+            } if chip_eight.paused_at().is_some() => vec![step_for(*keymod)],
+            Event::KeyDown {
+                keycode: Some(Keycode::O),
+                ..
+            } if chip_eight.paused_at().is_some() => vec![InputEvent::StepOver],
+            Event::KeyDown {
+                keycode: Some(Keycode::M),
+                ..
+            } if self.keymap.keys_bound_to(Keycode::M).is_empty() => vec![InputEvent::ToggleMute],
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => self
+                .keymap
+                .keys_bound_to(*keycode)
+                .into_iter()
+                .map(InputEvent::KeyPressed)
+                .collect(),
+            Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } => self
+                .keymap
+                .keys_bound_to(*keycode)
+                .into_iter()
+                .map(InputEvent::KeyReleased)
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+// The memory editor's key for a physical key: the arrows, Page Up and Page Down, and hex digits on either the number
+// row or the keypad.
+fn editor_key(keycode: Keycode) -> Option<EditorKey> {
+    let digit: u8 = match keycode {
+        Keycode::Up => return Some(EditorKey::Up),
+        Keycode::Down => return Some(EditorKey::Down),
+        Keycode::Left => return Some(EditorKey::Left),
+        Keycode::Right => return Some(EditorKey::Right),
+        Keycode::PageUp => return Some(EditorKey::PageUp),
+        Keycode::PageDown => return Some(EditorKey::PageDown),
+        Keycode::Num0 | Keycode::Kp0 => 0x0,
+        Keycode::Num1 | Keycode::Kp1 => 0x1,
+        Keycode::Num2 | Keycode::Kp2 => 0x2,
+        Keycode::Num3 | Keycode::Kp3 => 0x3,
+        Keycode::Num4 | Keycode::Kp4 => 0x4,
+        Keycode::Num5 | Keycode::Kp5 => 0x5,
+        Keycode::Num6 | Keycode::Kp6 => 0x6,
+        Keycode::Num7 | Keycode::Kp7 => 0x7,
+        Keycode::Num8 | Keycode::Kp8 => 0x8,
+        Keycode::Num9 | Keycode::Kp9 => 0x9,
+        Keycode::A => 0xA,
+        Keycode::B => 0xB,
+        Keycode::C => 0xC,
+        Keycode::D => 0xD,
+        Keycode::E => 0xE,
+        Keycode::F => 0xF,
+        _ => return None,
+    };
+    Some(EditorKey::Digit(digit))
+}
+
+// N steps forwards, and Shift+N backwards.
+fn step_for(keymod: Mod) -> InputEvent {
+    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+        InputEvent::StepBack
+    } else {
+        InputEvent::Step
+    }
+}
+
+impl Input for SdlInput<'_> {
+    fn poll(&mut self, chip_eight: &mut ChipEight) -> ControlFlow<()> {
+        self.poll_except(chip_eight, |_| false)
+    }
+}
+
+impl SdlInput<'_> {
+    // Polls the same as Input::poll, except that the events take returns true for are the caller's, and left alone here.
+    pub(crate) fn poll_except(
+        &mut self,
+        chip_eight: &mut ChipEight,
+        mut take: impl FnMut(&Event) -> bool,
+    ) -> ControlFlow<()> {
+        let polled: Vec<Event> = self.event_pump.poll_iter().collect();
+        let mut events: Vec<InputEvent> = vec![];
+
+        for event in polled.into_iter().filter(|event| !take(event)) {
+            match event {
+                Event::KeyDown {
+                    keycode: Some(Keycode::N),
+                    keymod,
+                    repeat: false,
+                    ..
+                } => self.step_held = Some((step_for(keymod), 0)),
+                Event::KeyUp {
+                    keycode: Some(Keycode::N),
+                    ..
+                } => self.step_held = None,
+                _ => {}
+            }
+            events.extend(self.translate_event(chip_eight, &event));
+        }
+        if let Some((step, frames)) = &mut self.step_held {
+            *frames += 1;
+            if chip_eight.paused_at().is_some()
+                && *frames >= STEP_REPEAT_DELAY
+                && *frames % STEP_REPEAT_FRAMES == 0
+            {
+                events.push(*step);
+            }
+        }
+        let (display, machine): (Vec<InputEvent>, Vec<InputEvent>) =
+            events.into_iter().partition(for_display);
+        let (editing, machine): (Vec<InputEvent>, Vec<InputEvent>) =
+            machine.into_iter().partition(|event| {
+                matches!(
+                    event,
+                    InputEvent::ToggleMemoryEditor | InputEvent::EditMemory(_)
+                )
+            });
+        for event in display {
+            self.send(DisplayEvent::Input(event));
+        }
+        // The editor goes last, so it opens if F5 paused the machine in the same frame.
+        let flow: ControlFlow<()> = apply_input_events(chip_eight, machine);
+        for event in editing {
+            self.edit_memory(chip_eight, event);
+        }
+        flow
+    }
+    // Opens the memory editor on the address I points at, if the machine is paused, or closes it if it's open. It stays
+    // open if the machine is resumed, to watch memory change as the program runs. Otherwise passes it a key, if it's open.
+    fn edit_memory(&mut self, chip_eight: &mut ChipEight, event: InputEvent) {
+        match (event, &mut self.editor) {
+            (InputEvent::ToggleMemoryEditor, Some(_)) => self.editor = None,
+            (InputEvent::ToggleMemoryEditor, None) if chip_eight.paused_at().is_some() => {
+                let addr: usize =
+                    (chip_eight.i_register() as usize).min(chip_eight.memory().len() - 1);
+                self.editor = Some(MemoryEditor::new(addr));
+            }
+            (InputEvent::EditMemory(key), Some(editor)) => editor.handle_key(key, chip_eight),
+            _ => return,
+        }
+        self.send(DisplayEvent::MemoryEditor(self.editor.clone()));
+    }
+    fn send(&self, event: DisplayEvent) {
+        // The display only goes away after the input, so there's always someone to send to.
+        let _ = self.display.send(event);
+    }
+}
+
+// Whether SdlInput passes an event on to the display rather than applying it to the machine.
+fn for_display(event: &InputEvent) -> bool {
+    matches!(
+        event,
+        InputEvent::ToggleDebugHud
+            | InputEvent::ToggleStats
+            | InputEvent::NextLayout
+            | InputEvent::NextTheme
+            | InputEvent::NextWaveform
+            | InputEvent::VolumeUp
+            | InputEvent::VolumeDown
+            | InputEvent::ToggleMute
+            | InputEvent::NextFilter
+            | InputEvent::TogglePixelGrid
+            | InputEvent::ToggleKeypad
+            | InputEvent::ToggleSmoothScaling
+            | InputEvent::ToggleFullscreen
+            | InputEvent::Screenshot
+            | InputEvent::Dump
+            | InputEvent::ToggleRecording
+    )
+}
+
+// How many samples SDL's audio buffer holds unless it's told otherwise, and how many it can be told to hold (always a power
+// of two). A bigger buffer is less likely to crackle, but a beep takes longer to be heard: 512 samples is about 12ms.
+pub const DEFAULT_AUDIO_BUFFER: u16 = 512;
+pub const AUDIO_BUFFER_RANGE: std::ops::RangeInclusive<u16> = 64..=8192;
+
+// An audio buffer size, as --audio-buffer takes it: a number of samples in AUDIO_BUFFER_RANGE that's a power of two.
+pub fn parse_audio_buffer(s: &str) -> Result<u16, String> {
+    match s.trim().parse::<u16>() {
+        Ok(samples) if samples.is_power_of_two() && AUDIO_BUFFER_RANGE.contains(&samples) => {
+            Ok(samples)
+        }
+        _ => Err(format!(
+            "bad audio buffer size '{}' (expected a power of two from {} to {})",
+            s,
+            AUDIO_BUFFER_RANGE.start(),
+            AUDIO_BUFFER_RANGE.end()
+        )),
+    }
+}
+
+// The sample rate and channels asked of SDL. It can open the device with others, and Tone is set up for whatever it gets.
+const AUDIO_FREQ: i32 = 44100;
+const AUDIO_CHANNELS: u8 = 1;
+
+// The audio code started out lifted 1:1 from the SDL2 crate's audio example code: https://rust-sdl2.github.io/rust-sdl2/sdl2/audio/index.html
+// The samples themselves now come from Tone.
+impl AudioCallback for Tone {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [Self::Channel]) {
+        self.fill(out);
+    }
+}
+
+// Which way SdlAudio gets samples to SDL. With a callback, SDL asks for them from its own thread as it needs them. Some
+// setups (certain WSL and headless ones, for instance) can't open a device that way but can with a queue, which the
+// main loop keeps topped up every frame instead. Either way the samples come from a Tone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AudioBackend {
+    Callback,
+    Queue,
+    // No sound at all, without even opening a device.
+    None,
+}
+
+impl AudioBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioBackend::Callback => "callback",
+            AudioBackend::Queue => "queue",
+            AudioBackend::None => "none",
+        }
+    }
+}
+
+impl FromStr for AudioBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "callback" => Ok(AudioBackend::Callback),
+            "queue" => Ok(AudioBackend::Queue),
+            "none" => Ok(AudioBackend::None),
+            _ => Err(format!(
+                "unknown audio backend '{}' (expected callback, queue, or none)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AudioBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// How many frames' worth of samples the queue backend keeps queued. Fewer would run dry whenever a frame is late; more
+// would make beeps start later.
+const QUEUED_FRAMES: usize = 3;
+
+enum AudioOutput {
+    Callback(AudioDevice<Tone>),
+    // The queue, the tone being queued, and a frame's worth of samples to fill from it.
+    Queue {
+        queue: AudioQueue<f32>,
+        tone: Tone,
+        frame: Vec<f32>,
+    },
+    None,
+}
+
+// A WAV being recorded from the tone's tap, which set_beeping() drains into it every frame.
+struct AudioRecording {
+    writer: WavWriter<io::BufWriter<std::fs::File>>,
+    tap: SampleTap,
+    path: PathBuf,
+}
+
+pub struct SdlAudio {
+    output: AudioOutput,
+    recording: Option<AudioRecording>,
+    // The buffer size asked for, to tell whether SDL went with it.
+    buffer_samples: u16,
+    // The sound the tone was last given, and whether it was last told to beep, so the callback's device is only locked
+    // when one of them changes (or while waiting for the release to finish).
+    sound: Sound,
+    beeping: bool,
+    // Where an SdlDisplay sends the sound when a key changes it (see SdlDisplay::audio), picked up at the next beep.
+    sounds: (Sender<Sound>, Receiver<Sound>),
+}
+
+impl SdlAudio {
+    // Opens the default playback device for 44.1KHz mono, with a buffer of buffer_samples (see AUDIO_BUFFER_RANGE): with a
+    // callback if it can, and with a queue if not.
+    pub fn new(
+        audio_subsystem: &sdl2::AudioSubsystem,
+        buffer_samples: u16,
+    ) -> Result<Self, String> {
+        Self::open(audio_subsystem, buffer_samples, AudioBackend::Callback).or_else(
+            |callback_error| {
+                Self::open(audio_subsystem, buffer_samples, AudioBackend::Queue).map_err(
+                    |queue_error| {
+                        format!(
+                            "{} (and with a queue instead: {})",
+                            callback_error, queue_error
+                        )
+                    },
+                )
+            },
+        )
+    }
+    // Opens the default playback device the way backend says, and no other.
+    pub fn open(
+        audio_subsystem: &sdl2::AudioSubsystem,
+        buffer_samples: u16,
+        backend: AudioBackend,
+    ) -> Result<Self, String> {
+        let desired_spec = AudioSpecDesired {
+            freq: Some(AUDIO_FREQ),
+            channels: Some(AUDIO_CHANNELS),
+            samples: Some(buffer_samples),
+        };
+
+        let output: AudioOutput = match backend {
+            AudioBackend::Callback => AudioOutput::Callback(audio_subsystem.open_playback(
+                None,
+                &desired_spec,
+                |spec| Tone::new(spec.freq as u32, Sound::default()).channels(spec.channels),
+            )?),
+            AudioBackend::Queue => {
+                let queue: AudioQueue<f32> = audio_subsystem.open_queue(None, &desired_spec)?;
+                let spec: &AudioSpec = queue.spec();
+                let tone: Tone =
+                    Tone::new(spec.freq as u32, Sound::default()).channels(spec.channels);
+                let frame: Vec<f32> =
+                    vec![0.0; (spec.freq as usize / 60).max(1) * spec.channels.max(1) as usize];
+                // An empty queue plays silence, so it can be left running.
+                queue.resume();
+                AudioOutput::Queue { queue, tone, frame }
+            }
+            AudioBackend::None => AudioOutput::None,
+        };
+        Ok(SdlAudio {
+            output,
+            recording: None,
+            buffer_samples,
+            sound: Sound::default(),
+            beeping: false,
+            sounds: mpsc::channel(),
+        })
+    }
+    // Makes no sound, for when there's no audio to be had (or wanted).
+    pub fn silent() -> Self {
+        SdlAudio {
+            output: AudioOutput::None,
+            recording: None,
+            buffer_samples: DEFAULT_AUDIO_BUFFER,
+            sound: Sound::default(),
+            beeping: false,
+            sounds: mpsc::channel(),
+        }
+    }
+    // Sets how the buzzer sounds. An SdlDisplay tied to this audio sets it whenever a key changes it.
+    pub fn set_sound(&mut self, sound: Sound) {
+        if sound != self.sound {
+            match &mut self.output {
+                AudioOutput::Callback(device) => device.lock().set_sound(sound),
+                AudioOutput::Queue { tone, .. } => tone.set_sound(sound),
+                AudioOutput::None => {}
+            }
+            self.sound = sound;
+        }
+    }
+    pub fn backend(&self) -> AudioBackend {
+        match self.output {
+            AudioOutput::Callback(_) => AudioBackend::Callback,
+            AudioOutput::Queue { .. } => AudioBackend::Queue,
+            AudioOutput::None => AudioBackend::None,
+        }
+    }
+    // What the device was actually opened with, which isn't always what was asked for. There's none without a device.
+    pub fn spec(&self) -> Option<&AudioSpec> {
+        match &self.output {
+            AudioOutput::Callback(device) => Some(device.spec()),
+            AudioOutput::Queue { queue, .. } => Some(queue.spec()),
+            AudioOutput::None => None,
+        }
+    }
+    // Describes the backend and spec, e.g. "callback, 44100Hz, 1 channel, 512-sample buffer (11.6ms)", noting anything
+    // that isn't what was asked for.
+    pub fn describe(&self) -> String {
+        let spec: &AudioSpec = match self.spec() {
+            Some(spec) => spec,
+            None => return self.backend().to_string(),
+        };
+        let mut description: String = format!(
+            "{}, {}Hz, {} channel{}, {}-sample buffer ({:.1}ms)",
+            self.backend(),
+            spec.freq,
+            spec.channels,
+            if spec.channels == 1 { "" } else { "s" },
+            spec.samples,
+            spec.samples as f32 * 1000.0 / spec.freq.max(1) as f32
+        );
+        let mut differences: Vec<String> = vec![];
+        if spec.freq != AUDIO_FREQ {
+            differences.push(format!("{}Hz", AUDIO_FREQ));
+        }
+        if spec.channels != AUDIO_CHANNELS {
+            differences.push(format!("{} channel", AUDIO_CHANNELS));
+        }
+        if spec.samples != self.buffer_samples {
+            differences.push(format!("a {}-sample buffer", self.buffer_samples));
+        }
+        if !differences.is_empty() {
+            description += &format!(" instead of {}", differences.join(", "));
+        }
+        description
+    }
+    // Starts recording everything the tone plays, silences included, to a WAV at path, at the device's sample rate and
+    // channels. Any recording already going is finished first. There has to be a device.
+    pub fn start_recording(&mut self, path: &Path) -> io::Result<()> {
+        if let Some(Err(e)) = self.stop_recording() {
+            eprintln!("Error finishing the audio recording: {}", e);
+        }
+        let (freq, channels): (i32, u8) = match self.spec() {
+            Some(spec) => (spec.freq, spec.channels),
+            None => return Err(io::Error::other("there's no audio device to record")),
+        };
+        let writer: WavWriter<io::BufWriter<std::fs::File>> =
+            WavWriter::create(path, freq as u32, channels as u16)?;
+        // A second's worth, which is plenty between frames.
+        let tap: SampleTap = SampleTap::new(freq as usize * channels.max(1) as usize);
+        self.set_tap(Some(tap.clone()));
+        // Silence has to be recorded too, so the device plays (silently) for as long as the recording goes.
+        if let AudioOutput::Callback(device) = &self.output {
+            device.resume();
+        }
+        self.recording = Some(AudioRecording {
+            writer,
+            tap,
+            path: path.to_path_buf(),
+        });
+        Ok(())
+    }
+    // Finishes the recording if there is one, with everything played up to now, and returns where it went.
+    pub fn stop_recording(&mut self) -> Option<io::Result<PathBuf>> {
+        let mut recording: AudioRecording = self.recording.take()?;
+        self.set_tap(None);
+        if recording.tap.dropped() > 0 {
+            eprintln!(
+                "The audio recording is missing {} samples that couldn't be written in time.",
+                recording.tap.dropped()
+            );
+        }
+        let result: io::Result<PathBuf> = recording
+            .writer
+            .write(&recording.tap.drain())
+            .and_then(|_| recording.writer.finish())
+            .map(|_| recording.path);
+        Some(result)
+    }
+    // Writes out what the tap has collected since last time.
+    fn drain_recording(&mut self) {
+        let result: io::Result<()> = match &mut self.recording {
+            Some(recording) => recording.writer.write(&recording.tap.drain()),
+            None => return,
+        };
+        if let Err(e) = result {
+            eprintln!("Error recording audio: {}", e);
+            self.recording = None;
+            self.set_tap(None);
+        }
+    }
+    fn set_tap(&mut self, tap: Option<SampleTap>) {
+        match &mut self.output {
+            AudioOutput::Callback(device) => device.lock().set_tap(tap),
+            AudioOutput::Queue { tone, .. } => tone.set_tap(tap),
+            AudioOutput::None => {}
+        }
+    }
+}
+
+// A recording still going when the audio goes away is finished, so the file's header is right.
+impl Drop for SdlAudio {
+    fn drop(&mut self) {
+        if let Some(Err(e)) = self.stop_recording() {
+            eprintln!("Error finishing the audio recording: {}", e);
+        }
+    }
+}
+
+impl Audio for SdlAudio {
+    // The tone fades itself in and out, so the device keeps playing after the beep stops until the release is over. Muted
+    // or at a volume of 0 it's never started, exactly as if nothing were beeping, and muting mid-beep fades out like the
+    // beep ending. With a callback, the device is paused once the release is over; with a queue, it's simply not given
+    // any more samples until the next beep.
+    fn set_beeping(&mut self, beeping: bool) {
+        while let Ok(sound) = self.sounds.1.try_recv() {
+            self.set_sound(sound);
+        }
+        let beeping: bool = beeping && self.sound.is_audible();
+        let recording: bool = self.recording.is_some();
+        match &mut self.output {
+            AudioOutput::Callback(device) => {
+                if beeping != self.beeping {
+                    device.lock().set_beeping(beeping);
+                    if beeping {
+                        device.resume();
+                    }
+                } else if !beeping
+                    && !recording
+                    && device.status() == AudioStatus::Playing
+                    && device.lock().is_silent()
+                {
+                    device.pause();
+                }
+            }
+            AudioOutput::Queue { queue, tone, frame } => {
+                tone.set_beeping(beeping);
+                let frame_bytes: usize = std::mem::size_of_val(frame.as_slice());
+                let mut queued: usize = queue.size() as usize;
+                while (recording || !tone.is_silent()) && queued < QUEUED_FRAMES * frame_bytes {
+                    tone.fill(frame);
+                    if queue.queue_audio(frame).is_err() {
+                        break;
+                    }
+                    queued += frame_bytes;
+                }
+            }
+            AudioOutput::None => {}
+        }
+        self.beeping = beeping;
+        self.drain_recording();
+    }
+}
+
+// A window title naming the loaded program and the variant it's running as, e.g. "PotatOcho — Space Invaders [CHIP-8]",
+// or "[CHIP-8, custom quirks]" if its quirks aren't the variant's. Programs identify_rom didn't find are named by their
+// file name (without its extension) instead. SdlDisplay adds the speed to the end.
+pub fn window_title(chip_eight: &ChipEight) -> String {
+    let name: Option<&str> = chip_eight.rom_info().and_then(|info: &RomInfo| {
+        info.title
+            .as_deref()
+            .or(info.file_name.as_deref().map(|file_name| {
+                Path::new(file_name)
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or(file_name)
+            }))
+    });
+    let variant: ChipVariant = chip_eight.variant();
+    let quirks: &str = if chip_eight.quirks() == variant.quirks() {
+        ""
+    } else {
+        ", custom quirks"
+    };
+    match name {
+        Some(name) => format!("PotatOcho — {} [{}{}]", name, variant, quirks),
+        None => "PotatOcho".to_string(),
+    }
+}
+
+// The index of SDL's OpenGL render driver, for Window::into_canvas().index().
+// This function is lifted entirely from the rust-sdl2 github page https://github.com/Rust-SDL2/rust-sdl2
+pub fn find_sdl_gl_driver() -> Option<u32> {
+    for (i, item) in sdl2::render::drivers().enumerate() {
+        if item.name == "opengl" {
+            return Some(i as u32);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_parse_print_and_cycle() {
+        for filter in [Filter::None, Filter::Scanlines, Filter::Grid] {
+            assert_eq!(filter.to_string().parse::<Filter>(), Ok(filter));
+        }
+        assert_eq!(Filter::Grid.next().next(), Filter::Scanlines);
+        assert!("crt".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn filters_darken_only_their_lines() {
+        let alphas = |filter: Filter, size: (u32, u32)| -> Vec<u8> {
+            filter_pixels(filter, false, size, (2, 2))
+                .chunks(4)
+                .map(|pixel| pixel[3])
+                .collect()
+        };
+        assert_eq!(alphas(Filter::Scanlines, (1, 4)), vec![0, 0x60, 0, 0x60]);
+        assert_eq!(alphas(Filter::Grid, (6, 1)), vec![0; 6]);
+        assert_eq!(
+            alphas(Filter::Grid, (6, 6))[..6],
+            [0x30; 6],
+            "the top row starts a screen pixel all the way along"
+        );
+        assert_eq!(
+            alphas(Filter::Grid, (6, 6))[6..12],
+            [0x30, 0, 0, 0x30, 0, 0]
+        );
+        assert_eq!(
+            alphas(Filter::Scanlines, (2, 3)),
+            vec![0; 6],
+            "too short for scanlines"
+        );
+    }
+
+    #[test]
+    fn the_pixel_grid_goes_between_pixels_over_any_filter() {
+        let rows = |filter: Filter, size: (u32, u32)| -> Vec<Vec<u8>> {
+            let alphas: Vec<u8> = filter_pixels(filter, true, size, (2, 2))
+                .chunks(4)
+                .map(|pixel| pixel[3])
+                .collect();
+            alphas.chunks(size.0 as usize).map(<[u8]>::to_vec).collect()
+        };
+        let grid: Vec<Vec<u8>> = rows(Filter::None, (8, 8));
+        assert_eq!(
+            grid[0],
+            [0, 0, 0, 0, 0x90, 0, 0, 0],
+            "no line around the edge"
+        );
+        assert_eq!(grid[4], [0x90; 8]);
+        assert_eq!(
+            rows(Filter::Scanlines, (8, 8))[1],
+            [0x60, 0x60, 0x60, 0x60, 0x90, 0x60, 0x60, 0x60]
+        );
+        assert!(
+            rows(Filter::None, (6, 6))
+                .concat()
+                .iter()
+                .all(|&alpha| alpha == 0),
+            "too small for the grid"
+        );
+    }
+
+    #[test]
+    fn the_scale_is_in_real_pixels_on_high_dpi_displays() {
+        // (window, drawable) sizes, for the same 64x32 logical size.
+        for (window, drawable, expected) in [
+            ((640, 320), (640, 320), 10.0),
+            ((640, 320), (1280, 640), 20.0),
+            // Retina displays are often 2x, but fractional scaling gives odd drawables.
+            ((800, 600), (1200, 900), 18.0),
+            ((200, 100), (300, 150), 4.0),
+        ] {
+            assert_eq!(
+                logical_scale(drawable, (64, 32), false),
+                expected,
+                "{:?} window, {:?} drawable",
+                window,
+                drawable
+            );
+        }
+    }
+
+    #[test]
+    fn the_scale_is_whole_unless_filling() {
+        for (output, logical, fill, expected) in [
+            ((1000, 500), (64, 32), false, 15.0),
+            ((1000, 500), (64, 32), true, 15.625),
+            ((1280, 640), (128, 64), false, 10.0),
+            // The narrower side decides.
+            ((1920, 400), (64, 32), false, 12.0),
+            ((400, 1920), (64, 32), true, 6.25),
+            // Too small to scale up at all still draws one to one.
+            ((32, 16), (64, 32), false, 1.0),
+            ((32, 16), (64, 32), true, 0.5),
+        ] {
+            assert_eq!(
+                logical_scale(output, logical, fill),
+                expected,
+                "{:?} in {:?}, fill {}",
+                logical,
+                output,
+                fill
+            );
+        }
+    }
+
+    #[test]
+    fn the_limiter_keeps_to_the_frame_time_and_restarts_when_late() {
+        let mut limiter: FrameLimiter = FrameLimiter::default();
+        let start: Instant = Instant::now();
+        limiter.wait();
+        assert!(start.elapsed() < FRAME_TIME);
+        for _ in 0..6 {
+            limiter.wait();
+        }
+        assert!(start.elapsed() >= FRAME_TIME * 6);
+
+        // A frame more than a frame late doesn't wait, and isn't followed by a rush of frames to catch up.
+        std::thread::sleep(FRAME_TIME * 3);
+        let late: Instant = Instant::now();
+        limiter.wait();
+        limiter.wait();
+        assert!(late.elapsed() >= FRAME_TIME);
+        assert!(limiter.due.is_some_and(|due| due >= late + FRAME_TIME * 2));
+    }
+
+    #[test]
+    fn rotations_parse_from_degrees() {
+        for (text, rotation) in [
+            ("0", Rotation::None),
+            ("90", Rotation::Clockwise),
+            ("180", Rotation::UpsideDown),
+            ("270", Rotation::Counterclockwise),
+        ] {
+            assert_eq!(text.parse(), Ok(rotation));
+            assert_eq!(rotation.to_string(), text);
+        }
+        assert_eq!(
+            "45".parse::<Rotation>(),
+            Err("unknown rotation '45' (expected 0, 90, 180, or 270)".to_string())
+        );
+    }
+
+    #[test]
+    fn quarter_turns_swap_the_sides() {
+        for (rotation, lores, hires) in [
+            (Rotation::None, (64, 32), (256, 64)),
+            (Rotation::Clockwise, (32, 64), (64, 256)),
+            (Rotation::UpsideDown, (64, 32), (256, 64)),
+            (Rotation::Counterclockwise, (32, 64), (64, 256)),
+        ] {
+            assert_eq!(rotation.size((64, 32)), lores, "{:?}", rotation);
+            // Turning comes after stretching, so tall pixels end up wide.
+            let aspect: PixelAspect = PixelAspect::new(2, 1).unwrap();
+            assert_eq!(
+                rotation.size(aspect.size((128, 64))),
+                hires,
+                "{:?}",
+                rotation
+            );
+        }
+    }
+
+    #[test]
+    fn rects_turn_with_the_picture() {
+        // The top left corner of a 64x32 picture.
+        let corner: Rect = Rect::new(0, 0, 8, 4);
+        for (rotation, expected) in [
+            (Rotation::None, Rect::new(0, 0, 8, 4)),
+            (Rotation::Clockwise, Rect::new(28, 0, 4, 8)),
+            (Rotation::UpsideDown, Rect::new(56, 28, 8, 4)),
+            (Rotation::Counterclockwise, Rect::new(0, 56, 4, 8)),
+        ] {
+            assert_eq!(rotation.rect(corner, (64, 32)), expected, "{:?}", rotation);
+        }
+
+        // Four quarter turns go all the way round.
+        let (mut rect, mut size) = (Rect::new(5, 3, 10, 2), (64, 32));
+        for _ in 0..4 {
+            rect = Rotation::Clockwise.rect(rect, size);
+            size = Rotation::Clockwise.size(size);
+        }
+        assert_eq!(rect, Rect::new(5, 3, 10, 2));
+    }
+
+    #[test]
+    fn pixel_aspects_are_kept_in_lowest_terms() {
+        // What each parses as, or the error.
+        for (text, expected) in [
+            ("1:1", "1:1"),
+            ("2:1", "2:1"),
+            (" 6 : 4 ", "3:2"),
+            ("8:2", "4:1"),
+            (
+                "5:1",
+                "pixel aspect 5:1 is too lopsided (neither side can be more than 4)",
+            ),
+            ("0:1", "0:1 isn't a pixel aspect"),
+            ("2x1", "bad pixel aspect '2x1' (expected W:H, like 2:1)"),
+        ] {
+            let parsed: String = match text.parse::<PixelAspect>() {
+                Ok(aspect) => aspect.to_string(),
+                Err(e) => e,
+            };
+            assert_eq!(parsed, expected, "{}", text);
+        }
+        assert_eq!(PixelAspect::default(), PixelAspect::SQUARE);
+    }
+
+    #[test]
+    fn pixel_aspects_stretch_by_whole_numbers() {
+        let aspect: PixelAspect = PixelAspect::new(1, 2).unwrap();
+        assert_eq!(aspect.size((64, 32)), (64, 64));
+        assert_eq!(aspect.unstretch((640, 641)), (640, 320));
+        assert_eq!(aspect.rect(Rect::new(3, 4, 5, 6)), Rect::new(3, 8, 5, 12));
+        // A stretched picture scales by a whole number as a whole, so each pixel stays a whole number of real ones.
+        let scale: f64 = logical_scale((1000, 700), aspect.size((64, 32)), false);
+        assert_eq!(scale, 10.0);
+        assert_eq!(
+            aspect.rect(Rect::new(0, 0, 1, 1)).height() as f64 * scale,
+            20.0
+        );
+    }
+
+    #[test]
+    fn audio_buffers_are_powers_of_two_in_range() {
+        assert!(AUDIO_BUFFER_RANGE.contains(&DEFAULT_AUDIO_BUFFER));
+        assert_eq!(
+            parse_audio_buffer(&DEFAULT_AUDIO_BUFFER.to_string()),
+            Ok(DEFAULT_AUDIO_BUFFER)
+        );
+        for (text, expected) in [("64", 64), ("1024", 1024), (" 8192 ", 8192)] {
+            assert_eq!(parse_audio_buffer(text), Ok(expected), "{}", text);
+        }
+        for text in ["0", "32", "500", "16384", "65535", "-512", "lots", ""] {
+            assert_eq!(
+                parse_audio_buffer(text),
+                Err(format!(
+                    "bad audio buffer size '{}' (expected a power of two from 64 to 8192)",
+                    text
+                )),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn audio_backends_go_by_name() {
+        for backend in [
+            AudioBackend::Callback,
+            AudioBackend::Queue,
+            AudioBackend::None,
+        ] {
+            assert_eq!(backend.to_string().parse(), Ok(backend));
+        }
+        assert_eq!(
+            "pulse".parse::<AudioBackend>(),
+            Err("unknown audio backend 'pulse' (expected callback, queue, or none)".to_string())
+        );
+    }
+
+    #[test]
+    fn silent_audio_still_takes_the_displays_sound() {
+        let mut audio: SdlAudio = SdlAudio::silent();
+        assert_eq!(audio.backend(), AudioBackend::None);
+        assert!(audio.spec().is_none());
+        assert_eq!(audio.describe(), "none");
+
+        let muted: Sound = Sound {
+            muted: true,
+            ..Sound::default()
+        };
+        audio.sounds.0.send(muted).unwrap();
+        audio.set_beeping(true);
+        assert_eq!(audio.sound, muted);
+        assert!(!audio.beeping);
+    }
+}
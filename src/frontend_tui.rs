@@ -0,0 +1,344 @@
+// A terminal frontend, for playing over SSH or anywhere else there's no window to open. The screen is drawn with
+// half-block characters: each cell is a "▀" with the upper pixel's color as its foreground and the lower one's as its
+// background, so a 64x32 screen fits in 64x16 cells. Colors are 24-bit, from the theme's palette.
+//
+// Most terminals only say when a key is pressed, not when it's let go, so a key press holds its Chip-8 key down for
+// KEY_HOLD_FRAMES, and the terminal's key repeat keeps it held for as long as the key is. (There's a gap between the first
+// press and the first repeat, which games that move while a key is held will show.) Terminals that can report releases
+// are asked to, and then keys are held for exactly as long as they're down.
+use crate::backend::{apply_input_events, Audio, Display, Input, InputEvent};
+use crate::{ChipEight, Palette};
+use crossterm::cursor::{Hide, MoveTo, MoveToNextLine, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Colors, Print, ResetColor, SetColors};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+// How many frames a key press holds its key down for, on terminals that don't report releases.
+pub const KEY_HOLD_FRAMES: u32 = 10;
+
+// The keys typed for the Chip-8 keypad, laid out the same way as the SDL frontend's default keymap:
+// 1 2 3 C      1 2 3 4
+// 4 5 6 D  ->  Q W E R
+// 7 8 9 E      A S D F
+// A 0 B F      Z X C V
+const KEYMAP: [(char, u8); 16] = [
+    ('1', 0x1),
+    ('2', 0x2),
+    ('3', 0x3),
+    ('4', 0xC),
+    ('q', 0x4),
+    ('w', 0x5),
+    ('e', 0x6),
+    ('r', 0xD),
+    ('a', 0x7),
+    ('s', 0x8),
+    ('d', 0x9),
+    ('f', 0xE),
+    ('z', 0xA),
+    ('x', 0x0),
+    ('c', 0xB),
+    ('v', 0xF),
+];
+
+// The terminal, set up for the emulator: raw mode (so keys arrive as they're pressed, and Ctrl+C doesn't kill the
+// process and leave the terminal in a mess), the alternate screen, no cursor, and key releases reported if the terminal
+// can. Dropping it puts everything back.
+pub struct TuiTerminal {
+    releases: bool,
+}
+
+impl TuiTerminal {
+    pub fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let releases: bool = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+        if releases {
+            execute!(
+                stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(TuiTerminal { releases })
+    }
+    // Whether the terminal reports when keys are let go.
+    pub fn reports_releases(&self) -> bool {
+        self.releases
+    }
+}
+
+impl Drop for TuiTerminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.releases {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// Draws the screen, with a status line under it. It's only drawn again when something on it changed, and present()
+// keeps the machine to 60 frames a second, since there's no vsync to do it.
+#[derive(Debug, Default)]
+pub struct TuiDisplay {
+    palette: Palette,
+    shown: Option<Shown>,
+    last_present: Option<Instant>,
+}
+
+// What was last drawn, to tell whether it needs drawing again.
+#[derive(Debug, PartialEq)]
+struct Shown {
+    screen: Vec<[bool; 128]>,
+    palette: Palette,
+    size: (u16, u16),
+    beeping: bool,
+}
+
+impl TuiDisplay {
+    pub fn new() -> Self {
+        TuiDisplay::default()
+    }
+    // Draws the screen in palette's colors rather than white on black.
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+    fn draw(
+        &self,
+        chip_eight: &ChipEight,
+        (columns, _): (u16, u16),
+        beeping: bool,
+    ) -> io::Result<()> {
+        let (width, _) = chip_eight.resolution();
+        let palette: Palette = self.palette;
+        let color = |lit: bool| {
+            let [r, g, b] = if lit {
+                palette.foreground
+            } else {
+                palette.background
+            };
+            Color::Rgb { r, g, b }
+        };
+        let mut stdout = io::stdout().lock();
+
+        queue!(stdout, MoveTo(0, 0))?;
+        for rows in chip_eight.framebuffer().chunks(2) {
+            let mut last: Option<Colors> = None;
+            for x in 0..width.min(columns as usize) {
+                let lower: bool = rows.get(1).is_some_and(|row| row[x]);
+                let colors: Colors = Colors::new(color(rows[0][x]), color(lower));
+                if last != Some(colors) {
+                    queue!(stdout, SetColors(colors))?;
+                    last = Some(colors);
+                }
+                queue!(stdout, Print('▀'))?;
+            }
+            queue!(
+                stdout,
+                ResetColor,
+                Clear(ClearType::UntilNewLine),
+                MoveToNextLine(1)
+            )?;
+        }
+        let status: &str = if beeping {
+            "Esc: quit  ♪"
+        } else {
+            "Esc: quit"
+        };
+        queue!(stdout, Print(status), Clear(ClearType::FromCursorDown))?;
+        stdout.flush()
+    }
+}
+
+impl Display for TuiDisplay {
+    fn present(&mut self, chip_eight: &ChipEight) -> Result<(), String> {
+        let size: (u16, u16) = terminal::size().map_err(|e| e.to_string())?;
+        let beeping: bool = chip_eight.sound_timer() > 0;
+        let shown: Shown = Shown {
+            screen: chip_eight.framebuffer().to_vec(),
+            palette: self.palette,
+            size,
+            beeping,
+        };
+        if self.shown.as_ref() != Some(&shown) {
+            self.draw(chip_eight, size, beeping)
+                .map_err(|e| e.to_string())?;
+            self.shown = Some(shown);
+        }
+
+        if let Some(last_present) = self.last_present {
+            let frame_time: Duration = Duration::from_nanos(1_000_000_000 / 60);
+            std::thread::sleep(frame_time.saturating_sub(last_present.elapsed()));
+        }
+        self.last_present = Some(Instant::now());
+        Ok(())
+    }
+}
+
+// Reads keys from the terminal without waiting for them. Escape (or Ctrl+C) quits.
+#[derive(Debug)]
+pub struct TuiInput {
+    releases: bool,
+    // How many more frames each key stays held, if it's held.
+    held: [Option<u32>; 16],
+}
+
+impl TuiInput {
+    pub fn new(terminal: &TuiTerminal) -> Self {
+        TuiInput {
+            releases: terminal.reports_releases(),
+            held: [None; 16],
+        }
+    }
+    fn translate_key(&mut self, key: KeyEvent) -> Vec<InputEvent> {
+        let quit: bool = key.code == KeyCode::Esc
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL));
+        if quit && key.kind != KeyEventKind::Release {
+            return vec![InputEvent::Quit];
+        }
+        let chip_key: u8 = match key.code {
+            KeyCode::Char(c) => match KEYMAP
+                .iter()
+                .find(|(typed, _)| *typed == c.to_ascii_lowercase())
+            {
+                Some((_, chip_key)) => *chip_key,
+                None => return vec![],
+            },
+            _ => return vec![],
+        };
+
+        let held: &mut Option<u32> = &mut self.held[chip_key as usize];
+        match key.kind {
+            KeyEventKind::Release => {
+                *held = None;
+                vec![InputEvent::KeyReleased(chip_key)]
+            }
+            _ => {
+                let pressed: bool = held.is_none();
+                *held = Some(KEY_HOLD_FRAMES);
+                if pressed {
+                    vec![InputEvent::KeyPressed(chip_key)]
+                } else {
+                    vec![]
+                }
+            }
+        }
+    }
+}
+
+impl Input for TuiInput {
+    fn poll(&mut self, chip_eight: &mut ChipEight) -> ControlFlow<()> {
+        let mut events: Vec<InputEvent> = vec![];
+        // Keys that weren't pressed again in time are let go, unless the terminal says when they are.
+        if !self.releases {
+            for (chip_key, held) in self.held.iter_mut().enumerate() {
+                *held = match *held {
+                    Some(0) => {
+                        events.push(InputEvent::KeyReleased(chip_key as u8));
+                        None
+                    }
+                    Some(frames) => Some(frames - 1),
+                    None => None,
+                };
+            }
+        }
+        // An error reading the terminal is treated like it having nothing to say, since there's nowhere to report it.
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                events.extend(self.translate_key(key));
+            }
+        }
+        apply_input_events(chip_eight, events)
+    }
+}
+
+// Rings the terminal's bell when a beep starts. (The status line shows a note for as long as it lasts.)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TuiAudio {
+    beeping: bool,
+}
+
+impl TuiAudio {
+    pub fn new() -> Self {
+        TuiAudio::default()
+    }
+}
+
+impl Audio for TuiAudio {
+    fn set_beeping(&mut self, beeping: bool) {
+        if beeping && !self.beeping {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+        }
+        self.beeping = beeping;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, kind: KeyEventKind) -> KeyEvent {
+        KeyEvent::new_with_kind(code, KeyModifiers::NONE, kind)
+    }
+
+    #[test]
+    fn keys_press_once_and_release() {
+        let mut input: TuiInput = TuiInput {
+            releases: true,
+            held: [None; 16],
+        };
+        assert_eq!(
+            input.translate_key(key(KeyCode::Char('W'), KeyEventKind::Press)),
+            vec![InputEvent::KeyPressed(0x5)]
+        );
+        assert_eq!(input.held[0x5], Some(KEY_HOLD_FRAMES));
+        // Key repeat refreshes the hold without pressing the key again.
+        assert_eq!(
+            input.translate_key(key(KeyCode::Char('w'), KeyEventKind::Repeat)),
+            vec![]
+        );
+        assert_eq!(
+            input.translate_key(key(KeyCode::Char('w'), KeyEventKind::Release)),
+            vec![InputEvent::KeyReleased(0x5)]
+        );
+        assert_eq!(input.held[0x5], None);
+        assert_eq!(
+            input.translate_key(key(KeyCode::Char('p'), KeyEventKind::Press)),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn escape_and_ctrl_c_quit() {
+        let mut input: TuiInput = TuiInput {
+            releases: false,
+            held: [None; 16],
+        };
+        assert_eq!(
+            input.translate_key(key(KeyCode::Esc, KeyEventKind::Press)),
+            vec![InputEvent::Quit]
+        );
+        assert_eq!(
+            input.translate_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            vec![InputEvent::Quit]
+        );
+        assert_eq!(
+            input.translate_key(key(KeyCode::Esc, KeyEventKind::Release)),
+            vec![]
+        );
+        assert_eq!(
+            input.translate_key(key(KeyCode::Char('c'), KeyEventKind::Press)),
+            vec![InputEvent::KeyPressed(0xB)]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+// Recording the screen as an animated GIF, for sharing clips. Frames are captured like screenshots are (see Capture), at
+// a steady rate of the machine's frames rather than the clock's, so a clip plays back at the speed the program ran even if
+// the emulator stuttered. A frame the same as the one before it isn't written again: the one before is just shown for
+// longer. Recordings stop by themselves after GIF_MAX_SECONDS, so one left running can't fill the disk.
+use crate::screenshot::{timestamped_path, Capture};
+use crate::{ChipEight, Palette};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Frames captured a second, how many times bigger than the screen they are, and how long a recording can be, unless
+// asked otherwise.
+pub const GIF_FPS: u32 = 30;
+pub const GIF_SCALE: u32 = 4;
+pub const GIF_MAX_SECONDS: u32 = 60;
+
+pub struct GifRecorder {
+    encoder: gif::Encoder<BufWriter<File>>,
+    path: PathBuf,
+    scale: u32,
+    fps: u32,
+    // The machine's frame_count() when recording started, and how many captures have been taken since.
+    start_frame: u64,
+    captures: u64,
+    // The last frame captured, which isn't written until it changes (or the recording ends), and the capture it was first
+    // seen at.
+    pending: Option<(Capture, u64)>,
+    colors: [[u8; 3]; 2],
+}
+
+impl GifRecorder {
+    // Starts recording chip_eight's screen in palette's colors to a new file in dir named after the time, e.g.
+    // potatocho-1700000000.gif, taking fps frames a second (at most the machine's 60) scale times bigger than the screen.
+    // dir is created if it isn't there.
+    pub fn start(
+        chip_eight: &ChipEight,
+        palette: Palette,
+        dir: &Path,
+        scale: u32,
+        fps: u32,
+    ) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path: PathBuf = timestamped_path(dir, "gif");
+        let capture: Capture = Capture::new(chip_eight, palette, scale)?;
+        let mut encoder = gif::Encoder::new(
+            BufWriter::new(File::create(&path)?),
+            capture.width as u16,
+            capture.height as u16,
+            &capture.palette(),
+        )
+        .map_err(io::Error::other)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(io::Error::other)?;
+        Ok(GifRecorder {
+            encoder,
+            path,
+            scale,
+            fps: fps.clamp(1, 60),
+            start_frame: chip_eight.frame_count(),
+            captures: 0,
+            pending: Some((capture.clone(), 0)),
+            colors: capture.colors,
+        })
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    // How long the recording is so far, in seconds.
+    pub fn seconds(&self) -> u64 {
+        self.captures / u64::from(self.fps)
+    }
+    // Captures the screen in palette's colors if it's time for another frame. Returns false once the recording has reached
+    // GIF_MAX_SECONDS or the program switched resolution (a GIF's frames are all one size), and it should be finished.
+    pub fn capture(&mut self, chip_eight: &ChipEight, palette: Palette) -> io::Result<bool> {
+        let due: u64 =
+            chip_eight.frame_count().saturating_sub(self.start_frame) * u64::from(self.fps) / 60;
+        if due <= self.captures {
+            return Ok(true);
+        }
+        self.captures = due;
+        if self.captures >= u64::from(GIF_MAX_SECONDS * self.fps) {
+            return Ok(false);
+        }
+
+        let capture: Capture = Capture::new(chip_eight, palette, self.scale)?;
+        match &self.pending {
+            Some((pending, _))
+                if pending.pixels == capture.pixels && pending.colors == capture.colors =>
+            {
+                return Ok(true)
+            }
+            Some((pending, _))
+                if (pending.width, pending.height) != (capture.width, capture.height) =>
+            {
+                return Ok(false)
+            }
+            _ => {}
+        }
+        self.write_pending()?;
+        self.pending = Some((capture, self.captures));
+        Ok(true)
+    }
+    // Writes the last frame and the end of the file, and returns the file's path.
+    pub fn finish(mut self) -> io::Result<PathBuf> {
+        self.write_pending()?;
+        self.encoder.into_inner()?.flush()?;
+        Ok(self.path)
+    }
+    // Writes the pending frame, shown until now. Delays are in hundredths of a second, so they're worked out from when the
+    // frame started and ended rather than added up, for rounding not to make the clip drift.
+    fn write_pending(&mut self) -> io::Result<()> {
+        let (capture, from) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        let centiseconds = |capture: u64| capture * 100 / u64::from(self.fps);
+        let delay: u64 = centiseconds(self.captures.max(from + 1)) - centiseconds(from);
+
+        let mut frame: gif::Frame = gif::Frame {
+            width: capture.width as u16,
+            height: capture.height as u16,
+            delay: delay.min(u64::from(u16::MAX)) as u16,
+            buffer: Cow::Borrowed(&capture.pixels),
+            ..gif::Frame::default()
+        };
+        // The theme was changed since the recording started, so this frame brings its own colors.
+        if capture.colors != self.colors {
+            frame.palette = Some(capture.palette());
+        }
+        self.encoder.write_frame(&frame).map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keypad;
+
+    #[test]
+    fn repeated_frames_are_merged_and_delays_add_up_to_the_clip() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        // Spins forever, so the screen never changes.
+        chip_eight.load_program_from_slice(&[0x12, 0x00]).unwrap();
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("potatocho-gif-test-{}", std::process::id()));
+        let green: Palette = Palette::theme("green").unwrap();
+
+        let mut recording: GifRecorder =
+            GifRecorder::start(&chip_eight, Palette::default(), &dir, 1, 30).unwrap();
+        // A second of the same screen, then half a second of it in another theme.
+        for frame in 0..90 {
+            chip_eight.frame(&Keypad::default()).unwrap();
+            let palette: Palette = if frame < 60 {
+                Palette::default()
+            } else {
+                green
+            };
+            assert!(recording.capture(&chip_eight, palette).unwrap());
+        }
+        assert_eq!(recording.seconds(), 1);
+        let path: PathBuf = recording.finish().unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (64, 32));
+        assert_eq!(
+            decoder.global_palette(),
+            Some(&[0, 0, 0, 0xFF, 0xFF, 0xFF][..])
+        );
+        let mut frames: Vec<(u16, Option<Vec<u8>>)> = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push((frame.delay, frame.palette.clone()));
+        }
+        // The theme changed at the 31st capture, 1.03 seconds in, and the clip ends at the 45th.
+        assert_eq!(
+            frames,
+            [
+                (103, None),
+                (47, Some([green.background, green.foreground].concat()))
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
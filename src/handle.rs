@@ -0,0 +1,209 @@
+// Running the emulator on its own thread, for frontends that can't block their UI thread on it. The thread runs frames at
+// 60Hz, takes commands through a channel, and publishes every finished frame through a small bounded channel. If the UI
+// falls behind, frames are dropped rather than queued, so it always sees something recent.
+use crate::{ChipEight, ChipEightError, DirtyRect, Keypad, LoadError, RomInfo};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+// How many finished frames can wait for the UI before new ones are dropped.
+const FRAME_QUEUE: usize = 2;
+
+// Things the UI can ask the emulator thread to do.
+pub enum Command {
+    KeyEvent { key: u8, pressed: bool },
+    // Loads a program, replying with the result.
+    LoadRom(Vec<u8>, Sender<Result<RomInfo, LoadError>>),
+    // Stops running frames (true) or starts again (false).
+    Pause(bool),
+    // Replies with a save state of the machine as it is between frames.
+    SaveState(Sender<Vec<u8>>),
+    // Ends the thread. Dropping the handle does the same.
+    Quit,
+}
+
+// A finished frame: the screen packed into rows of bits, and what the frontend needs to present it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    // One u128 per row, leftmost pixel in the most significant bit. Only the first height rows and width columns are in use.
+    pub rows: [u128; 64],
+    pub width: usize,
+    pub height: usize,
+    pub beep: bool,
+    pub halted: bool,
+    // The part of the screen that changed since the last published frame. Frames dropped because the UI fell behind still
+    // count, so a frame's dirty region covers everything since the last one the UI could have seen.
+    pub dirty: Option<DirtyRect>,
+}
+
+impl Frame {
+    fn capture(chip_eight: &ChipEight, beep: bool) -> Self {
+        let mut rows: [u128; 64] = [0; 64];
+        for (bits, row) in rows.iter_mut().zip(chip_eight.screen.pixels.iter()) {
+            *bits = row.iter().fold(0, |bits, &pixel| bits << 1 | pixel as u128);
+        }
+        let (width, height) = chip_eight.resolution();
+
+        Frame {
+            rows,
+            width,
+            height,
+            beep,
+            halted: chip_eight.halted,
+            dirty: chip_eight.dirty_region(),
+        }
+    }
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.rows[y] & (1 << (127 - x)) != 0
+    }
+}
+
+pub struct EmulatorHandle {
+    commands: Sender<Command>,
+    frames: Receiver<Frame>,
+    thread: Option<JoinHandle<Result<ChipEight, ChipEightError>>>,
+}
+
+impl EmulatorHandle {
+    // Moves chip_eight onto a new thread and starts running it.
+    pub fn spawn(chip_eight: ChipEight) -> Self {
+        let (commands, command_receiver) = mpsc::channel();
+        let (frame_sender, frames) = mpsc::sync_channel(FRAME_QUEUE);
+        let thread =
+            std::thread::spawn(move || emulate(chip_eight, command_receiver, frame_sender));
+
+        EmulatorHandle {
+            commands,
+            frames,
+            thread: Some(thread),
+        }
+    }
+    // Returns false if the emulator thread has already stopped.
+    pub fn send(&self, command: Command) -> bool {
+        self.commands.send(command).is_ok()
+    }
+    pub fn press(&self, key: u8) -> bool {
+        self.send(Command::KeyEvent { key, pressed: true })
+    }
+    pub fn release(&self, key: u8) -> bool {
+        self.send(Command::KeyEvent {
+            key,
+            pressed: false,
+        })
+    }
+    pub fn set_paused(&self, paused: bool) -> bool {
+        self.send(Command::Pause(paused))
+    }
+    // Waits for the emulator thread to load program. None if the thread has stopped.
+    pub fn load_rom(&self, program: Vec<u8>) -> Option<Result<RomInfo, LoadError>> {
+        let (reply, result) = mpsc::channel();
+        self.send(Command::LoadRom(program, reply));
+        result.recv().ok()
+    }
+    // Waits for a save state from the emulator thread. None if the thread has stopped.
+    pub fn save_state(&self) -> Option<Vec<u8>> {
+        let (reply, state) = mpsc::channel();
+        self.send(Command::SaveState(reply));
+        state.recv().ok()
+    }
+    // The channel finished frames arrive on, for blocking on the next one.
+    pub fn frames(&self) -> &Receiver<Frame> {
+        &self.frames
+    }
+    // The newest finished frame since the last call, skipping any older ones that are still queued. The skipped frames'
+    // dirty regions are folded into the one returned.
+    pub fn latest_frame(&self) -> Option<Frame> {
+        self.frames.try_iter().reduce(|older, mut newer| {
+            newer.dirty = match (older.dirty, newer.dirty) {
+                (Some(older), Some(newer)) => Some(older.union(&newer)),
+                (older, newer) => older.or(newer),
+            };
+            newer
+        })
+    }
+    // Stops the emulator thread and gives back the machine, or the error that stopped it.
+    pub fn join(mut self) -> Result<ChipEight, ChipEightError> {
+        self.send(Command::Quit);
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => unreachable!("the thread is only taken by join"),
+        }
+    }
+}
+
+impl Drop for EmulatorHandle {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.send(Command::Quit);
+            let _ = thread.join();
+        }
+    }
+}
+
+// The emulator thread's main loop. Returns when told to quit, when the handle is dropped, or when the machine errors.
+fn emulate(
+    mut chip_eight: ChipEight,
+    commands: Receiver<Command>,
+    frames: SyncSender<Frame>,
+) -> Result<ChipEight, ChipEightError> {
+    let mut keys: Keypad = Keypad::new();
+    let mut paused: bool = false;
+    let mut next_frame: Instant = Instant::now();
+
+    loop {
+        // While paused there's nothing to do but wait for the next command.
+        let command: Option<Command> = if paused {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return Ok(chip_eight),
+            }
+        } else {
+            match commands.recv_timeout(next_frame.saturating_duration_since(Instant::now())) {
+                Ok(command) => Some(command),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return Ok(chip_eight),
+            }
+        };
+
+        match command {
+            Some(Command::KeyEvent { key, pressed: true }) => keys.press(key),
+            Some(Command::KeyEvent {
+                key,
+                pressed: false,
+            }) => keys.release(key),
+            Some(Command::LoadRom(program, reply)) => {
+                let _ = reply.send(chip_eight.load_program(program));
+            }
+            Some(Command::Pause(pause)) => {
+                paused = pause;
+                next_frame = Instant::now();
+            }
+            Some(Command::SaveState(reply)) => {
+                let _ = reply.send(chip_eight.save_state());
+            }
+            Some(Command::Quit) => return Ok(chip_eight),
+            None => {
+                let beep: bool = chip_eight.frame(&keys)?.beep;
+                // A full queue means the UI is behind, so this frame is dropped. A disconnected one means nobody is
+                // watching, which is fine too.
+                if frames.try_send(Frame::capture(&chip_eight, beep)).is_ok() {
+                    chip_eight.take_dirty_region();
+                }
+                next_frame += FRAME_TIME;
+                // Don't try to catch up after a long stall (a debugger break, a suspended laptop).
+                if Instant::now() > next_frame + FRAME_TIME * 4 {
+                    next_frame = Instant::now();
+                }
+            }
+        }
+    }
+}
+
+// The machine has to be Send to move onto the emulator thread. This fails to compile if a field that can't cross threads
+// ever gets added.
+const _: fn() = || {
+    fn is_send<T: Send>() {}
+    is_send::<ChipEight>();
+};
@@ -0,0 +1,183 @@
+// Running a ROM with no frontend at all, for regression testing against test ROMs: run it for a while, then check the
+// screen hashes to what it did last time. Everything that could make two runs differ is pinned down, including the RNG
+// seed, so the same ROM, quirks, and script always end up on the same screen.
+use crate::backend::{apply_input_events, InputEvent};
+use crate::{ChipEight, ChipEightError, KeyEvent, Keypad, Quirks};
+
+// What every headless run seeds the RNG with.
+pub const HEADLESS_SEED: u64 = 0;
+
+// How a headless run ended up.
+#[derive(Debug)]
+pub struct HeadlessResult {
+    // The screen after the last frame, top row first, at whatever resolution the program left it in.
+    pub framebuffer: Vec<[bool; 128]>,
+    // ChipEight::framebuffer_hash() of that screen.
+    pub hash: u64,
+    // How many frames actually ran. This is fewer than asked for if the program exited, failed, or the script quit.
+    pub frames: u32,
+    // How many of them the buzzer sounded for, i.e. started with the sound timer running. A sound timer set to N beeps
+    // for exactly N frames, however many instructions a frame runs.
+    pub beep_frames: u32,
+    // What went wrong, if anything did. The screen is still the one the program had drawn up to that point.
+    pub error: Option<ChipEightError>,
+}
+
+// Runs rom on a CHIP-8 machine with the given quirks for up to frames frames. input_script is played back one event per
+// frame, event n just before frame n + 1, so a key pressed by one event and released by the next is held for exactly
+// one frame. (Releasing a key that isn't held does nothing, which makes it a handy way to wait.) Once the script runs out
+// the keypad stays as it was, and an InputEvent::Quit in it ends the run there.
+pub fn run_headless(
+    rom: &[u8],
+    frames: u32,
+    quirks: Quirks,
+    input_script: &[InputEvent],
+) -> HeadlessResult {
+    let mut chip_eight = ChipEight::new();
+    chip_eight.set_quirks(Some(quirks));
+    if let Err(e) = chip_eight.load_program_from_slice(rom) {
+        return chip_eight.headless_result(0, 0, Some(ChipEightError::Load(e)));
+    }
+    chip_eight.run_headless(frames, input_script)
+}
+
+// Parses an input script: key events at given frames, for a headless run that needs keys pressed at particular moments
+// rather than one event every frame. Each line is a key event as a recording has it, e.g. "120 press 5" to press key 5
+// just before frame 120 runs (0 being just before the first), and "126 release 5" to let go of it. Blank lines and lines
+// starting with # are skipped, and the events don't have to be in order.
+pub fn parse_input_script(src: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut events: Vec<KeyEvent> = vec![];
+    for (i, line) in src.lines().enumerate() {
+        let line: &str = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        events.push(line.parse().map_err(|e| format!("line {}: {}", i + 1, e))?);
+    }
+    events.sort_by_key(|event| event.frame);
+    Ok(events)
+}
+
+impl ChipEight {
+    // run_headless() for a machine that's already set up, e.g. with a different variant or a ROM's settings applied. The
+    // RNG is reseeded with HEADLESS_SEED first.
+    pub fn run_headless(&mut self, frames: u32, input_script: &[InputEvent]) -> HeadlessResult {
+        self.run_headless_with(frames, input_script, |_| {})
+    }
+    // run_headless(), with keys pressed and released on the frames events (e.g. from parse_input_script()) says rather than
+    // one event a frame. Any number of them can happen before the same frame, in the order they're given.
+    pub fn run_headless_keys(&mut self, frames: u32, events: &[KeyEvent]) -> HeadlessResult {
+        self.run_frames(
+            frames,
+            |frame| {
+                events
+                    .iter()
+                    .filter(|event| event.frame == frame as u64)
+                    .map(|event| {
+                        if event.pressed {
+                            InputEvent::KeyPressed(event.key)
+                        } else {
+                            InputEvent::KeyReleased(event.key)
+                        }
+                    })
+                    .collect()
+            },
+            |_| {},
+        )
+    }
+    // run_headless(), calling after_frame with the machine after every frame that ran.
+    pub(crate) fn run_headless_with(
+        &mut self,
+        frames: u32,
+        input_script: &[InputEvent],
+        after_frame: impl FnMut(&ChipEight),
+    ) -> HeadlessResult {
+        let mut script = input_script.iter().copied();
+        self.run_frames(frames, |_| script.next().into_iter().collect(), after_frame)
+    }
+    // Runs up to frames frames, applying events_before(n) just before frame n, and calling after_frame with the machine
+    // after every frame that ran.
+    fn run_frames(
+        &mut self,
+        frames: u32,
+        mut events_before: impl FnMut(u32) -> Vec<InputEvent>,
+        mut after_frame: impl FnMut(&ChipEight),
+    ) -> HeadlessResult {
+        self.seed_rng(HEADLESS_SEED);
+        let mut beep_frames: u32 = 0;
+
+        for frame in 0..frames {
+            if apply_input_events(self, events_before(frame)).is_break() {
+                return self.headless_result(frame, beep_frames, None);
+            }
+            let keys: Keypad = self.keypad;
+            match self.frame(&keys) {
+                Ok(output) => {
+                    beep_frames += u32::from(output.beep);
+                    after_frame(self);
+                    if output.halted {
+                        return self.headless_result(frame + 1, beep_frames, None);
+                    }
+                }
+                Err(e) => return self.headless_result(frame, beep_frames, Some(e)),
+            }
+        }
+        self.headless_result(frames, beep_frames, None)
+    }
+    fn headless_result(
+        &self,
+        frames: u32,
+        beep_frames: u32,
+        error: Option<ChipEightError>,
+    ) -> HeadlessResult {
+        HeadlessResult {
+            framebuffer: self.framebuffer().to_vec(),
+            hash: self.framebuffer_hash(),
+            frames,
+            beep_frames,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(frame: u64, key: u8, pressed: bool) -> KeyEvent {
+        KeyEvent {
+            frame,
+            key,
+            pressed,
+        }
+    }
+
+    #[test]
+    fn input_scripts_skip_comments_and_go_in_frame_order() {
+        let script: &str = "# Start the game.\n\n  30 release A\n20 press a\n";
+        assert_eq!(
+            parse_input_script(script),
+            Ok(vec![event(20, 0xA, true), event(30, 0xA, false)])
+        );
+        assert_eq!(
+            parse_input_script("20 press 5\n30 push 5\n"),
+            Err("line 2: expected a key event, not 30 push 5".to_string())
+        );
+    }
+
+    #[test]
+    fn a_key_can_go_down_and_up_before_the_same_frame() {
+        // LD V0, K; and then spin.
+        let mut chip_eight: ChipEight = ChipEight::new();
+        chip_eight
+            .load_program_from_slice(&[0xF0, 0x0A, 0x12, 0x02])
+            .unwrap();
+        let result: HeadlessResult =
+            chip_eight.run_headless_keys(10, &[event(4, 0x7, true), event(4, 0x7, false)]);
+        assert_eq!(result.frames, 10);
+        assert!(result.error.is_none());
+        assert_eq!(chip_eight.v_registers()[0], 0x7);
+        assert_eq!(chip_eight.pc(), 0x202);
+        assert!(!chip_eight.keypad().is_pressed(0x7));
+    }
+}
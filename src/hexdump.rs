@@ -0,0 +1,99 @@
+// Classic hex dumps of memory, 16 bytes to a row with the address on the left and the printable bytes on the right:
+// 0200  00 E0 A2 2A 60 0C 61 08  D0 1F 70 09 A2 39 D0 1F  |...*`.a...p..9..|
+use crate::ChipEight;
+use core::fmt::Write;
+use core::ops::Range;
+
+const ROW: usize = 16;
+
+// Dumps bytes as if they started at address base.
+pub fn hex_dump(bytes: &[u8], base: usize) -> String {
+    let mut dump: String = String::new();
+
+    for (row, chunk) in bytes.chunks(ROW).enumerate() {
+        let _ = write!(dump, "{:04X} ", base + row * ROW);
+        for column in 0..ROW {
+            if column == ROW / 2 {
+                dump.push(' ');
+            }
+            match chunk.get(column) {
+                Some(byte) => {
+                    let _ = write!(dump, " {:02X}", byte);
+                }
+                None => dump.push_str("   "),
+            }
+        }
+        dump.push_str("  |");
+        for &byte in chunk {
+            dump.push(if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            });
+        }
+        dump.push_str("|\n");
+    }
+    dump
+}
+
+impl ChipEight {
+    // A hex dump of the memory in range. Anything past the end of memory is left out, with a note at the end saying so.
+    pub fn dump_memory(&self, range: Range<usize>) -> String {
+        let size: usize = self.memory.len();
+        let start: usize = range.start.min(size);
+        let end: usize = range.end.clamp(start, size);
+        let mut dump: String = hex_dump(&self.memory[start..end], start);
+
+        let requested: usize = range.end.saturating_sub(range.start);
+        if end - start < requested {
+            let _ = writeln!(
+                dump,
+                "; only {} of {} bytes shown, memory is {:#X} bytes",
+                end - start,
+                requested,
+                size
+            );
+        }
+        dump
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_straddle_a_boundary_and_hide_unprintables() {
+        let bytes: [u8; 20] = [
+            0x00, 0xE0, b'H', b'i', b'!', b' ', 0x7F, 0x80, 0x09, 0x0A, b'~', b'A', b'B', b'C',
+            b'D', b'E', b'F', b'G', 0xFF, 0x00,
+        ];
+        let expected: &str = concat!(
+            "01F8  00 E0 48 69 21 20 7F 80  09 0A 7E 41 42 43 44 45  |..Hi! ....~ABCDE|\n",
+            "0208  46 47 FF 00                                       |FG..|\n",
+        );
+        assert_eq!(hex_dump(&bytes, 0x1F8), expected);
+    }
+
+    #[test]
+    fn dump_memory_notes_what_ran_past_the_end() {
+        let mut chip_eight: ChipEight = ChipEight::new();
+        let size: usize = chip_eight.memory.len();
+        chip_eight.memory[size - 4..].copy_from_slice(b"end!");
+
+        let dump: String = chip_eight.dump_memory(size - 4..size + 12);
+        let expected: String = format!(
+            "{:04X}  65 6E 64 21                                       |end!|\n\
+             ; only 4 of 16 bytes shown, memory is {:#X} bytes\n",
+            size - 4,
+            size
+        );
+        assert_eq!(dump, expected);
+
+        // A range wholly past the end shows nothing but the note.
+        assert_eq!(
+            chip_eight.dump_memory(size + 1..size + 3),
+            format!("; only 0 of 2 bytes shown, memory is {:#X} bytes\n", size)
+        );
+    }
+}
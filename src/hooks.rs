@@ -0,0 +1,240 @@
+// Callbacks for watching (and steering) execution from outside the crate. Every hook is optional and costs a single check when unset.
+// Hooks are boxed closures, so they're only available with std; without it the machine just never has any.
+use crate::{ChipEight, RegisterSnapshot};
+
+// What the before-step hook wants done with the instruction that's about to execute.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HookAction {
+    #[default]
+    Continue,
+    // Move past the instruction without executing it.
+    Skip,
+    // Don't execute the instruction, and report the machine as paused. The hook is asked again on the next step.
+    Pause,
+}
+
+// The area of the screen a sprite was drawn to. This is where the sprite starts; with wrapping, parts of it may end up on the
+// other side of the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawRect {
+    pub x: u8,
+    pub y: u8,
+    pub width: u8,
+    pub height: u8,
+}
+
+#[cfg(feature = "std")]
+type BeforeStepHook = Box<dyn FnMut(&ChipEight, u16) -> HookAction + Send>;
+#[cfg(feature = "std")]
+type AfterStepHook = Box<dyn FnMut(&ChipEight, u16, &RegisterSnapshot) + Send>;
+
+#[derive(Default)]
+pub(crate) struct Hooks {
+    #[cfg(feature = "std")]
+    before_step: Option<BeforeStepHook>,
+    #[cfg(feature = "std")]
+    after_step: Option<AfterStepHook>,
+    #[cfg(feature = "std")]
+    screen_cleared: Option<Box<dyn FnMut() + Send>>,
+    #[cfg(feature = "std")]
+    draw: Option<Box<dyn FnMut(DrawRect) + Send>>,
+    #[cfg(feature = "std")]
+    sound_state_change: Option<Box<dyn FnMut(bool) + Send>>,
+    // The sound state last reported to sound_state_change.
+    sound_on: bool,
+}
+
+// Closures can't be cloned, so a cloned machine starts out with no hooks.
+impl Clone for Hooks {
+    fn clone(&self) -> Self {
+        Hooks::default()
+    }
+}
+
+#[cfg(feature = "std")]
+impl ChipEight {
+    // Called before every instruction with the opcode about to be executed.
+    pub fn on_before_step(
+        &mut self,
+        hook: impl FnMut(&ChipEight, u16) -> HookAction + Send + 'static,
+    ) {
+        self.hooks.before_step = Some(Box::new(hook));
+    }
+    // Called after every instruction that executed successfully, with its opcode and the registers from before it ran, so
+    // the hook can see what it changed. Instructions the before-step hook skipped or paused on don't count.
+    pub fn on_after_step(
+        &mut self,
+        hook: impl FnMut(&ChipEight, u16, &RegisterSnapshot) + Send + 'static,
+    ) {
+        self.hooks.after_step = Some(Box::new(hook));
+    }
+    // Called whenever 00E0 (or hires 0230) clears the screen.
+    pub fn on_screen_cleared(&mut self, hook: impl FnMut() + Send + 'static) {
+        self.hooks.screen_cleared = Some(Box::new(hook));
+    }
+    // Called whenever Dxyn draws a sprite.
+    pub fn on_draw(&mut self, hook: impl FnMut(DrawRect) + Send + 'static) {
+        self.hooks.draw = Some(Box::new(hook));
+    }
+    // Called with true when the sound timer starts the beeper, and false when it stops.
+    pub fn on_sound_state_change(&mut self, hook: impl FnMut(bool) + Send + 'static) {
+        self.hooks.sound_state_change = Some(Box::new(hook));
+    }
+    pub fn clear_hooks(&mut self) {
+        self.hooks = Hooks::default();
+    }
+}
+
+impl ChipEight {
+    // Asks the before-step hook what to do with opcode. The hook is taken out while it runs so it can look at the whole machine.
+    #[cfg(feature = "std")]
+    pub(crate) fn run_before_step_hook(&mut self, opcode: u16) -> HookAction {
+        match self.hooks.before_step.take() {
+            Some(mut hook) => {
+                let action: HookAction = hook(self, opcode);
+                self.hooks.before_step = Some(hook);
+                action
+            }
+            None => HookAction::Continue,
+        }
+    }
+    #[cfg(not(feature = "std"))]
+    pub(crate) fn run_before_step_hook(&mut self, _opcode: u16) -> HookAction {
+        HookAction::Continue
+    }
+    // The registers to hand the after-step hook, if there is one. This is taken before the instruction runs.
+    pub(crate) fn after_step_snapshot(&self) -> Option<RegisterSnapshot> {
+        #[cfg(feature = "std")]
+        if self.hooks.after_step.is_some() {
+            return Some(self.register_snapshot());
+        }
+        None
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn run_after_step_hook(&mut self, opcode: u16, before: &RegisterSnapshot) {
+        if let Some(mut hook) = self.hooks.after_step.take() {
+            hook(self, opcode, before);
+            self.hooks.after_step = Some(hook);
+        }
+    }
+    #[cfg(not(feature = "std"))]
+    pub(crate) fn run_after_step_hook(&mut self, _opcode: u16, _before: &RegisterSnapshot) {}
+    pub(crate) fn report_screen_cleared(&mut self) {
+        #[cfg(feature = "std")]
+        if let Some(hook) = &mut self.hooks.screen_cleared {
+            hook();
+        }
+    }
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    pub(crate) fn report_draw(&mut self, rect: DrawRect) {
+        #[cfg(feature = "std")]
+        if let Some(hook) = &mut self.hooks.draw {
+            hook(rect);
+        }
+    }
+    pub(crate) fn report_sound_state(&mut self) {
+        let sound_on: bool = self.sound_timer > 0;
+
+        if sound_on != self.hooks.sound_on {
+            self.hooks.sound_on = sound_on;
+            #[cfg(feature = "std")]
+            if let Some(hook) = &mut self.hooks.sound_state_change {
+                hook(sound_on);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::Keypad;
+    use std::sync::{Arc, Mutex};
+
+    // A machine with program at 0x200, and a list each hook can push what it saw onto.
+    fn machine<T: Send + 'static>(program: &[u8]) -> (ChipEight, Arc<Mutex<Vec<T>>>) {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.write_memory(0x200, program).unwrap();
+        (chip_eight, Arc::new(Mutex::new(vec![])))
+    }
+
+    #[test]
+    fn before_step_fires_once_per_instruction() {
+        // Three loads, then a jump to itself.
+        let (mut chip_eight, seen) = machine(&[0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0x12, 0x06]);
+        let log: Arc<Mutex<Vec<(u16, u16)>>> = Arc::clone(&seen);
+        chip_eight.on_before_step(move |chip_eight, opcode| {
+            log.lock().unwrap().push((chip_eight.pc(), opcode));
+            HookAction::Continue
+        });
+        for _ in 0..5 {
+            chip_eight.step().unwrap();
+        }
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (0x200, 0x6001),
+                (0x202, 0x6102),
+                (0x204, 0x6203),
+                (0x206, 0x1206),
+                (0x206, 0x1206)
+            ]
+        );
+    }
+
+    #[test]
+    fn before_step_can_skip_or_pause() {
+        let (mut chip_eight, _) = machine::<()>(&[0x60, 0x01, 0x61, 0x02]);
+        chip_eight.on_before_step(|_, opcode| match opcode {
+            0x6001 => HookAction::Skip,
+            _ => HookAction::Pause,
+        });
+        chip_eight.step().unwrap();
+        assert_eq!((chip_eight.pc(), chip_eight.v_registers()[0]), (0x202, 0));
+        assert!(chip_eight.step().unwrap().paused);
+        assert!(chip_eight.step().unwrap().paused);
+        assert_eq!((chip_eight.pc(), chip_eight.v_registers()[1]), (0x202, 0));
+        chip_eight.clear_hooks();
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.v_registers()[1], 2);
+    }
+
+    #[test]
+    fn after_step_sees_the_registers_from_before() {
+        let (mut chip_eight, seen) = machine(&[0x60, 0x07, 0x70, 0x01]);
+        let log: Arc<Mutex<Vec<(u8, u8)>>> = Arc::clone(&seen);
+        chip_eight.on_after_step(move |chip_eight, _, before| {
+            log.lock()
+                .unwrap()
+                .push((before.v[0], chip_eight.v_registers()[0]));
+        });
+        chip_eight.step().unwrap();
+        chip_eight.step().unwrap();
+        assert_eq!(*seen.lock().unwrap(), [(0, 7), (7, 8)]);
+    }
+
+    #[test]
+    fn clears_draws_and_the_sound_are_reported() {
+        // Clears, draws the 0 at (0, 0), then beeps for two frames.
+        let (mut chip_eight, seen) = machine(&[
+            0x00, 0xE0, 0xA0, 0x00, 0xD0, 0x05, 0x60, 0x02, 0xF0, 0x18, 0x12, 0x0A,
+        ]);
+        let (cleared, drawn, sound) = (Arc::clone(&seen), Arc::clone(&seen), Arc::clone(&seen));
+        chip_eight.on_screen_cleared(move || cleared.lock().unwrap().push("cleared".to_string()));
+        chip_eight.on_draw(move |rect| drawn.lock().unwrap().push(format!("{:?}", rect)));
+        chip_eight
+            .on_sound_state_change(move |on| sound.lock().unwrap().push(format!("sound {}", on)));
+        for _ in 0..4 {
+            chip_eight.frame(&Keypad::default()).unwrap();
+        }
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                "cleared",
+                "DrawRect { x: 0, y: 0, width: 8, height: 5 }",
+                "sound true",
+                "sound false"
+            ]
+        );
+    }
+}
@@ -0,0 +1,514 @@
+// Decoded instructions. Decoding doesn't depend on the machine state, so everything any supported variant understands is decoded
+// here; whether the current variant actually supports an instruction is up to ChipEight::execute_instruction.
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Instruction {
+    // 0nnn
+    JumpToMachineCode { nnn: u16 },
+    // 00E0
+    ClearScreen,
+    // 00EE
+    ReturnFromSubroutine,
+    // 00Cn (SUPER-CHIP)
+    ScrollDown { n: u8 },
+    // 00FB (SUPER-CHIP)
+    ScrollRight,
+    // 00FC (SUPER-CHIP)
+    ScrollLeft,
+    // 00FD (SUPER-CHIP)
+    Exit,
+    // 00FE (SUPER-CHIP)
+    Lores,
+    // 00FF (SUPER-CHIP)
+    Hires,
+    // 00Dn (XO-CHIP)
+    ScrollUp { n: u8 },
+    // 0230 (hires Chip-8)
+    HiresClearScreen,
+    // 1nnn
+    JumpToAddress { nnn: u16 },
+    // 2nnn
+    CallSubroutine { nnn: u16 },
+    // 3xkk
+    SkipIfEqualsData { x: u8, kk: u8 },
+    // 4xkk
+    SkipIfNotEqualsData { x: u8, kk: u8 },
+    // 5xy0
+    SkipIfEquals { x: u8, y: u8 },
+    // 5xy2 (XO-CHIP)
+    StoreRange { x: u8, y: u8 },
+    // 5xy3 (XO-CHIP)
+    RestoreRange { x: u8, y: u8 },
+    // 6xkk
+    SetData { x: u8, kk: u8 },
+    // 7xkk
+    AddAssignData { x: u8, kk: u8 },
+    // 8xy0
+    Set { x: u8, y: u8 },
+    // 8xy1
+    BitOrAssign { x: u8, y: u8 },
+    // 8xy2
+    BitAndAssign { x: u8, y: u8 },
+    // 8xy3
+    BitXorAssign { x: u8, y: u8 },
+    // 8xy4
+    AddAssign { x: u8, y: u8 },
+    // 8xy5
+    SubAssign { x: u8, y: u8 },
+    // 8xy6
+    ShiftRight { x: u8, y: u8 },
+    // 8xy7
+    SubFrom { x: u8, y: u8 },
+    // 8xyE
+    ShiftLeft { x: u8, y: u8 },
+    // 9xy0
+    SkipIfNotEquals { x: u8, y: u8 },
+    // Annn
+    SetI { nnn: u16 },
+    // Bnnn (or Bxnn with the jump_uses_vx quirk)
+    JumpPlusV0 { nnn: u16 },
+    // Cxkk
+    Random { x: u8, kk: u8 },
+    // Dxyn. Dxy0 draws a 16x16 sprite in SUPER-CHIP, and nothing at all in Chip-8.
+    Draw { x: u8, y: u8, n: u8 },
+    // Ex9E
+    SkipIfPressed { x: u8 },
+    // ExA1
+    SkipIfNotPressed { x: u8 },
+    // F000 nnnn (XO-CHIP). The address is in the word after the opcode, so it isn't part of the decoded instruction.
+    SetILong,
+    // Fx07
+    GetDelay { x: u8 },
+    // Fx0A
+    WaitForKey { x: u8 },
+    // Fx15
+    SetDelay { x: u8 },
+    // Fx18
+    SetSound { x: u8 },
+    // Fx1E
+    AddAssignI { x: u8 },
+    // Fx29
+    SetIToFont { x: u8 },
+    // Fx30 (SUPER-CHIP)
+    SetIToBigFont { x: u8 },
+    // Fx33
+    Bcd { x: u8 },
+    // Fx55
+    StoreRegisters { x: u8 },
+    // Fx65
+    RestoreRegisters { x: u8 },
+    // Fx75 (SUPER-CHIP)
+    StoreFlags { x: u8 },
+    // Fx85 (SUPER-CHIP)
+    RestoreFlags { x: u8 },
+}
+
+// The opcode doesn't correspond to any instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownOpcode {
+    pub opcode: u16,
+}
+
+impl fmt::Display for UnknownOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown opcode {:04X}", self.opcode)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownOpcode {}
+
+pub fn decode(opcode: u16) -> Result<Instruction, UnknownOpcode> {
+    use Instruction::*;
+
+    let x: u8 = ((opcode & 0x0F00) >> 8) as u8;
+    let y: u8 = ((opcode & 0x00F0) >> 4) as u8;
+    let n: u8 = (opcode & 0x000F) as u8;
+    let kk: u8 = (opcode & 0x00FF) as u8;
+    let nnn: u16 = opcode & 0x0FFF;
+
+    let instruction: Instruction = match opcode >> 12 {
+        0x0 => match opcode {
+            0x00E0 => ClearScreen,
+            0x00EE => ReturnFromSubroutine,
+            0x00C0..=0x00CF => ScrollDown { n },
+            0x00FB => ScrollRight,
+            0x00FC => ScrollLeft,
+            0x00FD => Exit,
+            0x00FE => Lores,
+            0x00FF => Hires,
+            0x00D0..=0x00DF => ScrollUp { n },
+            0x0230 => HiresClearScreen,
+            _ => JumpToMachineCode { nnn },
+        },
+        0x1 => JumpToAddress { nnn },
+        0x2 => CallSubroutine { nnn },
+        0x3 => SkipIfEqualsData { x, kk },
+        0x4 => SkipIfNotEqualsData { x, kk },
+        0x5 => match n {
+            0x0 => SkipIfEquals { x, y },
+            0x2 => StoreRange { x, y },
+            0x3 => RestoreRange { x, y },
+            _ => return Err(UnknownOpcode { opcode }),
+        },
+        0x6 => SetData { x, kk },
+        0x7 => AddAssignData { x, kk },
+        0x8 => match n {
+            0x0 => Set { x, y },
+            0x1 => BitOrAssign { x, y },
+            0x2 => BitAndAssign { x, y },
+            0x3 => BitXorAssign { x, y },
+            0x4 => AddAssign { x, y },
+            0x5 => SubAssign { x, y },
+            0x6 => ShiftRight { x, y },
+            0x7 => SubFrom { x, y },
+            0xE => ShiftLeft { x, y },
+            _ => return Err(UnknownOpcode { opcode }),
+        },
+        0x9 if n == 0x0 => SkipIfNotEquals { x, y },
+        0xA => SetI { nnn },
+        0xB => JumpPlusV0 { nnn },
+        0xC => Random { x, kk },
+        0xD => Draw { x, y, n },
+        0xE => match kk {
+            0x9E => SkipIfPressed { x },
+            0xA1 => SkipIfNotPressed { x },
+            _ => return Err(UnknownOpcode { opcode }),
+        },
+        0xF => match kk {
+            0x00 if x == 0x0 => SetILong,
+            0x07 => GetDelay { x },
+            0x0A => WaitForKey { x },
+            0x15 => SetDelay { x },
+            0x18 => SetSound { x },
+            0x1E => AddAssignI { x },
+            0x29 => SetIToFont { x },
+            0x30 => SetIToBigFont { x },
+            0x33 => Bcd { x },
+            0x55 => StoreRegisters { x },
+            0x65 => RestoreRegisters { x },
+            0x75 => StoreFlags { x },
+            0x85 => RestoreFlags { x },
+            _ => return Err(UnknownOpcode { opcode }),
+        },
+        _ => return Err(UnknownOpcode { opcode }),
+    };
+    Ok(instruction)
+}
+
+impl Instruction {
+    // The opcode this instruction was decoded from, so decode(instruction.opcode()) == Ok(instruction) for anything decode()
+    // returned. That doesn't hold for instructions built by hand with fields too big for their nibbles (x > 0xF, nnn > 0xFFF,
+    // and so on), which get masked into their neighbours. SetILong is only the F000 half of F000 nnnn; the address isn't included.
+    pub fn opcode(&self) -> u16 {
+        use Instruction::*;
+
+        let xkk = |top: u16, x: u8, kk: u8| top << 12 | (x as u16) << 8 | kk as u16;
+        let xyn = |top: u16, x: u8, y: u8, n: u8| {
+            top << 12 | (x as u16) << 8 | (y as u16) << 4 | n as u16
+        };
+
+        match *self {
+            JumpToMachineCode { nnn } => nnn,
+            ClearScreen => 0x00E0,
+            ReturnFromSubroutine => 0x00EE,
+            ScrollDown { n } => 0x00C0 | n as u16,
+            ScrollRight => 0x00FB,
+            ScrollLeft => 0x00FC,
+            Exit => 0x00FD,
+            Lores => 0x00FE,
+            Hires => 0x00FF,
+            ScrollUp { n } => 0x00D0 | n as u16,
+            HiresClearScreen => 0x0230,
+            JumpToAddress { nnn } => 0x1000 | nnn,
+            CallSubroutine { nnn } => 0x2000 | nnn,
+            SkipIfEqualsData { x, kk } => xkk(0x3, x, kk),
+            SkipIfNotEqualsData { x, kk } => xkk(0x4, x, kk),
+            SkipIfEquals { x, y } => xyn(0x5, x, y, 0x0),
+            StoreRange { x, y } => xyn(0x5, x, y, 0x2),
+            RestoreRange { x, y } => xyn(0x5, x, y, 0x3),
+            SetData { x, kk } => xkk(0x6, x, kk),
+            AddAssignData { x, kk } => xkk(0x7, x, kk),
+            Set { x, y } => xyn(0x8, x, y, 0x0),
+            BitOrAssign { x, y } => xyn(0x8, x, y, 0x1),
+            BitAndAssign { x, y } => xyn(0x8, x, y, 0x2),
+            BitXorAssign { x, y } => xyn(0x8, x, y, 0x3),
+            AddAssign { x, y } => xyn(0x8, x, y, 0x4),
+            SubAssign { x, y } => xyn(0x8, x, y, 0x5),
+            ShiftRight { x, y } => xyn(0x8, x, y, 0x6),
+            SubFrom { x, y } => xyn(0x8, x, y, 0x7),
+            ShiftLeft { x, y } => xyn(0x8, x, y, 0xE),
+            SkipIfNotEquals { x, y } => xyn(0x9, x, y, 0x0),
+            SetI { nnn } => 0xA000 | nnn,
+            JumpPlusV0 { nnn } => 0xB000 | nnn,
+            Random { x, kk } => xkk(0xC, x, kk),
+            Draw { x, y, n } => xyn(0xD, x, y, n),
+            SkipIfPressed { x } => xkk(0xE, x, 0x9E),
+            SkipIfNotPressed { x } => xkk(0xE, x, 0xA1),
+            SetILong => 0xF000,
+            GetDelay { x } => xkk(0xF, x, 0x07),
+            WaitForKey { x } => xkk(0xF, x, 0x0A),
+            SetDelay { x } => xkk(0xF, x, 0x15),
+            SetSound { x } => xkk(0xF, x, 0x18),
+            AddAssignI { x } => xkk(0xF, x, 0x1E),
+            SetIToFont { x } => xkk(0xF, x, 0x29),
+            SetIToBigFont { x } => xkk(0xF, x, 0x30),
+            Bcd { x } => xkk(0xF, x, 0x33),
+            StoreRegisters { x } => xkk(0xF, x, 0x55),
+            RestoreRegisters { x } => xkk(0xF, x, 0x65),
+            StoreFlags { x } => xkk(0xF, x, 0x75),
+            RestoreFlags { x } => xkk(0xF, x, 0x85),
+        }
+    }
+    // The opcode pattern this instruction matches, as the references write it (e.g. "8xy4"). Handy for grouping instructions
+    // by kind.
+    pub fn pattern(&self) -> &'static str {
+        use Instruction::*;
+
+        match self {
+            JumpToMachineCode { .. } => "0nnn",
+            ClearScreen => "00E0",
+            ReturnFromSubroutine => "00EE",
+            ScrollDown { .. } => "00Cn",
+            ScrollRight => "00FB",
+            ScrollLeft => "00FC",
+            Exit => "00FD",
+            Lores => "00FE",
+            Hires => "00FF",
+            ScrollUp { .. } => "00Dn",
+            HiresClearScreen => "0230",
+            JumpToAddress { .. } => "1nnn",
+            CallSubroutine { .. } => "2nnn",
+            SkipIfEqualsData { .. } => "3xkk",
+            SkipIfNotEqualsData { .. } => "4xkk",
+            SkipIfEquals { .. } => "5xy0",
+            StoreRange { .. } => "5xy2",
+            RestoreRange { .. } => "5xy3",
+            SetData { .. } => "6xkk",
+            AddAssignData { .. } => "7xkk",
+            Set { .. } => "8xy0",
+            BitOrAssign { .. } => "8xy1",
+            BitAndAssign { .. } => "8xy2",
+            BitXorAssign { .. } => "8xy3",
+            AddAssign { .. } => "8xy4",
+            SubAssign { .. } => "8xy5",
+            ShiftRight { .. } => "8xy6",
+            SubFrom { .. } => "8xy7",
+            ShiftLeft { .. } => "8xyE",
+            SkipIfNotEquals { .. } => "9xy0",
+            SetI { .. } => "Annn",
+            JumpPlusV0 { .. } => "Bnnn",
+            Random { .. } => "Cxkk",
+            Draw { n: 0, .. } => "Dxy0",
+            Draw { .. } => "Dxyn",
+            SkipIfPressed { .. } => "Ex9E",
+            SkipIfNotPressed { .. } => "ExA1",
+            SetILong => "F000",
+            GetDelay { .. } => "Fx07",
+            WaitForKey { .. } => "Fx0A",
+            SetDelay { .. } => "Fx15",
+            SetSound { .. } => "Fx18",
+            AddAssignI { .. } => "Fx1E",
+            SetIToFont { .. } => "Fx29",
+            SetIToBigFont { .. } => "Fx30",
+            Bcd { .. } => "Fx33",
+            StoreRegisters { .. } => "Fx55",
+            RestoreRegisters { .. } => "Fx65",
+            StoreFlags { .. } => "Fx75",
+            RestoreFlags { .. } => "Fx85",
+        }
+    }
+    // Instructions SUPER-CHIP added. XO-CHIP understands these too. Dxy0 counts, as it only draws anything in SUPER-CHIP.
+    pub fn is_super_chip(&self) -> bool {
+        matches!(
+            self,
+            Instruction::ScrollDown { .. }
+                | Instruction::ScrollRight
+                | Instruction::ScrollLeft
+                | Instruction::Exit
+                | Instruction::Lores
+                | Instruction::Hires
+                | Instruction::Draw { n: 0, .. }
+                | Instruction::SetIToBigFont { .. }
+                | Instruction::StoreFlags { .. }
+                | Instruction::RestoreFlags { .. }
+        )
+    }
+    // Instructions only XO-CHIP understands.
+    pub fn is_xo_chip(&self) -> bool {
+        matches!(
+            self,
+            Instruction::ScrollUp { .. }
+                | Instruction::StoreRange { .. }
+                | Instruction::RestoreRange { .. }
+                | Instruction::SetILong
+        )
+    }
+}
+
+// Mnemonics mostly follow Cowgod's reference (http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#3.1). Bytes are written as 0xNN
+// and addresses as 0xNNN.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Instruction::*;
+
+        match *self {
+            JumpToMachineCode { nnn } => write!(f, "SYS {:#05X}", nnn),
+            ClearScreen => write!(f, "CLS"),
+            ReturnFromSubroutine => write!(f, "RET"),
+            ScrollDown { n } => write!(f, "SCD {}", n),
+            ScrollRight => write!(f, "SCR"),
+            ScrollLeft => write!(f, "SCL"),
+            Exit => write!(f, "EXIT"),
+            Lores => write!(f, "LOW"),
+            Hires => write!(f, "HIGH"),
+            ScrollUp { n } => write!(f, "SCU {}", n),
+            HiresClearScreen => write!(f, "HCLS"),
+            JumpToAddress { nnn } => write!(f, "JP {:#05X}", nnn),
+            CallSubroutine { nnn } => write!(f, "CALL {:#05X}", nnn),
+            SkipIfEqualsData { x, kk } => write!(f, "SE V{:X}, {:#04X}", x, kk),
+            SkipIfNotEqualsData { x, kk } => write!(f, "SNE V{:X}, {:#04X}", x, kk),
+            SkipIfEquals { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            StoreRange { x, y } => write!(f, "SAVE V{:X} - V{:X}", x, y),
+            RestoreRange { x, y } => write!(f, "LOAD V{:X} - V{:X}", x, y),
+            SetData { x, kk } => write!(f, "LD V{:X}, {:#04X}", x, kk),
+            AddAssignData { x, kk } => write!(f, "ADD V{:X}, {:#04X}", x, kk),
+            Set { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            BitOrAssign { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            BitAndAssign { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            BitXorAssign { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            AddAssign { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            SubAssign { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            ShiftRight { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            SubFrom { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            ShiftLeft { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            SkipIfNotEquals { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            SetI { nnn } => write!(f, "LD I, {:#05X}", nnn),
+            JumpPlusV0 { nnn } => write!(f, "JP V0, {:#05X}", nnn),
+            Random { x, kk } => write!(f, "RND V{:X}, {:#04X}", x, kk),
+            Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            SkipIfPressed { x } => write!(f, "SKP V{:X}", x),
+            SkipIfNotPressed { x } => write!(f, "SKNP V{:X}", x),
+            SetILong => write!(f, "LD I, LONG"),
+            GetDelay { x } => write!(f, "LD V{:X}, DT", x),
+            WaitForKey { x } => write!(f, "LD V{:X}, K", x),
+            SetDelay { x } => write!(f, "LD DT, V{:X}", x),
+            SetSound { x } => write!(f, "LD ST, V{:X}", x),
+            AddAssignI { x } => write!(f, "ADD I, V{:X}", x),
+            SetIToFont { x } => write!(f, "LD F, V{:X}", x),
+            SetIToBigFont { x } => write!(f, "LD HF, V{:X}", x),
+            Bcd { x } => write!(f, "LD B, V{:X}", x),
+            StoreRegisters { x } => write!(f, "LD [I], V{:X}", x),
+            RestoreRegisters { x } => write!(f, "LD V{:X}, [I]", x),
+            StoreFlags { x } => write!(f, "LD R, V{:X}", x),
+            RestoreFlags { x } => write!(f, "LD V{:X}, R", x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An opcode for every pattern, with fields that aren't all the same so a swapped x and y would show.
+    const EXAMPLES: [(u16, &str); 50] = [
+        (0x0123, "0nnn"),
+        (0x00E0, "00E0"),
+        (0x00EE, "00EE"),
+        (0x00C7, "00Cn"),
+        (0x00FB, "00FB"),
+        (0x00FC, "00FC"),
+        (0x00FD, "00FD"),
+        (0x00FE, "00FE"),
+        (0x00FF, "00FF"),
+        (0x00D3, "00Dn"),
+        (0x0230, "0230"),
+        (0x1ABC, "1nnn"),
+        (0x2ABC, "2nnn"),
+        (0x31AB, "3xkk"),
+        (0x41AB, "4xkk"),
+        (0x5120, "5xy0"),
+        (0x5122, "5xy2"),
+        (0x5213, "5xy3"),
+        (0x61AB, "6xkk"),
+        (0x71AB, "7xkk"),
+        (0x8120, "8xy0"),
+        (0x8121, "8xy1"),
+        (0x8122, "8xy2"),
+        (0x8123, "8xy3"),
+        (0x8124, "8xy4"),
+        (0x8125, "8xy5"),
+        (0x8126, "8xy6"),
+        (0x8127, "8xy7"),
+        (0x812E, "8xyE"),
+        (0x9120, "9xy0"),
+        (0xAABC, "Annn"),
+        (0xBABC, "Bnnn"),
+        (0xC1AB, "Cxkk"),
+        (0xD120, "Dxy0"),
+        (0xD12F, "Dxyn"),
+        (0xE19E, "Ex9E"),
+        (0xE1A1, "ExA1"),
+        (0xF000, "F000"),
+        (0xF107, "Fx07"),
+        (0xF10A, "Fx0A"),
+        (0xF115, "Fx15"),
+        (0xF118, "Fx18"),
+        (0xF11E, "Fx1E"),
+        (0xF129, "Fx29"),
+        (0xF130, "Fx30"),
+        (0xF133, "Fx33"),
+        (0xF155, "Fx55"),
+        (0xF165, "Fx65"),
+        (0xF175, "Fx75"),
+        (0xF185, "Fx85"),
+    ];
+
+    #[test]
+    fn every_pattern_decodes_and_encodes_back() {
+        for (opcode, pattern) in EXAMPLES {
+            let instruction: Instruction = decode(opcode).unwrap();
+            assert_eq!(instruction.pattern(), pattern, "{:04X}", opcode);
+            assert_eq!(instruction.opcode(), opcode, "{:04X}", opcode);
+            assert_eq!(decode(instruction.opcode()), Ok(instruction));
+        }
+    }
+
+    #[test]
+    fn every_decodable_opcode_encodes_back() {
+        for opcode in 0..=u16::MAX {
+            if let Ok(instruction) = decode(opcode) {
+                assert_eq!(instruction.opcode(), opcode, "{:04X}", opcode);
+            }
+        }
+    }
+
+    #[test]
+    fn opcodes_in_the_gaps_dont_decode() {
+        for opcode in [
+            0x5121, 0x5124, 0x8128, 0x812F, 0x9121, 0xE19F, 0xF100, 0xF1FF,
+        ] {
+            assert_eq!(decode(opcode), Err(UnknownOpcode { opcode }));
+        }
+    }
+
+    #[test]
+    fn super_chip_and_xo_chip_opcodes_are_told_apart() {
+        for (opcode, super_chip, xo_chip) in [
+            (0x00C1, true, false),
+            (0x00FF, true, false),
+            (0xD120, true, false),
+            (0xD121, false, false),
+            (0xF175, true, false),
+            (0x00D1, false, true),
+            (0x5122, false, true),
+            (0xF000, false, true),
+        ] {
+            let instruction: Instruction = decode(opcode).unwrap();
+            assert_eq!(instruction.is_super_chip(), super_chip, "{:04X}", opcode);
+            assert_eq!(instruction.is_xo_chip(), xo_chip, "{:04X}", opcode);
+        }
+    }
+}
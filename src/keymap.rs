@@ -0,0 +1,113 @@
+// Which physical keys press which Chip-8 keys, for the SDL frontend. It belongs to SdlInput, which translates key presses
+// with it, and the keypad overlay labels its keys from a copy. Settings files can rebind keys (see Settings::keymap), but
+// the machine never knows which physical key pressed what.
+use sdl2::keyboard::Keycode;
+
+// The physical keys the Chip-8 keypad is mapped to by default, laid out the same way as the original hex keypad:
+// 1 2 3 C      1 2 3 4
+// 4 5 6 D  ->  Q W E R
+// 7 8 9 E      A S D F
+// A 0 B F      Z X C V
+pub static DEFAULT_KEYMAP: [(Keycode, u8); 16] = [
+    (Keycode::Num1, 0x1),
+    (Keycode::Num2, 0x2),
+    (Keycode::Num3, 0x3),
+    (Keycode::Num4, 0xC),
+    (Keycode::Q, 0x4),
+    (Keycode::W, 0x5),
+    (Keycode::E, 0x6),
+    (Keycode::R, 0xD),
+    (Keycode::A, 0x7),
+    (Keycode::S, 0x8),
+    (Keycode::D, 0x9),
+    (Keycode::F, 0xE),
+    (Keycode::Z, 0xA),
+    (Keycode::X, 0x0),
+    (Keycode::C, 0xB),
+    (Keycode::V, 0xF),
+];
+
+// (physical key, Chip-8 key) pairs, in the order they were bound. A Chip-8 key can have several physical keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: Vec<(Keycode, u8)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            bindings: DEFAULT_KEYMAP.to_vec(),
+        }
+    }
+}
+
+impl Keymap {
+    // Binds the physical key with the given SDL name (e.g. "W", "Up", "Keypad 5") to a Chip-8 key, replacing whatever it was bound to.
+    pub fn remap(&mut self, key_name: &str, chip_key: u8) -> Result<(), String> {
+        let keycode: Keycode = match Keycode::from_name(key_name) {
+            Some(keycode) => keycode,
+            None => return Err(format!("unknown key '{}'", key_name)),
+        };
+        if chip_key > 0xF {
+            return Err(format!("{:#x} isn't a Chip-8 key", chip_key));
+        }
+
+        self.bindings.retain(|(k, _)| *k != keycode);
+        self.bindings.push((keycode, chip_key));
+        Ok(())
+    }
+    // The Chip-8 keys a physical key presses.
+    pub fn keys_bound_to(&self, keycode: Keycode) -> Vec<u8> {
+        self.bindings
+            .iter()
+            .filter(|(k, _)| *k == keycode)
+            .map(|(_, key)| *key)
+            .collect()
+    }
+    // The physical keys bound to a Chip-8 key, in the order they were bound.
+    pub fn physical_keys(&self, chip_key: u8) -> Vec<Keycode> {
+        self.bindings
+            .iter()
+            .filter(|(_, key)| *key == chip_key)
+            .map(|(k, _)| *k)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_is_the_hex_keypad_layout() {
+        let keymap: Keymap = Keymap::default();
+        assert_eq!(keymap.keys_bound_to(Keycode::Num4), vec![0xC]);
+        assert_eq!(keymap.keys_bound_to(Keycode::X), vec![0x0]);
+        assert_eq!(keymap.physical_keys(0xF), vec![Keycode::V]);
+        assert!(keymap.keys_bound_to(Keycode::M).is_empty());
+    }
+
+    #[test]
+    fn remap_replaces_the_physical_keys_binding() {
+        let mut keymap: Keymap = Keymap::default();
+        keymap.remap("W", 0x8).unwrap();
+        assert_eq!(keymap.keys_bound_to(Keycode::W), vec![0x8]);
+        // S still presses 8 as well, and nothing presses 5 any more.
+        assert_eq!(keymap.physical_keys(0x8), vec![Keycode::S, Keycode::W]);
+        assert!(keymap.physical_keys(0x5).is_empty());
+    }
+
+    #[test]
+    fn remap_rejects_unknown_keys_and_chip_keys() {
+        let mut keymap: Keymap = Keymap::default();
+        assert_eq!(
+            keymap.remap("Not A Key", 0x1),
+            Err("unknown key 'Not A Key'".to_string())
+        );
+        assert_eq!(
+            keymap.remap("W", 0x10),
+            Err("0x10 isn't a Chip-8 key".to_string())
+        );
+        assert_eq!(keymap, Keymap::default());
+    }
+}
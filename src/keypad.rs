@@ -0,0 +1,73 @@
+// The 16-key hex keypad. Frontends press and release keys on it; the Ex9E, ExA1, and Fx0A instructions read it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keypad {
+    held: [bool; 16],
+    // Keys that went down since Fx0A last looked, as a bitmask. Fx0A needs this so a key that's pressed and released between
+    // two steps still counts.
+    presses: u16,
+}
+
+impl Keypad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // There are only 16 keys, so only the lowest nybble of key is used. The same goes for the other methods.
+    pub fn press(&mut self, key: u8) {
+        let key: usize = (key & 0xF) as usize;
+
+        self.held[key] = true;
+        self.presses |= 1 << key;
+    }
+    pub fn release(&mut self, key: u8) {
+        self.held[(key & 0xF) as usize] = false;
+    }
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.held[(key & 0xF) as usize]
+    }
+    pub fn release_all(&mut self) {
+        self.held = [false; 16];
+    }
+    // Makes the held keys match other's, pressing and releasing keys as needed so new presses are seen by Fx0A.
+    pub(crate) fn sync_from(&mut self, other: &Keypad) {
+        for key in 0..16 {
+            match (self.is_pressed(key), other.is_pressed(key)) {
+                (false, true) => self.press(key),
+                (true, false) => self.release(key),
+                _ => {}
+            }
+        }
+    }
+    // The held keys and pending presses as bitmasks (bit n is key n), for save states.
+    #[cfg(feature = "std")]
+    pub(crate) fn to_bits(self) -> (u16, u16) {
+        let held: u16 = (0..16)
+            .filter(|&key| self.held[key])
+            .fold(0, |bits, key| bits | 1 << key);
+        (held, self.presses)
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn from_bits(held: u16, presses: u16) -> Self {
+        let mut keypad = Keypad {
+            held: [false; 16],
+            presses,
+        };
+        for (key, down) in keypad.held.iter_mut().enumerate() {
+            *down = held & (1 << key) != 0;
+        }
+        keypad
+    }
+    // Forgets about earlier presses, so the next take_press only sees keys pressed from now on.
+    pub(crate) fn clear_presses(&mut self) {
+        self.presses = 0;
+    }
+    // The lowest key pressed since the last clear_presses, if any. Returned keys aren't returned again.
+    pub(crate) fn take_press(&mut self) -> Option<u8> {
+        if self.presses == 0 {
+            return None;
+        }
+        let key: u8 = self.presses.trailing_zeros() as u8;
+        self.presses &= !(1 << key);
+        Some(key)
+    }
+}
@@ -0,0 +1,270 @@
+// The keypad overlay (F1), for seeing what the machine thinks is pressed. It's the 16 keys laid out as on the original
+// hex keypad, in the bottom right corner of the screen, each labelled with its Chip-8 digit and the physical key bound to
+// it, so it doubles as a reminder of the controls. Held keys are drawn inverted, and the key Fx0A last took blinks for a
+// moment. Like debug_layout, where everything goes is worked out from sizes alone, in logical pixels.
+use crate::debug_hud::{CHAR_ADVANCE, LINE_HEIGHT};
+use crate::debug_layout::Area;
+use crate::keymap::Keymap;
+use crate::ChipEight;
+
+// The keys as the original keypad has them, row by row.
+pub const KEYPAD_ROWS: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+// How many characters a physical key's label is cut to, and the space left around a cell's text.
+pub const LABEL_CHARS: usize = 3;
+const MARGIN: usize = 1;
+
+// How many frames the key Fx0A took blinks for, and how many frames each blink lasts.
+pub const FLASH_FRAMES: u64 = 30;
+const BLINK_FRAMES: u64 = 5;
+
+// A key's place in the overlay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeypadCell {
+    pub key: u8,
+    pub area: Area,
+}
+
+impl KeypadCell {
+    // Where line row of the cell's text starts, for text chars characters long, centred in the cell.
+    pub fn text_origin(&self, row: usize, chars: usize) -> (usize, usize) {
+        let text_width: usize = (chars.min(LABEL_CHARS) * CHAR_ADVANCE).saturating_sub(1);
+        (
+            self.area.x + (self.area.width - text_width) / 2,
+            self.area.y + MARGIN + row * LINE_HEIGHT,
+        )
+    }
+}
+
+// Where everything goes: the whole overlay (which is cleared to the background first), the cells in KEYPAD_ROWS order,
+// and the lines around and between them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeypadLayout {
+    pub area: Area,
+    pub cells: Vec<KeypadCell>,
+    pub lines: Vec<Area>,
+}
+
+impl KeypadLayout {
+    // The key whose cell the logical pixel (x, y) is in, e.g. to tell which key a click landed on. The lines between
+    // cells belong to no key.
+    pub fn key_at(&self, x: usize, y: usize) -> Option<u8> {
+        self.cells
+            .iter()
+            .find(|cell| {
+                (cell.area.x..cell.area.x + cell.area.width).contains(&x)
+                    && (cell.area.y..cell.area.y + cell.area.height).contains(&y)
+            })
+            .map(|cell| cell.key)
+    }
+}
+
+// The size of a cell in logical pixels: two lines of text (the digit, then the label) and their margins.
+fn cell_size() -> (usize, usize) {
+    (
+        LABEL_CHARS * CHAR_ADVANCE - 1 + MARGIN * 2,
+        2 * LINE_HEIGHT - 1 + MARGIN * 2,
+    )
+}
+
+// Lays the overlay out in the bottom right corner of a screen screen logical pixels big, or returns None if it doesn't
+// fit.
+pub fn keypad_layout(screen: (usize, usize)) -> Option<KeypadLayout> {
+    let (cell_width, cell_height) = cell_size();
+    let (width, height) = (cell_width * 4 + 5, cell_height * 4 + 5);
+    if width > screen.0 || height > screen.1 {
+        return None;
+    }
+    let (left, top) = (screen.0 - width, screen.1 - height);
+
+    let mut lines: Vec<Area> = vec![];
+    for i in 0..5 {
+        lines.push(Area {
+            x: left + i * (cell_width + 1),
+            y: top,
+            width: 1,
+            height,
+        });
+        lines.push(Area {
+            x: left,
+            y: top + i * (cell_height + 1),
+            width,
+            height: 1,
+        });
+    }
+    let mut cells: Vec<KeypadCell> = vec![];
+    for (row, keys) in KEYPAD_ROWS.iter().enumerate() {
+        for (column, &key) in keys.iter().enumerate() {
+            cells.push(KeypadCell {
+                key,
+                area: Area {
+                    x: left + 1 + column * (cell_width + 1),
+                    y: top + 1 + row * (cell_height + 1),
+                    width: cell_width,
+                    height: cell_height,
+                },
+            });
+        }
+    }
+    Some(KeypadLayout {
+        area: Area {
+            x: left,
+            y: top,
+            width,
+            height,
+        },
+        cells,
+        lines,
+    })
+}
+
+// What the overlay shows, indexed by Chip-8 key: whether each key is drawn inverted, and the label of the physical key
+// keymap binds to it last (so a key remapped in the settings shows its new binding).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeypadView {
+    pub inverted: [bool; 16],
+    pub labels: Vec<String>,
+}
+
+impl KeypadView {
+    pub fn new(chip_eight: &ChipEight, keymap: &Keymap) -> Self {
+        // The blinking key is inverted from how it'd otherwise be drawn every other blink, so it shows held or not.
+        let blinking: Option<u8> = chip_eight.key_taken().and_then(|(key, frame)| {
+            let age: u64 = chip_eight.frame_count().saturating_sub(frame);
+            (age < FLASH_FRAMES && (age / BLINK_FRAMES).is_multiple_of(2)).then_some(key)
+        });
+        let mut inverted: [bool; 16] = [false; 16];
+        for (key, inverted) in inverted.iter_mut().enumerate() {
+            *inverted = chip_eight.keypad().is_pressed(key as u8) != (blinking == Some(key as u8));
+        }
+        KeypadView {
+            inverted,
+            labels: (0..16)
+                .map(|key| match keymap.physical_keys(key).last() {
+                    Some(keycode) => key_label(&keycode.name()),
+                    None => String::new(),
+                })
+                .collect(),
+        }
+    }
+}
+
+// A physical key's SDL name cut down to LABEL_CHARS characters: "W" stays "W", "Keypad 5" is "KP5", "Left Shift" is "LS",
+// and a few common long names get their usual abbreviations.
+pub fn key_label(name: &str) -> String {
+    let label: String = match name {
+        "Space" => "SPC".to_string(),
+        "Return" => "RET".to_string(),
+        "Escape" => "ESC".to_string(),
+        "Backspace" => "BS".to_string(),
+        "Down" => "DN".to_string(),
+        "Left" => "LT".to_string(),
+        "Right" => "RT".to_string(),
+        _ => match name.strip_prefix("Keypad ") {
+            Some(rest) => format!("KP{}", rest),
+            None if name.contains(' ') => name
+                .split(' ')
+                .filter_map(|word| word.chars().next())
+                .collect(),
+            None => name.to_string(),
+        },
+    };
+    label.to_uppercase().chars().take(LABEL_CHARS).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_overlay_sits_in_the_bottom_right_corner() {
+        assert_eq!(keypad_layout((64, 32)), None, "too short for the overlay");
+        let layout: KeypadLayout = keypad_layout((128, 64)).unwrap();
+        assert_eq!(
+            layout.area,
+            Area {
+                x: 71,
+                y: 7,
+                width: 57,
+                height: 57
+            }
+        );
+        assert_eq!(layout.lines.len(), 10);
+
+        // Cells are 13 pixels square, with a line between each and around them all.
+        let keys: Vec<u8> = layout.cells.iter().map(|cell| cell.key).collect();
+        assert_eq!(keys, KEYPAD_ROWS.concat());
+        for (key, x, y) in [(0x1, 72, 8), (0xC, 114, 8), (0x0, 86, 50), (0xF, 114, 50)] {
+            let cell: &KeypadCell = layout.cells.iter().find(|cell| cell.key == key).unwrap();
+            assert_eq!(
+                cell.area,
+                Area {
+                    x,
+                    y,
+                    width: 13,
+                    height: 13
+                },
+                "key {:X}",
+                key
+            );
+        }
+
+        // A screen the overlay just fits is all overlay.
+        assert_eq!(keypad_layout((57, 57)).unwrap().area.x, 0);
+        assert_eq!(keypad_layout((56, 57)), None);
+    }
+
+    #[test]
+    fn points_hit_the_key_whose_cell_they_are_in() {
+        let layout: KeypadLayout = keypad_layout((128, 64)).unwrap();
+        for (x, y, expected) in [
+            (72, 8, Some(0x1)),
+            (84, 20, Some(0x1)),
+            (85, 8, None),
+            (86, 8, Some(0x2)),
+            (100, 40, Some(0x9)),
+            (126, 62, Some(0xF)),
+            (127, 63, None),
+            (71, 7, None),
+            (70, 30, None),
+            (0, 0, None),
+        ] {
+            assert_eq!(layout.key_at(x, y), expected, "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn text_is_centred_in_its_cell() {
+        let layout: KeypadLayout = keypad_layout((128, 64)).unwrap();
+        let cell: KeypadCell = layout.cells[0];
+        // The digit, then the label a line under it. Labels longer than LABEL_CHARS are cut, so they're centred as if
+        // they were that long.
+        assert_eq!(cell.text_origin(0, 1), (77, 9));
+        assert_eq!(cell.text_origin(1, 3), (73, 15));
+        assert_eq!(cell.text_origin(1, 8), (73, 15));
+    }
+
+    #[test]
+    fn key_labels_fit_in_three_characters() {
+        for (name, expected) in [
+            ("W", "W"),
+            ("a", "A"),
+            ("Tab", "TAB"),
+            ("Keypad 5", "KP5"),
+            ("Keypad Enter", "KPE"),
+            ("Left Shift", "LS"),
+            ("Space", "SPC"),
+            ("Return", "RET"),
+            ("Backspace", "BS"),
+            ("Down", "DN"),
+            ("CapsLock", "CAP"),
+        ] {
+            assert_eq!(key_label(name), expected, "{}", name);
+        }
+    }
+}
@@ -1,53 +1,501 @@
-use sdl2::audio::{AudioCallback, AudioSpecDesired};
-use std::collections::HashSet;
-
-// The audio code is pretty much lifted 1:1 from the SDL2 crate's audio example code: https://rust-sdl2.github.io/rust-sdl2/sdl2/audio/index.html
-struct SquareWave {
-    phase_inc: f32,
-    phase: f32,
-    volume: f32,
+// The interpreter itself only needs core, so it can run on microcontrollers. Without the std feature there's no heap either:
+// no hooks, no settings files or ROM database, no run() loop, and only 4KiB of memory.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// The core module below shadows the core crate here, hence the leading ::.
+use ::core::fmt;
+#[cfg(feature = "std")]
+use std::io::Read;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+#[cfg(feature = "std")]
+mod asm;
+#[cfg(feature = "std")]
+pub mod backend;
+mod builder;
+mod bus;
+mod checksum;
+mod compare;
+pub mod core;
+#[cfg(feature = "std")]
+mod coverage;
+#[cfg(feature = "std")]
+mod crash_dump;
+#[cfg(feature = "sdl")]
+mod debug_hud;
+#[cfg(feature = "sdl")]
+mod debug_layout;
+#[cfg(feature = "std")]
+mod debug_protocol;
+#[cfg(feature = "std")]
+mod debug_server;
+#[cfg(feature = "std")]
+mod debugger;
+mod dirty;
+#[cfg(feature = "std")]
+mod disasm;
+mod error;
+mod font;
+#[cfg(feature = "sdl")]
+pub mod frontend_sdl;
+#[cfg(feature = "tui")]
+pub mod frontend_tui;
+#[cfg(feature = "screenshots")]
+mod gif_recorder;
+#[cfg(feature = "std")]
+mod handle;
+#[cfg(feature = "std")]
+mod headless;
+#[cfg(feature = "std")]
+mod hexdump;
+mod hooks;
+mod instruction;
+#[cfg(feature = "sdl")]
+mod keymap;
+mod keypad;
+#[cfg(feature = "sdl")]
+mod keypad_overlay;
+#[cfg(feature = "std")]
+mod lint;
+#[cfg(feature = "std")]
+mod memory_editor;
+#[cfg(feature = "octo")]
+mod octo;
+#[cfg(feature = "sdl")]
+mod osd;
+mod palette;
+mod phosphor;
+#[cfg(feature = "std")]
+mod profiler;
+#[cfg(feature = "std")]
+mod quirk_search;
+mod random;
+#[cfg(feature = "std")]
+mod recording;
+mod render;
+#[cfg(feature = "std")]
+mod rewind;
+#[cfg(feature = "std")]
+pub mod rom_db;
+#[cfg(feature = "std")]
+mod save_state;
+mod screen;
+#[cfg(feature = "screenshots")]
+mod screenshot;
+#[cfg(feature = "scripting")]
+mod scripting;
+#[cfg(feature = "serde")]
+mod serde_support;
+#[cfg(feature = "std")]
+pub mod settings;
+#[cfg(feature = "sdl")]
+mod splash;
+mod sprite;
+#[cfg(feature = "sdl")]
+mod sprite_viewer;
+mod state_diff;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "std")]
+mod stop;
+#[cfg(feature = "std")]
+mod symbols;
+mod timing;
+#[cfg(feature = "std")]
+mod toml_lite;
+mod tone;
+mod trace;
+#[cfg(feature = "std")]
+mod trace_log;
+#[cfg(feature = "std")]
+mod trace_verify;
+mod variant;
+#[cfg(feature = "std")]
+mod wav_recorder;
+
+#[cfg(feature = "std")]
+pub use asm::{assemble, AsmError};
+pub use builder::{BuildError, ChipEightBuilder};
+pub use bus::MemoryMappedDevice;
+pub use checksum::{crc32, sha1};
+pub use compare::Comparison;
+#[cfg(feature = "std")]
+pub use coverage::CoverageMap;
+#[cfg(feature = "std")]
+pub use crash_dump::{CrashDump, RecentInstruction, RECENT_INSTRUCTIONS};
+#[cfg(feature = "std")]
+pub use debug_protocol::{run_debug_command, DebugSession};
+#[cfg(feature = "std")]
+pub use debug_server::DebugServer;
+#[cfg(feature = "std")]
+pub use debugger::{CallFrame, WatchHit, WatchKind};
+pub use dirty::DirtyRect;
+#[cfg(feature = "std")]
+pub use disasm::{
+    disassemble, disassemble_rom, disassemble_rom_with_symbols, disassemble_with_symbols,
+};
+pub use error::ChipEightError;
+pub use font::{Font, FontSet, BIG_FONT_ADDRESS, FONT_ADDRESS};
+#[cfg(feature = "screenshots")]
+pub use gif_recorder::{GifRecorder, GIF_FPS, GIF_MAX_SECONDS, GIF_SCALE};
+#[cfg(feature = "std")]
+pub use handle::{Command, EmulatorHandle, Frame};
+#[cfg(feature = "std")]
+pub use headless::{parse_input_script, run_headless, HeadlessResult, HEADLESS_SEED};
+#[cfg(feature = "std")]
+pub use hexdump::hex_dump;
+pub use hooks::{DrawRect, HookAction};
+pub use instruction::{decode, Instruction, UnknownOpcode};
+pub use keypad::Keypad;
+#[cfg(feature = "std")]
+pub use lint::{lint_rom, LintFinding, Severity};
+#[cfg(feature = "std")]
+pub use memory_editor::{EditorKey, EditorMark, MemoryEditor, EDITOR_COLUMNS, EDITOR_ROWS};
+#[cfg(feature = "octo")]
+pub use octo::assemble_octo;
+#[cfg(feature = "std")]
+pub use palette::parse_color;
+pub use palette::{Palette, NAMED_COLORS, THEMES};
+pub use phosphor::{Phosphor, PHOSPHOR_FRAMES};
+#[cfg(feature = "std")]
+pub use profiler::Profiler;
+#[cfg(feature = "std")]
+pub use quirk_search::{
+    quirk_combinations, quirk_presets, search_quirks, QuirkCorrelation, QuirkDivergence, QuirkRun,
+    QuirkSearch,
+};
+#[cfg(feature = "std")]
+pub use random::{MockRng, RandomSourceClone};
+pub use random::{RandomSource, Xorshift};
+#[cfg(feature = "std")]
+pub use recording::{KeyEvent, Recorder, Recording, RecordingError, Replayer, RECORDING_VERSION};
+#[cfg(feature = "std")]
+pub use render::screen_from_string;
+pub use render::{RenderError, ScreenTextError};
+#[cfg(feature = "std")]
+pub use rewind::RewindSettings;
+#[cfg(feature = "std")]
+pub use rom_db::{RomDatabase, RomEntry};
+#[cfg(feature = "std")]
+pub use save_state::StateError;
+pub use screen::{Screen, ScreenMode};
+#[cfg(feature = "screenshots")]
+pub use screenshot::SCREENSHOT_SCALE;
+#[cfg(feature = "scripting")]
+pub use scripting::{run_script, ScriptError};
+#[cfg(feature = "std")]
+pub use settings::{Settings, SettingsError};
+pub use sprite::sprite_row;
+#[cfg(feature = "std")]
+pub use sprite::{render_sprite, render_sprite_16};
+#[cfg(feature = "std")]
+pub use state_diff::StateDiff;
+#[cfg(feature = "std")]
+pub use stats::{Stats, StatsReport, STATS_WINDOW};
+#[cfg(feature = "std")]
+pub use stop::StopToken;
+#[cfg(feature = "std")]
+pub use symbols::{Symbol, SymbolError, Symbols};
+pub use timing::{TimingMode, VIP_CYCLES_PER_FRAME};
+#[cfg(feature = "std")]
+pub use toml_lite::TomlError;
+#[cfg(feature = "std")]
+pub use tone::Tone;
+pub use tone::{
+    Envelope, Sound, Waveform, DEFAULT_TONE_HZ, DEFAULT_VOLUME, ENVELOPE_RAMP, MAX_VOLUME,
+    TONE_HZ_RANGE, WAVEFORMS,
+};
+pub use trace::{RegisterSnapshot, TraceEntry};
+#[cfg(feature = "std")]
+pub use trace_log::TraceLog;
+#[cfg(feature = "std")]
+pub use trace_verify::{
+    parse_reference_trace, verify_trace, ExecutedStep, ReferenceStep, TraceDivergence,
+    TraceFormatError, TraceVerification,
+};
+pub use variant::{ChipVariant, Quirks};
+#[cfg(feature = "std")]
+pub use wav_recorder::{SampleTap, WavWriter};
+
+// Classic Chip-8 programs get 4KiB of RAM, but large XO-CHIP programs expect the full 64KiB addressable by F000 nnnn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemorySize {
+    Classic,
+    Extended,
 }
 
-impl AudioCallback for SquareWave {
-    type Channel = f32;
+impl MemorySize {
+    pub fn bytes(&self) -> usize {
+        match self {
+            MemorySize::Classic => 4096,
+            MemorySize::Extended => 65536,
+        }
+    }
+}
 
-    fn callback(&mut self, out: &mut [Self::Channel]) {
-        for x in out.iter_mut() {
-            *x = if self.phase <= 0.5 {
-                self.volume
-            } else {
-                -self.volume
-            };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
+#[derive(Debug)]
+pub enum LoadError {
+    // The ROM couldn't be read.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    // The ROM is zero bytes long, which is almost certainly the wrong file.
+    Empty,
+    // The program doesn't fit in the memory between the start address and the end of RAM.
+    RomTooLarge {
+        size: usize,
+        capacity: usize,
+    },
+    // The file was Octo source that didn't compile.
+    #[cfg(feature = "octo")]
+    Octo(AsmError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            LoadError::Io(e) => write!(f, "couldn't read the ROM: {}", e),
+            LoadError::Empty => write!(f, "the ROM is empty"),
+            LoadError::RomTooLarge { size, capacity } => write!(
+                f,
+                "the ROM is {} bytes but only {} bytes of memory are available",
+                size, capacity
+            ),
+            #[cfg(feature = "octo")]
+            LoadError::Octo(e) => write!(f, "couldn't compile the Octo source: {}", e),
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            #[cfg(feature = "octo")]
+            LoadError::Octo(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// What load_program found out about the program it loaded. The machine keeps a copy, see rom_info().
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RomInfo {
+    // The name of the file the program was loaded from, if it was loaded with load_program_from_path.
+    #[cfg(feature = "std")]
+    pub file_name: Option<String>,
+    pub size: usize,
+    pub crc32: u32,
+    pub sha1: [u8; 20],
+    // The variant the program was written for, once identify_rom has found it in a ROM database.
+    pub variant: Option<ChipVariant>,
+    // The program's title, once identify_rom has found it in a ROM database.
+    #[cfg(feature = "std")]
+    pub title: Option<String>,
+}
+
+impl RomInfo {
+    fn new(program: &[u8]) -> Self {
+        RomInfo {
+            #[cfg(feature = "std")]
+            file_name: None,
+            size: program.len(),
+            crc32: crc32(program),
+            sha1: sha1(program),
+            variant: None,
+            #[cfg(feature = "std")]
+            title: None,
+        }
+    }
+}
+
+// What happened during a call to step().
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StepOutcome {
+    // The instruction cleared, scrolled, or drew to the screen.
+    pub screen_changed: bool,
+    // The machine is blocked on Fx0A until a key is pressed. Further steps won't do anything until a key is pressed on the keypad.
+    pub waiting_for_key: bool,
+    // The before-step hook asked for a pause, or the machine is paused at a breakpoint, so nothing was executed.
+    pub paused: bool,
+    // The address the machine is paused at, when it stopped at a breakpoint or after single_step(). It stays paused until
+    // resume() or single_step() is called.
+    pub breakpoint: Option<u16>,
+    // The instruction touched a watched address. It finished executing, and the machine is now paused before the next one.
+    #[cfg(feature = "std")]
+    pub watchpoint: Option<WatchHit>,
+    // The program has exited with 00FD. Further steps won't do anything until the machine is reset.
+    pub halted: bool,
+}
+
+// What happened during a call to frame().
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameOutput {
+    // Something was drawn, cleared, or scrolled, so the screen needs redrawing.
+    pub screen_dirty: bool,
+    // The beeper should be sounding during this frame: the sound timer was running when it ticked at the start of it. This
+    // is decided once a frame, never per instruction, so a sound timer set to N beeps for exactly N frames (N/60 of a
+    // second) at any instruction rate.
+    pub beep: bool,
+    // The program has exited with 00FD.
+    pub halted: bool,
+}
+
+// Why run() stopped. Fatal errors come back as a ChipEightError instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitReason {
+    // The input backend asked to stop (the window was closed, Esc was pressed, etc.).
+    UserQuit,
+    // The program exited on its own with 00FD.
+    RomExit,
+    // Something outside the emulator asked it to stop through a StopToken.
+    Stopped,
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExitReason::UserQuit => write!(f, "quit by the user"),
+            ExitReason::RomExit => write!(f, "the program exited"),
+            ExitReason::Stopped => write!(f, "stopped by the host"),
+        }
+    }
+}
+
+// With std, memory is sized to the program's needs. Without it there's nowhere to put 64KiB, so machines get a fixed 4KiB.
+#[cfg(feature = "std")]
+type Memory = Box<[u8]>;
+#[cfg(not(feature = "std"))]
+type Memory = [u8; 4096];
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChipEight {
-    // Chip-8 has access to 4KiB RAM. Most programs start at 0x200, as bytes 0x000 to 0x1FF are reserved for the interpreter.
-    memory: [u8; 4096],
+    // Chip-8 has access to 4KiB RAM (64KiB in extended XO-CHIP mode). Most programs start at 0x200, as bytes 0x000 to 0x1FF are reserved for the interpreter.
+    memory: Memory,
     // Chip-8 has a 64x32 monochrome screen. Some later versions had higher resolution displays and color support though.
-    screen: [[bool; 64]; 32],
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    screen: Screen,
     // Chip-8 has a stack that can store up to 16 addresses that the interpreter should return to when a subroutine has finished executing.
-    stack: Vec<u16>,
+    // Only the first sp entries are in use.
+    stack: [u16; STACK_DEPTH],
     // Chip-8 has 16 general-purpose 8-bit registers V0 - VF, although VF is used as a flag by some instructions and should not be used by programs.
     v_registers: [u8; 16],
     // The following are special registers that are separated distinctly from the general-purpose registers
     // The program counter is a 16-bit register that stores the currently executing address
     pc: u16,
+    // Where programs are loaded and where the program counter starts.
+    start_address: u16,
     // The stack pointer is an 8-bit register that points to the topmost level of the stack
     sp: u8,
-    // The I register stores memory addresses. Since there's only 4KiB (0xFFF) RAM, only the lowest 12 bits are used.
+    // The I register stores memory addresses. Addresses are wrapped to the size of RAM whenever I is used to access memory.
     i_register: u16,
     // When greater than 0, the delay timer will decrement by 1 every cycle
     delay_timer: u8,
     // When greater than 0, the sound timer will decrement by 1 every cycle and play a tone (in this case, a square wave middle C note)
     sound_timer: u8,
+    // Which Chip-8 descendant is being emulated. This decides which extension opcodes are valid.
+    variant: ChipVariant,
+    // Quirks set explicitly by the user. When this is None, the variant's quirk preset is used.
+    quirks_override: Option<Quirks>,
+    // SUPER-CHIP's flag registers (the HP48's RPL user flags), which Fx75 and Fx85 copy V0 - Vx to and from. They outlive
+    // resets, as they did on the calculator, which is how programs kept high scores.
+    #[cfg_attr(feature = "serde", serde(default))]
+    flags: [u8; 16],
+    // How many instructions are executed for every 60Hz frame (and timer tick).
+    instructions_per_frame: u32,
+    timing_mode: TimingMode,
+    // In CosmacVip timing mode, how many machine cycles are left in the current frame. This can go negative when the last
+    // instruction of a frame overran the budget, in which case the next frame gets that many fewer cycles.
+    cycle_budget: i32,
+    // How many 60Hz frames have run since the machine was created or reset.
+    #[cfg_attr(feature = "serde", serde(default))]
+    frames: u64,
+    // How many instructions have been executed since the machine was created. This is for measuring speed, not part of
+    // the machine's state, so reset() leaves it alone and it isn't saved.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    instructions: u64,
+    // A copy of the loaded program, so reset() can undo anything a self-modifying program did to itself.
+    #[cfg(feature = "std")]
+    rom: Option<Vec<u8>>,
+    // Hashes and the like for the loaded program. The CRC-32 is used to look it up in the ROM database.
+    #[cfg_attr(feature = "serde", serde(default))]
+    rom_info: Option<RomInfo>,
+    // Where Cxkk gets its random numbers from.
+    #[cfg_attr(feature = "serde", serde(with = "serde_support::rng"))]
+    rng: random::Rng,
+    keypad: Keypad,
+    // The font init_memory puts in memory. Files saved before it could be changed were all in the built-in one.
+    #[cfg_attr(feature = "serde", serde(default = "serde_support::builtin_font"))]
+    font: Font,
+    // Set by Fx0A while it's waiting for a key press.
+    waiting_for_key: bool,
+    // The key Fx0A last took, and the frame it took it in, for the keypad overlay to show. It's only for debugging, so it
+    // isn't saved.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    key_taken: Option<(u8, u64)>,
+    // Set by 00FD. Nothing executes until the machine is reset.
+    halted: bool,
+    // Set by any instruction that modifies the screen, so step() can report it.
+    screen_changed: bool,
+    // The part of the screen that changed since the renderer last asked.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip, default = "serde_support::whole_screen")
+    )]
+    dirty: Option<DirtyRect>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hooks: hooks::Hooks,
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    devices: bus::Devices,
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    stop: stop::StopSlot,
+    // Breakpoints, and whether the machine is paused at one.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    debugger: debugger::Debugger,
+    // Snapshots and an input log for stepping backwards, if that's enabled.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    rewind: rewind::RewindSlot,
+    // Which addresses have been executed.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    coverage: CoverageMap,
+    // The last instructions executed, for crash dumps.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    recent: crash_dump::RecentInstructions,
+}
+
+// How many return addresses the stack can hold.
+const STACK_DEPTH: usize = 16;
+
+// Whether program (loaded at 0x200) has the two-page hires signature: a jump from 0x200 to the patch at 0x260, a machine
+// code call in the patch to switch the VIP's display over, and a program after the patch at 0x2C0.
+fn is_hires_chip8(program: &[u8]) -> bool {
+    let word = |offset: usize| -> Option<u16> {
+        program
+            .get(offset..offset + 2)
+            .map(|word| u16::from_be_bytes([word[0], word[1]]))
+    };
+    let machine_code_call = |word: Option<u16>| matches!(word, Some(op) if op >> 12 == 0 && !matches!(op, 0x0000 | 0x00E0 | 0x00EE));
+
+    word(0x00) == Some(0x1260)
+        && (0x60..0xC0)
+            .step_by(2)
+            .any(|offset| machine_code_call(word(offset)))
+        && word(0xC0).is_some()
 }
 
 // For the sake of my sanity and my fingers, I'm typing these as hexadecimal values, but their binary representation shows an 8x5 sprite of the number at the given index (i.e., SPRITES[0x0] is the sprite for the number 0)
 // A complete table with corresponding binary and hexadecimal values can be found here: http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#2.4
-static SPRITES: [[u8; 5]; 16] = [
+static SPRITES: Font = [
     // Zero (0)
     [0xF0, 0x90, 0x90, 0x90, 0xF0],
     // One (1)
@@ -82,420 +530,828 @@ static SPRITES: [[u8; 5]; 16] = [
     [0xF0, 0x80, 0xF0, 0x80, 0x80],
 ];
 
+#[cfg(feature = "std")]
+impl Default for ChipEight {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ChipEight {
+    // A machine whose RNG is seeded from the OS. Without std there's nothing to seed it from, so use with_seed instead.
+    #[cfg(feature = "std")]
     pub fn new() -> Self {
+        Self::with_memory_size(MemorySize::Classic)
+    }
+    #[cfg(feature = "std")]
+    pub fn with_memory_size(size: MemorySize) -> Self {
+        Self::with_rng(size, random::Xorshift::from_entropy())
+    }
+    // A machine whose RNG starts from seed, the same as new() followed by seed_rng(seed).
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(MemorySize::Classic, random::Xorshift::new(seed))
+    }
+    fn with_rng(size: MemorySize, rng: random::Xorshift) -> Self {
         ChipEight {
-            memory: Self::init_memory(SPRITES),
-            screen: [[false; 64]; 32],
-            stack: Vec::<u16>::with_capacity(16),
+            memory: Self::init_memory(&SPRITES, size),
+            screen: Screen::new(),
+            stack: [0; STACK_DEPTH],
             v_registers: [0; 16],
             pc: 0x200,
+            start_address: 0x200,
             sp: 0,
             i_register: 0,
             delay_timer: 0,
             sound_timer: 0,
+            variant: ChipVariant::default(),
+            quirks_override: None,
+            flags: [0; 16],
+            instructions_per_frame: 10,
+            timing_mode: TimingMode::default(),
+            cycle_budget: 0,
+            frames: 0,
+            instructions: 0,
+            #[cfg(feature = "std")]
+            rom: None,
+            rom_info: None,
+            rng: random::Rng::Xorshift(rng),
+            keypad: Keypad::new(),
+            font: SPRITES,
+            waiting_for_key: false,
+            key_taken: None,
+            halted: false,
+            screen_changed: false,
+            dirty: Some(dirty::WHOLE_SCREEN),
+            hooks: hooks::Hooks::default(),
+            #[cfg(feature = "std")]
+            devices: bus::Devices::default(),
+            #[cfg(feature = "std")]
+            stop: stop::StopSlot::default(),
+            #[cfg(feature = "std")]
+            debugger: debugger::Debugger::default(),
+            #[cfg(feature = "std")]
+            rewind: rewind::RewindSlot::default(),
+            #[cfg(feature = "std")]
+            coverage: CoverageMap::new(size.bytes()),
+            #[cfg(feature = "std")]
+            recent: crash_dump::RecentInstructions::default(),
+        }
+    }
+    // The (width, height) of the display in pixels.
+    pub fn resolution(&self) -> (usize, usize) {
+        (self.screen.width(), self.screen.height())
+    }
+    // The screen, for frontends that would rather not depend on its size.
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+    /// The rows of the screen that are in use at the current resolution, top to bottom, each 128 pixels long however wide
+    /// the screen is (see resolution()).
+    ///
+    /// ```
+    /// use potatocho::{ChipEight, Keypad};
+    ///
+    /// // The IBM logo ROM, which draws the logo and then loops forever.
+    /// let ibm_logo: [u8; 132] = [
+    ///     0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C, 0x61, 0x08, 0xD0, 0x1F, 0x70, 0x09, 0xA2, 0x39, 0xD0, 0x1F,
+    ///     0xA2, 0x48, 0x70, 0x08, 0xD0, 0x1F, 0x70, 0x04, 0xA2, 0x57, 0xD0, 0x1F, 0x70, 0x08, 0xA2, 0x66,
+    ///     0xD0, 0x1F, 0x70, 0x08, 0xA2, 0x75, 0xD0, 0x1F, 0x12, 0x28, 0xFF, 0x00, 0xFF, 0x00, 0x3C, 0x00,
+    ///     0x3C, 0x00, 0x3C, 0x00, 0x3C, 0x00, 0xFF, 0x00, 0xFF, 0xFF, 0x00, 0xFF, 0x00, 0x38, 0x00, 0x3F,
+    ///     0x00, 0x3F, 0x00, 0x38, 0x00, 0xFF, 0x00, 0xFF, 0x80, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0x00,
+    ///     0x80, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0xF8, 0x00, 0xFC, 0x00, 0x3E, 0x00, 0x3F, 0x00, 0x3B,
+    ///     0x00, 0x39, 0x00, 0xF8, 0x00, 0xF8, 0x03, 0x00, 0x07, 0x00, 0x0F, 0x00, 0xBF, 0x00, 0xFB, 0x00,
+    ///     0xF3, 0x00, 0xE3, 0x00, 0x43, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x00, 0x80,
+    ///     0x00, 0xE0, 0x00, 0xE0,
+    /// ];
+    /// let mut chip_eight = ChipEight::with_seed(0);
+    /// chip_eight.load_program_from_slice(&ibm_logo).unwrap();
+    /// for _ in 0..10 {
+    ///     chip_eight.frame(&Keypad::new()).unwrap();
+    /// }
+    ///
+    /// let framebuffer: &[[bool; 128]] = chip_eight.framebuffer();
+    /// assert_eq!(framebuffer.len(), 32);
+    /// // The top left corner of the I, and the gap to the left of it.
+    /// assert!(framebuffer[8][12] && !framebuffer[8][11]);
+    /// let lit: usize = framebuffer.iter().flatten().filter(|&&pixel| pixel).count();
+    /// assert_eq!(lit, 208);
+    /// assert_eq!(chip_eight.pixels_set().count(), lit);
+    /// ```
+    pub fn framebuffer(&self) -> &[[bool; 128]] {
+        self.screen.rows()
+    }
+    // Whether the pixel at (x, y) is lit. Coordinates outside the current resolution are never lit.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.screen.get(x, y)
+    }
+    // The (x, y) coordinates of every lit pixel, row by row.
+    pub fn pixels_set(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.framebuffer().iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, pixel)| **pixel)
+                .map(move |(x, _)| (x as u8, y as u8))
+        })
+    }
+    pub fn variant(&self) -> ChipVariant {
+        self.variant
+    }
+    pub fn set_variant(&mut self, variant: ChipVariant) {
+        self.variant = variant;
+    }
+    pub fn quirks(&self) -> Quirks {
+        match self.quirks_override {
+            Some(quirks) => quirks,
+            None => self.variant.quirks(),
         }
     }
-    fn init_memory(sprites: [[u8; 5]; 16]) -> [u8; 4096] {
-        let mut memory: [u8; 4096] = [0; 4096];
-        for (i, sprite) in sprites.iter().enumerate() {
-            for (j, byte) in sprite.iter().enumerate() {
-                let current_sprite: usize = i * sprite.len();
-                memory[current_sprite + j] = *byte;
+    // Overrides the variant's quirk preset. Passing None goes back to the preset.
+    pub fn set_quirks(&mut self, quirks: Option<Quirks>) {
+        self.quirks_override = quirks;
+    }
+    pub fn instructions_per_frame(&self) -> u32 {
+        self.instructions_per_frame
+    }
+    pub fn set_instructions_per_frame(&mut self, instructions_per_frame: u32) {
+        self.instructions_per_frame = instructions_per_frame.max(1);
+    }
+    pub fn timing_mode(&self) -> TimingMode {
+        self.timing_mode
+    }
+    pub fn set_timing_mode(&mut self, timing_mode: TimingMode) {
+        self.timing_mode = timing_mode;
+        self.cycle_budget = 0;
+    }
+    // How many times frame() has run since the machine was created or reset. Frames spent paused in the debugger don't count.
+    pub fn frame_count(&self) -> u64 {
+        self.frames
+    }
+    // The key Fx0A last took, and the frame_count() it took it in, if it has since the machine was created or reset.
+    pub fn key_taken(&self) -> Option<(u8, u64)> {
+        self.key_taken
+    }
+    // How many instructions have been executed since the machine was created, resets included.
+    pub fn instructions_executed(&self) -> u64 {
+        self.instructions
+    }
+    // Makes Cxkk's random numbers repeatable.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = random::Rng::Xorshift(random::Xorshift::new(seed));
+    }
+    // Replaces the random number generator, e.g. with a MockRng so a test knows what Cxkk will roll. seed_rng() switches
+    // back to the default xorshift.
+    #[cfg(feature = "std")]
+    pub fn set_random_source(
+        &mut self,
+        source: impl random::RandomSource + Clone + Send + 'static,
+    ) {
+        self.rng = random::Rng::Custom(Box::new(source));
+    }
+    pub fn rom_crc32(&self) -> Option<u32> {
+        self.rom_info.as_ref().map(|info| info.crc32)
+    }
+    // What's known about the loaded program, or None if nothing's loaded.
+    pub fn rom_info(&self) -> Option<&RomInfo> {
+        self.rom_info.as_ref()
+    }
+    // Looks the loaded program up in a ROM database and records its title and variant in rom_info(). This doesn't apply the
+    // entry's settings; Settings::from_rom_entry is for that.
+    #[cfg(feature = "std")]
+    pub fn identify_rom<'a>(&mut self, database: &'a RomDatabase) -> Option<&'a RomEntry> {
+        let info: &mut RomInfo = self.rom_info.as_mut()?;
+        let entry: &RomEntry = database.lookup(info.crc32)?;
+        info.title = Some(entry.title.clone());
+        info.variant = Some(entry.variant);
+        Some(entry)
+    }
+    pub fn v_registers(&self) -> &[u8; 16] {
+        &self.v_registers
+    }
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+    pub fn i_register(&self) -> u16 {
+        self.i_register
+    }
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+    // The stack, oldest first. Each entry is the address of a 2nnn call, not where it returns to: 00EE adds 2 on the way
+    // back. See call_stack() for both.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+    // Sets Vx. Only the lowest nybble of x is used.
+    pub fn set_v(&mut self, x: usize, value: u8) {
+        self.v_registers[x & 0xF] = value;
+    }
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+    pub fn set_i_register(&mut self, i: u16) {
+        self.i_register = i;
+    }
+    // Copies bytes into memory starting at addr. Unlike the program's own writes, this doesn't wrap around at the end of RAM,
+    // and nothing is written if any of it wouldn't fit.
+    pub fn write_memory(&mut self, addr: usize, bytes: &[u8]) -> Result<(), ChipEightError> {
+        let end: usize = addr.saturating_add(bytes.len());
+
+        match self.memory.get_mut(addr..end) {
+            Some(dest) => {
+                dest.copy_from_slice(bytes);
+                Ok(())
             }
+            None => Err(ChipEightError::MemoryOutOfBounds {
+                addr: end.saturating_sub(1).max(addr),
+                pc: self.pc,
+            }),
         }
+    }
+    pub fn keypad(&self) -> &Keypad {
+        &self.keypad
+    }
+    // Frontends press and release keys through this.
+    pub fn keypad_mut(&mut self) -> &mut Keypad {
+        &mut self.keypad
+    }
+    fn init_memory(font: &Font, size: MemorySize) -> Memory {
+        #[cfg(feature = "std")]
+        let mut memory: Memory = vec![0; size.bytes()].into_boxed_slice();
+        #[cfg(not(feature = "std"))]
+        let mut memory: Memory = {
+            let _ = size;
+            [0; 4096]
+        };
+        Self::copy_font(&mut memory, font);
+        Self::copy_big_font(&mut memory);
         memory
     }
-    fn poll_input(pressed: &mut HashSet<u8>, event_pump: &mut sdl2::EventPump) -> i32 {
-        use sdl2::{event::Event, keyboard::Keycode};
-
-        let mut last_pressed = -1;
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => return 0x1B,
-                Event::KeyDown {
-                    keycode: Some(keycode),
-                    ..
-                } => match keycode {
-                    Keycode::Num1 => {
-                        pressed.insert(0x1);
-                        last_pressed = 0x1;
-                    }
-                    Keycode::Num2 => {
-                        pressed.insert(0x2);
-                        last_pressed = 0x2;
-                    }
-                    Keycode::Num3 => {
-                        pressed.insert(0x3);
-                        last_pressed = 0x3;
-                    }
-                    Keycode::Num4 => {
-                        pressed.insert(0xC);
-                        last_pressed = 0xC;
-                    }
-                    Keycode::Q => {
-                        pressed.insert(0x4);
-                        last_pressed = 0x4;
-                    }
-                    Keycode::W => {
-                        pressed.insert(0x5);
-                        last_pressed = 0x5;
-                    }
-                    Keycode::E => {
-                        pressed.insert(0x6);
-                        last_pressed = 0x6;
-                    }
-                    Keycode::R => {
-                        pressed.insert(0xD);
-                        last_pressed = 0xD;
-                    }
-                    Keycode::A => {
-                        pressed.insert(0x7);
-                        last_pressed = 0x7;
-                    }
-                    Keycode::S => {
-                        pressed.insert(0x8);
-                        last_pressed = 0x8;
-                    }
-                    Keycode::D => {
-                        pressed.insert(0x9);
-                        last_pressed = 0x9;
-                    }
-                    Keycode::F => {
-                        pressed.insert(0xE);
-                        last_pressed = 0xE;
-                    }
-                    Keycode::Z => {
-                        pressed.insert(0xA);
-                        last_pressed = 0xA;
-                    }
-                    Keycode::X => {
-                        pressed.insert(0x0);
-                        last_pressed = 0x0;
-                    }
-                    Keycode::C => {
-                        pressed.insert(0xB);
-                        last_pressed = 0xB;
-                    }
-                    Keycode::V => {
-                        pressed.insert(0xF);
-                        last_pressed = 0xF;
-                    }
-                    _ => {}
-                },
-                Event::KeyUp {
-                    keycode: Some(keycode),
-                    ..
-                } => match keycode {
-                    Keycode::Num1 => {
-                        pressed.remove(&0x1);
-                    }
-                    Keycode::Num2 => {
-                        pressed.remove(&0x2);
-                    }
-                    Keycode::Num3 => {
-                        pressed.remove(&0x3);
-                    }
-                    Keycode::Num4 => {
-                        pressed.remove(&0xC);
-                    }
-                    Keycode::Q => {
-                        pressed.remove(&0x4);
-                    }
-                    Keycode::W => {
-                        pressed.remove(&0x5);
-                    }
-                    Keycode::E => {
-                        pressed.remove(&0x6);
-                    }
-                    Keycode::R => {
-                        pressed.remove(&0xD);
-                    }
-                    Keycode::A => {
-                        pressed.remove(&0x7);
-                    }
-                    Keycode::S => {
-                        pressed.remove(&0x8);
-                    }
-                    Keycode::D => {
-                        pressed.remove(&0x9);
-                    }
-                    Keycode::F => {
-                        pressed.remove(&0xE);
-                    }
-                    Keycode::Z => {
-                        pressed.remove(&0xA);
-                    }
-                    Keycode::X => {
-                        pressed.remove(&0x0);
-                    }
-                    Keycode::C => {
-                        pressed.remove(&0xB);
-                    }
-                    Keycode::V => {
-                        pressed.remove(&0xF);
-                    }
-                    _ => {}
-                },
-                _ => {}
+    #[cfg(feature = "std")]
+    pub fn load_program(&mut self, program: Vec<u8>) -> Result<RomInfo, LoadError> {
+        self.load_program_from_slice(&program)
+    }
+    // With the octo feature, .8o files are compiled as Octo source first.
+    #[cfg(feature = "std")]
+    pub fn load_program_from_path(&mut self, path: &Path) -> Result<RomInfo, LoadError> {
+        let program: Vec<u8> = std::fs::read(path).map_err(LoadError::Io)?;
+        #[cfg(feature = "octo")]
+        let program: Vec<u8> = match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("8o") => {
+                assemble_octo(&String::from_utf8_lossy(&program)).map_err(LoadError::Octo)?
             }
+            _ => program,
+        };
+        let mut info: RomInfo = self.load_program_from_slice(&program)?;
+        info.file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        self.rom_info = Some(info.clone());
+        Ok(info)
+    }
+    #[cfg(feature = "std")]
+    pub fn load_program_from_reader(
+        &mut self,
+        mut reader: impl Read,
+    ) -> Result<RomInfo, LoadError> {
+        let mut program: Vec<u8> = vec![];
+        reader.read_to_end(&mut program).map_err(LoadError::Io)?;
+        self.load_program_from_slice(&program)
+    }
+    pub fn load_program_from_slice(&mut self, program: &[u8]) -> Result<RomInfo, LoadError> {
+        if program.is_empty() {
+            return Err(LoadError::Empty);
         }
-        last_pressed
+        let capacity: usize = self.memory.len() - self.start_address as usize;
+        if program.len() > capacity {
+            return Err(LoadError::RomTooLarge {
+                size: program.len(),
+                capacity,
+            });
+        }
+        let info: RomInfo = RomInfo::new(program);
+        self.rom_info = Some(info.clone());
+        #[cfg(feature = "std")]
+        {
+            self.rom = Some(program.to_vec());
+        }
+
+        let start: usize = self.start_address as usize;
+        self.memory[start..start + program.len()].copy_from_slice(program);
+
+        // Hires Chip-8 programs (Hires Invaders, Astro Dodge, etc.) start with a patched copy of the VIP interpreter that jumps to 0x260
+        // and switches the display to 64x64. The program itself starts at 0x2C0, so we skip the interpreter and enable hires directly.
+        // Later variants have hires modes of their own, and their programs can start with a jump to 0x260 for other reasons.
+        if self.variant == ChipVariant::Chip8
+            && self.start_address == 0x200
+            && is_hires_chip8(program)
+        {
+            self.screen.set_mode(ScreenMode::Hires);
+            self.mark_dirty(dirty::WHOLE_SCREEN);
+            self.pc = 0x2C0;
+        }
+        Ok(info)
     }
-    pub fn load_program(&mut self, program: Vec<u8>) {
-        use std::collections::VecDeque;
+    // Puts the machine back in its power-on state and reloads the program, as if it had just been loaded. Settings like the
+    // variant, quirks, and speed are kept.
+    pub fn reset(&mut self) {
+        let memory_size: MemorySize = if self.memory.len() > MemorySize::Classic.bytes() {
+            MemorySize::Extended
+        } else {
+            MemorySize::Classic
+        };
 
-        let mut prog_queue: VecDeque<u8> = VecDeque::from(program);
-        let mut mem_idx: usize = 0x200;
-        while prog_queue.len() != 0 {
-            let byte: u8 = match prog_queue.pop_front() {
-                Some(b) => b,
-                None => 0,
-            };
+        self.memory = Self::init_memory(&self.font, memory_size);
+        self.screen = Screen::new();
+        self.mark_dirty(dirty::WHOLE_SCREEN);
+        self.stack = [0; STACK_DEPTH];
+        self.v_registers = [0; 16];
+        self.pc = self.start_address;
+        self.sp = 0;
+        self.i_register = 0;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.cycle_budget = 0;
+        self.frames = 0;
+        self.keypad = Keypad::new();
+        self.waiting_for_key = false;
+        self.key_taken = None;
+        self.halted = false;
+        self.screen_changed = false;
+        #[cfg(feature = "std")]
+        self.clear_pause();
+        #[cfg(feature = "std")]
+        self.rewind.clear();
 
-            self.memory[mem_idx] = byte;
-            mem_idx += 1;
+        #[cfg(feature = "std")]
+        if let Some(rom) = self.rom.take() {
+            // The ROM already fit when it was first loaded, so this can't fail. Reloading it would forget its file name and
+            // title, so the old info is kept.
+            let info: Option<RomInfo> = self.rom_info.take();
+            let _ = self.load_program_from_slice(&rom);
+            self.rom_info = info;
+        }
+    }
+    // Like reset(), but also forgets the program, leaving nothing but the font in memory.
+    pub fn hard_reset(&mut self) {
+        #[cfg(feature = "std")]
+        {
+            self.rom = None;
         }
+        self.rom_info = None;
+        self.reset();
     }
+    // Runs the program until the input backend or a StopToken asks to stop, one 60Hz frame at a time. Frame pacing is up to the backends
+    // (the SDL display waits for vsync when presenting, for example); with the Null backends this runs as fast as it can.
+    #[cfg(feature = "std")]
     pub fn run(
         &mut self,
-        mut canvas: sdl2::render::Canvas<sdl2::video::Window>,
-        sdl_context: sdl2::Sdl,
-    ) {
-        use sdl2::{pixels::Color, rect::Rect};
-
-        let audio_subsystem = match sdl_context.audio() {
-            Ok(audio) => {
-                println!("Created sdl audio!");
-                audio
+        display: &mut impl backend::Display,
+        input: &mut impl backend::Input,
+        audio: &mut impl backend::Audio,
+    ) -> Result<ExitReason, ChipEightError> {
+        loop {
+            if self.stop.0.take_request() {
+                audio.set_beeping(false);
+                return Ok(ExitReason::Stopped);
+            }
+            if input.poll(self).is_break() {
+                return Ok(ExitReason::UserQuit);
             }
-            Err(e) => panic!("Error creating sdl audiocontext: {:?}", e),
-        };
 
-        // Set up the audio subsystem with 44.1KHz mono playback
-        let desired_spec = AudioSpecDesired {
-            freq: Some(44100),
-            channels: Some(1),
-            samples: None,
-        };
+            let keys: Keypad = self.keypad;
+            let output: FrameOutput = self.frame(&keys)?;
+            audio.set_beeping(output.beep);
+            display.present(self).map_err(ChipEightError::Frontend)?;
+            self.take_dirty_region();
 
-        let audio_device =
-            match audio_subsystem.open_playback(None, &desired_spec, |spec| SquareWave {
-                phase_inc: 261.63 / spec.freq as f32, // middle C note
-                phase: 0.0,
-                volume: 0.0625,
-            }) {
-                Ok(audio) => {
-                    println!("Initialized audio device with a square wave!");
-                    audio
-                }
-                Err(e) => panic!("Error initializing audio device: {:?}", e),
-            };
+            if output.halted {
+                audio.set_beeping(false);
+                return Ok(ExitReason::RomExit);
+            }
+        }
+    }
+    // Runs one 60Hz frame: the timers tick exactly once, and then a frame's worth of instructions run according to the timing mode.
+    // keys is the state of the keypad for this frame. Frontends with their own main loop should call this once per frame.
+    pub fn frame(&mut self, keys: &Keypad) -> Result<FrameOutput, ChipEightError> {
+        self.keypad.sync_from(keys);
 
-        canvas.set_draw_color(Color::RGB(0, 0, 0));
-        match canvas.set_logical_size(64, 32) {
-            Ok(_) => {}
-            Err(e) => panic!("Error setting canvas logical size: {:?}", e),
-        };
-        canvas.clear();
-        canvas.present();
+        // Time stands still while the machine is paused at a breakpoint.
+        #[cfg(feature = "std")]
+        if self.paused_at().is_some() {
+            return Ok(FrameOutput {
+                halted: self.halted,
+                ..FrameOutput::default()
+            });
+        }
+        let beep: bool = self.sound_timer > 0;
+        self.frames += 1;
+        self.tick_timers();
+        let screen_dirty: bool = self.run_frame()?;
 
-        let mut event_pump = match sdl_context.event_pump() {
-            Ok(pump) => pump,
-            Err(e) => panic!("Error creating sdl context event pump: {:?}", e),
-        };
+        Ok(FrameOutput {
+            screen_dirty,
+            beep,
+            halted: self.halted,
+        })
+    }
+    // The delay and sound timers count down at 60Hz, independently of how fast instructions are executed.
+    pub fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+        #[cfg(feature = "std")]
+        self.record_timer_tick();
+        self.report_sound_state();
+    }
+    // Fetches, decodes, and executes exactly one instruction. Timers aren't touched; call tick_timers() at 60Hz for that.
+    pub fn step(&mut self) -> Result<StepOutcome, ChipEightError> {
+        if self.halted {
+            return Ok(StepOutcome {
+                halted: true,
+                ..StepOutcome::default()
+            });
+        }
+        #[cfg(feature = "std")]
+        if let Some(outcome) = self.check_breakpoint() {
+            return Ok(outcome);
+        }
+        let instruction: u16 = self.read_word(self.pc);
 
-        let mut pressed: HashSet<u8> = HashSet::new();
-        'running: loop {
-            for (y, row) in self.screen.iter().enumerate() {
-                for (x, pixel) in row.iter().enumerate() {
-                    let rect = Rect::new(x as i32, y as i32, 1, 1);
-                    if *pixel {
-                        canvas.set_draw_color(Color::RGB(255, 255, 255));
-                    } else {
-                        canvas.set_draw_color(Color::RGB(0, 0, 0));
-                    }
-                    match canvas.draw_rect(rect) {
-                        Ok(_) => {}
-                        Err(e) => println!("Error drawing rectangle at ({}, {}): {:?}", x, y, e),
-                    };
+        self.screen_changed = false;
+        #[cfg(feature = "std")]
+        let mut watchpoint: Option<WatchHit> = None;
+        match self.run_before_step_hook(instruction) {
+            HookAction::Continue => {
+                #[cfg(feature = "std")]
+                self.record_before_instruction(self.single_stepping());
+                let before: Option<RegisterSnapshot> = self.after_step_snapshot();
+                #[cfg(feature = "std")]
+                self.mark_executed(instruction);
+                #[cfg(feature = "std")]
+                self.record_recent(instruction);
+                #[cfg(feature = "std")]
+                self.begin_instruction(instruction);
+                let result: Result<(), ChipEightError> = self.execute(instruction);
+                #[cfg(feature = "std")]
+                {
+                    watchpoint = self.end_instruction();
+                }
+                result?;
+                self.instructions += 1;
+                #[cfg(feature = "std")]
+                self.record_after_instruction();
+                if let Some(before) = before {
+                    self.run_after_step_hook(instruction, &before);
                 }
             }
-
-            let key = Self::poll_input(&mut pressed, &mut event_pump);
-
-            if key == 0x1B {
-                break 'running;
+            HookAction::Skip => {
+                let size: u16 = if self.variant.has_xo_chip_opcodes() && instruction == 0xF000 {
+                    4
+                } else {
+                    2
+                };
+                self.pc = self.pc.wrapping_add(size);
             }
+            HookAction::Pause => {
+                return Ok(StepOutcome {
+                    paused: true,
+                    ..StepOutcome::default()
+                })
+            }
+        }
+        self.report_sound_state();
+        Ok(StepOutcome {
+            screen_changed: self.screen_changed,
+            waiting_for_key: self.waiting_for_key,
+            paused: false,
+            breakpoint: None,
+            halted: self.halted,
+            #[cfg(feature = "std")]
+            watchpoint,
+        })
+    }
+    // Executes one 60Hz frame's worth of instructions, according to the timing mode. Returns whether the screen changed.
+    fn run_frame(&mut self) -> Result<bool, ChipEightError> {
+        let mut screen_changed: bool = false;
 
-            let instruction: u16 = (self.memory[self.pc as usize] as u16) << 8
-                | self.memory[(self.pc + 1) as usize] as u16;
-
-            self.sound_timer = if self.sound_timer > 0 {
-                audio_device.resume();
-                self.sound_timer - 1
-            } else {
-                audio_device.pause();
-                0
-            };
+        match self.timing_mode {
+            TimingMode::InstructionsPerFrame => {
+                for _ in 0..self.instructions_per_frame {
+                    let instruction: u16 = self.read_word(self.pc);
+                    let outcome: StepOutcome = self.step()?;
+                    screen_changed |= outcome.screen_changed;
+                    // With the display_wait quirk, drawing waits for the next vertical blank, which ends the frame.
+                    if outcome.waiting_for_key
+                        || outcome.paused
+                        || outcome.halted
+                        || (self.display_waits() && instruction >> 12 == 0xD)
+                    {
+                        break;
+                    }
+                }
+            }
+            TimingMode::CosmacVip => {
+                self.cycle_budget += VIP_CYCLES_PER_FRAME;
+                while self.cycle_budget > 0 {
+                    let instruction: u16 = self.read_word(self.pc);
+                    self.cycle_budget -= self.vip_cycles(instruction);
+                    let outcome: StepOutcome = self.step()?;
+                    screen_changed |= outcome.screen_changed;
+                    if outcome.waiting_for_key || outcome.paused || outcome.halted {
+                        self.cycle_budget = self.cycle_budget.min(0);
+                        break;
+                    }
+                    // On the VIP, the wait for the vertical blank eats whatever was left of the frame.
+                    if self.display_waits() && instruction >> 12 == 0xD {
+                        self.cycle_budget = self.cycle_budget.min(0);
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(screen_changed)
+    }
+    fn execute(&mut self, opcode: u16) -> Result<(), ChipEightError> {
+        match decode(opcode) {
+            Ok(instruction) => self.execute_instruction(instruction),
+            Err(_) => Err(self.unknown_opcode(opcode)),
+        }
+    }
+    pub fn execute_instruction(&mut self, instruction: Instruction) -> Result<(), ChipEightError> {
+        use Instruction::*;
 
-            self.delay_timer = if self.delay_timer > 0 {
-                self.delay_timer - 1
-            } else {
-                0
+        // Outside of SUPER-CHIP, its 00Cn and 00Fx are just more machine code calls, Dxy0 draws nothing, and Fx30, Fx75, and
+        // Fx85 don't exist.
+        if instruction.is_super_chip() && !self.variant.has_super_chip_opcodes() {
+            match instruction {
+                Draw { .. } => {}
+                SetIToBigFont { .. } | StoreFlags { .. } | RestoreFlags { .. } => {
+                    return Err(self.unknown_opcode(instruction.opcode()))
+                }
+                _ => {
+                    self.jump_to_machine_code();
+                    return Ok(());
+                }
+            }
+        }
+        let xo_chip: bool = self.variant.has_xo_chip_opcodes();
+        if instruction.is_xo_chip() && !xo_chip {
+            // Outside of XO-CHIP, 00Dn is just another machine code call.
+            return match instruction {
+                ScrollUp { .. } => {
+                    self.jump_to_machine_code();
+                    Ok(())
+                }
+                _ => Err(self.unknown_opcode(instruction.opcode())),
             };
+        }
 
-            self.execute(instruction, &mut pressed, &mut event_pump);
-            canvas.present();
+        match instruction {
+            JumpToMachineCode { .. } => self.jump_to_machine_code(),
+            ClearScreen => self.clear_screen(),
+            ReturnFromSubroutine => self.return_from_subroutine()?,
+            ScrollDown { n } => self.scroll_down(n as usize),
+            ScrollRight => self.scroll_right(),
+            ScrollLeft => self.scroll_left(),
+            Exit => self.exit(),
+            Lores => self.set_resolution(ScreenMode::Lores),
+            Hires => self.set_resolution(ScreenMode::SuperHires),
+            ScrollUp { n } => self.scroll_up(n as usize),
+            HiresClearScreen if self.screen.mode() == ScreenMode::Hires => self.clear_screen(),
+            HiresClearScreen => self.jump_to_machine_code(),
+            JumpToAddress { nnn } => self.jump_to_address(nnn),
+            CallSubroutine { nnn } => self.call_subroutine_at_address(nnn)?,
+            SkipIfEqualsData { x, kk } => self.skip_if_vx_equals_data(x as usize, kk),
+            SkipIfNotEqualsData { x, kk } => self.skip_if_vx_not_equals_data(x as usize, kk),
+            SkipIfEquals { x, y } => self.skip_if_vx_equals_vy(x as usize, y as usize),
+            StoreRange { x, y } => self.store_vx_through_vy(x as usize, y as usize),
+            RestoreRange { x, y } => self.restore_vx_through_vy(x as usize, y as usize),
+            SetData { x, kk } => self.set_vx_equals_data(x as usize, kk),
+            AddAssignData { x, kk } => self.add_assign_data_to_vx(x as usize, kk),
+            Set { x, y } => self.set_vx_equals_vy(x as usize, y as usize),
+            BitOrAssign { x, y } => self.bitor_assign_vy_to_vx(x as usize, y as usize),
+            BitAndAssign { x, y } => self.bitand_assign_vy_to_vx(x as usize, y as usize),
+            BitXorAssign { x, y } => self.bitxor_assign_vy_to_vx(x as usize, y as usize),
+            AddAssign { x, y } => self.add_assign_vy_to_vx(x as usize, y as usize),
+            SubAssign { x, y } => self.sub_assign_vy_to_vx(x as usize, y as usize),
+            ShiftRight { x, y } => self.shift_right_vx(x as usize, y as usize),
+            SubFrom { x, y } => self.sub_vx_from_vy(x as usize, y as usize),
+            ShiftLeft { x, y } => self.shift_left_vx(x as usize, y as usize),
+            SkipIfNotEquals { x, y } => self.skip_if_vx_not_equals_vy(x as usize, y as usize),
+            SetI { nnn } => self.set_i_to_address(nnn),
+            JumpPlusV0 { nnn } => self.jump_to_address_plus_v0((nnn >> 8) as usize, nnn),
+            Random { x, kk } => self.set_vx_equals_rand(x as usize, kk),
+            Draw { x, y, n } => self.draw_n_bytes_at_xy(x as usize, y as usize, n as u16),
+            SkipIfPressed { x } => self.skip_if_vx_pressed(x as usize),
+            SkipIfNotPressed { x } => self.skip_if_vx_not_pressed(x as usize),
+            SetILong => self.set_i_to_long_address(),
+            GetDelay { x } => self.set_vx_equals_delay(x as usize),
+            WaitForKey { x } => self.set_vx_equals_key(x as usize),
+            SetDelay { x } => self.set_delay_equals_vx(x as usize),
+            SetSound { x } => self.set_sound_equals_vx(x as usize),
+            AddAssignI { x } => self.add_assign_vx_to_i(x as usize),
+            SetIToFont { x } => self.set_i_to_sprite(x as usize),
+            SetIToBigFont { x } => self.set_i_to_big_sprite(x as usize),
+            Bcd { x } => self.set_i_to_bcd(x as usize),
+            StoreRegisters { x } => self.store_v_registers(x as usize),
+            RestoreRegisters { x } => self.restore_v_registers(x as usize),
+            StoreFlags { x } => self.store_flags(x as usize),
+            RestoreFlags { x } => self.restore_flags(x as usize),
         }
+        Ok(())
     }
-    fn execute(
-        &mut self,
-        instruction: u16,
-        pressed: &mut HashSet<u8>,
-        event_pump: &mut sdl2::EventPump,
-    ) {
-        let top_nybble: u16 = instruction >> 12;
-        // These are usize because the second and third nybbles are pretty much exclusively used to access registers Vx and Vy respectively
-        let second_nybble: usize = ((instruction & 0x0F00) >> 8) as usize;
-        let third_nybble: usize = ((instruction & 0x00F0) >> 4) as usize;
-        let bottom_nybble: u16 = instruction & 0x000F;
-
-        let bottom_byte: u8 = (instruction & 0x00FF) as u8;
-        let bottom_three_nybbles: u16 = instruction & 0x0FFF;
-
-        match top_nybble {
-            0x0 => match bottom_byte {
-                0xE0 => self.clear_screen(),
-                0xEE => self.return_from_subroutine(),
-                _ => self.jump_to_machine_code(),
-            },
-            0x1 => self.jump_to_address(bottom_three_nybbles),
-            0x2 => self.call_subroutine_at_address(bottom_three_nybbles),
-            0x3 => self.skip_if_vx_equals_data(second_nybble, bottom_byte),
-            0x4 => self.skip_if_vx_not_equals_data(second_nybble, bottom_byte),
-            0x5 => self.skip_if_vx_equals_vy(second_nybble, third_nybble),
-            0x6 => self.set_vx_equals_data(second_nybble, bottom_byte),
-            0x7 => self.add_assign_data_to_vx(second_nybble, bottom_byte),
-            0x8 => match bottom_nybble {
-                0x0 => self.set_vx_equals_vy(second_nybble, third_nybble),
-                0x1 => self.bitor_assign_vy_to_vx(second_nybble, third_nybble),
-                0x2 => self.bitand_assign_vy_to_vx(second_nybble, third_nybble),
-                0x3 => self.bitxor_assign_vy_to_vx(second_nybble, third_nybble),
-                0x4 => self.add_assign_vy_to_vx(second_nybble, third_nybble),
-                0x5 => self.sub_assign_vy_to_vx(second_nybble, third_nybble),
-                0x6 => self.shift_right_vx(second_nybble, third_nybble),
-                0x7 => self.sub_vx_from_vy(second_nybble, third_nybble),
-                0xE => self.shift_left_vx(second_nybble, third_nybble),
-                _ => panic!("Invalid instruction {:#04x} encountered.", instruction),
-            },
-            0x9 => self.skip_if_vx_not_equals_vy(second_nybble, third_nybble),
-            0xA => self.set_i_to_address(bottom_three_nybbles),
-            0xB => self.jump_to_address_plus_v0(bottom_three_nybbles),
-            0xC => self.set_vx_equals_rand(second_nybble, bottom_byte),
-            0xD => self.draw_n_bytes_at_xy(second_nybble, third_nybble, bottom_nybble),
-            0xE => match bottom_byte {
-                0x9E => self.skip_if_vx_pressed(second_nybble, pressed),
-                0xA1 => self.skip_if_vx_not_pressed(second_nybble, pressed),
-                _ => panic!("Invalid instruction {:#04x} encountered.", instruction),
-            },
-            0xF => match bottom_byte {
-                0x07 => self.set_vx_equals_delay(second_nybble),
-                0x0A => self.set_vx_equals_key(second_nybble, pressed, event_pump),
-                0x15 => self.set_delay_equals_vx(second_nybble),
-                0x18 => self.set_sound_equals_vx(second_nybble),
-                0x1E => self.add_assign_vx_to_i(second_nybble),
-                0x29 => self.set_i_to_sprite(second_nybble),
-                0x33 => self.set_i_to_bcd(second_nybble),
-                0x55 => self.store_v_registers(second_nybble),
-                0x65 => self.restore_v_registers(second_nybble),
-                _ => panic!("Invalid instruction {:#04x} encountered.", instruction),
-            },
-            _ => unreachable!(
-                "Somehow encountered an instruction where the top nybble is greater than 0xF????"
-            ),
+    fn unknown_opcode(&self, instruction: u16) -> ChipEightError {
+        ChipEightError::UnknownOpcode {
+            opcode: instruction,
+            pc: self.pc,
+        }
+    }
+    // Wraps an address to the size of RAM, so I (or the program counter) running off the end of memory can't index out of bounds.
+    fn address(&self, addr: usize) -> usize {
+        addr % self.memory.len()
+    }
+    // Reads the big-endian 16-bit word at addr, which is how both instructions and the F000 operand are stored.
+    fn read_word(&self, addr: u16) -> u16 {
+        let hi: usize = self.address(addr as usize);
+        let lo: usize = self.address(addr as usize + 1);
+
+        (self.memory[hi] as u16) << 8 | self.memory[lo] as u16
+    }
+    // XO-CHIP's F000 is the only instruction that is 4 bytes wide, so skips need to know to jump over its operand too.
+    fn next_instruction_size(&self) -> u16 {
+        if self.variant.has_xo_chip_opcodes() && self.read_word(self.pc.wrapping_add(2)) == 0xF000 {
+            4
+        } else {
+            2
         }
     }
+    // Moves on to the next instruction, or the one after that if skip is set. Like every change to the PC, this wraps
+    // around from 0xFFFF to 0 rather than overflowing.
+    fn skip_next_if(&mut self, skip: bool) {
+        let size: u16 = if skip {
+            2 + self.next_instruction_size()
+        } else {
+            2
+        };
+        self.pc = self.pc.wrapping_add(size);
+    }
     // The following functions have very ugly names. They're named after the actual instruction + parameters. Sorry.
     // 0nnn - Jumps to machine code routine at address nnn. Ignored by modern interpreters
     fn jump_to_machine_code(&mut self) {
         // Do nothing
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
-    // 00E0 - Clears the display
+    // 00E0 - Clears the display. Hires Chip-8 programs use 0230 for this instead.
     fn clear_screen(&mut self) {
-        self.screen = [[false; 64]; 32];
-        self.pc += 2;
+        self.screen.clear();
+        self.screen_changed = true;
+        self.mark_dirty(dirty::WHOLE_SCREEN);
+        self.report_screen_cleared();
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // 00Cn - Scrolls the display down by n pixels. The top n rows are cleared. SUPER-CHIP only.
+    fn scroll_down(&mut self, n: usize) {
+        self.screen_changed = true;
+        self.mark_dirty(dirty::WHOLE_SCREEN);
+        self.screen.scroll_down(n);
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // 00Dn - Scrolls the display up by n pixels. The bottom n rows are cleared. XO-CHIP only.
+    fn scroll_up(&mut self, n: usize) {
+        self.screen_changed = true;
+        self.mark_dirty(dirty::WHOLE_SCREEN);
+        self.screen.scroll_up(n);
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // 00FB - Scrolls the display right by 4 pixels. The leftmost 4 columns are cleared. SUPER-CHIP only.
+    fn scroll_right(&mut self) {
+        self.screen_changed = true;
+        self.mark_dirty(dirty::WHOLE_SCREEN);
+        self.screen.scroll_right(4);
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // 00FC - Scrolls the display left by 4 pixels. The rightmost 4 columns are cleared. SUPER-CHIP only.
+    fn scroll_left(&mut self) {
+        self.screen_changed = true;
+        self.mark_dirty(dirty::WHOLE_SCREEN);
+        self.screen.scroll_left(4);
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // 00FE and 00FF - Switches to 64x32 and 128x64 respectively, clearing the screen. SUPER-CHIP only.
+    fn set_resolution(&mut self, mode: ScreenMode) {
+        self.screen.set_mode(mode);
+        self.screen_changed = true;
+        self.mark_dirty(dirty::WHOLE_SCREEN);
+        self.pc = self.pc.wrapping_add(2);
     }
     // 00EE - Returns from a subroutine. Sets program counter to address at the top of the stack and subtracts 1 from the stack pointer
-    fn return_from_subroutine(&mut self) {
-        self.pc = match self.stack.last() {
-            Some(val) => *val,
-            None => panic!(),
-        };
-        self.stack.pop();
+    fn return_from_subroutine(&mut self) -> Result<(), ChipEightError> {
+        if self.sp == 0 {
+            return Err(ChipEightError::StackUnderflow { pc: self.pc });
+        }
         self.sp -= 1;
-        self.pc += 2;
+        self.pc = self.stack[self.sp as usize];
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
+    }
+    // 00FD - Exits the interpreter. SUPER-CHIP only.
+    fn exit(&mut self) {
+        self.halted = true;
     }
     // 1nnn - Jumps to address nnn. Sets program counter equal to nnn.
     fn jump_to_address(&mut self, address: u16) {
         self.pc = address;
     }
     // 2nnn - Calls subroutine at nnn. Increments the stack pointer, puts the current program counter on top of the stack, then sets the program counter to nnn.
-    fn call_subroutine_at_address(&mut self, address: u16) {
-        self.stack.push(self.pc);
+    fn call_subroutine_at_address(&mut self, address: u16) -> Result<(), ChipEightError> {
+        if self.sp as usize >= STACK_DEPTH {
+            return Err(ChipEightError::StackOverflow { pc: self.pc });
+        }
+        self.stack[self.sp as usize] = self.pc;
         self.sp += 1;
         self.pc = address;
+        Ok(())
     }
     // 3xkk - Skips the next instruction if Vx == kk. Increments the program counter by 2.
     fn skip_if_vx_equals_data(&mut self, x: usize, data: u8) {
-        self.pc += if self.v_registers[x] == data { 4 } else { 2 };
+        self.skip_next_if(self.v_registers[x] == data);
     }
     // 4xkk - Skips the next instruction if Vx != kk. Increments the program counter by 2.
     fn skip_if_vx_not_equals_data(&mut self, x: usize, data: u8) {
-        self.pc += if self.v_registers[x] != data { 4 } else { 2 };
+        self.skip_next_if(self.v_registers[x] != data);
     }
     // 5xy0 - Skips the next instruction if Vx == Vy. Increments the program counter by 2.
     fn skip_if_vx_equals_vy(&mut self, x: usize, y: usize) {
-        self.pc += if self.v_registers[x] == self.v_registers[y] {
-            4
-        } else {
-            2
-        };
+        self.skip_next_if(self.v_registers[x] == self.v_registers[y]);
+    }
+    // XO-CHIP register ranges can go in either direction, so this lists the registers from Vx to Vy in the order they should be accessed.
+    fn register_range(x: usize, y: usize) -> impl Iterator<Item = usize> {
+        let count: usize = x.abs_diff(y) + 1;
+        let step_back: bool = x > y;
+
+        (0..count).map(move |i| if step_back { x - i } else { x + i })
+    }
+    // 5xy2 - Store the values in registers Vx - Vy in memory starting at location I. If x > y, the registers are stored in reverse order. I is not modified. XO-CHIP only.
+    fn store_vx_through_vy(&mut self, x: usize, y: usize) {
+        let idx: usize = self.i_register as usize;
+
+        for (offset, register) in Self::register_range(x, y).enumerate() {
+            self.write_bus(idx + offset, self.v_registers[register]);
+        }
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // 5xy3 - Read values from memory starting at location I and store them in registers Vx - Vy. If x > y, the registers are loaded in reverse order. I is not modified. XO-CHIP only.
+    fn restore_vx_through_vy(&mut self, x: usize, y: usize) {
+        let idx: usize = self.i_register as usize;
+
+        for (offset, register) in Self::register_range(x, y).enumerate() {
+            self.v_registers[register] = self.read_bus(idx + offset);
+        }
+        self.pc = self.pc.wrapping_add(2);
     }
     // 6xkk - Sets Vx = kk.
     fn set_vx_equals_data(&mut self, x: usize, data: u8) {
         self.v_registers[x] = data;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
     // 7xkk - Sets Vx = Vx + kk.
     fn add_assign_data_to_vx(&mut self, x: usize, data: u8) {
-        self.v_registers[x] += data;
-        self.pc += 2;
+        self.v_registers[x] = self.v_registers[x].wrapping_add(data);
+        self.pc = self.pc.wrapping_add(2);
     }
     // 8xy0 - Sets Vx = Vy.
     fn set_vx_equals_vy(&mut self, x: usize, y: usize) {
         self.v_registers[x] = self.v_registers[y];
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
-    // 8xy1 - Sets Vx = Vx | Vy.
+    // 8xy1 - Sets Vx = Vx | Vy. With the vf_reset quirk, also sets VF = 0 (the same goes for 8xy2 and 8xy3).
     fn bitor_assign_vy_to_vx(&mut self, x: usize, y: usize) {
         self.v_registers[x] |= self.v_registers[y];
-        self.pc += 2;
+        if self.quirks().vf_reset {
+            self.v_registers[0xF] = 0;
+        }
+        self.pc = self.pc.wrapping_add(2);
     }
     // 8xy2 - Sets Vx = Vx & Vy.
     fn bitand_assign_vy_to_vx(&mut self, x: usize, y: usize) {
         self.v_registers[x] &= self.v_registers[y];
-        self.pc += 2;
+        if self.quirks().vf_reset {
+            self.v_registers[0xF] = 0;
+        }
+        self.pc = self.pc.wrapping_add(2);
     }
     // 8xy3 - Sets Vx = Vx ^ Vy.
     fn bitxor_assign_vy_to_vx(&mut self, x: usize, y: usize) {
         self.v_registers[x] ^= self.v_registers[y];
-        self.pc += 2;
+        if self.quirks().vf_reset {
+            self.v_registers[0xF] = 0;
+        }
+        self.pc = self.pc.wrapping_add(2);
     }
     // 8xy4 - Sets Vx = Vx + Vy. Also sets VF = 1 if a carry flag is needed.
     fn add_assign_vy_to_vx(&mut self, x: usize, y: usize) {
@@ -505,13 +1361,13 @@ impl ChipEight {
         self.v_registers[f] = if sum > 255 { 1 } else { 0 };
         // We only need the lower byte, so just mask it.
         self.v_registers[x] = (sum & 0x00FF) as u8;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
     // 8xy5 - Sets Vx = Vx - Vy. If Vx > Vy, set VF to 1, otherwise set VF to 0.
     fn sub_assign_vy_to_vx(&mut self, x: usize, y: usize) {
         let f: usize = 0xF;
 
-        self.v_registers[x] -= self.v_registers[y];
+        self.v_registers[x] = self.v_registers[x].wrapping_sub(self.v_registers[y]);
 
         self.v_registers[f] = if self.v_registers[x] > self.v_registers[y] {
             1
@@ -519,24 +1375,28 @@ impl ChipEight {
             0
         };
 
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
     // 8xy6 - Sets Vx = Vx >> 1 (equivalent to Vx / 2). If the least significant bit of Vx == 1, set VF = 1.
-    fn shift_right_vx(&mut self, x: usize, _y: usize) {
+    // With the shift_uses_vy quirk, Vx = Vy >> 1 instead.
+    fn shift_right_vx(&mut self, x: usize, y: usize) {
         let f: usize = 0xF;
+        if self.quirks().shift_uses_vy {
+            self.v_registers[x] = self.v_registers[y];
+        }
         let prev: u8 = self.v_registers[x] & 0x0001;
 
         self.v_registers[x] >>= 1;
 
         self.v_registers[f] = if prev == 1 { 1 } else { 0 };
 
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
     // 8xy7 - Sets Vx = Vy - Vx. If Vy > Vx, set VF to 1, otherwise set VF to 0.
     fn sub_vx_from_vy(&mut self, x: usize, y: usize) {
         let f: usize = 0xF;
 
-        self.v_registers[x] = self.v_registers[y] - self.v_registers[x];
+        self.v_registers[x] = self.v_registers[y].wrapping_sub(self.v_registers[x]);
 
         self.v_registers[f] = if self.v_registers[y] > self.v_registers[x] {
             1
@@ -544,149 +1404,199 @@ impl ChipEight {
             0
         };
 
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
     // 8xyE - Sets Vx = Vx << 1 (Equivalent to Vx * 2). If the most significant bit of Vx == 1, set VF = 1.
-    fn shift_left_vx(&mut self, x: usize, _y: usize) {
+    // With the shift_uses_vy quirk, Vx = Vy << 1 instead.
+    fn shift_left_vx(&mut self, x: usize, y: usize) {
         let f: usize = 0xF;
+        if self.quirks().shift_uses_vy {
+            self.v_registers[x] = self.v_registers[y];
+        }
         let prev: u8 = self.v_registers[x] & 0x80;
 
         self.v_registers[x] <<= 1;
 
         self.v_registers[f] = if prev != 0 { 1 } else { 0 };
 
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
     // 9xy0 - Skips the next instruction if Vx != Vy.
     fn skip_if_vx_not_equals_vy(&mut self, x: usize, y: usize) {
-        self.pc += if self.v_registers[x] != self.v_registers[y] {
-            4
-        } else {
-            2
-        };
+        self.skip_next_if(self.v_registers[x] != self.v_registers[y]);
     }
     // Annn - Sets register I equal to nnn.
     fn set_i_to_address(&mut self, address: u16) {
         self.i_register = address;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
-    // Bnnn - Sets program counter equal to nnn + V0
-    fn jump_to_address_plus_v0(&mut self, address: u16) {
-        self.pc = address + self.v_registers[0] as u16;
+    // Bnnn - Sets program counter equal to nnn + V0. With the jump_uses_vx quirk, this is Bxnn and jumps to xnn + Vx instead.
+    fn jump_to_address_plus_v0(&mut self, x: usize, address: u16) {
+        let offset: usize = if self.quirks().jump_uses_vx { x } else { 0 };
+
+        self.pc = address.wrapping_add(self.v_registers[offset] as u16);
     }
     // Cxkk - Sets Vx = kk & random byte.
     fn set_vx_equals_rand(&mut self, x: usize, data: u8) {
-        let rand: u8 = rand::random();
+        let rand: u8 = self.rng.next_byte();
 
         self.v_registers[x] = data & rand;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // Whether drawing waits for the next vertical blank. Legacy SUPER-CHIP only ever waited in lores; its hires mode draws
+    // straight away.
+    fn display_waits(&self) -> bool {
+        self.quirks().display_wait
+            && !(self.variant == ChipVariant::SuperChipLegacy
+                && self.screen.mode() == ScreenMode::SuperHires)
     }
     // This function is particularly ugly. Sorry.
-    // Dxyn - Display an n-byte sprite starting at memory location I at coordinate (Vx, Vy) and set VF = collision
+    // Dxyn - Display an n-byte sprite starting at memory location I at coordinate (Vx, Vy) and set VF = collision. In
+    // SUPER-CHIP, Dxy0 displays a 16x16 sprite of 32 bytes, two to a row.
     fn draw_n_bytes_at_xy(&mut self, x: usize, y: usize, n: u16) {
         let f: usize = 0xF;
         let mut collision: bool = false;
-        let sprite_size: usize = (self.i_register + n) as usize;
-        let sprite_slice: &[u8] = &self.memory[self.i_register as usize..sprite_size];
-        let mut sprite: Vec<[bool; 8]> = vec![]; // We need to use a vector because the value of n isn't known at compile time
-
-        for &byte in sprite_slice {
-            // There is almost certainly a less ugly way to do this.
-            // We're just bitmasking all 8 bits and checking to see if the resulting value isn't 0.
-            let byte_array: [bool; 8] = [
-                (byte & 0b10000000) != 0,
-                (byte & 0b01000000) != 0,
-                (byte & 0b00100000) != 0,
-                (byte & 0b00010000) != 0,
-                (byte & 0b00001000) != 0,
-                (byte & 0b00000100) != 0,
-                (byte & 0b00000010) != 0,
-                (byte & 0b00000001) != 0,
-            ];
-            sprite.push(byte_array);
-        }
-
-        for i in 0..sprite.len() {
+        let idx: usize = self.i_register as usize;
+        let (sprite_width, rows): (usize, usize) =
+            if n == 0 && self.variant.has_super_chip_opcodes() {
+                (16, 16)
+            } else {
+                (8, n as usize)
+            };
+        // Sprites are never more than 16 rows tall or 16 pixels wide. Only the part of this the sprite covers gets used.
+        let mut sprite: [[bool; 16]; 16] = [[false; 16]; 16];
+
+        self.screen_changed = true;
+        let bytes_per_row: usize = sprite_width / 8;
+        for (offset, row) in sprite.iter_mut().enumerate().take(rows) {
+            for (byte, pixels) in row.chunks_mut(8).enumerate().take(bytes_per_row) {
+                let address: usize = idx + offset * bytes_per_row + byte;
+                pixels.copy_from_slice(&sprite_row(self.read_bus(address)));
+            }
+        }
+
+        let clip: bool = self.quirks().clip_sprites;
+        // The starting coordinates always wrap, even when the rest of the sprite is clipped.
+        let (width, height) = (self.screen.width(), self.screen.height());
+        let start_y: usize = self.v_registers[y] as usize % height;
+        let start_x: usize = self.v_registers[x] as usize % width;
+        // The corners of the box around every pixel the sprite flipped, as (left, top, right, bottom).
+        let mut changed: Option<(usize, usize, usize, usize)> = None;
+        // Legacy SUPER-CHIP's hires mode sets VF to how many rows collided or were clipped off the bottom, not just to 1.
+        let count_rows: bool = self.variant == ChipVariant::SuperChipLegacy
+            && self.screen.mode() == ScreenMode::SuperHires;
+        let mut collided_rows: u8 = 0;
+        for (i, row) in sprite.iter().enumerate().take(rows) {
+            if clip && start_y + i >= height {
+                collided_rows += (rows - i) as u8;
+                break;
+            }
             // If a sprite's coordinates on screen go past the screen boundaries, the sprite should wrap to the other side.
-            let sy: usize = (self.v_registers[y] as usize + i) % 32;
-            for j in 0..8 as usize {
+            let sy: usize = (start_y + i) % height;
+            let mut row_collision: bool = false;
+            for (j, &lit) in row.iter().enumerate().take(sprite_width) {
+                if clip && start_x + j >= width {
+                    break;
+                }
                 // Make sure to also wrap the x-axis.
-                let sx: usize = (self.v_registers[x] as usize + j) % 64;
-                let current_pixel: bool = self.screen[sy][sx];
-                self.screen[sy][sx] ^= sprite[i][j];
-                // If current_pixel is true and self.screen[sy][sx] is false, then a collision occurred.
-                if current_pixel && !self.screen[sy][sx] {
-                    collision = true;
+                let sx: usize = (start_x + j) % width;
+                if !lit {
+                    continue;
                 }
+                // A lit pixel drawn over a lit one turns it off, which is a collision.
+                row_collision |= self.screen.xor_pixel(sx, sy);
+                changed = Some(match changed {
+                    Some((left, top, right, bottom)) => {
+                        (left.min(sx), top.min(sy), right.max(sx), bottom.max(sy))
+                    }
+                    None => (sx, sy, sx, sy),
+                });
             }
+            collision |= row_collision;
+            collided_rows += row_collision as u8;
         }
-        self.v_registers[f] = if collision { 1 } else { 0 };
-        self.pc += 2;
-    }
-    // Ex9E - Skip next instruction if key with the value of Vx is pressed.
-    fn skip_if_vx_pressed(&mut self, x: usize, pressed: &HashSet<u8>) {
-        self.pc += if pressed.contains(&self.v_registers[x]) {
-            4
+        self.v_registers[f] = if count_rows {
+            collided_rows
         } else {
-            2
+            collision as u8
         };
+        if let Some((left, top, right, bottom)) = changed {
+            self.mark_dirty(DirtyRect {
+                x: left as u8,
+                y: top as u8,
+                width: (right - left + 1) as u8,
+                height: (bottom - top + 1) as u8,
+            });
+        }
+        self.report_draw(DrawRect {
+            x: start_x as u8,
+            y: start_y as u8,
+            width: sprite_width as u8,
+            height: rows as u8,
+        });
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // Ex9E - Skip next instruction if key with the value of Vx is pressed.
+    fn skip_if_vx_pressed(&mut self, x: usize) {
+        self.skip_next_if(self.keypad.is_pressed(self.v_registers[x]));
     }
     // ExA1 - Skip next instruction if key with the value of Vx is not pressed.
-    fn skip_if_vx_not_pressed(&mut self, x: usize, pressed: &HashSet<u8>) {
-        self.pc += if !pressed.contains(&self.v_registers[x]) {
-            4
-        } else {
-            2
-        };
+    fn skip_if_vx_not_pressed(&mut self, x: usize) {
+        self.skip_next_if(!self.keypad.is_pressed(self.v_registers[x]));
+    }
+    // F000 nnnn - Sets register I equal to the 16-bit address nnnn stored in the two bytes following the instruction. XO-CHIP only.
+    fn set_i_to_long_address(&mut self) {
+        self.i_register = self.read_word(self.pc.wrapping_add(2));
+        self.pc = self.pc.wrapping_add(4);
     }
     // Fx07 - Set Vx = delay_timer.
     fn set_vx_equals_delay(&mut self, x: usize) {
         self.v_registers[x] = self.delay_timer;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
     // Fx0A - Wait for a key press, then store the value of the key in Vx.
-    fn set_vx_equals_key(
-        &mut self,
-        x: usize,
-        pressed: &mut HashSet<u8>,
-        event_pump: &mut sdl2::EventPump,
-    ) {
-        let key = loop {
-            let key = Self::poll_input(pressed, event_pump);
-
-            if key == 0x1B {
-                // This probably isn't the best idea but oh well ¯\_(ツ)_/¯
-                std::process::exit(0);
-            } else if !(key == -1) {
-                break key;
-            }
-        };
-
-        self.v_registers[x] = key as u8;
-        self.pc += 2;
+    // The PC stays put until a key comes in, so the instruction keeps re-executing (and the frontend keeps polling input) while it waits.
+    fn set_vx_equals_key(&mut self, x: usize) {
+        if !self.waiting_for_key {
+            self.waiting_for_key = true;
+            self.keypad.clear_presses();
+            return;
+        }
+        if let Some(key) = self.keypad.take_press() {
+            self.waiting_for_key = false;
+            self.key_taken = Some((key, self.frames));
+            self.v_registers[x] = key;
+            self.pc = self.pc.wrapping_add(2);
+        }
     }
     // Fx15 - Set delay_timer = Vx.
     fn set_delay_equals_vx(&mut self, x: usize) {
         self.delay_timer = self.v_registers[x];
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
     // Fx18 - Set sound_timer = Vx.
     fn set_sound_equals_vx(&mut self, x: usize) {
         self.sound_timer = self.v_registers[x];
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
     // Fx1E - Set I = I + Vx.
     fn add_assign_vx_to_i(&mut self, x: usize) {
-        self.i_register += self.v_registers[x] as u16;
-        self.pc += 2;
+        self.i_register = self.i_register.wrapping_add(self.v_registers[x] as u16);
+        self.pc = self.pc.wrapping_add(2);
     }
     // Fx29 - Set I to the location of the hexadecimal sprite corresponding to the value of Vx.
     fn set_i_to_sprite(&mut self, x: usize) {
         // The hexadecimal sprites are 8x5, so we multiply the value of Vx by 5 to get the index of the sprite
-        let i: u16 = (self.v_registers[x] * 5) as u16;
+        let i: u16 = FONT_ADDRESS + self.v_registers[x].wrapping_mul(5) as u16;
 
         self.i_register = i;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // Fx30 - Set I to the location of the big hexadecimal sprite corresponding to the value of Vx. SUPER-CHIP only.
+    fn set_i_to_big_sprite(&mut self, x: usize) {
+        // The big sprites are 8x10.
+        self.i_register = BIG_FONT_ADDRESS + self.v_registers[x] as u16 * 10;
+        self.pc = self.pc.wrapping_add(2);
     }
     // Fx33 - Store the BCD representation of Vx in I, I+1, and I+2. The hundreds place is stored in I, tens in I+1, and ones in I+2.
     fn set_i_to_bcd(&mut self, x: usize) {
@@ -695,27 +1605,784 @@ impl ChipEight {
         let ones: u8 = self.v_registers[x] % 10;
         let idx: usize = self.i_register as usize;
 
-        self.memory[idx] = hundreds;
-        self.memory[idx + 1] = tens;
-        self.memory[idx + 2] = ones;
-        self.pc += 2;
+        self.write_bus(idx, hundreds);
+        self.write_bus(idx + 1, tens);
+        self.write_bus(idx + 2, ones);
+        self.pc = self.pc.wrapping_add(2);
     }
-    // Fx55 - Store the values in registers V0 - Vx in memory starting at location I.
+    // Fx55 - Store the values in registers V0 - Vx in memory starting at location I. With the load_store_increments_i quirk, I is left at I + x + 1.
     fn store_v_registers(&mut self, x: usize) {
         let idx: usize = self.i_register as usize;
 
         for i in 0..=x {
-            self.memory[idx + i] = self.v_registers[i];
+            self.write_bus(idx + i, self.v_registers[i]);
+        }
+        if self.quirks().load_store_increments_i {
+            self.i_register = self.i_register.wrapping_add(x as u16 + 1);
         }
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
-    // Fx65 - Read values from memory starting at location I and store them in registers V0 - Vx.
+    // Fx65 - Read values from memory starting at location I and store them in registers V0 - Vx. With the load_store_increments_i quirk, I is left at I + x + 1.
     fn restore_v_registers(&mut self, x: usize) {
         let idx: usize = self.i_register as usize;
 
         for i in 0..=x {
-            self.v_registers[i] = self.memory[idx + i];
+            self.v_registers[i] = self.read_bus(idx + i);
         }
-        self.pc += 2;
+        if self.quirks().load_store_increments_i {
+            self.i_register = self.i_register.wrapping_add(x as u16 + 1);
+        }
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // How many flag registers there are. The HP48 had 8; XO-CHIP has one for every V register.
+    fn flag_count(&self) -> usize {
+        if self.variant.has_xo_chip_opcodes() {
+            16
+        } else {
+            8
+        }
+    }
+    // Fx75 - Store registers V0 - Vx in the flag registers. SUPER-CHIP only.
+    fn store_flags(&mut self, x: usize) {
+        let count: usize = (x + 1).min(self.flag_count());
+
+        self.flags[..count].copy_from_slice(&self.v_registers[..count]);
+        self.pc = self.pc.wrapping_add(2);
+    }
+    // Fx85 - Read registers V0 - Vx from the flag registers. SUPER-CHIP only.
+    fn restore_flags(&mut self, x: usize) {
+        let count: usize = (x + 1).min(self.flag_count());
+
+        self.v_registers[..count].copy_from_slice(&self.flags[..count]);
+        self.pc = self.pc.wrapping_add(2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A machine of variant with program at 0x200, where it starts.
+    fn machine(variant: ChipVariant, program: &[u8]) -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.set_variant(variant);
+        chip_eight.write_memory(0x200, program).unwrap();
+        chip_eight
+    }
+
+    // Each skip, with whether it skips when V1 holds key 5 and V2 holds 5 (and key 5 is down) rather than 6 (and it's up).
+    const SKIPS: [(u16, bool); 6] = [
+        (0x3205, true),
+        (0x4205, false),
+        (0x5120, true),
+        (0x9120, false),
+        (0xE19E, true),
+        (0xE1A1, false),
+    ];
+
+    // Runs skip followed by F000 NNNN, and returns where the PC ends up.
+    fn skip_over_long_i(variant: ChipVariant, skip: u16, matching: bool) -> u16 {
+        let [high, low] = skip.to_be_bytes();
+        let mut chip_eight: ChipEight = machine(variant, &[high, low, 0xF0, 0x00, 0x12, 0x34]);
+        chip_eight.v_registers[1] = 5;
+        chip_eight.v_registers[2] = if matching { 5 } else { 6 };
+        if matching {
+            chip_eight.keypad_mut().press(5);
+        }
+        chip_eight.step().unwrap();
+        chip_eight.pc()
+    }
+
+    #[test]
+    fn skips_jump_over_all_four_bytes_of_f000() {
+        for (skip, skips_when_matching) in SKIPS {
+            for matching in [true, false] {
+                let expected: u16 = if matching == skips_when_matching {
+                    0x206
+                } else {
+                    0x202
+                };
+                assert_eq!(
+                    skip_over_long_i(ChipVariant::XoChip, skip, matching),
+                    expected,
+                    "{:04X} with matching = {}",
+                    skip,
+                    matching
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn f000_is_only_four_bytes_wide_in_xo_chip() {
+        for (skip, skips_when_matching) in SKIPS {
+            assert_eq!(
+                skip_over_long_i(ChipVariant::Chip8, skip, skips_when_matching),
+                0x204,
+                "{:04X}",
+                skip
+            );
+        }
+    }
+
+    #[test]
+    fn f000_loads_i_from_the_next_word() {
+        let mut chip_eight: ChipEight = machine(ChipVariant::XoChip, &[0xF0, 0x00, 0x12, 0x34]);
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.i_register(), 0x1234);
+        assert_eq!(chip_eight.pc(), 0x204);
+    }
+
+    // Runs a single 5xy2 or 5xy3 with I at 0x300, V0-VF holding 0x10-0x1F, and 0xA0-0xAF in memory at I.
+    fn range(opcode: u16) -> ChipEight {
+        let mut chip_eight: ChipEight = machine(ChipVariant::XoChip, &opcode.to_be_bytes());
+        for register in 0..16 {
+            chip_eight.v_registers[register] = 0x10 + register as u8;
+            chip_eight.memory[0x300 + register] = 0xA0 + register as u8;
+        }
+        chip_eight.i_register = 0x300;
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.i_register(), 0x300, "{:04X} moved I", opcode);
+        assert_eq!(chip_eight.pc(), 0x202);
+        chip_eight
+    }
+
+    #[test]
+    fn store_range_goes_forwards_backwards_or_stores_one() {
+        assert_eq!(range(0x5242).memory[0x300..0x304], [0x12, 0x13, 0x14, 0xA3]);
+        assert_eq!(range(0x5422).memory[0x300..0x304], [0x14, 0x13, 0x12, 0xA3]);
+        assert_eq!(range(0x5772).memory[0x300..0x302], [0x17, 0xA1]);
+    }
+
+    #[test]
+    fn restore_range_goes_forwards_backwards_or_loads_one() {
+        assert_eq!(
+            range(0x5243).v_registers()[1..6],
+            [0x11, 0xA0, 0xA1, 0xA2, 0x15]
+        );
+        assert_eq!(
+            range(0x5423).v_registers()[1..6],
+            [0x11, 0xA2, 0xA1, 0xA0, 0x15]
+        );
+        assert_eq!(range(0x5773).v_registers()[6..9], [0x16, 0xA0, 0x18]);
+    }
+
+    // Runs 00Dn on a screen in mode with a pixel lit at the start of every row, and one more at (5, row).
+    fn scroll_up(mode: ScreenMode, n: u8, row: usize) -> ChipEight {
+        let mut chip_eight: ChipEight = machine(ChipVariant::XoChip, &[0x00, 0xD0 | n]);
+        chip_eight.screen.set_mode(mode);
+        for y in 0..chip_eight.resolution().1 {
+            chip_eight.screen.xor_pixel(0, y);
+        }
+        chip_eight.screen.xor_pixel(5, row);
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.pc(), 0x202);
+        chip_eight
+    }
+
+    #[test]
+    fn scroll_up_moves_lores_rows_up_and_blanks_the_bottom() {
+        let chip_eight: ChipEight = scroll_up(ScreenMode::Lores, 4, 10);
+        assert!(chip_eight.pixel(5, 6));
+        assert!(!chip_eight.pixel(5, 10));
+        assert!(chip_eight.pixel(0, 27));
+        assert!(!chip_eight.pixel(0, 28));
+        assert!(!chip_eight.pixel(0, 31));
+        assert_eq!(chip_eight.resolution(), (64, 32));
+    }
+
+    #[test]
+    fn scroll_up_moves_hires_rows_up_and_blanks_the_bottom() {
+        let chip_eight: ChipEight = scroll_up(ScreenMode::Hires, 15, 40);
+        assert!(chip_eight.pixel(5, 25));
+        assert!(!chip_eight.pixel(5, 40));
+        assert!(chip_eight.pixel(0, 48));
+        assert!(!chip_eight.pixel(0, 49));
+        assert!(!chip_eight.pixel(0, 63));
+    }
+
+    #[test]
+    fn scroll_up_past_the_top_clears_everything() {
+        // 00Dn only scrolls up to 15 rows, so a lit pixel above that goes off the top.
+        let chip_eight: ChipEight = scroll_up(ScreenMode::Lores, 15, 3);
+        assert!(!(0..32).any(|y| chip_eight.pixel(5, y)));
+        // Scrolling further than the screen is tall leaves it blank, rather than reading past the bottom.
+        let mut screen: Screen = chip_eight.screen().clone();
+        screen.scroll_up(40);
+        assert!(!screen.rows().iter().flatten().any(|&pixel| pixel));
+        screen.set_mode(ScreenMode::Hires);
+        screen.xor_pixel(0, 63);
+        screen.scroll_up(64);
+        assert!(!screen.get(0, 63) && !screen.get(0, 0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn a_20kib_rom_only_fits_in_extended_memory() {
+        let rom: Vec<u8> = vec![0xAB; 20 * 1024];
+        let mut chip_eight: ChipEight = ChipEight::with_memory_size(MemorySize::Extended);
+        chip_eight.load_program(rom.clone()).unwrap();
+        assert_eq!(chip_eight.memory[0x200 + rom.len() - 1], 0xAB);
+        let mut chip_eight: ChipEight = ChipEight::with_memory_size(MemorySize::Classic);
+        assert!(matches!(
+            chip_eight.load_program(rom),
+            Err(LoadError::RomTooLarge {
+                size: 20480,
+                capacity: 3584
+            })
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn register_loads_wrap_at_the_end_of_memory() {
+        for size in [MemorySize::Classic, MemorySize::Extended] {
+            let mut chip_eight: ChipEight = ChipEight::with_memory_size(size);
+            let end: usize = size.bytes() - 1;
+            chip_eight.memory[end] = 0x12;
+            chip_eight.i_register = end as u16;
+            chip_eight.restore_v_registers(1);
+            // Address 0 holds the top row of the font's 0.
+            assert_eq!(chip_eight.v_registers[..2], [0x12, 0xF0], "{:?}", size);
+        }
+    }
+
+    // A CHIP-8 machine running program with the quirk called name turned on or off, and every other quirk as CHIP-8 has it.
+    fn quirky(name: &str, enabled: bool, program: &[u8]) -> ChipEight {
+        let mut quirks: Quirks = ChipVariant::Chip8.quirks();
+        assert!(quirks.set(name, enabled));
+        let mut chip_eight: ChipEight = machine(ChipVariant::Chip8, program);
+        chip_eight.set_quirks(Some(quirks));
+        chip_eight
+    }
+
+    #[test]
+    fn vf_reset_clears_vf_after_logic() {
+        for (enabled, vf) in [(true, 0), (false, 5)] {
+            let mut chip_eight: ChipEight = quirky("vf_reset", enabled, &[0x80, 0x11]);
+            chip_eight.v_registers[0xF] = 5;
+            chip_eight.step().unwrap();
+            assert_eq!(chip_eight.v_registers()[0xF], vf, "vf_reset = {}", enabled);
+        }
+    }
+
+    #[test]
+    fn shift_uses_vy_shifts_vy_into_vx() {
+        for (enabled, v0) in [(true, 0x08), (false, 0x02)] {
+            let mut chip_eight: ChipEight = quirky("shift_uses_vy", enabled, &[0x80, 0x1E]);
+            chip_eight.v_registers[0] = 0x01;
+            chip_eight.v_registers[1] = 0x04;
+            chip_eight.step().unwrap();
+            assert_eq!(
+                chip_eight.v_registers()[0],
+                v0,
+                "shift_uses_vy = {}",
+                enabled
+            );
+        }
+    }
+
+    #[test]
+    fn jump_uses_vx_adds_vx_rather_than_v0() {
+        for (enabled, pc) in [(true, 0x213), (false, 0x211)] {
+            let mut chip_eight: ChipEight = quirky("jump_uses_vx", enabled, &[0xB2, 0x10]);
+            chip_eight.v_registers[0] = 1;
+            chip_eight.v_registers[2] = 3;
+            chip_eight.step().unwrap();
+            assert_eq!(chip_eight.pc(), pc, "jump_uses_vx = {}", enabled);
+        }
+    }
+
+    #[test]
+    fn load_store_increments_i_moves_i_past_the_registers() {
+        for (enabled, i) in [(true, 0x303), (false, 0x300)] {
+            for opcode in [0xF255u16, 0xF265] {
+                let mut chip_eight: ChipEight =
+                    quirky("load_store_increments_i", enabled, &opcode.to_be_bytes());
+                chip_eight.i_register = 0x300;
+                chip_eight.step().unwrap();
+                assert_eq!(
+                    chip_eight.i_register(),
+                    i,
+                    "{:04X} with quirk = {}",
+                    opcode,
+                    enabled
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn clip_sprites_stops_sprites_wrapping() {
+        for enabled in [true, false] {
+            let mut chip_eight: ChipEight =
+                quirky("clip_sprites", enabled, &[0xD0, 0x12, 0xFF, 0xFF]);
+            chip_eight.v_registers[0] = 60;
+            chip_eight.v_registers[1] = 31;
+            chip_eight.i_register = 0x202;
+            chip_eight.step().unwrap();
+            // The sprite's top-left corner is on screen either way.
+            assert!(chip_eight.pixel(63, 31));
+            // Its right and bottom edges are off it, so wrap around to the other side only without the quirk.
+            assert_eq!(
+                chip_eight.pixel(0, 31),
+                !enabled,
+                "clip_sprites = {}",
+                enabled
+            );
+            assert_eq!(
+                chip_eight.pixel(63, 0),
+                !enabled,
+                "clip_sprites = {}",
+                enabled
+            );
+        }
+    }
+
+    #[test]
+    fn sprites_wrap_or_clip_at_the_edges_of_super_chip_hires() {
+        for clip in [true, false] {
+            let mut quirks: Quirks = ChipVariant::SuperChipModern.quirks();
+            assert!(quirks.set("clip_sprites", clip));
+            let mut chip_eight: ChipEight = machine(
+                ChipVariant::SuperChipModern,
+                &[0x00, 0xFF, 0xD0, 0x12, 0xFF, 0xFF],
+            );
+            chip_eight.set_quirks(Some(quirks));
+            // 252 wraps round to 124 whether or not the rest is clipped.
+            chip_eight.v_registers[0] = 252;
+            chip_eight.v_registers[1] = 63;
+            chip_eight.i_register = 0x204;
+            chip_eight.step().unwrap();
+            chip_eight.step().unwrap();
+            assert!(chip_eight.pixel(124, 63) && chip_eight.pixel(127, 63));
+            for (x, y) in [(0, 63), (3, 63), (124, 0), (3, 0)] {
+                assert_eq!(
+                    chip_eight.pixel(x, y),
+                    !clip,
+                    "({}, {}), clip_sprites = {}",
+                    x,
+                    y,
+                    clip
+                );
+            }
+            assert!(!chip_eight.pixel(4, 0));
+            assert_eq!(chip_eight.v_registers[0xF], 0);
+        }
+    }
+
+    #[test]
+    fn display_wait_ends_the_frame_at_a_draw() {
+        // Two draws, then a jump to itself.
+        for (enabled, pc) in [(true, 0x202), (false, 0x204)] {
+            let mut chip_eight: ChipEight = quirky(
+                "display_wait",
+                enabled,
+                &[0xD0, 0x01, 0xD0, 0x01, 0x12, 0x04],
+            );
+            chip_eight.frame(&Keypad::default()).unwrap();
+            assert_eq!(chip_eight.pc(), pc, "display_wait = {}", enabled);
+        }
+    }
+
+    // A two-page hires program: a jump to the patch, the patch calling machine code, and a program at 0x2C0.
+    fn hires_program() -> [u8; 0xC2] {
+        let mut program: [u8; 0xC2] = [0; 0xC2];
+        program[..2].copy_from_slice(&[0x12, 0x60]);
+        program[0x62..0x64].copy_from_slice(&[0x02, 0x6C]);
+        program[0xC0..].copy_from_slice(&[0x02, 0x30]);
+        program
+    }
+
+    // Loads program on a variant machine, and returns the resolution and where it starts.
+    fn load(variant: ChipVariant, program: &[u8]) -> ((usize, usize), u16) {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.set_variant(variant);
+        chip_eight.load_program_from_slice(program).unwrap();
+        (chip_eight.resolution(), chip_eight.pc())
+    }
+
+    #[test]
+    fn hires_chip8_programs_start_in_hires_after_the_patch() {
+        assert_eq!(
+            load(ChipVariant::Chip8, &hires_program()),
+            ((64, 64), 0x2C0)
+        );
+    }
+
+    #[test]
+    fn only_chip8_looks_for_hires_programs() {
+        for variant in [
+            ChipVariant::SuperChipLegacy,
+            ChipVariant::SuperChipModern,
+            ChipVariant::XoChip,
+        ] {
+            assert_eq!(
+                load(variant, &hires_program()),
+                ((64, 32), 0x200),
+                "{}",
+                variant
+            );
+        }
+    }
+
+    #[test]
+    fn a_jump_to_0x260_isnt_enough_to_be_hires() {
+        // No machine code call in the patch.
+        let mut program: [u8; 0xC2] = hires_program();
+        program[0x62..0x64].copy_from_slice(&[0x60, 0x00]);
+        assert_eq!(load(ChipVariant::Chip8, &program), ((64, 32), 0x200));
+        // Nothing after the patch.
+        assert_eq!(
+            load(ChipVariant::Chip8, &hires_program()[..0xC0]),
+            ((64, 32), 0x200)
+        );
+        // A different jump.
+        let mut program: [u8; 0xC2] = hires_program();
+        program[1] = 0x62;
+        assert_eq!(load(ChipVariant::Chip8, &program), ((64, 32), 0x200));
+    }
+
+    #[test]
+    fn super_chip_screen_opcodes_are_machine_code_calls_in_chip8() {
+        for opcode in [0x00C1u16, 0x00FB, 0x00FC, 0x00FE, 0x00FF] {
+            let mut chip_eight: ChipEight = machine(ChipVariant::Chip8, &opcode.to_be_bytes());
+            chip_eight.screen.xor_pixel(10, 10);
+            chip_eight.step().unwrap();
+            assert_eq!(chip_eight.pc(), 0x202, "{:04X}", opcode);
+            assert!(chip_eight.pixel(10, 10), "{:04X}", opcode);
+            assert_eq!(chip_eight.resolution(), (64, 32), "{:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn super_chip_register_opcodes_are_unknown_in_chip8() {
+        for opcode in [0xF130u16, 0xF175, 0xF185] {
+            let mut chip_eight: ChipEight = machine(ChipVariant::Chip8, &opcode.to_be_bytes());
+            assert!(
+                matches!(chip_eight.step(), Err(ChipEightError::UnknownOpcode { opcode: o, .. }) if o == opcode),
+                "{:04X}",
+                opcode
+            );
+            let mut chip_eight: ChipEight =
+                machine(ChipVariant::SuperChipModern, &opcode.to_be_bytes());
+            chip_eight.step().unwrap();
+            assert_eq!(chip_eight.pc(), 0x202);
+        }
+    }
+
+    #[test]
+    fn hires_and_lores_switch_resolution_and_clear() {
+        for variant in [
+            ChipVariant::SuperChipLegacy,
+            ChipVariant::SuperChipModern,
+            ChipVariant::XoChip,
+        ] {
+            let mut chip_eight: ChipEight = machine(variant, &[0x00, 0xFF, 0x00, 0xFE]);
+            chip_eight.screen.xor_pixel(1, 1);
+            chip_eight.step().unwrap();
+            assert_eq!(chip_eight.resolution(), (128, 64));
+            assert_eq!(chip_eight.screen().mode(), ScreenMode::SuperHires);
+            assert!(!chip_eight.pixel(1, 1));
+            chip_eight.screen.xor_pixel(127, 63);
+            assert_eq!(chip_eight.framebuffer().len(), 64);
+            assert!(chip_eight.framebuffer()[63][127]);
+            chip_eight.step().unwrap();
+            assert_eq!(chip_eight.resolution(), (64, 32));
+            assert!(!chip_eight.pixel(127, 63));
+        }
+    }
+
+    #[test]
+    fn super_chip_scrolls_down_left_and_right() {
+        let mut chip_eight: ChipEight = machine(
+            ChipVariant::SuperChipModern,
+            &[0x00, 0xFF, 0x00, 0xC3, 0x00, 0xFB, 0x00, 0xFC, 0x00, 0xFC],
+        );
+        chip_eight.step().unwrap();
+        chip_eight.screen.xor_pixel(10, 10);
+        chip_eight.step().unwrap();
+        assert!(chip_eight.pixel(10, 13));
+        chip_eight.step().unwrap();
+        assert!(chip_eight.pixel(14, 13));
+        chip_eight.step().unwrap();
+        chip_eight.step().unwrap();
+        assert!(chip_eight.pixel(6, 13));
+        assert_eq!(chip_eight.pixels_set().count(), 1);
+    }
+
+    #[test]
+    fn dxy0_draws_16x16_in_super_chip_and_nothing_in_chip8() {
+        // A 16x16 sprite with only its corners lit, two bytes a row.
+        let mut program: [u8; 34] = [0; 34];
+        program[..2].copy_from_slice(&[0xD0, 0x10]);
+        program[2] = 0x80;
+        program[3] = 0x01;
+        program[32] = 0x80;
+        program[33] = 0x01;
+        for (variant, drawn) in [
+            (ChipVariant::Chip8, false),
+            (ChipVariant::SuperChipModern, true),
+            (ChipVariant::XoChip, true),
+        ] {
+            let mut chip_eight: ChipEight = machine(variant, &program);
+            chip_eight.i_register = 0x202;
+            chip_eight.v_registers[0] = 20;
+            chip_eight.v_registers[1] = 4;
+            chip_eight.step().unwrap();
+            let corners: [bool; 4] =
+                [(20, 4), (35, 4), (20, 19), (35, 19)].map(|(x, y)| chip_eight.pixel(x, y));
+            assert_eq!(corners, [drawn; 4], "{}", variant);
+            assert_eq!(chip_eight.pixels_set().count(), if drawn { 4 } else { 0 });
+            assert_eq!(chip_eight.pc(), 0x202);
+        }
+    }
+
+    #[test]
+    fn fx30_points_i_at_the_big_font() {
+        let mut chip_eight: ChipEight = machine(ChipVariant::SuperChipLegacy, &[0xF3, 0x30]);
+        chip_eight.v_registers[3] = 8;
+        chip_eight.step().unwrap();
+        let i: usize = chip_eight.i_register() as usize;
+        assert_eq!(i, BIG_FONT_ADDRESS as usize + 80);
+        assert_eq!(chip_eight.memory[i..i + 10], font::BIG_FONT[8]);
+    }
+
+    #[test]
+    fn flags_are_kept_through_a_reset() {
+        // Saves V0-VF, then loads them back after a reset.
+        let registers: [u8; 16] = ::core::array::from_fn(|x| x as u8 + 1);
+        let mut chip_eight: ChipEight = machine(ChipVariant::XoChip, &[0xFF, 0x75]);
+        chip_eight.v_registers = registers;
+        chip_eight.step().unwrap();
+        chip_eight.reset();
+        assert_eq!(chip_eight.v_registers(), &[0; 16]);
+        chip_eight.write_memory(0x200, &[0xFF, 0x85]).unwrap();
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.v_registers(), &registers);
+    }
+
+    #[test]
+    fn super_chip_only_has_eight_flags() {
+        let mut chip_eight: ChipEight = machine(
+            ChipVariant::SuperChipModern,
+            &[0xFF, 0x75, 0x00, 0xE0, 0xFF, 0x85],
+        );
+        chip_eight.v_registers = [0xAA; 16];
+        chip_eight.step().unwrap();
+        chip_eight.v_registers = [0; 16];
+        chip_eight.step().unwrap();
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.v_registers()[..8], [0xAA; 8]);
+        assert_eq!(chip_eight.v_registers()[8..], [0; 8]);
+    }
+
+    // Draws an 8x3 sprite at (0, 62) in hires, where its last row is clipped off the bottom, over a pixel it collides with.
+    fn draw_off_the_bottom(variant: ChipVariant) -> u8 {
+        let mut chip_eight: ChipEight =
+            machine(variant, &[0x00, 0xFF, 0xD0, 0x13, 0xFF, 0xFF, 0xFF]);
+        chip_eight.step().unwrap();
+        chip_eight.screen.xor_pixel(0, 62);
+        chip_eight.i_register = 0x204;
+        chip_eight.v_registers[1] = 62;
+        chip_eight.step().unwrap();
+        chip_eight.v_registers()[0xF]
+    }
+
+    #[test]
+    fn legacy_super_chip_counts_collided_and_clipped_rows_in_hires() {
+        assert_eq!(draw_off_the_bottom(ChipVariant::SuperChipLegacy), 2);
+        assert_eq!(draw_off_the_bottom(ChipVariant::SuperChipModern), 1);
+    }
+
+    #[test]
+    fn legacy_super_chip_only_waits_for_the_display_in_lores() {
+        // Hires (or not), then two draws, then a jump to itself.
+        for (hires, pc) in [(false, 0x204), (true, 0x206)] {
+            let mode: u8 = if hires { 0xFF } else { 0xFE };
+            let mut chip_eight: ChipEight = machine(
+                ChipVariant::SuperChipLegacy,
+                &[0x00, mode, 0xD0, 0x01, 0xD0, 0x01, 0x12, 0x06],
+            );
+            chip_eight.frame(&Keypad::default()).unwrap();
+            assert_eq!(chip_eight.pc(), pc, "hires = {}", hires);
+        }
+    }
+
+    // Overwrites its own data at 0x20C with AA, draws a 0, then jumps to itself.
+    #[cfg(feature = "std")]
+    const SELF_MODIFYING: [u8; 14] = [
+        0x60, 0xAA, 0xA2, 0x0C, 0xF0, 0x55, 0xA0, 0x50, 0xD0, 0x15, 0x12, 0x0A, 0x12, 0x34,
+    ];
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reset_undoes_what_the_program_did_to_itself() {
+        let mut fresh: ChipEight = ChipEight::with_seed(1);
+        fresh.load_program_from_slice(&SELF_MODIFYING).unwrap();
+        let mut chip_eight: ChipEight = fresh.clone();
+        for _ in 0..6 {
+            chip_eight.step().unwrap();
+        }
+        assert_eq!(chip_eight.memory()[0x20C], 0xAA);
+        assert_ne!(chip_eight.pixels_set().count(), 0);
+
+        chip_eight.reset();
+        assert_eq!(chip_eight.memory(), fresh.memory());
+        assert_eq!(chip_eight.pc(), 0x200);
+        assert_eq!(chip_eight.v_registers(), &[0; 16]);
+        assert_eq!(chip_eight.i_register(), 0);
+        assert_eq!(chip_eight.pixels_set().count(), 0);
+        // The program is still there to be reset to again.
+        chip_eight.step().unwrap();
+        chip_eight.reset();
+        assert_eq!(chip_eight.memory(), fresh.memory());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn hard_reset_forgets_the_program() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.load_program_from_slice(&SELF_MODIFYING).unwrap();
+        chip_eight.hard_reset();
+        assert_eq!(
+            chip_eight.memory(),
+            ChipEight::with_seed(1).memory(),
+            "only the font should be left"
+        );
+        assert!(chip_eight.rom_crc32().is_none());
+    }
+
+    #[test]
+    fn timers_tick_once_a_frame_at_any_speed() {
+        for instructions_per_frame in [1, 10, 1000] {
+            // Sets DT and ST to 10, then jumps to itself.
+            let mut chip_eight: ChipEight = machine(
+                ChipVariant::Chip8,
+                &[0x60, 0x0A, 0xF0, 0x15, 0xF0, 0x18, 0x12, 0x06],
+            );
+            chip_eight.delay_timer = 10;
+            chip_eight.sound_timer = 10;
+            chip_eight.set_instructions_per_frame(instructions_per_frame);
+            chip_eight.pc = 0x206;
+            for frames in 1..=3 {
+                chip_eight.frame(&Keypad::default()).unwrap();
+                assert_eq!(
+                    (chip_eight.delay_timer(), chip_eight.sound_timer()),
+                    (10 - frames, 10 - frames),
+                    "{} instructions a frame",
+                    instructions_per_frame
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn frame_runs_the_instructions_per_frame() {
+        // 40 ADD V0, 1s.
+        let program: [u8; 80] = ::core::array::from_fn(|i| [0x70, 0x01][i % 2]);
+        let mut chip_eight: ChipEight = machine(ChipVariant::Chip8, &program);
+        chip_eight.set_instructions_per_frame(15);
+        chip_eight.frame(&Keypad::default()).unwrap();
+        assert_eq!((chip_eight.pc(), chip_eight.v_registers()[0]), (0x21E, 15));
+    }
+
+    #[test]
+    fn a_sound_timer_of_n_beeps_for_n_frames() {
+        // Sets ST to 3 straight away, then jumps to itself.
+        let mut chip_eight: ChipEight =
+            machine(ChipVariant::Chip8, &[0x60, 0x03, 0xF0, 0x18, 0x12, 0x04]);
+        let beeps: [bool; 6] =
+            ::core::array::from_fn(|_| chip_eight.frame(&Keypad::default()).unwrap().beep);
+        assert_eq!(beeps, [false, true, true, true, false, false]);
+    }
+
+    #[test]
+    fn frame_reports_drawing_and_exiting() {
+        // Draws the 0, then exits.
+        let mut chip_eight: ChipEight = machine(
+            ChipVariant::SuperChipModern,
+            &[0xA0, 0x50, 0xD0, 0x05, 0x00, 0xFD],
+        );
+        let output: FrameOutput = chip_eight.frame(&Keypad::default()).unwrap();
+        assert_eq!(
+            output,
+            FrameOutput {
+                screen_dirty: true,
+                beep: false,
+                halted: true
+            }
+        );
+        let output: FrameOutput = chip_eight.frame(&Keypad::default()).unwrap();
+        assert!(!output.screen_dirty && output.halted);
+    }
+
+    // What the fuzz targets found overflowing. Tests build with overflow checks on, so these used to panic; now they
+    // wrap, as release builds always did.
+    #[cfg(feature = "std")]
+    #[test]
+    fn fuzzed_overflows_wrap() {
+        // (opcode, PC, V0, V1, I) to run it with, then the PC, V0 and I it leaves.
+        for (opcode, start, expected) in [
+            (0x6005u16, (0xFFFE, 0, 0, 0), (0x0000, 0x05, 0)),
+            (0x7002, (0x200, 0xFF, 0, 0), (0x202, 0x01, 0)),
+            (0x8015, (0x200, 0x01, 0x02, 0), (0x202, 0xFF, 0)),
+            (0x8017, (0x200, 0x02, 0x01, 0), (0x202, 0xFF, 0)),
+            (0xBFFF, (0x200, 0xFF, 0, 0), (0x10FE, 0xFF, 0)),
+            (0xF01E, (0x200, 0x01, 0, 0xFFFF), (0x202, 0x01, 0x0000)),
+            (0xF155, (0x200, 0x01, 0x02, 0xFFFF), (0x202, 0x01, 0x0001)),
+            (0xF165, (0x200, 0x00, 0x00, 0xFFFF), (0x202, 0x00, 0x0001)),
+        ] {
+            let mut chip_eight: ChipEight = ChipEightBuilder::new()
+                .variant(ChipVariant::XoChip)
+                .memory_size(MemorySize::Extended)
+                .rng_seed(1)
+                .build()
+                .unwrap();
+            let (pc, v0, v1, i) = start;
+            chip_eight
+                .write_memory(pc as usize, &opcode.to_be_bytes())
+                .unwrap();
+            chip_eight.set_pc(pc);
+            chip_eight.set_v(0, v0);
+            chip_eight.set_v(1, v1);
+            chip_eight.set_i_register(i);
+            chip_eight.step().unwrap();
+            assert_eq!(
+                (
+                    chip_eight.pc,
+                    chip_eight.v_registers[0],
+                    chip_eight.i_register
+                ),
+                expected,
+                "{:04X}",
+                opcode
+            );
+        }
+    }
+
+    #[test]
+    fn fx29_with_a_large_vx_doesnt_overflow() {
+        for variant in [ChipVariant::Chip8, ChipVariant::XoChip] {
+            let mut chip_eight: ChipEight = machine(variant, &[0xF0, 0x29]);
+            chip_eight.set_v(0, 0xFF);
+            chip_eight.step().unwrap();
+            assert_eq!(chip_eight.pc, 0x202);
+        }
+    }
+
+    #[test]
+    fn fx0a_records_the_key_it_took_until_a_reset() {
+        // LD V0, K, and then spin.
+        let mut chip_eight: ChipEight = machine(ChipVariant::Chip8, &[0xF0, 0x0A, 0x12, 0x02]);
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.key_taken(), None);
+
+        chip_eight.frames = 3;
+        chip_eight.keypad_mut().press(7);
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.key_taken(), Some((7, 3)));
+        chip_eight.reset();
+        assert_eq!(chip_eight.key_taken(), None);
     }
 }
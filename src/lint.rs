@@ -0,0 +1,296 @@
+// Looking over a ROM for likely mistakes without running it. Nothing distinguishes code from data in a ROM, so every word
+// is checked as if it were an instruction, and findings inside sprites and tables are to be expected. That's why most of
+// them are warnings; only jumps and calls that can't land inside the ROM are errors.
+use crate::{decode, Instruction};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    // Worth knowing, but not a problem: e.g. which variant the ROM seems to be written for.
+    Info,
+    // Probably a problem, if the word is code.
+    Warning,
+    // A problem wherever the program gets to it.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name: &str = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintFinding {
+    pub address: u16,
+    pub severity: Severity,
+    pub message: String,
+}
+
+// 2A4: warning: jump to odd address 0x2A7
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:03X}: {}: {}",
+            self.address, self.severity, self.message
+        )
+    }
+}
+
+// Checks a ROM loaded at base for:
+// - an odd length, which leaves a byte at the end that can't be a whole instruction
+// - words that don't decode as any instruction
+// - jumps and calls to odd addresses, or to addresses outside the ROM
+// - Fx29 when Vx was just loaded with a value the font doesn't have a digit for
+// - SUPER-CHIP and XO-CHIP instructions, followed by a note of the variant the ROM looks like it was written for
+// Findings are in address order, apart from that last note.
+pub fn lint_rom(bytes: &[u8], base: u16) -> Vec<LintFinding> {
+    let mut findings: Vec<LintFinding> = vec![];
+    let end: usize = base as usize + bytes.len();
+    let decoded: Vec<(usize, u16, Option<Instruction>)> = bytes
+        .chunks_exact(2)
+        .enumerate()
+        .map(|(i, word)| {
+            let opcode: u16 = u16::from_be_bytes([word[0], word[1]]);
+            (i * 2, opcode, decode(opcode).ok())
+        })
+        .collect();
+    // Anywhere the program can jump to might be reached with any values in the registers.
+    let targets: Vec<usize> = decoded
+        .iter()
+        .filter_map(|&(_, _, instruction)| match instruction {
+            Some(Instruction::JumpToAddress { nnn } | Instruction::CallSubroutine { nnn }) => {
+                Some(nnn as usize)
+            }
+            _ => None,
+        })
+        .collect();
+
+    // The values each register is known to hold, from 6xkk earlier in the same run of straight-line code.
+    let mut known: [Option<u8>; 16] = [None; 16];
+    let mut super_chip: bool = false;
+    let mut xo_chip: bool = false;
+    // Whether the word is the address after F000 rather than an instruction, and whether it comes after a skip, so it
+    // might not run.
+    let mut long_address: bool = false;
+    let mut skippable: bool = false;
+
+    for &(offset, opcode, instruction) in &decoded {
+        let address: u16 = (base as usize + offset) as u16;
+        let mut find = |severity: Severity, message: String| {
+            findings.push(LintFinding {
+                address,
+                severity,
+                message,
+            })
+        };
+        if std::mem::take(&mut long_address) {
+            continue;
+        }
+        let conditional: bool = std::mem::take(&mut skippable);
+        if targets.contains(&(address as usize)) {
+            known = [None; 16];
+        }
+        let instruction: Instruction = match instruction {
+            Some(instruction) => instruction,
+            None => {
+                find(Severity::Warning, format!("unknown opcode {:04X}", opcode));
+                known = [None; 16];
+                continue;
+            }
+        };
+
+        if instruction.is_super_chip() {
+            super_chip = true;
+            find(
+                Severity::Info,
+                format!("{:04X} is a SUPER-CHIP instruction", opcode),
+            );
+        }
+        if instruction.is_xo_chip() {
+            xo_chip = true;
+            find(
+                Severity::Info,
+                format!("{:04X} is an XO-CHIP instruction", opcode),
+            );
+        }
+        match instruction {
+            Instruction::JumpToAddress { nnn } | Instruction::CallSubroutine { nnn } => {
+                let what: &str = match instruction {
+                    Instruction::CallSubroutine { .. } => "call",
+                    _ => "jump",
+                };
+                if (nnn as usize) < base as usize || nnn as usize >= end {
+                    find(
+                        Severity::Error,
+                        format!("{} to {:#05X}, outside the ROM", what, nnn),
+                    );
+                } else if !nnn.is_multiple_of(2) {
+                    find(
+                        Severity::Warning,
+                        format!("{} to odd address {:#05X}", what, nnn),
+                    );
+                }
+            }
+            Instruction::SetIToFont { x } => {
+                if let Some(value) = known[x as usize].filter(|&value| value > 0xF) {
+                    find(
+                        Severity::Warning,
+                        format!(
+                            "V{:X} is {:#04X} here, but the font only has digits 0 to F",
+                            x, value
+                        ),
+                    );
+                }
+            }
+            Instruction::SetILong => long_address = true,
+            _ => {}
+        }
+
+        // Only instructions that leave the registers alone (or, for Dxyn, just VF) keep what's known about them.
+        match instruction {
+            Instruction::SetData { x, kk } => known[x as usize] = Some(kk),
+            Instruction::Draw { .. } => known[0xF] = None,
+            Instruction::ClearScreen
+            | Instruction::HiresClearScreen
+            | Instruction::SetI { .. }
+            | Instruction::SetILong
+            | Instruction::SetDelay { .. }
+            | Instruction::SetSound { .. }
+            | Instruction::AddAssignI { .. }
+            | Instruction::SetIToFont { .. }
+            | Instruction::Bcd { .. }
+            | Instruction::StoreRegisters { .. } => {}
+            _ => known = [None; 16],
+        }
+        if conditional {
+            known = [None; 16];
+        }
+        skippable = matches!(
+            instruction,
+            Instruction::SkipIfEqualsData { .. }
+                | Instruction::SkipIfNotEqualsData { .. }
+                | Instruction::SkipIfEquals { .. }
+                | Instruction::SkipIfNotEquals { .. }
+                | Instruction::SkipIfPressed { .. }
+                | Instruction::SkipIfNotPressed { .. }
+        );
+    }
+
+    if !bytes.len().is_multiple_of(2) {
+        findings.push(LintFinding {
+            address: (end - 1) as u16,
+            severity: Severity::Warning,
+            message: format!(
+                "odd length ({} bytes), so the last byte can't be a whole instruction",
+                bytes.len()
+            ),
+        });
+    }
+    let variant: Option<&str> = if xo_chip {
+        Some("an XO-CHIP")
+    } else if super_chip {
+        Some("a SUPER-CHIP")
+    } else {
+        None
+    };
+    if let Some(variant) = variant {
+        findings.push(LintFinding {
+            address: base,
+            severity: Severity::Info,
+            message: format!("this looks like {} ROM", variant),
+        });
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(bytes: &[u8]) -> Vec<String> {
+        lint_rom(bytes, 0x200)
+            .iter()
+            .map(LintFinding::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn a_clean_rom_has_no_findings() {
+        assert!(lint(&[0x60, 0x05, 0xF0, 0x29, 0xA2, 0x00, 0x12, 0x00]).is_empty());
+    }
+
+    #[test]
+    fn odd_lengths_and_unknown_opcodes_are_warnings() {
+        assert_eq!(
+            lint(&[0x51, 0x21, 0x12, 0x00, 0xAB]),
+            [
+                "200: warning: unknown opcode 5121",
+                "204: warning: odd length (5 bytes), so the last byte can't be a whole instruction",
+            ]
+        );
+    }
+
+    #[test]
+    fn jumps_and_calls_have_to_land_inside_the_rom() {
+        assert_eq!(
+            lint(&[0x12, 0x03, 0x23, 0x00, 0x11, 0x00, 0x22, 0x06, 0x00, 0xEE]),
+            [
+                "200: warning: jump to odd address 0x203",
+                "202: error: call to 0x300, outside the ROM",
+                "204: error: jump to 0x100, outside the ROM",
+            ]
+        );
+    }
+
+    #[test]
+    fn fx29_is_checked_when_vx_is_known() {
+        for (rom, expected) in [
+            (
+                &[0x60, 0x1F, 0xF0, 0x29][..],
+                &["202: warning: V0 is 0x1F here, but the font only has digits 0 to F"][..],
+            ),
+            // Drawing only changes VF.
+            (
+                &[0x61, 0x10, 0xD0, 0x05, 0xF1, 0x29],
+                &["204: warning: V1 is 0x10 here, but the font only has digits 0 to F"],
+            ),
+            // After 7xkk, after a skip, or where something jumps to, V0 could be anything.
+            (&[0x60, 0x1F, 0x70, 0x01, 0xF0, 0x29], &[]),
+            (&[0x60, 0x1F, 0x31, 0x00, 0x60, 0x03, 0xF0, 0x29], &[]),
+            (&[0x60, 0x1F, 0xF0, 0x29, 0x12, 0x02], &[]),
+        ] {
+            assert_eq!(lint(rom), expected, "{:02X?}", rom);
+        }
+    }
+
+    #[test]
+    fn extension_instructions_say_which_variant_the_rom_is_for() {
+        assert_eq!(
+            lint(&[0x00, 0xFF, 0x12, 0x02]),
+            [
+                "200: info: 00FF is a SUPER-CHIP instruction",
+                "200: info: this looks like a SUPER-CHIP ROM",
+            ]
+        );
+        // The word after F000 is an address, not a jump to 0x234.
+        assert_eq!(
+            lint(&[0x00, 0xFF, 0xF0, 0x00, 0x12, 0x34, 0x12, 0x06]),
+            [
+                "200: info: 00FF is a SUPER-CHIP instruction",
+                "202: info: F000 is an XO-CHIP instruction",
+                "200: info: this looks like an XO-CHIP ROM",
+            ]
+        );
+        let findings: Vec<LintFinding> = lint_rom(&[0x00, 0xFF], 0x200);
+        assert!(findings
+            .iter()
+            .all(|finding| finding.severity == Severity::Info));
+    }
+}
@@ -1,18 +1,1140 @@
-use potatocho::ChipEight;
+use potatocho::backend::{Audio, Input};
+use potatocho::core::{
+    ChipEight, ChipEightBuilder, ChipEightError, ChipVariant, Comparison, CoverageMap, ExitReason,
+    FrameOutput, Keypad, Palette, Quirks, TimingMode, PHOSPHOR_FRAMES,
+};
+use potatocho::frontend_sdl::{
+    error_screen, find_sdl_gl_driver, parse_audio_buffer, splash_screen, window_title,
+    AudioBackend, ErrorAction, Filter, Keymap, PixelAspect, Rotation, SdlAudio, SdlDisplay,
+    SdlInput, SpriteViewer, DEFAULT_AUDIO_BUFFER, SPLASH_HINT,
+};
+#[cfg(feature = "tui")]
+use potatocho::frontend_tui::{TuiAudio, TuiDisplay, TuiInput, TuiTerminal};
+use potatocho::{
+    disassemble_rom_with_symbols, lint_rom, parse_color, parse_input_script, parse_reference_trace,
+    quirk_combinations, quirk_presets, search_quirks, verify_trace, DebugServer, KeyEvent,
+    LintFinding, Profiler, Recorder, Recording, RecordingError, ReferenceStep, Replayer,
+    RewindSettings, RomDatabase, Settings, Severity, Sound, Symbols, TraceLog, TraceVerification,
+    WatchKind, Waveform, GIF_FPS, GIF_SCALE, MAX_VOLUME, SCREENSHOT_SCALE, TONE_HZ_RANGE,
+};
+#[cfg(feature = "scripting")]
+use potatocho::{run_script, ScriptError, HEADLESS_SEED};
 use rfd::FileDialog;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-// This function is lifted entirely from the rust-sdl2 github page https://github.com/Rust-SDL2/rust-sdl2
-fn find_sdl_gl_driver() -> Option<u32> {
-    for (i, item) in sdl2::render::drivers().enumerate() {
-        if item.name == "opengl" {
-            return Some(i as u32);
+// Command line options. Anything given here wins over sidecar files and the ROM database.
+#[derive(Default)]
+struct Args {
+    rom: Option<PathBuf>,
+    variant: Option<ChipVariant>,
+    instructions_per_frame: Option<u32>,
+    timing: Option<TimingMode>,
+    // The colors of lit and unlit pixels.
+    foreground: Option<[u8; 3]>,
+    background: Option<[u8; 3]>,
+    rom_db: Option<PathBuf>,
+    // Two variants to run side by side.
+    compare: Option<(ChipVariant, ChipVariant)>,
+    // Run the comparison for this many frames without a window, and just report where the screens diverged.
+    headless: Option<u64>,
+    // Run this ROM without a window for a number of frames, print a hash of the screen, and fail if it isn't the one
+    // expected.
+    check: Option<PathBuf>,
+    frames: Option<u32>,
+    expect_hash: Option<u64>,
+    // Run this ROM without a window under several quirk configurations (every combination of these quirks, or with
+    // "presets", each variant's), and report where their screens part ways.
+    find_quirks: Option<PathBuf>,
+    quirks: Option<String>,
+    // Print a listing of this ROM instead of running anything.
+    disassemble: Option<PathBuf>,
+    // Names for addresses in the ROM. By default, the ROM's .sym file is used if it has one.
+    symbols: Option<PathBuf>,
+    // Where to write a report of which addresses ran when the program stops. When disassembling, a report to read back
+    // instead, to mark which instructions ran.
+    coverage: Option<PathBuf>,
+    // Look through this ROM's memory for sprites instead of running it.
+    sprites: Option<PathBuf>,
+    // Print what lint_rom() finds in the ROM instead of running anything.
+    lint: bool,
+    // In dump mode, print this much memory from this address (by default, the whole ROM) instead of running anything.
+    // The sprite viewer starts at the address too.
+    dump: bool,
+    addr: Option<u16>,
+    len: Option<usize>,
+    // In render mode, run the ROM without a window (for --frames, pressing keys as this script says) and write its screen
+    // to out as a PNG, --scale times bigger, instead of running anything else.
+    render: bool,
+    out: Option<PathBuf>,
+    input_script: Option<PathBuf>,
+    // Addresses to pause at.
+    breakpoints: Vec<u16>,
+    // Memory to pause after any access to.
+    watchpoints: Vec<(WatchKind, RangeInclusive<u16>)>,
+    // Where to log every executed instruction, and how many to log at most.
+    trace: Option<PathBuf>,
+    trace_limit: Option<u64>,
+    // Count where the program spends its time, and print a report when it stops.
+    profile: bool,
+    // Where to listen for a remote debugger.
+    debug_listen: Option<String>,
+    // Record snapshots so the debugger can step backwards.
+    rewind: bool,
+    // Run this Rhai script against the ROM without a window, and fail if any of its assertions do.
+    script: Option<PathBuf>,
+    // Run the ROM without a window and compare every instruction with this reference log from another emulator.
+    verify_trace: Option<PathBuf>,
+    // Write a dump of the machine to the current directory if the program fails.
+    dump_on_error: bool,
+    // Print how fast the emulator runs and draws to stderr every second.
+    log_timing: bool,
+    // The effect drawn over the screen in the window, and how many frames pixels take to fade out (0 if they don't).
+    filter: Filter,
+    phosphor: Option<u8>,
+    // Start in fullscreen, or with the pixel grid showing. F11 and F7 switch them back and forth either way.
+    fullscreen: bool,
+    grid: bool,
+    // How many times the screen's size the window starts at, and whether the screen is stretched to fill the window
+    // rather than scaled by a whole number.
+    scale: Option<u32>,
+    fill: bool,
+    // Scale the screen up smoothly rather than keeping its pixels sharp. Shift+F9 switches back and forth, and the choice
+    // is saved in the user's config.
+    smooth: bool,
+    // The shape of the buzzer's tone. Shift+F8 cycles through them, and the choice is saved in the user's config.
+    wave: Option<Waveform>,
+    // The buzzer's pitch in Hz, and its volume from 0 to 100. [ and ] turn it down and up, and the volume picked is saved in
+    // the user's config.
+    tone_hz: Option<f32>,
+    volume: Option<u8>,
+    // Start with the buzzer muted. M mutes and unmutes it, and the choice is saved in the user's config.
+    mute: bool,
+    // How many samples the audio buffer holds, a power of two. Smaller means beeps start sooner; bigger means less
+    // crackling on busy machines.
+    audio_buffer: Option<u16>,
+    // How samples get to SDL. Without it, a callback is tried first and then a queue.
+    audio_backend: Option<AudioBackend>,
+    // Record everything the buzzer plays, silences and all, to a WAV file until the emulator exits.
+    record_audio: Option<PathBuf>,
+    // Keep frames to 60 a second with a timer rather than waiting for vsync, for displays that don't refresh at 60Hz.
+    no_vsync: bool,
+    // Which way the picture in the window is turned, for displays on their side.
+    rotation: Rotation,
+    // The shape the screen's pixels are shown in, width:height.
+    pixel_aspect: PixelAspect,
+    // Where F12 saves screenshots and how many times bigger than the screen they are, whether they're stretched by the
+    // pixel aspect too, and a number of frames to run the ROM for without a window before saving one and exiting.
+    screenshot_dir: Option<PathBuf>,
+    screenshot_scale: Option<u32>,
+    screenshot_respect_aspect: bool,
+    screenshot_after: Option<u32>,
+    // How many times bigger than the screen F10's GIFs are, and how many frames a second they take.
+    gif_scale: Option<u32>,
+    gif_fps: Option<u32>,
+    // Play in the terminal instead of a window.
+    tui: bool,
+    // Where to save the session's key presses so it can be replayed, and a saved session to play back instead of taking
+    // input (without a window if --check is given too).
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+}
+
+fn usage(error: &str) -> ! {
+    eprintln!("{}", error);
+    eprintln!("Usage: potatocho [--variant chip8|schip-legacy|schip-modern|xochip] [--ipf N] [--timing ipf|vip] [--fg COLOR] [--bg COLOR] [--rom-db FILE] [--break ADDR]... [--watch-read|--watch-write ADDR[-ADDR]]... [--trace FILE [--trace-limit N]] [--profile] [--rewind] [--debug-listen HOST:PORT] [--symbols FILE] [--coverage FILE] [--record FILE|--replay FILE] [--dump-on-error] [--log-timing] [--filter scanlines|grid|none] [--phosphor FRAMES|off] [--fullscreen] [--grid] [--scale N] [--fill] [--smooth] [--wave square|sine|triangle|saw|noise] [--tone-hz HZ] [--volume 0..100] [--mute] [--audio-buffer SAMPLES] [--audio-backend callback|queue|none] [--record-audio FILE] [--no-vsync] [--rotate 0|90|180|270] [--pixel-aspect W:H] [--screenshot-dir DIR] [--screenshot-scale N] [--screenshot-respect-aspect] [--screenshot-after FRAMES] [--gif-scale N] [--gif-fps N] [--compare VARIANT,VARIANT [--headless FRAMES]] [ROM]");
+    eprintln!("       potatocho --check ROM [--frames N] [--expect-hash HASH] [--coverage FILE] [--dump-on-error]");
+    eprintln!("       potatocho --check ROM --replay FILE [--coverage FILE]");
+    eprintln!("       potatocho --find-quirks ROM [--quirks QUIRK,QUIRK...|presets] [--frames N]");
+    eprintln!("       potatocho --script FILE ROM");
+    eprintln!("       potatocho --tui [--variant VARIANT] [--ipf N] [--fg COLOR] [--bg COLOR] ROM");
+    eprintln!("       potatocho --verify-trace FILE [--frames N] ROM");
+    eprintln!("       potatocho --disassemble ROM [--symbols FILE] [--coverage FILE]");
+    eprintln!("       potatocho --sprites ROM [--variant VARIANT] [--addr ADDR]");
+    eprintln!("       potatocho dump [--variant VARIANT] [--addr ADDR] [--len N] ROM");
+    eprintln!("       potatocho lint ROM");
+    eprintln!("       potatocho render ROM --out FILE [--frames N] [--scale N] [--input-script FILE] [--variant VARIANT] [--fg COLOR] [--bg COLOR]");
+    std::process::exit(2);
+}
+
+// What the file dialog offers to open. Octo source is compiled when it's loaded.
+#[cfg(feature = "octo")]
+const ROM_EXTENSIONS: &[&str] = &["ch8", "sc8", "xo8", "8o"];
+#[cfg(not(feature = "octo"))]
+const ROM_EXTENSIONS: &[&str] = &["ch8", "sc8", "xo8"];
+
+// How long --check runs a ROM for if --frames doesn't say: five seconds.
+const CHECK_FRAMES: u32 = 300;
+
+// Asks for a ROM with the file dialog. None if it was cancelled.
+fn pick_rom() -> Option<PathBuf> {
+    FileDialog::new()
+        .set_title("Select a valid Chip-8 program")
+        .add_filter("Chip-8 programs", ROM_EXTENSIONS)
+        .add_filter("All files", &["*"])
+        .pick_file()
+}
+
+// A hex address, with or without the 0x.
+fn parse_address(s: &str) -> Option<u16> {
+    let digits: &str = s.trim().trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).ok()
+}
+
+fn parse_args() -> Args {
+    let mut args = Args::default();
+    let mut argv = std::env::args().skip(1).peekable();
+
+    match argv.peek().map(String::as_str) {
+        Some("dump") => args.dump = true,
+        Some("lint") => args.lint = true,
+        Some("render") => args.render = true,
+        _ => {}
+    }
+    if args.dump || args.lint || args.render {
+        argv.next();
+    }
+
+    while let Some(arg) = argv.next() {
+        let mut value = |flag: &str| match argv.next() {
+            Some(value) => value,
+            None => usage(&format!("{} needs a value", flag)),
+        };
+        match arg.as_str() {
+            "--variant" => match value("--variant").parse() {
+                Ok(variant) => args.variant = Some(variant),
+                Err(e) => usage(&e),
+            },
+            "--ipf" => match value("--ipf").parse() {
+                Ok(ipf) if ipf > 0 => args.instructions_per_frame = Some(ipf),
+                _ => usage("--ipf needs a positive number"),
+            },
+            "--timing" => match value("--timing").parse() {
+                Ok(timing) => args.timing = Some(timing),
+                Err(e) => usage(&e),
+            },
+            "--fg" => match parse_color(&value("--fg")) {
+                Ok(color) => args.foreground = Some(color),
+                Err(e) => usage(&format!("--fg: {}", e)),
+            },
+            "--bg" => match parse_color(&value("--bg")) {
+                Ok(color) => args.background = Some(color),
+                Err(e) => usage(&format!("--bg: {}", e)),
+            },
+            "--rom-db" => args.rom_db = Some(PathBuf::from(value("--rom-db"))),
+            "--compare" => {
+                let variants: String = value("--compare");
+                let parsed = variants
+                    .split_once(',')
+                    .map(|(left, right)| (left.trim().parse(), right.trim().parse()));
+                match parsed {
+                    Some((Ok(left), Ok(right))) => args.compare = Some((left, right)),
+                    Some((Err(e), _) | (_, Err(e))) => usage(&e),
+                    None => usage("--compare needs two variants, e.g. --compare chip8,schip"),
+                }
+            }
+            "--break" => match parse_address(&value("--break")) {
+                Some(addr) => args.breakpoints.push(addr),
+                None => usage("--break needs a hex address, e.g. --break 0x2A4"),
+            },
+            flag @ ("--watch-read" | "--watch-write") => {
+                let range: String = value(flag);
+                let (start, end) = range.split_once('-').unwrap_or((&range, &range));
+                match (parse_address(start), parse_address(end)) {
+                    (Some(start), Some(end)) if start <= end => {
+                        let kind: WatchKind = match flag {
+                            "--watch-read" => WatchKind::Read,
+                            _ => WatchKind::Write,
+                        };
+                        args.watchpoints.push((kind, start..=end));
+                    }
+                    _ => usage(&format!(
+                        "{} needs a hex address or range, e.g. {} 0x3A0 or {} 0x300-0x30F",
+                        flag, flag, flag
+                    )),
+                }
+            }
+            "--trace" => args.trace = Some(PathBuf::from(value("--trace"))),
+            "--trace-limit" => match value("--trace-limit").parse() {
+                Ok(lines) => args.trace_limit = Some(lines),
+                _ => usage("--trace-limit needs a number of lines"),
+            },
+            "--profile" => args.profile = true,
+            "--rewind" => args.rewind = true,
+            "--debug-listen" => args.debug_listen = Some(value("--debug-listen")),
+            "--addr" => match parse_address(&value("--addr")) {
+                Some(addr) => args.addr = Some(addr),
+                None => usage("--addr needs a hex address, e.g. --addr 0x200"),
+            },
+            "--len" if args.dump => match value("--len").parse() {
+                Ok(len) => args.len = Some(len),
+                _ => usage("--len needs a number of bytes"),
+            },
+            "--out" if args.render => args.out = Some(PathBuf::from(value("--out"))),
+            "--input-script" if args.render => {
+                args.input_script = Some(PathBuf::from(value("--input-script")))
+            }
+            "--check" => args.check = Some(PathBuf::from(value("--check"))),
+            "--find-quirks" => args.find_quirks = Some(PathBuf::from(value("--find-quirks"))),
+            "--quirks" => args.quirks = Some(value("--quirks")),
+            "--frames" => match value("--frames").parse() {
+                Ok(frames) => args.frames = Some(frames),
+                _ => usage("--frames needs a number of frames"),
+            },
+            "--expect-hash" => {
+                let hash: String = value("--expect-hash");
+                let digits: &str = hash.trim_start_matches("0x").trim_start_matches("0X");
+                match u64::from_str_radix(digits, 16) {
+                    Ok(hash) => args.expect_hash = Some(hash),
+                    _ => usage(
+                        "--expect-hash needs a hex hash, e.g. --expect-hash 0x1A2B3C4D5E6F7081",
+                    ),
+                }
+            }
+            "--sprites" => args.sprites = Some(PathBuf::from(value("--sprites"))),
+            "--symbols" => args.symbols = Some(PathBuf::from(value("--symbols"))),
+            "--coverage" => args.coverage = Some(PathBuf::from(value("--coverage"))),
+            "--script" => args.script = Some(PathBuf::from(value("--script"))),
+            "--verify-trace" => args.verify_trace = Some(PathBuf::from(value("--verify-trace"))),
+            "--dump-on-error" => args.dump_on_error = true,
+            "--log-timing" => args.log_timing = true,
+            "--fullscreen" => args.fullscreen = true,
+            "--grid" => args.grid = true,
+            "--scale" => match value("--scale").parse() {
+                Ok(scale) if (1..=64).contains(&scale) => args.scale = Some(scale),
+                _ => usage("--scale needs a number from 1 to 64"),
+            },
+            "--fill" => args.fill = true,
+            "--smooth" => args.smooth = true,
+            "--mute" => args.mute = true,
+            "--audio-buffer" => match parse_audio_buffer(&value("--audio-buffer")) {
+                Ok(samples) => args.audio_buffer = Some(samples),
+                Err(e) => usage(&e),
+            },
+            "--record-audio" => args.record_audio = Some(PathBuf::from(value("--record-audio"))),
+            "--audio-backend" => match value("--audio-backend").parse() {
+                Ok(backend) => args.audio_backend = Some(backend),
+                Err(e) => usage(&e),
+            },
+            "--screenshot-respect-aspect" => args.screenshot_respect_aspect = true,
+            "--no-vsync" => args.no_vsync = true,
+            "--tui" => args.tui = true,
+            "--screenshot-dir" => {
+                args.screenshot_dir = Some(PathBuf::from(value("--screenshot-dir")))
+            }
+            "--screenshot-scale" => match value("--screenshot-scale").parse() {
+                Ok(scale) if (1..=64).contains(&scale) => args.screenshot_scale = Some(scale),
+                _ => usage("--screenshot-scale needs a number from 1 to 64"),
+            },
+            "--gif-scale" => match value("--gif-scale").parse() {
+                Ok(scale) if (1..=8).contains(&scale) => args.gif_scale = Some(scale),
+                _ => usage("--gif-scale needs a number from 1 to 8"),
+            },
+            "--gif-fps" => match value("--gif-fps").parse() {
+                Ok(fps) if (1..=60).contains(&fps) => args.gif_fps = Some(fps),
+                _ => usage("--gif-fps needs a number from 1 to 60"),
+            },
+            "--screenshot-after" => match value("--screenshot-after").parse() {
+                Ok(frames) => args.screenshot_after = Some(frames),
+                _ => usage("--screenshot-after needs a number of frames"),
+            },
+            "--phosphor" => match value("--phosphor").as_str() {
+                "off" => args.phosphor = Some(0),
+                frames => match frames.parse() {
+                    Ok(frames) => args.phosphor = Some(frames),
+                    Err(_) => usage("--phosphor needs a number of frames (up to 255), or off"),
+                },
+            },
+            "--filter" => match value("--filter").parse() {
+                Ok(filter) => args.filter = filter,
+                Err(e) => usage(&e),
+            },
+            "--wave" => match value("--wave").parse() {
+                Ok(wave) => args.wave = Some(wave),
+                Err(e) => usage(&e),
+            },
+            "--tone-hz" => match value("--tone-hz").parse::<f32>() {
+                Ok(hz) if TONE_HZ_RANGE.contains(&hz) => args.tone_hz = Some(hz),
+                _ => usage(&format!(
+                    "--tone-hz needs a frequency between {} and {}",
+                    TONE_HZ_RANGE.start(),
+                    TONE_HZ_RANGE.end()
+                )),
+            },
+            "--volume" => match value("--volume").parse::<u8>() {
+                Ok(volume) if volume <= MAX_VOLUME => args.volume = Some(volume),
+                _ => usage(&format!(
+                    "--volume needs a number between 0 and {}",
+                    MAX_VOLUME
+                )),
+            },
+            "--rotate" => match value("--rotate").parse() {
+                Ok(rotation) => args.rotation = rotation,
+                Err(e) => usage(&e),
+            },
+            "--pixel-aspect" => match value("--pixel-aspect").parse() {
+                Ok(aspect) => args.pixel_aspect = aspect,
+                Err(e) => usage(&e),
+            },
+            "--record" => args.record = Some(PathBuf::from(value("--record"))),
+            "--replay" => args.replay = Some(PathBuf::from(value("--replay"))),
+            "--disassemble" => args.disassemble = Some(PathBuf::from(value("--disassemble"))),
+            "--headless" => match value("--headless").parse() {
+                Ok(frames) => args.headless = Some(frames),
+                _ => usage("--headless needs a number of frames"),
+            },
+            flag if flag.starts_with("--") => usage(&format!("Unknown option {}", flag)),
+            rom => args.rom = Some(PathBuf::from(rom)),
+        }
+    }
+    if args.dump && args.rom.is_none() {
+        usage("dump needs a ROM");
+    }
+    if args.lint && args.rom.is_none() {
+        usage("lint needs a ROM");
+    }
+    if args.addr.is_some() && !args.dump && args.sprites.is_none() {
+        usage("--addr needs dump or --sprites");
+    }
+    if args.sprites.is_some() && args.compare.is_some() {
+        usage("--sprites can't be used with --compare");
+    }
+    if args.headless.is_some() && (args.compare.is_none() || args.rom.is_none()) {
+        usage("--headless needs --compare and a ROM");
+    }
+    if args.frames.is_some()
+        && args.check.is_none()
+        && args.find_quirks.is_none()
+        && args.verify_trace.is_none()
+        && !args.render
+    {
+        usage("--frames needs render, --check, --find-quirks, or --verify-trace");
+    }
+    if args.render && (args.rom.is_none() || args.out.is_none()) {
+        usage("render needs a ROM and --out");
+    }
+    if args.expect_hash.is_some() && args.check.is_none() {
+        usage("--expect-hash needs --check");
+    }
+    if args.quirks.is_some() && args.find_quirks.is_none() {
+        usage("--quirks needs --find-quirks");
+    }
+    if args.trace.is_some() && args.compare.is_some() {
+        usage("--trace can't be used with --compare");
+    }
+    if args.profile && args.compare.is_some() {
+        usage("--profile can't be used with --compare");
+    }
+    if args.debug_listen.is_some() && args.compare.is_some() {
+        usage("--debug-listen can't be used with --compare");
+    }
+    if args.coverage.is_some() && args.compare.is_some() {
+        usage("--coverage can't be used with --compare");
+    }
+    if args.record.is_some() && args.replay.is_some() {
+        usage("--record can't be used with --replay");
+    }
+    if (args.record.is_some() || args.replay.is_some())
+        && (args.compare.is_some() || args.debug_listen.is_some())
+    {
+        usage("--record and --replay can't be used with --compare or --debug-listen");
+    }
+    if args.record.is_some() && args.check.is_some() {
+        usage("--record can't be used with --check");
+    }
+    if args.replay.is_some() && (args.frames.is_some() || args.expect_hash.is_some()) {
+        usage("--replay runs for as long as the recording, and checks its hash");
+    }
+    if args.verify_trace.is_some() && args.rom.is_none() {
+        usage("--verify-trace needs a ROM");
+    }
+    if args.script.is_some() && args.rom.is_none() {
+        usage("--script needs a ROM");
+    }
+    if args.script.is_some() && !cfg!(feature = "scripting") {
+        usage("--script needs potatocho to be built with the scripting feature");
+    }
+    if args.dump_on_error && args.compare.is_some() {
+        usage("--dump-on-error can't be used with --compare");
+    }
+    if args.log_timing
+        && (args.compare.is_some()
+            || args.check.is_some()
+            || args.find_quirks.is_some()
+            || args.script.is_some()
+            || args.verify_trace.is_some())
+    {
+        usage("--log-timing times the emulator running in a window, so it can't be used with --compare, --check, --find-quirks, --script, or --verify-trace");
+    }
+    if args.screenshot_after.is_some() && (args.rom.is_none() || args.compare.is_some()) {
+        usage("--screenshot-after needs a ROM, and can't be used with --compare");
+    }
+    if args.tui && (args.rom.is_none() || args.compare.is_some()) {
+        usage("--tui needs a ROM, and can't be used with --compare");
+    }
+    if args.tui && !cfg!(feature = "tui") {
+        usage("--tui needs potatocho to be built with the tui feature");
+    }
+    if args.trace_limit.is_some() && args.trace.is_none() {
+        usage("--trace-limit needs --trace");
+    }
+    args
+}
+
+impl Args {
+    fn settings(&self) -> Settings {
+        Settings {
+            variant: self.variant,
+            instructions_per_frame: self.instructions_per_frame,
+            timing: self.timing,
+            foreground: self.foreground,
+            background: self.background,
+            smooth: self.smooth.then_some(true),
+            wave: self.wave,
+            tone_hz: self.tone_hz,
+            volume: self.volume,
+            mute: self.mute.then_some(true),
+            ..Settings::default()
+        }
+    }
+}
+
+// Where user settings live: $XDG_CONFIG_HOME/potatocho, falling back to ~/.config/potatocho.
+fn config_dir() -> Option<PathBuf> {
+    match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => Some(PathBuf::from(dir).join("potatocho")),
+        None => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".config").join("potatocho")),
+    }
+}
+
+// The user's own settings, config.toml in the config directory. They apply to every ROM run in a window, under
+// everything else, and the theme picked with F8 and the scaling picked with Shift+F9 are saved there.
+fn user_config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+fn load_user_config() -> Settings {
+    let path: PathBuf = match user_config_path() {
+        Some(path) => path,
+        None => return Settings::default(),
+    };
+    match Settings::load(&path) {
+        Ok(Some(settings)) => {
+            println!("Loaded settings from {}!", path.display());
+            settings
+        }
+        Ok(None) => Settings::default(),
+        Err(e) => panic!("Error loading settings: {}", e),
+    }
+}
+
+// The built-in ROM database, extended with the user's roms.toml (or the file given with --rom-db) if there is one.
+fn load_rom_database(path: Option<PathBuf>) -> RomDatabase {
+    let mut database = RomDatabase::builtin();
+    let explicit: bool = path.is_some();
+    let path = match path.or_else(|| config_dir().map(|dir| dir.join("roms.toml"))) {
+        Some(path) => path,
+        None => return database,
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(src) => match database.extend_from_toml(&src) {
+            Ok(_) => println!("Loaded ROM database {}!", path.display()),
+            Err(e) => panic!("Error reading ROM database {}: {}", path.display(), e),
+        },
+        Err(e) if explicit => panic!("Error reading ROM database {}: {:?}", path.display(), e),
+        Err(_) => {}
+    };
+    database
+}
+
+// The symbols for a ROM: from --symbols, or else the .sym file next to the ROM, if there is one.
+fn load_symbols(path: Option<PathBuf>, rom: &Path) -> Symbols {
+    let explicit: bool = path.is_some();
+    let path: PathBuf = path.unwrap_or_else(|| rom.with_extension("sym"));
+
+    match std::fs::read_to_string(&path) {
+        Ok(src) => match Symbols::parse(&src) {
+            Ok(symbols) => {
+                println!("Loaded {} symbols from {}!", symbols.len(), path.display());
+                symbols
+            }
+            Err(e) => panic!("Error reading symbols {}: {}", path.display(), e),
+        },
+        Err(e) if explicit => panic!("Error reading symbols {}: {:?}", path.display(), e),
+        Err(_) => Symbols::new(),
+    }
+}
+
+// The settings for a ROM the machine has just loaded: the command line, over its sidecar file, over its ROM database entry.
+fn rom_settings(
+    args: &Args,
+    file: &Path,
+    chip_eight: &mut ChipEight,
+    rom_database: &RomDatabase,
+) -> Settings {
+    let sidecar = match Settings::load_sidecar(file) {
+        Ok(Some(settings)) => {
+            println!(
+                "Loaded settings from {}!",
+                Settings::sidecar_path(file).display()
+            );
+            settings
+        }
+        Ok(None) => Settings::default(),
+        Err(e) => panic!("Error loading ROM settings: {}", e),
+    };
+
+    let mut settings = Settings::default();
+    if let Some(entry) = chip_eight.identify_rom(rom_database) {
+        println!("Recognized {} ({})!", entry.title, entry.variant);
+        settings = Settings::from_rom_entry(entry);
+    }
+    args.settings().layered_over(sidecar.layered_over(settings))
+}
+
+// A machine for one side of a comparison, with the ROM loaded.
+fn comparison_machine(variant: ChipVariant, seed: u64, file: &Path) -> ChipEight {
+    let mut chip_eight = match ChipEightBuilder::new()
+        .variant(variant)
+        .rng_seed(seed)
+        .build()
+    {
+        Ok(chip_eight) => chip_eight,
+        Err(e) => panic!("Error creating {} machine: {}", variant, e),
+    };
+    if let Err(e) = chip_eight.load_program_from_path(file) {
+        panic!("Error loading {}: {}", file.display(), e);
+    }
+    chip_eight
+}
+
+// Two machines running the ROM with the --compare variants' quirk presets. Everything else about the ROM's settings is
+// shared, including the RNG seed, so the only differences between them are the ones being compared. The settings come
+// back too, for the frontend's share of them.
+fn build_comparison(
+    args: &Args,
+    file: &Path,
+    rom_database: &RomDatabase,
+) -> (Comparison, Settings) {
+    let (left_variant, right_variant) = args.compare.expect("not in comparison mode");
+    let seed: u64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64);
+    let mut left = comparison_machine(left_variant, seed, file);
+    let mut right = comparison_machine(right_variant, seed, file);
+
+    let settings = rom_settings(args, file, &mut left, rom_database);
+    right.identify_rom(rom_database);
+    for (chip_eight, variant) in [(&mut left, left_variant), (&mut right, right_variant)] {
+        let settings = Settings {
+            variant: Some(variant),
+            quirks: vec![],
+            ..settings.clone()
+        };
+        if let Err(e) = settings.apply(chip_eight) {
+            panic!("Error applying ROM settings: {}", e);
+        }
+    }
+    (Comparison::new(left, right), settings)
+}
+
+// Finishes the --record-audio recording, if there is one, so its header says how long it is.
+fn finish_audio_recording(audio: &mut SdlAudio) {
+    match audio.stop_recording() {
+        Some(Ok(path)) => println!("Saved audio recording {}!", path.display()),
+        Some(Err(e)) => eprintln!("Error finishing the audio recording: {}", e),
+        None => {}
+    }
+}
+
+// The comparison's version of ChipEight::run(): both machines get the keys pressed in the window, and the first
+// divergence is logged as it happens.
+fn run_comparison(
+    comparison: &mut Comparison,
+    display: &mut SdlDisplay,
+    input: &mut SdlInput,
+    audio: &mut SdlAudio,
+) -> Result<(), ChipEightError> {
+    let mut reported: bool = false;
+
+    loop {
+        if !reported {
+            if let Some(frame) = comparison.divergence() {
+                println!("Screens diverged at frame {}!", frame);
+                reported = true;
+            }
+        }
+        if input.poll(&mut comparison.left).is_break() {
+            return Ok(());
+        }
+
+        let keys: Keypad = *comparison.left.keypad();
+        let (left, right): (FrameOutput, FrameOutput) = comparison.frame(&keys)?;
+        audio.set_beeping(left.beep || right.beep);
+        display
+            .present_side_by_side(&comparison.left, &comparison.right)
+            .map_err(ChipEightError::Frontend)?;
+        comparison.left.take_dirty_region();
+        comparison.right.take_dirty_region();
+
+        if left.halted && right.halted {
+            audio.set_beeping(false);
+            return Ok(());
+        }
+    }
+}
+
+// --coverage: writes the coverage report for the program the machine ran.
+fn write_coverage(args: &Args, chip_eight: &ChipEight) {
+    if let Some(path) = &args.coverage {
+        let report: String = chip_eight.coverage_report();
+        match std::fs::write(path, &report) {
+            Ok(_) => print!(
+                "Wrote coverage to {}: {}",
+                path.display(),
+                report.lines().next().unwrap_or("")
+            ),
+            Err(e) => eprintln!("Error writing coverage report {}: {}", path.display(), e),
         }
     }
-    None
 }
+
+// --find-quirks: runs the ROM headless, with its usual settings, under each quirk configuration and prints where their
+// screens diverged.
+fn find_quirks(args: &Args, file: &Path, rom_database: &RomDatabase) {
+    let mut chip_eight = ChipEight::new();
+    if let Err(e) = chip_eight.load_program_from_path(file) {
+        panic!("Error loading {}: {}", file.display(), e);
+    }
+    let settings = rom_settings(args, file, &mut chip_eight, rom_database);
+    if let Err(e) = settings.apply(&mut chip_eight) {
+        panic!("Error applying ROM settings: {}", e);
+    }
+
+    let names: Vec<&str> = match args.quirks.as_deref() {
+        Some(names) => names.split(',').map(str::trim).collect(),
+        None => Quirks::NAMES.to_vec(),
+    };
+    let configs: Vec<(String, Quirks)> = match names[..] {
+        ["presets"] => quirk_presets(),
+        _ => match quirk_combinations(chip_eight.quirks(), &names) {
+            Ok(configs) => configs,
+            Err(e) => usage(&e),
+        },
+    };
+    print!(
+        "{}",
+        search_quirks(
+            &chip_eight,
+            &configs,
+            args.frames.unwrap_or(CHECK_FRAMES),
+            &[]
+        )
+    );
+}
+
+// --dump-on-error: writes a dump of the machine the program failed on to the current directory.
+fn dump_on_error(args: &Args, chip_eight: &ChipEight, error: &ChipEightError) {
+    if !args.dump_on_error || error.pc().is_none() {
+        return;
+    }
+    match chip_eight.crash_dump(Some(error)).write_to(Path::new(".")) {
+        Ok(path) => println!("Wrote a dump to {}!", path.display()),
+        Err(e) => eprintln!("Error writing a dump: {}", e),
+    }
+}
+
+// --script: runs a script against the ROM headless, with its usual settings and a fixed random seed, and exits with 1 if
+// the script fails.
+#[cfg(feature = "scripting")]
+fn run_rom_script(args: &Args, script: &Path, file: &Path, rom_database: &RomDatabase) -> ! {
+    let source: String = match std::fs::read_to_string(script) {
+        Ok(source) => source,
+        Err(e) => panic!("Error reading script {}: {:?}", script.display(), e),
+    };
+    let mut chip_eight = ChipEight::new();
+    if let Err(e) = chip_eight.load_program_from_path(file) {
+        panic!("Error loading {}: {}", file.display(), e);
+    }
+    let settings = rom_settings(args, file, &mut chip_eight, rom_database);
+    if let Err(e) = settings.apply(&mut chip_eight) {
+        panic!("Error applying ROM settings: {}", e);
+    }
+    chip_eight.seed_rng(HEADLESS_SEED);
+
+    let result: Result<(), ScriptError> = run_script(&mut chip_eight, &source);
+    write_coverage(args, &chip_eight);
+    match result {
+        Ok(()) => {
+            println!("{} passed.", script.display());
+            std::process::exit(0);
+        }
+        Err(e) => {
+            if let ScriptError::Emulation { error, .. } = &e {
+                dump_on_error(args, &chip_eight, error);
+            }
+            eprintln!("{} failed: {}", script.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// --verify-trace: runs the ROM headless with its usual settings, comparing each instruction with the reference log, and
+// exits with 1 at the first difference.
+fn verify_rom_trace(args: &Args, reference: &Path, file: &Path, rom_database: &RomDatabase) -> ! {
+    let src: String = match std::fs::read_to_string(reference) {
+        Ok(src) => src,
+        Err(e) => panic!("Error reading trace {}: {:?}", reference.display(), e),
+    };
+    let reference_steps: Vec<ReferenceStep> = match parse_reference_trace(&src) {
+        Ok(steps) => steps,
+        Err(e) => panic!("Error reading trace {}: {}", reference.display(), e),
+    };
+    let mut chip_eight = ChipEight::new();
+    if let Err(e) = chip_eight.load_program_from_path(file) {
+        panic!("Error loading {}: {}", file.display(), e);
+    }
+    let settings = rom_settings(args, file, &mut chip_eight, rom_database);
+    if let Err(e) = settings.apply(&mut chip_eight) {
+        panic!("Error applying ROM settings: {}", e);
+    }
+
+    let max_frames: u64 = args.frames.map_or(u64::MAX, u64::from);
+    let verification: TraceVerification =
+        verify_trace(&mut chip_eight, &reference_steps, max_frames);
+    if let Some(divergence) = &verification.divergence {
+        print!("{}", divergence);
+        std::process::exit(1);
+    }
+    if let Some(e) = &verification.error {
+        dump_on_error(args, &chip_eight, e);
+        eprintln!(
+            "Error running program after {} matching instructions: {}",
+            verification.matched, e
+        );
+        std::process::exit(1);
+    }
+    if !verification.passed() {
+        eprintln!(
+            "The program stopped after {} of the reference's {} instructions.",
+            verification.matched, verification.expected
+        );
+        std::process::exit(1);
+    }
+    println!(
+        "All {} instructions matched {}.",
+        verification.matched,
+        reference.display()
+    );
+    std::process::exit(0);
+}
+
+fn load_recording(path: &Path) -> Recording {
+    let src: String = match std::fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) => panic!("Error reading recording {}: {:?}", path.display(), e),
+    };
+    match Recording::parse(&src) {
+        Ok(recording) => recording,
+        Err(e) => panic!("Error reading recording {}: {}", path.display(), e),
+    }
+}
+
+// Prints whether a replay ended up where the recording did.
+fn report_replay(result: &Result<(), RecordingError>) {
+    match result {
+        Ok(_) => println!("Replay matched the recording."),
+        Err(e) => eprintln!("Replay didn't match the recording: {}", e),
+    }
+}
+
+// --check --replay: plays the recording back headless and exits with 1 if it doesn't end up on the recorded screen.
+fn check_replay(args: &Args, file: &Path, recording: &Path) -> ! {
+    let recording: Recording = load_recording(recording);
+    let mut chip_eight = ChipEight::new();
+    if let Err(e) = chip_eight.load_program_from_path(file) {
+        panic!("Error loading {}: {}", file.display(), e);
+    }
+
+    let result: Result<(), RecordingError> = recording.replay_headless(&mut chip_eight);
+    write_coverage(args, &chip_eight);
+    report_replay(&result);
+    std::process::exit(if result.is_ok() { 0 } else { 1 });
+}
+
+// lint: prints what lint_rom() finds, and exits with 1 if any of it is an error.
+fn lint(file: &Path) -> ! {
+    let rom: Vec<u8> = match std::fs::read(file) {
+        Ok(rom) => rom,
+        Err(e) => panic!("Error loading {}: {}", file.display(), e),
+    };
+    let findings: Vec<LintFinding> = lint_rom(&rom, 0x200);
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    if findings.is_empty() {
+        println!("No problems found.");
+    }
+    let errors: bool = findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error);
+    std::process::exit(if errors { 1 } else { 0 });
+}
+
+// --check: runs the ROM headless with its usual settings, prints the screen's hash, and exits with 1 if the program failed
+// or the hash isn't the expected one.
+fn check_rom(args: &Args, file: &Path, rom_database: &RomDatabase) -> ! {
+    if let Some(recording) = &args.replay {
+        check_replay(args, file, recording);
+    }
+    let mut chip_eight = ChipEight::new();
+    if let Err(e) = chip_eight.load_program_from_path(file) {
+        panic!("Error loading {}: {}", file.display(), e);
+    }
+    let settings = rom_settings(args, file, &mut chip_eight, rom_database);
+    if let Err(e) = settings.apply(&mut chip_eight) {
+        panic!("Error applying ROM settings: {}", e);
+    }
+
+    let result = chip_eight.run_headless(args.frames.unwrap_or(CHECK_FRAMES), &[]);
+    write_coverage(args, &chip_eight);
+    println!(
+        "Screen hash after {} frames: {:#018x}",
+        result.frames, result.hash
+    );
+    if let Some(e) = result.error {
+        dump_on_error(args, &chip_eight, &e);
+        eprintln!("Error running program: {}", e);
+        std::process::exit(1);
+    }
+    match args.expect_hash {
+        Some(expected) if expected != result.hash => {
+            eprintln!("Expected {:#018x}!", expected);
+            std::process::exit(1);
+        }
+        _ => std::process::exit(0),
+    }
+}
+
+// render: runs the ROM headless with its usual settings, except in the default colors unless --fg or --bg says otherwise,
+// and with no keys pressed but the ones in --input-script. Then writes the screen to --out as a PNG, for documentation and
+// golden images. It never makes a window, so it runs without a display. Exits with 1 if the program failed (after writing
+// the screen it failed on) or the PNG couldn't be written.
+fn render(args: &Args, file: &Path, out: &Path, rom_database: &RomDatabase) -> ! {
+    let events: Vec<KeyEvent> = match &args.input_script {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(src) => match parse_input_script(&src) {
+                Ok(events) => events,
+                Err(e) => panic!("Error loading input script {}: {}", path.display(), e),
+            },
+            Err(e) => panic!("Error loading input script {}: {}", path.display(), e),
+        },
+        None => vec![],
+    };
+    let mut chip_eight = ChipEight::new();
+    if let Err(e) = chip_eight.load_program_from_path(file) {
+        panic!("Error loading {}: {}", file.display(), e);
+    }
+    let mut settings = rom_settings(args, file, &mut chip_eight, rom_database);
+    settings.foreground = args.foreground;
+    settings.background = args.background;
+    if let Err(e) = settings.apply(&mut chip_eight) {
+        panic!("Error applying ROM settings: {}", e);
+    }
+
+    let result = chip_eight.run_headless_keys(args.frames.unwrap_or(CHECK_FRAMES), &events);
+    let mut png: Vec<u8> = vec![];
+    let written: std::io::Result<()> = chip_eight
+        .write_screenshot(
+            settings.palette(),
+            args.scale.unwrap_or(SCREENSHOT_SCALE),
+            &mut png,
+        )
+        .and_then(|_| std::fs::write(out, &png));
+    match written {
+        Ok(()) => println!("Rendered {} after {} frames!", out.display(), result.frames),
+        Err(e) => {
+            eprintln!("Error writing {}: {}", out.display(), e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(e) = result.error {
+        dump_on_error(args, &chip_eight, &e);
+        eprintln!("Error running program: {}", e);
+        std::process::exit(1);
+    }
+    std::process::exit(0);
+}
+
+// --screenshot-after: runs the ROM headless with its usual settings, saves a screenshot, and exits with 1 if the program
+// failed (after saving the screen it failed on) or the screenshot couldn't be saved.
+fn screenshot_rom(args: &Args, frames: u32, file: &Path, rom_database: &RomDatabase) -> ! {
+    let mut chip_eight = ChipEight::new();
+    if let Err(e) = chip_eight.load_program_from_path(file) {
+        panic!("Error loading {}: {}", file.display(), e);
+    }
+    let settings = rom_settings(args, file, &mut chip_eight, rom_database);
+    if let Err(e) = settings.apply(&mut chip_eight) {
+        panic!("Error applying ROM settings: {}", e);
+    }
+
+    let result = chip_eight.run_headless(frames, &[]);
+    let dir: &Path = args.screenshot_dir.as_deref().unwrap_or(Path::new("."));
+    let scale: u32 = args.screenshot_scale.unwrap_or(SCREENSHOT_SCALE);
+    let scales: (u32, u32) = if args.screenshot_respect_aspect {
+        (
+            scale * args.pixel_aspect.width(),
+            scale * args.pixel_aspect.height(),
+        )
+    } else {
+        (scale, scale)
+    };
+    match chip_eight.save_screenshot_scaled(settings.palette(), dir, scales) {
+        Ok(path) => println!(
+            "Saved screenshot {} after {} frames!",
+            path.display(),
+            result.frames
+        ),
+        Err(e) => {
+            eprintln!("Error saving screenshot: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(e) = result.error {
+        dump_on_error(args, &chip_eight, &e);
+        eprintln!("Error running program: {}", e);
+        std::process::exit(1);
+    }
+    std::process::exit(0);
+}
+
+// --tui: runs the ROM in the terminal, with its usual settings, and exits with 1 if the program failed.
+#[cfg(feature = "tui")]
+fn run_tui(args: &Args, file: &Path, rom_database: &RomDatabase) -> ! {
+    let mut chip_eight = ChipEight::new();
+    if let Err(e) = chip_eight.load_program_from_path(file) {
+        panic!("Error loading {}: {}", file.display(), e);
+    }
+    let settings = rom_settings(args, file, &mut chip_eight, rom_database);
+    if let Err(e) = settings.apply(&mut chip_eight) {
+        panic!("Error applying ROM settings: {}", e);
+    }
+
+    let terminal: TuiTerminal = match TuiTerminal::enter() {
+        Ok(terminal) => terminal,
+        Err(e) => panic!("Error setting up the terminal: {}", e),
+    };
+    let mut input = TuiInput::new(&terminal);
+    let mut display = TuiDisplay::new().palette(settings.palette());
+    let result = chip_eight.run(&mut display, &mut input, &mut TuiAudio::new());
+    // The terminal has to be back to normal before anything's printed.
+    drop(terminal);
+    write_coverage(args, &chip_eight);
+    if let Err(e) = result {
+        dump_on_error(args, &chip_eight, &e);
+        eprintln!("Error running program: {}", e);
+        std::process::exit(1);
+    }
+    std::process::exit(0);
+}
+
 fn main() {
+    let args = parse_args();
+    if let Some(file) = &args.disassemble {
+        let rom: Vec<u8> = match std::fs::read(file) {
+            Ok(rom) => rom,
+            Err(e) => panic!("Error loading {}: {}", file.display(), e),
+        };
+        let symbols: Symbols = load_symbols(args.symbols.clone(), file);
+        let coverage: Option<CoverageMap> =
+            args.coverage
+                .as_ref()
+                .map(|path| match std::fs::read_to_string(path) {
+                    Ok(report) => CoverageMap::from_report(&report),
+                    Err(e) => panic!("Error reading coverage report {}: {:?}", path.display(), e),
+                });
+        for (address, opcode, mnemonic) in disassemble_rom_with_symbols(&rom, 0x200, &symbols) {
+            if let Some(name) = symbols.name(address) {
+                println!("{}:", name);
+            }
+            // Executed instructions are marked with a *.
+            let mark: &str = match &coverage {
+                Some(coverage) if coverage.executed(address as usize) => "* ",
+                Some(_) => "  ",
+                None => "",
+            };
+            println!("{}{:03X}: {:04X}  {}", mark, address, opcode, mnemonic);
+        }
+        return;
+    }
+    if let (true, Some(file)) = (args.lint, &args.rom) {
+        lint(file);
+    }
+    if let (true, Some(file)) = (args.dump, &args.rom) {
+        let mut chip_eight = match ChipEightBuilder::new()
+            .variant(args.variant.unwrap_or_default())
+            .build()
+        {
+            Ok(chip_eight) => chip_eight,
+            Err(e) => panic!("Error creating machine: {}", e),
+        };
+        let info = match chip_eight.load_program_from_path(file) {
+            Ok(info) => info,
+            Err(e) => panic!("Error loading {}: {}", file.display(), e),
+        };
+        let addr: usize = args.addr.unwrap_or(0x200) as usize;
+        print!(
+            "{}",
+            chip_eight.dump_memory(addr..addr + args.len.unwrap_or(info.size))
+        );
+        return;
+    }
+    let rom_database = load_rom_database(args.rom_db.clone());
     let mut chip_eight_state = ChipEight::new();
 
+    if let Some(file) = &args.check {
+        check_rom(&args, file, &rom_database);
+    }
+    if let (true, Some(file), Some(out)) = (args.render, &args.rom, &args.out) {
+        render(&args, file, out, &rom_database);
+    }
+    if let (Some(frames), Some(file)) = (args.screenshot_after, &args.rom) {
+        screenshot_rom(&args, frames, file, &rom_database);
+    }
+    if let (Some(reference), Some(file)) = (&args.verify_trace, &args.rom) {
+        verify_rom_trace(&args, reference, file, &rom_database);
+    }
+    #[cfg(feature = "scripting")]
+    if let (Some(script), Some(file)) = (&args.script, &args.rom) {
+        run_rom_script(&args, script, file, &rom_database);
+    }
+    #[cfg(feature = "tui")]
+    if let (true, Some(file)) = (args.tui, &args.rom) {
+        run_tui(&args, file, &rom_database);
+    }
+    if let Some(file) = &args.find_quirks {
+        find_quirks(&args, file, &rom_database);
+        return;
+    }
+    if let (Some(frames), Some(file)) = (args.headless, &args.rom) {
+        let (mut comparison, _) = build_comparison(&args, file, &rom_database);
+        match comparison.run_headless(frames, |_| Keypad::new()) {
+            Ok(Some(frame)) => println!("Screens diverged at frame {}.", frame),
+            Ok(None) => println!(
+                "Screens still matched after {} frames.",
+                comparison.frames()
+            ),
+            Err(e) => {
+                eprintln!("Error running program: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let sdl_context = match sdl2::init() {
         Ok(sdl) => {
             println!("Created sdl context!");
@@ -29,10 +1151,27 @@ fn main() {
         Err(e) => panic!("Error creating sdl videocontext: {:?}", e),
     };
 
+    // The screen is 64x32 until a program switches to hires, or two of them side by side with a gap when comparing.
+    let (width, scale): (u32, u32) = match &args.compare {
+        Some(_) => (130, args.scale.unwrap_or(10)),
+        None => (64, args.scale.unwrap_or(20)),
+    };
+    // Pixels that aren't square make it the stretched picture's shape, as wide as it'd otherwise be. On its side, the
+    // window starts tall instead of wide.
+    let (width, height): (u32, u32) = (
+        width * scale,
+        32 * scale * args.pixel_aspect.height() / args.pixel_aspect.width(),
+    );
+    let (width, height): (u32, u32) = match args.rotation {
+        Rotation::Clockwise | Rotation::Counterclockwise => (height, width),
+        _ => (width, height),
+    };
     let window = match video_subsystem
-        .window("PotatOcho", 1280, 640)
+        .window("PotatOcho", width, height)
         .opengl()
         .position_centered()
+        .resizable()
+        .allow_highdpi()
         .build()
     {
         Ok(window) => {
@@ -42,15 +1181,14 @@ fn main() {
         Err(e) => panic!("Error creating sdl window: {:?}", e.to_string()),
     };
 
-    let canvas = match window
-        .into_canvas()
-        .index(match find_sdl_gl_driver() {
-            Some(i) => i,
-            None => panic!("Unable to find compatible OpenGL driver!"),
-        })
-        .present_vsync()
-        .build()
-    {
+    let mut canvas_builder = window.into_canvas().index(match find_sdl_gl_driver() {
+        Some(i) => i,
+        None => panic!("Unable to find compatible OpenGL driver!"),
+    });
+    if !args.no_vsync {
+        canvas_builder = canvas_builder.present_vsync();
+    }
+    let mut canvas = match canvas_builder.build() {
         Ok(canvas) => {
             println!("Created sdl canvas!");
             canvas
@@ -58,21 +1196,385 @@ fn main() {
         Err(e) => panic!("Error creating sdl canvas: {:?}", e.to_string()),
     };
 
-    let file = loop {
-        match FileDialog::new()
-            .set_title("Select a valid Chip-8 program")
-            .pick_file()
+    // Cancelling the dialog lands on the splash screen, which waits for a ROM to be opened or dropped on the window.
+    let file = match args
+        .sprites
+        .clone()
+        .or_else(|| args.rom.clone())
+        .or_else(pick_rom)
+    {
+        Some(file) => file,
+        None => {
+            let mut event_pump = match sdl_context.event_pump() {
+                Ok(pump) => pump,
+                Err(e) => panic!("Error creating sdl context event pump: {:?}", e),
+            };
+            let texture_creator = canvas.texture_creator();
+            let mut display = SdlDisplay::new(&mut canvas, &texture_creator)
+                .rotate(args.rotation)
+                .pixel_aspect(args.pixel_aspect)
+                .footer(&SPLASH_HINT);
+            if args.fill {
+                display = display.fill_window();
+            }
+            if args.no_vsync {
+                display = display.frame_limiter();
+            }
+            match splash_screen(&mut display, &mut event_pump, pick_rom) {
+                Ok(Some(file)) => file,
+                Ok(None) => return,
+                Err(e) => {
+                    eprintln!("Error showing the splash screen: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    };
+    let mut comparison: Option<Comparison> = None;
+    let keymap: Keymap;
+    let smooth: bool;
+    let palette: Palette;
+    let sound: Sound;
+    let mut replay: Option<Recording> = None;
+    if args.compare.is_some() {
+        let (built, settings) = build_comparison(&args, &file, &rom_database);
+        keymap = match settings.keymap() {
+            Ok(keymap) => keymap,
+            Err(e) => panic!("Error applying ROM settings: {}", e),
+        };
+        let title: String = format!("{} vs {}", window_title(&built.left), built.right.variant());
+        smooth = settings.smooth.unwrap_or(false);
+        palette = settings.palette();
+        sound = settings.sound();
+        if let Err(e) = canvas.window_mut().set_title(&title) {
+            println!("Error setting window title: {:?}", e);
+        }
+        comparison = Some(built);
+    } else {
+        match chip_eight_state.load_program_from_path(&file) {
+            Ok(info) => println!(
+                "Loaded program! ({} bytes, CRC-32 {:08X}, SHA-1 {})",
+                info.size,
+                info.crc32,
+                info.sha1
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>()
+            ),
+            Err(e) => panic!("Error loading {}: {}", file.display(), e),
+        };
+
+        let settings = rom_settings(&args, &file, &mut chip_eight_state, &rom_database)
+            .layered_over(load_user_config());
+        match settings.apply(&mut chip_eight_state) {
+            Ok(_) => {}
+            Err(e) => panic!("Error applying ROM settings: {}", e),
+        };
+        keymap = match settings.keymap() {
+            Ok(keymap) => keymap,
+            Err(e) => panic!("Error applying ROM settings: {}", e),
+        };
+        smooth = settings.smooth.unwrap_or(false);
+        palette = settings.palette();
+        sound = settings.sound();
+        if let Err(e) = canvas
+            .window_mut()
+            .set_title(&window_title(&chip_eight_state))
         {
-            Some(file) => break file,
-            None => println!("bruh"),
+            println!("Error setting window title: {:?}", e);
+        }
+        if args.sprites.is_some() {
+            let mut event_pump = match sdl_context.event_pump() {
+                Ok(pump) => pump,
+                Err(e) => panic!("Error creating sdl context event pump: {:?}", e),
+            };
+            let mut viewer =
+                SpriteViewer::new(args.addr.unwrap_or(0x200) as usize).palette(palette);
+            if let Err(e) = viewer.run(&mut canvas, &mut event_pump, &chip_eight_state) {
+                eprintln!("Error showing sprites: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        if let Some(path) = &args.replay {
+            let recording: Recording = load_recording(path);
+            if let Err(e) = recording.apply(&mut chip_eight_state) {
+                panic!("Error replaying {}: {}", path.display(), e);
+            }
+            replay = Some(recording);
+        }
+        chip_eight_state.set_symbols(load_symbols(args.symbols.clone(), &file));
+        if args.rewind {
+            chip_eight_state.enable_rewind(RewindSettings::default());
+        }
+        for &addr in &args.breakpoints {
+            chip_eight_state.add_breakpoint(addr);
+        }
+        for (kind, range) in &args.watchpoints {
+            match kind {
+                WatchKind::Read => chip_eight_state.watch_read(range.clone()),
+                WatchKind::Write => chip_eight_state.watch_write(range.clone()),
+            }
+        }
+    }
+    let trace: Option<TraceLog> = args.trace.as_ref().map(|path| {
+        let trace = match TraceLog::create(path) {
+            Ok(trace) => trace,
+            Err(e) => panic!("Error creating trace log {}: {}", path.display(), e),
         };
-    };
-    let program = loop {
-        match std::fs::read(file) {
-            Ok(bytes) => break bytes,
-            Err(err) => panic!("{:#?}", err),
+        let trace = match args.trace_limit {
+            Some(lines) => trace.with_limit(lines),
+            None => trace,
+        };
+        trace.attach(&mut chip_eight_state);
+        trace
+    });
+    let profiler: Option<Profiler> = args.profile.then(|| {
+        let profiler = Profiler::new();
+        profiler.attach(&mut chip_eight_state);
+        profiler
+    });
+
+    let mut audio: SdlAudio = if args.audio_backend == Some(AudioBackend::None) {
+        println!("Running without audio!");
+        SdlAudio::silent()
+    } else {
+        let audio_subsystem = match sdl_context.audio() {
+            Ok(audio) => {
+                println!("Created sdl audio!");
+                audio
+            }
+            Err(e) => panic!("Error creating sdl audiocontext: {:?}", e),
+        };
+        let audio_buffer: u16 = args.audio_buffer.unwrap_or(DEFAULT_AUDIO_BUFFER);
+        let opened: Result<SdlAudio, String> = match args.audio_backend {
+            Some(backend) => SdlAudio::open(&audio_subsystem, audio_buffer, backend),
+            None => SdlAudio::new(&audio_subsystem, audio_buffer),
         };
+        match opened {
+            Ok(audio) => {
+                println!("Initialized audio device: {}!", audio.describe());
+                audio
+            }
+            Err(e) => panic!("Error initializing audio device: {:?}", e),
+        }
+    };
+    if let Some(path) = &args.record_audio {
+        match audio.start_recording(path) {
+            Ok(_) => println!("Recording audio to {}...", path.display()),
+            Err(e) => eprintln!("Error recording audio to {}: {}", path.display(), e),
+        }
+    }
+    let mut event_pump = match sdl_context.event_pump() {
+        Ok(pump) => pump,
+        Err(e) => panic!("Error creating sdl context event pump: {:?}", e),
     };
-    chip_eight_state.load_program(program);
-    chip_eight_state.run(canvas, sdl_context);
+
+    let texture_creator = canvas.texture_creator();
+
+    if let Some(comparison) = &mut comparison {
+        let mut display = SdlDisplay::new(&mut canvas, &texture_creator)
+            .rotate(args.rotation)
+            .keymap(keymap.clone())
+            .pixel_aspect(args.pixel_aspect)
+            .audio(&audio);
+        if args.fill {
+            display = display.fill_window();
+        }
+        if args.no_vsync {
+            display = display.frame_limiter();
+        }
+        display.set_palette(palette);
+        display.set_filter(args.filter);
+        display.set_fullscreen(args.fullscreen);
+        display.set_pixel_grid(args.grid);
+        display.set_smooth_scaling(smooth);
+        display.set_sound(sound);
+        let mut input = SdlInput::new(&mut event_pump, &display).keymap(keymap);
+        if let Err(e) = run_comparison(comparison, &mut display, &mut input, &mut audio) {
+            finish_audio_recording(&mut audio);
+            eprintln!("Error running program: {}", e);
+            std::process::exit(1);
+        }
+        finish_audio_recording(&mut audio);
+        return;
+    }
+    let mut display = SdlDisplay::new(&mut canvas, &texture_creator)
+        .phosphor(args.phosphor.unwrap_or(PHOSPHOR_FRAMES))
+        .rotate(args.rotation)
+        .keymap(keymap.clone())
+        .pixel_aspect(args.pixel_aspect)
+        .audio(&audio);
+    if args.log_timing {
+        display = display.log_timing();
+    }
+    if args.fill {
+        display = display.fill_window();
+    }
+    if args.no_vsync {
+        display = display.frame_limiter();
+    }
+    if args.screenshot_respect_aspect {
+        display = display.screenshot_respect_aspect();
+    }
+    display = display.screenshots(
+        args.screenshot_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(".")),
+        args.screenshot_scale.unwrap_or(SCREENSHOT_SCALE),
+    );
+    display = display.gifs(
+        args.gif_scale.unwrap_or(GIF_SCALE),
+        args.gif_fps.unwrap_or(GIF_FPS),
+    );
+    display.set_palette(palette);
+    display.set_filter(args.filter);
+    display.set_fullscreen(args.fullscreen);
+    display.set_pixel_grid(args.grid);
+    display.set_smooth_scaling(smooth);
+    display.set_sound(sound);
+    let mut input = SdlInput::new(&mut event_pump, &display).keymap(keymap.clone());
+    let mut replay_result: Option<Result<(), RecordingError>> = None;
+    let mut result = match &args.debug_listen {
+        None if args.record.is_some() => {
+            // Seeded afresh each time, since the seed is saved with the recording anyway.
+            let seed: u64 = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos() as u64);
+            let mut recorder = Recorder::new(input, &mut chip_eight_state, seed);
+            let result = chip_eight_state.run(&mut display, &mut recorder, &mut audio);
+            let recording: Recording = recorder.finish(&chip_eight_state);
+            let path: &Path = args.record.as_deref().expect("recording");
+            match std::fs::write(path, recording.to_string()) {
+                Ok(_) => println!(
+                    "Recorded {} frames to {}!",
+                    recording.frames,
+                    path.display()
+                ),
+                Err(e) => eprintln!("Error writing recording {}: {}", path.display(), e),
+            }
+            result
+        }
+        None if replay.is_some() => {
+            let recording: Recording = replay.take().expect("replaying");
+            let mut replayer = Replayer::new(recording.clone(), input);
+            let result = chip_eight_state.run(&mut display, &mut replayer, &mut audio);
+            if replayer.finished() || matches!(result, Ok(ExitReason::RomExit)) {
+                replay_result = Some(recording.verify(&chip_eight_state));
+            } else {
+                println!("Stopped before the end of the recording.");
+            }
+            result
+        }
+        Some(addr) => {
+            let mut server = match DebugServer::bind(addr.as_str(), input) {
+                Ok(server) => server,
+                Err(e) => panic!("Error listening for a debugger on {}: {}", addr, e),
+            };
+            if let Ok(addr) = server.local_addr() {
+                println!("Listening for a debugger on {}!", addr);
+            }
+            chip_eight_state.run(&mut display, &mut server, &mut audio)
+        }
+        None => chip_eight_state.run(&mut display, &mut input, &mut audio),
+    };
+    // When the program fails, its screen stays up with the error over it until it's reset or the window is closed. After
+    // a reset it runs with just the keyboard, since any recording, replay, or debugger session has ended by then.
+    while let Err(e) = &result {
+        if e.pc().is_none() {
+            break;
+        }
+        audio.set_beeping(false);
+        dump_on_error(&args, &chip_eight_state, e);
+        match error_screen(&mut display, &mut event_pump, &chip_eight_state, e) {
+            Ok(ErrorAction::Reset) => {
+                chip_eight_state.reset();
+                let mut input = SdlInput::new(&mut event_pump, &display).keymap(keymap.clone());
+                result = chip_eight_state.run(&mut display, &mut input, &mut audio);
+            }
+            Ok(ErrorAction::Quit) => break,
+            Err(e) => {
+                eprintln!("Error showing the error screen: {}", e);
+                break;
+            }
+        }
+    }
+    finish_audio_recording(&mut audio);
+    // A theme picked with F8 sticks for next time.
+    if display.palette() != palette {
+        if let (Some(name), Some(path)) = (display.palette().theme_name(), user_config_path()) {
+            match Settings::save_theme(&path, name) {
+                Ok(_) => println!("Saved the {} theme to {}!", name, path.display()),
+                Err(e) => eprintln!("Error saving the theme to {}: {}", path.display(), e),
+            }
+        }
+    }
+    // So does the scaling picked with Shift+F9.
+    if display.smooth_scaling() != smooth {
+        if let Some(path) = user_config_path() {
+            let smooth: bool = display.smooth_scaling();
+            let name: &str = if smooth { "smooth" } else { "sharp" };
+            match Settings::save_smooth(&path, smooth) {
+                Ok(_) => println!("Saved {} scaling to {}!", name, path.display()),
+                Err(e) => eprintln!("Error saving the scaling to {}: {}", path.display(), e),
+            }
+        }
+    }
+    // And the wave picked with Shift+F8, and the volume picked with [ and ].
+    let wave: Waveform = display.sound().waveform;
+    if wave != sound.waveform {
+        if let Some(path) = user_config_path() {
+            match Settings::save_wave(&path, wave) {
+                Ok(_) => println!("Saved the {} wave to {}!", wave, path.display()),
+                Err(e) => eprintln!("Error saving the wave to {}: {}", path.display(), e),
+            }
+        }
+    }
+    let volume: u8 = display.sound().volume;
+    if volume != sound.volume {
+        if let Some(path) = user_config_path() {
+            match Settings::save_volume(&path, volume) {
+                Ok(_) => println!("Saved volume {}% to {}!", volume, path.display()),
+                Err(e) => eprintln!("Error saving the volume to {}: {}", path.display(), e),
+            }
+        }
+    }
+    let mute: bool = display.sound().muted;
+    if mute != sound.muted {
+        if let Some(path) = user_config_path() {
+            let name: &str = if mute { "muted" } else { "unmuted" };
+            match Settings::save_mute(&path, mute) {
+                Ok(_) => println!("Saved the buzzer as {} to {}!", name, path.display()),
+                Err(e) => eprintln!("Error saving muting to {}: {}", path.display(), e),
+            }
+        }
+    }
+    // The trace is most useful when something went wrong, so it has to be written out before exiting either way.
+    if let (Some(trace), Some(path)) = (&trace, &args.trace) {
+        match trace.flush() {
+            Ok(_) => println!(
+                "Traced {} instructions to {}.",
+                trace.lines(),
+                path.display()
+            ),
+            Err(e) => eprintln!("Error writing trace log {}: {}", path.display(), e),
+        }
+    }
+    if let Some(profiler) = &profiler {
+        print!("{}", profiler.report());
+    }
+    write_coverage(&args, &chip_eight_state);
+    match result {
+        Ok(reason) => println!("Stopped: {}.", reason),
+        Err(e) => {
+            eprintln!("Error running program: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(result) = &replay_result {
+        report_replay(result);
+        if result.is_err() {
+            std::process::exit(1);
+        }
+    }
 }
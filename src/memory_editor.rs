@@ -0,0 +1,277 @@
+// Editing memory by hand: a hex grid of EDITOR_COLUMNS bytes a row, with a cursor that the arrow keys, Page Up, and Page
+// Down move around, and where typing two hex digits writes a byte through the machine's bus. The SDL frontend keeps one,
+// opening it with F4 while the machine is paused, feeding it keys, and drawing it under the screen, but nothing in here
+// knows about SDL. Bytes that were edited are marked until the machine runs another frame, so it's clear what changed.
+use crate::font::FONTS_END;
+use crate::{ChipEight, FONT_ADDRESS};
+
+// Bytes a row, and rows shown at once. Page Up and Page Down move by a screenful.
+pub const EDITOR_COLUMNS: usize = 16;
+pub const EDITOR_ROWS: usize = 16;
+
+// The fonts are the interpreter's, and reset() would put them back anyway, so they can't be edited here. load_font()
+// changes the small one.
+const FONT_END: usize = FONTS_END as usize;
+
+// The keys the editor understands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    // A hex digit, 0 to F.
+    Digit(u8),
+}
+
+// How part of a line should stand out: the byte under the cursor, or one that was just edited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorMark {
+    Cursor,
+    Edited,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryEditor {
+    cursor: usize,
+    // The address of the top row shown, always a multiple of EDITOR_COLUMNS.
+    top: usize,
+    // The first digit of a byte that's halfway typed in.
+    high_nibble: Option<u8>,
+    // The addresses edited since the machine last ran, and the frame_count() when they were.
+    edited: Vec<usize>,
+    edited_frame: u64,
+    // Why the last key didn't do anything, if it didn't.
+    message: Option<String>,
+}
+
+impl MemoryEditor {
+    // An editor with the cursor on addr, which is scrolled into view.
+    pub fn new(addr: usize) -> Self {
+        let mut editor: MemoryEditor = MemoryEditor {
+            cursor: addr,
+            top: 0,
+            high_nibble: None,
+            edited: vec![],
+            edited_frame: 0,
+            message: None,
+        };
+        editor.scroll_to_cursor();
+        editor
+    }
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+    pub fn top(&self) -> usize {
+        self.top
+    }
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+    // The addresses edited since chip_eight last ran a frame.
+    pub fn edited(&self, chip_eight: &ChipEight) -> &[usize] {
+        if chip_eight.frame_count() == self.edited_frame {
+            &self.edited
+        } else {
+            &[]
+        }
+    }
+    // Moves the cursor, or types a digit into the byte under it. The second digit of a byte writes it with
+    // ChipEight::write_bus (so an attached device sees it) and moves on to the next one. Moving the cursor forgets a byte that's halfway typed in.
+    pub fn handle_key(&mut self, key: EditorKey, chip_eight: &mut ChipEight) {
+        let memory_size: usize = chip_eight.memory().len();
+        self.message = None;
+
+        let digit: u8 = match key {
+            EditorKey::Digit(digit) if digit <= 0xF => digit,
+            EditorKey::Digit(_) => return,
+            _ => {
+                let cursor: isize = self.cursor as isize;
+                let page: isize = (EDITOR_COLUMNS * EDITOR_ROWS) as isize;
+                let cursor: isize = match key {
+                    EditorKey::Up => cursor - EDITOR_COLUMNS as isize,
+                    EditorKey::Down => cursor + EDITOR_COLUMNS as isize,
+                    EditorKey::Left => cursor - 1,
+                    EditorKey::Right => cursor + 1,
+                    EditorKey::PageUp => cursor - page,
+                    _ => cursor + page,
+                };
+                self.cursor = cursor.clamp(0, memory_size as isize - 1) as usize;
+                self.high_nibble = None;
+                self.scroll_to_cursor();
+                return;
+            }
+        };
+        if self.cursor < FONT_END {
+            self.message = Some(format!(
+                "{:03X}-{:03X} holds the fonts",
+                FONT_ADDRESS,
+                FONT_END - 1
+            ));
+            return;
+        }
+        let high: u8 = match self.high_nibble.take() {
+            Some(high) => high,
+            None => {
+                self.high_nibble = Some(digit);
+                return;
+            }
+        };
+
+        chip_eight.write_bus(self.cursor, high << 4 | digit);
+        if chip_eight.frame_count() != self.edited_frame {
+            self.edited.clear();
+            self.edited_frame = chip_eight.frame_count();
+        }
+        self.edited.push(self.cursor);
+        self.cursor = (self.cursor + 1).min(memory_size - 1);
+        self.scroll_to_cursor();
+    }
+    // The rows shown, e.g. "0200: 00 E0 A2 2A ...", then a line for the keys or the last message. A byte that's halfway
+    // typed in shows its first digit and an underscore.
+    pub fn lines(&self, chip_eight: &ChipEight) -> Vec<String> {
+        let memory: &[u8] = chip_eight.memory();
+        let mut lines: Vec<String> = (self.top..memory.len())
+            .step_by(EDITOR_COLUMNS)
+            .take(EDITOR_ROWS)
+            .map(|row| {
+                let bytes: String = (row..(row + EDITOR_COLUMNS).min(memory.len()))
+                    .map(|addr| match self.high_nibble {
+                        Some(high) if addr == self.cursor => format!(" {:X}_", high),
+                        _ => format!(" {:02X}", memory[addr]),
+                    })
+                    .collect();
+                format!("{:04X}:{}", row, bytes)
+            })
+            .collect();
+        lines.push(match &self.message {
+            Some(message) => message.clone(),
+            None => "F4: CLOSE  ARROWS, PGUP, PGDN: MOVE  0-F: EDIT".to_string(),
+        });
+        lines
+    }
+    // What should stand out in lines(), as (line, first character, characters, mark).
+    pub fn marks(&self, chip_eight: &ChipEight) -> Vec<(usize, usize, usize, EditorMark)> {
+        let edited: &[usize] = self.edited(chip_eight);
+        let mark = |addr: usize, kind: EditorMark| -> Option<(usize, usize, usize, EditorMark)> {
+            if addr < self.top || addr >= self.top + EDITOR_COLUMNS * EDITOR_ROWS {
+                return None;
+            }
+            let offset: usize = addr - self.top;
+            // "0200:" and a space before each byte.
+            Some((
+                offset / EDITOR_COLUMNS,
+                6 + offset % EDITOR_COLUMNS * 3,
+                2,
+                kind,
+            ))
+        };
+        edited
+            .iter()
+            .filter_map(|&addr| mark(addr, EditorMark::Edited))
+            .chain(mark(self.cursor, EditorMark::Cursor))
+            .collect()
+    }
+    fn scroll_to_cursor(&mut self) {
+        let row: usize = self.cursor - self.cursor % EDITOR_COLUMNS;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + EDITOR_COLUMNS * EDITOR_ROWS {
+            self.top = row + EDITOR_COLUMNS - EDITOR_COLUMNS * EDITOR_ROWS;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryMappedDevice;
+    use std::sync::{Arc, Mutex};
+
+    // Swallows writes to one address, remembering them.
+    struct Port(u16, Arc<Mutex<Vec<u8>>>);
+
+    impl MemoryMappedDevice for Port {
+        fn read(&mut self, _addr: u16) -> Option<u8> {
+            None
+        }
+        fn write(&mut self, addr: u16, value: u8) -> bool {
+            if addr != self.0 {
+                return false;
+            }
+            self.1.lock().unwrap().push(value);
+            true
+        }
+    }
+
+    #[test]
+    fn two_digits_write_a_byte_and_move_on() {
+        let mut chip_eight: ChipEight = ChipEight::new();
+        let mut editor: MemoryEditor = MemoryEditor::new(0x300);
+        editor.handle_key(EditorKey::Digit(0xA), &mut chip_eight);
+        assert_eq!(chip_eight.memory()[0x300], 0);
+        // A new editor scrolls just far enough to show the cursor, on the bottom row.
+        assert_eq!(
+            editor.lines(&chip_eight)[EDITOR_ROWS - 1],
+            format!("0300: A_{}", " 00".repeat(15))
+        );
+        editor.handle_key(EditorKey::Digit(0x5), &mut chip_eight);
+        assert_eq!(chip_eight.memory()[0x300], 0xA5);
+        assert_eq!(editor.cursor(), 0x301);
+        assert_eq!(editor.edited(&chip_eight), &[0x300]);
+        assert_eq!(
+            editor.marks(&chip_eight),
+            vec![
+                (EDITOR_ROWS - 1, 6, 2, EditorMark::Edited),
+                (EDITOR_ROWS - 1, 9, 2, EditorMark::Cursor)
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_go_through_the_bus() {
+        let written: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(vec![]));
+        let mut chip_eight: ChipEight = ChipEight::new();
+        chip_eight.attach_device(Port(0x300, written.clone()));
+        let mut editor: MemoryEditor = MemoryEditor::new(0x300);
+        editor.handle_key(EditorKey::Digit(0x4), &mut chip_eight);
+        editor.handle_key(EditorKey::Digit(0x2), &mut chip_eight);
+        assert_eq!(*written.lock().unwrap(), vec![0x42]);
+        assert_eq!(chip_eight.memory()[0x300], 0);
+    }
+
+    #[test]
+    fn the_fonts_cant_be_edited() {
+        let mut chip_eight: ChipEight = ChipEight::new();
+        let fonts: Vec<u8> = chip_eight.memory()[..FONT_END].to_vec();
+        for address in [FONT_ADDRESS, crate::BIG_FONT_ADDRESS + 0x9F] {
+            let mut editor: MemoryEditor = MemoryEditor::new(address as usize);
+            editor.handle_key(EditorKey::Digit(0xF), &mut chip_eight);
+            editor.handle_key(EditorKey::Digit(0xF), &mut chip_eight);
+            assert_eq!(&chip_eight.memory()[..FONT_END], &fonts[..]);
+            assert_eq!(editor.cursor(), address as usize);
+            assert!(editor.message().unwrap().ends_with("holds the fonts"));
+        }
+    }
+
+    #[test]
+    fn the_cursor_stays_in_memory_and_in_view() {
+        let mut chip_eight: ChipEight = ChipEight::new();
+        let mut editor: MemoryEditor = MemoryEditor::new(0x005);
+        editor.handle_key(EditorKey::PageUp, &mut chip_eight);
+        assert_eq!((editor.cursor(), editor.top()), (0, 0));
+        editor.handle_key(EditorKey::Down, &mut chip_eight);
+        assert_eq!(editor.cursor(), EDITOR_COLUMNS);
+        let last: usize = chip_eight.memory().len() - 1;
+        editor = MemoryEditor::new(last);
+        editor.handle_key(EditorKey::Right, &mut chip_eight);
+        assert_eq!(editor.cursor(), last);
+        assert_eq!(
+            editor.top(),
+            last + 1 - EDITOR_COLUMNS * EDITOR_ROWS,
+            "the last page ends with the last row"
+        );
+    }
+}
@@ -0,0 +1,758 @@
+// Compiling Octo (https://github.com/JohnEarnest/Octo) source, which is what most homebrew is written in, so .8o files
+// can be loaded without exporting them from Octo first. This covers the everyday part of the language: labels, :const,
+// :alias, :org, register arithmetic, if ... then and if ... begin/else/end, loop/while/again, and bare numbers as sprite
+// data. Macros, :calc, :next, :unpack, string mode, comparisons other than == and !=, and XO-CHIP's bitplanes and audio
+// aren't supported, and say so when they turn up.
+use crate::{AsmError, Instruction};
+use std::collections::HashMap;
+
+// Where programs are loaded. Like Octo, the first instruction there is a jump to main.
+const ORIGIN: u32 = 0x200;
+
+// Octo keywords this subset doesn't handle, which would otherwise be taken for calls to labels that don't exist.
+const UNSUPPORTED: [&str; 5] = ["plane", "audio", "pitch", "{", "}"];
+
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+// Where in an instruction an operand goes.
+#[derive(Clone, Copy)]
+enum Field {
+    // The n of Dxyn and 00Dn.
+    Nibble,
+    // The kk of an xkk instruction.
+    Byte,
+    // The nnn of an nnn instruction.
+    Address,
+    // The whole word, for i := long.
+    Word,
+    // A byte of data by itself.
+    Data,
+}
+
+// A block that's still open, waiting for the jumps into or out of it to be filled in.
+enum Block {
+    // if ... begin, with the jump over the block.
+    If {
+        jump: usize,
+        line: usize,
+    },
+    // else, with the jump over the else block.
+    Else {
+        jump: usize,
+        line: usize,
+    },
+    // loop, with where again jumps back to and the jumps out of it that whiles made.
+    Loop {
+        start: u32,
+        exits: Vec<usize>,
+        line: usize,
+    },
+}
+
+// What if and while test. Key means the key in the register is held, NotKey that it isn't.
+#[derive(Clone, Copy)]
+enum Condition<'a> {
+    Equal(u8, Token<'a>),
+    NotEqual(u8, Token<'a>),
+    Key(u8),
+    NotKey(u8),
+}
+
+struct Compiler<'a> {
+    tokens: Vec<Token<'a>>,
+    next: usize,
+    rom: Vec<u8>,
+    labels: HashMap<&'a str, u32>,
+    constants: HashMap<&'a str, i64>,
+    aliases: HashMap<&'a str, u8>,
+    // Operands that name labels that hadn't been defined yet, to be filled in at the end.
+    fixups: Vec<(usize, Field, Token<'a>)>,
+    blocks: Vec<Block>,
+}
+
+// Compiles Octo source into a ROM to be loaded at 0x200. The program has to have a main label, where it starts running.
+pub fn assemble_octo(src: &str) -> Result<Vec<u8>, AsmError> {
+    let tokens: Vec<Token> = src
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| {
+            let code: &str = line.split('#').next().unwrap_or("");
+            code.split_whitespace()
+                .map(move |text| Token { text, line: i + 1 })
+        })
+        .collect();
+    let mut compiler = Compiler {
+        tokens,
+        next: 0,
+        rom: vec![],
+        labels: HashMap::new(),
+        constants: HashMap::new(),
+        aliases: HashMap::new(),
+        fixups: vec![],
+        blocks: vec![],
+    };
+
+    let main = Token {
+        text: "main",
+        line: 1,
+    };
+    let jump: usize = compiler.emit(Instruction::JumpToAddress { nnn: 0 });
+    compiler.fixups.push((jump, Field::Address, main));
+    while compiler.next < compiler.tokens.len() {
+        compiler.statement()?;
+    }
+    compiler.finish()
+}
+
+fn register(text: &str) -> Option<u8> {
+    let digit: &str = text.strip_prefix(['v', 'V'])?;
+    match digit.len() {
+        1 => u8::from_str_radix(digit, 16).ok(),
+        _ => None,
+    }
+}
+
+// Octo numbers: decimal, 0x hex, or 0b binary, optionally negative.
+fn number(text: &str) -> Option<i64> {
+    let (negative, digits): (bool, &str) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value: i64 = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+impl<'a> Compiler<'a> {
+    fn here(&self) -> u32 {
+        ORIGIN + self.rom.len() as u32
+    }
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.rom
+            .extend_from_slice(&instruction.opcode().to_be_bytes());
+        self.rom.len() - 2
+    }
+    fn error(&self, line: usize, message: impl Into<String>) -> AsmError {
+        AsmError::new(line, message)
+    }
+    fn token(&mut self) -> Result<Token<'a>, AsmError> {
+        match self.tokens.get(self.next) {
+            Some(&token) => {
+                self.next += 1;
+                Ok(token)
+            }
+            None => {
+                let line: usize = self.tokens.last().map_or(1, |token| token.line);
+                Err(self.error(line, "unexpected end of file"))
+            }
+        }
+    }
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.next).map(|token| token.text)
+    }
+    fn expect(&mut self, text: &str) -> Result<(), AsmError> {
+        let token: Token = self.token()?;
+        if token.text != text {
+            return Err(self.error(token.line, format!("expected {}, not {}", text, token.text)));
+        }
+        Ok(())
+    }
+    fn register(&mut self) -> Result<u8, AsmError> {
+        let token: Token = self.token()?;
+        self.register_of(token)
+    }
+    fn register_of(&self, token: Token) -> Result<u8, AsmError> {
+        match register(token.text).or_else(|| self.aliases.get(token.text).copied()) {
+            Some(x) => Ok(x),
+            None => Err(self.error(token.line, format!("{} isn't a register", token.text))),
+        }
+    }
+    fn is_register(&self, text: &str) -> bool {
+        register(text).is_some() || self.aliases.contains_key(text)
+    }
+    // The value of a number, constant, or label that's already been defined.
+    fn value_of(&self, text: &str) -> Option<i64> {
+        number(text)
+            .or_else(|| self.constants.get(text).copied())
+            .or_else(|| self.labels.get(text).map(|&address| address as i64))
+    }
+    // A value that has to be known already, like the one a :const is given.
+    fn constant(&mut self) -> Result<i64, AsmError> {
+        let token: Token = self.token()?;
+        self.known(token)
+    }
+    fn known(&self, token: Token) -> Result<i64, AsmError> {
+        self.value_of(token.text)
+            .ok_or_else(|| self.error(token.line, format!("{} isn't a known value", token.text)))
+    }
+    // Fills in the operand of the instruction at offset, or leaves a fixup to do it once the label it names is defined.
+    fn operand(&mut self, offset: usize, field: Field, token: Token<'a>) -> Result<(), AsmError> {
+        if self.is_register(token.text) {
+            return Err(self.error(token.line, format!("expected a number, not {}", token.text)));
+        }
+        match self.value_of(token.text) {
+            Some(value) => self.patch(offset, field, value, token),
+            None => {
+                self.fixups.push((offset, field, token));
+                Ok(())
+            }
+        }
+    }
+    fn patch(
+        &mut self,
+        offset: usize,
+        field: Field,
+        value: i64,
+        token: Token,
+    ) -> Result<(), AsmError> {
+        let (min, max): (i64, i64) = match field {
+            Field::Nibble => (0, 0xF),
+            Field::Byte | Field::Data => (-128, 0xFF),
+            Field::Address => (0, 0xFFF),
+            Field::Word => (0, 0xFFFF),
+        };
+        if value < min || value > max {
+            let message: String = match number(token.text) {
+                Some(_) => format!("{} doesn't fit here", token.text),
+                None => format!("{} ({:#X}) doesn't fit here", token.text, value),
+            };
+            return Err(self.error(token.line, message));
+        }
+        match field {
+            Field::Nibble => self.rom[offset + 1] |= value as u8,
+            Field::Byte => self.rom[offset + 1] = value as u8,
+            Field::Address => {
+                self.rom[offset] |= (value >> 8) as u8;
+                self.rom[offset + 1] = value as u8;
+            }
+            Field::Word => {
+                self.rom[offset..offset + 2].copy_from_slice(&(value as u16).to_be_bytes())
+            }
+            Field::Data => self.rom[offset] = value as u8,
+        }
+        Ok(())
+    }
+    // Emits an instruction whose operand comes from the next token.
+    fn emit_with(&mut self, instruction: Instruction, field: Field) -> Result<(), AsmError> {
+        let offset: usize = self.emit(instruction);
+        let token: Token = self.token()?;
+        self.operand(offset, field, token)
+    }
+    fn statement(&mut self) -> Result<(), AsmError> {
+        use Instruction::*;
+
+        let token: Token = self.token()?;
+        match token.text {
+            ":" => {
+                let name: Token = self.token()?;
+                if self.is_register(name.text) || number(name.text).is_some() {
+                    return Err(self.error(name.line, format!("{} can't be a label", name.text)));
+                }
+                if self.labels.insert(name.text, self.here()).is_some() {
+                    return Err(
+                        self.error(name.line, format!("label {} is defined twice", name.text))
+                    );
+                }
+            }
+            ":const" => {
+                let name: Token = self.token()?;
+                let value: i64 = self.constant()?;
+                self.constants.insert(name.text, value);
+            }
+            ":alias" => {
+                let name: Token = self.token()?;
+                let x: u8 = self.register()?;
+                self.aliases.insert(name.text, x);
+            }
+            ":org" => {
+                let address: i64 = self.constant()?;
+                if address < self.here() as i64 {
+                    return Err(self.error(
+                        token.line,
+                        format!(
+                            ":org {:#X} is before the code that's already there",
+                            address
+                        ),
+                    ));
+                }
+                if address > 0x10000 {
+                    return Err(self.error(
+                        token.line,
+                        format!(":org {:#X} is past the end of memory", address),
+                    ));
+                }
+                self.rom.resize((address as u32 - ORIGIN) as usize, 0);
+            }
+            ":byte" => {
+                self.rom.push(0);
+                let value: Token = self.token()?;
+                self.operand(self.rom.len() - 1, Field::Data, value)?;
+            }
+            ":call" => self.emit_with(CallSubroutine { nnn: 0 }, Field::Address)?,
+            "clear" => {
+                self.emit(ClearScreen);
+            }
+            "return" | ";" => {
+                self.emit(ReturnFromSubroutine);
+            }
+            "exit" => {
+                self.emit(Exit);
+            }
+            "scroll-up" => self.emit_with(ScrollUp { n: 0 }, Field::Nibble)?,
+            "scroll-down" => self.emit_with(ScrollDown { n: 0 }, Field::Nibble)?,
+            "scroll-left" => {
+                self.emit(ScrollLeft);
+            }
+            "scroll-right" => {
+                self.emit(ScrollRight);
+            }
+            "lores" => {
+                self.emit(Lores);
+            }
+            "hires" => {
+                self.emit(Hires);
+            }
+            "saveflags" | "loadflags" => {
+                let x: u8 = self.register()?;
+                self.emit(match token.text {
+                    "saveflags" => StoreFlags { x },
+                    _ => RestoreFlags { x },
+                });
+            }
+            "jump" => self.emit_with(JumpToAddress { nnn: 0 }, Field::Address)?,
+            "jump0" => self.emit_with(JumpPlusV0 { nnn: 0 }, Field::Address)?,
+            "native" => self.emit_with(JumpToMachineCode { nnn: 0 }, Field::Address)?,
+            "sprite" => {
+                let x: u8 = self.register()?;
+                let y: u8 = self.register()?;
+                self.emit_with(Draw { x, y, n: 0 }, Field::Nibble)?;
+            }
+            "bcd" => {
+                let x: u8 = self.register()?;
+                self.emit(Bcd { x });
+            }
+            "save" | "load" => {
+                let x: u8 = self.register()?;
+                let instruction: Instruction = if self.peek() == Some("-") {
+                    self.token()?;
+                    let y: u8 = self.register()?;
+                    match token.text {
+                        "save" => StoreRange { x, y },
+                        _ => RestoreRange { x, y },
+                    }
+                } else {
+                    match token.text {
+                        "save" => StoreRegisters { x },
+                        _ => RestoreRegisters { x },
+                    }
+                };
+                self.emit(instruction);
+            }
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let x: u8 = self.register()?;
+                self.emit(match token.text {
+                    "delay" => SetDelay { x },
+                    _ => SetSound { x },
+                });
+            }
+            "i" => self.i_statement()?,
+            "if" => {
+                let condition: Condition = self.condition()?;
+                let then: Token = self.token()?;
+                match then.text {
+                    // One instruction follows, and it's skipped if the condition doesn't hold.
+                    "then" => self.skip_when(condition, false)?,
+                    "begin" => {
+                        self.skip_when(condition, true)?;
+                        let jump: usize = self.emit(JumpToAddress { nnn: 0 });
+                        self.blocks.push(Block::If {
+                            jump,
+                            line: token.line,
+                        });
+                    }
+                    _ => {
+                        return Err(self.error(
+                            then.line,
+                            format!("expected then or begin, not {}", then.text),
+                        ))
+                    }
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump: over, .. }) => {
+                    let jump: usize = self.emit(JumpToAddress { nnn: 0 });
+                    self.jump_here(over, token)?;
+                    self.blocks.push(Block::Else {
+                        jump,
+                        line: token.line,
+                    });
+                }
+                _ => return Err(self.error(token.line, "else without if ... begin")),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) | Some(Block::Else { jump, .. }) => {
+                    self.jump_here(jump, token)?
+                }
+                _ => return Err(self.error(token.line, "end without if ... begin")),
+            },
+            "loop" => self.blocks.push(Block::Loop {
+                start: self.here(),
+                exits: vec![],
+                line: token.line,
+            }),
+            "while" => {
+                let condition: Condition = self.condition()?;
+                self.skip_when(condition, true)?;
+                let jump: usize = self.emit(JumpToAddress { nnn: 0 });
+                match self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find(|block| matches!(block, Block::Loop { .. }))
+                {
+                    Some(Block::Loop { exits, .. }) => exits.push(jump),
+                    _ => return Err(self.error(token.line, "while outside of a loop")),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits, .. }) => {
+                    let jump: usize = self.emit(JumpToAddress { nnn: 0 });
+                    self.patch(jump, Field::Address, start as i64, token)?;
+                    for exit in exits {
+                        self.jump_here(exit, token)?;
+                    }
+                }
+                _ => return Err(self.error(token.line, "again without loop")),
+            },
+            text if text.starts_with(':') || UNSUPPORTED.contains(&text) => {
+                return Err(self.error(token.line, format!("unsupported construct {}", text)))
+            }
+            text if self.is_register(text) => self.register_statement(token)?,
+            // Bare numbers are data, and bare labels are subroutine calls.
+            text if number(text).is_some() || self.constants.contains_key(text) => {
+                self.rom.push(0);
+                self.operand(self.rom.len() - 1, Field::Data, token)?;
+            }
+            _ => {
+                let offset: usize = self.emit(CallSubroutine { nnn: 0 });
+                self.operand(offset, Field::Address, token)?;
+            }
+        }
+        if self.here() > 0x10000 {
+            return Err(self.error(token.line, "the program doesn't fit in 64KiB of memory"));
+        }
+        Ok(())
+    }
+    fn i_statement(&mut self) -> Result<(), AsmError> {
+        use Instruction::*;
+
+        let op: Token = self.token()?;
+        match op.text {
+            ":=" => match self.peek() {
+                Some("hex") => {
+                    self.token()?;
+                    let x: u8 = self.register()?;
+                    self.emit(SetIToFont { x });
+                }
+                Some("bighex") => {
+                    self.token()?;
+                    let x: u8 = self.register()?;
+                    self.emit(SetIToBigFont { x });
+                }
+                Some("long") => {
+                    self.token()?;
+                    self.emit(SetILong);
+                    self.rom.extend_from_slice(&[0, 0]);
+                    let token: Token = self.token()?;
+                    self.operand(self.rom.len() - 2, Field::Word, token)?;
+                }
+                _ => self.emit_with(SetI { nnn: 0 }, Field::Address)?,
+            },
+            "+=" => {
+                let x: u8 = self.register()?;
+                self.emit(AddAssignI { x });
+            }
+            _ => return Err(self.error(op.line, format!("i {} isn't supported", op.text))),
+        }
+        Ok(())
+    }
+    fn register_statement(&mut self, register: Token<'a>) -> Result<(), AsmError> {
+        use Instruction::*;
+
+        let x: u8 = self.register_of(register)?;
+        let op: Token = self.token()?;
+        let rhs: Token = self.token()?;
+        let y: Result<u8, AsmError> = self.register_of(rhs);
+
+        let instruction: Instruction = match (op.text, rhs.text) {
+            (":=", "random") => return self.emit_with(Random { x, kk: 0 }, Field::Byte),
+            (":=", "delay") => GetDelay { x },
+            (":=", "key") => WaitForKey { x },
+            (":=", _) if y.is_ok() => Set { x, y: y.clone()? },
+            (":=", _) => {
+                let offset: usize = self.emit(SetData { x, kk: 0 });
+                return self.operand(offset, Field::Byte, rhs);
+            }
+            ("+=", _) if y.is_ok() => AddAssign { x, y: y.clone()? },
+            ("+=", _) => {
+                let offset: usize = self.emit(AddAssignData { x, kk: 0 });
+                return self.operand(offset, Field::Byte, rhs);
+            }
+            // Subtracting a constant is adding its negation.
+            ("-=", _) if y.is_err() => {
+                let value: i64 = self.known(rhs)?;
+                AddAssignData {
+                    x,
+                    kk: (value as u8).wrapping_neg(),
+                }
+            }
+            ("-=", _) => SubAssign { x, y: y.clone()? },
+            ("=-", _) => SubFrom { x, y: y.clone()? },
+            ("|=", _) => BitOrAssign { x, y: y.clone()? },
+            ("&=", _) => BitAndAssign { x, y: y.clone()? },
+            ("^=", _) => BitXorAssign { x, y: y.clone()? },
+            (">>=", _) => ShiftRight { x, y: y.clone()? },
+            ("<<=", _) => ShiftLeft { x, y: y.clone()? },
+            _ => {
+                return Err(self.error(
+                    op.line,
+                    format!(
+                        "unsupported construct {} {} {}",
+                        register.text, op.text, rhs.text
+                    ),
+                ))
+            }
+        };
+        self.emit(instruction);
+        Ok(())
+    }
+    fn condition(&mut self) -> Result<Condition<'a>, AsmError> {
+        let x: u8 = self.register()?;
+        let op: Token = self.token()?;
+        match op.text {
+            "key" => Ok(Condition::Key(x)),
+            "-key" => Ok(Condition::NotKey(x)),
+            "==" => Ok(Condition::Equal(x, self.token()?)),
+            "!=" => Ok(Condition::NotEqual(x, self.token()?)),
+            _ => Err(self.error(
+                op.line,
+                format!(
+                    "unsupported construct {} (conditions can only use ==, !=, key, and -key)",
+                    op.text
+                ),
+            )),
+        }
+    }
+    // Emits the instruction that skips the next one when the condition is (or isn't) true.
+    fn skip_when(&mut self, condition: Condition<'a>, holds: bool) -> Result<(), AsmError> {
+        use Instruction::*;
+
+        let (x, rhs, equal): (u8, Token, bool) = match condition {
+            Condition::Key(x) | Condition::NotKey(x) => {
+                let pressed: bool = matches!(condition, Condition::Key(_)) == holds;
+                self.emit(if pressed {
+                    SkipIfPressed { x }
+                } else {
+                    SkipIfNotPressed { x }
+                });
+                return Ok(());
+            }
+            Condition::Equal(x, rhs) => (x, rhs, holds),
+            Condition::NotEqual(x, rhs) => (x, rhs, !holds),
+        };
+        match (self.register_of(rhs).ok(), equal) {
+            (Some(y), true) => {
+                self.emit(SkipIfEquals { x, y });
+            }
+            (Some(y), false) => {
+                self.emit(SkipIfNotEquals { x, y });
+            }
+            (None, true) => {
+                let offset: usize = self.emit(SkipIfEqualsData { x, kk: 0 });
+                self.operand(offset, Field::Byte, rhs)?;
+            }
+            (None, false) => {
+                let offset: usize = self.emit(SkipIfNotEqualsData { x, kk: 0 });
+                self.operand(offset, Field::Byte, rhs)?;
+            }
+        }
+        Ok(())
+    }
+    // Points the jump at offset to the current address.
+    fn jump_here(&mut self, offset: usize, token: Token) -> Result<(), AsmError> {
+        let here: i64 = self.here() as i64;
+        self.patch(offset, Field::Address, here, token)
+    }
+    fn finish(mut self) -> Result<Vec<u8>, AsmError> {
+        if let Some(block) = self.blocks.last() {
+            let (line, message): (usize, &str) = match block {
+                Block::If { line, .. } | Block::Else { line, .. } => (*line, "begin without end"),
+                Block::Loop { line, .. } => (*line, "loop without again"),
+            };
+            return Err(self.error(line, message));
+        }
+        for (offset, field, token) in std::mem::take(&mut self.fixups) {
+            let address: u32 = match self.labels.get(token.text) {
+                Some(&address) => address,
+                None if token.text == "main" => {
+                    return Err(
+                        self.error(token.line, "there's no : main for the program to start at")
+                    )
+                }
+                None => {
+                    return Err(self.error(
+                        token.line,
+                        format!("no label or constant called {}", token.text),
+                    ))
+                }
+            };
+            self.patch(offset, field, address as i64, token)?;
+        }
+        Ok(self.rom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(src: &str) -> (usize, String) {
+        let error: AsmError = assemble_octo(src).unwrap_err();
+        (error.line, error.message)
+    }
+
+    #[test]
+    fn labels_constants_and_data() {
+        let src: &str = "
+            :const LEN 3
+            : main
+                v0 := 5
+                i := digit        # a label further down
+                sprite v0 v1 LEN
+                draw
+                jump main
+            : draw
+                v1 += 1
+                return
+            : digit
+                :byte 0xF0
+                0x90 0xF0
+        ";
+        assert_eq!(
+            assemble_octo(src),
+            Ok(vec![
+                0x12, 0x02, 0x60, 0x05, 0xA2, 0x10, 0xD0, 0x13, 0x22, 0x0C, 0x12, 0x02, 0x71, 0x01,
+                0x00, 0xEE, 0xF0, 0x90, 0xF0,
+            ])
+        );
+    }
+
+    #[test]
+    fn loops_and_ifs_jump_to_the_right_places() {
+        let src: &str = "
+            : main
+                loop
+                    v0 += 1
+                    while v0 != 10
+                    if v1 key then v2 := 1
+                    if v0 == v3 begin
+                        v4 := 2
+                    else
+                        v4 := 3
+                    end
+                again
+        ";
+        // while skips the jump out of the loop (at 0x206) while it holds, and a then skips its one instruction unless its
+        // condition does. begin's jump (at 0x20E) goes past the else's, to 0x214.
+        assert_eq!(
+            assemble_octo(src),
+            Ok(vec![
+                0x12, 0x02, 0x70, 0x01, 0x40, 0x0A, 0x12, 0x18, 0xE1, 0xA1, 0x62, 0x01, 0x50, 0x30,
+                0x12, 0x14, 0x64, 0x02, 0x12, 0x16, 0x64, 0x03, 0x12, 0x02,
+            ])
+        );
+    }
+
+    #[test]
+    fn aliases_subtraction_and_long_addresses() {
+        let src: &str = "
+            :alias counter v3
+            : main
+                counter -= 2
+                i := long 0x1234
+        ";
+        assert_eq!(
+            assemble_octo(src),
+            Ok(vec![0x12, 0x02, 0x73, 0xFE, 0xF0, 0x00, 0x12, 0x34])
+        );
+    }
+
+    #[test]
+    fn super_chip_instructions() {
+        let src: &str = "
+            : main
+                hires
+                scroll-down 4
+                scroll-left
+                scroll-right
+                saveflags v3
+                loadflags v3
+                i := bighex v2
+                lores
+        ";
+        assert_eq!(
+            assemble_octo(src),
+            Ok(vec![
+                0x12, 0x02, 0x00, 0xFF, 0x00, 0xC4, 0x00, 0xFC, 0x00, 0xFB, 0xF3, 0x75, 0xF3, 0x85,
+                0xF2, 0x30, 0x00, 0xFE,
+            ])
+        );
+    }
+
+    #[test]
+    fn errors_say_what_and_where() {
+        for (src, line, message) in [
+            (
+                "v0 := 1",
+                1,
+                "there's no : main for the program to start at",
+            ),
+            (
+                ": main\njump nowhere",
+                2,
+                "no label or constant called nowhere",
+            ),
+            (": main\n: main", 2, "label main is defined twice"),
+            (": main\n:const X Y", 2, "Y isn't a known value"),
+            (": main\nv0 := 256", 2, "256 doesn't fit here"),
+            (": main\nv0 +=", 2, "unexpected end of file"),
+            (": main\nif v0 == 1 begin\nv1 := 2", 2, "begin without end"),
+            (": main\nagain", 2, "again without loop"),
+            (
+                ": main\nv0 := key\nwhile v0 == 1",
+                3,
+                "while outside of a loop",
+            ),
+            (": main\n:calc X { 1 }", 2, "unsupported construct :calc"),
+            (
+                ": main\nif v0 > 1 then",
+                2,
+                "unsupported construct > (conditions can only use ==, !=, key, and -key)",
+            ),
+        ] {
+            assert_eq!(error(src), (line, message.to_string()), "{:?}", src);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+// On-screen messages: short confirmations of what the frontend just did (a screenshot saved, the theme changed, the
+// program paused), shown over the bottom left corner of the screen for a moment. A new message goes under the ones
+// already showing and pushes them up, and only the newest OSD_LINES are kept. This only keeps track of which messages
+// are showing when; drawing them is up to the frontend.
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// How long a message stays up, and how many can be up at once.
+pub const OSD_TIME: Duration = Duration::from_secs(2);
+pub const OSD_LINES: usize = 3;
+
+#[derive(Clone, Debug, Default)]
+pub struct Osd {
+    // Oldest first, each with when it goes away.
+    messages: VecDeque<(String, Instant)>,
+}
+
+impl Osd {
+    pub fn new() -> Self {
+        Osd::default()
+    }
+    // Shows text from now for OSD_TIME. If there are already OSD_LINES messages up, the oldest one goes.
+    pub fn show(&mut self, text: impl Into<String>, now: Instant) {
+        self.expire(now);
+        if self.messages.len() == OSD_LINES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back((text.into(), now + OSD_TIME));
+    }
+    // Forgets the messages whose time is up at now.
+    pub fn expire(&mut self, now: Instant) {
+        self.messages.retain(|(_, until)| now < *until);
+    }
+    // The messages up at now, oldest (and so highest up) first.
+    pub fn visible(&self, now: Instant) -> Vec<&str> {
+        self.messages
+            .iter()
+            .filter(|(_, until)| now < *until)
+            .map(|(text, _)| text.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_messages_go_under_the_old_ones() {
+        let start: Instant = Instant::now();
+        let mut osd: Osd = Osd::new();
+        osd.show("PAUSED", start);
+        osd.show("THEME: AMBER", start + Duration::from_millis(100));
+        assert_eq!(
+            osd.visible(start + Duration::from_millis(200)),
+            vec!["PAUSED", "THEME: AMBER"]
+        );
+    }
+
+    #[test]
+    fn only_the_newest_lines_stay_up() {
+        let start: Instant = Instant::now();
+        let mut osd: Osd = Osd::new();
+        for n in 0..OSD_LINES + 2 {
+            osd.show(format!("MESSAGE {}", n), start);
+        }
+        let expected: Vec<String> = (2..OSD_LINES + 2)
+            .map(|n| format!("MESSAGE {}", n))
+            .collect();
+        assert_eq!(osd.visible(start), expected);
+    }
+
+    #[test]
+    fn messages_go_away_after_osd_time() {
+        let start: Instant = Instant::now();
+        let later: Instant = start + OSD_TIME / 2;
+        let mut osd: Osd = Osd::new();
+        osd.show("SAVED shot.png", start);
+        osd.show("RECORDING", later);
+        assert_eq!(
+            osd.visible(start + OSD_TIME - Duration::from_millis(1))
+                .len(),
+            2
+        );
+        assert_eq!(osd.visible(start + OSD_TIME), vec!["RECORDING"]);
+        assert!(osd.visible(later + OSD_TIME).is_empty());
+        osd.expire(start + OSD_TIME);
+        assert_eq!(osd.messages.len(), 1);
+    }
+
+    #[test]
+    fn expired_messages_dont_push_new_ones_out() {
+        let start: Instant = Instant::now();
+        let mut osd: Osd = Osd::new();
+        for _ in 0..OSD_LINES {
+            osd.show("OLD", start);
+        }
+        let later: Instant = start + OSD_TIME;
+        osd.show("NEW", later);
+        assert_eq!(osd.visible(later), vec!["NEW"]);
+        assert_eq!(osd.messages.len(), 1);
+    }
+}
@@ -0,0 +1,206 @@
+// The colors the screen is drawn in. The default is the white on black most interpreters use, and NAMED_COLORS has the
+// greens and ambers of old phosphor monitors too. XO-CHIP's second bitplane will make this four colors (one for each
+// combination of planes), so everything that draws the screen takes its colors from a Palette rather than a pair of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Palette {
+    // Lit pixels, as RGB.
+    pub foreground: [u8; 3],
+    // Unlit pixels.
+    pub background: [u8; 3],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            foreground: [0xFF, 0xFF, 0xFF],
+            background: [0x00, 0x00, 0x00],
+        }
+    }
+}
+
+impl Palette {
+    // The colors as RGBA, fully opaque, e.g. for render_to_rgba.
+    pub fn foreground_rgba(&self) -> [u8; 4] {
+        let [r, g, b] = self.foreground;
+        [r, g, b, 0xFF]
+    }
+    pub fn background_rgba(&self) -> [u8; 4] {
+        let [r, g, b] = self.background;
+        [r, g, b, 0xFF]
+    }
+    // The theme in THEMES called name, if there is one.
+    pub fn theme(name: &str) -> Option<Palette> {
+        THEMES
+            .iter()
+            .find(|(theme, _)| theme.eq_ignore_ascii_case(name))
+            .map(|(_, palette)| *palette)
+    }
+    // The name of the theme with exactly these colors, if there is one.
+    pub fn theme_name(&self) -> Option<&'static str> {
+        THEMES
+            .iter()
+            .find(|(_, palette)| palette == self)
+            .map(|(name, _)| *name)
+    }
+    // The theme after this one in THEMES, wrapping around, or the first theme if these colors aren't one.
+    pub fn next_theme(&self) -> Palette {
+        let next: usize = THEMES
+            .iter()
+            .position(|(_, palette)| palette == self)
+            .map_or(0, |i| (i + 1) % THEMES.len());
+        THEMES[next].1
+    }
+}
+
+// The built-in themes, which the SDL frontend cycles through with F8 and settings files can pick with theme = "NAME".
+pub const THEMES: &[(&str, Palette)] = &[
+    (
+        "classic",
+        Palette {
+            foreground: [0xFF, 0xFF, 0xFF],
+            background: [0x00, 0x00, 0x00],
+        },
+    ),
+    (
+        "green",
+        Palette {
+            foreground: [0x33, 0xFF, 0x66],
+            background: [0x00, 0x11, 0x00],
+        },
+    ),
+    (
+        "amber",
+        Palette {
+            foreground: [0xFF, 0xB0, 0x00],
+            background: [0x1A, 0x0F, 0x00],
+        },
+    ),
+    (
+        "gameboy",
+        Palette {
+            foreground: [0x0F, 0x38, 0x0F],
+            background: [0x9B, 0xBC, 0x0F],
+        },
+    ),
+    (
+        "high-contrast",
+        Palette {
+            foreground: [0xFF, 0xFF, 0x00],
+            background: [0x00, 0x00, 0x00],
+        },
+    ),
+];
+
+// Colors that can be given by name instead of as "#RRGGBB". The dark ones are backgrounds to go with the bright ones.
+pub const NAMED_COLORS: &[(&str, [u8; 3])] = &[
+    ("white", [0xFF, 0xFF, 0xFF]),
+    ("black", [0x00, 0x00, 0x00]),
+    ("green", [0x33, 0xFF, 0x66]),
+    ("dark-green", [0x00, 0x11, 0x00]),
+    ("amber", [0xFF, 0xB0, 0x00]),
+    ("dark-amber", [0x1A, 0x0F, 0x00]),
+];
+
+// Parses "#RRGGBB", the short "#RGB" (each digit doubled, so "#3F6" is "#33FF66"), or one of NAMED_COLORS. The # is
+// optional.
+#[cfg(feature = "std")]
+pub fn parse_color(s: &str) -> Result<[u8; 3], String> {
+    if let Some((_, color)) = NAMED_COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(s))
+    {
+        return Ok(*color);
+    }
+    let hex: &str = s.strip_prefix('#').unwrap_or(s);
+
+    if ![3, 6].contains(&hex.len()) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        let names: Vec<&str> = NAMED_COLORS.iter().map(|(name, _)| *name).collect();
+        return Err(format!(
+            "'{}' isn't a color: expected \"#RRGGBB\" or \"#RGB\" (e.g. \"#33FF66\") or one of {}",
+            s,
+            names.join(", ")
+        ));
+    }
+    let digits: usize = hex.len() / 3;
+    let channel = |i: usize| -> u8 {
+        let value: u8 = u8::from_str_radix(&hex[i * digits..(i + 1) * digits], 16).unwrap_or(0);
+        if digits == 1 {
+            value * 0x11
+        } else {
+            value
+        }
+    };
+    Ok([channel(0), channel(1), channel(2)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn colors_parse_from_hex_or_names() {
+        for (input, expected) in [
+            ("#33FF66", [0x33, 0xFF, 0x66]),
+            ("33ff66", [0x33, 0xFF, 0x66]),
+            ("#1a0F00", [0x1A, 0x0F, 0x00]),
+            ("#3F6", [0x33, 0xFF, 0x66]),
+            ("fff", [0xFF, 0xFF, 0xFF]),
+            ("#000", [0x00, 0x00, 0x00]),
+            ("amber", [0xFF, 0xB0, 0x00]),
+            ("Dark-Green", [0x00, 0x11, 0x00]),
+        ] {
+            assert_eq!(parse_color(input), Ok(expected), "{}", input);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn bad_colors_are_rejected_with_the_choices() {
+        for input in [
+            "", "#", "#33FF6", "#33FF666", "#3F", "#3F66", "#33GG66", "#xyz", "33 F66", "##33FF66",
+            "purple", "#ÿÿÿ",
+        ] {
+            let e: String = parse_color(input).unwrap_err();
+            assert!(
+                e.starts_with(&format!("'{}' isn't a color: expected", input)),
+                "{}: {}",
+                input,
+                e
+            );
+            assert!(
+                e.ends_with("white, black, green, dark-green, amber, dark-amber"),
+                "{}",
+                e
+            );
+        }
+    }
+
+    #[test]
+    fn themes_cycle_and_have_names() {
+        assert_eq!(Palette::theme("GREEN"), Some(THEMES[1].1));
+        assert_eq!(Palette::theme("sepia"), None);
+        assert_eq!(Palette::default().theme_name(), Some("classic"));
+
+        let mut palette: Palette = Palette::default();
+        for (name, _) in THEMES.iter().skip(1) {
+            palette = palette.next_theme();
+            assert_eq!(palette.theme_name(), Some(*name));
+        }
+        assert_eq!(
+            palette.next_theme(),
+            Palette::default(),
+            "the last theme wraps around"
+        );
+
+        let custom: Palette = Palette {
+            foreground: [1, 2, 3],
+            background: [4, 5, 6],
+        };
+        assert_eq!(custom.theme_name(), None);
+        assert_eq!(custom.next_theme(), THEMES[0].1);
+        assert_eq!(custom.foreground_rgba(), [1, 2, 3, 0xFF]);
+        assert_eq!(custom.background_rgba(), [4, 5, 6, 0xFF]);
+    }
+}
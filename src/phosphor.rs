@@ -0,0 +1,259 @@
+// Phosphor persistence, to take the edge off the flicker Chip-8 programs are known for. They move a sprite by drawing it
+// again where it was (which erases it, since drawing is XOR) and then drawing it in its new place, so a frame that ends
+// between the two is missing the sprite. The screens of the day took a moment to fade, which hid that. Here a lit pixel
+// is at full brightness, and once it goes out it fades to the background over a few frames. This is only a way of
+// looking at the framebuffer for frontends to draw; the machine never sees it.
+use crate::{ChipEight, Palette, RenderError};
+
+// How many frames a pixel takes to fade out, unless the frontend says otherwise.
+pub const PHOSPHOR_FRAMES: u8 = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Phosphor {
+    frames: u8,
+    // How bright each pixel is, from frames (lit) down to 0 (faded out). Only the first height rows and width columns are
+    // in use.
+    levels: [[u8; 128]; 64],
+    width: usize,
+    height: usize,
+    // The machine's frame_count() when the levels were last updated, since pixels only fade while frames run.
+    frame: Option<u64>,
+}
+
+impl Phosphor {
+    // Pixels fade out over frames frames. 1 (or 0) makes them go out at once, the same as not using this at all.
+    pub fn new(frames: u8) -> Self {
+        Phosphor {
+            frames: frames.max(1),
+            levels: [[0; 128]; 64],
+            width: 0,
+            height: 0,
+            frame: None,
+        }
+    }
+    pub fn frames(&self) -> u8 {
+        self.frames
+    }
+    // How bright the pixel at (x, y) is, from 0 (the background) to frames() (the foreground).
+    pub fn level(&self, x: usize, y: usize) -> u8 {
+        if y < self.height && x < self.width {
+            self.levels[y][x]
+        } else {
+            0
+        }
+    }
+    // Catches up with the machine's screen: lit pixels go to full brightness, and unlit ones fade a step for every frame
+    // that has run since last time. A change of resolution starts afresh. Returns whether any pixel's brightness changed,
+    // i.e. whether the screen needs drawing again.
+    pub fn update(&mut self, chip_eight: &ChipEight) -> bool {
+        self.catch_up(chip_eight, false)
+    }
+    // Like update, but unlit pixels go straight out, so the screen shows exactly what's in the framebuffer. For while the
+    // machine is paused, when instructions run without frames passing.
+    pub fn settle(&mut self, chip_eight: &ChipEight) -> bool {
+        self.catch_up(chip_eight, true)
+    }
+    fn catch_up(&mut self, chip_eight: &ChipEight, settle: bool) -> bool {
+        let resolution: (usize, usize) = chip_eight.resolution();
+        let frame: u64 = chip_eight.frame_count();
+        let same_resolution: bool = resolution == (self.width, self.height);
+        let elapsed: u64 = match self.frame {
+            Some(last) if same_resolution && !settle => frame.saturating_sub(last),
+            Some(_) if same_resolution => u64::from(self.frames),
+            _ => {
+                self.levels = [[0; 128]; 64];
+                (self.width, self.height) = resolution;
+                u64::from(self.frames)
+            }
+        };
+        self.frame = Some(frame);
+
+        let mut changed: bool = false;
+        for (levels, row) in self.levels.iter_mut().zip(chip_eight.framebuffer()) {
+            for (level, &lit) in levels.iter_mut().zip(row.iter()) {
+                let new: u8 = fade(*level, lit, elapsed, self.frames);
+                changed |= new != *level;
+                *level = new;
+            }
+        }
+        changed
+    }
+    // Writes the screen to out like ChipEight::render_to_rgba, but with fading pixels somewhere between the palette's
+    // colors.
+    pub fn render_to_rgba(&self, palette: Palette, out: &mut [u8]) -> Result<(), RenderError> {
+        if out.len() != self.width * self.height * 4 {
+            return Err(RenderError::BufferSize {
+                expected: self.width * self.height * 4,
+                actual: out.len(),
+            });
+        }
+
+        let levels = self.levels[..self.height]
+            .iter()
+            .flat_map(|row| row[..self.width].iter());
+        for (color, &level) in out.chunks_exact_mut(4).zip(levels) {
+            let [r, g, b] = blend(palette, level, self.frames);
+            color.copy_from_slice(&[r, g, b, 0xFF]);
+        }
+        Ok(())
+    }
+}
+
+// A pixel's brightness after elapsed frames, out of frames: full if it's lit, and otherwise a step dimmer for every frame
+// until it's out.
+fn fade(level: u8, lit: bool, elapsed: u64, frames: u8) -> u8 {
+    if lit {
+        frames
+    } else {
+        u64::from(level).saturating_sub(elapsed) as u8
+    }
+}
+
+// The color of a pixel level out of frames bright: the foreground at frames, the background at 0, and evenly in between.
+fn blend(palette: Palette, level: u8, frames: u8) -> [u8; 3] {
+    let level: u32 = u32::from(level.min(frames));
+    let frames: u32 = u32::from(frames.max(1));
+    let mut color: [u8; 3] = [0; 3];
+    for (i, channel) in color.iter_mut().enumerate() {
+        let (fg, bg) = (
+            u32::from(palette.foreground[i]),
+            u32::from(palette.background[i]),
+        );
+        *channel = ((fg * level + bg * (frames - level) + frames / 2) / frames) as u8;
+    }
+    color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keypad;
+
+    const WHITE_ON_BLACK: Palette = Palette {
+        foreground: [0xFF; 3],
+        background: [0x00; 3],
+    };
+
+    // Lights the top left pixel, and then loops at 0x204 so that frames can pass. Running 0x202 again turns it off.
+    fn lit() -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight
+            .write_memory(0x200, &[0xA3, 0x00, 0xD0, 0x11, 0x12, 0x04])
+            .unwrap();
+        chip_eight.write_memory(0x300, &[0x80]).unwrap();
+        chip_eight.step().unwrap();
+        chip_eight.step().unwrap();
+        chip_eight
+    }
+
+    fn turn_off(chip_eight: &mut ChipEight) {
+        chip_eight.set_pc(0x202);
+        chip_eight.step().unwrap();
+    }
+
+    #[test]
+    fn unlit_pixels_fade_a_step_a_frame() {
+        for (level, lit, elapsed, expected) in [
+            (0, true, 0, 3),
+            (1, true, 5, 3),
+            (3, false, 0, 3),
+            (3, false, 1, 2),
+            (2, false, 2, 0),
+            (1, false, u64::MAX, 0),
+        ] {
+            assert_eq!(
+                fade(level, lit, elapsed, 3),
+                expected,
+                "{} {} {}",
+                level,
+                lit,
+                elapsed
+            );
+        }
+    }
+
+    #[test]
+    fn levels_blend_evenly_between_the_colors() {
+        for (level, expected) in [(0, 0x00), (1, 0x55), (2, 0xAA), (3, 0xFF), (9, 0xFF)] {
+            assert_eq!(blend(WHITE_ON_BLACK, level, 3), [expected; 3], "{}", level);
+        }
+        let palette: Palette = Palette {
+            foreground: [0, 0, 0],
+            background: [90, 30, 0],
+        };
+        assert_eq!(blend(palette, 1, 3), [60, 20, 0]);
+        assert_eq!(blend(palette, 1, 1), [0, 0, 0]);
+    }
+
+    #[test]
+    fn a_pixel_that_goes_out_fades_over_the_frames() {
+        let mut chip_eight: ChipEight = lit();
+        let mut phosphor: Phosphor = Phosphor::new(3);
+        assert!(phosphor.update(&chip_eight));
+        assert_eq!((phosphor.level(0, 0), phosphor.level(1, 0)), (3, 0));
+
+        // Until a frame passes, it stays lit.
+        turn_off(&mut chip_eight);
+        assert!(!phosphor.update(&chip_eight));
+        assert_eq!(phosphor.level(0, 0), 3);
+        let levels: [u8; 4] = ::core::array::from_fn(|_| {
+            chip_eight.frame(&Keypad::new()).unwrap();
+            phosphor.update(&chip_eight);
+            phosphor.level(0, 0)
+        });
+        assert_eq!(levels, [2, 1, 0, 0]);
+        assert!(!phosphor.update(&chip_eight));
+    }
+
+    #[test]
+    fn missed_frames_and_settling_catch_up_at_once() {
+        let mut chip_eight: ChipEight = lit();
+        let mut phosphor: Phosphor = Phosphor::new(5);
+        phosphor.update(&chip_eight);
+        turn_off(&mut chip_eight);
+        for _ in 0..3 {
+            chip_eight.frame(&Keypad::new()).unwrap();
+        }
+        assert!(phosphor.update(&chip_eight));
+        assert_eq!(phosphor.level(0, 0), 2);
+
+        let mut chip_eight: ChipEight = lit();
+        phosphor.update(&chip_eight);
+        turn_off(&mut chip_eight);
+        assert!(phosphor.settle(&chip_eight));
+        assert_eq!(phosphor.level(0, 0), 0);
+    }
+
+    #[test]
+    fn one_frame_is_the_same_as_no_phosphor() {
+        assert_eq!(Phosphor::new(0).frames(), 1);
+        let mut chip_eight: ChipEight = lit();
+        let mut phosphor: Phosphor = Phosphor::new(1);
+        phosphor.update(&chip_eight);
+        turn_off(&mut chip_eight);
+        chip_eight.frame(&Keypad::new()).unwrap();
+        phosphor.update(&chip_eight);
+        assert_eq!(phosphor.level(0, 0), 0);
+    }
+
+    #[test]
+    fn rendering_needs_a_buffer_the_size_of_the_screen() {
+        let mut chip_eight: ChipEight = lit();
+        let mut phosphor: Phosphor = Phosphor::new(3);
+        phosphor.update(&chip_eight);
+        turn_off(&mut chip_eight);
+        chip_eight.frame(&Keypad::new()).unwrap();
+        phosphor.update(&chip_eight);
+
+        let mut rgba: [u8; 64 * 32 * 4] = [0; 64 * 32 * 4];
+        phosphor.render_to_rgba(WHITE_ON_BLACK, &mut rgba).unwrap();
+        assert_eq!(rgba[..8], [0xAA, 0xAA, 0xAA, 0xFF, 0x00, 0x00, 0x00, 0xFF]);
+        assert_eq!(
+            phosphor.render_to_rgba(WHITE_ON_BLACK, &mut [0; 8]),
+            Err(RenderError::BufferSize {
+                expected: 64 * 32 * 4,
+                actual: 8
+            })
+        );
+    }
+}
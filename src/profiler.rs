@@ -0,0 +1,135 @@
+// Counting which instructions a program spends its time on. The profiler hangs off the before-step hook, so machines that
+// aren't being profiled don't pay for it.
+use crate::{decode, disassemble, ChipEight, HookAction};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// A handle to a profile. Clones share the same counts, so the application can keep one to report from once the machine
+// stops.
+#[derive(Clone)]
+pub struct Profiler {
+    inner: Arc<Mutex<Counts>>,
+}
+
+struct Counts {
+    // Executions of each address, and the opcode last seen there (programs can modify themselves).
+    by_address: Box<[u64]>,
+    opcodes: Box<[u16]>,
+    // Executions of each opcode pattern, e.g. "8xy4". Opcodes that don't decode are counted as "????".
+    by_pattern: Vec<(&'static str, u64)>,
+    total: u64,
+    // When the first instruction was counted, for working out how fast the machine ran.
+    started: Option<Instant>,
+}
+
+// How many of the hottest addresses report() lists.
+const HOTTEST: usize = 20;
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            inner: Arc::new(Mutex::new(Counts {
+                by_address: vec![0; 0x10000].into_boxed_slice(),
+                opcodes: vec![0; 0x10000].into_boxed_slice(),
+                by_pattern: vec![],
+                total: 0,
+                started: None,
+            })),
+        }
+    }
+    // Starts counting every instruction the machine executes. This replaces its before-step hook.
+    pub fn attach(&self, chip_eight: &mut ChipEight) {
+        let profiler: Profiler = self.clone();
+        chip_eight.on_before_step(move |chip_eight, opcode| {
+            profiler.lock().count(chip_eight.pc(), opcode);
+            HookAction::Continue
+        });
+    }
+    pub fn total(&self) -> u64 {
+        self.lock().total
+    }
+    // The n most executed addresses, with how many times each ran and the opcode there, busiest first.
+    pub fn hottest(&self, n: usize) -> Vec<(u16, u64, u16)> {
+        let counts = self.lock();
+        let mut hottest: Vec<(u16, u64, u16)> = counts
+            .by_address
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(addr, &count)| (addr as u16, count, counts.opcodes[addr]))
+            .collect();
+        hottest.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hottest.truncate(n);
+        hottest
+    }
+    // How many times each opcode pattern ran, most common first.
+    pub fn histogram(&self) -> Vec<(&'static str, u64)> {
+        let mut histogram: Vec<(&'static str, u64)> = self.lock().by_pattern.clone();
+        histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        histogram
+    }
+    // Instructions per second of wall clock time since the first one was counted.
+    pub fn instructions_per_second(&self) -> f64 {
+        let counts = self.lock();
+        let elapsed: Duration = counts
+            .started
+            .map_or(Duration::ZERO, |started| started.elapsed());
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+        counts.total as f64 / elapsed.as_secs_f64()
+    }
+    // A human readable summary: the total, the speed, the hottest addresses with their disassembly, and the histogram.
+    pub fn report(&self) -> String {
+        let mut report: String = String::new();
+        let _ = writeln!(
+            report,
+            "{} instructions, {:.0} per second",
+            self.total(),
+            self.instructions_per_second()
+        );
+        let _ = writeln!(report, "\nHottest addresses:");
+        for (addr, count, opcode) in self.hottest(HOTTEST) {
+            let _ = writeln!(
+                report,
+                "{:>12}  {:04X}: {:04X}  {}",
+                count,
+                addr,
+                opcode,
+                disassemble(opcode)
+            );
+        }
+        let _ = writeln!(report, "\nInstructions:");
+        for (pattern, count) in self.histogram() {
+            let _ = writeln!(report, "{:>12}  {}", count, pattern);
+        }
+        report
+    }
+    // The hook can't do anything useful about a poisoned lock, and neither can anyone reporting, so both carry on with it.
+    fn lock(&self) -> MutexGuard<'_, Counts> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Counts {
+    fn count(&mut self, pc: u16, opcode: u16) {
+        self.started.get_or_insert_with(Instant::now);
+        self.total += 1;
+        self.by_address[pc as usize] += 1;
+        self.opcodes[pc as usize] = opcode;
+
+        let pattern: &'static str =
+            decode(opcode).map_or("????", |instruction| instruction.pattern());
+        match self.by_pattern.iter_mut().find(|(p, _)| *p == pattern) {
+            Some((_, count)) => *count += 1,
+            None => self.by_pattern.push((pattern, 1)),
+        }
+    }
+}
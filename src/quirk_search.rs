@@ -0,0 +1,335 @@
+// Working out which quirks a program is sensitive to. The program runs headless once per quirk configuration, with the
+// same seed and input script every time, and the screens are compared frame by frame. Where they first part ways, the
+// quirks that tend to be set differently between the runs that differ (and the same between the runs that don't) are
+// the likely culprits.
+use crate::backend::InputEvent;
+use crate::{ChipEight, ChipEightError, ChipVariant, Quirks};
+use std::fmt;
+
+// One configuration's run.
+#[derive(Debug)]
+pub struct QuirkRun {
+    pub name: String,
+    pub quirks: Quirks,
+    // framebuffer_hash() and the PC after every frame that ran.
+    pub hashes: Vec<u64>,
+    pub pcs: Vec<u16>,
+    pub error: Option<ChipEightError>,
+}
+
+impl QuirkRun {
+    // The screen's hash after frame (counting from 1). Runs that stopped early keep their last screen.
+    pub fn hash_at(&self, frame: u32) -> Option<u64> {
+        self.hashes
+            .get(frame as usize - 1)
+            .or(self.hashes.last())
+            .copied()
+    }
+    pub fn pc_at(&self, frame: u32) -> Option<u16> {
+        self.pcs
+            .get(frame as usize - 1)
+            .or(self.pcs.last())
+            .copied()
+    }
+}
+
+// How often a quirk was set differently between two runs, among the pairs of runs whose screens differed after the
+// divergence frame and among those whose screens matched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuirkCorrelation {
+    pub name: &'static str,
+    pub diverging: usize,
+    pub matching: usize,
+}
+
+#[derive(Debug)]
+pub struct QuirkDivergence {
+    // The first frame after which not every run had the same screen, and the first pair of runs (as indices into runs)
+    // whose screens differed then.
+    pub frame: u32,
+    pub pair: (usize, usize),
+    // How many pairs of runs had different and matching screens after that frame.
+    pub diverging_pairs: usize,
+    pub matching_pairs: usize,
+    // The quirks that were set differently in more of the diverging pairs than the matching ones, most telling first.
+    pub correlations: Vec<QuirkCorrelation>,
+}
+
+#[derive(Debug)]
+pub struct QuirkSearch {
+    pub runs: Vec<QuirkRun>,
+    pub divergence: Option<QuirkDivergence>,
+}
+
+// Every combination of the named quirks on top of base, named after the quirks that are on ("none" for none of them).
+pub fn quirk_combinations(base: Quirks, names: &[&str]) -> Result<Vec<(String, Quirks)>, String> {
+    if let Some(name) = names.iter().find(|name| !Quirks::NAMES.contains(name)) {
+        return Err(format!("unknown quirk '{}'", name));
+    }
+    let combinations: Vec<(String, Quirks)> = (0..1usize << names.len())
+        .map(|bits| {
+            let mut quirks: Quirks = base;
+            let mut enabled: Vec<&str> = vec![];
+            for (i, name) in names.iter().enumerate() {
+                quirks.set(name, bits & (1 << i) != 0);
+                if bits & (1 << i) != 0 {
+                    enabled.push(name);
+                }
+            }
+            let name: String = if enabled.is_empty() {
+                "none".to_string()
+            } else {
+                enabled.join(" ")
+            };
+            (name, quirks)
+        })
+        .collect();
+    Ok(combinations)
+}
+
+// The quirks of each variant's preset.
+pub fn quirk_presets() -> Vec<(String, Quirks)> {
+    [
+        ChipVariant::Chip8,
+        ChipVariant::SuperChipLegacy,
+        ChipVariant::SuperChipModern,
+        ChipVariant::XoChip,
+    ]
+    .iter()
+    .map(|variant| (variant.to_string(), variant.quirks()))
+    .collect()
+}
+
+// Runs a copy of chip_eight, which should have the program loaded, with each configuration's quirks for up to frames
+// frames, like run_headless().
+pub fn search_quirks(
+    chip_eight: &ChipEight,
+    configs: &[(String, Quirks)],
+    frames: u32,
+    input_script: &[InputEvent],
+) -> QuirkSearch {
+    let runs: Vec<QuirkRun> = configs
+        .iter()
+        .map(|(name, quirks)| {
+            let mut machine: ChipEight = chip_eight.clone();
+            machine.set_quirks(Some(*quirks));
+            let mut hashes: Vec<u64> = vec![];
+            let mut pcs: Vec<u16> = vec![];
+            let result = machine.run_headless_with(frames, input_script, |machine| {
+                hashes.push(machine.framebuffer_hash());
+                pcs.push(machine.pc());
+            });
+            QuirkRun {
+                name: name.clone(),
+                quirks: *quirks,
+                hashes,
+                pcs,
+                error: result.error,
+            }
+        })
+        .collect();
+    let divergence: Option<QuirkDivergence> = find_divergence(&runs);
+    QuirkSearch { runs, divergence }
+}
+
+fn find_divergence(runs: &[QuirkRun]) -> Option<QuirkDivergence> {
+    let last: u32 = runs.iter().map(|run| run.hashes.len()).max()? as u32;
+    let frame: u32 = (1..=last).find(|&frame| {
+        runs.iter()
+            .any(|run| run.hash_at(frame) != runs[0].hash_at(frame))
+    })?;
+    let differ = |a: &QuirkRun, b: &QuirkRun| -> bool { a.hash_at(frame) != b.hash_at(frame) };
+
+    let mut diverging: Vec<(usize, usize)> = vec![];
+    let mut matching: Vec<(usize, usize)> = vec![];
+    for a in 0..runs.len() {
+        for b in a + 1..runs.len() {
+            if differ(&runs[a], &runs[b]) {
+                diverging.push((a, b));
+            } else {
+                matching.push((a, b));
+            }
+        }
+    }
+    let count = |pairs: &[(usize, usize)], name: &str| -> usize {
+        pairs
+            .iter()
+            .filter(|&&(a, b)| runs[a].quirks.get(name) != runs[b].quirks.get(name))
+            .count()
+    };
+
+    // Comparing the fraction of each kind of pair the quirk differs in, without dividing.
+    let score = |c: &QuirkCorrelation| -> i64 {
+        (c.diverging * matching.len().max(1)) as i64 - (c.matching * diverging.len()) as i64
+    };
+    let mut correlations: Vec<QuirkCorrelation> = Quirks::NAMES
+        .iter()
+        .map(|&name| QuirkCorrelation {
+            name,
+            diverging: count(&diverging, name),
+            matching: count(&matching, name),
+        })
+        .filter(|correlation| score(correlation) > 0)
+        .collect();
+    correlations.sort_by_key(|correlation| std::cmp::Reverse(score(correlation)));
+
+    Some(QuirkDivergence {
+        frame,
+        pair: diverging[0],
+        diverging_pairs: diverging.len(),
+        matching_pairs: matching.len(),
+        correlations,
+    })
+}
+
+// A table of the runs, with where each one's screen first differed from the first run's, then the divergence:
+// Quirks          First differs  PC     Final hash
+// none            -              -      0x1a2b3c4d5e6f7081
+// shift_uses_vy   frame 12       0x2A4  0x0123456789abcdef
+//
+// First divergence after frame 12: none (pc 0x2A2) vs shift_uses_vy (pc 0x2A4)
+// shift_uses_vy differs in 1 of 1 diverging pairs and 0 of 0 matching pairs
+impl fmt::Display for QuirkSearch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width: usize = self
+            .runs
+            .iter()
+            .map(|run| run.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Quirks".len());
+        writeln!(
+            f,
+            "{:width$}  {:13}  {:5}  Final hash",
+            "Quirks",
+            "First differs",
+            "PC",
+            width = width
+        )?;
+        for run in &self.runs {
+            let last: u32 = run.hashes.len() as u32;
+            let differs: Option<u32> = self.runs.first().and_then(|first| {
+                (1..=last.max(first.hashes.len() as u32))
+                    .find(|&frame| run.hash_at(frame) != first.hash_at(frame))
+            });
+            let (frame, pc) = match differs {
+                Some(frame) => (
+                    format!("frame {}", frame),
+                    run.pc_at(frame)
+                        .map_or("-".to_string(), |pc| format!("{:#05X}", pc)),
+                ),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            let hash: String = match (&run.error, run.hashes.last()) {
+                (Some(e), _) => format!("error: {}", e),
+                (None, Some(hash)) => format!("{:#018x}", hash),
+                (None, None) => "-".to_string(),
+            };
+            writeln!(
+                f,
+                "{:width$}  {:13}  {:5}  {}",
+                run.name,
+                frame,
+                pc,
+                hash,
+                width = width
+            )?;
+        }
+
+        let divergence: &QuirkDivergence = match &self.divergence {
+            Some(divergence) => divergence,
+            None => return writeln!(f, "\nThe screens never differed."),
+        };
+        let (a, b) = (&self.runs[divergence.pair.0], &self.runs[divergence.pair.1]);
+        let pc = |run: &QuirkRun| -> String {
+            run.pc_at(divergence.frame)
+                .map_or("-".to_string(), |pc| format!("{:#05X}", pc))
+        };
+        writeln!(
+            f,
+            "\nFirst divergence after frame {}: {} (pc {}) vs {} (pc {})",
+            divergence.frame,
+            a.name,
+            pc(a),
+            b.name,
+            pc(b)
+        )?;
+        for correlation in &divergence.correlations {
+            writeln!(
+                f,
+                "{} differs in {} of {} diverging pairs and {} of {} matching pairs",
+                correlation.name,
+                correlation.diverging,
+                divergence.diverging_pairs,
+                correlation.matching,
+                divergence.matching_pairs
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_program_that_shifts_points_at_shift_uses_vy() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        // V0 = 4, V1 = 6, SHR V0, V1, then draw the digit in V0: 3 if the shift uses VY, 2 if it doesn't.
+        chip_eight
+            .load_program_from_slice(&[
+                0x60, 0x04, 0x61, 0x06, 0x80, 0x16, 0xF0, 0x29, 0x62, 0x00, 0xD2, 0x25, 0x12, 0x0C,
+            ])
+            .unwrap();
+        let configs: Vec<(String, Quirks)> = quirk_combinations(
+            ChipVariant::Chip8.quirks(),
+            &["shift_uses_vy", "vf_reset", "jump_uses_vx"],
+        )
+        .unwrap();
+
+        let search: QuirkSearch = search_quirks(&chip_eight, &configs, 3, &[]);
+        assert_eq!(search.runs.len(), 8);
+        assert!(search.runs.iter().all(|run| run.error.is_none()));
+
+        let divergence: &QuirkDivergence = search.divergence.as_ref().unwrap();
+        assert_eq!(divergence.frame, 1);
+        assert_eq!(divergence.pair, (0, 1));
+        assert_eq!(
+            (divergence.diverging_pairs, divergence.matching_pairs),
+            (16, 12)
+        );
+        assert_eq!(
+            divergence.correlations,
+            vec![QuirkCorrelation {
+                name: "shift_uses_vy",
+                diverging: 16,
+                matching: 0,
+            }]
+        );
+        assert!(search.to_string().ends_with(
+            "\nFirst divergence after frame 1: none (pc 0x20C) vs shift_uses_vy (pc 0x20C)\n\
+             shift_uses_vy differs in 16 of 16 diverging pairs and 0 of 12 matching pairs\n"
+        ));
+    }
+
+    #[test]
+    fn a_program_that_ignores_the_quirks_never_diverges() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        chip_eight
+            .load_program_from_slice(&[0x60, 0x01, 0x12, 0x02])
+            .unwrap();
+        let configs: Vec<(String, Quirks)> =
+            quirk_combinations(ChipVariant::Chip8.quirks(), &["shift_uses_vy"]).unwrap();
+
+        let search: QuirkSearch = search_quirks(&chip_eight, &configs, 3, &[]);
+        assert!(search.divergence.is_none());
+        assert!(search
+            .to_string()
+            .ends_with("\nThe screens never differed.\n"));
+        assert_eq!(
+            quirk_combinations(Quirks::default(), &["no_such_quirk"]),
+            Err("unknown quirk 'no_such_quirk'".to_string())
+        );
+    }
+}
@@ -0,0 +1,264 @@
+// Cxkk's random number generator. The default is a xorshift, which is plenty random for games and doesn't need the standard
+// library, so the same generator runs everywhere; with std it's seeded from the OS, without it there's no entropy to be had,
+// so the seed comes from whoever makes the machine (ChipEight::with_seed). With std, any RandomSource can be plugged in
+// instead (a scripted MockRng for tests, for example).
+
+// Where Cxkk gets its random bytes from.
+pub trait RandomSource {
+    fn next_byte(&mut self) -> u8;
+    // The source's state as bytes, so save states can capture it. Sources with nothing worth saving can keep the default.
+    #[cfg(feature = "std")]
+    fn save(&self) -> Vec<u8> {
+        Vec::new()
+    }
+    // Puts back a state returned by save. Returns false if state didn't come from this kind of source.
+    #[cfg(feature = "std")]
+    fn restore(&mut self, state: &[u8]) -> bool {
+        state.is_empty()
+    }
+}
+
+// Machines can be cloned and sent between threads, so a source plugged into one has to be too. This is implemented for
+// every RandomSource that's Clone and Send.
+#[cfg(feature = "std")]
+pub trait RandomSourceClone: RandomSource + Send {
+    fn clone_box(&self) -> Box<dyn RandomSourceClone>;
+}
+
+#[cfg(feature = "std")]
+impl<T: RandomSource + Clone + Send + 'static> RandomSourceClone for T {
+    fn clone_box(&self) -> Box<dyn RandomSourceClone> {
+        Box::new(self.clone())
+    }
+}
+
+// xorshift64* (Vigna, "An experimental exploration of Marsaglia's xorshift generators, scrambled").
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Xorshift {
+    state: u64,
+}
+
+impl Xorshift {
+    pub fn new(seed: u64) -> Self {
+        // An all-zero state would only ever produce zeroes.
+        Xorshift {
+            state: if seed == 0 {
+                0x9E37_79B9_7F4A_7C15
+            } else {
+                seed
+            },
+        }
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn from_entropy() -> Self {
+        Self::new(rand::random())
+    }
+    pub fn next_byte(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+}
+
+impl RandomSource for Xorshift {
+    fn next_byte(&mut self) -> u8 {
+        Xorshift::next_byte(self)
+    }
+    #[cfg(feature = "std")]
+    fn save(&self) -> Vec<u8> {
+        self.state.to_le_bytes().to_vec()
+    }
+    #[cfg(feature = "std")]
+    fn restore(&mut self, state: &[u8]) -> bool {
+        match <[u8; 8]>::try_from(state) {
+            Ok(bytes) => {
+                // new() rather than setting the state directly, so a bogus all-zero state can't get in.
+                *self = Xorshift::new(u64::from_le_bytes(bytes));
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+// Plays back a fixed sequence of bytes, starting over once it runs out. For tests that need to know what Cxkk will roll.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockRng {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+#[cfg(feature = "std")]
+impl MockRng {
+    // An empty sequence rolls nothing but zeroes.
+    pub fn new(bytes: impl Into<Vec<u8>>) -> Self {
+        MockRng {
+            bytes: bytes.into(),
+            position: 0,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl RandomSource for MockRng {
+    fn next_byte(&mut self) -> u8 {
+        let byte: u8 = self.bytes.get(self.position).copied().unwrap_or(0);
+        self.position = (self.position + 1) % self.bytes.len().max(1);
+        byte
+    }
+    // Only the position is saved; the sequence is the test's to provide.
+    fn save(&self) -> Vec<u8> {
+        (self.position as u32).to_le_bytes().to_vec()
+    }
+    fn restore(&mut self, state: &[u8]) -> bool {
+        match <[u8; 4]>::try_from(state) {
+            Ok(bytes) => {
+                self.position = u32::from_le_bytes(bytes) as usize % self.bytes.len().max(1);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+// The generator a machine is using. Without std there's nowhere to put a custom source, so it's always the xorshift.
+pub(crate) enum Rng {
+    Xorshift(Xorshift),
+    #[cfg(feature = "std")]
+    Custom(Box<dyn RandomSourceClone>),
+}
+
+impl Rng {
+    pub(crate) fn next_byte(&mut self) -> u8 {
+        match self {
+            Rng::Xorshift(xorshift) => xorshift.next_byte(),
+            #[cfg(feature = "std")]
+            Rng::Custom(source) => source.next_byte(),
+        }
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn save(&self) -> Vec<u8> {
+        match self {
+            Rng::Xorshift(xorshift) => xorshift.save(),
+            Rng::Custom(source) => source.save(),
+        }
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn restore(&mut self, state: &[u8]) -> bool {
+        match self {
+            Rng::Xorshift(xorshift) => xorshift.restore(state),
+            Rng::Custom(source) => source.restore(state),
+        }
+    }
+}
+
+impl Clone for Rng {
+    fn clone(&self) -> Self {
+        match self {
+            Rng::Xorshift(xorshift) => Rng::Xorshift(xorshift.clone()),
+            #[cfg(feature = "std")]
+            Rng::Custom(source) => Rng::Custom(source.clone_box()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BuildError, ChipEight, ChipEightBuilder};
+
+    fn rolls(source: &mut impl RandomSource) -> [u8; 8] {
+        core::array::from_fn(|_| source.next_byte())
+    }
+
+    #[test]
+    fn same_seed_same_rolls() {
+        assert_eq!(rolls(&mut Xorshift::new(42)), rolls(&mut Xorshift::new(42)));
+        assert_ne!(rolls(&mut Xorshift::new(42)), rolls(&mut Xorshift::new(43)));
+    }
+
+    #[test]
+    fn zero_seed_still_rolls() {
+        assert_ne!(rolls(&mut Xorshift::new(0)), [0; 8]);
+    }
+
+    #[test]
+    fn with_seed_matches_builder_seed() {
+        let mut seeded: ChipEight = ChipEight::with_seed(7);
+        let mut built: ChipEight = ChipEightBuilder::new().rng_seed(7).build().unwrap();
+        let a: [u8; 8] = core::array::from_fn(|_| seeded.rng.next_byte());
+        let b: [u8; 8] = core::array::from_fn(|_| built.rng.next_byte());
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn builder_needs_a_seed_only_without_std() {
+        let built: Result<ChipEight, BuildError> = ChipEightBuilder::new().build();
+        if cfg!(feature = "std") {
+            assert!(built.is_ok());
+        } else {
+            assert_eq!(built.err(), Some(BuildError::SeedRequired));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn custom_source_survives_save_and_restore() {
+        let mut rng: Rng = Rng::Custom(Box::new(MockRng::new([1, 2, 3])));
+        rng.next_byte();
+        let saved: Vec<u8> = rng.save();
+        assert_eq!(rng.next_byte(), 2);
+        assert!(rng.restore(&saved));
+        assert_eq!(rng.next_byte(), 2);
+        assert!(!rng.restore(&[0; 3]));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn cxkk_masks_what_the_source_rolls() {
+        // Cxkk for each kk, then a jump to itself.
+        for (kk, expected) in [
+            (0xFF, [0xFF, 0xA5, 0x3C]),
+            (0x0F, [0x0F, 0x05, 0x0C]),
+            (0x00, [0; 3]),
+        ] {
+            let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+            chip_eight.set_random_source(MockRng::new([0xFF, 0xA5, 0x3C]));
+            chip_eight
+                .write_memory(0x200, &[0xC0, kk, 0xC1, kk, 0xC2, kk])
+                .unwrap();
+            for _ in 0..3 {
+                chip_eight.step().unwrap();
+            }
+            assert_eq!(chip_eight.v_registers()[..3], expected, "kk = {:#04X}", kk);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn save_states_carry_the_sources_position() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.set_random_source(MockRng::new([1, 2, 3]));
+        chip_eight
+            .write_memory(0x200, &[0xC0, 0xFF, 0x12, 0x00])
+            .unwrap();
+        chip_eight.step().unwrap();
+        let state: Vec<u8> = chip_eight.save_state();
+        chip_eight.step().unwrap();
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.v_registers()[0], 2);
+        chip_eight.load_state(&state).unwrap();
+        chip_eight.step().unwrap();
+        chip_eight.step().unwrap();
+        assert_eq!(chip_eight.v_registers()[0], 2);
+        // A state saved with the xorshift doesn't fit a MockRng.
+        let xorshift: Vec<u8> = ChipEight::with_seed(1).save_state();
+        assert_eq!(
+            chip_eight.load_state(&xorshift),
+            Err(crate::StateError::Invalid("random number generator state"))
+        );
+    }
+}
@@ -0,0 +1,566 @@
+// Recording a session's key presses so it can be played back exactly, for reproducing bugs. With the same ROM, settings,
+// and RNG seed, a machine given the same keys on the same frames does exactly the same thing, so a recording is just
+// those plus the key events, and the screen's hash at the end to check the replay against. Recordings are text:
+// potatocho-recording 1
+// rom_sha1 0f3a...
+// seed 1234567
+// variant chip8
+// quirks vf_reset=1 shift_uses_vy=0 load_store_increments_i=1 jump_uses_vx=0 clip_sprites=1 display_wait=1
+// instructions_per_frame 10
+// timing ipf
+// frames 600
+// hash 0x1a2b3c4d5e6f7081
+// 120 press 5
+// 126 release 5
+// Frames count from the start of the recording. Frames where the machine was paused in the debugger don't count, but
+// debugger commands aren't recorded, so sessions that step around in the debugger may not replay the same.
+use crate::backend::{apply_input_events, Input, InputEvent, NullAudio, NullDisplay, NullInput};
+use crate::{ChipEight, ChipEightError, ChipVariant, Keypad, Quirks, TimingMode};
+use std::fmt;
+use std::ops::ControlFlow;
+use std::str::FromStr;
+
+// The version of the format written, and the only one read.
+pub const RECORDING_VERSION: u32 = 1;
+
+const MAGIC: &str = "potatocho-recording";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    // Frames since the recording started, so 0 is just before the first frame.
+    pub frame: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recording {
+    // The SHA-1 of the ROM, so it can't be replayed against a different one.
+    pub rom_sha1: [u8; 20],
+    pub seed: u64,
+    pub variant: ChipVariant,
+    pub quirks: Quirks,
+    pub instructions_per_frame: u32,
+    pub timing_mode: TimingMode,
+    // How many frames the session ran, and framebuffer_hash() after the last of them.
+    pub frames: u64,
+    pub hash: u64,
+    // In the order they happened.
+    pub events: Vec<KeyEvent>,
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Parse { line: usize, message: String },
+    UnsupportedVersion(u32),
+    // The loaded ROM isn't the one the session was recorded with.
+    WrongRom,
+    // The program failed partway through the replay.
+    Run(ChipEightError),
+    // The replay ended up with a different screen than the session did.
+    Mismatch { expected: u64, actual: u64 },
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordingError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            RecordingError::UnsupportedVersion(version) => write!(
+                f,
+                "recording version {} isn't supported (expected {})",
+                version, RECORDING_VERSION
+            ),
+            RecordingError::WrongRom => write!(f, "the recording was made with a different ROM"),
+            RecordingError::Run(e) => write!(f, "{}", e),
+            RecordingError::Mismatch { expected, actual } => write!(
+                f,
+                "the replay ended with screen hash {:#018x} instead of {:#018x}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RecordingError::Run(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Recording {
+    pub fn parse(src: &str) -> Result<Self, RecordingError> {
+        let mut lines = src
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+        let error = |line: usize, message: String| RecordingError::Parse { line, message };
+
+        match lines.next() {
+            Some((line, header)) => match header.split_once(' ') {
+                Some((MAGIC, version)) => match version.trim().parse() {
+                    Ok(RECORDING_VERSION) => {}
+                    Ok(version) => return Err(RecordingError::UnsupportedVersion(version)),
+                    Err(_) => return Err(error(line, format!("bad version {}", version))),
+                },
+                _ => return Err(error(line, "not a PotatOcho recording".to_string())),
+            },
+            None => return Err(error(1, "not a PotatOcho recording".to_string())),
+        }
+
+        let mut rom_sha1: Option<[u8; 20]> = None;
+        let mut seed: Option<u64> = None;
+        let mut variant: Option<ChipVariant> = None;
+        let mut quirks: Option<Quirks> = None;
+        let mut instructions_per_frame: Option<u32> = None;
+        let mut timing_mode: Option<TimingMode> = None;
+        let mut frames: Option<u64> = None;
+        let mut hash: Option<u64> = None;
+        let mut events: Vec<KeyEvent> = vec![];
+
+        for (line, text) in lines {
+            let (key, value) = text.split_once(' ').unwrap_or((text, ""));
+            let value: &str = value.trim();
+            let bad = |what: &str| error(line, format!("bad {} {}", what, value));
+            match key {
+                "rom_sha1" => rom_sha1 = Some(parse_sha1(value).ok_or_else(|| bad("SHA-1"))?),
+                "seed" => seed = Some(value.parse().map_err(|_| bad("seed"))?),
+                "variant" => variant = Some(value.parse().map_err(|e| error(line, e))?),
+                "quirks" => {
+                    let mut parsed: Quirks = Quirks::default();
+                    for quirk in value.split_whitespace() {
+                        match quirk.split_once('=') {
+                            Some((name, "0")) if parsed.set(name, false) => {}
+                            Some((name, "1")) if parsed.set(name, true) => {}
+                            _ => return Err(error(line, format!("bad quirk {}", quirk))),
+                        }
+                    }
+                    quirks = Some(parsed);
+                }
+                "instructions_per_frame" => {
+                    instructions_per_frame = Some(value.parse().map_err(|_| bad("speed"))?)
+                }
+                "timing" => timing_mode = Some(value.parse().map_err(|e| error(line, e))?),
+                "frames" => frames = Some(value.parse().map_err(|_| bad("frame count"))?),
+                "hash" => {
+                    let digits: &str = value.trim_start_matches("0x");
+                    hash = Some(u64::from_str_radix(digits, 16).map_err(|_| bad("hash"))?);
+                }
+                _ => events.push(text.parse().map_err(|e| error(line, e))?),
+            }
+        }
+
+        let missing = |name: &str| error(src.lines().count(), format!("missing {}", name));
+        Ok(Recording {
+            rom_sha1: rom_sha1.ok_or_else(|| missing("rom_sha1"))?,
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            variant: variant.ok_or_else(|| missing("variant"))?,
+            quirks: quirks.ok_or_else(|| missing("quirks"))?,
+            instructions_per_frame: instructions_per_frame
+                .ok_or_else(|| missing("instructions_per_frame"))?,
+            timing_mode: timing_mode.ok_or_else(|| missing("timing"))?,
+            frames: frames.ok_or_else(|| missing("frames"))?,
+            hash: hash.ok_or_else(|| missing("hash"))?,
+            events,
+        })
+    }
+    // Sets chip_eight up the way the session was: the variant, quirks, speed, and RNG seed. The ROM should already be
+    // loaded, and has to be the one the session was recorded with.
+    pub fn apply(&self, chip_eight: &mut ChipEight) -> Result<(), RecordingError> {
+        match chip_eight.rom_info() {
+            Some(info) if info.sha1 == self.rom_sha1 => {}
+            _ => return Err(RecordingError::WrongRom),
+        }
+        chip_eight.set_variant(self.variant);
+        chip_eight.set_quirks(Some(self.quirks));
+        chip_eight.set_instructions_per_frame(self.instructions_per_frame);
+        chip_eight.set_timing_mode(self.timing_mode);
+        chip_eight.seed_rng(self.seed);
+        Ok(())
+    }
+    // Whether chip_eight's screen is the one the session ended with.
+    pub fn verify(&self, chip_eight: &ChipEight) -> Result<(), RecordingError> {
+        let actual: u64 = chip_eight.framebuffer_hash();
+        if actual != self.hash {
+            return Err(RecordingError::Mismatch {
+                expected: self.hash,
+                actual,
+            });
+        }
+        Ok(())
+    }
+    // Sets chip_eight up with apply(), plays the session back without a display, and checks the screen it ends with.
+    pub fn replay_headless(&self, chip_eight: &mut ChipEight) -> Result<(), RecordingError> {
+        self.apply(chip_eight)?;
+        chip_eight
+            .run(
+                &mut NullDisplay,
+                &mut Replayer::new(self.clone(), NullInput::new()),
+                &mut NullAudio,
+            )
+            .map_err(RecordingError::Run)?;
+        self.verify(chip_eight)
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", MAGIC, RECORDING_VERSION)?;
+        writeln!(
+            f,
+            "rom_sha1 {}",
+            self.rom_sha1
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        )?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "variant {}", self.variant.name())?;
+        let quirks: Vec<String> = Quirks::NAMES
+            .iter()
+            .map(|name| format!("{}={}", name, (self.quirks.get(name) == Some(true)) as u8))
+            .collect();
+        writeln!(f, "quirks {}", quirks.join(" "))?;
+        writeln!(f, "instructions_per_frame {}", self.instructions_per_frame)?;
+        writeln!(f, "timing {}", self.timing_mode)?;
+        writeln!(f, "frames {}", self.frames)?;
+        writeln!(f, "hash {:#018x}", self.hash)?;
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+// A key event as a line of a recording (or an input script): the frame, press or release, and the key in hex, e.g.
+// "120 press 5".
+impl FromStr for KeyEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let event = || -> Option<KeyEvent> {
+            let mut words = s.split_whitespace();
+            let (frame, action, key) = (words.next()?, words.next()?, words.next()?);
+            if words.next().is_some() {
+                return None;
+            }
+            let key: u8 = u8::from_str_radix(key, 16).ok().filter(|&key| key < 16)?;
+            Some(KeyEvent {
+                frame: frame.parse().ok()?,
+                key,
+                pressed: match action {
+                    "press" => true,
+                    "release" => false,
+                    _ => return None,
+                },
+            })
+        };
+        event().ok_or_else(|| format!("expected a key event, not {}", s))
+    }
+}
+
+impl fmt::Display for KeyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let action: &str = if self.pressed { "press" } else { "release" };
+        write!(f, "{} {} {:X}", self.frame, action, self.key)
+    }
+}
+
+fn parse_sha1(hex: &str) -> Option<[u8; 20]> {
+    let mut sha1: [u8; 20] = [0; 20];
+    if hex.len() != 40 {
+        return None;
+    }
+    for (i, byte) in sha1.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(sha1)
+}
+
+// An input backend that records the key presses and releases another one makes.
+pub struct Recorder<I> {
+    input: I,
+    recording: Recording,
+    // chip_eight.frame_count() when the recording started.
+    start: u64,
+}
+
+impl<I: Input> Recorder<I> {
+    // Starts recording input's key events on chip_eight, noting down how it's set up and reseeding its RNG with seed.
+    // The program should already be loaded and configured.
+    pub fn new(input: I, chip_eight: &mut ChipEight, seed: u64) -> Self {
+        chip_eight.seed_rng(seed);
+        Recorder {
+            input,
+            recording: Recording {
+                rom_sha1: chip_eight.rom_info().map_or([0; 20], |info| info.sha1),
+                seed,
+                variant: chip_eight.variant(),
+                quirks: chip_eight.quirks(),
+                instructions_per_frame: chip_eight.instructions_per_frame(),
+                timing_mode: chip_eight.timing_mode(),
+                frames: 0,
+                hash: 0,
+                events: vec![],
+            },
+            start: chip_eight.frame_count(),
+        }
+    }
+    // The session up to now, ending with chip_eight's screen.
+    pub fn finish(mut self, chip_eight: &ChipEight) -> Recording {
+        self.recording.frames = chip_eight.frame_count() - self.start;
+        self.recording.hash = chip_eight.framebuffer_hash();
+        self.recording
+    }
+}
+
+impl<I: Input> Input for Recorder<I> {
+    fn poll(&mut self, chip_eight: &mut ChipEight) -> ControlFlow<()> {
+        let (held_before, presses_before) = chip_eight.keypad().to_bits();
+        let flow: ControlFlow<()> = self.input.poll(chip_eight);
+        let (held, presses) = chip_eight.keypad().to_bits();
+        let frame: u64 = chip_eight.frame_count() - self.start;
+
+        // The keypad only shows where the keys ended up, but a new press also leaves a bit in presses, which catches keys
+        // that were pressed and released (or released and pressed again) within the poll.
+        for key in 0..16u8 {
+            let bit: u16 = 1 << key;
+            let was_held: bool = held_before & bit != 0;
+            let is_held: bool = held & bit != 0;
+            let pressed: bool = presses & !presses_before & bit != 0;
+            let changes: &[bool] = match (was_held, is_held, pressed) {
+                (false, true, _) => &[true],
+                (true, false, _) => &[false],
+                (false, false, true) => &[true, false],
+                (true, true, true) => &[false, true],
+                _ => &[],
+            };
+            self.recording
+                .events
+                .extend(changes.iter().map(|&pressed| KeyEvent {
+                    frame,
+                    key,
+                    pressed,
+                }));
+        }
+        flow
+    }
+}
+
+// An input backend that plays a recording back, then stops the machine once it's run as many frames as the session
+// did. Key events from input are ignored, but it can still stop the machine early (by closing the window, say).
+pub struct Replayer<I> {
+    input: I,
+    recording: Recording,
+    // The next event to apply.
+    next: usize,
+    // chip_eight.frame_count() at the first poll.
+    start: Option<u64>,
+    finished: bool,
+}
+
+impl<I: Input> Replayer<I> {
+    pub fn new(recording: Recording, input: I) -> Self {
+        Replayer {
+            input,
+            recording,
+            next: 0,
+            start: None,
+            finished: false,
+        }
+    }
+    // Whether the whole session has been played back, rather than the machine being stopped early.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl<I: Input> Input for Replayer<I> {
+    fn poll(&mut self, chip_eight: &mut ChipEight) -> ControlFlow<()> {
+        let keys: Keypad = *chip_eight.keypad();
+        if self.input.poll(chip_eight).is_break() {
+            return ControlFlow::Break(());
+        }
+        *chip_eight.keypad_mut() = keys;
+
+        let frame: u64 =
+            chip_eight.frame_count() - *self.start.get_or_insert(chip_eight.frame_count());
+        if frame >= self.recording.frames {
+            self.finished = true;
+            return ControlFlow::Break(());
+        }
+        let events: &[KeyEvent] = &self.recording.events[self.next..];
+        let due: usize = events
+            .iter()
+            .take_while(|event| event.frame <= frame)
+            .count();
+        self.next += due;
+        apply_input_events(
+            chip_eight,
+            events[..due].iter().map(|event| match event.pressed {
+                true => InputEvent::KeyPressed(event.key),
+                false => InputEvent::KeyReleased(event.key),
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{NullAudio, NullDisplay};
+
+    // Each key press draws a random digit: waits for a key, picks a digit from the RNG, and draws it over the last one.
+    const ROM: [u8; 12] = [
+        0xF0, 0x0A, 0xC1, 0x0F, 0xF1, 0x29, 0x00, 0xE0, 0xD2, 0x25, 0x12, 0x00,
+    ];
+
+    // Plays (frame, event) pairs as if someone were pressing keys, then quits after the given number of frames.
+    struct Scripted {
+        frame: u64,
+        frames: u64,
+        script: Vec<(u64, InputEvent)>,
+    }
+
+    impl Input for Scripted {
+        fn poll(&mut self, chip_eight: &mut ChipEight) -> ControlFlow<()> {
+            if self.frame == self.frames {
+                return ControlFlow::Break(());
+            }
+            let frame: u64 = self.frame;
+            self.frame += 1;
+            let due = self
+                .script
+                .iter()
+                .filter(|(at, _)| *at == frame)
+                .map(|(_, event)| *event);
+            apply_input_events(chip_eight, due)
+        }
+    }
+
+    fn machine() -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.load_program_from_slice(&ROM).unwrap();
+        chip_eight
+    }
+
+    fn recorded() -> Recording {
+        let mut chip_eight: ChipEight = machine();
+        let script: Vec<(u64, InputEvent)> = vec![
+            (3, InputEvent::KeyPressed(0x5)),
+            (5, InputEvent::KeyReleased(0x5)),
+            (9, InputEvent::KeyPressed(0xA)),
+            (9, InputEvent::KeyReleased(0xA)),
+            (14, InputEvent::KeyPressed(0x1)),
+            (16, InputEvent::KeyReleased(0x1)),
+        ];
+        let mut recorder: Recorder<Scripted> = Recorder::new(
+            Scripted {
+                frame: 0,
+                frames: 20,
+                script,
+            },
+            &mut chip_eight,
+            1234,
+        );
+        chip_eight
+            .run(&mut NullDisplay, &mut recorder, &mut NullAudio)
+            .unwrap();
+        recorder.finish(&chip_eight)
+    }
+
+    #[test]
+    fn a_recording_replays_to_the_same_screen() {
+        let recording: Recording = recorded();
+        assert_eq!(recording.frames, 20);
+        assert_eq!(
+            recording.events,
+            [
+                KeyEvent {
+                    frame: 3,
+                    key: 0x5,
+                    pressed: true
+                },
+                KeyEvent {
+                    frame: 5,
+                    key: 0x5,
+                    pressed: false
+                },
+                KeyEvent {
+                    frame: 9,
+                    key: 0xA,
+                    pressed: true
+                },
+                KeyEvent {
+                    frame: 9,
+                    key: 0xA,
+                    pressed: false
+                },
+                KeyEvent {
+                    frame: 14,
+                    key: 0x1,
+                    pressed: true
+                },
+                KeyEvent {
+                    frame: 16,
+                    key: 0x1,
+                    pressed: false
+                },
+            ]
+        );
+
+        let text: String = recording.to_string();
+        let parsed: Recording = Recording::parse(&text).unwrap();
+        assert_eq!(parsed, recording);
+        parsed.replay_headless(&mut machine()).unwrap();
+    }
+
+    #[test]
+    fn a_different_seed_is_caught() {
+        let text: String = recorded().to_string().replace("seed 1234", "seed 4321");
+        let recording: Recording = Recording::parse(&text).unwrap();
+        assert!(matches!(
+            recording.replay_headless(&mut machine()),
+            Err(RecordingError::Mismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn a_different_rom_is_refused() {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.load_program_from_slice(&ROM[..10]).unwrap();
+        assert!(matches!(
+            recorded().replay_headless(&mut chip_eight),
+            Err(RecordingError::WrongRom)
+        ));
+    }
+
+    #[test]
+    fn bad_recordings_say_whats_wrong() {
+        let text: String = recorded().to_string();
+        for (bad, expected) in [
+            (
+                text.replacen("recording 1", "recording 2", 1),
+                "recording version 2 isn't supported (expected 1)",
+            ),
+            (
+                text.replacen("potatocho-recording", "potatoes", 1),
+                "line 1: not a PotatOcho recording",
+            ),
+            (text.replacen("seed 1234\n", "", 1), "line 14: missing seed"),
+            (
+                text.replacen("3 press 5", "3 prod 5", 1),
+                "line 10: expected a key event, not 3 prod 5",
+            ),
+            (
+                text.replacen("9 press A", "9 press 10", 1),
+                "line 12: expected a key event, not 9 press 10",
+            ),
+        ] {
+            assert_eq!(Recording::parse(&bad).unwrap_err().to_string(), expected);
+        }
+    }
+}
@@ -0,0 +1,313 @@
+// Turning the screen into packed pixels, for frontends (pixels, minifb, a wasm canvas) that want an RGBA buffer rather
+// than a grid of bools, and into text, for tests that want to show what they expect on screen.
+use crate::ChipEight;
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderError {
+    // The output buffer has to hold exactly 4 bytes for every pixel at the current resolution.
+    BufferSize { expected: usize, actual: usize },
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::BufferSize { expected, actual } => write!(
+                f,
+                "pixel buffer is {} bytes, but the screen needs {}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RenderError {}
+
+// What's wrong with the text given to screen_from_string. Lines are numbered from 1, not counting blank lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScreenTextError {
+    // A row had more than 128 pixels in it.
+    TooWide { line: usize, width: usize },
+    // There were more than 64 rows.
+    TooTall { height: usize },
+    // A pixel was something other than # or .
+    UnknownPixel { line: usize, pixel: char },
+}
+
+impl fmt::Display for ScreenTextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScreenTextError::TooWide { line, width } => {
+                write!(f, "row {} is {} pixels wide, the most is 128", line, width)
+            }
+            ScreenTextError::TooTall { height } => {
+                write!(f, "there are {} rows, the most is 64", height)
+            }
+            ScreenTextError::UnknownPixel { line, pixel } => {
+                write!(f, "row {} has {:?} in it, pixels are # or .", line, pixel)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ScreenTextError {}
+
+// The inverse of screen_to_string, for writing out the screen a test expects and comparing it with framebuffer(). Leading
+// and trailing whitespace and blank lines are ignored, so the text can be indented to line up in the test. Rows shorter
+// than 128 pixels are filled out with unlit pixels.
+#[cfg(feature = "std")]
+pub fn screen_from_string(text: &str) -> Result<Vec<[bool; 128]>, ScreenTextError> {
+    let mut screen: Vec<[bool; 128]> = vec![];
+
+    for (i, line) in text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .enumerate()
+    {
+        let width: usize = line.chars().count();
+        if width > 128 {
+            return Err(ScreenTextError::TooWide { line: i + 1, width });
+        }
+        let mut row: [bool; 128] = [false; 128];
+        for (pixel, c) in row.iter_mut().zip(line.chars()) {
+            *pixel = match c {
+                '#' => true,
+                '.' => false,
+                _ => {
+                    return Err(ScreenTextError::UnknownPixel {
+                        line: i + 1,
+                        pixel: c,
+                    })
+                }
+            };
+        }
+        screen.push(row);
+    }
+    if screen.len() > 64 {
+        return Err(ScreenTextError::TooTall {
+            height: screen.len(),
+        });
+    }
+    Ok(screen)
+}
+
+impl ChipEight {
+    // Writes the screen to out as RGBA, 4 bytes per pixel, row by row from the top left. out has to be exactly
+    // width * height * 4 bytes for the current resolution(), so it needs resizing when the program switches to hires.
+    pub fn render_to_rgba(
+        &self,
+        fg: [u8; 4],
+        bg: [u8; 4],
+        out: &mut [u8],
+    ) -> Result<(), RenderError> {
+        let (width, height) = self.resolution();
+        if out.len() != width * height * 4 {
+            return Err(RenderError::BufferSize {
+                expected: width * height * 4,
+                actual: out.len(),
+            });
+        }
+
+        let pixels = self
+            .framebuffer()
+            .iter()
+            .flat_map(|row| row[..width].iter());
+        for (color, &lit) in out.chunks_exact_mut(4).zip(pixels) {
+            color.copy_from_slice(if lit { &fg } else { &bg });
+        }
+        Ok(())
+    }
+    // Like render_to_rgba, but allocates a buffer of the right size.
+    #[cfg(feature = "std")]
+    pub fn render_to_rgba_vec(&self, fg: [u8; 4], bg: [u8; 4]) -> Vec<u8> {
+        let (width, height) = self.resolution();
+        let mut out: Vec<u8> = vec![0; width * height * 4];
+        // The buffer is the right size, so this can't fail.
+        let _ = self.render_to_rgba(fg, bg, &mut out);
+        out
+    }
+    // The screen as text, a line per row with # for lit pixels and . for unlit ones, e.g. to assert on in a test. Failed
+    // asserts show exactly which pixels differ, which a hash can't.
+    #[cfg(feature = "std")]
+    pub fn screen_to_string(&self) -> String {
+        self.screen_to_string_with('#', '.')
+    }
+    // Like screen_to_string, with other characters for lit and unlit pixels (say '█' and ' ' for printing to a terminal).
+    #[cfg(feature = "std")]
+    pub fn screen_to_string_with(&self, lit: char, unlit: char) -> String {
+        let (width, _) = self.resolution();
+        let mut text: String = String::new();
+
+        for row in self.framebuffer() {
+            text.extend(
+                row[..width]
+                    .iter()
+                    .map(|&pixel| if pixel { lit } else { unlit }),
+            );
+            text.push('\n');
+        }
+        text
+    }
+    // A 64-bit FNV-1a hash of the screen, for checking a test ROM drew what it should without keeping a copy of the screen
+    // around. It covers the height, then each row as wide as the current resolution, packed 8 pixels to a byte with the
+    // leftmost pixel in the high bit, so the same screen always hashes the same on every platform and in every version.
+    pub fn framebuffer_hash(&self) -> u64 {
+        let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+        let mut feed = |byte: u8| {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+        };
+
+        let (width, height) = self.resolution();
+        feed(height as u8);
+        for row in self.framebuffer() {
+            for pixels in row[..width].chunks(8) {
+                feed(pixels.iter().fold(0, |byte, &lit| (byte << 1) | lit as u8));
+            }
+        }
+        hash
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{ChipVariant, ScreenMode};
+
+    // A machine of variant that's drawn each sprite in turn at (x, y).
+    fn drawn(variant: ChipVariant, sprites: &[&[u8]], x: u8, y: u8) -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight.set_variant(variant);
+        chip_eight.set_v(0, x);
+        chip_eight.set_v(1, y);
+        for sprite in sprites {
+            chip_eight.write_memory(0x300, sprite).unwrap();
+            chip_eight
+                .write_memory(0x200, &[0xA3, 0x00, 0xD0, 0x10 | sprite.len() as u8])
+                .unwrap();
+            chip_eight.set_pc(0x200);
+            chip_eight.step().unwrap();
+            chip_eight.step().unwrap();
+        }
+        chip_eight
+    }
+
+    // A blank lores screen as text, with the given rows replaced.
+    fn lores_text(rows: &[(usize, &str)]) -> String {
+        let mut lines: Vec<String> = vec![".".repeat(64); 32];
+        for &(y, row) in rows {
+            lines[y] = row.to_string();
+        }
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    #[test]
+    fn dxyn_wraps_round_both_edges() {
+        let chip_eight: ChipEight = drawn(ChipVariant::XoChip, &[&[0xFF, 0x81]], 60, 31);
+        let dots: String = ".".repeat(56);
+        assert_eq!(
+            chip_eight.screen_to_string(),
+            lores_text(&[
+                (0, &format!("...#{}#...", dots)),
+                (31, &format!("####{}####", dots)),
+            ])
+        );
+    }
+
+    #[test]
+    fn dxyn_collisions_turn_pixels_off() {
+        let chip_eight: ChipEight = drawn(ChipVariant::Chip8, &[&[0xF0, 0x81], &[0x3C]], 0, 0);
+        assert_eq!(
+            chip_eight.screen_to_string(),
+            lores_text(&[
+                (0, &format!("##..##..{}", ".".repeat(56))),
+                (1, &format!("#......#{}", ".".repeat(56))),
+            ])
+        );
+        assert_eq!(chip_eight.v_registers()[0xF], 1);
+    }
+
+    #[test]
+    fn text_round_trips_at_the_current_resolution() {
+        let mut chip_eight: ChipEight = drawn(ChipVariant::SuperChipModern, &[&[0xA5, 0x5A]], 3, 1);
+        let text: String = chip_eight.screen_to_string();
+        assert_eq!(
+            (text.lines().count(), text.lines().next().unwrap().len()),
+            (32, 64)
+        );
+        assert_eq!(screen_from_string(&text).unwrap(), chip_eight.framebuffer());
+
+        chip_eight.set_resolution(ScreenMode::SuperHires);
+        let text: String = chip_eight.screen_to_string();
+        assert_eq!(
+            (text.lines().count(), text.lines().next().unwrap().len()),
+            (64, 128)
+        );
+        assert_eq!(screen_from_string(&text).unwrap(), chip_eight.framebuffer());
+    }
+
+    #[test]
+    fn other_characters_can_be_used() {
+        let chip_eight: ChipEight = drawn(ChipVariant::Chip8, &[&[0xC0]], 0, 0);
+        assert!(chip_eight
+            .screen_to_string_with('█', ' ')
+            .starts_with("██ "));
+    }
+
+    #[test]
+    fn screen_text_is_indented_and_filled_out() {
+        let screen: Vec<[bool; 128]> = screen_from_string(
+            "
+            #.#
+
+            .#
+            ",
+        )
+        .unwrap();
+        assert_eq!(screen.len(), 2);
+        assert_eq!(screen[0][..4], [true, false, true, false]);
+        assert_eq!(screen[1][..3], [false, true, false]);
+    }
+
+    #[test]
+    fn bad_screen_text_is_rejected() {
+        for (text, error) in [
+            (
+                &"#".repeat(129),
+                ScreenTextError::TooWide {
+                    line: 1,
+                    width: 129,
+                },
+            ),
+            (&"#\n".repeat(65), ScreenTextError::TooTall { height: 65 }),
+            (
+                &"#\n\n#x".to_string(),
+                ScreenTextError::UnknownPixel {
+                    line: 2,
+                    pixel: 'x',
+                },
+            ),
+        ] {
+            assert_eq!(screen_from_string(text), Err(error));
+        }
+    }
+
+    #[test]
+    fn rgba_needs_a_buffer_the_size_of_the_screen() {
+        let chip_eight: ChipEight = drawn(ChipVariant::Chip8, &[&[0x40]], 0, 0);
+        let (on, off) = ([1, 2, 3, 4], [0; 4]);
+        let rgba: Vec<u8> = chip_eight.render_to_rgba_vec(on, off);
+        assert_eq!(rgba.len(), 64 * 32 * 4);
+        assert_eq!(rgba[..12], [0, 0, 0, 0, 1, 2, 3, 4, 0, 0, 0, 0]);
+        assert_eq!(
+            chip_eight.render_to_rgba(on, off, &mut [0; 16]),
+            Err(RenderError::BufferSize {
+                expected: 64 * 32 * 4,
+                actual: 16
+            })
+        );
+    }
+}
@@ -0,0 +1,193 @@
+// Stepping backwards. While rewinding is enabled, the machine snapshots itself every so many instructions and logs
+// everything from outside that affects execution (timer ticks and the keypad). Stepping back restores the last snapshot
+// from before the previous instruction and replays the log up to it, which lands in exactly the same state as before.
+use crate::{ChipEight, ChipEightError, Keypad};
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RewindSettings {
+    // How many snapshots to keep. How far back you can step is about this times interval instructions.
+    pub snapshots: usize,
+    // Instructions between snapshots while the machine is running. Stepping back replays up to this many instructions.
+    pub interval: u32,
+    // Instructions between snapshots while single-stepping.
+    pub stepping_interval: u32,
+}
+
+impl Default for RewindSettings {
+    fn default() -> Self {
+        RewindSettings {
+            snapshots: 64,
+            interval: 256,
+            stepping_interval: 1,
+        }
+    }
+}
+
+// Something from outside the machine that replaying has to repeat.
+#[derive(Clone, Copy, Debug)]
+enum Event {
+    TickTimers,
+    Keys(Keypad),
+}
+
+struct History {
+    settings: RewindSettings,
+    // How many instructions have executed since recording started.
+    executed: u64,
+    // Machines as they were before the instruction with that number executed, oldest first.
+    snapshots: VecDeque<(u64, ChipEight)>,
+    // What happened once that many instructions had executed, in order.
+    events: VecDeque<(u64, Event)>,
+    // The keypad as last logged.
+    keys: Keypad,
+}
+
+// Histories are big and only make sense for the machine that recorded them, so a cloned machine starts out without one.
+#[derive(Default)]
+pub(crate) struct RewindSlot(Option<Box<History>>);
+
+impl Clone for RewindSlot {
+    fn clone(&self) -> Self {
+        RewindSlot::default()
+    }
+}
+
+impl RewindSlot {
+    // Forgets everything recorded so far, e.g. because the machine was reset. Rewinding stays enabled.
+    pub(crate) fn clear(&mut self) {
+        if let Some(history) = &mut self.0 {
+            **history = History::new(history.settings);
+        }
+    }
+}
+
+impl History {
+    fn new(settings: RewindSettings) -> Self {
+        History {
+            settings,
+            executed: 0,
+            snapshots: VecDeque::new(),
+            events: VecDeque::new(),
+            keys: Keypad::new(),
+        }
+    }
+    // Throws away anything recorded after the machine was at instruction executed, so it can go a different way.
+    fn truncate(&mut self, executed: u64) {
+        self.snapshots.retain(|(at, _)| *at <= executed);
+        self.events.retain(|(at, _)| *at <= executed);
+        self.executed = executed;
+    }
+}
+
+impl ChipEight {
+    // Starts recording, so step_back() can go back through what runs from here on. Memory mapped devices and hooks
+    // aren't part of the replay, so programs that read from devices may not come back to quite the same state.
+    pub fn enable_rewind(&mut self, settings: RewindSettings) {
+        let mut history: History = History::new(settings);
+        history.keys = self.keypad;
+        self.rewind = RewindSlot(Some(Box::new(history)));
+    }
+    pub fn disable_rewind(&mut self) {
+        self.rewind = RewindSlot::default();
+    }
+    pub fn rewind_enabled(&self) -> bool {
+        self.rewind.0.is_some()
+    }
+    // Puts the machine back how it was before the last instruction executed, and leaves it paused there. Returns false
+    // (and changes nothing) if rewinding isn't enabled or the history doesn't go back that far.
+    pub fn step_back(&mut self) -> Result<bool, ChipEightError> {
+        let mut history: Box<History> = match self.rewind.0.take() {
+            Some(history) => history,
+            None => return Ok(false),
+        };
+        let result: Result<bool, ChipEightError> = self.replay_to_previous(&mut history);
+        self.rewind.0 = Some(history);
+        result
+    }
+    fn replay_to_previous(&mut self, history: &mut History) -> Result<bool, ChipEightError> {
+        let target: u64 = match history.executed.checked_sub(1) {
+            Some(target) => target,
+            None => return Ok(false),
+        };
+        let (mut executed, mut replay): (u64, ChipEight) =
+            match history.snapshots.iter().rev().find(|(at, _)| *at <= target) {
+                Some((at, snapshot)) => (*at, snapshot.clone()),
+                None => return Ok(false),
+            };
+        // The replay must run exactly as it did the first time, so nothing gets to pause it.
+        replay.clear_breakpoints();
+        replay.clear_watchpoints();
+        replay.clear_pause();
+
+        while executed < target {
+            replay.step()?;
+            executed += 1;
+            for (_, event) in history.events.iter().filter(|(at, _)| *at == executed) {
+                match event {
+                    Event::TickTimers => replay.tick_timers(),
+                    Event::Keys(keys) => replay.keypad = *keys,
+                }
+            }
+        }
+        // The replay is a clone, so load_state gets it back into this machine without losing the hooks and devices.
+        if self.load_state(&replay.save_state()).is_err() {
+            return Ok(false);
+        }
+        history.truncate(target);
+        history.keys = self.keypad;
+        self.pause_at_pc();
+        Ok(true)
+    }
+    // step() calls this before executing an instruction: it logs the keypad if it changed, and takes a snapshot if one's
+    // due.
+    pub(crate) fn record_before_instruction(&mut self, stepping: bool) {
+        let mut history: Box<History> = match self.rewind.0.take() {
+            Some(history) => history,
+            None => return,
+        };
+        if history.keys != self.keypad {
+            history.keys = self.keypad;
+            history
+                .events
+                .push_back((history.executed, Event::Keys(self.keypad)));
+        }
+        let interval: u64 = if stepping {
+            history.settings.stepping_interval
+        } else {
+            history.settings.interval
+        }
+        .max(1) as u64;
+        let last_snapshot: Option<u64> = history.snapshots.back().map(|(at, _)| *at);
+        let due: bool = match last_snapshot {
+            Some(at) => at != history.executed && history.executed - at >= interval,
+            None => true,
+        };
+        if due {
+            history
+                .snapshots
+                .push_back((history.executed, self.clone()));
+            while history.snapshots.len() > history.settings.snapshots.max(1) {
+                history.snapshots.pop_front();
+            }
+            let oldest: u64 = history.snapshots.front().map_or(0, |(at, _)| *at);
+            while history.events.front().is_some_and(|(at, _)| *at < oldest) {
+                history.events.pop_front();
+            }
+        }
+        self.rewind.0 = Some(history);
+    }
+    // And this once the instruction has executed successfully.
+    pub(crate) fn record_after_instruction(&mut self) {
+        if let Some(history) = &mut self.rewind.0 {
+            history.executed += 1;
+        }
+    }
+    pub(crate) fn record_timer_tick(&mut self) {
+        if let Some(history) = &mut self.rewind.0 {
+            history
+                .events
+                .push_back((history.executed, Event::TickTimers));
+        }
+    }
+}
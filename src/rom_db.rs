@@ -0,0 +1,261 @@
+// A small database of known ROMs, keyed by the CRC-32 of the program bytes, so we can pick the right variant, quirks, and speed
+// for a ROM without the user having to figure them out by trial and error.
+use crate::toml_lite::{self, TomlError};
+use crate::{ChipVariant, Quirks};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomEntry {
+    pub crc32: u32,
+    pub title: String,
+    pub variant: ChipVariant,
+    // Quirks the ROM needs that differ from its variant's preset. None means the preset is fine.
+    pub quirks: Option<Quirks>,
+    pub instructions_per_frame: Option<u32>,
+}
+
+// Entries compiled into the binary: (CRC-32, title, variant, instructions per frame).
+// Only add ROMs here whose hash has been checked against a known-good dump. Each one has a copy in tests/fixtures, and the
+// tests check its hash against the entry.
+static BUILTIN_ROMS: &[(u32, &str, ChipVariant, u32)] =
+    &[(0xC46C_A868, "IBM Logo", ChipVariant::Chip8, 10)];
+
+pub struct RomDatabase {
+    entries: Vec<RomEntry>,
+}
+
+impl RomDatabase {
+    pub fn builtin() -> Self {
+        RomDatabase {
+            entries: BUILTIN_ROMS
+                .iter()
+                .map(
+                    |&(crc32, title, variant, instructions_per_frame)| RomEntry {
+                        crc32,
+                        title: title.to_string(),
+                        variant,
+                        quirks: None,
+                        instructions_per_frame: Some(instructions_per_frame),
+                    },
+                )
+                .collect(),
+        }
+    }
+    // Adds the entries from a user database file. Entries for a ROM that's already known replace the existing entry.
+    // The file is a list of [[rom]] tables:
+    //
+    //   [[rom]]
+    //   crc32 = 0x1A2B3C4D
+    //   title = "Brix"
+    //   variant = "chip8"             # optional, defaults to chip8
+    //   instructions_per_frame = 15   # optional
+    //   shift_uses_vy = false         # optional, any quirk name overrides the variant's preset
+    pub fn extend_from_toml(&mut self, src: &str) -> Result<(), TomlError> {
+        for table in toml_lite::parse(src)? {
+            if table.name.is_empty() {
+                if let Some(entry) = table.entries.first() {
+                    return Err(TomlError::new(
+                        entry.line,
+                        "entries must be inside a [[rom]] table",
+                    ));
+                }
+                continue;
+            }
+            if table.name != "rom" {
+                return Err(TomlError::new(
+                    table.entries.first().map_or(0, |entry| entry.line),
+                    format!("unknown table [{}]", table.name),
+                ));
+            }
+            let rom: RomEntry = Self::parse_entry(&table)?;
+
+            self.entries.retain(|entry| entry.crc32 != rom.crc32);
+            self.entries.push(rom);
+        }
+        Ok(())
+    }
+    fn parse_entry(table: &toml_lite::Table) -> Result<RomEntry, TomlError> {
+        let mut crc32: Option<u32> = None;
+        let mut title: Option<String> = None;
+        let mut variant: ChipVariant = ChipVariant::default();
+        let mut quirk_overrides: Vec<(&str, bool)> = vec![];
+        let mut instructions_per_frame: Option<u32> = None;
+
+        for entry in &table.entries {
+            let invalid = |expected: &str| {
+                TomlError::new(
+                    entry.line,
+                    format!("'{}' should be {}", entry.key, expected),
+                )
+            };
+            match entry.key.as_str() {
+                "crc32" => {
+                    crc32 = Some(
+                        entry
+                            .value
+                            .as_integer()
+                            .and_then(|i| u32::try_from(i).ok())
+                            .ok_or_else(|| invalid("a 32-bit integer"))?,
+                    )
+                }
+                "title" => {
+                    title = Some(
+                        entry
+                            .value
+                            .as_str()
+                            .ok_or_else(|| invalid("a string"))?
+                            .to_string(),
+                    )
+                }
+                "variant" => {
+                    variant = entry
+                        .value
+                        .as_str()
+                        .ok_or_else(|| invalid("a string"))?
+                        .parse()
+                        .map_err(|e: String| TomlError::new(entry.line, e))?
+                }
+                "instructions_per_frame" => {
+                    instructions_per_frame = Some(
+                        entry
+                            .value
+                            .as_integer()
+                            .and_then(|i| u32::try_from(i).ok())
+                            .filter(|&i| i > 0)
+                            .ok_or_else(|| invalid("a positive integer"))?,
+                    )
+                }
+                key if Quirks::NAMES.contains(&key) => quirk_overrides.push((
+                    key,
+                    entry
+                        .value
+                        .as_bool()
+                        .ok_or_else(|| invalid("true or false"))?,
+                )),
+                key => return Err(TomlError::new(entry.line, format!("unknown key '{}'", key))),
+            }
+        }
+
+        let first_line: usize = table.entries.first().map_or(0, |entry| entry.line);
+        let crc32: u32 =
+            crc32.ok_or_else(|| TomlError::new(first_line, "[[rom]] is missing 'crc32'"))?;
+        let title: String =
+            title.ok_or_else(|| TomlError::new(first_line, "[[rom]] is missing 'title'"))?;
+        let quirks: Option<Quirks> = if quirk_overrides.is_empty() {
+            None
+        } else {
+            let mut quirks: Quirks = variant.quirks();
+            for (name, enabled) in quirk_overrides {
+                quirks.set(name, enabled);
+            }
+            Some(quirks)
+        };
+
+        Ok(RomEntry {
+            crc32,
+            title,
+            variant,
+            quirks,
+            instructions_per_frame,
+        })
+    }
+    pub fn lookup(&self, crc32: u32) -> Option<&RomEntry> {
+        self.entries.iter().find(|entry| entry.crc32 == crc32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crc32;
+
+    #[test]
+    fn built_in_entries_match_their_fixtures() {
+        let database: RomDatabase = RomDatabase::builtin();
+        let ibm_logo: &[u8] = include_bytes!("../tests/fixtures/ibm_logo.ch8");
+        let entry: &RomEntry = database.lookup(crc32(ibm_logo)).unwrap();
+        assert_eq!(entry.title, "IBM Logo");
+        assert_eq!(entry.variant, ChipVariant::Chip8);
+        assert_eq!(database.entries.len(), BUILTIN_ROMS.len());
+    }
+
+    #[test]
+    fn user_entries_add_to_and_replace_the_built_in_ones() {
+        let mut database: RomDatabase = RomDatabase::builtin();
+        database
+            .extend_from_toml(
+                "# mine\n\
+                 [[rom]]\n\
+                 crc32 = 0x1A2B3C4D\n\
+                 title = \"Brix\"\n\
+                 instructions_per_frame = 15\n\
+                 shift_uses_vy = false\n\
+                 [[rom]]\n\
+                 crc32 = 0xC46CA868\n\
+                 title = \"IBM Logo (fast)\"\n\
+                 variant = \"schip-modern\"\n",
+            )
+            .unwrap();
+
+        let brix: &RomEntry = database.lookup(0x1A2B_3C4D).unwrap();
+        let mut quirks: Quirks = ChipVariant::Chip8.quirks();
+        quirks.shift_uses_vy = false;
+        assert_eq!(
+            *brix,
+            RomEntry {
+                crc32: 0x1A2B_3C4D,
+                title: "Brix".to_string(),
+                variant: ChipVariant::Chip8,
+                quirks: Some(quirks),
+                instructions_per_frame: Some(15),
+            }
+        );
+        let ibm_logo: &RomEntry = database.lookup(0xC46C_A868).unwrap();
+        assert_eq!(ibm_logo.title, "IBM Logo (fast)");
+        assert_eq!(ibm_logo.variant, ChipVariant::SuperChipModern);
+        assert_eq!(
+            (ibm_logo.quirks, ibm_logo.instructions_per_frame),
+            (None, None)
+        );
+        assert_eq!(database.entries.len(), 2);
+        assert!(database.lookup(0).is_none());
+    }
+
+    #[test]
+    fn bad_entries_say_what_and_where() {
+        for (src, line, message) in [
+            ("crc32 = 1\n", 1, "entries must be inside a [[rom]] table"),
+            ("[game]\ncrc32 = 1\n", 2, "unknown table [game]"),
+            ("[[rom]]\ntitle = \"x\"\n", 2, "[[rom]] is missing 'crc32'"),
+            ("[[rom]]\ncrc32 = 1\n", 2, "[[rom]] is missing 'title'"),
+            (
+                "[[rom]]\ncrc32 = 0x100000000\n",
+                2,
+                "'crc32' should be a 32-bit integer",
+            ),
+            (
+                "[[rom]]\ncrc32 = 1\ntitle = 2\n",
+                3,
+                "'title' should be a string",
+            ),
+            (
+                "[[rom]]\ncrc32 = 1\ninstructions_per_frame = 0\n",
+                3,
+                "'instructions_per_frame' should be a positive integer",
+            ),
+            (
+                "[[rom]]\ncrc32 = 1\nvf_reset = 1\n",
+                3,
+                "'vf_reset' should be true or false",
+            ),
+            ("[[rom]]\ncrc32 = 1\nspeed = 2\n", 3, "unknown key 'speed'"),
+        ] {
+            let mut database: RomDatabase = RomDatabase::builtin();
+            assert_eq!(
+                database.extend_from_toml(src),
+                Err(TomlError::new(line, message)),
+                "{:?}",
+                src
+            );
+        }
+    }
+}
@@ -0,0 +1,453 @@
+// Compact binary save states. Unlike the serde support, this needs nothing outside the standard library and always
+// produces the same bytes for the same machine.
+//
+// The format is the magic "PCH8", a version byte, the payload's length, the payload, and a CRC-32 of the payload. The
+// payload is every piece of machine state in a fixed order (see write_payload). Multi-byte numbers are little-endian. Hooks, the
+// keymap, and colors are settings of the frontend rather than of the machine, so they aren't saved.
+use crate::{
+    crc32, ChipEight, ChipVariant, Keypad, MemorySize, Quirks, RomInfo, TimingMode, STACK_DEPTH,
+};
+use std::fmt;
+
+const MAGIC: &[u8; 4] = b"PCH8";
+// Bump this whenever the payload's layout changes, so older states are rejected rather than misread.
+const VERSION: u8 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateError {
+    // The data doesn't start with the save state magic, so it's probably not a save state at all.
+    BadMagic,
+    // The save state was written by a newer (or unknown) version of the format.
+    UnsupportedVersion(u8),
+    // The data ends before the save state does.
+    Truncated,
+    // The payload doesn't match its checksum, so the file was corrupted somewhere along the way.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    // The checksum is fine, but a value in the payload is impossible (an unknown variant, a stack pointer past the end of
+    // the stack, etc.). The string names the value.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::BadMagic => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save state version {}", version)
+            }
+            StateError::Truncated => write!(f, "the save state is truncated"),
+            StateError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "the save state is corrupted (checksum {:08X}, expected {:08X})",
+                actual, expected
+            ),
+            StateError::Invalid(what) => write!(f, "the save state has an invalid {}", what),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl ChipEight {
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut payload: Vec<u8> = vec![];
+        self.write_payload(&mut payload);
+
+        let mut state: Vec<u8> = Vec::with_capacity(MAGIC.len() + 1 + 4 + payload.len() + 4);
+        state.extend_from_slice(MAGIC);
+        state.push(VERSION);
+        state.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        state.extend_from_slice(&payload);
+        state.extend_from_slice(&crc32(&payload).to_le_bytes());
+        state
+    }
+    // Restores a machine saved with save_state. The machine is only changed if the whole state is valid.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        if state.len() < MAGIC.len() || &state[..MAGIC.len()] != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let mut input: Reader = Reader {
+            bytes: &state[MAGIC.len()..],
+        };
+        let version: u8 = input.u8()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let size: usize = input.u32()? as usize;
+        let payload: &[u8] = input.bytes(size)?;
+        let expected: u32 = input.u32()?;
+        if !input.bytes.is_empty() {
+            return Err(StateError::Invalid("length"));
+        }
+        let actual: u32 = crc32(payload);
+        if expected != actual {
+            return Err(StateError::ChecksumMismatch { expected, actual });
+        }
+
+        let mut loaded: ChipEight = self.clone();
+        loaded.read_payload(&mut Reader { bytes: payload })?;
+        // Hooks and devices can't be cloned, so they're moved over from the original machine, along with its stop token.
+        loaded.hooks = std::mem::take(&mut self.hooks);
+        loaded.devices = std::mem::take(&mut self.devices);
+        loaded.stop = std::mem::take(&mut self.stop);
+        // So is the rewind history, but it's history of a different machine now.
+        loaded.rewind = std::mem::take(&mut self.rewind);
+        loaded.rewind.clear();
+        // Breakpoints carry over, but wherever this machine was paused has nothing to do with the loaded state.
+        loaded.clear_pause();
+        *self = loaded;
+        Ok(())
+    }
+    fn write_payload(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.memory);
+        for row in self.screen.pixels.iter() {
+            let bits: u128 = row.iter().fold(0, |bits, &pixel| bits << 1 | pixel as u128);
+            out.extend_from_slice(&bits.to_le_bytes());
+        }
+        out.push(self.screen.height as u8);
+        out.push(self.screen.width as u8);
+        for address in self.stack.iter() {
+            out.extend_from_slice(&address.to_le_bytes());
+        }
+        out.push(self.sp);
+        out.extend_from_slice(&self.v_registers);
+        out.extend_from_slice(&self.pc.to_le_bytes());
+        out.extend_from_slice(&self.start_address.to_le_bytes());
+        out.extend_from_slice(&self.i_register.to_le_bytes());
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.push(match self.variant {
+            ChipVariant::Chip8 => 0,
+            ChipVariant::SuperChipLegacy => 1,
+            ChipVariant::SuperChipModern => 2,
+            ChipVariant::XoChip => 3,
+        });
+        match self.quirks_override {
+            Some(quirks) => {
+                out.push(1);
+                out.extend_from_slice(&[
+                    quirks.vf_reset as u8,
+                    quirks.shift_uses_vy as u8,
+                    quirks.load_store_increments_i as u8,
+                    quirks.jump_uses_vx as u8,
+                    quirks.clip_sprites as u8,
+                    quirks.display_wait as u8,
+                ]);
+            }
+            None => out.push(0),
+        }
+        out.extend_from_slice(&self.instructions_per_frame.to_le_bytes());
+        out.push(match self.timing_mode {
+            TimingMode::InstructionsPerFrame => 0,
+            TimingMode::CosmacVip => 1,
+        });
+        out.extend_from_slice(&self.cycle_budget.to_le_bytes());
+        match &self.rom {
+            Some(rom) => {
+                out.push(1);
+                out.extend_from_slice(&(rom.len() as u32).to_le_bytes());
+                out.extend_from_slice(rom);
+            }
+            None => out.push(0),
+        }
+        match &self.rom_info {
+            Some(info) => {
+                out.push(1);
+                out.extend_from_slice(&info.crc32.to_le_bytes());
+            }
+            None => out.push(0),
+        }
+        let rng: Vec<u8> = self.rng.save();
+        out.extend_from_slice(&(rng.len() as u32).to_le_bytes());
+        out.extend_from_slice(&rng);
+        let (held, presses) = self.keypad.to_bits();
+        out.extend_from_slice(&held.to_le_bytes());
+        out.extend_from_slice(&presses.to_le_bytes());
+        out.extend_from_slice(&self.flags);
+        for glyph in self.font.iter() {
+            out.extend_from_slice(glyph);
+        }
+        out.push(self.waiting_for_key as u8);
+        out.push(self.halted as u8);
+        out.push(self.screen_changed as u8);
+    }
+    fn read_payload(&mut self, input: &mut Reader) -> Result<(), StateError> {
+        let memory_size: usize = input.u32()? as usize;
+        if memory_size != MemorySize::Classic.bytes() && memory_size != MemorySize::Extended.bytes()
+        {
+            return Err(StateError::Invalid("memory size"));
+        }
+        self.memory = input.bytes(memory_size)?.into();
+        for row in self.screen.pixels.iter_mut() {
+            let bits: u128 = input.u128()?;
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = bits & (1 << (127 - x)) != 0;
+            }
+        }
+        self.screen.height = input.u8()? as usize;
+        self.screen.width = input.u8()? as usize;
+        if !matches!(
+            (self.screen.width, self.screen.height),
+            (64, 32) | (64, 64) | (128, 64)
+        ) {
+            return Err(StateError::Invalid("screen size"));
+        }
+        for address in self.stack.iter_mut() {
+            *address = input.u16()?;
+        }
+        self.sp = input.u8()?;
+        if self.sp as usize > STACK_DEPTH {
+            return Err(StateError::Invalid("stack pointer"));
+        }
+        self.v_registers.copy_from_slice(input.bytes(16)?);
+        self.pc = input.u16()?;
+        self.start_address = input.u16()?;
+        self.i_register = input.u16()?;
+        self.delay_timer = input.u8()?;
+        self.sound_timer = input.u8()?;
+        self.variant = match input.u8()? {
+            0 => ChipVariant::Chip8,
+            1 => ChipVariant::SuperChipLegacy,
+            2 => ChipVariant::SuperChipModern,
+            3 => ChipVariant::XoChip,
+            _ => return Err(StateError::Invalid("variant")),
+        };
+        self.quirks_override = match input.flag()? {
+            true => Some(Quirks {
+                vf_reset: input.flag()?,
+                shift_uses_vy: input.flag()?,
+                load_store_increments_i: input.flag()?,
+                jump_uses_vx: input.flag()?,
+                clip_sprites: input.flag()?,
+                display_wait: input.flag()?,
+            }),
+            false => None,
+        };
+        self.instructions_per_frame = input.u32()?;
+        if self.instructions_per_frame == 0 {
+            return Err(StateError::Invalid("instructions per frame"));
+        }
+        self.timing_mode = match input.u8()? {
+            0 => TimingMode::InstructionsPerFrame,
+            1 => TimingMode::CosmacVip,
+            _ => return Err(StateError::Invalid("timing mode")),
+        };
+        self.cycle_budget = input.i32()?;
+        self.rom = match input.flag()? {
+            true => {
+                let size: usize = input.u32()? as usize;
+                Some(input.bytes(size)?.to_vec())
+            }
+            false => None,
+        };
+        // Only the CRC-32 is saved, and the rest of the ROM info is worked out again from the ROM. The file name and title
+        // aren't part of the state, so they're kept if this machine has the same ROM loaded.
+        self.rom_info = match (input.flag()?, &self.rom) {
+            (true, Some(rom)) => {
+                let saved_crc32: u32 = input.u32()?;
+                if crc32(rom) != saved_crc32 {
+                    return Err(StateError::Invalid("ROM checksum"));
+                }
+                match self.rom_info.take() {
+                    Some(info) if info.crc32 == saved_crc32 => Some(info),
+                    _ => Some(RomInfo::new(rom)),
+                }
+            }
+            (true, None) => return Err(StateError::Invalid("ROM checksum")),
+            (false, _) => None,
+        };
+        let rng_size: usize = input.u32()? as usize;
+        // The state goes back into whatever source this machine is using, so a machine with a MockRng can't load a state
+        // saved with the xorshift (or vice versa).
+        if !self.rng.restore(input.bytes(rng_size)?) {
+            return Err(StateError::Invalid("random number generator state"));
+        }
+        let held: u16 = input.u16()?;
+        self.keypad = Keypad::from_bits(held, input.u16()?);
+        self.flags.copy_from_slice(input.bytes(16)?);
+        for glyph in self.font.iter_mut() {
+            glyph.copy_from_slice(input.bytes(5)?);
+        }
+        self.waiting_for_key = input.flag()?;
+        self.halted = input.flag()?;
+        self.screen_changed = input.flag()?;
+        self.dirty = Some(crate::dirty::WHOLE_SCREEN);
+
+        if !input.bytes.is_empty() {
+            return Err(StateError::Invalid("length"));
+        }
+        Ok(())
+    }
+}
+
+// Reads the payload front to back. Running out of bytes is always a Truncated error.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], StateError> {
+        if self.bytes.len() < count {
+            return Err(StateError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let mut array: [u8; N] = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.array::<1>()?[0])
+    }
+    fn flag(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Invalid("flag")),
+        }
+    }
+    fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+    fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+    fn i32(&mut self) -> Result<i32, StateError> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+    fn u128(&mut self) -> Result<u128, StateError> {
+        Ok(u128::from_le_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FontSet;
+
+    // Where the payload starts: after the magic, the version, and the payload's length.
+    const HEADER_SIZE: usize = 9;
+
+    // A SUPER-CHIP machine partway through a program, with a bit of everything set: hires, a sprite, a call on the stack,
+    // timers, flags, held keys, and a different font.
+    fn machine() -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(5);
+        chip_eight.set_variant(ChipVariant::SuperChipModern);
+        chip_eight.load_font(FontSet::CosmacVip.glyphs());
+        chip_eight
+            .load_program_from_slice(&[
+                0x00, 0xFF, 0x60, 0x7C, 0xA0, 0x00, 0xD0, 0x15, 0xF0, 0x15, 0xF3, 0x75, 0x22, 0x10,
+                0x00, 0x00, 0x12, 0x10,
+            ])
+            .unwrap();
+        let mut keys: Keypad = Keypad::default();
+        keys.press(0xA);
+        chip_eight.frame(&keys).unwrap();
+        chip_eight
+    }
+
+    // A fresh machine with state loaded into it.
+    fn load(state: &[u8]) -> Result<ChipEight, StateError> {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        chip_eight.load_state(state)?;
+        Ok(chip_eight)
+    }
+
+    // state with its payload changed by edit and the checksum fixed up to match, to get past the checksum.
+    fn edited(state: &[u8], edit: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+        let mut payload: Vec<u8> = state[HEADER_SIZE..state.len() - 4].to_vec();
+        edit(&mut payload);
+        let mut edited: Vec<u8> = state[..5].to_vec();
+        edited.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        edited.extend_from_slice(&payload);
+        edited.extend_from_slice(&crc32(&payload).to_le_bytes());
+        edited
+    }
+
+    #[test]
+    fn a_machine_round_trips() {
+        let chip_eight: ChipEight = machine();
+        let state: Vec<u8> = chip_eight.save_state();
+        let loaded: ChipEight = load(&state).unwrap();
+        assert_eq!(loaded, chip_eight);
+        assert_eq!(loaded.save_state(), state);
+        assert_eq!(loaded.stack(), &[0x20C]);
+        assert!(loaded.framebuffer()[0][124]);
+    }
+
+    #[test]
+    fn truncated_states_are_truncated() {
+        let state: Vec<u8> = machine().save_state();
+        for len in MAGIC.len()..state.len() {
+            assert_eq!(
+                load(&state[..len]).err(),
+                Some(StateError::Truncated),
+                "cut to {} bytes",
+                len
+            );
+        }
+        assert_eq!(load(&state[..2]).err(), Some(StateError::BadMagic));
+    }
+
+    #[test]
+    fn corruption_is_caught_by_the_checksum() {
+        let state: Vec<u8> = machine().save_state();
+        for offset in [HEADER_SIZE, HEADER_SIZE + 0x200, state.len() - 5] {
+            let mut corrupted: Vec<u8> = state.clone();
+            corrupted[offset] ^= 0x10;
+            assert!(
+                matches!(load(&corrupted), Err(StateError::ChecksumMismatch { .. })),
+                "byte {} flipped",
+                offset
+            );
+        }
+    }
+
+    #[test]
+    fn bad_headers_and_trailing_bytes_are_rejected() {
+        let state: Vec<u8> = machine().save_state();
+        let mut not_a_state: Vec<u8> = state.clone();
+        not_a_state[0] = b'X';
+        assert_eq!(load(&not_a_state).err(), Some(StateError::BadMagic));
+        let mut newer: Vec<u8> = state.clone();
+        newer[4] = VERSION + 1;
+        assert_eq!(
+            load(&newer).err(),
+            Some(StateError::UnsupportedVersion(VERSION + 1))
+        );
+        let mut longer: Vec<u8> = state.clone();
+        longer.push(0);
+        assert_eq!(load(&longer).err(), Some(StateError::Invalid("length")));
+    }
+
+    #[test]
+    fn impossible_values_are_rejected() {
+        let state: Vec<u8> = machine().save_state();
+        // The memory size, then the screen's rows, height, and width.
+        let screen_size: usize = 4 + 4096 + 64 * 16;
+        let sp: usize = screen_size + 2 + 16 * 2;
+        for (edit, what) in [
+            (screen_size + 1, "screen size"),
+            (sp, "stack pointer"),
+            (sp + 1 + 16 + 6 + 2, "variant"),
+        ] {
+            let bad: Vec<u8> = edited(&state, |payload| payload[edit] = 0x77);
+            assert_eq!(load(&bad).err(), Some(StateError::Invalid(what)));
+        }
+        let bad: Vec<u8> = edited(&state, |payload| payload.push(0));
+        assert_eq!(load(&bad).err(), Some(StateError::Invalid("length")));
+    }
+
+    #[test]
+    fn a_failed_load_leaves_the_machine_alone() {
+        let mut chip_eight: ChipEight = machine();
+        let before: ChipEight = chip_eight.clone();
+        let state: Vec<u8> = ChipEight::with_seed(0).save_state();
+        assert!(chip_eight.load_state(&state[..state.len() - 1]).is_err());
+        assert_eq!(chip_eight, before);
+    }
+}
@@ -0,0 +1,289 @@
+// The machine's screen: a grid of monochrome pixels, in whichever of the supported resolutions the program has switched
+// to. The opcode handlers draw, clear, and scroll it through these methods, and frontends can read it pixel by pixel
+// without knowing how big it is. Every mode fits in 128x64, so that's what's kept, with only the rows and columns of the
+// current mode in use.
+#[cfg(feature = "serde")]
+use crate::serde_support;
+
+const BUFFER_WIDTH: usize = 128;
+const BUFFER_HEIGHT: usize = 64;
+
+// The resolutions the screen can be in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ScreenMode {
+    // 64x32, what every program starts in.
+    #[default]
+    Lores,
+    // 64x64, for hires Chip-8 programs.
+    Hires,
+    // 128x64, SUPER-CHIP's hires mode (00FF), which XO-CHIP has too.
+    SuperHires,
+}
+
+impl ScreenMode {
+    // The (width, height) of the mode in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        match self {
+            ScreenMode::Lores => (64, 32),
+            ScreenMode::Hires => (64, 64),
+            ScreenMode::SuperHires => (128, 64),
+        }
+    }
+}
+
+// The serialized form keeps the field names ChipEight had before the screen was split out of it (ChipEight flattens
+// this into itself), so older saved files still load.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Screen {
+    // Rows of BUFFER_WIDTH pixels, top to bottom. Pixels outside the current mode are always unlit.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "screen", with = "serde_support::screen")
+    )]
+    pub(crate) pixels: [[bool; BUFFER_WIDTH]; BUFFER_HEIGHT],
+    // Files saved before SUPER-CHIP's hires mode don't have this, and were all 64 pixels wide.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "screen_width", default = "serde_support::lores_width")
+    )]
+    pub(crate) width: usize,
+    #[cfg_attr(feature = "serde", serde(rename = "screen_height"))]
+    pub(crate) height: usize,
+}
+
+impl Screen {
+    // A blank screen in lores mode.
+    pub fn new() -> Self {
+        Screen {
+            pixels: [[false; BUFFER_WIDTH]; BUFFER_HEIGHT],
+            width: ScreenMode::Lores.size().0,
+            height: ScreenMode::Lores.size().1,
+        }
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn mode(&self) -> ScreenMode {
+        match (self.width, self.height) {
+            (128, _) => ScreenMode::SuperHires,
+            (_, 64) => ScreenMode::Hires,
+            _ => ScreenMode::Lores,
+        }
+    }
+    // Switches to mode, clearing the screen, as switching resolution does on every interpreter that can.
+    pub fn set_mode(&mut self, mode: ScreenMode) {
+        self.clear();
+        (self.width, self.height) = mode.size();
+    }
+    // Whether the pixel at (x, y) is lit. Coordinates off the screen are never lit.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width() && y < self.height() && self.pixels[y][x]
+    }
+    // Flips the pixel at (x, y), as drawing a sprite's lit pixel over it does, and returns whether that was a collision:
+    // whether it was lit, and so got turned off. Coordinates off the screen wrap around to the other side; clipping is up
+    // to whatever's drawing.
+    pub fn xor_pixel(&mut self, x: usize, y: usize) -> bool {
+        let (x, y) = (x % self.width(), y % self.height());
+        let pixel: &mut bool = &mut self.pixels[y][x];
+        *pixel = !*pixel;
+        !*pixel
+    }
+    // Turns every pixel off.
+    pub fn clear(&mut self) {
+        self.pixels = [[false; BUFFER_WIDTH]; BUFFER_HEIGHT];
+    }
+    // Moves everything up n pixels. The top n rows are lost, and the bottom n come in blank.
+    pub fn scroll_up(&mut self, n: usize) {
+        let height: usize = self.height();
+        for row in 0..height {
+            self.pixels[row] = if row + n < height {
+                self.pixels[row + n]
+            } else {
+                [false; BUFFER_WIDTH]
+            };
+        }
+    }
+    // Moves everything down n pixels. The bottom n rows are lost, and the top n come in blank.
+    pub fn scroll_down(&mut self, n: usize) {
+        for row in (0..self.height()).rev() {
+            self.pixels[row] = if row >= n {
+                self.pixels[row - n]
+            } else {
+                [false; BUFFER_WIDTH]
+            };
+        }
+    }
+    // Moves everything left n pixels. The leftmost n columns are lost, and the rightmost n come in blank.
+    pub fn scroll_left(&mut self, n: usize) {
+        let width: usize = self.width();
+        for row in self.pixels.iter_mut().take(self.height) {
+            for x in 0..width {
+                row[x] = x + n < width && row[x + n];
+            }
+        }
+    }
+    // Moves everything right n pixels. The rightmost n columns are lost, and the leftmost n come in blank.
+    pub fn scroll_right(&mut self, n: usize) {
+        let width: usize = self.width();
+        for row in self.pixels.iter_mut().take(self.height) {
+            for x in (0..width).rev() {
+                row[x] = x >= n && row[x - n];
+            }
+        }
+    }
+    // The rows in use, for ChipEight::framebuffer().
+    pub(crate) fn rows(&self) -> &[[bool; BUFFER_WIDTH]] {
+        &self.pixels[..self.height]
+    }
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Screen::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A screen in mode with the given pixels lit.
+    fn screen(mode: ScreenMode, lit: &[(usize, usize)]) -> Screen {
+        let mut screen: Screen = Screen::new();
+        screen.set_mode(mode);
+        for &(x, y) in lit {
+            screen.xor_pixel(x, y);
+        }
+        screen
+    }
+
+    // The lit pixels, left to right along each row, top to bottom.
+    fn lit(screen: &Screen) -> [Option<(usize, usize)>; 4] {
+        let mut pixels = (0..screen.height())
+            .flat_map(|y| (0..screen.width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| screen.get(x, y));
+        ::core::array::from_fn(|_| pixels.next())
+    }
+
+    #[test]
+    fn modes_have_their_own_sizes() {
+        for mode in [ScreenMode::Lores, ScreenMode::Hires, ScreenMode::SuperHires] {
+            let screen: Screen = screen(mode, &[]);
+            assert_eq!((screen.width(), screen.height()), mode.size());
+            assert_eq!(screen.mode(), mode);
+            assert_eq!(screen.rows().len(), mode.size().1);
+        }
+        assert_eq!(Screen::default().mode(), ScreenMode::Lores);
+    }
+
+    #[test]
+    fn switching_modes_clears_the_screen() {
+        let mut screen: Screen = screen(ScreenMode::Lores, &[(3, 4)]);
+        screen.set_mode(ScreenMode::SuperHires);
+        assert_eq!(lit(&screen), [None; 4]);
+        screen.xor_pixel(100, 50);
+        screen.set_mode(ScreenMode::Lores);
+        assert_eq!(screen, Screen::new());
+    }
+
+    #[test]
+    fn pixels_off_the_screen_arent_lit() {
+        let screen: Screen = screen(ScreenMode::Lores, &[(63, 31)]);
+        assert!(screen.get(63, 31));
+        for (x, y) in [(64, 31), (63, 32), (127, 63), (usize::MAX, 0)] {
+            assert!(!screen.get(x, y), "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn xor_pixel_reports_collisions() {
+        let mut screen: Screen = Screen::new();
+        assert!(!screen.xor_pixel(5, 6));
+        assert!(screen.get(5, 6));
+        assert!(screen.xor_pixel(5, 6));
+        assert!(!screen.get(5, 6));
+    }
+
+    #[test]
+    fn xor_pixel_wraps_at_the_modes_edges() {
+        for (mode, (x, y), expected) in [
+            (ScreenMode::Lores, (64, 32), (0, 0)),
+            (ScreenMode::Lores, (70, 40), (6, 8)),
+            (ScreenMode::Hires, (70, 40), (6, 40)),
+            (ScreenMode::SuperHires, (70, 40), (70, 40)),
+            (ScreenMode::SuperHires, (130, 66), (2, 2)),
+        ] {
+            assert_eq!(
+                lit(&screen(mode, &[(x, y)])),
+                [Some(expected), None, None, None],
+                "{:?} ({}, {})",
+                mode,
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn scrolls_move_pixels_and_lose_them_off_the_edge() {
+        for mode in [ScreenMode::Lores, ScreenMode::SuperHires] {
+            let (width, height) = mode.size();
+            let corners: [(usize, usize); 2] = [(1, 1), (width - 2, height - 2)];
+
+            let mut down: Screen = screen(mode, &corners);
+            down.scroll_down(4);
+            assert_eq!(lit(&down), [Some((1, 5)), None, None, None], "{:?}", mode);
+
+            let mut up: Screen = screen(mode, &corners);
+            up.scroll_up(4);
+            assert_eq!(lit(&up), [Some((width - 2, height - 6)), None, None, None]);
+
+            let mut left: Screen = screen(mode, &corners);
+            left.scroll_left(4);
+            assert_eq!(
+                lit(&left),
+                [Some((width - 6, height - 2)), None, None, None]
+            );
+
+            let mut right: Screen = screen(mode, &corners);
+            right.scroll_right(4);
+            assert_eq!(lit(&right), [Some((5, 1)), None, None, None]);
+        }
+    }
+
+    #[test]
+    fn scrolling_past_the_edge_leaves_nothing() {
+        let mut hires: Screen = screen(ScreenMode::SuperHires, &[(0, 0), (127, 63)]);
+        hires.scroll_right(128);
+        assert_eq!(lit(&hires), [None; 4]);
+        let mut lores: Screen = screen(ScreenMode::Lores, &[(0, 0), (63, 31)]);
+        lores.scroll_up(64);
+        assert_eq!(lores, Screen::new());
+    }
+
+    #[test]
+    fn scrolling_lores_leaves_the_rest_of_the_buffer_blank() {
+        let mut screen: Screen = screen(ScreenMode::Lores, &[(0, 0), (63, 31)]);
+        screen.scroll_down(8);
+        screen.scroll_right(8);
+        assert_eq!(lit(&screen), [Some((8, 8)), None, None, None]);
+        // Nothing moved into the part of the buffer lores doesn't use, so switching up shows a blank screen.
+        assert!(screen.pixels.iter().skip(32).flatten().all(|&pixel| !pixel));
+        assert!(screen
+            .pixels
+            .iter()
+            .all(|row| row[64..].iter().all(|&pixel| !pixel)));
+    }
+
+    #[test]
+    fn clear_turns_everything_off() {
+        let mut screen: Screen = screen(ScreenMode::Hires, &[(0, 0), (63, 63)]);
+        screen.clear();
+        assert_eq!(lit(&screen), [None; 4]);
+        assert_eq!(screen.mode(), ScreenMode::Hires);
+    }
+}
@@ -0,0 +1,203 @@
+// Screenshots: the screen as a PNG, in the frontend's palette and scaled up by a whole number so it isn't the size of a
+// postage stamp. They're made from render_to_rgba rather than read back from a window, so they work the same without one.
+// GifRecorder captures its frames the same way.
+use crate::{ChipEight, Palette};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// How many times bigger than the screen screenshots are, unless asked otherwise.
+pub const SCREENSHOT_SCALE: u32 = 8;
+
+// The screen scaled up, with each pixel an index into colors: 0 for the background and 1 for the foreground. Image formats
+// with palettes take it as it is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Capture {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) colors: [[u8; 3]; 2],
+    pub(crate) pixels: Vec<u8>,
+}
+
+impl Capture {
+    pub(crate) fn new(chip_eight: &ChipEight, palette: Palette, scale: u32) -> io::Result<Self> {
+        Capture::scaled(chip_eight, palette, (scale, scale))
+    }
+    // The screen with each of its pixels x_scale pixels wide and y_scale tall, for pixels that aren't square.
+    pub(crate) fn scaled(
+        chip_eight: &ChipEight,
+        palette: Palette,
+        (x_scale, y_scale): (u32, u32),
+    ) -> io::Result<Self> {
+        let (x_scale, y_scale) = (x_scale.max(1) as usize, y_scale.max(1) as usize);
+        let (width, height) = chip_eight.resolution();
+        let mut rgba: Vec<u8> = vec![0; width * height * 4];
+        chip_eight
+            .render_to_rgba(
+                palette.foreground_rgba(),
+                palette.background_rgba(),
+                &mut rgba,
+            )
+            .map_err(|e| io::Error::other(e.to_string()))?;
+
+        let mut pixels: Vec<u8> = Vec::with_capacity(width * height * x_scale * y_scale);
+        for row in rgba.chunks_exact(width * 4) {
+            let line: Vec<u8> = row
+                .chunks_exact(4)
+                .flat_map(|color| [(color == palette.foreground_rgba()) as u8].repeat(x_scale))
+                .collect();
+            for _ in 0..y_scale {
+                pixels.extend_from_slice(&line);
+            }
+        }
+        Ok(Capture {
+            width: width * x_scale,
+            height: height * y_scale,
+            colors: [palette.background, palette.foreground],
+            pixels,
+        })
+    }
+    // colors one after the other, as GIF and PNG palettes are laid out.
+    pub(crate) fn palette(&self) -> Vec<u8> {
+        self.colors.concat()
+    }
+}
+
+// A path in dir named after the time, e.g. potatocho-1700000000.png, that isn't taken yet. More than one a second gets a
+// suffix rather than overwriting the last one.
+pub(crate) fn timestamped_path(dir: &Path, extension: &str) -> PathBuf {
+    let time: u64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let mut path: PathBuf = dir.join(format!("potatocho-{}.{}", time, extension));
+    let mut n: u32 = 1;
+    while path.exists() {
+        n += 1;
+        path = dir.join(format!("potatocho-{}-{}.{}", time, n, extension));
+    }
+    path
+}
+
+impl ChipEight {
+    // Writes the screen to out as a PNG in palette's colors, with each of its pixels scale pixels square.
+    pub fn write_screenshot(
+        &self,
+        palette: Palette,
+        scale: u32,
+        out: impl Write,
+    ) -> io::Result<()> {
+        self.write_screenshot_scaled(palette, (scale, scale), out)
+    }
+    // Writes the screen to out as a PNG in palette's colors, with each of its pixels x_scale pixels wide and y_scale tall.
+    pub fn write_screenshot_scaled(
+        &self,
+        palette: Palette,
+        scales: (u32, u32),
+        out: impl Write,
+    ) -> io::Result<()> {
+        let capture: Capture = Capture::scaled(self, palette, scales)?;
+        let mut encoder = png::Encoder::new(out, capture.width as u32, capture.height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(capture.palette());
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&capture.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+    // Writes a screenshot to a new file in dir named after the time, e.g. potatocho-1700000000.png, and returns its path.
+    // dir is created if it isn't there.
+    pub fn save_screenshot(&self, palette: Palette, dir: &Path, scale: u32) -> io::Result<PathBuf> {
+        self.save_screenshot_scaled(palette, dir, (scale, scale))
+    }
+    // save_screenshot, with each of the screen's pixels x_scale pixels wide and y_scale tall.
+    pub fn save_screenshot_scaled(
+        &self,
+        palette: Palette,
+        dir: &Path,
+        scales: (u32, u32),
+    ) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path: PathBuf = timestamped_path(dir, "png");
+        let mut file: BufWriter<File> = BufWriter::new(File::create(&path)?);
+        self.write_screenshot_scaled(palette, scales, &mut file)?;
+        file.flush()?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A machine with a 0 drawn in the top left corner.
+    fn zero() -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        // LD V0, 0; LD F, V0; DRW V0, V0, 5; then spin.
+        chip_eight
+            .load_program_from_slice(&[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06])
+            .unwrap();
+        for _ in 0..4 {
+            chip_eight.step().unwrap();
+        }
+        chip_eight
+    }
+
+    // The PNG's width, height, and pixels as RGB.
+    fn decode(png: &[u8]) -> (u32, u32, Vec<u8>) {
+        let mut decoder = png::Decoder::new(png);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels: Vec<u8> = vec![0; reader.output_buffer_size()];
+        let info: png::OutputInfo = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        pixels.truncate(info.buffer_size());
+        (info.width, info.height, pixels)
+    }
+
+    #[test]
+    fn screenshots_decode_to_the_screen_in_the_palette() {
+        let palette: Palette = Palette::theme("green").unwrap();
+        let mut png: Vec<u8> = vec![];
+        zero().write_screenshot(palette, 3, &mut png).unwrap();
+
+        let (width, height, pixels) = decode(&png);
+        assert_eq!((width, height), (64 * 3, 32 * 3));
+        let pixel = |x: u32, y: u32| -> [u8; 3] {
+            let i: usize = ((y * width + x) * 3) as usize;
+            [pixels[i], pixels[i + 1], pixels[i + 2]]
+        };
+        // The 0's top left corner covers the first 3x3 pixels, and its hollow middle starts at (3, 3).
+        for (x, y, expected) in [
+            (0, 0, palette.foreground),
+            (2, 2, palette.foreground),
+            (11, 0, palette.foreground),
+            (12, 0, palette.background),
+            (3, 3, palette.background),
+            (191, 95, palette.background),
+        ] {
+            assert_eq!(pixel(x, y), expected, "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn pixels_can_be_wider_than_they_are_tall() {
+        let mut png: Vec<u8> = vec![];
+        zero()
+            .write_screenshot_scaled(Palette::default(), (2, 1), &mut png)
+            .unwrap();
+
+        let (width, height, pixels) = decode(&png);
+        assert_eq!((width, height), (128, 32));
+        // The first row of the 0 is 4 pixels, so 8 wide here.
+        let lit: Vec<bool> = pixels[..10 * 3]
+            .chunks(3)
+            .map(|rgb| rgb == [0xFF; 3])
+            .collect();
+        assert_eq!(
+            lit,
+            [true, true, true, true, true, true, true, true, false, false]
+        );
+    }
+}
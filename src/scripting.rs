@@ -0,0 +1,272 @@
+// Testing ROMs with Rhai scripts (https://rhai.rs), so a test like "press 5 for 10 frames, wait until a pixel lights up,
+// and check V0" doesn't need recompiling. Scripts can only look at the machine, press keys, and run it; they can't
+// change registers or memory, or get at anything outside the machine. The functions they get are:
+// v(x), i(), pc(), delay_timer(), sound_timer(), peek(addr), pixel(x, y), frame_count(), screen(), screen_hash()
+// press(key), release(key), hold(key, frames)
+// step(), frame(), frames(n), wait_until(|| condition, max_frames)
+// assert(condition, message), assert_eq(actual, expected, message)
+// wait_until runs frames until the condition is true, and returns whether it was before max_frames ran out.
+use crate::{ChipEight, ChipEightError, Keypad, StepOutcome};
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, NativeCallContext, INT};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug)]
+pub enum ScriptError {
+    // The script didn't compile, or went wrong as it ran (calling a function that doesn't exist, say).
+    Script {
+        line: Option<usize>,
+        message: String,
+    },
+    // One of the script's assertions failed.
+    Assertion {
+        line: Option<usize>,
+        message: String,
+    },
+    // The program failed while the script was running it.
+    Emulation {
+        line: Option<usize>,
+        error: ChipEightError,
+    },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line: Option<usize> = match self {
+            ScriptError::Script { line, .. }
+            | ScriptError::Assertion { line, .. }
+            | ScriptError::Emulation { line, .. } => *line,
+        };
+        if let Some(line) = line {
+            write!(f, "line {}: ", line)?;
+        }
+        match self {
+            ScriptError::Script { message, .. } => write!(f, "{}", message),
+            ScriptError::Assertion { message, .. } => write!(f, "assertion failed: {}", message),
+            ScriptError::Emulation { error, .. } => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScriptError::Emulation { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// Why a script was stopped from inside one of the functions it called. Rhai errors can only carry values scripts could
+// use, so the details wait here while the error unwinds the script.
+enum Failure {
+    Assertion(String),
+    Emulation(ChipEightError),
+}
+
+type Shared<T> = Rc<RefCell<T>>;
+
+// Runs a script against chip_eight, which should have the program loaded. The machine is left however the script left
+// it, even if the script failed.
+pub fn run_script(chip_eight: &mut ChipEight, source: &str) -> Result<(), ScriptError> {
+    let machine: Shared<ChipEight> = Rc::new(RefCell::new(std::mem::take(chip_eight)));
+    let failure: Shared<Option<Failure>> = Rc::new(RefCell::new(None));
+
+    let engine: Engine = script_engine(&machine, &failure);
+    let result: Result<(), Box<EvalAltResult>> = engine.run(source);
+    drop(engine);
+    *chip_eight = match Rc::try_unwrap(machine) {
+        Ok(machine) => machine.into_inner(),
+        Err(machine) => machine.borrow().clone(),
+    };
+
+    let mut e: Box<EvalAltResult> = match result {
+        Ok(()) => return Ok(()),
+        Err(e) => e,
+    };
+    let line: Option<usize> = e.position().line();
+    let failure: Option<Failure> = failure.borrow_mut().take();
+    Err(match failure {
+        Some(Failure::Assertion(message)) => ScriptError::Assertion { line, message },
+        Some(Failure::Emulation(error)) => ScriptError::Emulation { line, error },
+        None => ScriptError::Script {
+            line,
+            message: e.clear_position().to_string(),
+        },
+    })
+}
+
+fn script_engine(machine: &Shared<ChipEight>, failure: &Shared<Option<Failure>>) -> Engine {
+    let mut engine: Engine = Engine::new();
+
+    // Looking at the machine.
+    let m = machine.clone();
+    engine.register_fn("v", move |x: INT| -> Result<INT, Box<EvalAltResult>> {
+        match m.borrow().v_registers().get(x as usize) {
+            Some(&v) if x >= 0 => Ok(v as INT),
+            _ => Err(format!("there's no register V{}", x).into()),
+        }
+    });
+    let m = machine.clone();
+    engine.register_fn("i", move || m.borrow().i_register() as INT);
+    let m = machine.clone();
+    engine.register_fn("pc", move || m.borrow().pc() as INT);
+    let m = machine.clone();
+    engine.register_fn("delay_timer", move || m.borrow().delay_timer() as INT);
+    let m = machine.clone();
+    engine.register_fn("sound_timer", move || m.borrow().sound_timer() as INT);
+    let m = machine.clone();
+    engine.register_fn(
+        "peek",
+        move |addr: INT| -> Result<INT, Box<EvalAltResult>> {
+            let machine = m.borrow();
+            match machine.memory().get(addr as usize) {
+                Some(&byte) if addr >= 0 => Ok(byte as INT),
+                _ => Err(format!("address {:#X} is outside memory", addr).into()),
+            }
+        },
+    );
+    let m = machine.clone();
+    engine.register_fn(
+        "pixel",
+        move |x: INT, y: INT| -> Result<bool, Box<EvalAltResult>> {
+            let machine = m.borrow();
+            let (width, height) = machine.resolution();
+            if !(0..width as INT).contains(&x) || !(0..height as INT).contains(&y) {
+                return Err(format!("({}, {}) is off the screen", x, y).into());
+            }
+            Ok(machine.framebuffer()[y as usize][x as usize])
+        },
+    );
+    let m = machine.clone();
+    engine.register_fn("frame_count", move || m.borrow().frame_count() as INT);
+    let m = machine.clone();
+    engine.register_fn("screen", move || m.borrow().screen_to_string());
+    let m = machine.clone();
+    engine.register_fn("screen_hash", move || {
+        format!("{:#018x}", m.borrow().framebuffer_hash())
+    });
+
+    // Pressing keys. Presses and releases take effect on the next step or frame.
+    let m = machine.clone();
+    engine.register_fn("press", move |key: INT| -> Result<(), Box<EvalAltResult>> {
+        m.borrow_mut().keypad_mut().press(key_number(key)?);
+        Ok(())
+    });
+    let m = machine.clone();
+    engine.register_fn(
+        "release",
+        move |key: INT| -> Result<(), Box<EvalAltResult>> {
+            m.borrow_mut().keypad_mut().release(key_number(key)?);
+            Ok(())
+        },
+    );
+    let (m, f) = (machine.clone(), failure.clone());
+    engine.register_fn(
+        "hold",
+        move |key: INT, frames: INT| -> Result<(), Box<EvalAltResult>> {
+            let key: u8 = key_number(key)?;
+            m.borrow_mut().keypad_mut().press(key);
+            for _ in 0..frames {
+                run_frame(&m, &f)?;
+            }
+            m.borrow_mut().keypad_mut().release(key);
+            Ok(())
+        },
+    );
+
+    // Running the machine.
+    let (m, f) = (machine.clone(), failure.clone());
+    engine.register_fn("step", move || -> Result<(), Box<EvalAltResult>> {
+        let result: Result<StepOutcome, ChipEightError> = m.borrow_mut().step();
+        result
+            .map(|_| ())
+            .map_err(|e| fail(&f, Failure::Emulation(e)))
+    });
+    let (m, f) = (machine.clone(), failure.clone());
+    engine.register_fn("frame", move || run_frame(&m, &f));
+    let (m, f) = (machine.clone(), failure.clone());
+    engine.register_fn("frames", move |n: INT| -> Result<(), Box<EvalAltResult>> {
+        for _ in 0..n {
+            run_frame(&m, &f)?;
+        }
+        Ok(())
+    });
+    let (m, f) = (machine.clone(), failure.clone());
+    engine.register_fn(
+        "wait_until",
+        move |context: NativeCallContext,
+              condition: FnPtr,
+              max_frames: INT|
+              -> Result<bool, Box<EvalAltResult>> {
+            for _ in 0..max_frames {
+                if condition.call_within_context::<bool>(&context, ())? {
+                    return Ok(true);
+                }
+                run_frame(&m, &f)?;
+            }
+            condition.call_within_context::<bool>(&context, ())
+        },
+    );
+
+    // Checking.
+    let f = failure.clone();
+    engine.register_fn(
+        "assert",
+        move |condition: bool, message: &str| -> Result<(), Box<EvalAltResult>> {
+            match condition {
+                true => Ok(()),
+                false => Err(fail(&f, Failure::Assertion(message.to_string()))),
+            }
+        },
+    );
+    let f = failure.clone();
+    engine.register_fn(
+        "assert_eq",
+        move |actual: Dynamic,
+              expected: Dynamic,
+              message: &str|
+              -> Result<(), Box<EvalAltResult>> {
+            // Comparing the text saves comparing every pair of types a script could pass.
+            if actual.type_name() == expected.type_name()
+                && actual.to_string() == expected.to_string()
+            {
+                return Ok(());
+            }
+            let message: String = format!("{} (expected {}, got {})", message, expected, actual);
+            Err(fail(&f, Failure::Assertion(message)))
+        },
+    );
+    engine
+}
+
+fn key_number(key: INT) -> Result<u8, Box<EvalAltResult>> {
+    match key {
+        0..=15 => Ok(key as u8),
+        _ => Err(format!("there's no key {}", key).into()),
+    }
+}
+
+// Runs one frame with the keys the script has pressed, like run() does.
+fn run_frame(
+    machine: &Shared<ChipEight>,
+    failure: &Shared<Option<Failure>>,
+) -> Result<(), Box<EvalAltResult>> {
+    let mut machine = machine.borrow_mut();
+    let keys: Keypad = *machine.keypad();
+    match machine.frame(&keys) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(fail(failure, Failure::Emulation(e))),
+    }
+}
+
+// Notes down why the script is being stopped, and returns the error to stop it with.
+fn fail(failure: &Shared<Option<Failure>>, reason: Failure) -> Box<EvalAltResult> {
+    let message: String = match &reason {
+        Failure::Assertion(message) => format!("assertion failed: {}", message),
+        Failure::Emulation(e) => e.to_string(),
+    };
+    *failure.borrow_mut() = Some(reason);
+    message.into()
+}
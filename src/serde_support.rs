@@ -0,0 +1,176 @@
+// Serialization helpers for the parts of ChipEight that serde can't derive on its own.
+//
+// The serialized form is a map keyed by ChipEight's field names, and those names are part of the format: renaming a field
+// breaks every saved file, so don't. Hooks and devices aren't saved; a deserialized machine has
+// none.
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// The screen as 64 rows of two 64-bit masks each, the left half of a row then the right, leftmost pixel in the most
+// significant bit. A [[bool; 128]; 64] would be 8192 separate booleans, and serde only derives arrays of up to 32
+// elements anyway. Files saved before SUPER-CHIP's hires mode have just the left halves, one mask per row.
+pub(crate) mod screen {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        screen: &[[bool; 128]; 64],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let halves: Vec<u64> = screen
+            .iter()
+            .flat_map(|row| row.chunks(64))
+            .map(|half| half.iter().fold(0, |bits, &pixel| bits << 1 | pixel as u64))
+            .collect();
+        halves.serialize(serializer)
+    }
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<[[bool; 128]; 64], D::Error> {
+        let halves: Vec<u64> = Vec::deserialize(deserializer)?;
+        let halves_per_row: usize = match halves.len() {
+            64 => 1,
+            128 => 2,
+            len => return Err(D::Error::invalid_length(len, &"64 or 128 screen row masks")),
+        };
+
+        let mut screen: [[bool; 128]; 64] = [[false; 128]; 64];
+        for (row, bits) in screen.iter_mut().zip(halves.chunks(halves_per_row)) {
+            for (x, pixel) in row.iter_mut().enumerate().take(64 * halves_per_row) {
+                *pixel = bits[x / 64] & (1 << (63 - x % 64)) != 0;
+            }
+        }
+        Ok(screen)
+    }
+}
+
+// How wide the screen was in files saved before it could be anything else.
+pub(crate) fn lores_width() -> usize {
+    64
+}
+
+// The random number generator is saved as the xorshift's state. Custom random sources can't be serialized, as there's no
+// way to know what type to deserialize them as.
+pub(crate) mod rng {
+    use super::*;
+    use crate::random::{Rng, Xorshift};
+    use serde::ser::Error;
+
+    pub(crate) fn serialize<S: Serializer>(rng: &Rng, serializer: S) -> Result<S::Ok, S::Error> {
+        match rng {
+            Rng::Xorshift(xorshift) => xorshift.serialize(serializer),
+            Rng::Custom(_) => Err(S::Error::custom(
+                "custom random sources can't be serialized",
+            )),
+        }
+    }
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rng, D::Error> {
+        Ok(Rng::Xorshift(Xorshift::deserialize(deserializer)?))
+    }
+}
+
+// The font every machine had before load_font.
+pub(crate) fn builtin_font() -> crate::Font {
+    crate::SPRITES
+}
+
+// A deserialized machine hasn't been drawn by anyone yet.
+pub(crate) fn whole_screen() -> Option<crate::DirtyRect> {
+    Some(crate::dirty::WHOLE_SCREEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChipEight, ChipVariant, FontSet, Keypad};
+    use serde_json::{json, Value};
+
+    // A SUPER-CHIP machine that's switched to hires, drawn the 0 in the far right column, and stored some flags.
+    fn machine() -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(3);
+        chip_eight.set_variant(ChipVariant::SuperChipModern);
+        chip_eight
+            .load_program_from_slice(&[
+                0x00, 0xFF, 0x60, 0x7C, 0xA0, 0x00, 0xD0, 0x15, 0xF3, 0x75, 0x12, 0x0A,
+            ])
+            .unwrap();
+        chip_eight.frame(&Keypad::default()).unwrap();
+        chip_eight
+    }
+
+    #[test]
+    fn a_machine_round_trips_through_json() {
+        let chip_eight: ChipEight = machine();
+        let json: String = serde_json::to_string(&chip_eight).unwrap();
+        let back: ChipEight = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, chip_eight);
+        assert_eq!(back.resolution(), (128, 64));
+        assert!(back.pixel(124, 0));
+    }
+
+    #[test]
+    fn fields_are_saved_under_their_names() {
+        let value: Value = serde_json::to_value(machine()).unwrap();
+        for field in [
+            "memory",
+            "screen",
+            "screen_width",
+            "screen_height",
+            "stack",
+            "v_registers",
+            "pc",
+            "i_register",
+            "delay_timer",
+            "sound_timer",
+            "variant",
+            "flags",
+            "rng",
+        ] {
+            assert!(value.get(field).is_some(), "{} is missing", field);
+        }
+        assert_eq!(value["screen_width"], json!(128));
+        assert_eq!(value["screen"].as_array().unwrap().len(), 128);
+        assert_eq!(value["flags"].as_array().unwrap()[..4], [0x7C, 0, 0, 0]);
+    }
+
+    #[test]
+    fn files_from_before_super_chip_still_load() {
+        // Saved by the first version with serde support, after running A000 D005 6007: the 0 drawn in the top left corner.
+        // Those files had only the left half of each row, and no screen width, flags, or font.
+        let old: &str = include_str!("../tests/fixtures/saved_before_super_chip.json");
+        let back: ChipEight = serde_json::from_str(old).unwrap();
+        assert_eq!(back.resolution(), (64, 32));
+        assert_eq!(back.pc(), 0x206);
+        assert_eq!(back.v_registers()[0], 0x07);
+        assert_eq!(back.font(), FontSet::Classic.glyphs());
+        let zero: [&str; 5] = ["####", "#..#", "#..#", "#..#", "####"];
+        for (y, row) in zero.iter().enumerate() {
+            for (x, pixel) in row.chars().enumerate() {
+                assert_eq!(back.pixel(x, y), pixel == '#', "pixel ({}, {})", x, y);
+            }
+        }
+        assert!(!back.pixel(4, 0));
+        assert!(
+            back.framebuffer()[0][64..].iter().all(|&pixel| !pixel),
+            "the right half of the row is off"
+        );
+    }
+
+    #[test]
+    fn files_from_before_load_font_get_the_built_in_font() {
+        let chip_eight: ChipEight = machine();
+        let mut old: Value = serde_json::to_value(&chip_eight).unwrap();
+        old.as_object_mut().unwrap().remove("font");
+        let back: ChipEight = serde_json::from_value(old).unwrap();
+        assert_eq!(back.font(), FontSet::Classic.glyphs());
+        assert_eq!(back, chip_eight);
+    }
+
+    #[test]
+    fn the_wrong_number_of_rows_is_rejected() {
+        let mut value: Value = serde_json::to_value(machine()).unwrap();
+        value["screen"] = json!([0, 0, 0]);
+        let error: String = serde_json::from_value::<ChipEight>(value)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("64 or 128 screen row masks"), "{}", error);
+    }
+}
@@ -0,0 +1,553 @@
+// Per-ROM settings. These can come from several places (the ROM database, a sidecar file next to the ROM, the command line),
+// so every field is optional and layers are stacked with layered_over: CLI > sidecar > ROM database > defaults.
+#[cfg(feature = "sdl")]
+use crate::keymap::Keymap;
+use crate::toml_lite::{self, TomlError};
+use crate::{
+    parse_color, ChipEight, ChipVariant, Palette, RomEntry, Sound, TimingMode, Waveform,
+    MAX_VOLUME, THEMES, TONE_HZ_RANGE,
+};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Settings {
+    pub variant: Option<ChipVariant>,
+    // Individual quirks by name (see Quirks::NAMES). These are applied on top of the variant's preset, so a layer that
+    // only changes the variant still keeps quirk overrides from the layers below it.
+    pub quirks: Vec<(String, bool)>,
+    pub instructions_per_frame: Option<u32>,
+    pub timing: Option<TimingMode>,
+    pub foreground: Option<[u8; 3]>,
+    pub background: Option<[u8; 3]>,
+    // (SDL key name, Chip-8 key) pairs.
+    pub key_remaps: Vec<(String, u8)>,
+    // Whether the SDL frontend scales the screen up smoothly rather than keeping its pixels sharp. apply() leaves this to
+    // the frontend, since it isn't the machine's.
+    pub smooth: Option<bool>,
+    // The shape of the SDL frontend's buzzer tone. apply() leaves this to the frontend too.
+    pub wave: Option<Waveform>,
+    // The buzzer's pitch in Hz, and its volume from 0 to MAX_VOLUME. Frontend settings too, like wave.
+    pub tone_hz: Option<f32>,
+    pub volume: Option<u8>,
+    // Whether the buzzer starts out muted, which is the frontend's business too.
+    pub mute: Option<bool>,
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, TomlError),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Io(path, e) => write!(f, "couldn't read {}: {}", path.display(), e),
+            SettingsError::Parse(path, e) => write!(f, "{}, {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl Settings {
+    // The settings a ROM database entry recommends. Only quirks that differ from the entry's variant preset are recorded.
+    pub fn from_rom_entry(entry: &RomEntry) -> Self {
+        let mut quirks: Vec<(String, bool)> = vec![];
+
+        if let Some(entry_quirks) = entry.quirks {
+            let preset = entry.variant.quirks();
+            for name in crate::Quirks::NAMES {
+                if entry_quirks.get(name) != preset.get(name) {
+                    quirks.push((name.to_string(), entry_quirks.get(name) == Some(true)));
+                }
+            }
+        }
+
+        Settings {
+            variant: Some(entry.variant),
+            quirks,
+            instructions_per_frame: entry.instructions_per_frame,
+            ..Settings::default()
+        }
+    }
+    // Sidecar files live next to the ROM with .toml tacked onto the full file name, e.g. PONG.ch8 -> PONG.ch8.toml.
+    pub fn sidecar_path(rom: &Path) -> PathBuf {
+        let mut name = rom.as_os_str().to_owned();
+        name.push(".toml");
+        PathBuf::from(name)
+    }
+    // Loads the sidecar file for a ROM. A missing sidecar isn't an error, but one that can't be read or parsed is.
+    pub fn load_sidecar(rom: &Path) -> Result<Option<Self>, SettingsError> {
+        Self::load(&Self::sidecar_path(rom))
+    }
+    // Loads a settings file that doesn't have to exist, like a sidecar or the user's config.toml.
+    pub fn load(path: &Path) -> Result<Option<Self>, SettingsError> {
+        let src: String = match std::fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(SettingsError::Io(path.to_path_buf(), e)),
+        };
+        match Self::from_toml(&src) {
+            Ok(settings) => Ok(Some(settings)),
+            Err(e) => Err(SettingsError::Parse(path.to_path_buf(), e)),
+        }
+    }
+    // Sets theme = "NAME" in a settings file, creating the file if it doesn't exist and keeping the rest of it. Its own
+    // foreground and background go, since they'd override the theme.
+    pub fn save_theme(path: &Path, name: &str) -> std::io::Result<()> {
+        save_top_level(
+            path,
+            &format!("theme = \"{}\"", name),
+            &["theme", "foreground", "background"],
+        )
+    }
+    // Sets smooth = true or false in a settings file, the same way save_theme() sets the theme.
+    pub fn save_smooth(path: &Path, smooth: bool) -> std::io::Result<()> {
+        save_top_level(path, &format!("smooth = {}", smooth), &["smooth"])
+    }
+    // Sets wave = "..." in a settings file, the same way save_theme() sets the theme.
+    pub fn save_wave(path: &Path, wave: Waveform) -> std::io::Result<()> {
+        save_top_level(path, &format!("wave = \"{}\"", wave), &["wave"])
+    }
+    // Sets volume = N in a settings file, the same way save_theme() sets the theme.
+    pub fn save_volume(path: &Path, volume: u8) -> std::io::Result<()> {
+        save_top_level(path, &format!("volume = {}", volume), &["volume"])
+    }
+    // Sets mute = true or false in a settings file, the same way save_theme() sets the theme.
+    pub fn save_mute(path: &Path, mute: bool) -> std::io::Result<()> {
+        save_top_level(path, &format!("mute = {}", mute), &["mute"])
+    }
+    // Parses a settings file:
+    //
+    //   variant = "schip-modern"
+    //   instructions_per_frame = 20
+    //   timing = "vip"
+    //   foreground = "#33FF66"
+    //   background = "#001100"
+    //   theme = "amber"
+    //   smooth = true
+    //   wave = "triangle"
+    //   tone_hz = 440
+    //   volume = 40
+    //   mute = false
+    //
+    //   [quirks]
+    //   shift_uses_vy = true
+    //
+    //   [keys]
+    //   Up = 0x5
+    //   Down = 0x8
+    pub fn from_toml(src: &str) -> Result<Self, TomlError> {
+        let mut settings = Settings::default();
+
+        for table in toml_lite::parse(src)? {
+            for entry in &table.entries {
+                let invalid = |expected: &str| {
+                    TomlError::new(
+                        entry.line,
+                        format!("'{}' should be {}", entry.key, expected),
+                    )
+                };
+                match (table.name.as_str(), entry.key.as_str()) {
+                    ("", "variant") => {
+                        settings.variant = Some(
+                            entry
+                                .value
+                                .as_str()
+                                .ok_or_else(|| invalid("a string"))?
+                                .parse()
+                                .map_err(|e: String| TomlError::new(entry.line, e))?,
+                        )
+                    }
+                    ("", "instructions_per_frame") => {
+                        settings.instructions_per_frame = Some(
+                            entry
+                                .value
+                                .as_integer()
+                                .and_then(|i| u32::try_from(i).ok())
+                                .filter(|&i| i > 0)
+                                .ok_or_else(|| invalid("a positive integer"))?,
+                        )
+                    }
+                    ("", "timing") => {
+                        settings.timing = Some(
+                            entry
+                                .value
+                                .as_str()
+                                .ok_or_else(|| invalid("a string"))?
+                                .parse()
+                                .map_err(|e: String| TomlError::new(entry.line, e))?,
+                        )
+                    }
+                    ("", "foreground") | ("", "background") => {
+                        let color: [u8; 3] = parse_color(
+                            entry
+                                .value
+                                .as_str()
+                                .ok_or_else(|| invalid("a color like \"#RRGGBB\" or \"#RGB\""))?,
+                        )
+                        .map_err(|e| TomlError::new(entry.line, e))?;
+                        if entry.key == "foreground" {
+                            settings.foreground = Some(color);
+                        } else {
+                            settings.background = Some(color);
+                        }
+                    }
+                    // A theme sets both colors. A foreground or background further down overrides one of them.
+                    ("", "theme") => {
+                        let name: &str = entry.value.as_str().ok_or_else(|| invalid("a string"))?;
+                        let palette: Palette = Palette::theme(name).ok_or_else(|| {
+                            let names: Vec<&str> = THEMES.iter().map(|(name, _)| *name).collect();
+                            invalid(&format!("one of {}", names.join(", ")))
+                        })?;
+                        settings.foreground = Some(palette.foreground);
+                        settings.background = Some(palette.background);
+                    }
+                    ("", "smooth") => {
+                        settings.smooth = Some(
+                            entry
+                                .value
+                                .as_bool()
+                                .ok_or_else(|| invalid("true or false"))?,
+                        )
+                    }
+                    ("", "mute") => {
+                        settings.mute = Some(
+                            entry
+                                .value
+                                .as_bool()
+                                .ok_or_else(|| invalid("true or false"))?,
+                        )
+                    }
+                    ("", "wave") => {
+                        let name: &str = entry.value.as_str().ok_or_else(|| invalid("a string"))?;
+                        settings.wave = Some(name.parse().map_err(|_| {
+                            invalid("one of square, sine, triangle, saw, or noise")
+                        })?);
+                    }
+                    ("", "tone_hz") => {
+                        settings.tone_hz = Some(
+                            entry
+                                .value
+                                .as_float()
+                                .map(|hz| hz as f32)
+                                .filter(|hz| TONE_HZ_RANGE.contains(hz))
+                                .ok_or_else(|| {
+                                    invalid(&format!(
+                                        "a frequency between {} and {}",
+                                        TONE_HZ_RANGE.start(),
+                                        TONE_HZ_RANGE.end()
+                                    ))
+                                })?,
+                        )
+                    }
+                    ("", "volume") => {
+                        settings.volume = Some(
+                            entry
+                                .value
+                                .as_integer()
+                                .and_then(|volume| u8::try_from(volume).ok())
+                                .filter(|&volume| volume <= MAX_VOLUME)
+                                .ok_or_else(|| {
+                                    invalid(&format!("an integer between 0 and {}", MAX_VOLUME))
+                                })?,
+                        )
+                    }
+                    ("quirks", name) if crate::Quirks::NAMES.contains(&name) => {
+                        settings.quirks.push((
+                            name.to_string(),
+                            entry
+                                .value
+                                .as_bool()
+                                .ok_or_else(|| invalid("true or false"))?,
+                        ))
+                    }
+                    ("keys", name) => settings.key_remaps.push((
+                        name.to_string(),
+                        entry
+                            .value
+                            .as_integer()
+                            .filter(|&key| (0..=0xF).contains(&key))
+                            .ok_or_else(|| invalid("a Chip-8 key between 0x0 and 0xF"))?
+                            as u8,
+                    )),
+                    ("", key) | ("quirks", key) => {
+                        return Err(TomlError::new(
+                            entry.line,
+                            format!("unknown setting '{}'", key),
+                        ))
+                    }
+                    (name, _) => {
+                        return Err(TomlError::new(
+                            entry.line,
+                            format!("unknown table [{}]", name),
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(settings)
+    }
+    // Stacks self on top of lower, so anything set in self wins.
+    pub fn layered_over(self, lower: Settings) -> Settings {
+        let mut quirks: Vec<(String, bool)> = lower.quirks;
+        quirks.extend(self.quirks);
+        let mut key_remaps: Vec<(String, u8)> = lower.key_remaps;
+        key_remaps.extend(self.key_remaps);
+
+        Settings {
+            variant: self.variant.or(lower.variant),
+            quirks,
+            instructions_per_frame: self.instructions_per_frame.or(lower.instructions_per_frame),
+            timing: self.timing.or(lower.timing),
+            foreground: self.foreground.or(lower.foreground),
+            background: self.background.or(lower.background),
+            key_remaps,
+            smooth: self.smooth.or(lower.smooth),
+            wave: self.wave.or(lower.wave),
+            tone_hz: self.tone_hz.or(lower.tone_hz),
+            volume: self.volume.or(lower.volume),
+            mute: self.mute.or(lower.mute),
+        }
+    }
+    // The SDL frontend's keymap: the DEFAULT_KEYMAP with key_remaps applied in order, so a remap in a higher layer wins.
+    #[cfg(feature = "sdl")]
+    pub fn keymap(&self) -> Result<Keymap, String> {
+        let mut keymap: Keymap = Keymap::default();
+        for (key_name, chip_key) in &self.key_remaps {
+            keymap.remap(key_name, *chip_key)?;
+        }
+        Ok(keymap)
+    }
+    // The colors the frontend draws the screen in, with the default ones for any the settings don't give.
+    pub fn palette(&self) -> Palette {
+        let default: Palette = Palette::default();
+        Palette {
+            foreground: self.foreground.unwrap_or(default.foreground),
+            background: self.background.unwrap_or(default.background),
+        }
+    }
+    // How the frontend's buzzer sounds, with the default for anything the settings don't say. The pitch is kept within
+    // TONE_HZ_RANGE and the volume to MAX_VOLUME.
+    pub fn sound(&self) -> Sound {
+        let default: Sound = Sound::default();
+        Sound {
+            waveform: self.wave.unwrap_or(default.waveform),
+            frequency: self.tone_hz.map_or(default.frequency, |hz| {
+                hz.clamp(*TONE_HZ_RANGE.start(), *TONE_HZ_RANGE.end())
+            }),
+            volume: self
+                .volume
+                .map_or(default.volume, |volume| volume.min(MAX_VOLUME)),
+            muted: self.mute.unwrap_or(default.muted),
+        }
+    }
+    pub fn apply(&self, chip_eight: &mut ChipEight) -> Result<(), String> {
+        let variant: ChipVariant = self.variant.unwrap_or_else(|| chip_eight.variant());
+        chip_eight.set_variant(variant);
+
+        if self.quirks.is_empty() {
+            chip_eight.set_quirks(None);
+        } else {
+            let mut quirks = variant.quirks();
+            for (name, enabled) in &self.quirks {
+                if !quirks.set(name, *enabled) {
+                    return Err(format!("unknown quirk '{}'", name));
+                }
+            }
+            chip_eight.set_quirks(Some(quirks));
+        }
+        if let Some(instructions_per_frame) = self.instructions_per_frame {
+            chip_eight.set_instructions_per_frame(instructions_per_frame);
+        }
+        if let Some(timing) = self.timing {
+            chip_eight.set_timing_mode(timing);
+        }
+        Ok(())
+    }
+}
+
+// Puts line at the top of a settings file, creating the file if it doesn't exist, in place of any top-level settings
+// named in replaces. The rest of the file is kept as it was.
+fn save_top_level(path: &Path, line: &str, replaces: &[&str]) -> std::io::Result<()> {
+    let src: String = match std::fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let mut out: String = format!("{}\n", line);
+    let mut top_level: bool = true;
+    for line in src.lines() {
+        let trimmed: &str = line.trim_start();
+        top_level &= !trimmed.starts_with('[');
+        let key: Option<&str> = trimmed.split_once('=').map(|(key, _)| key.trim());
+        if top_level && key.is_some_and(|key| replaces.contains(&key)) {
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Quirks;
+    #[cfg(feature = "sdl")]
+    use sdl2::keyboard::Keycode;
+
+    // A database entry for a SUPER-CHIP game that needs Bxnn, running at 30 instructions a frame.
+    fn database_entry() -> Settings {
+        let mut quirks: Quirks = ChipVariant::SuperChipModern.quirks();
+        quirks.jump_uses_vx = !quirks.jump_uses_vx;
+        Settings::from_rom_entry(&RomEntry {
+            crc32: 0,
+            title: "Test".to_string(),
+            variant: ChipVariant::SuperChipModern,
+            quirks: Some(quirks),
+            instructions_per_frame: Some(30),
+        })
+    }
+
+    #[test]
+    fn database_entry_only_records_quirks_off_its_preset() {
+        let settings: Settings = database_entry();
+        let jump_uses_vx: bool = !ChipVariant::SuperChipModern.quirks().jump_uses_vx;
+        assert_eq!(
+            settings.quirks,
+            vec![("jump_uses_vx".to_string(), jump_uses_vx)]
+        );
+    }
+
+    #[test]
+    fn sidecar_wins_where_it_says_something() {
+        let sidecar: Settings =
+            Settings::from_toml("instructions_per_frame = 15\nforeground = \"#33FF66\"\n").unwrap();
+        let settings: Settings = sidecar.layered_over(database_entry());
+        assert_eq!(settings.instructions_per_frame, Some(15));
+        assert_eq!(settings.foreground, Some([0x33, 0xFF, 0x66]));
+        // The sidecar didn't pick a variant, so the database's stands.
+        assert_eq!(settings.variant, Some(ChipVariant::SuperChipModern));
+    }
+
+    #[test]
+    fn sidecar_variant_keeps_database_quirks() {
+        let sidecar: Settings = Settings::from_toml("variant = \"chip8\"\n").unwrap();
+        let settings: Settings = sidecar.layered_over(database_entry());
+        let mut chip_eight: ChipEight = ChipEight::new();
+        settings.apply(&mut chip_eight).unwrap();
+
+        let mut expected: Quirks = ChipVariant::Chip8.quirks();
+        expected.jump_uses_vx = !ChipVariant::SuperChipModern.quirks().jump_uses_vx;
+        assert_eq!(chip_eight.variant(), ChipVariant::Chip8);
+        assert_eq!(chip_eight.quirks(), expected);
+    }
+
+    #[test]
+    fn sidecar_quirk_overrides_database_quirk() {
+        let jump_uses_vx: bool = ChipVariant::SuperChipModern.quirks().jump_uses_vx;
+        let sidecar: Settings =
+            Settings::from_toml(&format!("[quirks]\njump_uses_vx = {}\n", jump_uses_vx)).unwrap();
+        let settings: Settings = sidecar.layered_over(database_entry());
+        let mut chip_eight: ChipEight = ChipEight::new();
+        settings.apply(&mut chip_eight).unwrap();
+        assert_eq!(chip_eight.quirks(), ChipVariant::SuperChipModern.quirks());
+    }
+
+    #[test]
+    fn command_line_wins_over_sidecar() {
+        let sidecar: Settings = Settings::from_toml("instructions_per_frame = 15\n").unwrap();
+        let command_line: Settings = Settings {
+            instructions_per_frame: Some(1000),
+            ..Settings::default()
+        };
+        let settings: Settings = command_line.layered_over(sidecar.layered_over(database_entry()));
+        assert_eq!(settings.instructions_per_frame, Some(1000));
+    }
+
+    #[test]
+    fn sound_fills_in_what_the_settings_dont_say() {
+        let settings: Settings =
+            Settings::from_toml("wave = \"sine\"\ntone_hz = 440\nmute = true\n").unwrap();
+        let sound: Sound = settings.sound();
+        assert_eq!(sound.waveform, Waveform::Sine);
+        assert_eq!(sound.frequency, 440.0);
+        assert!(sound.muted);
+        assert_eq!(sound.volume, Sound::default().volume);
+
+        let loud: Settings = Settings {
+            tone_hz: Some(1_000_000.0),
+            volume: Some(255),
+            ..Settings::default()
+        };
+        assert_eq!(loud.sound().frequency, *TONE_HZ_RANGE.end());
+        assert_eq!(loud.sound().volume, MAX_VOLUME);
+    }
+
+    #[test]
+    fn tone_hz_and_volume_are_checked() {
+        assert_eq!(
+            Settings::from_toml("tone_hz = 261.63\nvolume = 0\n")
+                .map(|settings| (settings.tone_hz, settings.volume)),
+            Ok((Some(261.63), Some(0)))
+        );
+        for src in [
+            "tone_hz = 5",
+            "tone_hz = \"high\"",
+            "volume = 101",
+            "volume = -1",
+            "volume = 2.5",
+        ] {
+            assert!(Settings::from_toml(src).is_err(), "{}", src);
+        }
+    }
+
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn sidecar_keys_rebind_over_lower_layers() {
+        let user: Settings = Settings::from_toml("[keys]\nW = 0x1\n").unwrap();
+        let sidecar: Settings = Settings::from_toml("[keys]\nW = 0x2\nUp = 0x5\n").unwrap();
+        let keymap: Keymap = sidecar.layered_over(user).keymap().unwrap();
+        assert_eq!(keymap.keys_bound_to(Keycode::W), vec![0x2]);
+        assert_eq!(keymap.keys_bound_to(Keycode::Up), vec![0x5]);
+        // Keys nobody rebound keep the defaults.
+        assert_eq!(keymap.keys_bound_to(Keycode::Q), vec![0x4]);
+    }
+
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn unknown_key_is_an_error() {
+        let sidecar: Settings = Settings::from_toml("[keys]\nNope = 0x1\n").unwrap();
+        assert_eq!(sidecar.keymap(), Err("unknown key 'Nope'".to_string()));
+    }
+
+    #[test]
+    fn saving_replaces_only_its_own_top_level_settings() {
+        let dir: std::path::PathBuf =
+            std::env::temp_dir().join(format!("potatocho-settings-test-{}", std::process::id()));
+        let path: std::path::PathBuf = dir.join("config.toml");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            &path,
+            "foreground = \"#33FF66\"\nsmooth = false\n\n[keys]\nsmooth = 0x1\n",
+        )
+        .unwrap();
+        Settings::save_smooth(&path, true).unwrap();
+        Settings::save_theme(&path, "amber").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "theme = \"amber\"\nsmooth = true\n\n[keys]\nsmooth = 0x1\n"
+        );
+        let settings: Settings =
+            Settings::from_toml(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(settings.smooth, Some(true));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+; The splash screen's demo: the name across the top, underlined, and a ball bouncing around under it. It's written for
+; PotatOcho and is in the public domain. It runs on any CHIP-8, and uses nothing but the original instructions.
+
+        .org 0x200
+start:  CLS
+        ; POTATOCHO, in letters like the built-in font's digits, 5 pixels apart.
+        LD V0, 10
+        LD V1, 4
+        LD I, letter_p
+        DRW V0, V1, 5
+        ADD V0, 5
+        LD I, letter_o
+        DRW V0, V1, 5
+        ADD V0, 5
+        LD I, letter_t
+        DRW V0, V1, 5
+        ADD V0, 5
+        LD I, letter_a
+        DRW V0, V1, 5
+        ADD V0, 5
+        LD I, letter_t
+        DRW V0, V1, 5
+        ADD V0, 5
+        LD I, letter_o
+        DRW V0, V1, 5
+        ADD V0, 5
+        LD I, letter_c
+        DRW V0, V1, 5
+        ADD V0, 5
+        LD I, letter_h
+        DRW V0, V1, 5
+        ADD V0, 5
+        LD I, letter_o
+        DRW V0, V1, 5
+
+        ; A line under it, 8 pixels at a time from x 8 to 56.
+        LD V0, 8
+        LD V1, 11
+        LD I, line
+underline:
+        DRW V0, V1, 1
+        ADD V0, 8
+        SE V0, 56
+        JP underline
+
+        ; The ball is VA, VB, moving VC, VD a step (1 or -1), inside x 0 to 62 and y 14 to 30.
+        LD VA, 5
+        LD VB, 20
+        LD VC, 1
+        LD VD, 1
+        LD I, ball
+        DRW VA, VB, 2
+
+        ; Every other frame, the ball is erased, moved, turned around at the edges, and drawn again.
+frame:  LD V3, 2
+        LD DT, V3
+wait:   LD V3, DT
+        SE V3, 0
+        JP wait
+        LD I, ball
+        DRW VA, VB, 2
+        ADD VA, VC
+        ADD VB, VD
+        SNE VA, 0
+        LD VC, 1
+        SNE VA, 62
+        LD VC, 0xFF
+        SNE VB, 14
+        LD VD, 1
+        SNE VB, 30
+        LD VD, 0xFF
+        DRW VA, VB, 2
+        JP frame
+
+letter_p:       DB 0xF0 0x90 0xF0 0x80 0x80
+letter_o:       DB 0xF0 0x90 0x90 0x90 0xF0
+letter_t:       DB 0xF0 0x40 0x40 0x40 0x40
+letter_a:       DB 0xF0 0x90 0xF0 0x90 0x90
+letter_c:       DB 0xF0 0x80 0x80 0x80 0xF0
+letter_h:       DB 0x90 0x90 0xF0 0x90 0x90
+line:           DB 0xFF
+ball:           DB 0xC0 0xC0
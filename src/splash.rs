@@ -0,0 +1,115 @@
+// The splash screen, for when PotatOcho starts without a ROM and the file dialog is cancelled. It runs a little demo
+// (splash.asm, assembled when it's needed) the same way any other program runs, with a hint under it, and waits for a
+// ROM: Ctrl+O opens the dialog again, and a file dropped on the window is opened as it is.
+use crate::backend::{Input, NullAudio};
+use crate::frontend_sdl::{SdlDisplay, SdlInput};
+use crate::{assemble, ChipEight, ExitReason};
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod};
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+
+// The demo's source.
+const SPLASH_SOURCE: &str = include_str!("splash.asm");
+
+// The lines shown under the demo.
+pub const SPLASH_HINT: [&str; 2] = ["PRESS CTRL+O TO OPEN A ROM", "DRAG AND DROP ALSO WORKS"];
+
+// The demo, assembled.
+pub fn splash_rom() -> Vec<u8> {
+    assemble(SPLASH_SOURCE).expect("the splash screen's demo assembles")
+}
+
+// Runs the demo on display until a ROM is picked with pick (which Ctrl+O calls, and which can return None if nothing was
+// picked) or dropped on the window, and returns its path. Returns None if the window is closed (or Escape is pressed)
+// first. The display should have SPLASH_HINT as its footer.
+pub fn splash_screen(
+    display: &mut SdlDisplay,
+    event_pump: &mut sdl2::EventPump,
+    pick: impl FnMut() -> Option<PathBuf>,
+) -> Result<Option<PathBuf>, String> {
+    let mut chip_eight: ChipEight = ChipEight::new();
+    chip_eight
+        .load_program(splash_rom())
+        .map_err(|e| e.to_string())?;
+    let mut input: SplashInput<'_, _> = SplashInput {
+        input: SdlInput::new(event_pump, display),
+        pick,
+        picked: None,
+    };
+    match chip_eight.run(display, &mut input, &mut NullAudio) {
+        Ok(ExitReason::UserQuit) => Ok(input.picked),
+        Ok(_) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// The keyboard as SdlInput has it (so F11 and the like still work), except that Ctrl+O and dropped files pick a ROM,
+// which stops the demo.
+struct SplashInput<'a, P> {
+    input: SdlInput<'a>,
+    pick: P,
+    picked: Option<PathBuf>,
+}
+
+impl<P: FnMut() -> Option<PathBuf>> Input for SplashInput<'_, P> {
+    fn poll(&mut self, chip_eight: &mut ChipEight) -> ControlFlow<()> {
+        let (pick, picked) = (&mut self.pick, &mut self.picked);
+        let flow: ControlFlow<()> = self.input.poll_except(chip_eight, |event| match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::O),
+                keymod,
+                repeat: false,
+                ..
+            } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                if picked.is_none() {
+                    *picked = pick();
+                }
+                true
+            }
+            Event::DropFile { filename, .. } => {
+                *picked = Some(PathBuf::from(filename));
+                true
+            }
+            _ => false,
+        });
+        if self.picked.is_some() {
+            return ControlFlow::Break(());
+        }
+        flow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keypad;
+
+    // The lit pixels under the underline, which should be the ball and nothing else.
+    fn ball(chip_eight: &ChipEight) -> Vec<(usize, usize)> {
+        (12..32)
+            .flat_map(|y| (0..64).map(move |x| (x, y)))
+            .filter(|&(x, y)| chip_eight.framebuffer()[y][x])
+            .collect()
+    }
+
+    #[test]
+    fn the_demo_draws_the_name_and_bounces_the_ball() {
+        let mut chip_eight: ChipEight = ChipEight::new();
+        chip_eight.load_program(splash_rom()).unwrap();
+        for _ in 0..200 {
+            chip_eight.frame(&Keypad::default()).unwrap();
+        }
+        // The P's top left corner, and the whole underline.
+        assert!(chip_eight.framebuffer()[4][10]);
+        assert!((8..56).all(|x| chip_eight.framebuffer()[11][x]));
+        let before: Vec<(usize, usize)> = ball(&chip_eight);
+        assert_eq!(before.len(), 4);
+        for _ in 0..200 {
+            chip_eight.frame(&Keypad::default()).unwrap();
+        }
+        let after: Vec<(usize, usize)> = ball(&chip_eight);
+        assert_eq!(after.len(), 4);
+        assert_ne!(before, after);
+    }
+}
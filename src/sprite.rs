@@ -0,0 +1,103 @@
+// Sprites as Dxyn sees them: each byte of memory is a row of 8 pixels, with the high bit on the left. The same functions
+// back the sprite viewer, so what it shows is exactly what a program drawing from that address would get.
+
+// One row of a sprite.
+pub fn sprite_row(byte: u8) -> [bool; 8] {
+    let mut row: [bool; 8] = [false; 8];
+    for (x, pixel) in row.iter_mut().enumerate() {
+        *pixel = byte & (0x80 >> x) != 0;
+    }
+    row
+}
+
+// The 8 pixel wide sprite height rows tall at addr. Like the program's own accesses, reads past the end of memory wrap
+// around to the start.
+#[cfg(feature = "std")]
+pub fn render_sprite(memory: &[u8], addr: usize, height: usize) -> Vec<[bool; 8]> {
+    if memory.is_empty() {
+        return vec![[false; 8]; height];
+    }
+    (0..height)
+        .map(|row| sprite_row(memory[(addr + row) % memory.len()]))
+        .collect()
+}
+
+// The 16x16 sprite at addr that SUPER-CHIP draws in hires with Dxy0: 32 bytes, two to a row.
+#[cfg(feature = "std")]
+pub fn render_sprite_16(memory: &[u8], addr: usize) -> Vec<[bool; 16]> {
+    render_sprite(memory, addr, 32)
+        .chunks(2)
+        .map(|halves| {
+            let mut row: [bool; 16] = [false; 16];
+            row[..8].copy_from_slice(&halves[0]);
+            row[8..].copy_from_slice(&halves[1]);
+            row
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_read_high_bit_first() {
+        for (byte, expected) in [
+            (0x00, [false; 8]),
+            (0xFF, [true; 8]),
+            (
+                0x80,
+                [true, false, false, false, false, false, false, false],
+            ),
+            (0x81, [true, false, false, false, false, false, false, true]),
+            (0x5A, [false, true, false, true, true, false, true, false]),
+        ] {
+            assert_eq!(sprite_row(byte), expected, "byte {:#04X}", byte);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sprites_wrap_past_the_end_of_memory() {
+        let mut memory: [u8; 16] = [0; 16];
+        memory[14] = 0xF0;
+        memory[15] = 0x0F;
+        memory[0] = 0xAA;
+
+        let sprite: Vec<[bool; 8]> = render_sprite(&memory, 14, 4);
+        let expected: Vec<[bool; 8]> = vec![
+            sprite_row(0xF0),
+            sprite_row(0x0F),
+            sprite_row(0xAA),
+            sprite_row(0x00),
+        ];
+        assert_eq!(sprite, expected);
+
+        // An address past the end wraps too, the way I does.
+        assert_eq!(render_sprite(&memory, 16, 1), vec![sprite_row(0xAA)]);
+        assert_eq!(render_sprite(&[], 0, 2), vec![[false; 8]; 2]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sixteen_wide_sprites_take_two_bytes_a_row() {
+        let mut memory: [u8; 64] = [0; 64];
+        for row in 0..16 {
+            memory[row * 2] = 0xFF;
+            memory[row * 2 + 1] = 1 << (row % 8);
+        }
+
+        let sprite: Vec<[bool; 16]> = render_sprite_16(&memory, 0);
+        assert_eq!(sprite.len(), 16);
+        for (y, row) in sprite.iter().enumerate() {
+            let lit: Vec<usize> = (0..16).filter(|&x| row[x]).collect();
+            let mut expected: Vec<usize> = (0..8).collect();
+            expected.push(15 - y % 8);
+            assert_eq!(lit, expected, "row {}", y);
+        }
+
+        // The last row's right half comes from the start of memory.
+        let tail: Vec<[bool; 16]> = render_sprite_16(&memory, 33);
+        assert_eq!(tail[15][8..], sprite_row(0xFF));
+    }
+}
@@ -0,0 +1,192 @@
+// Looking at a program's graphics: a window full of memory drawn as sprites, one after another, with the address of each
+// above it. Nothing says where a ROM's sprites are or how tall they are, so the keys move through memory and change the
+// height until the graphics line up.
+use crate::debug_hud::{self, CHAR_ADVANCE, GLYPH_WIDTH, LINE_HEIGHT};
+use crate::{render_sprite, render_sprite_16, ChipEight, Palette};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+// The size of the view in logical pixels, and the room each sprite gets: enough for a 16x16 sprite or a four digit
+// address, plus a gap.
+const VIEW_WIDTH: usize = 320;
+const VIEW_HEIGHT: usize = 180;
+const CELL_WIDTH: usize = 20;
+const GAP: usize = 3;
+
+pub struct SpriteViewer {
+    // The address of the first sprite shown.
+    addr: usize,
+    // How many rows (bytes) each sprite is assumed to have, from 1 to 15.
+    height: usize,
+    // Whether sprites are read as SUPER-CHIP's 16x16 ones instead, which ignores height.
+    big: bool,
+    // The colors the sprites are drawn in.
+    palette: Palette,
+}
+
+impl SpriteViewer {
+    pub fn new(addr: usize) -> Self {
+        SpriteViewer {
+            addr,
+            height: 8,
+            big: false,
+            palette: Palette::default(),
+        }
+    }
+    // Draws the sprites in palette's colors rather than white on black.
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn big(&self) -> bool {
+        self.big
+    }
+    // Shows the machine's memory until the window is closed or Escape is pressed. + and - change the sprite height, Left and
+    // Right move a byte through memory, Up and Down a row of sprites, Page Up and Page Down a screenful, and H switches
+    // between 8xN and 16x16 sprites.
+    pub fn run(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        event_pump: &mut sdl2::EventPump,
+        chip_eight: &ChipEight,
+    ) -> Result<(), String> {
+        loop {
+            self.draw(canvas, chip_eight)?;
+            match event_pump.wait_event() {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return Ok(()),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } => self.handle_key(keycode, chip_eight.memory().len()),
+                _ => {}
+            }
+        }
+    }
+    // Moves the view or changes how sprites are read, for the keys run() describes.
+    pub fn handle_key(&mut self, keycode: Keycode, memory_size: usize) {
+        let (columns, rows) = self.grid();
+        let addr: isize = self.addr as isize;
+        let sprite: isize = self.sprite_size() as isize;
+
+        let addr: isize = match keycode {
+            Keycode::Equals | Keycode::Plus | Keycode::KpPlus => {
+                self.height = (self.height + 1).min(15);
+                addr
+            }
+            Keycode::Minus | Keycode::KpMinus => {
+                self.height = (self.height - 1).max(1);
+                addr
+            }
+            Keycode::H => {
+                self.big = !self.big;
+                addr
+            }
+            Keycode::Left => addr - 1,
+            Keycode::Right => addr + 1,
+            Keycode::Up => addr - sprite * columns as isize,
+            Keycode::Down => addr + sprite * columns as isize,
+            Keycode::PageUp => addr - sprite * (columns * rows) as isize,
+            Keycode::PageDown => addr + sprite * (columns * rows) as isize,
+            _ => addr,
+        };
+        self.addr = addr.clamp(0, memory_size.saturating_sub(1) as isize) as usize;
+    }
+    // How many bytes each sprite takes up.
+    fn sprite_size(&self) -> usize {
+        if self.big {
+            32
+        } else {
+            self.height
+        }
+    }
+    // How many sprites fit across and down.
+    fn grid(&self) -> (usize, usize) {
+        let rows: usize = if self.big { 16 } else { self.height };
+        let cell_height: usize = LINE_HEIGHT + rows + GAP;
+        (VIEW_WIDTH / CELL_WIDTH, (VIEW_HEIGHT / cell_height).max(1))
+    }
+    fn draw(&self, canvas: &mut Canvas<Window>, chip_eight: &ChipEight) -> Result<(), String> {
+        let memory: &[u8] = chip_eight.memory();
+        let (columns, rows) = self.grid();
+        let cell_height: usize = VIEW_HEIGHT / rows;
+        let mut rects: Vec<Rect> = vec![];
+
+        for cell in 0..columns * rows {
+            let addr: usize = self.addr + cell * self.sprite_size();
+            if addr >= memory.len() {
+                break;
+            }
+            let left: usize = (cell % columns) * CELL_WIDTH;
+            let top: usize = (cell / columns) * cell_height;
+            let label: String = format!("{:03X}", addr);
+            for (column, c) in label.chars().enumerate() {
+                let x: usize = left + column * CHAR_ADVANCE;
+                for (dy, bits) in debug_hud::glyph(c).into_iter().enumerate() {
+                    for dx in (0..GLYPH_WIDTH).filter(|dx| bits & (0b100 >> dx) != 0) {
+                        rects.push(Rect::new((x + dx) as i32, (top + dy) as i32, 1, 1));
+                    }
+                }
+            }
+            let pixels: Vec<Vec<bool>> = if self.big {
+                render_sprite_16(memory, addr)
+                    .iter()
+                    .map(|row| row.to_vec())
+                    .collect()
+            } else {
+                render_sprite(memory, addr, self.height)
+                    .iter()
+                    .map(|row| row.to_vec())
+                    .collect()
+            };
+            for (y, row) in pixels.iter().enumerate() {
+                for x in (0..row.len()).filter(|&x| row[x]) {
+                    rects.push(Rect::new(
+                        (left + x) as i32,
+                        (top + LINE_HEIGHT + y) as i32,
+                        1,
+                        1,
+                    ));
+                }
+            }
+        }
+
+        canvas
+            .set_logical_size(VIEW_WIDTH as u32, VIEW_HEIGHT as u32)
+            .map_err(|e| e.to_string())?;
+        let title: String = format!(
+            "PotatOcho — sprites from {:#05X}, {} (+/- height, arrows and Page Up/Down to scroll, H for 16x16)",
+            self.addr,
+            if self.big {
+                "16x16".to_string()
+            } else {
+                format!("8x{}", self.height)
+            }
+        );
+        canvas
+            .window_mut()
+            .set_title(&title)
+            .map_err(|e| e.to_string())?;
+        let [bg_r, bg_g, bg_b] = self.palette.background;
+        let [fg_r, fg_g, fg_b] = self.palette.foreground;
+        canvas.set_draw_color(Color::RGB(bg_r, bg_g, bg_b));
+        canvas.clear();
+        canvas.set_draw_color(Color::RGB(fg_r, fg_g, fg_b));
+        canvas.fill_rects(&rects)?;
+        canvas.present();
+        Ok(())
+    }
+}
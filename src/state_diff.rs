@@ -0,0 +1,251 @@
+// Comparing two machines, mostly so failing tests can say what's actually different instead of dumping two 4KiB arrays.
+// Without std there's no StateDiff to collect the differences into, but machines can still be compared with == and
+// printed with {:?}, neither of which needs a heap.
+use crate::ChipEight;
+use core::fmt;
+use core::ops::ControlFlow;
+
+// One thing that differs between two machines. Without std, only whether there are any matters.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+enum Difference {
+    Field(&'static str),
+    Register(usize),
+    Memory(usize),
+    Pixel(usize, usize),
+}
+
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    // Names of the scalar fields (pc, i_register, etc.) that differ.
+    pub fields: Vec<&'static str>,
+    // Which V registers differ.
+    pub registers: Vec<usize>,
+    // Memory addresses whose contents differ. If the two machines have different amounts of RAM, only the addresses they
+    // both have are compared and "memory_size" shows up in fields.
+    pub memory: Vec<usize>,
+    // (x, y) of the pixels that differ.
+    pub pixels: Vec<(usize, usize)>,
+}
+
+#[cfg(feature = "std")]
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+            && self.registers.is_empty()
+            && self.memory.is_empty()
+            && self.pixels.is_empty()
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences");
+        }
+        let mut parts: Vec<String> = vec![];
+
+        if !self.fields.is_empty() {
+            parts.push(self.fields.join(", "));
+        }
+        if !self.registers.is_empty() {
+            let registers: Vec<String> =
+                self.registers.iter().map(|x| format!("V{:X}", x)).collect();
+            parts.push(registers.join(", "));
+        }
+        if !self.memory.is_empty() {
+            // Listing every byte of a cleared framebuffer or a reloaded ROM isn't useful, so long lists get cut short.
+            let shown: Vec<String> = self
+                .memory
+                .iter()
+                .take(8)
+                .map(|a| format!("{:#05X}", a))
+                .collect();
+            let more: usize = self.memory.len().saturating_sub(shown.len());
+            parts.push(if more > 0 {
+                format!("memory at {} and {} more", shown.join(", "), more)
+            } else {
+                format!("memory at {}", shown.join(", "))
+            });
+        }
+        if !self.pixels.is_empty() {
+            parts.push(format!("{} pixel(s)", self.pixels.len()));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+impl ChipEight {
+    // Lists everything that differs between self and other. The random number generator isn't compared, as its state can't be
+    // inspected, and neither is the dirty region, which is about what the renderer has seen rather than the machine.
+    #[cfg(feature = "std")]
+    pub fn diff(&self, other: &Self) -> StateDiff {
+        let mut diff = StateDiff::default();
+        let _ = self.differences(other, &mut |difference| {
+            match difference {
+                Difference::Field(name) => diff.fields.push(name),
+                Difference::Register(x) => diff.registers.push(x),
+                Difference::Memory(addr) => diff.memory.push(addr),
+                Difference::Pixel(x, y) => diff.pixels.push((x, y)),
+            }
+            ControlFlow::Continue(())
+        });
+        diff
+    }
+    // Passes each difference between self and other to each, in the order diff() lists them, until it breaks.
+    fn differences(
+        &self,
+        other: &Self,
+        each: &mut impl FnMut(Difference) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let mut field = |name: &'static str, differs: bool| -> ControlFlow<()> {
+            if differs {
+                each(Difference::Field(name))?;
+            }
+            ControlFlow::Continue(())
+        };
+
+        field("memory_size", self.memory.len() != other.memory.len())?;
+        field("screen_height", self.screen.height != other.screen.height)?;
+        field("screen_width", self.screen.width != other.screen.width)?;
+        field("stack", self.stack() != other.stack())?;
+        field("pc", self.pc != other.pc)?;
+        field("start_address", self.start_address != other.start_address)?;
+        field("sp", self.sp != other.sp)?;
+        field("i_register", self.i_register != other.i_register)?;
+        field("delay_timer", self.delay_timer != other.delay_timer)?;
+        field("sound_timer", self.sound_timer != other.sound_timer)?;
+        field("variant", self.variant != other.variant)?;
+        field("quirks", self.quirks_override != other.quirks_override)?;
+        field(
+            "instructions_per_frame",
+            self.instructions_per_frame != other.instructions_per_frame,
+        )?;
+        field("timing_mode", self.timing_mode != other.timing_mode)?;
+        field("cycle_budget", self.cycle_budget != other.cycle_budget)?;
+        #[cfg(feature = "std")]
+        field("rom", self.rom != other.rom)?;
+        field("keypad", self.keypad != other.keypad)?;
+        field("font", self.font != other.font)?;
+        field("flags", self.flags != other.flags)?;
+        field(
+            "waiting_for_key",
+            self.waiting_for_key != other.waiting_for_key,
+        )?;
+        field("halted", self.halted != other.halted)?;
+        field(
+            "screen_changed",
+            self.screen_changed != other.screen_changed,
+        )?;
+
+        for x in 0..16 {
+            if self.v_registers[x] != other.v_registers[x] {
+                each(Difference::Register(x))?;
+            }
+        }
+        for (addr, (a, b)) in self.memory.iter().zip(other.memory.iter()).enumerate() {
+            if a != b {
+                each(Difference::Memory(addr))?;
+            }
+        }
+        let (pixels, other_pixels) = (&self.screen.pixels, &other.screen.pixels);
+        for (y, (row, other_row)) in pixels.iter().zip(other_pixels.iter()).enumerate() {
+            for (x, (pixel, other_pixel)) in row.iter().zip(other_row.iter()).enumerate() {
+                if pixel != other_pixel {
+                    each(Difference::Pixel(x, y))?;
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl PartialEq for ChipEight {
+    fn eq(&self, other: &Self) -> bool {
+        // Stops at the first difference, rather than listing them all like diff() does.
+        self.differences(other, &mut |_| ControlFlow::Break(()))
+            .is_continue()
+    }
+}
+
+impl fmt::Debug for ChipEight {
+    // Just the registers. The full memory and screen are too big to be useful in a debug print; use diff() to compare them.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChipEight")
+            .field("variant", &self.variant)
+            .field("pc", &format_args!("{:#05X}", self.pc))
+            .field("i", &format_args!("{:#05X}", self.i_register))
+            .field("v", &format_args!("{:02X?}", self.v_registers))
+            .field("delay_timer", &self.delay_timer)
+            .field("sound_timer", &self.sound_timer)
+            .field("stack", &format_args!("{:03X?}", self.stack()))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Machines can be compared and printed in every configuration, including without std.
+    fn comparable<T: PartialEq + fmt::Debug>(a: &T, b: &T) -> bool {
+        a == b
+    }
+
+    #[test]
+    fn equal_until_something_changes() {
+        let mut a: ChipEight = ChipEight::with_seed(1);
+        let b: ChipEight = ChipEight::with_seed(2);
+        // The RNG isn't compared.
+        assert!(comparable(&a, &b));
+        a.write_memory(0x300, &[1]).unwrap();
+        assert!(!comparable(&a, &b));
+    }
+
+    #[test]
+    fn each_kind_of_difference_counts() {
+        let b: ChipEight = ChipEight::with_seed(1);
+        let mut a: ChipEight = b.clone();
+        a.set_pc(0x202);
+        assert!(a != b);
+        a = b.clone();
+        a.v_registers[0xA] = 1;
+        assert!(a != b);
+        a = b.clone();
+        a.screen.pixels[3][4] = true;
+        assert!(a != b);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn diff_lists_what_differs() {
+        let b: ChipEight = ChipEight::new();
+        let mut a: ChipEight = b.clone();
+        a.set_pc(0x202);
+        a.v_registers[0xA] = 1;
+        a.write_memory(0x300, &[1, 2]).unwrap();
+        a.screen.pixels[3][4] = true;
+        let diff: StateDiff = a.diff(&b);
+        assert_eq!(diff.fields, vec!["pc"]);
+        assert_eq!(diff.registers, vec![0xA]);
+        assert_eq!(diff.memory, vec![0x300, 0x301]);
+        assert_eq!(diff.pixels, vec![(4, 3)]);
+        assert_eq!(
+            diff.to_string(),
+            "pc; VA; memory at 0x300, 0x301; 1 pixel(s)"
+        );
+        assert!(b.diff(&b.clone()).is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn debug_prints_the_registers() {
+        let mut chip_eight: ChipEight = ChipEight::new();
+        chip_eight.set_i_register(0x2A);
+        let printed: String = format!("{:?}", chip_eight);
+        assert!(printed.starts_with("ChipEight { variant: "));
+        assert!(printed.contains("pc: 0x200, i: 0x02A"));
+        assert!(printed.ends_with(".. }"));
+    }
+}
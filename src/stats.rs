@@ -0,0 +1,222 @@
+// Measuring how fast the emulator really runs, to tell slow emulation from slow rendering. The frontend records every
+// frame as it ends, with the machine's running counts and whether anything was drawn, and the numbers cover the last
+// STATS_WINDOW of frames. The time is passed in rather than read from the clock, so the sums can be checked against
+// made-up times.
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// How far back the numbers look.
+pub const STATS_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    at: Instant,
+    instructions: u64,
+    timer_ticks: u64,
+    rendered: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    // The frames in the window, oldest first, plus the one before them to measure the first one from.
+    samples: VecDeque<Sample>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StatsReport {
+    pub instructions_per_second: f64,
+    // Frames that drew something. Frames with nothing new on the screen aren't rendered.
+    pub frames_per_second: f64,
+    // How often the delay and sound timers ticked, which should be 60Hz.
+    pub timer_hz: f64,
+    // How long frames took from the end of one to the end of the next: the median, the 90th and 99th percentiles, and
+    // the slowest.
+    pub frame_time_p50: Duration,
+    pub frame_time_p90: Duration,
+    pub frame_time_p99: Duration,
+    pub frame_time_max: Duration,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Stats::default()
+    }
+    // Notes that a frame ended at now. instructions and timer_ticks are the machine's running totals, e.g.
+    // ChipEight::instructions_executed() and frame_count(), and rendered is whether the frame drew anything.
+    pub fn record(&mut self, now: Instant, instructions: u64, timer_ticks: u64, rendered: bool) {
+        // Totals going backwards means the machine was reset or replaced, so the old frames can't be compared with the
+        // new ones.
+        if let Some(last) = self.samples.back() {
+            if instructions < last.instructions || timer_ticks < last.timer_ticks {
+                self.samples.clear();
+            }
+        }
+        self.samples.push_back(Sample {
+            at: now,
+            instructions,
+            timer_ticks,
+            rendered,
+        });
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].at) > STATS_WINDOW {
+            self.samples.pop_front();
+        }
+    }
+    // The numbers for the frames in the window. They're all zero until two frames have been recorded.
+    pub fn report(&self) -> StatsReport {
+        let (first, last) = match (self.samples.front(), self.samples.back()) {
+            (Some(first), Some(last)) if last.at > first.at => (first, last),
+            _ => return StatsReport::default(),
+        };
+        let seconds: f64 = last.at.duration_since(first.at).as_secs_f64();
+        let rendered: usize = self.samples.iter().skip(1).filter(|s| s.rendered).count();
+        let mut frame_times: Vec<Duration> = self
+            .samples
+            .iter()
+            .zip(self.samples.iter().skip(1))
+            .map(|(a, b)| b.at.duration_since(a.at))
+            .collect();
+        frame_times.sort();
+        // The nearest-rank percentile.
+        let percentile = |p: usize| -> Duration {
+            let rank: usize = (frame_times.len() * p).div_ceil(100).max(1);
+            frame_times[rank - 1]
+        };
+
+        StatsReport {
+            instructions_per_second: (last.instructions - first.instructions) as f64 / seconds,
+            frames_per_second: rendered as f64 / seconds,
+            timer_hz: (last.timer_ticks - first.timer_ticks) as f64 / seconds,
+            frame_time_p50: percentile(50),
+            frame_time_p90: percentile(90),
+            frame_time_p99: percentile(99),
+            frame_time_max: percentile(100),
+        }
+    }
+}
+
+impl StatsReport {
+    // The report as it's shown in the SDL frontend's overlay, short enough to fit its lines.
+    pub fn lines(&self) -> [String; 2] {
+        [
+            format!(
+                "IPS {:.0}  FPS {:.1}  TIMERS {:.1}HZ",
+                self.instructions_per_second, self.frames_per_second, self.timer_hz
+            ),
+            format!(
+                "FRAME MS P50 {:.1} P90 {:.1} P99 {:.1} MAX {:.1}",
+                millis(self.frame_time_p50),
+                millis(self.frame_time_p90),
+                millis(self.frame_time_p99),
+                millis(self.frame_time_max)
+            ),
+        ]
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// 700000 instructions/s, 60.0 frames/s rendered, timers at 60.0Hz, frame times p50 16.7ms p90 16.9ms p99 17.4ms max 18.1ms
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:.0} instructions/s, {:.1} frames/s rendered, timers at {:.1}Hz, frame times p50 {:.1}ms p90 {:.1}ms p99 {:.1}ms max {:.1}ms",
+            self.instructions_per_second,
+            self.frames_per_second,
+            self.timer_hz,
+            millis(self.frame_time_p50),
+            millis(self.frame_time_p90),
+            millis(self.frame_time_p99),
+            millis(self.frame_time_max)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn rates_over_a_second_of_frames() {
+        let start: Instant = Instant::now();
+        let mut stats: Stats = Stats::new();
+        assert_eq!(stats.report(), StatsReport::default());
+
+        // 50 frames 20ms apart, each running 500 instructions and ticking the timers once, with every other one drawn.
+        for frame in 0..=50u32 {
+            stats.record(
+                start + frame * 20 * MS,
+                frame as u64 * 500,
+                frame as u64,
+                frame % 2 == 1,
+            );
+        }
+        let report: StatsReport = stats.report();
+        assert_eq!(report.instructions_per_second, 25000.0);
+        assert_eq!(report.frames_per_second, 25.0);
+        assert_eq!(report.timer_hz, 50.0);
+        assert_eq!(report.frame_time_p50, 20 * MS);
+        assert_eq!(report.frame_time_max, 20 * MS);
+        assert_eq!(
+            report.lines(),
+            [
+                "IPS 25000  FPS 25.0  TIMERS 50.0HZ".to_string(),
+                "FRAME MS P50 20.0 P90 20.0 P99 20.0 MAX 20.0".to_string(),
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "25000 instructions/s, 25.0 frames/s rendered, timers at 50.0Hz, frame times p50 20.0ms p90 20.0ms p99 20.0ms \
+             max 20.0ms"
+        );
+
+        // Two more seconds at twice the speed push the first second out of the window. The window starts from the frame
+        // before the first one in it, so it spans 1.01s.
+        for frame in 51..=250u32 {
+            let at: Instant = start + Duration::from_secs(1) + (frame - 50) * 10 * MS;
+            stats.record(at, frame as u64 * 500, frame as u64, true);
+        }
+        let report: StatsReport = stats.report();
+        assert_eq!(report.instructions_per_second.round(), 50000.0);
+        assert_eq!(report.frames_per_second.round(), 100.0);
+        assert_eq!(report.frame_time_max, 10 * MS);
+    }
+
+    #[test]
+    fn frame_time_percentiles() {
+        let start: Instant = Instant::now();
+        let mut stats: Stats = Stats::new();
+        // Frames taking 1ms, 2ms, ... 10ms.
+        let mut at: Instant = start;
+        stats.record(at, 0, 0, true);
+        for ms in 1..=10u32 {
+            at += ms * MS;
+            stats.record(at, ms as u64, ms as u64, true);
+        }
+        let report: StatsReport = stats.report();
+        assert_eq!(report.frame_time_p50, 5 * MS);
+        assert_eq!(report.frame_time_p90, 9 * MS);
+        assert_eq!(report.frame_time_p99, 10 * MS);
+        assert_eq!(report.frame_time_max, 10 * MS);
+    }
+
+    #[test]
+    fn a_reset_machine_starts_over() {
+        let start: Instant = Instant::now();
+        let mut stats: Stats = Stats::new();
+        stats.record(start, 1000, 100, true);
+        stats.record(start + 100 * MS, 2000, 106, true);
+        assert_eq!(stats.report().instructions_per_second, 10000.0);
+
+        // The totals went back to zero, so there's only one frame to go on.
+        stats.record(start + 200 * MS, 0, 0, true);
+        assert_eq!(stats.report(), StatsReport::default());
+        stats.record(start + 300 * MS, 100, 6, true);
+        assert_eq!(stats.report().instructions_per_second, 1000.0);
+    }
+}
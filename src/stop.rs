@@ -0,0 +1,43 @@
+// Stopping run() from outside, e.g. from an application's UI thread. run() checks for a stop request once per frame.
+use crate::ChipEight;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// A handle that asks a machine's run() loop to stop. Clones share the same request, so the token can be handed to as many
+// threads as need it.
+#[derive(Clone, Debug, Default)]
+pub struct StopToken {
+    requested: Arc<AtomicBool>,
+}
+
+impl StopToken {
+    // run() returns ExitReason::Stopped at the start of its next frame. Stopping a machine that isn't running makes its
+    // next run() return straight away.
+    pub fn stop(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+    pub fn is_stop_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+    // Clears the request and reports whether there was one, so each stop() ends exactly one run().
+    pub(crate) fn take_request(&self) -> bool {
+        self.requested.swap(false, Ordering::Relaxed)
+    }
+}
+
+// The machine's own token. A cloned machine gets a fresh one, so stopping the original doesn't stop the clone.
+#[derive(Default)]
+pub(crate) struct StopSlot(pub(crate) StopToken);
+
+impl Clone for StopSlot {
+    fn clone(&self) -> Self {
+        StopSlot::default()
+    }
+}
+
+impl ChipEight {
+    // A token that stops this machine's run() loop.
+    pub fn stop_token(&self) -> StopToken {
+        self.stop.0.clone()
+    }
+}
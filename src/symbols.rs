@@ -0,0 +1,216 @@
+// Names for addresses in a ROM, read from a .sym file, so listings and the debugger can say CALL draw_score instead of
+// CALL 0x2A4. Each line of the file is an address and a name, plus "data" for tables and sprites:
+// 0x200 main
+// 0x2A4 draw_score
+// 0x300 score_bcd data
+// A data symbol covers everything up to the next symbol, and listings show those bytes as data instead of disassembling
+// them. # starts a comment.
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub data: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Symbols {
+    // Sorted by address, so the symbol an address falls under is the closest one at or below it.
+    by_address: BTreeMap<u16, Symbol>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SymbolError {}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // Reads a symbol file. Addresses are hex, with or without the 0x, and every address and name can only be used once.
+    pub fn parse(src: &str) -> Result<Self, SymbolError> {
+        let mut symbols: Symbols = Symbols::new();
+
+        for (i, line) in src.lines().enumerate() {
+            let error = |message: String| SymbolError {
+                line: i + 1,
+                message,
+            };
+            let words: Vec<&str> = line
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect();
+            let (addr, name, data) = match words[..] {
+                [] => continue,
+                [addr, name] => (addr, name, false),
+                [addr, name, "data"] => (addr, name, true),
+                [_, _, kind] => {
+                    return Err(error(format!(
+                        "unknown symbol kind {}, expected data",
+                        kind
+                    )))
+                }
+                _ => return Err(error("expected an address and a name".to_string())),
+            };
+            let digits: &str = addr.trim_start_matches("0x").trim_start_matches("0X");
+            let addr: u16 = u16::from_str_radix(digits, 16)
+                .map_err(|_| error(format!("bad address {}", addr)))?;
+            if let Some(symbol) = symbols.by_address.get(&addr) {
+                return Err(error(format!(
+                    "{:#05X} is already called {}",
+                    addr, symbol.name
+                )));
+            }
+            if symbols.address(name).is_some() {
+                return Err(error(format!("{} is defined twice", name)));
+            }
+            symbols.insert(addr, name, data);
+        }
+        Ok(symbols)
+    }
+    // Names addr, replacing any name it already had.
+    pub fn insert(&mut self, addr: u16, name: impl Into<String>, data: bool) {
+        self.by_address.insert(
+            addr,
+            Symbol {
+                name: name.into(),
+                data,
+            },
+        );
+    }
+    // The name of the symbol at exactly addr.
+    pub fn name(&self, addr: u16) -> Option<&str> {
+        self.by_address
+            .get(&addr)
+            .map(|symbol| symbol.name.as_str())
+    }
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.by_address
+            .iter()
+            .find(|(_, symbol)| symbol.name == name)
+            .map(|(&addr, _)| addr)
+    }
+    // Whether addr is inside the range of a data symbol.
+    pub fn is_data(&self, addr: u16) -> bool {
+        matches!(self.by_address.range(..=addr).next_back(), Some((_, symbol)) if symbol.data)
+    }
+    // In address order.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &Symbol)> {
+        self.by_address.iter().map(|(&addr, symbol)| (addr, symbol))
+    }
+    pub fn len(&self) -> usize {
+        self.by_address.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.by_address.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let src: &str = "# Pong\n\
+                         \n\
+                         0x200 main   # entry point\n\
+                         \t2A4  draw_score\n\
+                         0X300 score_bcd data\n\
+                         \x20\x20# trailing comment\n";
+        let symbols: Symbols = Symbols::parse(src).unwrap();
+
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(
+            symbols.iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    0x200,
+                    &Symbol {
+                        name: "main".to_string(),
+                        data: false
+                    }
+                ),
+                (
+                    0x2A4,
+                    &Symbol {
+                        name: "draw_score".to_string(),
+                        data: false
+                    }
+                ),
+                (
+                    0x300,
+                    &Symbol {
+                        name: "score_bcd".to_string(),
+                        data: true
+                    }
+                ),
+            ]
+        );
+        assert!(Symbols::parse("# nothing here\n\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn lookups_go_both_ways() {
+        let symbols: Symbols =
+            Symbols::parse("0x200 main\n0x2A4 draw_score\n0x300 digits data\n").unwrap();
+
+        assert_eq!(symbols.name(0x2A4), Some("draw_score"));
+        assert_eq!(symbols.address("draw_score"), Some(0x2A4));
+        assert_eq!(symbols.name(0x2A6), None, "only exact addresses have names");
+        assert_eq!(symbols.address("missing"), None);
+
+        // A data symbol covers everything up to the next one, and nothing before it.
+        for (addr, expected) in [(0x2FF, false), (0x300, true), (0x350, true), (0x1FF, false)] {
+            assert_eq!(symbols.is_data(addr), expected, "{:#05X}", addr);
+        }
+    }
+
+    #[test]
+    fn errors_say_what_and_where() {
+        for (src, line, message) in [
+            ("0x200\n", 1, "expected an address and a name"),
+            (
+                "0x200 main\n0x202 a b c d\n",
+                2,
+                "expected an address and a name",
+            ),
+            (
+                "\n0x200 main code\n",
+                2,
+                "unknown symbol kind code, expected data",
+            ),
+            ("0x2G0 main\n", 1, "bad address 0x2G0"),
+            ("10000 main\n", 1, "bad address 10000"),
+            (
+                "0x200 main\n0x200 start\n",
+                2,
+                "0x200 is already called main",
+            ),
+            (
+                "0x200 main\n# again\n0x300 main\n",
+                3,
+                "main is defined twice",
+            ),
+        ] {
+            let expected: SymbolError = SymbolError {
+                line,
+                message: message.to_string(),
+            };
+            assert_eq!(Symbols::parse(src), Err(expected), "{:?}", src);
+        }
+    }
+}
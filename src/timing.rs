@@ -0,0 +1,97 @@
+// How the emulator decides how much code to run each 60Hz frame.
+use crate::ChipEight;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimingMode {
+    // Run a fixed number of instructions every frame, regardless of what they are.
+    #[default]
+    InstructionsPerFrame,
+    // Charge every instruction the number of machine cycles it took on the original COSMAC VIP interpreter, and run until the
+    // frame's worth of cycles is used up. Games that were tuned on real hardware run at their real speed this way.
+    CosmacVip,
+}
+
+impl fmt::Display for TimingMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimingMode::InstructionsPerFrame => write!(f, "ipf"),
+            TimingMode::CosmacVip => write!(f, "vip"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for TimingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ipf" => Ok(TimingMode::InstructionsPerFrame),
+            "vip" | "cosmac-vip" => Ok(TimingMode::CosmacVip),
+            _ => Err(format!("unknown timing mode '{}' (expected ipf or vip)", s)),
+        }
+    }
+}
+
+// The VIP's 1802 runs at 1.7609MHz and a machine cycle is 8 clock cycles, so there are about 3668 machine cycles per 60Hz frame.
+pub const VIP_CYCLES_PER_FRAME: i32 = 3668;
+
+// Every instruction pays for the interpreter's fetch and decode loop on top of its own cost.
+const VIP_FETCH_CYCLES: i32 = 40;
+
+impl ChipEight {
+    // The approximate number of VIP machine cycles instruction will take to execute in the current machine state. The costs are
+    // approximations based on Laurence Scotford's walkthrough of the VIP interpreter's source; they aren't exact for every case
+    // (the VIP's timing also depends on interrupts landing mid-instruction), but they're close enough to get the speed right.
+    pub(crate) fn vip_cycles(&self, instruction: u16) -> i32 {
+        let x: usize = ((instruction & 0x0F00) >> 8) as usize;
+        let y: usize = ((instruction & 0x00F0) >> 4) as usize;
+        let n: i32 = (instruction & 0x000F) as i32;
+        let kk: u8 = (instruction & 0x00FF) as u8;
+        let vx: u8 = self.v_registers[x];
+        let vy: u8 = self.v_registers[y];
+        // Skips cost a little more when they're taken.
+        let skip = |taken: bool, base: i32| if taken { base + 4 } else { base };
+
+        let cost: i32 = match instruction >> 12 {
+            0x0 => match instruction {
+                0x00E0 => 24,
+                0x00EE => 10,
+                _ => 0,
+            },
+            0x1 => 12,
+            0x2 => 26,
+            0x3 => skip(vx == kk, 10),
+            0x4 => skip(vx != kk, 10),
+            0x5 => skip(vx == vy, 14),
+            0x6 => 6,
+            0x7 => 10,
+            0x8 => 44,
+            0x9 => skip(vx != vy, 14),
+            0xA => 12,
+            0xB => 22,
+            0xC => 36,
+            // Drawing is the slow one: every row is shifted into place a bit at a time, and sprites that don't start on a byte
+            // boundary spill into a second byte of display memory.
+            0xD => {
+                let unaligned: bool = !vx.is_multiple_of(8);
+                26 + n * if unaligned { 68 } else { 46 }
+            }
+            0xE => 14,
+            0xF => match kk {
+                0x07 | 0x15 | 0x18 => 10,
+                0x0A => 18,
+                0x1E | 0x29 => 16,
+                0x33 => 84 + 16 * (vx / 100 + vx / 10 % 10 + vx % 10) as i32,
+                0x55 | 0x65 => 14 + 14 * (x as i32 + 1),
+                _ => 10,
+            },
+            _ => 0,
+        };
+        VIP_FETCH_CYCLES + cost
+    }
+}
@@ -0,0 +1,334 @@
+// A tiny parser for the subset of TOML our settings files use: [tables], [[arrays of tables]], comments, and
+// key = value pairs where the value is a string, integer, float, or boolean. Pulling in a full TOML crate for this felt like overkill.
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+    // Integers count as floats too, so "440" works where "440.0" would.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(f) => Some(*f),
+            Value::Integer(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub value: Value,
+    // The line the entry was defined on, so callers can point at it when the value doesn't make sense to them.
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    // The root table (everything before the first header) has an empty name.
+    pub name: String,
+    pub entries: Vec<Entry>,
+}
+
+impl Table {
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.key == key)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TomlError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl TomlError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        TomlError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for TomlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for TomlError {}
+
+// Parses src into a list of tables in the order they appear. [[name]] headers produce one table per occurrence with the same name.
+pub fn parse(src: &str) -> Result<Vec<Table>, TomlError> {
+    let mut tables: Vec<Table> = vec![Table {
+        name: String::new(),
+        entries: vec![],
+    }];
+
+    for (i, raw_line) in src.lines().enumerate() {
+        let line_number: usize = i + 1;
+        let line: &str = strip_comment(raw_line).trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let name: &str = match header.strip_prefix('[') {
+                Some(array_header) => array_header.strip_suffix("]]"),
+                None => header.strip_suffix(']'),
+            }
+            .ok_or_else(|| TomlError::new(line_number, "unterminated table header"))?
+            .trim();
+
+            if name.is_empty() || !name.chars().all(is_key_char) {
+                return Err(TomlError::new(
+                    line_number,
+                    format!("invalid table name '{}'", name),
+                ));
+            }
+            tables.push(Table {
+                name: name.to_string(),
+                entries: vec![],
+            });
+            continue;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => {
+                return Err(TomlError::new(
+                    line_number,
+                    format!("expected 'key = value', found '{}'", line),
+                ))
+            }
+        };
+
+        if key.is_empty() || !key.chars().all(is_key_char) {
+            return Err(TomlError::new(
+                line_number,
+                format!("invalid key '{}'", key),
+            ));
+        }
+
+        let value: Value = parse_value(value).map_err(|e| TomlError::new(line_number, e))?;
+        let table: &mut Table = tables.last_mut().unwrap();
+
+        if table.get(key).is_some() {
+            return Err(TomlError::new(
+                line_number,
+                format!("duplicate key '{}'", key),
+            ));
+        }
+        table.entries.push(Entry {
+            key: key.to_string(),
+            value,
+            line: line_number,
+        });
+    }
+    Ok(tables)
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+// Comments start with # but a # inside a string doesn't count.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string: bool = false;
+    let mut escaped: bool = false;
+
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(value: &str) -> Result<Value, String> {
+    if let Some(rest) = value.strip_prefix('"') {
+        let inner: &str = rest
+            .strip_suffix('"')
+            .ok_or_else(|| String::from("unterminated string"))?;
+        let mut out: String = String::new();
+        let mut chars = inner.chars();
+
+        while let Some(c) = chars.next() {
+            if c == '"' {
+                return Err(String::from("unescaped '\"' inside a string"));
+            }
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('"') => out.push('"'),
+                Some('\\') => out.push('\\'),
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some(other) => return Err(format!("unsupported escape '\\{}'", other)),
+                None => return Err(String::from("unterminated string")),
+            }
+        }
+        return Ok(Value::String(out));
+    }
+
+    match value {
+        "true" => return Ok(Value::Boolean(true)),
+        "false" => return Ok(Value::Boolean(false)),
+        _ => {}
+    }
+
+    let digits: String = value.replace('_', "");
+    let (negative, digits): (bool, &str) = match digits.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, digits.strip_prefix('+').unwrap_or(&digits)),
+    };
+    let integer = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()
+    } else {
+        digits.parse::<i64>().ok()
+    };
+
+    if let Some(i) = integer {
+        return Ok(Value::Integer(if negative { -i } else { i }));
+    }
+    if let Ok(f) = digits.parse::<f64>() {
+        return Ok(Value::Float(if negative { -f } else { f }));
+    }
+    Err(format!("unrecognized value '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_entries_and_comments() {
+        let src: &str = "# settings\n\
+                         theme = \"green # not a comment\"  # a comment\n\
+                         \n\
+                         [sound]\n\
+                         volume = 40\n\
+                         [[rom]]\n\
+                         crc32 = 0x1A2B_3C4D\n\
+                         [[rom]]\n\
+                         crc32 = 0b101\n";
+        let tables: Vec<Table> = parse(src).unwrap();
+        let names: Vec<&str> = tables.iter().map(|table| table.name.as_str()).collect();
+        assert_eq!(names, ["", "sound", "rom", "rom"]);
+        assert_eq!(
+            tables[0].entries,
+            vec![Entry {
+                key: "theme".to_string(),
+                value: Value::String("green # not a comment".to_string()),
+                line: 2,
+            }]
+        );
+        assert_eq!(tables[1].get("volume").unwrap().line, 5);
+        assert_eq!(
+            tables[2].get("crc32").unwrap().value,
+            Value::Integer(0x1A2B_3C4D)
+        );
+        assert_eq!(tables[3].get("crc32").unwrap().value, Value::Integer(5));
+        assert_eq!(tables[3].get("title"), None);
+    }
+
+    #[test]
+    fn values() {
+        for (src, expected) in [
+            ("\"\"", Value::String(String::new())),
+            (
+                "\"say \\\"hi\\\"\\n\\t\\\\\"",
+                Value::String("say \"hi\"\n\t\\".to_string()),
+            ),
+            ("true", Value::Boolean(true)),
+            ("false", Value::Boolean(false)),
+            ("42", Value::Integer(42)),
+            ("-7", Value::Integer(-7)),
+            ("+1_000", Value::Integer(1000)),
+            ("0xFF", Value::Integer(255)),
+            ("-0x10", Value::Integer(-16)),
+            ("261.63", Value::Float(261.63)),
+            ("-0.5", Value::Float(-0.5)),
+        ] {
+            assert_eq!(parse_value(src), Ok(expected), "{}", src);
+        }
+        assert_eq!(Value::Float(1.5).as_integer(), None);
+        assert_eq!(Value::Integer(440).as_float(), Some(440.0));
+    }
+
+    #[test]
+    fn bad_values_are_rejected() {
+        for (src, message) in [
+            ("\"a\"b\"", "unescaped '\"' inside a string"),
+            ("\"a\" \"b\"", "unescaped '\"' inside a string"),
+            ("\"abc", "unterminated string"),
+            ("\"abc\\\"", "unterminated string"),
+            ("\"\\x\"", "unsupported escape '\\x'"),
+            ("yes", "unrecognized value 'yes'"),
+            ("0xZZ", "unrecognized value '0xZZ'"),
+            ("", "unrecognized value ''"),
+        ] {
+            assert_eq!(parse_value(src), Err(message.to_string()), "{}", src);
+        }
+    }
+
+    #[test]
+    fn errors_say_where() {
+        for (src, line, message) in [
+            ("[rom\n", 1, "unterminated table header"),
+            ("[[rom]\n", 1, "unterminated table header"),
+            ("\n[]\n", 2, "invalid table name ''"),
+            ("[a b]\n", 1, "invalid table name 'a b'"),
+            ("title\n", 1, "expected 'key = value', found 'title'"),
+            ("a key = 1\n", 1, "invalid key 'a key'"),
+            ("= 1\n", 1, "invalid key ''"),
+            ("a = 1\n\na = 2\n", 3, "duplicate key 'a'"),
+            (
+                "[x]\ntitle = \"a\"b\"\n",
+                2,
+                "unescaped '\"' inside a string",
+            ),
+        ] {
+            assert_eq!(parse(src), Err(TomlError::new(line, message)), "{:?}", src);
+        }
+        // The same key in two tables is fine.
+        assert!(parse("a = 1\n[x]\na = 2\n").is_ok());
+    }
+}
@@ -0,0 +1,534 @@
+// The buzzer's tone. CHIP-8 only says when the buzzer sounds, not what it sounds like, so the shape of the wave is up to
+// the user: the classic square wave, or something gentler. Tone works out the samples without knowing anything about
+// where they go, so audio backends just hand it their buffers.
+#[cfg(feature = "std")]
+use crate::{SampleTap, Xorshift};
+use core::fmt;
+#[cfg(feature = "std")]
+use core::str::FromStr;
+
+// The shapes the tone can have, in the order NextWaveform cycles through them.
+pub const WAVEFORMS: [Waveform; 5] = [
+    Waveform::Square,
+    Waveform::Sine,
+    Waveform::Triangle,
+    Waveform::Saw,
+    Waveform::Noise,
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+    Saw,
+    // A random level, held for half a period at a time, so it hisses at about the tone's pitch.
+    Noise,
+}
+
+impl Waveform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Waveform::Square => "square",
+            Waveform::Sine => "sine",
+            Waveform::Triangle => "triangle",
+            Waveform::Saw => "saw",
+            Waveform::Noise => "noise",
+        }
+    }
+    // The next of WAVEFORMS, back to the first after the last.
+    pub fn next(&self) -> Waveform {
+        let i: usize = WAVEFORMS.iter().position(|w| w == self).unwrap_or(0);
+        WAVEFORMS[(i + 1) % WAVEFORMS.len()]
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WAVEFORMS
+            .iter()
+            .find(|waveform| waveform.name().eq_ignore_ascii_case(s.trim()))
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "unknown waveform '{}' (expected square, sine, triangle, saw, or noise)",
+                    s
+                )
+            })
+    }
+}
+
+impl fmt::Display for Waveform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// The buzzer's pitch unless it's set otherwise: middle C.
+pub const DEFAULT_TONE_HZ: f32 = 261.63;
+// The range the pitch can be set in, in Hz, which is about what can be heard.
+pub const TONE_HZ_RANGE: core::ops::RangeInclusive<f32> = 20.0..=20_000.0;
+
+// The volume unless it's set otherwise, and the most it can be, as a percentage. 100% is a wave a quarter of full scale,
+// so the default is the 1/16 the square wave always had.
+pub const DEFAULT_VOLUME: u8 = 25;
+pub const MAX_VOLUME: u8 = 100;
+#[cfg(feature = "std")]
+const FULL_VOLUME: f32 = 0.25;
+
+// How the buzzer sounds: the shape of its tone, its pitch in Hz, its volume from 0 (silent) to MAX_VOLUME, and whether it's
+// muted, which silences it without forgetting the volume.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sound {
+    pub waveform: Waveform,
+    pub frequency: f32,
+    pub volume: u8,
+    pub muted: bool,
+}
+
+impl Default for Sound {
+    fn default() -> Self {
+        Sound {
+            waveform: Waveform::Square,
+            frequency: DEFAULT_TONE_HZ,
+            volume: DEFAULT_VOLUME,
+            muted: false,
+        }
+    }
+}
+
+impl Sound {
+    // Whether a beep would be heard at all: it isn't muted, and the volume isn't 0.
+    pub fn is_audible(&self) -> bool {
+        !self.muted && self.volume > 0
+    }
+}
+
+// How long the buzzer takes to fade in when it starts and out when it stops, in seconds. Starting or stopping a wave
+// mid-cycle is an instant jump, which is heard as a click, and programs often beep for only a frame or two.
+pub const ENVELOPE_RAMP: f32 = 0.003;
+
+// A linear attack and release, as a level between 0 and 1 that's multiplied into each sample. While the gate is open the
+// level rises to 1, and once it's closed it falls back to 0, by the same amount every sample. Opening the gate again
+// partway through the release just turns the level around where it is, so quick retriggers don't jump either.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Envelope {
+    level: f32,
+    step: f32,
+    gate: bool,
+}
+
+impl Envelope {
+    // A closed, silent envelope whose ramps take ENVELOPE_RAMP at sample_rate samples a second.
+    pub fn new(sample_rate: u32) -> Self {
+        Envelope {
+            level: 0.0,
+            step: 1.0 / (ENVELOPE_RAMP * sample_rate as f32).max(1.0),
+            gate: false,
+        }
+    }
+    pub fn gate(&self) -> bool {
+        self.gate
+    }
+    pub fn set_gate(&mut self, gate: bool) {
+        self.gate = gate;
+    }
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+    // Whether the gate is closed and the release has finished, so nothing more will be heard until it opens again.
+    pub fn is_silent(&self) -> bool {
+        !self.gate && self.level == 0.0
+    }
+    // The level for the next sample.
+    pub fn next_level(&mut self) -> f32 {
+        self.level = if self.gate {
+            (self.level + self.step).min(1.0)
+        } else {
+            (self.level - self.step).max(0.0)
+        };
+        self.level
+    }
+}
+
+// What the noise is seeded with, so it's the same every time.
+#[cfg(feature = "std")]
+const NOISE_SEED: u64 = 0x8A5C_D789_635D_2DFF;
+
+// A sound's tone, as samples for playing sample_rate a second, faded in and out by an Envelope as the buzzer starts and
+// stops. The phase goes from 0 to 1 over each period whatever the waveform, so changing the sound mid-tone doesn't skip.
+// It starts out silent.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct Tone {
+    sample_rate: u32,
+    // How many channels the output has. Each sample goes to all of them.
+    channels: usize,
+    sound: Sound,
+    phase_inc: f32,
+    phase: f32,
+    // The noise waveform's random levels, and the one it's holding.
+    noise: Xorshift,
+    noise_level: f32,
+    // Whether the buzzer should be sounding. The envelope's gate is open while it is and the sound is audible, so muting
+    // fades out like a beep ending does.
+    beeping: bool,
+    envelope: Envelope,
+    // Where a copy of every sample goes while it's being recorded.
+    tap: Option<SampleTap>,
+}
+
+#[cfg(feature = "std")]
+impl Tone {
+    pub fn new(sample_rate: u32, sound: Sound) -> Self {
+        Tone {
+            sample_rate,
+            channels: 1,
+            sound,
+            phase_inc: sound.frequency / sample_rate as f32,
+            phase: 0.0,
+            noise: Xorshift::new(NOISE_SEED),
+            noise_level: 0.0,
+            beeping: false,
+            envelope: Envelope::new(sample_rate),
+            tap: None,
+        }
+    }
+    // For output with more than one channel, interleaved, as SDL has it. 0 is taken as 1.
+    pub fn channels(mut self, channels: u8) -> Self {
+        self.channels = channels.max(1) as usize;
+        self
+    }
+    pub fn sound(&self) -> Sound {
+        self.sound
+    }
+    pub fn set_sound(&mut self, sound: Sound) {
+        self.sound = sound;
+        self.phase_inc = sound.frequency / self.sample_rate as f32;
+        self.envelope.set_gate(self.beeping && sound.is_audible());
+    }
+    // Starts the attack or the release.
+    pub fn set_beeping(&mut self, beeping: bool) {
+        self.beeping = beeping;
+        self.envelope.set_gate(beeping && self.sound.is_audible());
+    }
+    // Starts or stops copying every sample filled from now on into tap.
+    pub fn set_tap(&mut self, tap: Option<SampleTap>) {
+        self.tap = tap;
+    }
+    // Whether the tone has stopped and finished fading out, so whatever's playing it can stop.
+    pub fn is_silent(&self) -> bool {
+        self.envelope.is_silent()
+    }
+    // The next sample, between -FULL_VOLUME and FULL_VOLUME at MAX_VOLUME, and always 0 at a volume of 0 or once the
+    // release has finished.
+    pub fn next_sample(&mut self) -> f32 {
+        let phase: f32 = self.phase;
+        let level: f32 = match self.sound.waveform {
+            Waveform::Square if phase < 0.5 => 1.0,
+            Waveform::Square => -1.0,
+            Waveform::Sine => (phase * core::f32::consts::TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Saw => 2.0 * phase - 1.0,
+            Waveform::Noise => self.noise_level,
+        };
+        self.phase = (phase + self.phase_inc) % 1.0;
+        // A new noise level every half period: when the phase wraps, or crosses the middle.
+        if self.phase < phase || (phase < 0.5 && self.phase >= 0.5) {
+            self.noise_level = self.noise.next_byte() as f32 / 127.5 - 1.0;
+        }
+        let volume: f32 =
+            FULL_VOLUME * self.sound.volume.min(MAX_VOLUME) as f32 / MAX_VOLUME as f32;
+        level * volume * self.envelope.next_level()
+    }
+    // Fills out with the next samples, the same one in every channel, and copies them to the tap if there is one.
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for frame in out.chunks_mut(self.channels) {
+            frame.fill(self.next_sample());
+        }
+        if let Some(tap) = &self.tap {
+            tap.push(out);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 8000;
+    // 100Hz at 8000 samples a second.
+    const PERIOD: usize = 80;
+
+    // A full-volume tone of waveform that's been beeping long enough to have finished its attack.
+    fn beeping(waveform: Waveform) -> Tone {
+        let mut tone: Tone = Tone::new(
+            SAMPLE_RATE,
+            Sound {
+                waveform,
+                frequency: 100.0,
+                volume: MAX_VOLUME,
+                muted: false,
+            },
+        );
+        tone.set_beeping(true);
+        tone.fill(&mut [0.0; PERIOD]);
+        tone
+    }
+
+    fn samples(tone: &mut Tone, n: usize) -> Vec<f32> {
+        let mut out: Vec<f32> = vec![0.0; n];
+        tone.fill(&mut out);
+        out
+    }
+
+    #[test]
+    fn waveforms_are_named_and_cycle() {
+        for (i, waveform) in WAVEFORMS.iter().enumerate() {
+            assert_eq!(waveform.name().parse(), Ok(*waveform));
+            assert_eq!(waveform.next(), WAVEFORMS[(i + 1) % WAVEFORMS.len()]);
+        }
+        assert_eq!(" SINE ".parse(), Ok(Waveform::Sine));
+        assert_eq!(
+            "kazoo".parse::<Waveform>(),
+            Err(
+                "unknown waveform 'kazoo' (expected square, sine, triangle, saw, or noise)"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn every_waveform_stays_within_the_volume() {
+        for waveform in WAVEFORMS {
+            let out: Vec<f32> = samples(&mut beeping(waveform), PERIOD * 4);
+            let (min, max) = out.iter().fold((f32::MAX, f32::MIN), |(min, max), &s| {
+                (min.min(s), max.max(s))
+            });
+            assert!(
+                min >= -FULL_VOLUME && max <= FULL_VOLUME,
+                "{}: {} to {}",
+                waveform,
+                min,
+                max
+            );
+            // Noise might not happen to reach the ends.
+            if waveform != Waveform::Noise {
+                assert!(
+                    min < -0.24 && max > 0.24,
+                    "{}: {} to {}",
+                    waveform,
+                    min,
+                    max
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tones_repeat_every_period() {
+        for waveform in [
+            Waveform::Square,
+            Waveform::Sine,
+            Waveform::Triangle,
+            Waveform::Saw,
+        ] {
+            let out: Vec<f32> = samples(&mut beeping(waveform), PERIOD * 3);
+            for i in 0..PERIOD * 2 {
+                // The square and saw waves' edges can land a sample either side as rounding errors add up.
+                let tolerance: f32 = if matches!(waveform, Waveform::Square | Waveform::Saw) {
+                    FULL_VOLUME * 2.0
+                } else {
+                    1e-3
+                };
+                assert!(
+                    (out[i] - out[i + PERIOD]).abs() <= tolerance,
+                    "{} sample {}: {} then {}",
+                    waveform,
+                    i,
+                    out[i],
+                    out[i + PERIOD]
+                );
+            }
+            let mean: f32 = out.iter().sum::<f32>() / out.len() as f32;
+            assert!(mean.abs() < 0.01, "{} averages {}", waveform, mean);
+        }
+    }
+
+    #[test]
+    fn shapes_go_the_right_way() {
+        // Starting from the beginning of a period, after the attack. Rounding errors can move the edges by a sample.
+        let square: Vec<f32> = samples(&mut beeping(Waveform::Square), PERIOD);
+        let at = |s: f32, level: f32| (s - level).abs() < 1e-6;
+        assert!(
+            square[2..PERIOD / 2 - 2]
+                .iter()
+                .all(|&s| at(s, FULL_VOLUME)),
+            "{:?}",
+            square
+        );
+        assert!(
+            square[PERIOD / 2 + 2..PERIOD - 2]
+                .iter()
+                .all(|&s| at(s, -FULL_VOLUME)),
+            "{:?}",
+            square
+        );
+        let saw: Vec<f32> = samples(&mut beeping(Waveform::Saw), PERIOD);
+        assert!(saw[2..PERIOD - 2].windows(2).all(|pair| pair[1] > pair[0]));
+        let triangle: Vec<f32> = samples(&mut beeping(Waveform::Triangle), PERIOD);
+        assert!(triangle[2..PERIOD / 2 - 2]
+            .windows(2)
+            .all(|pair| pair[1] > pair[0]));
+        assert!(triangle[PERIOD / 2 + 2..PERIOD - 2]
+            .windows(2)
+            .all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn the_volume_scales_the_samples() {
+        let mut tone: Tone = beeping(Waveform::Square);
+        assert!(samples(&mut tone, PERIOD)
+            .iter()
+            .all(|s| s.abs() == FULL_VOLUME));
+        tone.set_sound(Sound {
+            volume: DEFAULT_VOLUME,
+            ..tone.sound()
+        });
+        // The default volume is the 1/16 the square wave always had.
+        assert!(samples(&mut tone, PERIOD).iter().all(|s| s.abs() == 0.0625));
+        tone.set_sound(Sound {
+            volume: 0,
+            ..tone.sound()
+        });
+        assert!(samples(&mut tone, PERIOD).iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn changing_the_pitch_changes_the_period() {
+        let mut tone: Tone = beeping(Waveform::Saw);
+        tone.set_sound(Sound {
+            frequency: 200.0,
+            ..tone.sound()
+        });
+        let out: Vec<f32> = samples(&mut tone, PERIOD);
+        for i in 1..PERIOD / 2 - 1 {
+            assert!((out[i] - out[i + PERIOD / 2]).abs() < 1e-3, "sample {}", i);
+        }
+    }
+
+    #[test]
+    fn noise_holds_each_level_for_half_a_period_and_repeats() {
+        let out: Vec<f32> = samples(&mut beeping(Waveform::Noise), PERIOD * 2);
+        let changes: usize = out.windows(2).filter(|pair| pair[0] != pair[1]).count();
+        assert!((3..=4).contains(&changes), "{} changes", changes);
+        assert_eq!(out, samples(&mut beeping(Waveform::Noise), PERIOD * 2));
+    }
+
+    #[test]
+    fn channels_get_the_same_sample() {
+        let mut tone: Tone = beeping(Waveform::Saw).channels(2);
+        let out: Vec<f32> = samples(&mut tone, 8);
+        assert!(out.chunks(2).all(|frame| frame[0] == frame[1]));
+        assert_ne!(out[0], out[2]);
+    }
+
+    // How much the level moves a sample at SAMPLE_RATE: the ramps take 24 samples.
+    const STEP: f32 = 1.0 / 24.0;
+
+    #[test]
+    fn the_envelope_ramps_up_and_down_a_step_a_sample() {
+        let mut envelope: Envelope = Envelope::new(SAMPLE_RATE);
+        assert!(envelope.is_silent());
+        envelope.set_gate(true);
+        let attack: [f32; 30] = ::core::array::from_fn(|_| envelope.next_level());
+        assert!((attack[0] - STEP).abs() < 1e-6);
+        assert_eq!(attack[23..], [1.0; 7]);
+        envelope.set_gate(false);
+        assert!(!envelope.is_silent());
+        let release: [f32; 30] = ::core::array::from_fn(|_| envelope.next_level());
+        assert_eq!(release[23..], [0.0; 7]);
+        assert!(envelope.is_silent());
+
+        for ramp in [&attack[..], &release[..]] {
+            assert!(ramp
+                .windows(2)
+                .all(|pair| (pair[1] - pair[0]).abs() <= STEP + 1e-6));
+        }
+    }
+
+    #[test]
+    fn retriggering_turns_the_envelope_around_where_it_is() {
+        let mut envelope: Envelope = Envelope::new(SAMPLE_RATE);
+        envelope.set_gate(true);
+        for _ in 0..30 {
+            envelope.next_level();
+        }
+        envelope.set_gate(false);
+        for _ in 0..10 {
+            envelope.next_level();
+        }
+        let released: f32 = envelope.level();
+        envelope.set_gate(true);
+        assert!((envelope.next_level() - (released + STEP)).abs() < 1e-6);
+    }
+
+    // The most a triangle wave at full volume moves from one sample to the next at 100Hz, with a bit to spare for the
+    // envelope. Anything more would be heard as a click.
+    const MAX_JUMP: f32 = 0.025;
+
+    // Where one sample jumps more than MAX_JUMP from the one before, and by how much.
+    fn clicks(samples: &[f32]) -> Vec<(usize, f32)> {
+        samples
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| (pair[1] - pair[0]).abs() > MAX_JUMP)
+            .map(|(i, pair)| (i + 1, pair[1] - pair[0]))
+            .collect()
+    }
+
+    #[test]
+    fn short_and_retriggered_beeps_dont_click() {
+        let mut tone: Tone = Tone::new(
+            SAMPLE_RATE,
+            Sound {
+                waveform: Waveform::Triangle,
+                frequency: 100.0,
+                volume: MAX_VOLUME,
+                muted: false,
+            },
+        );
+        let mut out: Vec<f32> = samples(&mut tone, 10);
+        // A frame's beep, a release cut short by another beep, and then the release.
+        for (beeping, n) in [(true, 133), (false, 12), (true, 40), (false, 60)] {
+            tone.set_beeping(beeping);
+            out.extend(samples(&mut tone, n));
+        }
+        assert_eq!(out[..10], [0.0; 10]);
+        assert_eq!(clicks(&out), []);
+        assert!(tone.is_silent());
+        assert_eq!(out[out.len() - 10..], [0.0; 10]);
+
+        // Without the envelope, the first sample would be the bottom of the triangle.
+        assert!(out[10].abs() <= FULL_VOLUME * STEP + 1e-6);
+    }
+
+    #[test]
+    fn muting_fades_out_too() {
+        let mut tone: Tone = beeping(Waveform::Triangle);
+        let mut out: Vec<f32> = samples(&mut tone, 20);
+        let sound: Sound = Sound {
+            muted: true,
+            ..tone.sound()
+        };
+        tone.set_sound(sound);
+        out.extend(samples(&mut tone, 30));
+        assert_eq!(clicks(&out), []);
+        assert!(tone.is_silent());
+        assert_eq!(out[out.len() - 5..], [0.0; 5]);
+    }
+}
@@ -0,0 +1,74 @@
+// Stepping through a program one instruction at a time and recording what ran, for analysis scripts and tests.
+use crate::{decode, ChipEight, ChipEightError, Instruction, StepOutcome};
+
+// One executed instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceEntry {
+    // Where the instruction was fetched from.
+    pub pc: u16,
+    pub opcode: u16,
+    pub instruction: Instruction,
+    // The registers after the instruction executed.
+    pub registers: RegisterSnapshot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterSnapshot {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub sp: u8,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+}
+
+impl ChipEight {
+    // Steps the machine every time the iterator is advanced, yielding what each step executed. The trace ends after the
+    // first error, once the program exits with 00FD, or when the before-step hook pauses the machine. While Fx0A is waiting
+    // for a key the same entry comes back every time, as the instruction keeps executing without getting anywhere.
+    // Timers don't tick while tracing; use frame() for that.
+    pub fn trace(&mut self) -> impl Iterator<Item = Result<TraceEntry, ChipEightError>> + '_ {
+        let mut done: bool = false;
+
+        core::iter::from_fn(move || {
+            if done || self.halted {
+                return None;
+            }
+            let pc: u16 = self.pc;
+            let opcode: u16 = self.read_word(pc);
+
+            let outcome: StepOutcome = match self.step() {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    done = true;
+                    return Some(Err(e));
+                }
+            };
+            if outcome.paused {
+                done = true;
+                return None;
+            }
+            // step() already rejected anything that doesn't decode.
+            let instruction: Instruction = decode(opcode).ok()?;
+
+            Some(Ok(TraceEntry {
+                pc,
+                opcode,
+                instruction,
+                registers: self.register_snapshot(),
+            }))
+        })
+    }
+    pub(crate) fn register_snapshot(&self) -> RegisterSnapshot {
+        RegisterSnapshot {
+            v: self.v_registers,
+            i: self.i_register,
+            pc: self.pc,
+            sp: self.sp,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+        }
+    }
+}
@@ -0,0 +1,189 @@
+// Writing a line for every executed instruction to a file, for attaching to bug reports. The log hangs off the after-step
+// hook, so machines that aren't being traced don't pay for it.
+use crate::{disassemble, ChipEight, RegisterSnapshot};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// A handle to a trace log. Clones share the same log, so the application can keep one to flush once the machine stops.
+#[derive(Clone)]
+pub struct TraceLog {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    // How many instructions to log before going quiet, so a long session can't fill the disk.
+    limit: Option<u64>,
+    lines: u64,
+    // Whether the limit was reached and the note saying so written.
+    truncated: bool,
+    // The first write that failed. Nothing more is written after one does.
+    error: Option<io::Error>,
+}
+
+impl TraceLog {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        TraceLog {
+            inner: Arc::new(Mutex::new(Inner {
+                writer: BufWriter::new(Box::new(writer)),
+                limit: None,
+                lines: 0,
+                truncated: false,
+                error: None,
+            })),
+        }
+    }
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(File::create(path)?))
+    }
+    // Stops logging after this many instructions. A last line says the limit was reached.
+    pub fn with_limit(self, lines: u64) -> Self {
+        self.lock().limit = Some(lines);
+        self
+    }
+    // Starts logging every instruction the machine executes. This replaces its after-step hook.
+    pub fn attach(&self, chip_eight: &mut ChipEight) {
+        let log: TraceLog = self.clone();
+        chip_eight.on_after_step(move |chip_eight, opcode, before| {
+            log.lock().log(chip_eight, opcode, before);
+        });
+    }
+    // How many instructions have been logged.
+    pub fn lines(&self) -> u64 {
+        self.lock().lines
+    }
+    // Writes out anything still buffered. If any write has failed since the log was created, this returns that error.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut inner = self.lock();
+        if let Some(e) = inner.error.take() {
+            return Err(e);
+        }
+        inner.writer.flush()
+    }
+    // The hook can't do anything useful about a poisoned lock, and neither can anyone flushing, so both carry on with it.
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Inner {
+    fn log(&mut self, chip_eight: &ChipEight, opcode: u16, before: &RegisterSnapshot) {
+        if self.error.is_some() || self.truncated {
+            return;
+        }
+        let result: io::Result<()> = if self.limit == Some(self.lines) {
+            self.truncated = true;
+            writeln!(self.writer, "; trace limit of {} lines reached", self.lines)
+        } else {
+            self.lines += 1;
+            writeln!(self.writer, "{}", trace_line(chip_eight, opcode, before))
+        };
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+}
+
+// One instruction's line: the frame it ran in, where it was, the opcode and its mnemonic, and every register it changed,
+// e.g. "f=120 pc=0228 8124 ADD V1, V2 ; V1:3A->5C VF:00->01".
+fn trace_line(chip_eight: &ChipEight, opcode: u16, before: &RegisterSnapshot) -> String {
+    let after: RegisterSnapshot = chip_eight.register_snapshot();
+    let mut line: String = format!(
+        "f={} pc={:04X} {:04X} {}",
+        chip_eight.frame_count(),
+        before.pc,
+        opcode,
+        disassemble(opcode)
+    );
+    let mut changes: Vec<String> = vec![];
+
+    for (x, (old, new)) in before.v.iter().zip(after.v).enumerate() {
+        if *old != new {
+            changes.push(format!("V{:X}:{:02X}->{:02X}", x, old, new));
+        }
+    }
+    if before.i != after.i {
+        changes.push(format!("I:{:04X}->{:04X}", before.i, after.i));
+    }
+    if before.sp != after.sp {
+        changes.push(format!("SP:{}->{}", before.sp, after.sp));
+    }
+    if before.delay_timer != after.delay_timer {
+        changes.push(format!(
+            "DT:{:02X}->{:02X}",
+            before.delay_timer, after.delay_timer
+        ));
+    }
+    if before.sound_timer != after.sound_timer {
+        changes.push(format!(
+            "ST:{:02X}->{:02X}",
+            before.sound_timer, after.sound_timer
+        ));
+    }
+    if !changes.is_empty() {
+        line.push_str(" ; ");
+        line.push_str(&changes.join(" "));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A writer that can still be read after the log has been given it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn traced(program: &[u8], steps: usize, limit: Option<u64>) -> String {
+        let out: Shared = Shared::default();
+        let log: TraceLog = match limit {
+            Some(lines) => TraceLog::new(out.clone()).with_limit(lines),
+            None => TraceLog::new(out.clone()),
+        };
+        let mut chip_eight: ChipEight = ChipEight::with_seed(0);
+        chip_eight.load_program_from_slice(program).unwrap();
+        log.attach(&mut chip_eight);
+        for _ in 0..steps {
+            chip_eight.step().unwrap();
+        }
+        log.flush().unwrap();
+        let bytes: Vec<u8> = out.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn each_line_has_the_instruction_and_what_it_changed() {
+        // LD V0, 5; LD I, 0x123; CALL 0x208; and at 0x208, LD DT, V0.
+        let program: [u8; 10] = [0x60, 0x05, 0xA1, 0x23, 0x22, 0x08, 0x00, 0x00, 0xF0, 0x15];
+        assert_eq!(
+            traced(&program, 4, None),
+            "f=0 pc=0200 6005 LD V0, 0x05 ; V0:00->05\n\
+             f=0 pc=0202 A123 LD I, 0x123 ; I:0000->0123\n\
+             f=0 pc=0204 2208 CALL 0x208 ; SP:0->1\n\
+             f=0 pc=0208 F015 LD DT, V0 ; DT:00->05\n"
+        );
+    }
+
+    #[test]
+    fn the_limit_ends_with_a_note() {
+        let program: [u8; 4] = [0x60, 0x05, 0x12, 0x02];
+        assert_eq!(
+            traced(&program, 5, Some(2)),
+            "f=0 pc=0200 6005 LD V0, 0x05 ; V0:00->05\n\
+             f=0 pc=0202 1202 JP 0x202\n\
+             ; trace limit of 2 lines reached\n"
+        );
+    }
+}
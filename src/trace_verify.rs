@@ -0,0 +1,528 @@
+// Checking PotatOcho against another emulator, instruction by instruction. The other emulator's execution log is converted
+// to a simple reference format, and the program is run headless, with the same pinned-down seed as run_headless(), until
+// the first instruction where the PC or the registers afterwards don't match the reference.
+//
+// The reference format is one line per executed instruction, in order. Blank lines and lines starting with # or ; are
+// skipped. Each line is whitespace-separated key=value pairs with hex values (a 0x is optional), e.g.
+//   pc=0228 op=8124 v=0005A300000000000000000000000001 i=0300 sp=1 dt=00 st=00
+// pc, the address the instruction was fetched from, is the only key every line needs. The others are the state after
+// the instruction ran, and are only compared where they're given, so logs that don't have everything still work:
+//   op         the opcode
+//   v          V0 to VF, as 32 hex digits
+//   v0 ... vf  a single register
+//   i, sp      I, and how many return addresses are on the stack
+//   dt, st     the delay and sound timers
+// Keys are case-insensitive, and unknown keys (a cycle count, say) are ignored.
+use crate::{
+    disassemble, ChipEight, ChipEightError, FrameOutput, Keypad, RegisterSnapshot, HEADLESS_SEED,
+};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+// One line of a reference log.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReferenceStep {
+    // The line it came from, counting from 1.
+    pub line: usize,
+    pub pc: u16,
+    pub opcode: Option<u16>,
+    pub v: [Option<u8>; 16],
+    pub i: Option<u16>,
+    pub sp: Option<u8>,
+    pub delay_timer: Option<u8>,
+    pub sound_timer: Option<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceFormatError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TraceFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for TraceFormatError {}
+
+// An instruction PotatOcho executed: where it was fetched from, the opcode, and the registers afterwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExecutedStep {
+    pub pc: u16,
+    pub opcode: u16,
+    pub after: RegisterSnapshot,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceDivergence {
+    // Which instruction it was, counting from 0, and the frame it ran in.
+    pub index: usize,
+    pub frame: u64,
+    pub expected: ReferenceStep,
+    pub actual: ExecutedStep,
+    // What didn't match, e.g. "V3: expected 05, got 04".
+    pub mismatches: Vec<String>,
+    // The instructions around the one that diverged, as (address, opcode), from memory as it was at the end of that
+    // frame.
+    pub surrounding: Vec<(u16, u16)>,
+}
+
+#[derive(Debug)]
+pub struct TraceVerification {
+    // How many instructions matched the reference.
+    pub matched: usize,
+    // How many instructions the reference has.
+    pub expected: usize,
+    pub frames: u64,
+    pub divergence: Option<TraceDivergence>,
+    // Why the program stopped before the end of the reference, if it failed.
+    pub error: Option<ChipEightError>,
+}
+
+impl TraceVerification {
+    // Whether every instruction in the reference matched.
+    pub fn passed(&self) -> bool {
+        self.divergence.is_none() && self.matched == self.expected
+    }
+}
+
+// How many instructions either side of a divergence are shown.
+const SURROUNDING: u16 = 3;
+
+// Reads a reference log in the format described at the top of this file.
+pub fn parse_reference_trace(text: &str) -> Result<Vec<ReferenceStep>, TraceFormatError> {
+    let mut steps: Vec<ReferenceStep> = vec![];
+
+    for (n, line) in text.lines().enumerate() {
+        let line: &str = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let error = |message: String| TraceFormatError {
+            line: n + 1,
+            message,
+        };
+        let mut step: ReferenceStep = ReferenceStep {
+            line: n + 1,
+            ..ReferenceStep::default()
+        };
+        let mut pc: Option<u16> = None;
+
+        for pair in line.split_whitespace() {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected key=value, got '{}'", pair)))?;
+            let key: String = key.to_ascii_lowercase();
+            let digits: &str = value.trim_start_matches("0x").trim_start_matches("0X");
+            let number = |max: u32| -> Result<u32, TraceFormatError> {
+                match u32::from_str_radix(digits, 16) {
+                    Ok(number) if number <= max => Ok(number),
+                    _ => Err(error(format!("bad value for {}: '{}'", key, value))),
+                }
+            };
+            match key.as_str() {
+                "pc" => pc = Some(number(0xFFFF)? as u16),
+                "op" => step.opcode = Some(number(0xFFFF)? as u16),
+                "i" => step.i = Some(number(0xFFFF)? as u16),
+                "sp" => step.sp = Some(number(0xFF)? as u8),
+                "dt" => step.delay_timer = Some(number(0xFF)? as u8),
+                "st" => step.sound_timer = Some(number(0xFF)? as u8),
+                "v" => {
+                    if digits.len() != 32 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(error(format!(
+                            "v needs 32 hex digits, one pair for each of V0 to VF, got '{}'",
+                            value
+                        )));
+                    }
+                    for (x, v) in step.v.iter_mut().enumerate() {
+                        *v = u8::from_str_radix(&digits[x * 2..x * 2 + 2], 16).ok();
+                    }
+                }
+                register if register.len() == 2 && register.starts_with('v') => {
+                    match u8::from_str_radix(&register[1..], 16) {
+                        Ok(x) => step.v[x as usize] = Some(number(0xFF)? as u8),
+                        Err(_) => continue,
+                    }
+                }
+                _ => {}
+            }
+        }
+        step.pc = pc.ok_or_else(|| error("missing pc".to_string()))?;
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+// Runs chip_eight, which should have the program loaded, with no keys pressed and the RNG seeded with HEADLESS_SEED, and
+// compares each instruction it executes with the reference. It stops at the first difference, at the end of the
+// reference, or once max_frames frames have run. This replaces the machine's after-step hook.
+pub fn verify_trace(
+    chip_eight: &mut ChipEight,
+    reference: &[ReferenceStep],
+    max_frames: u64,
+) -> TraceVerification {
+    let executed: Arc<Mutex<Vec<ExecutedStep>>> = Arc::new(Mutex::new(vec![]));
+    let log: Arc<Mutex<Vec<ExecutedStep>>> = executed.clone();
+    chip_eight.on_after_step(move |chip_eight, opcode, before| {
+        log.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(ExecutedStep {
+                pc: before.pc,
+                opcode,
+                after: chip_eight.register_snapshot(),
+            });
+    });
+    chip_eight.seed_rng(HEADLESS_SEED);
+
+    let mut verification: TraceVerification = TraceVerification {
+        matched: 0,
+        expected: reference.len(),
+        frames: 0,
+        divergence: None,
+        error: None,
+    };
+    let keys: Keypad = Keypad::new();
+    while verification.matched < reference.len() && verification.frames < max_frames {
+        let result: Result<FrameOutput, ChipEightError> = chip_eight.frame(&keys);
+        verification.frames += 1;
+        let steps: Vec<ExecutedStep> =
+            std::mem::take(&mut *executed.lock().unwrap_or_else(|e| e.into_inner()));
+
+        for actual in steps {
+            let expected: &ReferenceStep = match reference.get(verification.matched) {
+                Some(expected) => expected,
+                None => break,
+            };
+            let mismatches: Vec<String> = compare(expected, &actual);
+            if !mismatches.is_empty() {
+                verification.divergence = Some(TraceDivergence {
+                    index: verification.matched,
+                    frame: chip_eight.frame_count(),
+                    expected: expected.clone(),
+                    actual,
+                    mismatches,
+                    surrounding: surrounding(chip_eight, actual.pc),
+                });
+                return verification;
+            }
+            verification.matched += 1;
+        }
+        match result {
+            Ok(output) if output.halted => break,
+            Ok(_) => {}
+            Err(e) => {
+                verification.error = Some(e);
+                break;
+            }
+        }
+    }
+    verification
+}
+
+fn compare(expected: &ReferenceStep, actual: &ExecutedStep) -> Vec<String> {
+    let mut mismatches: Vec<String> = vec![];
+    let mut check = |name: String, expected: Option<u32>, actual: u32, width: usize| match expected
+    {
+        Some(expected) if expected != actual => mismatches.push(format!(
+            "{}: expected {:0width$X}, got {:0width$X}",
+            name,
+            expected,
+            actual,
+            width = width
+        )),
+        _ => {}
+    };
+    let after: &RegisterSnapshot = &actual.after;
+
+    check(
+        "PC".to_string(),
+        Some(expected.pc as u32),
+        actual.pc as u32,
+        4,
+    );
+    check(
+        "opcode".to_string(),
+        expected.opcode.map(u32::from),
+        actual.opcode as u32,
+        4,
+    );
+    for (x, v) in expected.v.iter().enumerate() {
+        check(format!("V{:X}", x), v.map(u32::from), after.v[x] as u32, 2);
+    }
+    check(
+        "I".to_string(),
+        expected.i.map(u32::from),
+        after.i as u32,
+        4,
+    );
+    check(
+        "SP".to_string(),
+        expected.sp.map(u32::from),
+        after.sp as u32,
+        1,
+    );
+    check(
+        "DT".to_string(),
+        expected.delay_timer.map(u32::from),
+        after.delay_timer as u32,
+        2,
+    );
+    check(
+        "ST".to_string(),
+        expected.sound_timer.map(u32::from),
+        after.sound_timer as u32,
+        2,
+    );
+    mismatches
+}
+
+fn surrounding(chip_eight: &ChipEight, pc: u16) -> Vec<(u16, u16)> {
+    let memory: &[u8] = chip_eight.memory();
+    let start: u16 = pc.saturating_sub(SURROUNDING * 2);
+    (0..=SURROUNDING * 2)
+        .filter_map(|n| {
+            let addr: u16 = start.checked_add(n * 2)?;
+            let bytes: &[u8] = memory.get(addr as usize..addr as usize + 2)?;
+            Some((addr, u16::from_be_bytes([bytes[0], bytes[1]])))
+        })
+        .collect()
+}
+
+impl fmt::Display for ExecutedStep {
+    // In the reference format, so it lines up with the reference's line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let v: String = self.after.v.iter().map(|v| format!("{:02X}", v)).collect();
+        write!(
+            f,
+            "pc={:04X} op={:04X} v={} i={:04X} sp={:X} dt={:02X} st={:02X}",
+            self.pc,
+            self.opcode,
+            v,
+            self.after.i,
+            self.after.sp,
+            self.after.delay_timer,
+            self.after.sound_timer
+        )
+    }
+}
+
+impl fmt::Display for ReferenceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pc={:04X}", self.pc)?;
+        if let Some(opcode) = self.opcode {
+            write!(f, " op={:04X}", opcode)?;
+        }
+        if self.v.iter().all(Option::is_some) {
+            let v: String = self
+                .v
+                .iter()
+                .flatten()
+                .map(|v| format!("{:02X}", v))
+                .collect();
+            write!(f, " v={}", v)?;
+        } else {
+            for (x, v) in self.v.iter().enumerate() {
+                if let Some(v) = v {
+                    write!(f, " v{:x}={:02X}", x, v)?;
+                }
+            }
+        }
+        if let Some(i) = self.i {
+            write!(f, " i={:04X}", i)?;
+        }
+        if let Some(sp) = self.sp {
+            write!(f, " sp={:X}", sp)?;
+        }
+        if let Some(dt) = self.delay_timer {
+            write!(f, " dt={:02X}", dt)?;
+        }
+        if let Some(st) = self.sound_timer {
+            write!(f, " st={:02X}", st)?;
+        }
+        Ok(())
+    }
+}
+
+// Diverged at instruction 1234 (frame 20, reference line 1240):
+//   reference: pc=02A4 op=8346 v3=05
+//   potatocho: pc=02A4 op=8346 v=... i=0300 sp=0 dt=00 st=00
+//   V3: expected 05, got 04
+//
+//   029E: 6305  LD V3, 0x05
+// > 02A4: 8346  SHR V3, V4
+impl fmt::Display for TraceDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Diverged at instruction {} (frame {}, reference line {}):",
+            self.index, self.frame, self.expected.line
+        )?;
+        writeln!(f, "  reference: {}", self.expected)?;
+        writeln!(f, "  potatocho: {}", self.actual)?;
+        for mismatch in &self.mismatches {
+            writeln!(f, "  {}", mismatch)?;
+        }
+        writeln!(f)?;
+        for &(addr, opcode) in &self.surrounding {
+            let mark: &str = if addr == self.actual.pc { ">" } else { " " };
+            writeln!(
+                f,
+                "{} {:04X}: {:04X}  {}",
+                mark,
+                addr,
+                opcode,
+                disassemble(opcode)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sets V0 to 5, then adds 1 to V1 forever.
+    fn machine() -> ChipEight {
+        let mut chip_eight: ChipEight = ChipEight::with_seed(1);
+        chip_eight
+            .load_program_from_slice(&[0x60, 0x05, 0x71, 0x01, 0x12, 0x02])
+            .unwrap();
+        chip_eight
+    }
+
+    #[test]
+    fn reference_lines_are_parsed() {
+        let steps: Vec<ReferenceStep> = parse_reference_trace(
+            "# from somewhere else
+            pc=0228 op=8124 v=0005A300000000000000000000000001 i=0300 sp=1 dt=00 st=3C
+
+            ; only some of it
+            PC=0x22A V3=0x05 cycles=1234",
+        )
+        .unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(
+            (
+                steps[0].line,
+                steps[0].pc,
+                steps[0].opcode,
+                steps[0].i,
+                steps[0].sp
+            ),
+            (2, 0x228, Some(0x8124), Some(0x300), Some(1))
+        );
+        assert_eq!(
+            (steps[0].delay_timer, steps[0].sound_timer),
+            (Some(0), Some(0x3C))
+        );
+        assert_eq!(steps[0].v[1..3], [Some(0x05), Some(0xA3)]);
+        assert_eq!(steps[0].v[0xF], Some(0x01));
+        assert_eq!(
+            steps[1],
+            ReferenceStep {
+                line: 5,
+                pc: 0x22A,
+                v: ::core::array::from_fn(|x| (x == 3).then_some(5)),
+                ..ReferenceStep::default()
+            }
+        );
+        // Lines print back in the same format.
+        for step in &steps {
+            assert_eq!(
+                parse_reference_trace(&step.to_string()).unwrap()[0].v,
+                step.v
+            );
+        }
+        assert_eq!(steps[1].to_string(), "pc=022A v3=05");
+    }
+
+    #[test]
+    fn bad_reference_lines_are_errors() {
+        for (text, error) in [
+            ("op=1200", "line 1: missing pc"),
+            ("\npc=200 op", "line 2: expected key=value, got 'op'"),
+            ("pc=10000", "line 1: bad value for pc: '10000'"),
+            ("pc=200 v0=100", "line 1: bad value for v0: '100'"),
+            (
+                "pc=200 v=00",
+                "line 1: v needs 32 hex digits, one pair for each of V0 to VF, got '00'",
+            ),
+        ] {
+            assert_eq!(
+                parse_reference_trace(text).unwrap_err().to_string(),
+                error,
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn a_matching_reference_passes() {
+        let reference: Vec<ReferenceStep> = parse_reference_trace(
+            "pc=200 op=6005 v0=05
+            pc=202 op=7101 v1=01 i=0
+            pc=204 op=1202
+            pc=202 v=05020000000000000000000000000000",
+        )
+        .unwrap();
+        let verification: TraceVerification = verify_trace(&mut machine(), &reference, 10);
+        assert!(verification.passed());
+        assert_eq!((verification.matched, verification.frames), (4, 1));
+    }
+
+    #[test]
+    fn the_first_difference_is_reported_with_the_code_around_it() {
+        let reference: Vec<ReferenceStep> = parse_reference_trace(
+            "pc=200 op=6005
+            pc=202 op=7101 v1=01
+            pc=204 op=1202
+            pc=202 op=7101 v1=03 i=0200",
+        )
+        .unwrap();
+        let verification: TraceVerification = verify_trace(&mut machine(), &reference, 10);
+        assert!(!verification.passed());
+        assert_eq!(verification.matched, 3);
+        let divergence: TraceDivergence = verification.divergence.unwrap();
+        assert_eq!(divergence.index, 3);
+        assert_eq!(
+            divergence.mismatches,
+            ["V1: expected 03, got 02", "I: expected 0200, got 0000"]
+        );
+        assert_eq!(divergence.surrounding[3], (0x202, 0x7101));
+        let report: String = divergence.to_string();
+        assert!(
+            report.starts_with("Diverged at instruction 3 (frame 1, reference line 4):\n"),
+            "{}",
+            report
+        );
+        assert!(report.contains("\n> 0202: 7101  "), "{}", report);
+    }
+
+    #[test]
+    fn running_out_of_frames_doesnt_pass() {
+        // 0x200, then round the loop at 0x202 and 0x204 for as long as the reference goes on.
+        let reference: Vec<ReferenceStep> = (0..1000)
+            .map(|n| ReferenceStep {
+                pc: if n == 0 {
+                    0x200
+                } else {
+                    0x202 + (n + 1) % 2 * 2
+                },
+                ..ReferenceStep::default()
+            })
+            .collect();
+        let mut chip_eight: ChipEight = machine();
+        let verification: TraceVerification = verify_trace(&mut chip_eight, &reference, 2);
+        assert!(!verification.passed());
+        assert!(verification.divergence.is_none());
+        assert_eq!(verification.frames, 2);
+        assert_eq!(
+            verification.matched,
+            2 * chip_eight.instructions_per_frame() as usize
+        );
+    }
+}
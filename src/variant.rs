@@ -0,0 +1,243 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+// Chip-8 grew a handful of descendants over the years. Each one adds opcodes and changes how some of the original instructions behave.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChipVariant {
+    // The original COSMAC VIP interpreter.
+    #[default]
+    Chip8,
+    // SUPER-CHIP 1.1 as it ran on the HP48 calculators.
+    SuperChipLegacy,
+    // SUPER-CHIP as implemented by most modern interpreters (Octo's "schip" target).
+    SuperChipModern,
+    // Octo's XO-CHIP extensions: long addressing, register ranges, scrolling, extended memory.
+    XoChip,
+}
+
+impl ChipVariant {
+    // The quirk preset each variant is expected to run with. These follow Timendus' quirks test: https://github.com/Timendus/chip8-test-suite
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            ChipVariant::Chip8 => Quirks {
+                vf_reset: true,
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                clip_sprites: true,
+                display_wait: true,
+            },
+            // The HP48 waited for the vertical blank in lores, but not in hires (see ChipEight::display_waits). Its hires
+            // mode also counts clipped sprite rows as collisions, which modern interpreters don't.
+            ChipVariant::SuperChipLegacy => Quirks {
+                vf_reset: false,
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                jump_uses_vx: true,
+                clip_sprites: true,
+                display_wait: true,
+            },
+            ChipVariant::SuperChipModern => Quirks {
+                vf_reset: false,
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                jump_uses_vx: true,
+                clip_sprites: true,
+                display_wait: false,
+            },
+            ChipVariant::XoChip => Quirks {
+                vf_reset: false,
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                clip_sprites: false,
+                display_wait: false,
+            },
+        }
+    }
+    // SUPER-CHIP opcodes: 00Cn, 00FB, 00FC, 00FD, 00FE, 00FF, Dxy0, Fx30, Fx75, and Fx85. XO-CHIP is a superset of SUPER-CHIP,
+    // so it has them too.
+    pub fn has_super_chip_opcodes(&self) -> bool {
+        *self != ChipVariant::Chip8
+    }
+    // XO-CHIP opcodes: 00Dn, 5xy2, 5xy3, F000 nnnn.
+    pub fn has_xo_chip_opcodes(&self) -> bool {
+        *self == ChipVariant::XoChip
+    }
+    // The short name used in settings files and on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChipVariant::Chip8 => "chip8",
+            ChipVariant::SuperChipLegacy => "schip-legacy",
+            ChipVariant::SuperChipModern => "schip-modern",
+            ChipVariant::XoChip => "xochip",
+        }
+    }
+}
+
+impl fmt::Display for ChipVariant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name: &str = match self {
+            ChipVariant::Chip8 => "CHIP-8",
+            ChipVariant::SuperChipLegacy => "SCHIP (legacy)",
+            ChipVariant::SuperChipModern => "SCHIP (modern)",
+            ChipVariant::XoChip => "XO-CHIP",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for ChipVariant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "chip8" | "chip-8" => Ok(ChipVariant::Chip8),
+            "schip-legacy" | "schip" | "superchip" => Ok(ChipVariant::SuperChipLegacy),
+            "schip-modern" => Ok(ChipVariant::SuperChipModern),
+            "xochip" | "xo-chip" => Ok(ChipVariant::XoChip),
+            _ => Err(format!(
+                "unknown variant '{}' (expected chip8, schip-legacy, schip-modern, or xochip)",
+                s
+            )),
+        }
+    }
+}
+
+// Behaviours that differ between interpreters. Programs written for one interpreter often break subtly when run with another's quirks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quirks {
+    // 8xy1, 8xy2, and 8xy3 reset VF to 0.
+    pub vf_reset: bool,
+    // 8xy6 and 8xyE shift Vy and store the result in Vx, rather than shifting Vx in place.
+    pub shift_uses_vy: bool,
+    // Fx55 and Fx65 leave I pointing just past the last register accessed.
+    pub load_store_increments_i: bool,
+    // Bnnn jumps to xnn + Vx instead of nnn + V0.
+    pub jump_uses_vx: bool,
+    // Sprites drawn past the edge of the screen are clipped instead of wrapping around to the other side.
+    pub clip_sprites: bool,
+    // Dxyn waits for the next vertical blank before drawing, so at most one sprite is drawn per frame.
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    // The names quirks go by in settings files and on the command line.
+    pub const NAMES: [&'static str; 6] = [
+        "vf_reset",
+        "shift_uses_vy",
+        "load_store_increments_i",
+        "jump_uses_vx",
+        "clip_sprites",
+        "display_wait",
+    ];
+
+    // Gets the quirk with the given name, or None if there's no quirk by that name.
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "vf_reset" => Some(self.vf_reset),
+            "shift_uses_vy" => Some(self.shift_uses_vy),
+            "load_store_increments_i" => Some(self.load_store_increments_i),
+            "jump_uses_vx" => Some(self.jump_uses_vx),
+            "clip_sprites" => Some(self.clip_sprites),
+            "display_wait" => Some(self.display_wait),
+            _ => None,
+        }
+    }
+    // Sets the quirk with the given name. Returns false if there's no quirk by that name.
+    pub fn set(&mut self, name: &str, enabled: bool) -> bool {
+        let quirk: &mut bool = match name {
+            "vf_reset" => &mut self.vf_reset,
+            "shift_uses_vy" => &mut self.shift_uses_vy,
+            "load_store_increments_i" => &mut self.load_store_increments_i,
+            "jump_uses_vx" => &mut self.jump_uses_vx,
+            "clip_sprites" => &mut self.clip_sprites,
+            "display_wait" => &mut self.display_wait,
+            _ => return false,
+        };
+        *quirk = enabled;
+        true
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        ChipVariant::default().quirks()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (vf_reset, shift_uses_vy, load_store_increments_i, jump_uses_vx, clip_sprites, display_wait), as Timendus' quirks
+    // test expects them.
+    fn quirks(variant: ChipVariant) -> [bool; 6] {
+        let quirks: Quirks = variant.quirks();
+        Quirks::NAMES.map(|name| quirks.get(name).unwrap())
+    }
+
+    #[test]
+    fn chip8_has_the_cosmac_vip_quirks() {
+        assert_eq!(
+            quirks(ChipVariant::Chip8),
+            [true, true, true, false, true, true]
+        );
+    }
+
+    #[test]
+    fn legacy_super_chip_waits_for_the_display() {
+        assert_eq!(
+            quirks(ChipVariant::SuperChipLegacy),
+            [false, false, false, true, true, true]
+        );
+    }
+
+    #[test]
+    fn modern_super_chip_doesnt_wait_for_the_display() {
+        assert_eq!(
+            quirks(ChipVariant::SuperChipModern),
+            [false, false, false, true, true, false]
+        );
+    }
+
+    #[test]
+    fn xo_chip_wraps_sprites() {
+        assert_eq!(
+            quirks(ChipVariant::XoChip),
+            [false, true, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn each_variant_has_its_own_opcodes() {
+        let opcodes = |variant: ChipVariant| {
+            (
+                variant.has_super_chip_opcodes(),
+                variant.has_xo_chip_opcodes(),
+            )
+        };
+        assert_eq!(opcodes(ChipVariant::Chip8), (false, false));
+        assert_eq!(opcodes(ChipVariant::SuperChipLegacy), (true, false));
+        assert_eq!(opcodes(ChipVariant::SuperChipModern), (true, false));
+        assert_eq!(opcodes(ChipVariant::XoChip), (true, true));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn names_parse_back_to_the_variant() {
+        for variant in [
+            ChipVariant::Chip8,
+            ChipVariant::SuperChipLegacy,
+            ChipVariant::SuperChipModern,
+            ChipVariant::XoChip,
+        ] {
+            assert_eq!(variant.name().parse(), Ok(variant));
+        }
+        assert!("schip2".parse::<ChipVariant>().is_err());
+    }
+}
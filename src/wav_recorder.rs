@@ -0,0 +1,203 @@
+// Recording what the buzzer plays as a WAV file: 16-bit PCM, at whatever rate and channels the samples were made for. The
+// samples are tapped where Tone makes them, not from the OS mixer, so a recording has exactly the beeps and silences the
+// program asked for. Tone can be filling buffers on the audio thread, so it pushes them into a SampleTap, and whoever
+// owns the WavWriter drains that on its own thread, once a frame or so.
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// How big the header before the samples is, and where the two sizes in it go once they're known.
+const HEADER_SIZE: u32 = 44;
+const RIFF_SIZE_OFFSET: u64 = 4;
+const DATA_SIZE_OFFSET: u64 = 40;
+
+// Writes 16-bit PCM samples after a WAV header whose sizes are filled in by finish(). Until then the file says it's
+// empty, so a recording cut short by a crash is still a valid (if silent-looking) file.
+pub struct WavWriter<W: Write + Seek> {
+    out: W,
+    channels: u16,
+    data_size: u32,
+}
+
+impl WavWriter<BufWriter<File>> {
+    // Creates (or replaces) the file at path.
+    pub fn create(path: &Path, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        WavWriter::new(BufWriter::new(File::create(path)?), sample_rate, channels)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    // Starts a WAV of sample_rate samples a second, in channels interleaved channels, by writing its header to out.
+    pub fn new(mut out: W, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        let channels: u16 = channels.max(1);
+        let block_align: u16 = channels * 2;
+        out.write_all(b"RIFF")?;
+        out.write_all(&(HEADER_SIZE - 8).to_le_bytes())?;
+        out.write_all(b"WAVEfmt ")?;
+        out.write_all(&16u32.to_le_bytes())?;
+        // Plain PCM.
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&channels.to_le_bytes())?;
+        out.write_all(&sample_rate.to_le_bytes())?;
+        out.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        out.write_all(&block_align.to_le_bytes())?;
+        out.write_all(&16u16.to_le_bytes())?;
+        out.write_all(b"data")?;
+        out.write_all(&0u32.to_le_bytes())?;
+        Ok(WavWriter {
+            out,
+            channels,
+            data_size: 0,
+        })
+    }
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+    // How many samples have been written, counting each channel's separately.
+    pub fn samples(&self) -> u32 {
+        self.data_size / 2
+    }
+    // Writes samples between -1 and 1 (anything outside is clipped), interleaved if there's more than one channel.
+    pub fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(samples.len() * 2);
+        for sample in samples {
+            let sample: i16 = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        self.out.write_all(&bytes)?;
+        self.data_size = self.data_size.saturating_add(bytes.len() as u32);
+        Ok(())
+    }
+    // Fills in the header's sizes and flushes, giving back what was written to.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.seek(SeekFrom::Start(RIFF_SIZE_OFFSET))?;
+        self.out
+            .write_all(&(HEADER_SIZE - 8 + self.data_size).to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(DATA_SIZE_OFFSET))?;
+        self.out.write_all(&self.data_size.to_le_bytes())?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+// A bounded buffer of samples, shared between the thread making them and the one writing them out. If it isn't drained
+// in time, the newest samples are dropped (and counted) rather than letting it grow without end.
+#[derive(Clone, Debug)]
+pub struct SampleTap {
+    shared: Arc<Mutex<TapBuffer>>,
+}
+
+#[derive(Debug)]
+struct TapBuffer {
+    samples: VecDeque<f32>,
+    capacity: usize,
+    dropped: u64,
+}
+
+impl SampleTap {
+    // A tap holding up to capacity samples between drains.
+    pub fn new(capacity: usize) -> Self {
+        SampleTap {
+            shared: Arc::new(Mutex::new(TapBuffer {
+                samples: VecDeque::with_capacity(capacity),
+                capacity,
+                dropped: 0,
+            })),
+        }
+    }
+    pub fn push(&self, samples: &[f32]) {
+        let mut buffer = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        let room: usize = buffer.capacity - buffer.samples.len();
+        let kept: usize = samples.len().min(room);
+        buffer.samples.extend(&samples[..kept]);
+        buffer.dropped += (samples.len() - kept) as u64;
+    }
+    // Takes every sample pushed since the last drain, oldest first.
+    pub fn drain(&self) -> Vec<f32> {
+        let mut buffer = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        buffer.samples.drain(..).collect()
+    }
+    // How many samples have been dropped because the tap was full.
+    pub fn dropped(&self) -> u64 {
+        self.shared
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn u16_at(wav: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([wav[at], wav[at + 1]])
+    }
+
+    fn u32_at(wav: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([wav[at], wav[at + 1], wav[at + 2], wav[at + 3]])
+    }
+
+    #[test]
+    fn the_header_describes_the_samples() {
+        let mut writer: WavWriter<Cursor<Vec<u8>>> =
+            WavWriter::new(Cursor::new(vec![]), 44100, 2).unwrap();
+        writer.write(&[1.0, -1.0, 0.5, 2.0]).unwrap();
+        writer.write(&[-0.25, 0.0]).unwrap();
+        assert_eq!(writer.samples(), 6);
+        let wav: Vec<u8> = writer.finish().unwrap().into_inner();
+
+        assert_eq!(wav.len(), 44 + 12);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32_at(&wav, 4), 36 + 12);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&wav, 16), 16);
+        // PCM, two channels, 44.1KHz, four bytes a sample frame, 16 bits a sample.
+        assert_eq!(u16_at(&wav, 20), 1);
+        assert_eq!(u16_at(&wav, 22), 2);
+        assert_eq!(u32_at(&wav, 24), 44100);
+        assert_eq!(u32_at(&wav, 28), 44100 * 4);
+        assert_eq!(u16_at(&wav, 32), 4);
+        assert_eq!(u16_at(&wav, 34), 16);
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(u32_at(&wav, 40), 12);
+
+        let samples: Vec<i16> = wav[44..]
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        // Rounded to the nearest step, and clipped at full scale.
+        assert_eq!(samples, [32767, -32767, 16384, 32767, -8192, 0]);
+    }
+
+    #[test]
+    fn an_empty_recording_is_still_a_wav() {
+        let writer: WavWriter<Cursor<Vec<u8>>> =
+            WavWriter::new(Cursor::new(vec![]), 8000, 0).unwrap();
+        // No channels is taken as one.
+        assert_eq!(writer.channels(), 1);
+        let wav: Vec<u8> = writer.finish().unwrap().into_inner();
+        assert_eq!(wav.len(), 44);
+        assert_eq!((u32_at(&wav, 4), u32_at(&wav, 40)), (36, 0));
+        assert_eq!((u16_at(&wav, 22), u32_at(&wav, 28)), (1, 8000 * 2));
+    }
+
+    #[test]
+    fn a_full_tap_drops_and_counts_the_newest_samples() {
+        let tap: SampleTap = SampleTap::new(4);
+        let audio_thread: SampleTap = tap.clone();
+        audio_thread.push(&[0.1, 0.2, 0.3]);
+        audio_thread.push(&[0.4, 0.5, 0.6]);
+        assert_eq!(tap.dropped(), 2);
+        assert_eq!(tap.drain(), [0.1, 0.2, 0.3, 0.4]);
+        assert!(tap.drain().is_empty());
+        // Draining makes room again.
+        audio_thread.push(&[0.7]);
+        assert_eq!(tap.drain(), [0.7]);
+        assert_eq!(tap.dropped(), 2);
+    }
+}
@@ -0,0 +1,53 @@
+// Builds the no_std core for a Cortex-M4F, like scripts/check-embedded.sh does, so `cargo test` catches anything that
+// quietly pulls in std or an allocator. The target's standard library has to be installed for that (`rustup target add
+// thumbv7em-none-eabihf`), and the test says so and passes without it, rather than failing on machines that only build
+// for themselves.
+#![cfg(feature = "std")]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+// Whether the target's standard library is installed.
+fn target_installed() -> bool {
+    Command::new("rustc")
+        .args(["--print", "target-libdir", "--target", TARGET])
+        .output()
+        .is_ok_and(|output| {
+            output.status.success()
+                && Path::new(String::from_utf8_lossy(&output.stdout).trim()).is_dir()
+        })
+}
+
+#[test]
+fn the_core_builds_for_an_embedded_target() {
+    if !target_installed() {
+        eprintln!(
+            "skipped: {} isn't installed, see scripts/check-embedded.sh",
+            TARGET
+        );
+        return;
+    }
+    let manifest_dir: &Path = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // A target directory of its own, so it doesn't wait on the lock the build running this test may still hold.
+    let target_dir: PathBuf = manifest_dir.join("target").join("embedded");
+    let output: Output = Command::new(env!("CARGO"))
+        .args([
+            "build",
+            "--lib",
+            "--no-default-features",
+            "--target",
+            TARGET,
+        ])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .current_dir(manifest_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,187 @@
+// Runs the ROMs in tests/fixtures headlessly and checks they end up on the screens they always have, the same way
+// `potatocho --check ROM --expect-hash HASH` does. Both ROMs are tiny and public domain: the well-known IBM logo demo,
+// and keypad_echo, which waits for a key, then clears the screen and draws the key's digit in the top left corner. A
+// few tiny programs written out below check how runs fail and beep.
+#![cfg(feature = "std")]
+
+use potatocho::backend::{InputEvent, NullAudio, NullDisplay, NullInput};
+use potatocho::{
+    run_headless, ChipEight, ChipEightError, ChipVariant, FrameOutput, HeadlessResult, Keypad,
+    Quirks, SampleTap, Sound, Tone, WavWriter, ENVELOPE_RAMP,
+};
+use std::io::Cursor;
+
+const IBM_LOGO: &[u8] = include_bytes!("fixtures/ibm_logo.ch8");
+const KEYPAD_ECHO: &[u8] = include_bytes!("fixtures/keypad_echo.ch8");
+
+fn quirks() -> Quirks {
+    ChipVariant::Chip8.quirks()
+}
+
+#[test]
+fn ibm_logo_hash() {
+    let result: HeadlessResult = run_headless(IBM_LOGO, 30, quirks(), &[]);
+    assert!(result.error.is_none(), "{:?}", result.error);
+    assert_eq!(result.frames, 30);
+    assert_eq!(result.hash, 0xF12E_1880_4CA9_BC12);
+    assert_eq!(result.framebuffer.len(), 32);
+}
+
+#[test]
+fn keypad_echo_hash() {
+    // Nothing for a few frames, then a press and release of A.
+    let script: [InputEvent; 4] = [
+        InputEvent::KeyReleased(0),
+        InputEvent::KeyReleased(0),
+        InputEvent::KeyPressed(0xA),
+        InputEvent::KeyReleased(0xA),
+    ];
+    let result: HeadlessResult = run_headless(KEYPAD_ECHO, 10, quirks(), &script);
+    assert!(result.error.is_none(), "{:?}", result.error);
+    assert_eq!(result.hash, 0x2D86_687D_70F0_AA6F);
+    // The top of the A.
+    assert_eq!(result.framebuffer[0][..5], [true, true, true, true, false]);
+    // Before any key, the screen's blank, which hashes differently.
+    let waiting: HeadlessResult = run_headless(KEYPAD_ECHO, 10, quirks(), &[]);
+    assert_ne!(waiting.hash, result.hash);
+    assert!(waiting.framebuffer.iter().flatten().all(|&pixel| !pixel));
+}
+
+#[test]
+fn runs_stop_at_errors_and_quits() {
+    // 5xy1 isn't an instruction.
+    let result: HeadlessResult = run_headless(&[0x51, 0x21], 30, quirks(), &[]);
+    assert_eq!(result.frames, 0);
+    assert_eq!(
+        result.error.map(|e| e.to_string()),
+        Some("unknown opcode 5121 at 0x200".to_string())
+    );
+    let result: HeadlessResult = run_headless(IBM_LOGO, 30, quirks(), &[InputEvent::Quit]);
+    assert_eq!(result.frames, 0);
+}
+
+#[test]
+fn a_return_with_an_empty_stack_stops_the_program_where_it_failed() {
+    // Draws a 0 in the top left corner, then returns from a subroutine it never called.
+    let rom: [u8; 8] = [0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x00, 0xEE];
+    let result: HeadlessResult = run_headless(&rom, 30, quirks(), &[]);
+    let error: ChipEightError = result.error.expect("the run should fail");
+    assert!(
+        matches!(error, ChipEightError::StackUnderflow { pc: 0x206 }),
+        "{:?}",
+        error
+    );
+    assert_eq!(error.pc(), Some(0x206));
+    assert_eq!(
+        error.to_string(),
+        "returned from a subroutine at 0x206 with an empty stack"
+    );
+    // The screen is what the program drew before it failed, which is what the error screen dims and writes over.
+    assert_eq!(result.framebuffer[0][..5], [true, true, true, true, false]);
+
+    // run() hands the same error back for the frontend to show, with the machine still at the failing instruction.
+    let mut chip_eight: ChipEight = ChipEight::new();
+    chip_eight.set_quirks(Some(quirks()));
+    chip_eight.load_program_from_slice(&rom).unwrap();
+    let error: ChipEightError = chip_eight
+        .run(
+            &mut NullDisplay,
+            &mut NullInput::for_frames(30),
+            &mut NullAudio,
+        )
+        .expect_err("the run should fail");
+    assert_eq!(error.pc(), Some(0x206));
+    assert_eq!(chip_eight.pc(), 0x206);
+    assert!(chip_eight.stack().is_empty());
+}
+
+#[test]
+fn a_sound_timer_of_n_beeps_for_n_frames() {
+    for n in [1u8, 2, 10, 45] {
+        // LD V0, n; LD ST, V0; and then spin, with a few instructions to pad out the frame the timer was set in.
+        let rom: [u8; 10] = [0x60, n, 0xF0, 0x18, 0x61, 0x01, 0x61, 0x02, 0x12, 0x08];
+        for ipf in [1, 15, 100] {
+            let mut chip_eight: ChipEight = ChipEight::new();
+            chip_eight.set_quirks(Some(quirks()));
+            chip_eight.set_instructions_per_frame(ipf);
+            chip_eight.load_program_from_slice(&rom).unwrap();
+            let result: HeadlessResult = chip_eight.run_headless(60, &[]);
+            assert!(result.error.is_none(), "{:?}", result.error);
+            assert_eq!(
+                result.beep_frames, n as u32,
+                "ST={} at {} instructions a frame",
+                n, ipf
+            );
+        }
+    }
+    // A program that never sets the timer never beeps.
+    assert_eq!(run_headless(IBM_LOGO, 30, quirks(), &[]).beep_frames, 0);
+}
+
+// Waits 10 frames on the delay timer, then sets the sound timer to 30 and spins.
+const BEEP_AFTER_10: [u8; 16] = [
+    0x60, 0x0A, 0xF0, 0x15, 0xF1, 0x07, 0x31, 0x00, 0x12, 0x04, 0x60, 0x1E, 0xF0, 0x18, 0x12, 0x0E,
+];
+
+#[test]
+fn a_recording_of_a_beep_is_silent_around_it() {
+    const RATE: u32 = 48000;
+    const SAMPLES_PER_FRAME: usize = RATE as usize / 60;
+    let mut chip_eight: ChipEight = ChipEight::new();
+    chip_eight.set_quirks(Some(quirks()));
+    chip_eight.load_program_from_slice(&BEEP_AFTER_10).unwrap();
+
+    // The way --record-audio hooks the synthesizer up: a tap on the tone, drained into the WAV once a frame.
+    let tap: SampleTap = SampleTap::new(RATE as usize);
+    let mut tone: Tone = Tone::new(RATE, Sound::default());
+    tone.set_tap(Some(tap.clone()));
+    let mut wav: WavWriter<Cursor<Vec<u8>>> = WavWriter::new(Cursor::new(vec![]), RATE, 1).unwrap();
+    let mut beep_frames: Vec<usize> = vec![];
+    for frame in 0..60 {
+        let output: FrameOutput = chip_eight.frame(&Keypad::default()).unwrap();
+        if output.beep {
+            beep_frames.push(frame);
+        }
+        tone.set_beeping(output.beep);
+        tone.fill(&mut [0.0; SAMPLES_PER_FRAME]);
+        wav.write(&tap.drain()).unwrap();
+    }
+    assert_eq!(tap.dropped(), 0);
+    let wav: Vec<u8> = wav.finish().unwrap().into_inner();
+
+    assert_eq!(beep_frames.len(), 30);
+    let u32_at = |at: usize| u32::from_le_bytes([wav[at], wav[at + 1], wav[at + 2], wav[at + 3]]);
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u32_at(24), RATE);
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(u32_at(40), 60 * SAMPLES_PER_FRAME as u32 * 2);
+    assert_eq!(u32_at(4), 36 + u32_at(40));
+
+    // The sound starts with the first beep frame, and lasts half a second plus the release.
+    let samples: Vec<i16> = wav[44..]
+        .chunks(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+        .collect();
+    let first: usize = samples.iter().position(|&sample| sample != 0).unwrap();
+    let last: usize = samples.iter().rposition(|&sample| sample != 0).unwrap();
+    assert_eq!(first, beep_frames[0] * SAMPLES_PER_FRAME);
+    let release: usize = (ENVELOPE_RAMP * RATE as f32) as usize;
+    let length: usize = last + 1 - first;
+    assert!(
+        (30 * SAMPLES_PER_FRAME..=30 * SAMPLES_PER_FRAME + release).contains(&length),
+        "{} samples of sound",
+        length
+    );
+    assert!(
+        length > 30 * SAMPLES_PER_FRAME + release / 2,
+        "the release was cut short: {}",
+        length
+    );
+
+    // The same ROM headless beeps for the same frames.
+    assert_eq!(
+        run_headless(&BEEP_AFTER_10, 60, quirks(), &[]).beep_frames,
+        30
+    );
+}
@@ -0,0 +1,72 @@
+// Runs the example scripts in examples/scripts against their Octo programs, the way `potatocho --script` does, and checks
+// they pass and leave the machines where they say.
+#![cfg(all(feature = "scripting", feature = "octo"))]
+
+use potatocho::{run_script, ChipEight, ScriptError, HEADLESS_SEED};
+use std::path::{Path, PathBuf};
+
+fn example(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("examples/scripts")
+        .join(name)
+}
+
+// Compiles and loads the example program, then runs source against it.
+fn run(program: &str, source: &str) -> (ChipEight, Result<(), ScriptError>) {
+    let mut chip_eight: ChipEight = ChipEight::new();
+    chip_eight
+        .load_program_from_path(&example(program))
+        .unwrap();
+    chip_eight.seed_rng(HEADLESS_SEED);
+    let result: Result<(), ScriptError> = run_script(&mut chip_eight, source);
+    (chip_eight, result)
+}
+
+fn script(name: &str) -> String {
+    std::fs::read_to_string(example(name)).unwrap()
+}
+
+#[test]
+fn countdown_draws_its_0_once_the_delay_timer_runs_out() {
+    let (chip_eight, result) = run("countdown.8o", &script("countdown.rhai"));
+    assert!(result.is_ok(), "{}", result.unwrap_err());
+    assert_eq!(chip_eight.delay_timer(), 0);
+    assert!(chip_eight.frame_count() >= 30);
+    assert!(chip_eight.screen_to_string().starts_with("####"));
+}
+
+#[test]
+fn keypad_shows_the_last_key_pressed() {
+    let (chip_eight, result) = run("keypad.8o", &script("keypad.rhai"));
+    assert!(result.is_ok(), "{}", result.unwrap_err());
+    assert_eq!(chip_eight.v_registers()[0], 0xA);
+    assert_eq!(chip_eight.v_registers()[1], 2);
+    // The top of the A.
+    assert_eq!(
+        chip_eight.framebuffer()[0][..5],
+        [true, true, true, true, false]
+    );
+}
+
+#[test]
+fn failures_say_what_kind_and_where() {
+    let (_, result) = run(
+        "keypad.8o",
+        "frames(2);\nassert_eq(v(1), 3, \"keys counted\");\n",
+    );
+    match result {
+        Err(ScriptError::Assertion { line, message }) => {
+            assert_eq!(line, Some(2));
+            assert_eq!(message, "keys counted (expected 3, got 0)");
+        }
+        other => panic!("expected an assertion failure, got {:?}", other),
+    }
+
+    let (_, result) = run("keypad.8o", "frames(1);\n\nno_such_function();\n");
+    match result {
+        Err(e @ ScriptError::Script { line: Some(3), .. }) => {
+            assert!(e.to_string().starts_with("line 3: "), "{}", e)
+        }
+        other => panic!("expected a script error on line 3, got {:?}", other),
+    }
+}